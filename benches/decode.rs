@@ -0,0 +1,257 @@
+//! Výkon dekodéru nad syntetickými soubory (`cargo bench --bench decode`): celý obrázek,
+//! výřez, jednotlivé kodeky, velikosti dlaždic a typy barev. Soubory se vygenerují při
+//! prvním běhu do dočasné složky; criterion výsledky porovná s minulým během, takže
+//! zpomalení dekodéru je vidět hned (`--save-baseline` / `--baseline` pro srovnání větví).
+//! Po změně formátu nebo kodéru složku `cti-view-bench` smažte, ať se soubory vytvoří znovu.
+
+use std::hint::black_box;
+use std::io::Cursor;
+use std::path::PathBuf;
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use cti_view::crc;
+use cti_view::cti::{CTIDecoder, CTIEncoder, CTITileReader, CompressionId, EncodeParams};
+
+/// Parametry syntetického souboru.
+#[derive(Clone, Copy)]
+struct Spec {
+    size: u32,
+    tile_size: u32,
+    color_type: u8,
+    compression: CompressionId,
+    level: i32,
+}
+
+impl Spec {
+    const fn new(size: u32) -> Self {
+        Self {
+            size,
+            tile_size: 256,
+            color_type: 3,
+            compression: CompressionId::Zstd,
+            level: 9,
+        }
+    }
+
+    const fn tile(self, tile_size: u32) -> Self {
+        Self { tile_size, ..self }
+    }
+
+    const fn color(self, color_type: u8) -> Self {
+        Self { color_type, ..self }
+    }
+
+    const fn codec(self, compression: CompressionId, level: i32) -> Self {
+        Self {
+            compression,
+            level,
+            ..self
+        }
+    }
+
+    fn bpp(&self) -> u64 {
+        match self.color_type {
+            1 => 1,
+            2 => 2,
+            3 => 3,
+            4 => 4,
+            5 => 6,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Velikost dekódovaných dat.
+    fn raw_len(&self) -> u64 {
+        u64::from(self.size) * u64::from(self.size) * self.bpp()
+    }
+
+    fn codec_name(&self) -> String {
+        match self.compression {
+            CompressionId::Zstd => format!("zstd{}", self.level),
+            other => other.as_str().to_ascii_lowercase(),
+        }
+    }
+
+    /// Cesta k souboru; chybějící se zakóduje.
+    fn file(&self) -> PathBuf {
+        let dir = std::env::temp_dir().join("cti-view-bench");
+        std::fs::create_dir_all(&dir).expect("create bench dir");
+        let path = dir.join(format!(
+            "{}px-t{}-c{}-{}.cti",
+            self.size,
+            self.tile_size,
+            self.color_type,
+            self.codec_name()
+        ));
+        if !path.exists() {
+            let params = EncodeParams {
+                tile_size: self.tile_size,
+                compression: self.compression,
+                level: self.level,
+                ..EncodeParams::default()
+            };
+            CTIEncoder::encode_file(
+                &path,
+                self.size,
+                self.size,
+                self.color_type,
+                &image(self.size, self.raw_len() as usize),
+                &params,
+            )
+            .expect("encode bench file");
+        }
+        path
+    }
+}
+
+/// Plynulý přechod se šumem: komprimuje se podobně jako skutečný sken, ne jako konstanta
+/// ani jako čistý šum.
+fn image(size: u32, len: usize) -> Vec<u8> {
+    let mut x = 0x2545_f491_u32;
+    (0..len)
+        .map(|i| {
+            x ^= x << 13;
+            x ^= x >> 17;
+            x ^= x << 5;
+            let px = (i as u32 / 3) % size + (i as u32 / 3) / size;
+            ((px >> 4) as u8).wrapping_add((x & 0x0F) as u8)
+        })
+        .collect()
+}
+
+const CODECS: [(CompressionId, i32); 4] = [
+    (CompressionId::None, 0),
+    (CompressionId::Lz4, 0),
+    (CompressionId::Zstd, 3),
+    (CompressionId::Zstd, 19),
+];
+
+/// Celý obrázek ze souboru pro různé velikosti a kodeky.
+fn full(c: &mut Criterion) {
+    let mut g = c.benchmark_group("full");
+    g.sample_size(10);
+    for size in [1024, 4096] {
+        for (codec, level) in CODECS {
+            let spec = Spec::new(size).codec(codec, level);
+            let path = spec.file();
+            g.throughput(Throughput::Bytes(spec.raw_len()));
+            g.bench_with_input(
+                BenchmarkId::new(spec.codec_name(), format!("{size}px")),
+                &path,
+                |b, path| b.iter(|| CTIDecoder::decode_file(black_box(path)).unwrap()),
+            );
+        }
+    }
+    g.finish();
+}
+
+/// Výřez 1024 × 1024 uprostřed velkého obrázku podle velikosti dlaždic.
+fn region(c: &mut Criterion) {
+    let mut g = c.benchmark_group("region");
+    for tile in [128, 256, 512, 1024] {
+        let spec = Spec::new(8192).tile(tile);
+        let path = spec.file();
+        let area = (3584, 3584, 1024, 1024);
+        g.throughput(Throughput::Bytes(1024 * 1024 * spec.bpp()));
+        g.bench_with_input(BenchmarkId::new("tile", tile), &path, |b, path| {
+            b.iter(|| CTIDecoder::decode_region(black_box(path), 0, area).unwrap())
+        });
+    }
+    g.finish();
+}
+
+/// Propustnost kodeků z paměti (bez čtení z disku).
+fn codec(c: &mut Criterion) {
+    let mut g = c.benchmark_group("codec");
+    for (codec, level) in CODECS {
+        let spec = Spec::new(4096).codec(codec, level);
+        let bytes = std::fs::read(spec.file()).unwrap();
+        g.throughput(Throughput::Bytes(spec.raw_len()));
+        g.bench_with_input(
+            BenchmarkId::from_parameter(spec.codec_name()),
+            &bytes,
+            |b, bytes| {
+                b.iter(|| CTIDecoder::decode_frame_from(Cursor::new(black_box(bytes)), 0).unwrap())
+            },
+        );
+    }
+    g.finish();
+}
+
+/// Celý obrázek z paměti podle velikosti dlaždic.
+fn tile_size(c: &mut Criterion) {
+    let mut g = c.benchmark_group("tile_size");
+    for tile in [64, 128, 256, 512, 1024] {
+        let spec = Spec::new(4096).tile(tile);
+        let bytes = std::fs::read(spec.file()).unwrap();
+        g.throughput(Throughput::Bytes(spec.raw_len()));
+        g.bench_with_input(BenchmarkId::from_parameter(tile), &bytes, |b, bytes| {
+            b.iter(|| CTIDecoder::decode_frame_from(Cursor::new(black_box(bytes)), 0).unwrap())
+        });
+    }
+    g.finish();
+}
+
+/// Celý obrázek z paměti podle typu barev (RCT u RGB8/RGB16).
+fn color_type(c: &mut Criterion) {
+    let mut g = c.benchmark_group("color_type");
+    for (name, color_type) in [
+        ("L8", 1),
+        ("L16", 2),
+        ("RGB8", 3),
+        ("RGBA8", 4),
+        ("RGB16", 5),
+    ] {
+        let spec = Spec::new(4096).color(color_type);
+        let bytes = std::fs::read(spec.file()).unwrap();
+        g.throughput(Throughput::Bytes(spec.raw_len()));
+        g.bench_with_input(BenchmarkId::from_parameter(name), &bytes, |b, bytes| {
+            b.iter(|| CTIDecoder::decode_frame_from(Cursor::new(black_box(bytes)), 0).unwrap())
+        });
+    }
+    g.finish();
+}
+
+/// Jedna dlaždice přes [`CTITileReader`] (cesta prohlížeče a serveru IIIF).
+fn single_tile(c: &mut Criterion) {
+    let mut g = c.benchmark_group("single_tile");
+    for tile in [256, 512] {
+        let spec = Spec::new(4096).tile(tile);
+        let mut reader = CTITileReader::open(spec.file()).unwrap();
+        let mid = 2048 / tile;
+        g.throughput(Throughput::Bytes(u64::from(tile * tile) * spec.bpp()));
+        g.bench_function(BenchmarkId::from_parameter(tile), |b| {
+            b.iter(|| {
+                reader
+                    .decode_tile(0, black_box(mid), black_box(mid))
+                    .unwrap()
+            })
+        });
+    }
+    g.finish();
+}
+
+/// Kontrolní součty dlaždice 256 × 256 RGB8.
+fn checksum(c: &mut Criterion) {
+    let mut g = c.benchmark_group("checksum");
+    let data = image(256, 256 * 256 * 3);
+    g.throughput(Throughput::Bytes(data.len() as u64));
+    g.bench_function("crc32", |b| b.iter(|| crc::crc32(black_box(&data))));
+    g.bench_function("crc32c", |b| b.iter(|| crc::crc32c(black_box(&data))));
+    g.bench_function("crc32_bytewise", |b| {
+        b.iter(|| crc::crc32_bytewise(black_box(&data)))
+    });
+    g.finish();
+}
+
+criterion_group!(
+    benches,
+    full,
+    region,
+    codec,
+    tile_size,
+    color_type,
+    single_tile,
+    checksum
+);
+criterion_main!(benches);
@@ -0,0 +1,139 @@
+//! Zrychlení vektorizovaných smyček proti původním smyčkám po pixelech
+//! (`cargo bench --bench simd`). Ověří i shodu výsledků.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use cti_view::cti::CTIHeader;
+use cti_view::simd::{self, scalar};
+use cti_view::view::to_color_image;
+use egui::ColorImage;
+
+const WIDTH: u32 = 4000;
+const HEIGHT: u32 = 3000;
+
+/// Nejlepší čas z několika běhů.
+fn measure(mut f: impl FnMut()) -> Duration {
+    f();
+    (0..10)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn report(name: &str, scalar: Duration, simd: Duration) {
+    println!(
+        "{name:<16} scalar {:>8.2} ms   simd {:>8.2} ms   {:.1}x",
+        scalar.as_secs_f64() * 1e3,
+        simd.as_secs_f64() * 1e3,
+        scalar.as_secs_f64() / simd.as_secs_f64()
+    );
+}
+
+/// Pseudonáhodná data (xorshift), ať se nic nedá předpočítat.
+fn noise(len: usize) -> Vec<u8> {
+    let mut x = 0x2545_f491_u32;
+    (0..len)
+        .map(|_| {
+            x ^= x << 13;
+            x ^= x >> 17;
+            x ^= x << 5;
+            x as u8
+        })
+        .collect()
+}
+
+fn header(color_type: u8) -> CTIHeader {
+    CTIHeader {
+        magic: *b"CTI1",
+        version: 1,
+        flags: 0,
+        width: WIDTH,
+        height: HEIGHT,
+        tile_size: 256,
+        tiles_x: WIDTH.div_ceil(256),
+        tiles_y: HEIGHT.div_ceil(256),
+        color_type,
+        compression: 0,
+        quality: 0,
+        frames: 1,
+        chunks_len: 0,
+    }
+}
+
+fn bench_rct(name: &str, bpp: usize, scalar: fn(&mut [u8]), simd: fn(&mut [u8])) {
+    let data = noise(WIDTH as usize * HEIGHT as usize * bpp);
+    let (mut a, mut b) = (data.clone(), data.clone());
+    scalar(&mut a);
+    simd(&mut b);
+    assert!(a == b, "{name}: results differ");
+    // každý běh znovu ze stejných dat
+    let mut buf = data.clone();
+    let t_scalar = measure(|| {
+        buf.copy_from_slice(&data);
+        scalar(black_box(&mut buf));
+    });
+    let t_simd = measure(|| {
+        buf.copy_from_slice(&data);
+        simd(black_box(&mut buf));
+    });
+    let t_copy = measure(|| buf.copy_from_slice(black_box(&data)));
+    report(name, t_scalar - t_copy, t_simd - t_copy);
+}
+
+/// Náhled přes [`to_color_image`] proti původnímu postupu (RGBA po pixelech a převod
+/// na texturu).
+fn bench_preview(name: &str, color_type: u8, bpp: usize, scalar: fn(&[u8]) -> Vec<u8>) {
+    let hdr = header(color_type);
+    let raw = noise(WIDTH as usize * HEIGHT as usize * bpp);
+    let size = [WIDTH as usize, HEIGHT as usize];
+    let old = |raw: &[u8]| ColorImage::from_rgba_unmultiplied(size, &scalar(raw));
+    assert!(
+        old(&raw).pixels == to_color_image(&hdr, &raw).unwrap().pixels,
+        "{name}: results differ"
+    );
+    let t_scalar = measure(|| {
+        black_box(old(black_box(&raw)));
+    });
+    let t_simd = measure(|| {
+        black_box(to_color_image(&hdr, black_box(&raw)).unwrap());
+    });
+    report(name, t_scalar, t_simd);
+}
+
+/// Samotné rozšíření na RGBA; původní smyčka včetně alokace výstupu, jak ji měl náhled.
+fn bench_expand(name: &str, bpp: usize, scalar: fn(&[u8]) -> Vec<u8>, simd: fn(&[u8], &mut [u8])) {
+    let src = noise(WIDTH as usize * HEIGHT as usize * bpp);
+    let mut out = vec![0u8; WIDTH as usize * HEIGHT as usize * 4];
+    simd(&src, &mut out);
+    assert!(scalar(&src) == out, "{name}: results differ");
+    let t_scalar = measure(|| {
+        black_box(scalar(black_box(&src)));
+    });
+    let t_simd = measure(|| simd(black_box(&src), black_box(&mut out)));
+    report(name, t_scalar, t_simd);
+}
+
+fn main() {
+    println!("{WIDTH} x {HEIGHT} px");
+    bench_rct(
+        "RCT RGB8",
+        3,
+        scalar::rct_inverse_rgb8,
+        simd::rct_inverse_rgb8,
+    );
+    bench_rct(
+        "RCT RGB16",
+        6,
+        scalar::rct_inverse_rgb16,
+        simd::rct_inverse_rgb16,
+    );
+    bench_expand("L8 → RGBA", 1, scalar::l8_to_rgba, simd::l8_to_rgba);
+    bench_expand("RGB8 → RGBA", 3, scalar::rgb8_to_rgba, simd::rgb8_to_rgba);
+    bench_preview("preview L8", 1, 1, scalar::l8_to_rgba);
+    bench_preview("preview RGB8", 3, 3, scalar::rgb8_to_rgba);
+}
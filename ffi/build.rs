@@ -0,0 +1,9 @@
+// Hlavička include/cti.h se generuje z src/lib.rs (nastavení v cbindgen.toml).
+fn main() {
+    let dir = std::env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR");
+    println!("cargo:rerun-if-changed=src/lib.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    cbindgen::generate(&dir)
+        .expect("generate C header")
+        .write_to_file(format!("{dir}/include/cti.h"));
+}
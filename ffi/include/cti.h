@@ -0,0 +1,129 @@
+#ifndef CTI_H
+#define CTI_H
+
+/* Generováno cbindgen z ffi/src/lib.rs – neupravovat ručně. */
+
+#include <stddef.h>
+#include <stdint.h>
+
+// Otevřený soubor CTI.
+typedef struct CtiFile CtiFile;
+
+// Soubor zapisovaný po řádcích dlaždic.
+typedef struct CtiWriter CtiWriter;
+
+// Hlavička souboru.
+typedef struct CtiHeader {
+  uint32_t width;
+  uint32_t height;
+  // Hrana dlaždice; krajní dlaždice vpravo a dole mohou být menší.
+  uint32_t tile_size;
+  uint32_t tiles_x;
+  uint32_t tiles_y;
+  // Počet snímků (≥ 1).
+  uint32_t frames;
+  // 1 = L8, 2 = L16, 3 = RGB8, 4 = RGBA8, 5 = RGB16 (16 bitů little-endian), 6 = L1 a 7 = L4
+  // (dekódují se do L8), 8 = CMYK8, 9 = L32F a 10 = RGB32F (f32 little-endian).
+  uint8_t color_type;
+  uint8_t bytes_per_pixel;
+  uint8_t compression;
+  uint8_t quality;
+  uint16_t version;
+  uint16_t flags;
+} CtiHeader;
+
+// Parametry zápisu (`cti_writer_create`).
+typedef struct CtiEncodeParams {
+  uint32_t tile_size;
+  // 0 = bez komprese, 10 = Zstd, 11 = LZ4, 20 = DCT (ztrátová, jen 8bit L, RGB, RGBA, CMYK).
+  uint8_t compression;
+  // Úroveň Zstd (1..=22, i pro koeficienty DCT).
+  int32_t level;
+  // Kvalita DCT (1..=100).
+  uint8_t quality;
+  // 1 = na konec připojit SHA-256 celého souboru.
+  uint8_t file_hash;
+} CtiEncodeParams;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Text poslední chyby v tomto vlákně (UTF-8), nebo NULL. Platí do dalšího volání
+// funkce `cti_*`, které selže.
+const char *cti_last_error(void);
+
+// Otevře soubor (cesta v UTF-8); NULL při chybě. Uvolnit přes `cti_free`.
+//
+// # Safety
+// `path` je NULL nebo ukazatel na řetězec ukončený nulou.
+struct CtiFile *cti_open(const char *path);
+
+// Vyplní `out` hlavičkou souboru; 0, nebo -1 při chybě.
+//
+// # Safety
+// `file` je NULL nebo ukazatel z `cti_open`; `out` je NULL nebo ukazatel na `CtiHeader`.
+int32_t cti_header(const struct CtiFile *file, struct CtiHeader *out);
+
+// Dekóduje dlaždici `(tx, ty)` snímku `frame` do `buf`: pixely po řádcích bez mezer,
+// šířka × výška dlaždice × `bytes_per_pixel` bajtů. Vrací počet zapsaných bajtů, nebo -1
+// při chybě (poškozená dlaždice, malý buffer). S `buf` = NULL jen vrátí potřebnou velikost.
+//
+// # Safety
+// `file` je NULL nebo ukazatel z `cti_open`; `buf` je NULL nebo ukazatel na `buf_len`
+// zapisovatelných bajtů.
+int64_t cti_decode_tile(struct CtiFile *file,
+                        uint32_t frame,
+                        uint32_t tx,
+                        uint32_t ty,
+                        uint8_t *buf,
+                        size_t buf_len);
+
+// Zavře soubor z `cti_open`; NULL nevadí.
+//
+// # Safety
+// `file` je NULL nebo ukazatel z `cti_open`, který se dál nepoužije.
+void cti_free(struct CtiFile *file);
+
+// Založí soubor `path` pro zápis obrázku `width × height` typu `color_type` po řádcích
+// dlaždic (index se zapíše až v `cti_writer_finish`); `params` NULL = výchozí parametry.
+// NULL při chybě.
+//
+// # Safety
+// `path` je NULL nebo ukazatel na řetězec ukončený nulou; `params` je NULL nebo ukazatel
+// na `CtiEncodeParams`.
+struct CtiWriter *cti_writer_create(const char *path,
+                                    uint32_t width,
+                                    uint32_t height,
+                                    uint8_t color_type,
+                                    const struct CtiEncodeParams *params);
+
+// Výška pásu pixelů, který čeká `cti_writer_push_row` (0 = všechny řádky jsou zapsané),
+// nebo -1 při chybě.
+//
+// # Safety
+// `writer` je NULL nebo ukazatel z `cti_writer_create`.
+int64_t cti_writer_row_height(const struct CtiWriter *writer);
+
+// Připojí další řádek dlaždic: pás přes celou šířku o výšce z `cti_writer_row_height`,
+// pixely po řádcích bez mezer (`len` = šířka × výška × bajty na pixel). 0, nebo -1 při chybě.
+//
+// # Safety
+// `writer` je NULL nebo ukazatel z `cti_writer_create`; `data` je NULL nebo ukazatel na
+// `len` čitelných bajtů.
+int32_t cti_writer_push_row(struct CtiWriter *writer,
+                            const uint8_t *data,
+                            size_t len);
+
+// Zapíše index a uzavře soubor; `writer` se uvolní i při chybě (např. chybějící řádky –
+// takový soubor nejde otevřít). 0, nebo -1 při chybě.
+//
+// # Safety
+// `writer` je NULL nebo ukazatel z `cti_writer_create`, který se dál nepoužije.
+int32_t cti_writer_finish(struct CtiWriter *writer);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* CTI_H */
@@ -0,0 +1,289 @@
+//! C rozhraní dekodéru CTI (`libcti`), aby obrazové linky v C/C++/Pythonu četly CTI bez
+//! vlastní implementace formátu, a zápisu po řádcích dlaždic přímo ze snímacích zařízení
+//! (`cti_writer_*`). Hlavičku `include/cti.h` generuje cbindgen při sestavení.
+//!
+//! Funkce vracejí 0 (nebo počet bajtů) při úspěchu a -1 při chybě; text chyby vrací
+//! `cti_last_error` (pro každé vlákno zvlášť). Jeden `CtiFile` nesmí současně používat
+//! víc vláken.
+
+use std::cell::RefCell;
+use std::ffi::{CStr, CString, c_char};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use std::ptr;
+
+use anyhow::{Context, Result, ensure};
+use cti_view::cti::{
+    self, CTIAppendEncoder, CTIExtensions, CTITileReader, CompressionId, EncodeParams,
+};
+
+/// Otevřený soubor CTI.
+pub struct CtiFile(CTITileReader);
+
+/// Hlavička souboru.
+#[repr(C)]
+pub struct CtiHeader {
+    pub width: u32,
+    pub height: u32,
+    /// Hrana dlaždice; krajní dlaždice vpravo a dole mohou být menší.
+    pub tile_size: u32,
+    pub tiles_x: u32,
+    pub tiles_y: u32,
+    /// Počet snímků (≥ 1).
+    pub frames: u32,
+    /// 1 = L8, 2 = L16, 3 = RGB8, 4 = RGBA8, 5 = RGB16 (16 bitů little-endian), 6 = L1 a 7 = L4
+    /// (dekódují se do L8), 8 = CMYK8, 9 = L32F a 10 = RGB32F (f32 little-endian).
+    pub color_type: u8,
+    pub bytes_per_pixel: u8,
+    pub compression: u8,
+    pub quality: u8,
+    pub version: u16,
+    pub flags: u16,
+}
+
+/// Soubor zapisovaný po řádcích dlaždic.
+pub struct CtiWriter(CTIAppendEncoder<BufWriter<File>>);
+
+/// Parametry zápisu (`cti_writer_create`).
+#[repr(C)]
+pub struct CtiEncodeParams {
+    pub tile_size: u32,
+    /// 0 = bez komprese, 10 = Zstd, 11 = LZ4, 20 = DCT (ztrátová, jen 8bit L, RGB, RGBA, CMYK).
+    pub compression: u8,
+    /// Úroveň Zstd (1..=22, i pro koeficienty DCT).
+    pub level: i32,
+    /// Kvalita DCT (1..=100).
+    pub quality: u8,
+    /// 1 = na konec připojit SHA-256 celého souboru.
+    pub file_hash: u8,
+}
+
+/// Text cesty z C.
+///
+/// # Safety
+/// `path` je NULL nebo ukazatel na řetězec ukončený nulou.
+unsafe fn path_str<'a>(path: *const c_char) -> Result<&'a str> {
+    ensure!(!path.is_null(), "path is NULL");
+    // SAFETY: nenulový ukazatel na řetězec ukončený nulou (viz # Safety)
+    unsafe { CStr::from_ptr(path) }
+        .to_str()
+        .context("path is not valid UTF-8")
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Výsledek převede na návratovou hodnotu C; chybu si zapamatuje pro `cti_last_error`.
+fn report<T>(result: Result<T>, error: T) -> T {
+    match result {
+        Ok(v) => v,
+        Err(e) => {
+            let text = CString::new(format!("{e:#}").replace('\0', " ")).unwrap_or_default();
+            LAST_ERROR.with(|last| *last.borrow_mut() = Some(text));
+            error
+        }
+    }
+}
+
+/// Text poslední chyby v tomto vlákně (UTF-8), nebo NULL. Platí do dalšího volání
+/// funkce `cti_*`, které selže.
+#[unsafe(no_mangle)]
+pub extern "C" fn cti_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |e| e.as_ptr()))
+}
+
+/// Otevře soubor (cesta v UTF-8); NULL při chybě. Uvolnit přes `cti_free`.
+///
+/// # Safety
+/// `path` je NULL nebo ukazatel na řetězec ukončený nulou.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn cti_open(path: *const c_char) -> *mut CtiFile {
+    let open = || {
+        // SAFETY: viz # Safety
+        let path = unsafe { path_str(path) }?;
+        let reader = CTITileReader::open(Path::new(path)).with_context(|| path.to_owned())?;
+        Ok(Box::into_raw(Box::new(CtiFile(reader))))
+    };
+    report(open(), ptr::null_mut())
+}
+
+/// Vyplní `out` hlavičkou souboru; 0, nebo -1 při chybě.
+///
+/// # Safety
+/// `file` je NULL nebo ukazatel z `cti_open`; `out` je NULL nebo ukazatel na `CtiHeader`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn cti_header(file: *const CtiFile, out: *mut CtiHeader) -> i32 {
+    // SAFETY: ukazatele z `cti_open` a na platnou strukturu (viz # Safety)
+    let (file, out) = unsafe { (file.as_ref(), out.as_mut()) };
+    let header = || {
+        let (Some(file), Some(out)) = (file, out) else {
+            anyhow::bail!("file or out is NULL");
+        };
+        let h = file.0.header();
+        *out = CtiHeader {
+            width: h.width,
+            height: h.height,
+            tile_size: h.tile_size,
+            tiles_x: h.tiles_x,
+            tiles_y: h.tiles_y,
+            frames: h.frames,
+            color_type: h.color_type,
+            bytes_per_pixel: cti::bytes_per_pixel(h.color_type)? as u8,
+            compression: h.compression,
+            quality: h.quality,
+            version: h.version,
+            flags: h.flags,
+        };
+        Ok(0)
+    };
+    report(header(), -1)
+}
+
+/// Dekóduje dlaždici `(tx, ty)` snímku `frame` do `buf`: pixely po řádcích bez mezer,
+/// šířka × výška dlaždice × `bytes_per_pixel` bajtů. Vrací počet zapsaných bajtů, nebo -1
+/// při chybě (poškozená dlaždice, malý buffer). S `buf` = NULL jen vrátí potřebnou velikost.
+///
+/// # Safety
+/// `file` je NULL nebo ukazatel z `cti_open`; `buf` je NULL nebo ukazatel na `buf_len`
+/// zapisovatelných bajtů.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn cti_decode_tile(
+    file: *mut CtiFile,
+    frame: u32,
+    tx: u32,
+    ty: u32,
+    buf: *mut u8,
+    buf_len: usize,
+) -> i64 {
+    // SAFETY: ukazatel z `cti_open` (viz # Safety)
+    let file = unsafe { file.as_mut() };
+    let decode = || {
+        let file = &mut file.context("file is NULL")?.0;
+        let h = *file.header();
+        ensure!(
+            tx < h.tiles_x && ty < h.tiles_y,
+            "Tile {tx},{ty} out of range ({} x {} tiles)",
+            h.tiles_x,
+            h.tiles_y
+        );
+        let (w, th) = cti::tile_dims(&h, tx, ty);
+        let len = (w * th * cti::bytes_per_pixel(h.color_type)?) as usize;
+        if buf.is_null() {
+            return Ok(len as i64);
+        }
+        ensure!(buf_len >= len, "buffer too small: {buf_len} < {len} bytes");
+        let tile = file.decode_tile(frame, tx, ty)?;
+        // SAFETY: `buf` má aspoň `buf_len` ≥ `len` bajtů (viz # Safety)
+        unsafe { ptr::copy_nonoverlapping(tile.as_ptr(), buf, len) };
+        Ok(len as i64)
+    };
+    report(decode(), -1)
+}
+
+/// Zavře soubor z `cti_open`; NULL nevadí.
+///
+/// # Safety
+/// `file` je NULL nebo ukazatel z `cti_open`, který se dál nepoužije.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn cti_free(file: *mut CtiFile) {
+    if !file.is_null() {
+        // SAFETY: vlastnictví se vrací z `cti_open` (viz # Safety)
+        drop(unsafe { Box::from_raw(file) });
+    }
+}
+
+/// Založí soubor `path` pro zápis obrázku `width × height` typu `color_type` po řádcích
+/// dlaždic (index se zapíše až v `cti_writer_finish`); `params` NULL = výchozí parametry.
+/// NULL při chybě.
+///
+/// # Safety
+/// `path` je NULL nebo ukazatel na řetězec ukončený nulou; `params` je NULL nebo ukazatel
+/// na `CtiEncodeParams`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn cti_writer_create(
+    path: *const c_char,
+    width: u32,
+    height: u32,
+    color_type: u8,
+    params: *const CtiEncodeParams,
+) -> *mut CtiWriter {
+    // SAFETY: ukazatel na platnou strukturu (viz # Safety)
+    let params = unsafe { params.as_ref() };
+    let create = || {
+        // SAFETY: viz # Safety
+        let path = unsafe { path_str(path) }?;
+        let mut p = EncodeParams::default();
+        if let Some(c) = params {
+            p.tile_size = c.tile_size;
+            p.compression = CompressionId::from(c.compression);
+            p.level = c.level;
+            p.quality = c.quality;
+            p.file_hash = c.file_hash != 0;
+        }
+        let ext = CTIExtensions::default();
+        let writer = CTIAppendEncoder::create(path, width, height, color_type, &p, &ext)
+            .with_context(|| path.to_owned())?;
+        Ok(Box::into_raw(Box::new(CtiWriter(writer))))
+    };
+    report(create(), ptr::null_mut())
+}
+
+/// Výška pásu pixelů, který čeká `cti_writer_push_row` (0 = všechny řádky jsou zapsané),
+/// nebo -1 při chybě.
+///
+/// # Safety
+/// `writer` je NULL nebo ukazatel z `cti_writer_create`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn cti_writer_row_height(writer: *const CtiWriter) -> i64 {
+    // SAFETY: ukazatel z `cti_writer_create` (viz # Safety)
+    let writer = unsafe { writer.as_ref() };
+    report(
+        writer
+            .context("writer is NULL")
+            .map(|w| i64::from(w.0.next_row_height())),
+        -1,
+    )
+}
+
+/// Připojí další řádek dlaždic: pás přes celou šířku o výšce z `cti_writer_row_height`,
+/// pixely po řádcích bez mezer (`len` = šířka × výška × bajty na pixel). 0, nebo -1 při chybě.
+///
+/// # Safety
+/// `writer` je NULL nebo ukazatel z `cti_writer_create`; `data` je NULL nebo ukazatel na
+/// `len` čitelných bajtů.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn cti_writer_push_row(
+    writer: *mut CtiWriter,
+    data: *const u8,
+    len: usize,
+) -> i32 {
+    // SAFETY: ukazatel z `cti_writer_create` (viz # Safety)
+    let writer = unsafe { writer.as_mut() };
+    let push = || {
+        let writer = &mut writer.context("writer is NULL")?.0;
+        ensure!(!data.is_null(), "data is NULL");
+        // SAFETY: `data` ukazuje na `len` čitelných bajtů (viz # Safety)
+        writer.push_row(unsafe { std::slice::from_raw_parts(data, len) })?;
+        Ok(0)
+    };
+    report(push(), -1)
+}
+
+/// Zapíše index a uzavře soubor; `writer` se uvolní i při chybě (např. chybějící řádky –
+/// takový soubor nejde otevřít). 0, nebo -1 při chybě.
+///
+/// # Safety
+/// `writer` je NULL nebo ukazatel z `cti_writer_create`, který se dál nepoužije.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn cti_writer_finish(writer: *mut CtiWriter) -> i32 {
+    let finish = || {
+        ensure!(!writer.is_null(), "writer is NULL");
+        // SAFETY: vlastnictví se vrací z `cti_writer_create` (viz # Safety)
+        let writer = unsafe { Box::from_raw(writer) };
+        writer.0.finish()?;
+        Ok(0)
+    };
+    report(finish(), -1)
+}
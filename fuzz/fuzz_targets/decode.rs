@@ -0,0 +1,23 @@
+//! Celý soubor z libovolných bajtů: hlavička, index, rozšiřující bloky a dlaždice prvního
+//! snímku. Obrázky nad [`MAX_PIXELS`] se přeskočí – výstupní buffer podle rozměru z hlavičky
+//! se alokuje oprávněně a limit drží fuzzer pod jeho limitem paměti.
+
+#![no_main]
+
+use cti_view::cti::{self, CTIDecoder, fuzzing};
+use libfuzzer_sys::fuzz_target;
+use std::io::Cursor;
+
+const MAX_PIXELS: u64 = 1 << 22;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(hdr) = fuzzing::read_header(data) else {
+        return;
+    };
+    let _ = CTIDecoder::extensions_from(Cursor::new(data));
+    let pixels = u64::from(hdr.width) * u64::from(hdr.height);
+    if pixels > MAX_PIXELS || cti::bytes_per_pixel(hdr.color_type).is_err() {
+        return;
+    }
+    let _ = CTIDecoder::decode_frame_from(Cursor::new(data), 0);
+});
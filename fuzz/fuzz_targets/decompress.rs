@@ -0,0 +1,35 @@
+//! Každá větev dekomprese dlaždice: první bajt vybere kodek, další dva očekávanou velikost
+//! (v násobcích 16 B, jako ji dekodér zná z indexu), zbytek jsou data. Kromě nekomprimovaných
+//! dlaždic nesmí výsledek přesáhnout očekávanou velikost – podvržená dlaždice nesmí zabrat
+//! libovolně paměti.
+
+#![no_main]
+
+use cti_view::cti::{CompressionId, fuzzing};
+use libfuzzer_sys::fuzz_target;
+
+const CODECS: [CompressionId; 8] = [
+    CompressionId::None,
+    CompressionId::Zstd,
+    CompressionId::Lz4,
+    CompressionId::Brotli,
+    CompressionId::Deflate,
+    CompressionId::Dct,
+    CompressionId::Rle,
+    CompressionId::Unknown(0xEE),
+];
+
+fuzz_target!(|data: &[u8]| {
+    let [codec, lo, hi, comp @ ..] = data else {
+        return;
+    };
+    let codec = CODECS[*codec as usize % CODECS.len()];
+    let original_size = usize::from(u16::from_le_bytes([*lo, *hi])) * 16;
+    let result = fuzzing::decompress_tile_with_size(codec.id(), comp, original_size);
+    if let Ok(tile) = result
+        && !matches!(codec, CompressionId::None)
+    {
+        let len = tile.len();
+        assert!(len <= original_size, "{len} B > {original_size} B");
+    }
+});
@@ -0,0 +1,16 @@
+//! Hlavička z libovolných bajtů: chyba je v pořádku, panika ne. Přijatá hlavička musí mít
+//! geometrii, se kterou dekodér počítá bez přetečení.
+
+#![no_main]
+
+use cti_view::cti::fuzzing;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(hdr) = fuzzing::read_header(data) {
+        assert!(hdr.width > 0 && hdr.height > 0 && hdr.tile_size > 0);
+        assert_eq!(hdr.tiles_x, hdr.width.div_ceil(hdr.tile_size));
+        assert_eq!(hdr.tiles_y, hdr.height.div_ceil(hdr.tile_size));
+        assert!(hdr.frames >= 1);
+    }
+});
@@ -0,0 +1,12 @@
+//! Hlavička a index dlaždic (za hlavičkou i podle lokátoru na konci souboru). Hlavička může
+//! slibovat miliardy dlaždic; čtení indexu musí skončit chybou na konci dat, ne vyčerpáním
+//! paměti.
+
+#![no_main]
+
+use cti_view::cti::fuzzing;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = fuzzing::read_indices(data);
+});
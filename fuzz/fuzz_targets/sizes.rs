@@ -0,0 +1,27 @@
+//! Výpočty velikostí bufferů z hlavičky (snímek, výřez, dlaždice) pro libovolné rozměry, bez
+//! omezení počtu pixelů jako v `decode`. Alokace místo toho hlídají [`DecodeOptions`], takže
+//! se k násobení rozměrů dostanou i hlavičky s miliardami pixelů.
+
+#![no_main]
+
+use cti_view::cti::{CTIDecoder, DecodeOptions, fuzzing};
+use libfuzzer_sys::fuzz_target;
+use std::io::Cursor;
+
+const MAX_BYTES: u64 = 1 << 24;
+
+fuzz_target!(|data: &[u8]| {
+    let opts = DecodeOptions {
+        max_pixels: None,
+        max_tile_bytes: Some(MAX_BYTES),
+        max_total_bytes: Some(MAX_BYTES),
+    };
+    let _ = CTIDecoder::decode_frame_from_with_options(Cursor::new(data), 0, &opts);
+    let Ok(hdr) = fuzzing::read_header(data) else {
+        return;
+    };
+    // výřez v pravém dolním rohu, kde leží neúplné okrajové dlaždice
+    let (w, h) = (hdr.width.min(64), hdr.height.min(64));
+    let region = (hdr.width - w, hdr.height - h, w, h);
+    let _ = CTIDecoder::decode_region_from_with_options(Cursor::new(data), 0, region, &opts);
+});
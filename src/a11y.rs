@@ -0,0 +1,45 @@
+use eframe::egui::{self, Response, Ui, WidgetInfo, WidgetType};
+
+/// Tlačítko s ikonou: `label` slouží jako tooltip i jako jméno pro čtečku obrazovky.
+pub fn icon_button(ui: &mut Ui, icon: &str, label: &str) -> Response {
+    icon_button_enabled(ui, true, icon, label)
+}
+
+pub fn icon_button_enabled(ui: &mut Ui, enabled: bool, icon: &str, label: &str) -> Response {
+    let resp = ui
+        .add_enabled(enabled, egui::Button::new(icon))
+        .on_hover_text(label);
+    resp.widget_info(|| WidgetInfo::labeled(WidgetType::Button, enabled, label));
+    resp
+}
+
+/// Modální dialog: blokuje zbytek UI (fokus zůstává uvnitř), Esc nebo klik mimo ho zavře.
+pub fn modal_dialog<R>(
+    ctx: &egui::Context,
+    title: &str,
+    open: &mut bool,
+    content: impl FnOnce(&mut Ui) -> R,
+) -> Option<R> {
+    if !*open {
+        return None;
+    }
+    let resp = egui::Modal::new(egui::Id::new(("dialog", title))).show(ctx, |ui| {
+        ui.horizontal(|ui| {
+            ui.heading(title);
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if icon_button(ui, "✖", "Close dialog").clicked() {
+                    *open = false;
+                }
+            });
+        });
+        ui.separator();
+        egui::ScrollArea::vertical()
+            .max_height(ui.ctx().screen_rect().height() * 0.8)
+            .show(ui, content)
+            .inner
+    });
+    if resp.should_close() {
+        *open = false;
+    }
+    Some(resp.inner)
+}
@@ -0,0 +1,363 @@
+use anyhow::{Result, anyhow, ensure};
+use eframe::egui::{Pos2, Vec2, pos2, vec2};
+use rayon::prelude::*;
+use rustfft::FftPlanner;
+use rustfft::num_complex::Complex32;
+
+use crate::cti::CTIHeader;
+use crate::diff::{self, Layout};
+
+/// Hrubý odhad se počítá na zmenšenině s delší stranou nejvýš tolik pixelů.
+const COARSE_SIZE: usize = 512;
+/// Největší výřez pro zpřesnění v plném rozlišení.
+const FINE_SIZE: usize = 512;
+/// Rozsah hledání natočení (±°).
+const MAX_ANGLE: f32 = 5.0;
+
+/// Transformace druhého snímku (B) do souřadnic prvního (A):
+/// `a = R(angle) · (b − center) + center + shift`, `center` je střed B.
+#[derive(Debug, Clone, Copy)]
+pub struct Alignment {
+    pub shift: Vec2,
+    /// Natočení ve stupních (kladné = po směru hodin na obrazovce).
+    pub angle: f32,
+    pub center: Pos2,
+    /// Výška korelačního vrcholu (0..1, vyšší = spolehlivější).
+    pub score: f32,
+}
+
+impl Alignment {
+    pub fn b_to_a(&self, b: Pos2) -> Pos2 {
+        self.center + rotate(b - self.center, self.angle) + self.shift
+    }
+
+    pub fn a_to_b(&self, a: Pos2) -> Pos2 {
+        self.center + rotate(a - self.center - self.shift, -self.angle)
+    }
+}
+
+fn rotate(v: Vec2, deg: f32) -> Vec2 {
+    let (s, c) = deg.to_radians().sin_cos();
+    vec2(c * v.x - s * v.y, s * v.x + c * v.y)
+}
+
+/// Jasový obraz v plovoucí čárce.
+pub(crate) struct Gray {
+    pub w: usize,
+    pub h: usize,
+    pub px: Vec<f32>,
+}
+
+impl Gray {
+    pub fn at(&self, x: usize, y: usize) -> f32 {
+        self.px[y * self.w + x]
+    }
+
+    /// Bilineární vzorek; mimo obraz `None`.
+    fn sample(&self, p: Pos2) -> Option<f32> {
+        let (x0, y0) = (p.x.floor(), p.y.floor());
+        if x0 < 0.0 || y0 < 0.0 || x0 as usize + 1 >= self.w || y0 as usize + 1 >= self.h {
+            return None;
+        }
+        let (fx, fy) = (p.x - x0, p.y - y0);
+        let (x, y) = (x0 as usize, y0 as usize);
+        let top = self.at(x, y) * (1.0 - fx) + self.at(x + 1, y) * fx;
+        let bottom = self.at(x, y + 1) * (1.0 - fx) + self.at(x + 1, y + 1) * fx;
+        Some(top * (1.0 - fy) + bottom * fy)
+    }
+}
+
+/// Přístup k jasu pixelů RAW bufferu (průměr barevných kanálů, 0..1).
+pub(crate) struct Luma<'a> {
+    raw: &'a [u8],
+    pub w: usize,
+    pub h: usize,
+    layout: Layout,
+}
+
+impl<'a> Luma<'a> {
+    pub fn new(hdr: &CTIHeader, raw: &'a [u8]) -> Result<Self> {
+        let layout = diff::layout(hdr.color_type)
+            .ok_or_else(|| anyhow!("unsupported color type {}", hdr.color_type))?;
+        Ok(Self {
+            raw,
+            w: hdr.width as usize,
+            h: hdr.height as usize,
+            layout,
+        })
+    }
+
+    pub fn at(&self, x: usize, y: usize) -> f32 {
+        let Layout { channels, wide } = self.layout;
+        let color = channels.min(3);
+        let peak = if wide { 65535.0 } else { 255.0 };
+        let i = (y * self.w + x) * channels;
+        let v: u32 = (0..color)
+            .map(|c| diff::sample(self.raw, wide, i + c))
+            .sum();
+        v as f32 / (color as f32 * peak)
+    }
+
+    fn bilinear(&self, p: Pos2) -> Option<f32> {
+        let (x0, y0) = (p.x.floor(), p.y.floor());
+        if x0 < 0.0 || y0 < 0.0 || x0 as usize + 1 >= self.w || y0 as usize + 1 >= self.h {
+            return None;
+        }
+        let (fx, fy) = (p.x - x0, p.y - y0);
+        let (x, y) = (x0 as usize, y0 as usize);
+        let top = self.at(x, y) * (1.0 - fx) + self.at(x + 1, y) * fx;
+        let bottom = self.at(x, y + 1) * (1.0 - fx) + self.at(x + 1, y + 1) * fx;
+        Some(top * (1.0 - fy) + bottom * fy)
+    }
+
+    /// Zmenšenina s krokem `step` (průměr bloků).
+    pub fn downsample(&self, step: usize) -> Gray {
+        let (gw, gh) = (self.w.div_ceil(step), self.h.div_ceil(step));
+        let mut px = vec![0f32; gw * gh];
+        let mut n = vec![0u32; gw * gh];
+        for y in 0..self.h {
+            for x in 0..self.w {
+                let g = (y / step) * gw + x / step;
+                px[g] += self.at(x, y);
+                n[g] += 1;
+            }
+        }
+        for (p, n) in px.iter_mut().zip(n) {
+            *p /= n.max(1) as f32;
+        }
+        Gray { w: gw, h: gh, px }
+    }
+}
+
+/// Odečte průměr a utlumí okraje Tukeyho oknem; `taper` = podíl délky s náběhem
+/// (1.0 = Hannovo okno).
+pub(crate) fn windowed(img: &[f32], w: usize, h: usize, taper: f32) -> Vec<f32> {
+    let win = |n: usize, i: usize| {
+        let r = (taper * n as f32 / 2.0).max(1.0);
+        let d = (i as f32).min((n - 1 - i) as f32);
+        if d >= r {
+            1.0
+        } else {
+            0.5 - 0.5 * (std::f32::consts::PI * d / r).cos()
+        }
+    };
+    let mean = img.iter().sum::<f32>() / img.len() as f32;
+    img.iter()
+        .enumerate()
+        .map(|(i, &v)| (v - mean) * win(w, i % w) * win(h, i / w))
+        .collect()
+}
+
+/// Fázová korelace dvou stejně velkých obrazů: vrací posun `d`, pro který `a(x) ≈ b(x − d)`,
+/// a výšku vrcholu.
+fn phase_correlate(a: &[f32], b: &[f32], w: usize, h: usize) -> (Vec2, f32) {
+    correlate(&windowed(a, w, h, 1.0), &windowed(b, w, h, 1.0), w, h)
+}
+
+/// Fázová korelace už připravených (okénkovaných) obrazů, viz [`phase_correlate`].
+pub(crate) fn correlate(a: &[f32], b: &[f32], w: usize, h: usize) -> (Vec2, f32) {
+    let mut planner = FftPlanner::<f32>::new();
+    let (row_f, col_f) = (planner.plan_fft_forward(w), planner.plan_fft_forward(h));
+    let (row_i, col_i) = (planner.plan_fft_inverse(w), planner.plan_fft_inverse(h));
+
+    let prep =
+        |img: &[f32]| -> Vec<Complex32> { img.iter().map(|&v| Complex32::new(v, 0.0)).collect() };
+    let fft2 =
+        |buf: &mut [Complex32], rows: &dyn rustfft::Fft<f32>, cols: &dyn rustfft::Fft<f32>| {
+            rows.process(buf);
+            let mut col = vec![Complex32::default(); h];
+            for x in 0..w {
+                for y in 0..h {
+                    col[y] = buf[y * w + x];
+                }
+                cols.process(&mut col);
+                for y in 0..h {
+                    buf[y * w + x] = col[y];
+                }
+            }
+        };
+
+    let (mut fa, mut fb) = (prep(a), prep(b));
+    fft2(&mut fa, row_f.as_ref(), col_f.as_ref());
+    fft2(&mut fb, row_f.as_ref(), col_f.as_ref());
+    // normalizované křížové spektrum
+    for (x, y) in fa.iter_mut().zip(&fb) {
+        let c = *x * y.conj();
+        let m = c.norm();
+        *x = if m > 1e-12 {
+            c / m
+        } else {
+            Complex32::default()
+        };
+    }
+    fft2(&mut fa, row_i.as_ref(), col_i.as_ref());
+
+    let n = (w * h) as f32;
+    let corr: Vec<f32> = fa.iter().map(|c| c.re / n).collect();
+    let (imax, peak) = corr
+        .iter()
+        .copied()
+        .enumerate()
+        .fold((0, f32::MIN), |m, (i, v)| if v > m.1 { (i, v) } else { m });
+    let (px, py) = (imax % w, imax / w);
+
+    // subpixelově parabolou přes sousedy
+    let at = |x: usize, y: usize| corr[(y % h) * w + (x % w)];
+    let refine = |l: f32, c: f32, r: f32| {
+        let d = l - 2.0 * c + r;
+        if d.abs() > 1e-12 {
+            (0.5 * (l - r) / d).clamp(-0.5, 0.5)
+        } else {
+            0.0
+        }
+    };
+    let sx = refine(at(px + w - 1, py), peak, at(px + 1, py));
+    let sy = refine(at(px, py + h - 1), peak, at(px, py + 1));
+    let wrap = |p: usize, n: usize| {
+        if p > n / 2 {
+            p as f32 - n as f32
+        } else {
+            p as f32
+        }
+    };
+    (vec2(wrap(px, w) + sx, wrap(py, h) + sy), peak)
+}
+
+/// Obraz B natočený o `angle` kolem `center` a vzorkovaný do mřížky `w`×`h`.
+fn rotated(b: &Gray, angle: f32, center: Pos2, w: usize, h: usize) -> Vec<f32> {
+    let mut out = vec![0f32; w * h];
+    for y in 0..h {
+        for x in 0..w {
+            let p = pos2(x as f32, y as f32);
+            let src = center + rotate(p - center, -angle);
+            out[y * w + x] = b.sample(src).unwrap_or(0.0);
+        }
+    }
+    out
+}
+
+/// Vloží obraz do mřížky `w`×`h` (zbytek nulový).
+fn padded(g: &Gray, w: usize, h: usize) -> Vec<f32> {
+    let mut out = vec![0f32; w * h];
+    for y in 0..g.h.min(h) {
+        for x in 0..g.w.min(w) {
+            out[y * w + x] = g.at(x, y);
+        }
+    }
+    out
+}
+
+/// Odhadne posun a natočení snímku B vůči A (fázová korelace + hledání úhlu).
+pub fn estimate(ha: &CTIHeader, a: &[u8], hb: &CTIHeader, b: &[u8]) -> Result<Alignment> {
+    ensure!(
+        ha.width >= 16 && ha.height >= 16 && hb.width >= 16 && hb.height >= 16,
+        "images are too small to align"
+    );
+    let longest = ha.width.max(ha.height).max(hb.width).max(hb.height) as usize;
+    let step = longest.div_ceil(COARSE_SIZE).max(1);
+    let (la, lb) = (Luma::new(ha, a)?, Luma::new(hb, b)?);
+    let (ga, gb) = (la.downsample(step), lb.downsample(step));
+    let (w, h) = (ga.w.max(gb.w), ga.h.max(gb.h));
+    let pa = padded(&ga, w, h);
+    let center = pos2(gb.w as f32 / 2.0, gb.h as f32 / 2.0);
+
+    // úhel: hrubě po 0,5°, pak jemně po 0,1° kolem nejlepšího
+    let mut best = (0f32, Vec2::ZERO, f32::MIN);
+    let try_angle = |angle: f32, best: &mut (f32, Vec2, f32)| {
+        let (d, peak) = phase_correlate(&pa, &rotated(&gb, angle, center, w, h), w, h);
+        if peak > best.2 {
+            *best = (angle, d, peak);
+        }
+    };
+    let coarse = (MAX_ANGLE / 0.5) as i32;
+    for i in -coarse..=coarse {
+        try_angle(i as f32 * 0.5, &mut best);
+    }
+    let base = best.0;
+    for i in -4..=4 {
+        try_angle(base + i as f32 * 0.1, &mut best);
+    }
+    let (angle, d, score) = best;
+    let s = step as f32;
+    let mut al = Alignment {
+        shift: d * s,
+        angle,
+        center: pos2(hb.width as f32 / 2.0, hb.height as f32 / 2.0),
+        score,
+    };
+
+    // zpřesnění v plném rozlišení: zbytkový posun výřezů kolem středu A, natočení
+    // z rozdílu posunů protilehlých výřezů (vlevo/vpravo, nahoře/dole)
+    let residual = |al: &Alignment, center: Pos2, size: usize| {
+        let (x0, y0) = (center.x as usize - size / 2, center.y as usize - size / 2);
+        let mut crop_a = Vec::with_capacity(size * size);
+        let mut crop_b = Vec::with_capacity(size * size);
+        for y in y0..y0 + size {
+            for x in x0..x0 + size {
+                crop_a.push(la.at(x, y));
+                let p = al.a_to_b(pos2(x as f32, y as f32));
+                crop_b.push(lb.bilinear(p).unwrap_or(0.0));
+            }
+        }
+        phase_correlate(&crop_a, &crop_b, size, size).0
+    };
+    let (cx, cy) = (la.w as f32 / 2.0, la.h as f32 / 2.0);
+    let (qx, qy) = (la.w as f32 / 4.0, la.h as f32 / 4.0);
+    let patch = FINE_SIZE.min(la.w / 3).min(la.h / 3) & !1;
+    let center = FINE_SIZE.min(la.w).min(la.h) & !1;
+    for _ in 0..4 {
+        let mut da = 0.0;
+        if patch >= 32 {
+            let l = residual(&al, pos2(cx - qx, cy), patch);
+            let r = residual(&al, pos2(cx + qx, cy), patch);
+            let t = residual(&al, pos2(cx, cy - qy), patch);
+            let b = residual(&al, pos2(cx, cy + qy), patch);
+            da = ((r.y - l.y) / (2.0 * qx) + (t.x - b.x) / (2.0 * qy)) / 2.0;
+            al.angle += da.to_degrees();
+        }
+        let d = residual(&al, pos2(cx, cy), center);
+        al.shift += d;
+        if d.length() < 0.05 && da.abs() < 1e-4 {
+            break;
+        }
+    }
+    Ok(al)
+}
+
+/// Převzorkuje B do geometrie A (velikost A, bilineárně, mimo B černá/průhledná).
+pub fn warp(ha: &CTIHeader, hb: &CTIHeader, b: &[u8], al: &Alignment) -> Result<Vec<u8>> {
+    let Layout { channels, wide } = diff::layout(hb.color_type)
+        .ok_or_else(|| anyhow!("unsupported color type {}", hb.color_type))?;
+    let (wa, ha_) = (ha.width as usize, ha.height as usize);
+    let (wb, hb_) = (hb.width as usize, hb.height as usize);
+    let bps = if wide { 2 } else { 1 };
+    let mut out = vec![0u8; wa * ha_ * channels * bps];
+    out.par_chunks_mut(wa * channels * bps)
+        .enumerate()
+        .for_each(|(y, row)| {
+            for x in 0..wa {
+                let p = al.a_to_b(pos2(x as f32, y as f32));
+                let (x0, y0) = (p.x.floor(), p.y.floor());
+                if x0 < 0.0 || y0 < 0.0 || x0 as usize + 1 >= wb || y0 as usize + 1 >= hb_ {
+                    continue;
+                }
+                let (fx, fy) = (p.x - x0, p.y - y0);
+                let (bx, by) = (x0 as usize, y0 as usize);
+                for c in 0..channels {
+                    let s = |xx: usize, yy: usize| {
+                        diff::sample(b, wide, (yy * wb + xx) * channels + c) as f32
+                    };
+                    let top = s(bx, by) * (1.0 - fx) + s(bx + 1, by) * fx;
+                    let bottom = s(bx, by + 1) * (1.0 - fx) + s(bx + 1, by + 1) * fx;
+                    let v = (top * (1.0 - fy) + bottom * fy).round();
+                    let o = (x * channels + c) * bps;
+                    if wide {
+                        row[o..o + 2].copy_from_slice(&(v as u16).to_le_bytes());
+                    } else {
+                        row[o] = v as u8;
+                    }
+                }
+            }
+        });
+    Ok(out)
+}
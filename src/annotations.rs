@@ -0,0 +1,1083 @@
+//! Anotace obrázku (poškození, poznámky konzervátorů).
+//!
+//! Formát (JSON, verze 1) se ukládá do sidecaru `<soubor>.annotations.json`:
+//! `{ "version": 1, "items": [ { "id", "author", "created", "color", "label", "shape" } ] }`,
+//! kde `shape` je `{"type": "rect", x, y, w, h}`, `{"type": "highlight", x, y, w, h}`,
+//! `{"type": "arrow", x1, y1, x2, y2}`, `{"type": "polygon", points: [[x, y], …]}`,
+//! `{"type": "path", width, points: [{x, y, pressure}, …]}`, `{"type": "point", x, y}`
+//! nebo `{"type": "text", x, y, size}` (text je `label`).
+//! Souřadnice jsou v pixelech obrázku (neotočeného), `created` je RFC 3339 v UTC.
+//! Stejný JSON lze vložit i do CTI jako položku metadat [`EMBEDDED_KEY`]; sidecar má přednost.
+//! Export do W3C Web Annotation (AnnotationPage) viz [`Annotations::to_w3c`].
+
+use anyhow::{Context, Result};
+use eframe::egui::{self, Color32, Key, Pos2, Rect, Response, Shape as EguiShape, Stroke};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::cti::{CTIDecoder, CTIMetadata};
+
+const FORMAT_VERSION: u32 = 1;
+
+/// Klíč metadat CTI s vloženými anotacemi (JSON jako v sidecaru).
+pub const EMBEDDED_KEY: &str = "Annotations";
+
+/// Bod tahu v souřadnicích obrázku (pixely) s přítlakem pera 0..=1.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct StrokePoint {
+    pub x: f32,
+    pub y: f32,
+    pub pressure: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Shape {
+    Rect {
+        x: f32,
+        y: f32,
+        w: f32,
+        h: f32,
+    },
+    Polygon {
+        points: Vec<[f32; 2]>,
+    },
+    /// Volný tah; šířka je v pixelech obrázku při plném přítlaku.
+    Path {
+        width: f32,
+        points: Vec<StrokePoint>,
+    },
+    /// Textový štítek ukotvený v bodě.
+    Point {
+        x: f32,
+        y: f32,
+    },
+    /// Zvýraznění: poloprůhledně vyplněný obdélník.
+    Highlight {
+        x: f32,
+        y: f32,
+        w: f32,
+        h: f32,
+    },
+    /// Šipka z (x1, y1) do (x2, y2).
+    Arrow {
+        x1: f32,
+        y1: f32,
+        x2: f32,
+        y2: f32,
+    },
+    /// Text (popisek anotace) vepsaný do obrázku; `size` = výška písma v pixelech obrázku.
+    Text {
+        x: f32,
+        y: f32,
+        size: f32,
+    },
+}
+
+impl Shape {
+    fn kind(&self) -> &'static str {
+        match self {
+            Shape::Rect { .. } => "Rectangle",
+            Shape::Polygon { .. } => "Polygon",
+            Shape::Path { .. } => "Freehand",
+            Shape::Point { .. } => "Note",
+            Shape::Highlight { .. } => "Highlight",
+            Shape::Arrow { .. } => "Arrow",
+            Shape::Text { .. } => "Text",
+        }
+    }
+
+    /// Kotva pro popisek (levý horní roh / první bod).
+    fn anchor(&self) -> Option<Pos2> {
+        match self {
+            Shape::Rect { x, y, .. }
+            | Shape::Highlight { x, y, .. }
+            | Shape::Point { x, y }
+            | Shape::Text { x, y, .. } => Some(Pos2::new(*x, *y)),
+            Shape::Arrow { x1, y1, .. } => Some(Pos2::new(*x1, *y1)),
+            Shape::Polygon { points } => points.first().map(|&[x, y]| Pos2::new(x, y)),
+            Shape::Path { points, .. } => points.first().map(|p| Pos2::new(p.x, p.y)),
+        }
+    }
+
+    /// Ohraničující obdélník v souřadnicích obrázku.
+    fn bbox(&self) -> Rect {
+        let pts: Vec<Pos2> = match self {
+            Shape::Rect { x, y, w, h } | Shape::Highlight { x, y, w, h } => {
+                vec![Pos2::new(*x, *y), Pos2::new(x + w, y + h)]
+            }
+            Shape::Point { x, y } | Shape::Text { x, y, .. } => vec![Pos2::new(*x, *y)],
+            Shape::Arrow { x1, y1, x2, y2 } => vec![Pos2::new(*x1, *y1), Pos2::new(*x2, *y2)],
+            Shape::Polygon { points } => points.iter().map(|&[x, y]| Pos2::new(x, y)).collect(),
+            Shape::Path { points, .. } => points.iter().map(|p| Pos2::new(p.x, p.y)).collect(),
+        };
+        Rect::from_points(&pts)
+    }
+
+    /// Kopie tvaru s přepočtenými souřadnicemi.
+    fn mapped(&self, f: impl Fn(Pos2) -> Pos2) -> Shape {
+        let p = |x: f32, y: f32| f(Pos2::new(x, y));
+        match self {
+            Shape::Rect { x, y, w, h } => {
+                rect_shape(Rect::from_two_pos(p(*x, *y), p(x + w, y + h)))
+            }
+            Shape::Highlight { x, y, w, h } => {
+                let r = Rect::from_two_pos(p(*x, *y), p(x + w, y + h));
+                Shape::Highlight {
+                    x: r.min.x,
+                    y: r.min.y,
+                    w: r.width(),
+                    h: r.height(),
+                }
+            }
+            Shape::Point { x, y } => {
+                let q = p(*x, *y);
+                Shape::Point { x: q.x, y: q.y }
+            }
+            Shape::Text { x, y, size } => {
+                let q = p(*x, *y);
+                Shape::Text {
+                    x: q.x,
+                    y: q.y,
+                    size: *size,
+                }
+            }
+            Shape::Arrow { x1, y1, x2, y2 } => {
+                let (a, b) = (p(*x1, *y1), p(*x2, *y2));
+                Shape::Arrow {
+                    x1: a.x,
+                    y1: a.y,
+                    x2: b.x,
+                    y2: b.y,
+                }
+            }
+            Shape::Polygon { points } => Shape::Polygon {
+                points: points
+                    .iter()
+                    .map(|&[x, y]| {
+                        let q = p(x, y);
+                        [q.x, q.y]
+                    })
+                    .collect(),
+            },
+            Shape::Path { width, points } => Shape::Path {
+                width: *width,
+                points: points
+                    .iter()
+                    .map(|s| {
+                        let q = p(s.x, s.y);
+                        StrokePoint {
+                            x: q.x,
+                            y: q.y,
+                            ..*s
+                        }
+                    })
+                    .collect(),
+            },
+        }
+    }
+
+    /// Selektor cíle pro W3C Web Annotation.
+    fn w3c_selector(&self) -> serde_json::Value {
+        let svg = |body: String| {
+            json!({
+                "type": "SvgSelector",
+                "value": format!("<svg xmlns=\"http://www.w3.org/2000/svg\">{body}</svg>"),
+            })
+        };
+        let xywh = |x: f32, y: f32, w: f32, h: f32| {
+            json!({
+                "type": "FragmentSelector",
+                "conformsTo": "http://www.w3.org/TR/media-frags/",
+                "value": format!(
+                    "xywh={},{},{},{}",
+                    x.round(),
+                    y.round(),
+                    w.round().max(1.0),
+                    h.round().max(1.0)
+                ),
+            })
+        };
+        let coords = |pts: &mut dyn Iterator<Item = (f32, f32)>| {
+            pts.map(|(x, y)| format!("{x:.1},{y:.1}"))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        match self {
+            Shape::Rect { x, y, w, h } | Shape::Highlight { x, y, w, h } => xywh(*x, *y, *w, *h),
+            Shape::Point { x, y } | Shape::Text { x, y, .. } => xywh(*x, *y, 1.0, 1.0),
+            Shape::Arrow { x1, y1, x2, y2 } => svg(format!(
+                "<line x1=\"{x1:.1}\" y1=\"{y1:.1}\" x2=\"{x2:.1}\" y2=\"{y2:.1}\"/>"
+            )),
+            Shape::Polygon { points } => svg(format!(
+                "<polygon points=\"{}\"/>",
+                coords(&mut points.iter().map(|&[x, y]| (x, y)))
+            )),
+            Shape::Path { width, points } => svg(format!(
+                "<polyline fill=\"none\" stroke-width=\"{width}\" points=\"{}\"/>",
+                coords(&mut points.iter().map(|p| (p.x, p.y)))
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Annotation {
+    pub id: String,
+    pub author: String,
+    pub created: String,
+    pub color: [u8; 4],
+    #[serde(default)]
+    pub label: String,
+    pub shape: Shape,
+}
+
+/// Anotace jednoho souboru.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Annotations {
+    pub version: u32,
+    pub items: Vec<Annotation>,
+}
+
+impl Default for Annotations {
+    fn default() -> Self {
+        Self {
+            version: FORMAT_VERSION,
+            items: Vec::new(),
+        }
+    }
+}
+
+pub fn sidecar_path(image: &Path) -> PathBuf {
+    let mut name = image.file_name().unwrap_or_default().to_os_string();
+    name.push(".annotations.json");
+    image.with_file_name(name)
+}
+
+/// Anotace vložené v metadatech CTI (`None` = soubor žádné nemá).
+fn embedded(image: &Path) -> Result<Option<Annotations>> {
+    let Ok(meta) = CTIDecoder::metadata(image) else {
+        return Ok(None);
+    };
+    meta.get(EMBEDDED_KEY)
+        .map(|json| {
+            serde_json::from_str(json)
+                .with_context(|| format!("parse {EMBEDDED_KEY} in {:?}", image))
+        })
+        .transpose()
+}
+
+impl Annotations {
+    /// Načte sidecar, bez něj anotace vložené v CTI; nic z toho = žádné anotace.
+    pub fn load(image: &Path) -> Result<Self> {
+        let path = sidecar_path(image);
+        match std::fs::read(&path) {
+            Ok(bytes) => {
+                serde_json::from_slice(&bytes).with_context(|| format!("parse {:?}", path))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                Ok(embedded(image)?.unwrap_or_default())
+            }
+            Err(e) => Err(e).with_context(|| format!("read {:?}", path)),
+        }
+    }
+
+    /// Uloží sidecar; prázdné anotace sidecar smažou (ledaže má CTI anotace vložené –
+    /// prázdný sidecar je pak skryje).
+    pub fn save(&self, image: &Path) -> Result<()> {
+        let path = sidecar_path(image);
+        if self.items.is_empty() && embedded(image).ok().flatten().is_none() {
+            return match std::fs::remove_file(&path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                    Err(e).with_context(|| format!("remove {:?}", path))
+                }
+                _ => Ok(()),
+            };
+        }
+        let json = serde_json::to_vec_pretty(self)?;
+        std::fs::write(&path, json).with_context(|| format!("write {:?}", path))
+    }
+
+    /// Metadata `meta` s vloženými anotacemi (prázdné anotace položku odeberou).
+    pub fn embed_into(&self, meta: &mut CTIMetadata) -> Result<()> {
+        if self.items.is_empty() {
+            meta.entries.retain(|(k, _)| k != EMBEDDED_KEY);
+        } else {
+            meta.set(EMBEDDED_KEY, serde_json::to_string(self)?);
+        }
+        Ok(())
+    }
+
+    fn push(&mut self, author: &str, color: Color32, shape: Shape) -> usize {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        self.items.push(Annotation {
+            id: format!("{:x}-{}", now.as_millis(), self.items.len()),
+            author: author.to_string(),
+            created: rfc3339_utc(now.as_secs()),
+            color: color.to_srgba_unmultiplied(),
+            label: String::new(),
+            shape,
+        });
+        self.items.len() - 1
+    }
+
+    /// W3C Web Annotation `AnnotationPage` (použitelné i jako IIIF anotace).
+    /// `source` je URI cílového obrázku (např. `file:///…` nebo IIIF canvas).
+    pub fn to_w3c(&self, source: &str) -> serde_json::Value {
+        let items: Vec<_> = self
+            .items
+            .iter()
+            .map(|a| {
+                let motivation = match a.shape {
+                    Shape::Point { .. } | Shape::Text { .. } => "commenting",
+                    Shape::Highlight { .. } => "highlighting",
+                    _ if a.label.is_empty() => "highlighting",
+                    _ => "describing",
+                };
+                let body: Vec<_> = (!a.label.is_empty())
+                    .then(|| {
+                        json!({
+                            "type": "TextualBody",
+                            "value": a.label,
+                            "format": "text/plain",
+                            "purpose": motivation,
+                        })
+                    })
+                    .into_iter()
+                    .collect();
+                let [r, g, b, _] = a.color;
+                json!({
+                    "id": format!("{source}#anno-{}", a.id),
+                    "type": "Annotation",
+                    "motivation": motivation,
+                    "creator": { "type": "Person", "name": a.author },
+                    "created": a.created,
+                    "body": body,
+                    "target": {
+                        "source": source,
+                        "selector": a.shape.w3c_selector(),
+                        "styleClass": format!("color-{r:02x}{g:02x}{b:02x}"),
+                    },
+                })
+            })
+            .collect();
+        json!({
+            "@context": "http://www.w3.org/ns/anno.jsonld",
+            "id": format!("{source}#annotations"),
+            "type": "AnnotationPage",
+            "items": items,
+        })
+    }
+}
+
+/// Sekundy od epochy → `YYYY-MM-DDTHH:MM:SSZ`.
+/// Aktuální čas jako RFC 3339 (UTC).
+pub fn now_rfc3339() -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    rfc3339_utc(now.as_secs())
+}
+
+fn rfc3339_utc(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let rem = secs % 86_400;
+    // civil_from_days (H. Hinnant)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + (m <= 2) as i64;
+    format!(
+        "{y:04}-{m:02}-{d:02}T{:02}:{:02}:{:02}Z",
+        rem / 3600,
+        rem / 60 % 60,
+        rem % 60
+    )
+}
+
+/// `file://` URI pro cíl anotací.
+fn file_uri(path: &Path) -> String {
+    let abs = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let s = abs.display().to_string().replace('\\', "/");
+    // Windows: "//?/C:/…" z canonicalize → "/C:/…"
+    let s = s.strip_prefix("//?/").unwrap_or(&s);
+    if s.starts_with('/') {
+        format!("file://{s}")
+    } else {
+        format!("file:///{s}")
+    }
+}
+
+/// Stav anotace při porovnání dvou verzí.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    Unchanged,
+    /// Jen v levé (starší) verzi.
+    Removed,
+    /// Jen v pravé (novější) verzi.
+    Added,
+    /// V obou verzích, ale jinde nebo s jiným tvarem.
+    Moved,
+}
+
+impl Change {
+    fn color(self) -> Color32 {
+        match self {
+            Change::Unchanged => Color32::from_gray(160),
+            Change::Removed => Color32::from_rgb(230, 50, 50),
+            Change::Added => Color32::from_rgb(40, 200, 80),
+            Change::Moved => Color32::from_rgb(255, 170, 0),
+        }
+    }
+}
+
+/// Výsledek porovnání dvou sad anotací (stav pro každou položku vlevo a vpravo).
+pub struct AnnotationDiff {
+    pub left: Vec<Change>,
+    pub right: Vec<Change>,
+}
+
+impl AnnotationDiff {
+    /// Páruje nejdřív podle `id` (sidecar zkopírovaný s novým skenem), zbytek podle druhu
+    /// a popisku s překrývajícím se ohraničením. `to_left` převádí souřadnice pravého obrázku
+    /// do levého (např. poměr velikostí nebo výsledek zarovnání).
+    pub fn compute(
+        left: &Annotations,
+        right: &Annotations,
+        to_left: impl Fn(Pos2) -> Pos2,
+    ) -> Self {
+        let right_shapes: Vec<Shape> = right
+            .items
+            .iter()
+            .map(|a| a.shape.mapped(&to_left))
+            .collect();
+        let mut l = vec![Change::Removed; left.items.len()];
+        let mut r = vec![Change::Added; right.items.len()];
+        let pair = |i: usize, j: usize, l: &mut [Change], r: &mut [Change]| {
+            let (a, b) = (left.items[i].shape.bbox(), right_shapes[j].bbox());
+            // tolerance 1 px (zaokrouhlení po zarovnání)
+            let same = (a.min - b.min).length() <= 1.0 && (a.max - b.max).length() <= 1.0;
+            let c = if same {
+                Change::Unchanged
+            } else {
+                Change::Moved
+            };
+            l[i] = c;
+            r[j] = c;
+        };
+        for (i, a) in left.items.iter().enumerate() {
+            if let Some(j) = right.items.iter().position(|b| b.id == a.id) {
+                pair(i, j, &mut l, &mut r);
+            }
+        }
+        for (i, a) in left.items.iter().enumerate() {
+            if l[i] != Change::Removed {
+                continue;
+            }
+            let abox = a.shape.bbox();
+            let best = right
+                .items
+                .iter()
+                .enumerate()
+                .filter(|&(j, b)| {
+                    r[j] == Change::Added
+                        && b.shape.kind() == a.shape.kind()
+                        && b.label == a.label
+                        && right_shapes[j]
+                            .bbox()
+                            .expand(2.0)
+                            .intersects(abox.expand(2.0))
+                })
+                .min_by(|(j1, _), (j2, _)| {
+                    let d = |j: usize| (right_shapes[j].bbox().center() - abox.center()).length();
+                    d(*j1).total_cmp(&d(*j2))
+                })
+                .map(|(j, _)| j);
+            if let Some(j) = best {
+                pair(i, j, &mut l, &mut r);
+            }
+        }
+        Self { left: l, right: r }
+    }
+
+    pub fn count(&self, change: Change) -> usize {
+        let side = if change == Change::Removed {
+            &self.left
+        } else {
+            &self.right
+        };
+        side.iter().filter(|&&c| c == change).count()
+    }
+}
+
+/// Vykreslí sadu anotací obarvenou podle stavu v porovnání.
+pub fn paint_diff(
+    painter: &egui::Painter,
+    doc: &Annotations,
+    changes: &[Change],
+    scale: f32,
+    to_screen: impl Fn(Pos2) -> Pos2,
+) {
+    for (a, &c) in doc.items.iter().zip(changes) {
+        paint_shape(painter, &a.shape, c.color(), scale, &to_screen);
+        if let Some(anchor) = a.shape.anchor() {
+            let text = match c {
+                Change::Unchanged => a.label.clone(),
+                Change::Removed => format!("− {}", a.label),
+                Change::Added => format!("+ {}", a.label),
+                Change::Moved => format!("↔ {}", a.label),
+            };
+            if !text.trim().is_empty() {
+                paint_label(painter, to_screen(anchor), text.trim());
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tool {
+    Pen,
+    Rect,
+    Highlight,
+    Arrow,
+    Polygon,
+    Note,
+    Text,
+}
+
+impl Tool {
+    const ALL: [Tool; 7] = [
+        Tool::Pen,
+        Tool::Rect,
+        Tool::Highlight,
+        Tool::Arrow,
+        Tool::Polygon,
+        Tool::Note,
+        Tool::Text,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            Tool::Pen => "✏ Pen",
+            Tool::Rect => "▭ Rectangle",
+            Tool::Highlight => "▨ Highlight",
+            Tool::Arrow => "↗ Arrow",
+            Tool::Polygon => "⬟ Polygon",
+            Tool::Note => "🗩 Note",
+            Tool::Text => "T Text",
+        }
+    }
+}
+
+/// Rozpracovaný tvar (souřadnice obrázku).
+enum Draft {
+    Path(Vec<StrokePoint>),
+    /// Tažení obdélníku, zvýraznění nebo šipky (začátek, konec).
+    Drag(Pos2, Pos2),
+    Polygon(Vec<Pos2>),
+}
+
+/// Kreslení anotací perem/myší nad obrázkem + vrstva s jejich zobrazením.
+pub struct Annotator {
+    pub enabled: bool,
+    pub visible: bool,
+    pub show_list: bool,
+    pub tool: Tool,
+    pub color: Color32,
+    pub width: f32,
+    /// Výška písma nástroje Text v pixelech obrázku.
+    pub text_size: f32,
+    pub doc: Annotations,
+    /// Uživatel chce anotace vložit do CTI souboru (vyřizuje aplikace).
+    pub embed_requested: bool,
+    draft: Option<Draft>,
+}
+
+impl Default for Annotator {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            visible: true,
+            show_list: false,
+            tool: Tool::Pen,
+            color: Color32::from_rgb(255, 40, 40),
+            width: 4.0,
+            text_size: 48.0,
+            doc: Annotations::default(),
+            embed_requested: false,
+            draft: None,
+        }
+    }
+}
+
+impl Annotator {
+    /// Ovládání v toolbaru; vrací `true`, když se anotace změnily (k uložení).
+    pub fn controls_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+        ui.toggle_value(&mut self.enabled, "Annotate")
+            .on_hover_text("Draw shapes, freehand strokes (pressure-aware) and notes");
+        if self.enabled {
+            self.visible = true;
+            egui::ComboBox::from_id_salt("annotation-tool")
+                .selected_text(self.tool.label())
+                .show_ui(ui, |ui| {
+                    for t in Tool::ALL {
+                        ui.selectable_value(&mut self.tool, t, t.label());
+                    }
+                });
+            ui.color_edit_button_srgba(&mut self.color);
+            if self.tool == Tool::Pen {
+                ui.add(
+                    egui::DragValue::new(&mut self.width)
+                        .range(0.5..=200.0)
+                        .suffix(" px"),
+                )
+                .on_hover_text("Stroke width at full pressure (image pixels)");
+            }
+            if self.tool == Tool::Text {
+                ui.add(
+                    egui::DragValue::new(&mut self.text_size)
+                        .range(4.0..=2000.0)
+                        .suffix(" px"),
+                )
+                .on_hover_text("Text height (image pixels)");
+            }
+            if ui
+                .add_enabled(!self.doc.items.is_empty(), egui::Button::new("Undo"))
+                .clicked()
+            {
+                self.doc.items.pop();
+                changed = true;
+            }
+        } else {
+            self.draft = None;
+            if !self.doc.items.is_empty() {
+                ui.checkbox(&mut self.visible, "Show annotations");
+            }
+        }
+        ui.toggle_value(&mut self.show_list, format!("☰ {}", self.doc.items.len()))
+            .on_hover_text("Annotation list");
+        changed
+    }
+
+    /// Zpracuje vstup nad obrázkem; `to_image` převádí pozici na obrazovce do souřadnic obrázku.
+    /// Vrací `true`, když přibyla anotace.
+    pub fn handle_input(
+        &mut self,
+        resp: &Response,
+        author: &str,
+        to_image: impl Fn(Pos2) -> Pos2,
+    ) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        let pos = resp.interact_pointer_pos().map(&to_image);
+        let (enter, escape) = resp
+            .ctx
+            .input(|i| (i.key_pressed(Key::Enter), i.key_pressed(Key::Escape)));
+        if escape {
+            self.draft = None;
+        }
+        let shape = match self.tool {
+            Tool::Pen => self.pen_input(resp, pos),
+            Tool::Rect => self
+                .drag_input(resp, pos)
+                .map(|(a, b)| Rect::from_two_pos(a, b))
+                .filter(|r| r.width() >= 1.0 && r.height() >= 1.0)
+                .map(rect_shape),
+            Tool::Highlight => self
+                .drag_input(resp, pos)
+                .map(|(a, b)| Rect::from_two_pos(a, b))
+                .filter(|r| r.width() >= 1.0 && r.height() >= 1.0)
+                .map(highlight_shape),
+            Tool::Arrow => self
+                .drag_input(resp, pos)
+                .filter(|(a, b)| a.distance(*b) >= 2.0)
+                .map(|(a, b)| arrow_shape(a, b)),
+            Tool::Polygon => {
+                // klik přidá vrchol, dvojklik nebo Enter polygon uzavře
+                if resp.clicked()
+                    && let Some(p) = pos
+                {
+                    match &mut self.draft {
+                        Some(Draft::Polygon(pts)) => {
+                            if pts.last().is_none_or(|l| l.distance(p) > 0.5) {
+                                pts.push(p);
+                            }
+                        }
+                        _ => self.draft = Some(Draft::Polygon(vec![p])),
+                    }
+                }
+                let close = resp.double_clicked() || enter;
+                match self.draft.take_if(|_| close) {
+                    Some(Draft::Polygon(pts)) if pts.len() >= 3 => Some(Shape::Polygon {
+                        points: pts.iter().map(|p| [p.x, p.y]).collect(),
+                    }),
+                    _ => None,
+                }
+            }
+            Tool::Note => match pos {
+                Some(p) if resp.clicked() => Some(Shape::Point { x: p.x, y: p.y }),
+                _ => None,
+            },
+            Tool::Text => match pos {
+                Some(p) if resp.clicked() => Some(Shape::Text {
+                    x: p.x,
+                    y: p.y,
+                    size: self.text_size,
+                }),
+                _ => None,
+            },
+        };
+        let Some(shape) = shape else {
+            return false;
+        };
+        // poznámka a text se hned popíšou v seznamu
+        let label = match shape {
+            Shape::Point { .. } => Some("Note"),
+            Shape::Text { .. } => Some("Text"),
+            _ => None,
+        };
+        let idx = self.doc.push(author, self.color, shape);
+        if let Some(label) = label {
+            self.doc.items[idx].label = label.into();
+            self.show_list = true;
+        }
+        true
+    }
+
+    fn pen_input(&mut self, resp: &Response, pos: Option<Pos2>) -> Option<Shape> {
+        // tablety hlásí přítlak přes dotykové události; myš = plný přítlak
+        let pressure = resp.ctx.input(|i| {
+            i.events.iter().rev().find_map(|e| match e {
+                egui::Event::Touch { force: Some(f), .. } => Some(*f),
+                _ => None,
+            })
+        });
+        if resp.drag_started() {
+            self.draft = Some(Draft::Path(Vec::new()));
+        }
+        if let Some(Draft::Path(points)) = &mut self.draft
+            && let Some(p) = pos
+        {
+            let last_pressure = points.last().map_or(1.0, |l| l.pressure);
+            // nepřidávat body, které se téměř nepohnuly
+            if points
+                .last()
+                .is_none_or(|l| (l.x - p.x).abs() + (l.y - p.y).abs() > 0.25)
+            {
+                points.push(StrokePoint {
+                    x: p.x,
+                    y: p.y,
+                    pressure: pressure.unwrap_or(last_pressure).clamp(0.05, 1.0),
+                });
+            }
+        }
+        match self.draft.take_if(|_| resp.drag_stopped()) {
+            Some(Draft::Path(points)) if !points.is_empty() => Some(Shape::Path {
+                width: self.width,
+                points,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Tažení z bodu do bodu; po puštění vrátí začátek a konec.
+    fn drag_input(&mut self, resp: &Response, pos: Option<Pos2>) -> Option<(Pos2, Pos2)> {
+        if resp.drag_started()
+            && let Some(p) = pos
+        {
+            self.draft = Some(Draft::Drag(p, p));
+        }
+        if let Some(Draft::Drag(_, b)) = &mut self.draft
+            && let Some(p) = pos
+        {
+            *b = p;
+        }
+        match self.draft.take_if(|_| resp.drag_stopped()) {
+            Some(Draft::Drag(a, b)) => Some((a, b)),
+            _ => None,
+        }
+    }
+
+    /// Vykreslí vrstvu anotací i rozpracovaný tvar; `to_screen` převádí souřadnice obrázku na obrazovku.
+    pub fn paint(&self, painter: &egui::Painter, scale: f32, to_screen: impl Fn(Pos2) -> Pos2) {
+        if !self.visible {
+            return;
+        }
+        for a in &self.doc.items {
+            let [r, g, b, alpha] = a.color;
+            let color = Color32::from_rgba_unmultiplied(r, g, b, alpha);
+            paint_shape(painter, &a.shape, color, scale, &to_screen);
+            if let Shape::Text { size, .. } = a.shape {
+                paint_text(painter, &a.shape, &a.label, size * scale, color, &to_screen);
+            } else if !a.label.is_empty()
+                && let Some(anchor) = a.shape.anchor()
+            {
+                paint_label(painter, to_screen(anchor), &a.label);
+            }
+        }
+        match &self.draft {
+            Some(Draft::Path(points)) => {
+                let shape = Shape::Path {
+                    width: self.width,
+                    points: points.clone(),
+                };
+                paint_shape(painter, &shape, self.color, scale, &to_screen);
+            }
+            Some(Draft::Drag(a, b)) => {
+                let r = Rect::from_two_pos(*a, *b);
+                let shape = match self.tool {
+                    Tool::Highlight => highlight_shape(r),
+                    Tool::Arrow => arrow_shape(*a, *b),
+                    _ => rect_shape(r),
+                };
+                paint_shape(painter, &shape, self.color, scale, &to_screen);
+            }
+            Some(Draft::Polygon(pts)) => {
+                let screen: Vec<Pos2> = pts.iter().map(|&p| to_screen(p)).collect();
+                for &p in &screen {
+                    painter.circle_filled(p, 3.0, self.color);
+                }
+                painter.add(EguiShape::line(screen, Stroke::new(2.0, self.color)));
+            }
+            None => {}
+        }
+    }
+
+    /// Seznam anotací (popisky, autor, čas, mazání, export); vrací `true` při změně.
+    pub fn list_window(&mut self, ctx: &egui::Context, image: Option<&Path>) -> bool {
+        let mut changed = false;
+        let mut open = self.show_list;
+        egui::Window::new("Annotations")
+            .open(&mut open)
+            .default_width(320.0)
+            .show(ctx, |ui| {
+                let mut remove = None;
+                egui::ScrollArea::vertical()
+                    .max_height(360.0)
+                    .show(ui, |ui| {
+                        for (i, a) in self.doc.items.iter_mut().enumerate() {
+                            ui.horizontal(|ui| {
+                                let [r, g, b, _] = a.color;
+                                let (swatch, _) = ui.allocate_exact_size(
+                                    egui::vec2(10.0, 10.0),
+                                    egui::Sense::hover(),
+                                );
+                                ui.painter()
+                                    .rect_filled(swatch, 2.0, Color32::from_rgb(r, g, b));
+                                ui.label(a.shape.kind());
+                                changed |= ui
+                                    .add(
+                                        egui::TextEdit::singleline(&mut a.label)
+                                            .hint_text("Label")
+                                            .desired_width(140.0),
+                                    )
+                                    .lost_focus();
+                                if ui.small_button("🗑").on_hover_text("Delete").clicked() {
+                                    remove = Some(i);
+                                }
+                            });
+                            ui.weak(format!("{} · {}", a.author, a.created));
+                            ui.separator();
+                        }
+                    });
+                if let Some(i) = remove {
+                    self.doc.items.remove(i);
+                    changed = true;
+                }
+                if self.doc.items.is_empty() {
+                    ui.weak("No annotations.");
+                }
+                let cti = image
+                    .is_some_and(|p| p.extension().is_some_and(|e| e.eq_ignore_ascii_case("cti")));
+                if ui
+                    .add_enabled(cti, egui::Button::new("Embed in CTI file"))
+                    .on_hover_text(format!(
+                        "Store the annotations in the file's metadata ({EMBEDDED_KEY}) so they \
+                         travel with it; the sidecar JSON stays the working copy"
+                    ))
+                    .clicked()
+                {
+                    self.embed_requested = true;
+                }
+                let can_export = image.is_some() && !self.doc.items.is_empty();
+                if ui
+                    .add_enabled(can_export, egui::Button::new("Export W3C JSON…"))
+                    .on_hover_text("W3C Web Annotation page (IIIF compatible)")
+                    .clicked()
+                    && let Some(image) = image
+                    && let Err(e) = self.export_w3c(image)
+                {
+                    tracing::error!("annotation export error: {e:?}");
+                }
+            });
+        self.show_list = open;
+        changed
+    }
+
+    fn export_w3c(&self, image: &Path) -> Result<()> {
+        let stem = image.file_stem().unwrap_or_default().to_string_lossy();
+        let Some(dst) = rfd::FileDialog::new()
+            .add_filter("JSON", &["json"])
+            .set_file_name(format!("{stem}.w3c.json"))
+            .save_file()
+        else {
+            return Ok(());
+        };
+        let json = serde_json::to_vec_pretty(&self.doc.to_w3c(&file_uri(image)))?;
+        std::fs::write(&dst, json).with_context(|| format!("write {:?}", dst))
+    }
+}
+
+fn rect_shape(r: Rect) -> Shape {
+    Shape::Rect {
+        x: r.min.x,
+        y: r.min.y,
+        w: r.width(),
+        h: r.height(),
+    }
+}
+
+fn highlight_shape(r: Rect) -> Shape {
+    Shape::Highlight {
+        x: r.min.x,
+        y: r.min.y,
+        w: r.width(),
+        h: r.height(),
+    }
+}
+
+fn arrow_shape(a: Pos2, b: Pos2) -> Shape {
+    Shape::Arrow {
+        x1: a.x,
+        y1: a.y,
+        x2: b.x,
+        y2: b.y,
+    }
+}
+
+/// Text vepsaný do obrázku (levý horní roh v kotvě); `size` = výška písma na obrazovce.
+fn paint_text(
+    painter: &egui::Painter,
+    shape: &Shape,
+    text: &str,
+    size: f32,
+    color: Color32,
+    to_screen: &impl Fn(Pos2) -> Pos2,
+) {
+    let Some(anchor) = shape.anchor() else {
+        return;
+    };
+    // prázdný text aspoň jako značka, jinak by anotace nebyla vidět
+    let text = if text.is_empty() { "T" } else { text };
+    painter.text(
+        to_screen(anchor),
+        egui::Align2::LEFT_TOP,
+        text,
+        egui::FontId::proportional(size.max(4.0)),
+        color,
+    );
+}
+
+fn paint_label(painter: &egui::Painter, at: Pos2, text: &str) {
+    let galley = painter.layout_no_wrap(
+        text.to_string(),
+        egui::FontId::proportional(13.0),
+        Color32::WHITE,
+    );
+    let rect = egui::Align2::LEFT_BOTTOM
+        .anchor_size(at + egui::vec2(4.0, -4.0), galley.size())
+        .expand(2.0);
+    painter.rect_filled(rect, 2.0, Color32::from_black_alpha(180));
+    painter.galley(rect.min + egui::vec2(2.0, 2.0), galley, Color32::WHITE);
+}
+
+fn paint_shape(
+    painter: &egui::Painter,
+    shape: &Shape,
+    color: Color32,
+    scale: f32,
+    to_screen: &impl Fn(Pos2) -> Pos2,
+) {
+    let outline = Stroke::new(2.0, color);
+    match shape {
+        Shape::Rect { x, y, w, h } => {
+            // otočení o 90° zachovává obdélník, stačí přepočítat rohy
+            let a = to_screen(Pos2::new(*x, *y));
+            let b = to_screen(Pos2::new(x + w, y + h));
+            painter.rect_stroke(
+                Rect::from_two_pos(a, b),
+                0.0,
+                outline,
+                egui::StrokeKind::Middle,
+            );
+        }
+        Shape::Polygon { points } => {
+            let pts = points
+                .iter()
+                .map(|&[x, y]| to_screen(Pos2::new(x, y)))
+                .collect();
+            painter.add(EguiShape::closed_line(pts, outline));
+        }
+        Shape::Path { width, points } => {
+            let pts: Vec<(Pos2, f32)> = points
+                .iter()
+                .map(|p| {
+                    let w = (width * p.pressure * scale).max(1.0);
+                    (to_screen(Pos2::new(p.x, p.y)), w)
+                })
+                .collect();
+            if let [(pos, w)] = pts[..] {
+                painter.circle_filled(pos, w / 2.0, color);
+                return;
+            }
+            // proměnná šířka: segment po segmentu, kulaté spoje přes kruhy
+            for pair in pts.windows(2) {
+                let ((a, wa), (b, wb)) = (pair[0], pair[1]);
+                let w = (wa + wb) / 2.0;
+                painter.line_segment([a, b], Stroke::new(w, color));
+                painter.circle_filled(b, w / 2.0, color);
+            }
+        }
+        Shape::Point { x, y } => {
+            let p = to_screen(Pos2::new(*x, *y));
+            painter.circle_filled(p, 5.0, color);
+            painter.circle_stroke(p, 5.0, Stroke::new(1.5, Color32::WHITE));
+        }
+        Shape::Highlight { x, y, w, h } => {
+            let a = to_screen(Pos2::new(*x, *y));
+            let b = to_screen(Pos2::new(x + w, y + h));
+            painter.rect_filled(Rect::from_two_pos(a, b), 0.0, color.gamma_multiply(0.35));
+        }
+        Shape::Arrow { x1, y1, x2, y2 } => {
+            let (a, b) = (
+                to_screen(Pos2::new(*x1, *y1)),
+                to_screen(Pos2::new(*x2, *y2)),
+            );
+            painter.line_segment([a, b], outline);
+            // hrot má stálou velikost na obrazovce
+            let dir = (b - a).normalized();
+            let len = (b - a).length().min(14.0);
+            let back = b - dir * len;
+            let side = dir.rot90() * len * 0.45;
+            painter.add(EguiShape::convex_polygon(
+                vec![b, back + side, back - side],
+                color,
+                Stroke::NONE,
+            ));
+        }
+        // samotný text kreslí paint_text (je to popisek anotace)
+        Shape::Text { .. } => {}
+    }
+}
@@ -0,0 +1,1518 @@
+use image::GrayImage;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+use crate::cti::CTIMetadata;
+
+/// Čtení identifikátoru z kódu na první stránce při převodu do CTI.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BarcodeImport {
+    /// Klíč v metadatech, kam se identifikátor zapíše.
+    pub key: String,
+}
+
+impl Default for BarcodeImport {
+    fn default() -> Self {
+        Self {
+            key: CTIMetadata::IDENTIFIER.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Symbology {
+    Qr,
+    Code128,
+    Code39,
+}
+
+impl Symbology {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Symbology::Qr => "QR",
+            Symbology::Code128 => "Code 128",
+            Symbology::Code39 => "Code 39",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Barcode {
+    pub symbology: Symbology,
+    pub text: String,
+}
+
+/// Najde první čitelný kód: QR (verze 1–10), Code 128 nebo Code 39, i otočený po 90°.
+pub fn detect(img: &GrayImage) -> Option<Barcode> {
+    let text = |t: String| t.trim().to_string();
+    if let Some(t) = qr::detect(img) {
+        return Some(Barcode {
+            symbology: Symbology::Qr,
+            text: text(t),
+        });
+    }
+    linear::detect(img).map(|(symbology, t)| Barcode {
+        symbology,
+        text: text(t),
+    })
+}
+
+/// Čárové kódy čtené po řádcích (a sloupcích) obrázku.
+mod linear {
+    use super::Symbology;
+    use image::GrayImage;
+
+    /// Kolik řádků a sloupců se prohledá.
+    const LINES: u32 = 96;
+    /// Nejmenší rozdíl světlé a tmavé v řádku, aby se vůbec hledalo.
+    const MIN_CONTRAST: u8 = 48;
+
+    pub fn detect(img: &GrayImage) -> Option<(Symbology, String)> {
+        let (w, h) = img.dimensions();
+        let mut line = Vec::new();
+        for k in 0..LINES {
+            let y = ((2 * k + 1) as u64 * h as u64 / (2 * LINES) as u64) as u32;
+            line.clear();
+            line.extend((0..w).map(|x| img.get_pixel(x, y)[0]));
+            if let Some(found) = decode_line(&line) {
+                return Some(found);
+            }
+        }
+        for k in 0..LINES {
+            let x = ((2 * k + 1) as u64 * w as u64 / (2 * LINES) as u64) as u32;
+            line.clear();
+            line.extend((0..h).map(|y| img.get_pixel(x, y)[0]));
+            if let Some(found) = decode_line(&line) {
+                return Some(found);
+            }
+        }
+        None
+    }
+
+    fn decode_line(line: &[u8]) -> Option<(Symbology, String)> {
+        let mut runs = runs(line)?;
+        for _ in 0..2 {
+            if let Some(t) = code128(&runs) {
+                return Some((Symbology::Code128, t));
+            }
+            if let Some(t) = code39(&runs) {
+                return Some((Symbology::Code39, t));
+            }
+            // kód vzhůru nohama
+            runs.reverse();
+            if runs.len() % 2 == 0 {
+                runs.insert(0, 0);
+            }
+        }
+        None
+    }
+
+    /// Délky úseků světlá/tmavá/světlá…; první úsek je vždy světlý (může mít délku 0).
+    fn runs(line: &[u8]) -> Option<Vec<u32>> {
+        let mut hist = [0u32; 256];
+        for &v in line {
+            hist[v as usize] += 1;
+        }
+        let percentile = |p: usize| {
+            let target = line.len() * p / 100;
+            let mut acc = 0;
+            hist.iter()
+                .position(|&n| {
+                    acc += n as usize;
+                    acc > target
+                })
+                .unwrap_or(255) as u8
+        };
+        let (lo, hi) = (percentile(5), percentile(95));
+        if hi.saturating_sub(lo) < MIN_CONTRAST {
+            return None;
+        }
+        let t = ((lo as u16 + hi as u16) / 2) as u8;
+        let mut runs = vec![0u32];
+        let mut dark = false;
+        for &v in line {
+            let d = v < t;
+            if d != dark {
+                runs.push(0);
+                dark = d;
+            }
+            *runs.last_mut().unwrap() += 1;
+        }
+        Some(runs)
+    }
+
+    /// Vzory Code 128 (šířky pruhů a mezer v modulech); 106 = stop bez závěrečného pruhu.
+    pub(super) const CODE128: [[u8; 6]; 107] = [
+        [2, 1, 2, 2, 2, 2],
+        [2, 2, 2, 1, 2, 2],
+        [2, 2, 2, 2, 2, 1],
+        [1, 2, 1, 2, 2, 3],
+        [1, 2, 1, 3, 2, 2],
+        [1, 3, 1, 2, 2, 2],
+        [1, 2, 2, 2, 1, 3],
+        [1, 2, 2, 3, 1, 2],
+        [1, 3, 2, 2, 1, 2],
+        [2, 2, 1, 2, 1, 3],
+        [2, 2, 1, 3, 1, 2],
+        [2, 3, 1, 2, 1, 2],
+        [1, 1, 2, 2, 3, 2],
+        [1, 2, 2, 1, 3, 2],
+        [1, 2, 2, 2, 3, 1],
+        [1, 1, 3, 2, 2, 2],
+        [1, 2, 3, 1, 2, 2],
+        [1, 2, 3, 2, 2, 1],
+        [2, 2, 3, 2, 1, 1],
+        [2, 2, 1, 1, 3, 2],
+        [2, 2, 1, 2, 3, 1],
+        [2, 1, 3, 2, 1, 2],
+        [2, 2, 3, 1, 1, 2],
+        [3, 1, 2, 1, 3, 1],
+        [3, 1, 1, 2, 2, 2],
+        [3, 2, 1, 1, 2, 2],
+        [3, 2, 1, 2, 2, 1],
+        [3, 1, 2, 2, 1, 2],
+        [3, 2, 2, 1, 1, 2],
+        [3, 2, 2, 2, 1, 1],
+        [2, 1, 2, 1, 2, 3],
+        [2, 1, 2, 3, 2, 1],
+        [2, 3, 2, 1, 2, 1],
+        [1, 1, 1, 3, 2, 3],
+        [1, 3, 1, 1, 2, 3],
+        [1, 3, 1, 3, 2, 1],
+        [1, 1, 2, 3, 1, 3],
+        [1, 3, 2, 1, 1, 3],
+        [1, 3, 2, 3, 1, 1],
+        [2, 1, 1, 3, 1, 3],
+        [2, 3, 1, 1, 1, 3],
+        [2, 3, 1, 3, 1, 1],
+        [1, 1, 2, 1, 3, 3],
+        [1, 1, 2, 3, 3, 1],
+        [1, 3, 2, 1, 3, 1],
+        [1, 1, 3, 1, 2, 3],
+        [1, 1, 3, 3, 2, 1],
+        [1, 3, 3, 1, 2, 1],
+        [3, 1, 3, 1, 2, 1],
+        [2, 1, 1, 3, 3, 1],
+        [2, 3, 1, 1, 3, 1],
+        [2, 1, 3, 1, 1, 3],
+        [2, 1, 3, 3, 1, 1],
+        [2, 1, 3, 1, 3, 1],
+        [3, 1, 1, 1, 2, 3],
+        [3, 1, 1, 3, 2, 1],
+        [3, 3, 1, 1, 2, 1],
+        [3, 1, 2, 1, 1, 3],
+        [3, 1, 2, 3, 1, 1],
+        [3, 3, 2, 1, 1, 1],
+        [3, 1, 4, 1, 1, 1],
+        [2, 2, 1, 4, 1, 1],
+        [4, 3, 1, 1, 1, 1],
+        [1, 1, 1, 2, 2, 4],
+        [1, 1, 1, 4, 2, 2],
+        [1, 2, 1, 1, 2, 4],
+        [1, 2, 1, 4, 2, 1],
+        [1, 4, 1, 1, 2, 2],
+        [1, 4, 1, 2, 2, 1],
+        [1, 1, 2, 2, 1, 4],
+        [1, 1, 2, 4, 1, 2],
+        [1, 2, 2, 1, 1, 4],
+        [1, 2, 2, 4, 1, 1],
+        [1, 4, 2, 1, 1, 2],
+        [1, 4, 2, 2, 1, 1],
+        [2, 4, 1, 2, 1, 1],
+        [2, 2, 1, 1, 1, 4],
+        [4, 1, 3, 1, 1, 1],
+        [2, 4, 1, 1, 1, 2],
+        [1, 3, 4, 1, 1, 1],
+        [1, 1, 1, 2, 4, 2],
+        [1, 2, 1, 1, 4, 2],
+        [1, 2, 1, 2, 4, 1],
+        [1, 1, 4, 2, 1, 2],
+        [1, 2, 4, 1, 1, 2],
+        [1, 2, 4, 2, 1, 1],
+        [4, 1, 1, 2, 1, 2],
+        [4, 2, 1, 1, 1, 2],
+        [4, 2, 1, 2, 1, 1],
+        [2, 1, 2, 1, 4, 1],
+        [2, 1, 4, 1, 2, 1],
+        [4, 1, 2, 1, 2, 1],
+        [1, 1, 1, 1, 4, 3],
+        [1, 1, 1, 3, 4, 1],
+        [1, 3, 1, 1, 4, 1],
+        [1, 1, 4, 1, 1, 3],
+        [1, 1, 4, 3, 1, 1],
+        [4, 1, 1, 1, 1, 3],
+        [4, 1, 1, 3, 1, 1],
+        [1, 1, 3, 1, 4, 1],
+        [1, 1, 4, 1, 3, 1],
+        [3, 1, 1, 1, 4, 1],
+        [4, 1, 1, 1, 3, 1],
+        [2, 1, 1, 4, 1, 2],
+        [2, 1, 1, 2, 1, 4],
+        [2, 1, 1, 2, 3, 2],
+        [2, 3, 3, 1, 1, 1],
+    ];
+    const START_A: usize = 103;
+    const START_C: usize = 105;
+    const STOP: usize = 106;
+
+    /// Nejbližší vzor Code 128 k šesti úsekům (tolerance jako u běžných čteček).
+    fn match128(runs: &[u32]) -> Option<(usize, f32)> {
+        let total: u32 = runs.iter().sum();
+        let unit = total as f32 / 11.0;
+        let mut best = None;
+        let mut best_err = f32::MAX;
+        for (value, pattern) in CODE128.iter().enumerate() {
+            let mut err = 0.0;
+            let mut worst: f32 = 0.0;
+            for (&r, &p) in runs.iter().zip(pattern) {
+                let d = (r as f32 / unit - p as f32).abs();
+                err += d;
+                worst = worst.max(d);
+            }
+            if worst < 0.7 && err < best_err {
+                best_err = err;
+                best = Some(value);
+            }
+        }
+        best.filter(|_| best_err / 11.0 < 0.25).map(|v| (v, unit))
+    }
+
+    fn code128(runs: &[u32]) -> Option<String> {
+        for i in (1..runs.len().saturating_sub(6)).step_by(2) {
+            let Some((start, unit)) = match128(&runs[i..i + 6]) else {
+                continue;
+            };
+            // před startem tichá zóna (norma chce 10 modulů, tolerujeme méně)
+            if !(START_A..=START_C).contains(&start) || (runs[i - 1] as f32) < unit * 5.0 {
+                continue;
+            }
+            if let Some(text) = read128(runs, i + 6, start) {
+                return Some(text);
+            }
+        }
+        None
+    }
+
+    fn read128(runs: &[u32], mut pos: usize, start: usize) -> Option<String> {
+        let mut values = vec![start];
+        loop {
+            let (v, unit) = match128(runs.get(pos..pos + 6)?)?;
+            if v == STOP {
+                // závěrečný pruh (2 moduly) a za ním tichá zóna nebo konec řádku
+                let bar = *runs.get(pos + 6)? as f32 / unit;
+                let quiet = runs.get(pos + 7).map_or(f32::MAX, |&q| q as f32 / unit);
+                if !(1.3..2.7).contains(&bar) || quiet < 5.0 {
+                    return None;
+                }
+                break;
+            }
+            if v >= START_A {
+                return None;
+            }
+            values.push(v);
+            pos += 6;
+        }
+        // poslední hodnota je kontrolní součet
+        let check = values.pop()?;
+        if values.len() < 2 {
+            return None;
+        }
+        let sum = values
+            .iter()
+            .enumerate()
+            .map(|(k, &v)| k.max(1) * v)
+            .sum::<usize>();
+        if sum % 103 != check {
+            return None;
+        }
+        text128(start, &values[1..])
+    }
+
+    #[derive(Clone, Copy, PartialEq)]
+    enum Set {
+        A,
+        B,
+        C,
+    }
+
+    fn text128(start: usize, values: &[usize]) -> Option<String> {
+        let mut set = match start {
+            103 => Set::A,
+            104 => Set::B,
+            _ => Set::C,
+        };
+        let mut shift = false;
+        let mut out = String::new();
+        for &v in values {
+            let cur = match (shift, set) {
+                (true, Set::A) => Set::B,
+                (true, Set::B) => Set::A,
+                (_, s) => s,
+            };
+            shift = false;
+            match (cur, v) {
+                (Set::C, 0..=99) => out.push_str(&format!("{v:02}")),
+                (Set::C, 100) => set = Set::B,
+                (Set::C, 101) => set = Set::A,
+                (Set::A, 0..=63) | (Set::B, 0..=95) => out.push((32 + v as u8) as char),
+                (Set::A, 64..=95) => out.push((v as u8 - 64) as char),
+                (Set::A | Set::B, 98) => shift = true,
+                (Set::A | Set::B, 99) => set = Set::C,
+                (Set::A, 100) => set = Set::B,
+                (Set::B, 101) => set = Set::A,
+                // FNC1–FNC4 nenesou text
+                (_, 96..=102) => {}
+                _ => return None,
+            }
+        }
+        Some(out)
+    }
+
+    pub(super) const CODE39_CHARS: &str = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ-. $/+%";
+    /// Široké prvky (pruh, mezera, …) jako 9 bitů od nejvyššího.
+    pub(super) const CODE39: [u16; 43] = [
+        0x034, 0x121, 0x061, 0x160, 0x031, 0x130, 0x070, 0x025, 0x124, 0x064, 0x109, 0x049, 0x148,
+        0x019, 0x118, 0x058, 0x00D, 0x10C, 0x04C, 0x01C, 0x103, 0x043, 0x142, 0x013, 0x112, 0x052,
+        0x007, 0x106, 0x046, 0x016, 0x181, 0x0C1, 0x1C0, 0x091, 0x190, 0x0D0, 0x085, 0x184, 0x0C4,
+        0x0A8, 0x0A2, 0x08A, 0x02A,
+    ];
+    pub(super) const CODE39_STAR: u16 = 0x094;
+
+    /// Znak z devíti úseků; `'*'` = start/stop.
+    fn match39(runs: &[u32]) -> Option<(char, f32)> {
+        let mut sorted = runs.to_vec();
+        sorted.sort_unstable();
+        // tři nejširší jsou široké a musí se zřetelně lišit od úzkých
+        let (narrow, wide) = (sorted[5] as f32, sorted[6] as f32);
+        if wide < narrow * 1.4 {
+            return None;
+        }
+        let bits = runs
+            .iter()
+            .fold(0u16, |acc, &r| (acc << 1) | (r as f32 > narrow) as u16);
+        let unit = sorted[..6].iter().sum::<u32>() as f32 / 6.0;
+        if bits == CODE39_STAR {
+            return Some(('*', unit));
+        }
+        let i = CODE39.iter().position(|&b| b == bits)?;
+        Some((CODE39_CHARS.as_bytes()[i] as char, unit))
+    }
+
+    fn code39(runs: &[u32]) -> Option<String> {
+        for i in (1..runs.len().saturating_sub(9)).step_by(2) {
+            let Some(('*', unit)) = match39(&runs[i..i + 9]) else {
+                continue;
+            };
+            if (runs[i - 1] as f32) < unit * 5.0 {
+                continue;
+            }
+            // znaky oddělené úzkou mezerou
+            let mut pos = i + 10;
+            let mut text = String::new();
+            while let Some((c, _)) = runs.get(pos..pos + 9).and_then(match39) {
+                if c == '*' {
+                    let quiet = runs.get(pos + 9).map_or(f32::MAX, |&q| q as f32 / unit);
+                    if !text.is_empty() && quiet >= 5.0 {
+                        return Some(text);
+                    }
+                    break;
+                }
+                text.push(c);
+                pos += 10;
+            }
+        }
+        None
+    }
+}
+
+/// QR kód: vyhledávací značky, vzorkování mřížky (afinně – předloha leží na skeneru rovně),
+/// formátová informace, oprava chyb Reed–Solomon a dekódování dat.
+mod qr {
+    use super::rs;
+    use image::GrayImage;
+
+    /// Delší strana obrázku, na kterou se před hledáním zmenší.
+    const MAX_SIDE: u32 = 3000;
+    const MAX_VERSION: usize = 10;
+
+    /// Binární obrázek (true = tmavá).
+    struct Bits {
+        w: usize,
+        h: usize,
+        data: Vec<bool>,
+    }
+
+    impl Bits {
+        fn inside(&self, x: i64, y: i64) -> bool {
+            x >= 0 && y >= 0 && (x as usize) < self.w && (y as usize) < self.h
+        }
+
+        /// Modul mimo obrázek je světlý.
+        fn get(&self, x: i64, y: i64) -> bool {
+            self.inside(x, y) && self.data[y as usize * self.w + x as usize]
+        }
+    }
+
+    pub fn detect(img: &GrayImage) -> Option<String> {
+        let bits = binarize(img);
+        let finders = find_finders(&bits);
+        for (tl, tr, bl) in candidate_triples(&finders) {
+            if let Some(text) = decode_at(&bits, tl, tr, bl) {
+                return Some(text);
+            }
+        }
+        None
+    }
+
+    /// Zmenšení průměrováním a práh podle Otsua.
+    fn binarize(img: &GrayImage) -> Bits {
+        let (w, h) = img.dimensions();
+        let step = w.max(h).div_ceil(MAX_SIDE).max(1);
+        let (bw, bh) = ((w / step).max(1) as usize, (h / step).max(1) as usize);
+        let mut gray = Vec::with_capacity(bw * bh);
+        for by in 0..bh as u32 {
+            for bx in 0..bw as u32 {
+                let mut sum = 0u32;
+                for y in by * step..((by + 1) * step).min(h) {
+                    for x in bx * step..((bx + 1) * step).min(w) {
+                        sum += img.get_pixel(x, y)[0] as u32;
+                    }
+                }
+                gray.push((sum / (step * step)) as u8);
+            }
+        }
+        let mut hist = [0u64; 256];
+        for &v in &gray {
+            hist[v as usize] += 1;
+        }
+        let total = gray.len() as f64;
+        let sum_all: f64 = hist
+            .iter()
+            .enumerate()
+            .map(|(i, &n)| i as f64 * n as f64)
+            .sum();
+        let (mut w0, mut sum0, mut best, mut t) = (0.0, 0.0, 0.0, 128u8);
+        for (i, &n) in hist.iter().enumerate() {
+            w0 += n as f64;
+            sum0 += i as f64 * n as f64;
+            let w1 = total - w0;
+            if w0 == 0.0 || w1 == 0.0 {
+                continue;
+            }
+            let (m0, m1) = (sum0 / w0, (sum_all - sum0) / w1);
+            let between = w0 * w1 * (m0 - m1) * (m0 - m1);
+            if between > best {
+                best = between;
+                t = i as u8;
+            }
+        }
+        Bits {
+            w: bw,
+            h: bh,
+            data: gray.iter().map(|&v| v <= t).collect(),
+        }
+    }
+
+    #[derive(Debug, Clone, Copy)]
+    struct Finder {
+        x: f32,
+        y: f32,
+        module: f32,
+        count: u32,
+    }
+
+    /// Poměr 1:1:3:1:1 s tolerancí poloviny modulu.
+    fn is_finder(c: &[u32; 5]) -> bool {
+        let total: u32 = c.iter().sum();
+        if total < 7 || c.contains(&0) {
+            return false;
+        }
+        let m = total as f32 / 7.0;
+        let v = m / 2.0;
+        (c[0] as f32 - m).abs() < v
+            && (c[1] as f32 - m).abs() < v
+            && (c[2] as f32 - 3.0 * m).abs() < 3.0 * v
+            && (c[3] as f32 - m).abs() < v
+            && (c[4] as f32 - m).abs() < v
+    }
+
+    /// Úseky tmavá/světlá/tmavá kolem `(x, y)` ve směru `(dx, dy)`; vrátí posun středu
+    /// značky od `(x, y)` a celkovou délku.
+    fn cross_check(
+        b: &Bits,
+        (x, y): (i64, i64),
+        (dx, dy): (i64, i64),
+        max: u32,
+    ) -> Option<(f32, u32)> {
+        if !b.get(x, y) {
+            return None;
+        }
+        let dark_at = |k: i64| {
+            let (px, py) = (x + k * dx, y + k * dy);
+            b.inside(px, py).then(|| b.get(px, py))
+        };
+        let mut c = [0u32; 5];
+        // zpět: střed, světlý a tmavý okraj; potom dopředu totéž
+        let mut k = 0;
+        for (state, dark) in [(2, true), (1, false), (0, true)] {
+            while dark_at(-k) == Some(dark) {
+                c[state] += 1;
+                k += 1;
+                if state != 2 && c[state] > max {
+                    return None;
+                }
+            }
+        }
+        let mut k = 1;
+        for (state, dark) in [(2, true), (3, false), (4, true)] {
+            while dark_at(k) == Some(dark) {
+                c[state] += 1;
+                k += 1;
+                if state != 2 && c[state] > max {
+                    return None;
+                }
+            }
+        }
+        if !is_finder(&c) {
+            return None;
+        }
+        // k ukazuje za konec; střed = konec prostředního úseku minus jeho polovina
+        let end = k as f32 - c[4] as f32 - c[3] as f32;
+        Some((end - c[2] as f32 / 2.0, c.iter().sum()))
+    }
+
+    fn find_finders(b: &Bits) -> Vec<Finder> {
+        let mut found: Vec<Finder> = Vec::new();
+        let mut add = |f: Finder| match found.iter_mut().find(|g| {
+            (g.x - f.x).abs() <= g.module && (g.y - f.y).abs() <= g.module && {
+                let r = g.module / f.module;
+                (0.7..1.4).contains(&r)
+            }
+        }) {
+            Some(g) => {
+                let n = g.count as f32;
+                g.x = (g.x * n + f.x) / (n + 1.0);
+                g.y = (g.y * n + f.y) / (n + 1.0);
+                g.module = (g.module * n + f.module) / (n + 1.0);
+                g.count += 1;
+            }
+            None => found.push(f),
+        };
+        for y in (0..b.h).step_by(2) {
+            let mut c = [0u32; 5];
+            let mut state = 0;
+            for x in 0..=b.w {
+                let dark = x < b.w && b.data[y * b.w + x];
+                if dark {
+                    if state % 2 == 1 {
+                        state += 1;
+                    }
+                    c[state] += 1;
+                } else if state % 2 == 0 {
+                    if state == 4 {
+                        if is_finder(&c)
+                            && let Some(f) = confirm(b, &c, x, y)
+                        {
+                            add(f);
+                        }
+                        c = [c[2], c[3], c[4], 1, 0];
+                        state = 3;
+                    } else {
+                        state += 1;
+                        c[state] += 1;
+                    }
+                } else {
+                    c[state] += 1;
+                }
+            }
+        }
+        found.sort_by_key(|f| std::cmp::Reverse(f.count));
+        found
+    }
+
+    /// Ověří kandidáta svisle a znovu vodorovně a zpřesní jeho střed.
+    fn confirm(b: &Bits, c: &[u32; 5], end_x: usize, y: usize) -> Option<Finder> {
+        let total: u32 = c.iter().sum();
+        let cx = end_x as f32 - c[4] as f32 - c[3] as f32 - c[2] as f32 / 2.0;
+        let (dy, v_total) = cross_check(b, (cx as i64, y as i64), (0, 1), c[2])?;
+        let cy = y as f32 + dy;
+        let (dx, h_total) = cross_check(b, (cx as i64, cy as i64), (1, 0), c[2])?;
+        // svislý průřez musí mít přibližně stejnou velikost
+        if 5 * v_total.abs_diff(total) >= 2 * total {
+            return None;
+        }
+        Some(Finder {
+            x: cx.trunc() + dx,
+            y: cy,
+            module: (total + v_total + h_total) as f32 / 21.0,
+            count: 1,
+        })
+    }
+
+    /// Trojice značek seřazené od nejpravděpodobnější jako (levá horní, pravá horní, levá dolní).
+    fn candidate_triples(f: &[Finder]) -> Vec<(Finder, Finder, Finder)> {
+        let f = &f[..f.len().min(12)];
+        let dist = |a: &Finder, b: &Finder| ((a.x - b.x).powi(2) + (a.y - b.y).powi(2)).sqrt();
+        let mut out = Vec::new();
+        for i in 0..f.len() {
+            for j in i + 1..f.len() {
+                for k in j + 1..f.len() {
+                    let (a, b, c) = (f[i], f[j], f[k]);
+                    let m = [a.module, b.module, c.module];
+                    let (mmin, mmax) = (
+                        m.iter().cloned().fold(f32::MAX, f32::min),
+                        m.iter().cloned().fold(0.0, f32::max),
+                    );
+                    if mmax > mmin * 1.5 {
+                        continue;
+                    }
+                    // roh pravého úhlu leží proti nejdelší straně
+                    let (ab, bc, ac) = (dist(&a, &b), dist(&b, &c), dist(&a, &c));
+                    let (corner, p, q, hyp, l1, l2) = if bc >= ab && bc >= ac {
+                        (a, b, c, bc, ab, ac)
+                    } else if ac >= ab {
+                        (b, a, c, ac, ab, bc)
+                    } else {
+                        (c, a, b, ab, ac, bc)
+                    };
+                    let leg = (l1 + l2) / 2.0;
+                    let err = (l1 - l2).abs() / leg + (hyp / leg - std::f32::consts::SQRT_2).abs();
+                    if err > 0.25 || leg < 7.0 * mmax {
+                        continue;
+                    }
+                    let cross =
+                        (p.x - corner.x) * (q.y - corner.y) - (p.y - corner.y) * (q.x - corner.x);
+                    let (tr, bl) = if cross > 0.0 { (p, q) } else { (q, p) };
+                    out.push((err, (corner, tr, bl)));
+                }
+            }
+        }
+        out.sort_by(|a, b| a.0.total_cmp(&b.0));
+        out.into_iter().map(|(_, t)| t).collect()
+    }
+
+    fn decode_at(b: &Bits, tl: Finder, tr: Finder, bl: Finder) -> Option<String> {
+        let module = (tl.module + tr.module + bl.module) / 3.0;
+        let dist = |a: &Finder, b: &Finder| ((a.x - b.x).powi(2) + (a.y - b.y).powi(2)).sqrt();
+        let modules = (dist(&tl, &tr) / module).round() + (dist(&tl, &bl) / module).round();
+        let estimate = (modules / 2.0).round() as usize + 7;
+        // nejbližší platné rozměry 4·v + 17
+        let base = (estimate.saturating_sub(17) as f32 / 4.0).round() as usize;
+        for version in [base, base + 1, base.wrapping_sub(1)] {
+            if !(1..=MAX_VERSION).contains(&version) {
+                continue;
+            }
+            let dim = 4 * version + 17;
+            let grid = sample(b, &tl, &tr, &bl, dim);
+            if let Some(text) = decode_grid(&grid, version) {
+                return Some(text);
+            }
+        }
+        None
+    }
+
+    /// Hodnoty modulů (řádek × sloupec) afinním zobrazením ze středů tří značek.
+    fn sample(b: &Bits, tl: &Finder, tr: &Finder, bl: &Finder, dim: usize) -> Vec<bool> {
+        let span = (dim - 7) as f32;
+        let mut grid = Vec::with_capacity(dim * dim);
+        for r in 0..dim {
+            for c in 0..dim {
+                let u = (c as f32 + 0.5 - 3.5) / span;
+                let v = (r as f32 + 0.5 - 3.5) / span;
+                let x = tl.x + u * (tr.x - tl.x) + v * (bl.x - tl.x);
+                let y = tl.y + u * (tr.y - tl.y) + v * (bl.y - tl.y);
+                grid.push(b.get(x.floor() as i64, y.floor() as i64));
+            }
+        }
+        grid
+    }
+
+    const ALIGNMENT: [&[usize]; MAX_VERSION + 1] = [
+        &[],
+        &[],
+        &[6, 18],
+        &[6, 22],
+        &[6, 26],
+        &[6, 30],
+        &[6, 34],
+        &[6, 22, 38],
+        &[6, 24, 42],
+        &[6, 26, 46],
+        &[6, 28, 50],
+    ];
+
+    /// Bloky opravy chyb: (EC kódových slov na blok, [(počet bloků, datových slov)]),
+    /// pro úrovně v pořadí L, M, Q, H.
+    type Blocks = (usize, &'static [(usize, usize)]);
+    const EC_BLOCKS: [[Blocks; 4]; MAX_VERSION + 1] = [
+        [(0, &[]), (0, &[]), (0, &[]), (0, &[])],
+        [
+            (7, &[(1, 19)]),
+            (10, &[(1, 16)]),
+            (13, &[(1, 13)]),
+            (17, &[(1, 9)]),
+        ],
+        [
+            (10, &[(1, 34)]),
+            (16, &[(1, 28)]),
+            (22, &[(1, 22)]),
+            (28, &[(1, 16)]),
+        ],
+        [
+            (15, &[(1, 55)]),
+            (26, &[(1, 44)]),
+            (18, &[(2, 17)]),
+            (22, &[(2, 13)]),
+        ],
+        [
+            (20, &[(1, 80)]),
+            (18, &[(2, 32)]),
+            (26, &[(2, 24)]),
+            (16, &[(4, 9)]),
+        ],
+        [
+            (26, &[(1, 108)]),
+            (24, &[(2, 43)]),
+            (18, &[(2, 15), (2, 16)]),
+            (22, &[(2, 11), (2, 12)]),
+        ],
+        [
+            (18, &[(2, 68)]),
+            (16, &[(4, 27)]),
+            (24, &[(4, 19)]),
+            (28, &[(4, 15)]),
+        ],
+        [
+            (20, &[(2, 78)]),
+            (18, &[(4, 31)]),
+            (18, &[(2, 14), (4, 15)]),
+            (26, &[(4, 13), (1, 14)]),
+        ],
+        [
+            (24, &[(2, 97)]),
+            (22, &[(2, 38), (2, 39)]),
+            (22, &[(4, 18), (2, 19)]),
+            (26, &[(4, 14), (2, 15)]),
+        ],
+        [
+            (30, &[(2, 116)]),
+            (22, &[(3, 36), (2, 37)]),
+            (20, &[(4, 16), (4, 17)]),
+            (24, &[(4, 12), (4, 13)]),
+        ],
+        [
+            (18, &[(2, 68), (2, 69)]),
+            (26, &[(4, 43), (1, 44)]),
+            (24, &[(6, 19), (2, 20)]),
+            (28, &[(6, 15), (2, 16)]),
+        ],
+    ];
+
+    fn decode_grid(grid: &[bool], version: usize) -> Option<String> {
+        let dim = 4 * version + 17;
+        let at = |x: usize, y: usize| grid[y * dim + x];
+        let (level, mask) = format_info(&at, dim)?;
+        let function = function_pattern(version);
+        let codewords = read_codewords(grid, &function, dim, mask);
+        let (ec, groups) = EC_BLOCKS[version][level];
+        let data = deinterleave(&codewords, ec, groups)?;
+        decode_data(&data, version)
+    }
+
+    /// (úroveň 0..4 v pořadí L, M, Q, H; maska) z obou kopií formátové informace.
+    fn format_info(at: &dyn Fn(usize, usize) -> bool, dim: usize) -> Option<(usize, u8)> {
+        let mut a = 0u32;
+        let push = |v: &mut u32, x: usize, y: usize| *v = (*v << 1) | at(x, y) as u32;
+        for x in 0..6 {
+            push(&mut a, x, 8);
+        }
+        push(&mut a, 7, 8);
+        push(&mut a, 8, 8);
+        push(&mut a, 8, 7);
+        for y in (0..6).rev() {
+            push(&mut a, 8, y);
+        }
+        let mut b = 0u32;
+        for y in (dim - 7..dim).rev() {
+            push(&mut b, 8, y);
+        }
+        for x in dim - 8..dim {
+            push(&mut b, x, 8);
+        }
+        let mut best = (u32::MAX, 0u32);
+        for data in 0..32u32 {
+            let code = bch_format(data) ^ 0x5412;
+            for read in [a, b] {
+                let d = (code ^ read).count_ones();
+                if d < best.0 {
+                    best = (d, data);
+                }
+            }
+        }
+        if best.0 > 3 {
+            return None;
+        }
+        // bity úrovně: 01 = L, 00 = M, 11 = Q, 10 = H
+        let level = [1, 0, 3, 2][(best.1 >> 3) as usize];
+        Some((level, (best.1 & 7) as u8))
+    }
+
+    /// Kód BCH(15, 5) formátové informace (bez masky).
+    fn bch_format(data: u32) -> u32 {
+        let mut v = data << 10;
+        for bit in (10..15).rev() {
+            if v & (1 << bit) != 0 {
+                v ^= 0x537 << (bit - 10);
+            }
+        }
+        (data << 10) | v
+    }
+
+    /// Moduly, které nenesou data (značky, formát, časování, zarovnání, verze).
+    fn function_pattern(version: usize) -> Vec<bool> {
+        let dim = 4 * version + 17;
+        let mut f = vec![false; dim * dim];
+        let mut region = |left: usize, top: usize, w: usize, h: usize| {
+            for y in top..top + h {
+                for x in left..left + w {
+                    f[y * dim + x] = true;
+                }
+            }
+        };
+        region(0, 0, 9, 9);
+        region(dim - 8, 0, 8, 9);
+        region(0, dim - 8, 9, 8);
+        let centers = ALIGNMENT[version];
+        let n = centers.len();
+        for (i, &cy) in centers.iter().enumerate() {
+            for (j, &cx) in centers.iter().enumerate() {
+                // bez míst překrytých vyhledávacími značkami
+                let finder = [(0, 0), (0, n - 1), (n - 1, 0)].contains(&(i, j));
+                if !finder {
+                    region(cx - 2, cy - 2, 5, 5);
+                }
+            }
+        }
+        region(6, 9, 1, dim - 17);
+        region(9, 6, dim - 17, 1);
+        if version > 6 {
+            region(dim - 11, 0, 3, 6);
+            region(0, dim - 11, 6, 3);
+        }
+        f
+    }
+
+    fn masked(mask: u8, r: usize, c: usize) -> bool {
+        match mask {
+            0 => (r + c).is_multiple_of(2),
+            1 => r.is_multiple_of(2),
+            2 => c.is_multiple_of(3),
+            3 => (r + c).is_multiple_of(3),
+            4 => (r / 2 + c / 3).is_multiple_of(2),
+            5 => (r * c) % 2 + (r * c) % 3 == 0,
+            6 => ((r * c) % 2 + (r * c) % 3).is_multiple_of(2),
+            _ => ((r + c) % 2 + (r * c) % 3).is_multiple_of(2),
+        }
+    }
+
+    /// Kódová slova v pořadí umístění (dvojsloupce zprava, střídavě nahoru a dolů).
+    fn read_codewords(grid: &[bool], function: &[bool], dim: usize, mask: u8) -> Vec<u8> {
+        let mut out = Vec::new();
+        let (mut byte, mut n) = (0u8, 0);
+        let mut up = true;
+        let mut x = dim - 1;
+        while x > 0 {
+            if x == 6 {
+                x -= 1;
+            }
+            for k in 0..dim {
+                let y = if up { dim - 1 - k } else { k };
+                for col in [x, x - 1] {
+                    if function[y * dim + col] {
+                        continue;
+                    }
+                    let bit = grid[y * dim + col] ^ masked(mask, y, col);
+                    byte = (byte << 1) | bit as u8;
+                    n += 1;
+                    if n == 8 {
+                        out.push(byte);
+                        (byte, n) = (0, 0);
+                    }
+                }
+            }
+            up = !up;
+            x = x.saturating_sub(2);
+        }
+        out
+    }
+
+    /// Rozdělí prokládaná kódová slova do bloků, opraví je a vrátí data za sebou.
+    fn deinterleave(codewords: &[u8], ec: usize, groups: &[(usize, usize)]) -> Option<Vec<u8>> {
+        let sizes: Vec<usize> = groups
+            .iter()
+            .flat_map(|&(count, data)| std::iter::repeat_n(data, count))
+            .collect();
+        let max_data = *sizes.iter().max()?;
+        let mut blocks: Vec<Vec<u8>> = sizes.iter().map(|&d| Vec::with_capacity(d + ec)).collect();
+        let mut it = codewords.iter().copied();
+        for i in 0..max_data {
+            for (b, &d) in blocks.iter_mut().zip(&sizes) {
+                if i < d {
+                    b.push(it.next()?);
+                }
+            }
+        }
+        for _ in 0..ec {
+            for b in &mut blocks {
+                b.push(it.next()?);
+            }
+        }
+        let mut data = Vec::new();
+        for (mut b, &d) in blocks.into_iter().zip(&sizes) {
+            if !rs::correct(&mut b, ec) {
+                return None;
+            }
+            data.extend_from_slice(&b[..d]);
+        }
+        Some(data)
+    }
+
+    struct Reader<'a> {
+        data: &'a [u8],
+        pos: usize,
+    }
+
+    impl Reader<'_> {
+        fn left(&self) -> usize {
+            self.data.len() * 8 - self.pos
+        }
+
+        fn read(&mut self, n: usize) -> Option<u32> {
+            if n > self.left() {
+                return None;
+            }
+            let mut v = 0u32;
+            for _ in 0..n {
+                let bit = (self.data[self.pos / 8] >> (7 - self.pos % 8)) & 1;
+                v = (v << 1) | bit as u32;
+                self.pos += 1;
+            }
+            Some(v)
+        }
+    }
+
+    const ALNUM: &[u8; 45] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+    fn decode_data(data: &[u8], version: usize) -> Option<String> {
+        let mut r = Reader { data, pos: 0 };
+        let mut bytes = Vec::new();
+        let small = version <= 9;
+        while r.left() >= 4 {
+            match r.read(4)? {
+                0 => break,
+                // číslice po třech
+                1 => {
+                    let mut n = r.read(if small { 10 } else { 12 })?;
+                    while n > 0 {
+                        let (bits, digits) = match n {
+                            1 => (4, 1),
+                            2 => (7, 2),
+                            _ => (10, 3),
+                        };
+                        let v = r.read(bits)?;
+                        if v >= 10u32.pow(digits) {
+                            return None;
+                        }
+                        bytes.extend(format!("{v:0width$}", width = digits as usize).bytes());
+                        n -= digits;
+                    }
+                }
+                2 => {
+                    let mut n = r.read(if small { 9 } else { 11 })?;
+                    while n >= 2 {
+                        let v = r.read(11)? as usize;
+                        if v >= 45 * 45 {
+                            return None;
+                        }
+                        bytes.extend([ALNUM[v / 45], ALNUM[v % 45]]);
+                        n -= 2;
+                    }
+                    if n == 1 {
+                        bytes.push(*ALNUM.get(r.read(6)? as usize)?);
+                    }
+                }
+                4 => {
+                    let n = r.read(if small { 8 } else { 16 })?;
+                    for _ in 0..n {
+                        bytes.push(r.read(8)? as u8);
+                    }
+                }
+                // ECI (jednobajtové označení), strukturované připojení, FNC1
+                7 => {
+                    r.read(8)?;
+                }
+                3 => {
+                    r.read(16)?;
+                }
+                5 => {}
+                9 => {
+                    r.read(8)?;
+                }
+                _ => return None,
+            }
+        }
+        // UTF-8, jinak výchozí ISO 8859-1
+        Some(match String::from_utf8(bytes) {
+            Ok(s) => s,
+            Err(e) => e.into_bytes().iter().map(|&b| b as char).collect(),
+        })
+    }
+}
+
+/// Reed–Solomon nad GF(256) s polynomem 0x11D (kořeny α⁰…αⁿ⁻¹ jako v QR).
+mod rs {
+    use super::OnceLock;
+
+    struct Gf {
+        exp: [u8; 512],
+        log: [u8; 256],
+    }
+
+    fn gf() -> &'static Gf {
+        static GF: OnceLock<Gf> = OnceLock::new();
+        GF.get_or_init(|| {
+            let mut g = Gf {
+                exp: [0; 512],
+                log: [0; 256],
+            };
+            let mut x = 1u16;
+            for i in 0..255 {
+                g.exp[i] = x as u8;
+                g.log[x as usize] = i as u8;
+                x <<= 1;
+                if x & 0x100 != 0 {
+                    x ^= 0x11D;
+                }
+            }
+            for i in 255..512 {
+                g.exp[i] = g.exp[i - 255];
+            }
+            g
+        })
+    }
+
+    fn mul(a: u8, b: u8) -> u8 {
+        if a == 0 || b == 0 {
+            return 0;
+        }
+        let g = gf();
+        g.exp[g.log[a as usize] as usize + g.log[b as usize] as usize]
+    }
+
+    fn div(a: u8, b: u8) -> u8 {
+        if a == 0 {
+            return 0;
+        }
+        let g = gf();
+        g.exp[g.log[a as usize] as usize + 255 - g.log[b as usize] as usize]
+    }
+
+    fn pow_alpha(i: usize) -> u8 {
+        gf().exp[i % 255]
+    }
+
+    /// Hodnota polynomu s koeficienty od nejnižšího stupně.
+    fn eval_low(p: &[u8], x: u8) -> u8 {
+        p.iter().rev().fold(0, |acc, &c| mul(acc, x) ^ c)
+    }
+
+    /// Opraví blok na místě (nejvýš `ec / 2` chybných slov); `false` = neopravitelný.
+    pub fn correct(block: &mut [u8], ec: usize) -> bool {
+        let n = block.len();
+        let syndromes = |b: &[u8]| -> Vec<u8> {
+            (0..ec)
+                .map(|i| b.iter().fold(0, |acc, &c| mul(acc, pow_alpha(i)) ^ c))
+                .collect()
+        };
+        let s = syndromes(block);
+        if s.iter().all(|&v| v == 0) {
+            return true;
+        }
+        // Berlekamp–Massey: lokátor chyb Λ (od nejnižšího stupně)
+        let mut lambda = vec![1u8];
+        let mut prev = vec![1u8];
+        let (mut l, mut m, mut last) = (0usize, 1usize, 1u8);
+        for k in 0..ec {
+            let mut d = s[k];
+            for i in 1..=l.min(lambda.len() - 1) {
+                d ^= mul(lambda[i], s[k - i]);
+            }
+            if d == 0 {
+                m += 1;
+                continue;
+            }
+            let coef = div(d, last);
+            let old = lambda.clone();
+            if lambda.len() < prev.len() + m {
+                lambda.resize(prev.len() + m, 0);
+            }
+            for (i, &p) in prev.iter().enumerate() {
+                lambda[i + m] ^= mul(coef, p);
+            }
+            if 2 * l <= k {
+                l = k + 1 - l;
+                prev = old;
+                last = d;
+                m = 1;
+            } else {
+                m += 1;
+            }
+        }
+        if 2 * l > ec {
+            return false;
+        }
+        // Ω = S·Λ mod x^ec
+        let mut omega = vec![0u8; ec];
+        for (i, &si) in s.iter().enumerate() {
+            for (j, &lj) in lambda.iter().enumerate() {
+                if i + j < ec {
+                    omega[i + j] ^= mul(si, lj);
+                }
+            }
+        }
+        // formální derivace Λ (v GF(2) zůstanou liché členy)
+        let deriv: Vec<u8> = lambda
+            .iter()
+            .enumerate()
+            .skip(1)
+            .map(|(i, &c)| if i % 2 == 1 { c } else { 0 })
+            .collect();
+        let mut found = 0;
+        for (pos, byte) in block.iter_mut().enumerate() {
+            let degree = n - 1 - pos;
+            let x = pow_alpha(degree);
+            let x_inv = div(1, x);
+            if eval_low(&lambda, x_inv) != 0 {
+                continue;
+            }
+            let denom = eval_low(&deriv, x_inv);
+            if denom == 0 {
+                return false;
+            }
+            // Forney pro první kořen α⁰: e = X · Ω(X⁻¹) / Λ'(X⁻¹)
+            *byte ^= mul(x, div(eval_low(&omega, x_inv), denom));
+            found += 1;
+        }
+        found == l && syndromes(block).iter().all(|&v| v == 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::linear::{CODE39, CODE39_CHARS, CODE39_STAR, CODE128};
+    use super::{Symbology, detect, rs};
+    use image::{GrayImage, Luma, imageops};
+    use qrcodegen::{Mask, QrCode, QrCodeEcc, QrSegment, Version};
+
+    /// Pixely na modul vykreslených vzorů.
+    const SCALE: u32 = 3;
+
+    fn detect_as(img: &GrayImage, symbology: Symbology) -> Option<String> {
+        detect(img)
+            .filter(|b| b.symbology == symbology)
+            .map(|b| b.text)
+    }
+
+    /// QR kód s tichou zónou 4 moduly; moduly z `flip` mají opačnou barvu.
+    fn render_qr(qr: &QrCode, flip: &[(i32, i32)]) -> GrayImage {
+        const QUIET: i32 = 4;
+        let side = (qr.size() + 2 * QUIET) as u32 * SCALE;
+        GrayImage::from_fn(side, side, |x, y| {
+            let (mx, my) = ((x / SCALE) as i32 - QUIET, (y / SCALE) as i32 - QUIET);
+            let inside = (0..qr.size()).contains(&mx) && (0..qr.size()).contains(&my);
+            let dark = inside && qr.get_module(mx, my) != flip.contains(&(mx, my));
+            Luma([if dark { 0 } else { 255 }])
+        })
+    }
+
+    fn encode_qr(text: &str, ecl: QrCodeEcc, mask: Option<Mask>) -> Option<QrCode> {
+        let segs = QrSegment::make_segments(text);
+        let (min, max) = (Version::new(1), Version::new(10));
+        QrCode::encode_segments_advanced(&segs, ecl, min, max, mask, false).ok()
+    }
+
+    /// Pruhy a mezery (šířky v modulech, začíná pruh) s tichou zónou 12 modulů.
+    fn render_bars(widths: &[u32]) -> GrayImage {
+        const QUIET: u32 = 12;
+        let mut line = vec![false; QUIET as usize];
+        for (i, &w) in widths.iter().enumerate() {
+            line.extend(std::iter::repeat_n(i % 2 == 0, w as usize));
+        }
+        line.extend(std::iter::repeat_n(false, QUIET as usize));
+        GrayImage::from_fn(line.len() as u32 * SCALE, 40, |x, _| {
+            Luma([if line[(x / SCALE) as usize] { 0 } else { 255 }])
+        })
+    }
+
+    /// Code 128 se startem `start` (103–105), kontrolním součtem a stopem.
+    fn code128(start: usize, values: &[usize]) -> Vec<u32> {
+        let check = values
+            .iter()
+            .enumerate()
+            .map(|(k, &v)| (k + 1) * v)
+            .sum::<usize>()
+            + start;
+        let mut widths = Vec::new();
+        for v in [start].iter().chain(values).chain(&[check % 103, 106]) {
+            widths.extend(CODE128[*v].iter().map(|&m| u32::from(m)));
+        }
+        // závěrečný pruh stopu
+        widths.push(2);
+        widths
+    }
+
+    /// Code 39 se start/stop znakem `*`; široký prvek = 3 moduly, mezi znaky úzká mezera.
+    fn code39(text: &str) -> Vec<u32> {
+        let symbol = |bits: u16| {
+            (0..9)
+                .rev()
+                .map(move |k| if bits >> k & 1 == 1 { 3 } else { 1 })
+        };
+        let mut widths: Vec<u32> = symbol(CODE39_STAR).collect();
+        for c in text.chars() {
+            let i = CODE39_CHARS.find(c).expect("Code 39 character");
+            widths.push(1);
+            widths.extend(symbol(CODE39[i]));
+        }
+        widths.push(1);
+        widths.extend(symbol(CODE39_STAR));
+        widths
+    }
+
+    #[test]
+    fn qr_decodes_every_mode_and_error_correction_level() {
+        let long = "CTI View archivní skeny 0123456789 ".repeat(5);
+        let texts = [
+            "0123456789012345",
+            "CTI-VIEW 2024/ABC",
+            "archiv://fond/12/karton-7?id=481516",
+            "Kniha č. 42 – Žďár nad Sázavou",
+            long.trim(),
+        ];
+        let levels = [
+            QrCodeEcc::Low,
+            QrCodeEcc::Medium,
+            QrCodeEcc::Quartile,
+            QrCodeEcc::High,
+        ];
+        let mut versions = Vec::new();
+        for text in texts {
+            for ecl in levels {
+                let Some(qr) = encode_qr(text, ecl, None) else {
+                    continue;
+                };
+                versions.push(qr.version().value());
+                let img = render_qr(&qr, &[]);
+                assert_eq!(
+                    detect_as(&img, Symbology::Qr).as_deref(),
+                    Some(text),
+                    "version {} {ecl:?}",
+                    qr.version().value()
+                );
+            }
+        }
+        // s formátovou i verzní informací (od verze 7)
+        assert!(versions.contains(&1) && versions.iter().any(|&v| v >= 7));
+    }
+
+    #[test]
+    fn qr_decodes_every_mask_and_rotation() {
+        for mask in 0..8 {
+            let qr = encode_qr("CTI 0001", QrCodeEcc::Medium, Some(Mask::new(mask))).unwrap();
+            let img = render_qr(&qr, &[]);
+            for img in [
+                img.clone(),
+                imageops::rotate90(&img),
+                imageops::rotate180(&img),
+            ] {
+                let text = detect_as(&img, Symbology::Qr);
+                assert_eq!(text.as_deref(), Some("CTI 0001"), "mask {mask}");
+            }
+        }
+    }
+
+    /// Verze 1-M: první datové slovo leží v pravém dolním rohu (sloupce 19–20, řádky
+    /// 17–20), šesté vedle něj (17–18); přebarvený blok 4 × 4 moduly poškodí obě.
+    #[test]
+    fn qr_corrects_damaged_codewords() {
+        let text = "CTI-000123";
+        let qr = encode_qr(text, QrCodeEcc::Medium, Some(Mask::new(0))).unwrap();
+        assert_eq!(qr.version().value(), 1);
+        let corner: Vec<(i32, i32)> = (17..21)
+            .flat_map(|x| (17..21).map(move |y| (x, y)))
+            .collect();
+        let img = render_qr(&qr, &corner);
+        assert_eq!(detect_as(&img, Symbology::Qr).as_deref(), Some(text));
+    }
+
+    #[test]
+    fn code128_decodes_sets_b_and_c_in_any_orientation() {
+        let text = "CTI-000042";
+        let values: Vec<usize> = text.bytes().map(|b| (b - 32) as usize).collect();
+        let img = render_bars(&code128(104, &values));
+        for img in [
+            img.clone(),
+            imageops::rotate180(&img),
+            imageops::rotate90(&img),
+        ] {
+            assert_eq!(detect_as(&img, Symbology::Code128).as_deref(), Some(text));
+        }
+        let img = render_bars(&code128(105, &[12, 34, 56, 78]));
+        assert_eq!(
+            detect_as(&img, Symbology::Code128).as_deref(),
+            Some("12345678")
+        );
+        // sada C, přepnutí na B a písmena
+        let img = render_bars(&code128(105, &[20, 24, 100, 33, 34]));
+        assert_eq!(
+            detect_as(&img, Symbology::Code128).as_deref(),
+            Some("2024AB")
+        );
+    }
+
+    #[test]
+    fn code128_rejects_a_wrong_check_digit() {
+        let mut widths = code128(104, &[35, 52, 41]);
+        // kontrolní znak (čtvrtý symbol) nahradí jiná hodnota
+        let other: Vec<u32> = CODE128[0].iter().map(|&m| u32::from(m)).collect();
+        widths.splice(24..30, other);
+        assert_eq!(detect(&render_bars(&widths)), None);
+    }
+
+    #[test]
+    fn code39_decodes_in_any_orientation() {
+        let text = "CTI-2024 A/1";
+        let img = render_bars(&code39(text));
+        for img in [
+            img.clone(),
+            imageops::rotate180(&img),
+            imageops::rotate270(&img),
+        ] {
+            assert_eq!(detect_as(&img, Symbology::Code39).as_deref(), Some(text));
+        }
+    }
+
+    #[test]
+    fn blank_and_noisy_pages_have_no_code() {
+        assert_eq!(detect(&GrayImage::from_pixel(300, 200, Luma([255]))), None);
+        let mut state = 0x9E37_79B9u32;
+        let noise = GrayImage::from_fn(300, 200, |_, _| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            Luma([state as u8])
+        });
+        assert_eq!(detect(&noise), None);
+    }
+
+    /// Násobení v GF(256) s polynomem 0x11D nezávisle na tabulkách v `rs`.
+    fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+        let mut p = 0;
+        while b != 0 {
+            if b & 1 != 0 {
+                p ^= a;
+            }
+            let carry = a & 0x80 != 0;
+            a <<= 1;
+            if carry {
+                a ^= 0x1D;
+            }
+            b >>= 1;
+        }
+        p
+    }
+
+    /// Data a `ec` opravných slov (nejvyšší stupeň první, generátor s kořeny α⁰…αᵉᶜ⁻¹).
+    fn rs_encode(data: &[u8], ec: usize) -> Vec<u8> {
+        let mut generator = vec![1u8];
+        let mut root = 1u8;
+        for _ in 0..ec {
+            let mut next = vec![0u8; generator.len() + 1];
+            for (i, &g) in generator.iter().enumerate() {
+                next[i] ^= g;
+                next[i + 1] ^= gf_mul(g, root);
+            }
+            generator = next;
+            root = gf_mul(root, 2);
+        }
+        let mut rem = vec![0u8; ec];
+        for &d in data {
+            let factor = d ^ rem[0];
+            rem.rotate_left(1);
+            rem[ec - 1] = 0;
+            for (r, &g) in rem.iter_mut().zip(&generator[1..]) {
+                *r ^= gf_mul(g, factor);
+            }
+        }
+        [data, &rem].concat()
+    }
+
+    /// Bloky ve velikostech z QR (data, opravná slova) a deterministický zdroj chyb.
+    const BLOCKS: [(usize, usize); 5] = [(19, 7), (16, 10), (13, 13), (9, 17), (68, 26)];
+
+    fn xorshift(state: &mut u32) -> u32 {
+        *state ^= *state << 13;
+        *state ^= *state >> 17;
+        *state ^= *state << 5;
+        *state
+    }
+
+    /// Blok s `errors` poškozenými slovy na různých místech.
+    fn damaged(block: &[u8], errors: usize, state: &mut u32) -> Vec<u8> {
+        let mut out = block.to_vec();
+        let mut hit = Vec::new();
+        while hit.len() < errors {
+            let pos = xorshift(state) as usize % block.len();
+            if !hit.contains(&pos) {
+                hit.push(pos);
+                out[pos] ^= (xorshift(state) % 255 + 1) as u8;
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn rs_corrects_errors_up_to_capacity() {
+        let mut state = 0x1234_5678;
+        for (data_len, ec) in BLOCKS {
+            let data: Vec<u8> = (0..data_len).map(|_| xorshift(&mut state) as u8).collect();
+            let block = rs_encode(&data, ec);
+            let mut clean = block.clone();
+            assert!(rs::correct(&mut clean, ec));
+            assert_eq!(clean, block);
+            for errors in 1..=ec / 2 {
+                for _ in 0..20 {
+                    let mut bad = damaged(&block, errors, &mut state);
+                    assert!(rs::correct(&mut bad, ec), "{errors} errors, ec {ec}");
+                    assert_eq!(bad, block, "{errors} errors, ec {ec}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rs_rejects_errors_beyond_capacity() {
+        let mut state = 0x8765_4321;
+        for (data_len, ec) in BLOCKS {
+            let data: Vec<u8> = (0..data_len).map(|_| xorshift(&mut state) as u8).collect();
+            let block = rs_encode(&data, ec);
+            for errors in ec / 2 + 1..=ec / 2 + 3 {
+                for _ in 0..20 {
+                    let mut bad = damaged(&block, errors, &mut state);
+                    let fixed = rs::correct(&mut bad, ec);
+                    assert!(!fixed, "{errors} errors accepted, ec {ec}");
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,423 @@
+use anyhow::{Result, anyhow, bail};
+use eframe::egui;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::barcode::BarcodeImport;
+use crate::browser;
+use crate::convert;
+use crate::cti::{self, CTIDecoder, EncodeParams};
+use crate::export::{self, ExportOptions};
+use crate::metaform::FormField;
+use crate::naming::OutputName;
+use crate::presets;
+
+/// Akce aplikovaná na všechny vybrané soubory v jednom jobu (ukládá se i do obnovy
+/// po pádu, viz [`crate::recovery`]).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum BatchAction {
+    Verify,
+    /// CTI → PNG/TIFF/JPEG; s `in_root` se zachová struktura podadresářů.
+    Export {
+        in_root: Option<PathBuf>,
+        out_dir: PathBuf,
+        options: ExportOptions,
+    },
+    Recompress {
+        out_dir: PathBuf,
+        #[serde(with = "presets::encode_params")]
+        params: EncodeParams,
+    },
+    Rate(u8),
+    /// PNG/TIFF → CTI; struktura podadresářů `in_dir` se zachová v `out_dir`.
+    Convert {
+        in_dir: PathBuf,
+        out_dir: PathBuf,
+        #[serde(with = "presets::encode_params")]
+        params: EncodeParams,
+        /// Šablona metadat (viz [`presets::render_metadata`]).
+        metadata: BTreeMap<String, String>,
+        /// Pravidla formuláře metadat, kterým musí vyhovět každý soubor.
+        form: Vec<FormField>,
+        /// Identifikátor z čárového/QR kódu na snímku.
+        barcode: Option<BarcodeImport>,
+        name: OutputName,
+    },
+}
+
+impl BatchAction {
+    pub fn label(&self) -> String {
+        match self {
+            BatchAction::Verify => "Verify".into(),
+            BatchAction::Export { options, .. } => format!("Export {}", options.format.as_str()),
+            BatchAction::Recompress { params, .. } => {
+                format!("Recompress ({})", params.compression.as_str())
+            }
+            BatchAction::Rate(n) => format!("Rate {n}★"),
+            BatchAction::Convert { params, .. } => {
+                format!("Convert → CTI ({})", params.compression.as_str())
+            }
+        }
+    }
+
+    /// Kontrola celé dávky před prvním souborem (zatím volné místo pro převod do CTI).
+    fn preflight(&self, files: &[PathBuf]) -> Result<()> {
+        match self {
+            BatchAction::Convert {
+                out_dir, params, ..
+            } => convert::check_space(files, out_dir, params),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct JobState {
+    pub done: usize,
+    pub total: usize,
+    pub failed: usize,
+    pub log: Vec<String>,
+    pub finished: bool,
+    /// Který soubor už je zpracovaný (i neúspěšně).
+    processed: Vec<bool>,
+}
+
+/// Běžící (nebo doběhnutý) dávkový job; práce probíhá ve vlákně na pozadí.
+pub struct BatchJob {
+    pub label: String,
+    state: Arc<Mutex<JobState>>,
+    cancel: Arc<AtomicBool>,
+    /// Akce a soubory jobu ze [`spawn`](Self::spawn) (pro obnovu po pádu).
+    source: Option<(BatchAction, Vec<PathBuf>)>,
+}
+
+impl BatchJob {
+    pub fn spawn(ctx: &egui::Context, action: BatchAction, files: Vec<PathBuf>) -> Self {
+        let state = Arc::new(Mutex::new(JobState {
+            total: files.len(),
+            processed: vec![false; files.len()],
+            ..Default::default()
+        }));
+        let cancel = Arc::new(AtomicBool::new(false));
+        let label = action.label();
+        let source = Some((action.clone(), files.clone()));
+
+        let (st, cn, ctx) = (state.clone(), cancel.clone(), ctx.clone());
+        std::thread::spawn(move || {
+            if let Err(e) = action.preflight(&files) {
+                let mut s = st.lock().unwrap();
+                s.failed = s.total;
+                s.log.push(format!("FAIL {e:#}"));
+                s.finished = true;
+                drop(s);
+                ctx.request_repaint();
+                return;
+            }
+            // soubory se zpracovávají paralelně; log je v pořadí dokončení
+            files.par_iter().enumerate().for_each(|(i, path)| {
+                if cn.load(Ordering::Relaxed) {
+                    return;
+                }
+                let res = run_one(&action, path, i + 1);
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                let mut s = st.lock().unwrap();
+                s.done += 1;
+                s.processed[i] = true;
+                match res {
+                    Ok(msg) => s.log.push(format!("OK   {name}: {msg}")),
+                    Err(e) => {
+                        s.failed += 1;
+                        s.log.push(format!("FAIL {name}: {e:#}"));
+                    }
+                }
+                drop(s);
+                ctx.request_repaint();
+            });
+            let mut s = st.lock().unwrap();
+            if cn.load(Ordering::Relaxed) {
+                s.log.push("cancelled".into());
+            }
+            s.finished = true;
+            drop(s);
+            ctx.request_repaint();
+        });
+
+        Self {
+            label,
+            state,
+            cancel,
+            source,
+        }
+    }
+
+    /// Jedna dlouhá úloha nad celou sadou souborů (např. sešití), s průběhem po krocích.
+    pub fn spawn_task(
+        ctx: &egui::Context,
+        label: String,
+        steps: usize,
+        task: impl FnOnce(&Progress) -> Result<String> + Send + 'static,
+    ) -> Self {
+        let state = Arc::new(Mutex::new(JobState {
+            total: steps,
+            ..Default::default()
+        }));
+        let cancel = Arc::new(AtomicBool::new(false));
+        let progress = Progress {
+            state: state.clone(),
+            cancel: cancel.clone(),
+            ctx: ctx.clone(),
+        };
+        std::thread::spawn(move || {
+            let res = task(&progress);
+            let mut s = progress.state.lock().unwrap();
+            match res {
+                Ok(msg) => {
+                    s.done = s.total;
+                    s.log.push(format!("OK   {msg}"));
+                }
+                Err(e) => {
+                    s.failed += 1;
+                    s.log.push(format!("FAIL {e:#}"));
+                }
+            }
+            s.finished = true;
+            drop(s);
+            progress.ctx.request_repaint();
+        });
+        Self {
+            label,
+            state,
+            cancel,
+            source: None,
+        }
+    }
+
+    pub fn with_state<R>(&self, f: impl FnOnce(&JobState) -> R) -> R {
+        f(&self.state.lock().unwrap())
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.with_state(|s| s.finished)
+    }
+
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    /// Akce a dosud nezpracované soubory běžícího jobu; `None` u doběhnutého jobu
+    /// a u jednorázové úlohy ze [`spawn_task`](Self::spawn_task).
+    pub fn remaining(&self) -> Option<(BatchAction, Vec<PathBuf>)> {
+        let (action, files) = self.source.as_ref()?;
+        self.with_state(|s| {
+            let left = files
+                .iter()
+                .enumerate()
+                .filter(|&(i, _)| !s.processed[i])
+                .map(|(_, f)| f.clone())
+                .collect();
+            (!s.finished).then(|| (action.clone(), left))
+        })
+    }
+}
+
+/// Hlášení průběhu z úlohy spuštěné přes [`BatchJob::spawn_task`].
+pub struct Progress {
+    state: Arc<Mutex<JobState>>,
+    cancel: Arc<AtomicBool>,
+    ctx: egui::Context,
+}
+
+impl Progress {
+    /// Dokončený krok (posune ukazatel a zapíše zprávu do logu).
+    pub fn step(&self, msg: impl Into<String>) {
+        let mut s = self.state.lock().unwrap();
+        s.done = (s.done + 1).min(s.total);
+        s.log.push(msg.into());
+        drop(s);
+        self.ctx.request_repaint();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+}
+
+/// Dekóduje soubor (kontroluje CRC každé dlaždice všech snímků) a vypíše všechny poškozené
+/// dlaždice; s `deep` ověří i hash celého souboru.
+pub fn verify_file(path: &Path, deep: bool) -> Result<String> {
+    let frames = CTIDecoder::frame_count(path)?;
+    let mut total = 0;
+    let mut list = Vec::new();
+    for frame in 0..frames {
+        let (hdr, _, bad) = CTIDecoder::decode_frame_lossy(path, frame)?;
+        total += hdr.tiles_per_frame();
+        // u sekvence se poloha uvádí i se snímkem (od 1)
+        let prefix = if frames > 1 {
+            format!("#{} ", frame + 1)
+        } else {
+            String::new()
+        };
+        list.extend(bad.iter().map(|t| {
+            format!(
+                "{prefix}{},{} ({})",
+                t.index % hdr.tiles_x,
+                t.index / hdr.tiles_x,
+                t.error
+            )
+        }));
+    }
+    if !list.is_empty() {
+        bail!(
+            "{} of {total} tiles damaged: {}",
+            list.len(),
+            list.join("; ")
+        );
+    }
+    let tiles = format!("{total} tiles OK");
+    if !deep {
+        return Ok(tiles);
+    }
+    Ok(if cti::verify_file_hash(path)? {
+        format!("{tiles}, SHA-256 OK")
+    } else {
+        format!("{tiles}, no file hash")
+    })
+}
+
+/// `n` = pořadí souboru v jobu (od 1), pro šablonu jména výstupu.
+fn run_one(action: &BatchAction, path: &Path, n: usize) -> Result<String> {
+    match action {
+        BatchAction::Verify => verify_file(path, true),
+        BatchAction::Export {
+            in_root,
+            out_dir,
+            options,
+        } => export::export_into(path, in_root.as_deref(), out_dir, n, options),
+        BatchAction::Recompress { out_dir, params } => {
+            let name = path.file_name().ok_or_else(|| anyhow!("no file name"))?;
+            let dst = out_dir.join(name);
+            // ICC profil, metadata, RCT i kontrolní součty zůstanou jako ve zdroji; zápis
+            // přes zdroj (i jinou cestou k témuž souboru) odmítne sám
+            cti::transcode_file(
+                path,
+                &dst,
+                params.compression,
+                params.level,
+                params.quality,
+                Some(params.tile_size),
+            )?;
+            let before = std::fs::metadata(path)?.len();
+            let after = std::fs::metadata(&dst)?.len();
+            Ok(format!("{before} → {after} B"))
+        }
+        BatchAction::Rate(n) => {
+            browser::write_rating(path, *n)?;
+            Ok(format!("{n}★"))
+        }
+        BatchAction::Convert {
+            in_dir,
+            out_dir,
+            params,
+            metadata,
+            form,
+            barcode,
+            name,
+        } => {
+            let dir = convert::output_dir(path, in_dir, out_dir)?;
+            let meta = presets::render_metadata(metadata, path)?;
+            convert::convert_file(path, &dir, name, n, params, meta, barcode.as_ref(), form)
+        }
+    }
+}
+
+/// Okno s průběhem jobu. Vrací `false`, když ho uživatel zavřel.
+pub fn job_window(ctx: &egui::Context, job: &BatchJob) -> bool {
+    let mut open = true;
+    egui::Window::new(format!("Batch: {}", job.label))
+        .collapsible(true)
+        .resizable(true)
+        .open(&mut open)
+        .show(ctx, |ui| {
+            job.with_state(|s| {
+                let frac = if s.total == 0 {
+                    1.0
+                } else {
+                    s.done as f32 / s.total as f32
+                };
+                ui.add(
+                    egui::ProgressBar::new(frac)
+                        .text(format!("{}/{}  (failed: {})", s.done, s.total, s.failed)),
+                );
+                egui::ScrollArea::vertical()
+                    .max_height(240.0)
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        for line in &s.log {
+                            ui.monospace(line);
+                        }
+                    });
+            });
+            if !job.is_finished() && ui.button("Cancel").clicked() {
+                job.cancel();
+            }
+        });
+    open
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cti::{CTIEncoder, CTIExtensions, CompressionId};
+
+    #[test]
+    fn recompress_keeps_extensions_and_refuses_the_source() {
+        let dir = std::env::temp_dir().join(format!("cti-batch-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        let src = dir.join("a.cti");
+        let mut ext = CTIExtensions {
+            icc: Some(b"profile".to_vec()),
+            ..Default::default()
+        };
+        ext.metadata.set("Title", "Master");
+        let source = EncodeParams {
+            crc32c: true,
+            ..EncodeParams::default()
+        };
+        CTIEncoder::encode_frames_with_extensions(&src, 4, 3, 1, &[&[7; 12]], &source, &ext)
+            .unwrap();
+        let before = std::fs::read(&src).unwrap();
+        let params = EncodeParams {
+            compression: CompressionId::Lz4,
+            ..EncodeParams::default()
+        };
+
+        // `dir/sub/..` se od `dir` liší jen zápisem
+        let action = BatchAction::Recompress {
+            out_dir: dir.join("sub").join(".."),
+            params,
+        };
+        let err = run_one(&action, &src, 1).unwrap_err();
+        assert!(err.to_string().contains("different file"), "{err:#}");
+        assert_eq!(std::fs::read(&src).unwrap(), before);
+
+        let action = BatchAction::Recompress {
+            out_dir: dir.join("sub"),
+            params,
+        };
+        run_one(&action, &src, 1).unwrap();
+        // kodek podle akce, ostatní podle zdroje
+        let dst = dir.join("sub").join("a.cti");
+        let hdr = CTIDecoder::info(&dst).unwrap();
+        assert_eq!(hdr.compression, CompressionId::Lz4.id());
+        assert!(hdr.has_crc32c());
+        let kept = CTIDecoder::extensions(&dst).unwrap();
+        assert_eq!(kept.icc.as_deref(), Some(&b"profile"[..]));
+        assert_eq!(kept.metadata.get("Title"), Some("Master"));
+        assert_eq!(CTIDecoder::decode_file(&dst).unwrap().1, [7; 12]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,31 @@
+//! Vstupní bod prohlížeče pro web: `trunk build --release` (ve složce `web/`) sestaví
+//! wasm32 a vykreslí [`WebViewer`](cti_view::web::WebViewer) do plátna `cti-view`.
+
+#[cfg(target_arch = "wasm32")]
+fn main() {
+    use eframe::wasm_bindgen::JsCast;
+
+    wasm_bindgen_futures::spawn_local(async {
+        let canvas = eframe::web_sys::window()
+            .and_then(|w| w.document())
+            .and_then(|d| d.get_element_by_id("cti-view"))
+            .and_then(|e| e.dyn_into::<eframe::web_sys::HtmlCanvasElement>().ok())
+            .expect("index.html has no <canvas id=\"cti-view\">");
+        let started = eframe::WebRunner::new()
+            .start(
+                canvas,
+                eframe::WebOptions::default(),
+                Box::new(|cc| Ok(Box::new(cti_view::web::WebViewer::new(cc)))),
+            )
+            .await;
+        if let Err(e) = started {
+            eframe::web_sys::console::error_1(&e);
+        }
+    });
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    eprintln!("cti-view-web runs in a web browser: build it with `trunk build --release` in web/");
+    std::process::exit(2);
+}
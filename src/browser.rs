@@ -0,0 +1,278 @@
+use anyhow::{Result, bail};
+use eframe::egui;
+use rfd::FileDialog;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use crate::a11y;
+use crate::batch::BatchAction;
+use crate::cti::{CompressionId, EncodeParams};
+
+/// Postranní panel se seznamem .cti souborů v adresáři právě otevřeného souboru.
+#[derive(Default)]
+pub struct FileBrowser {
+    dir: Option<PathBuf>,
+    entries: Vec<Entry>,
+    selected: BTreeSet<usize>,
+    anchor: Option<usize>,
+    recompress: EncodeParams,
+    /// Bez akcí nad výběrem (kiosek).
+    pub read_only: bool,
+}
+
+struct Entry {
+    path: PathBuf,
+    rating: Option<u8>,
+}
+
+pub enum BrowserAction {
+    Open(PathBuf),
+    OpenInNewWindow(PathBuf),
+    OpenInNewTab(PathBuf),
+    Batch(BatchAction, Vec<PathBuf>),
+    /// Otevřít dialog exportu pro vybrané soubory.
+    Export(Vec<PathBuf>),
+}
+
+impl FileBrowser {
+    /// Přepne na adresář (znovu načte jen při změně).
+    pub fn set_dir(&mut self, dir: &Path) {
+        if self.dir.as_deref() != Some(dir) {
+            self.dir = Some(dir.to_path_buf());
+            self.rescan();
+        }
+    }
+
+    pub fn rescan(&mut self) {
+        let Some(dir) = &self.dir else { return };
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)
+            .map(|rd| {
+                rd.filter_map(|e| e.ok().map(|e| e.path()))
+                    .filter(|p| p.is_file() && is_cti(p))
+                    .collect()
+            })
+            .unwrap_or_default();
+        paths.sort();
+
+        // výběr držíme podle cesty, indexy se mohou posunout
+        let keep: BTreeSet<PathBuf> = self
+            .selected
+            .iter()
+            .filter_map(|&i| self.entries.get(i).map(|e| e.path.clone()))
+            .collect();
+        self.entries = paths
+            .into_iter()
+            .map(|path| Entry {
+                rating: read_rating(&path),
+                path,
+            })
+            .collect();
+        self.selected = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| keep.contains(&e.path))
+            .map(|(i, _)| i)
+            .collect();
+        self.anchor = None;
+    }
+
+    pub fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        current: Option<&Path>,
+        job_running: bool,
+    ) -> Option<BrowserAction> {
+        let mut action = None;
+
+        ui.horizontal(|ui| {
+            ui.strong("Files");
+            if a11y::icon_button(ui, "⟳", "Rescan folder").clicked() {
+                self.rescan();
+            }
+        });
+        ui.separator();
+
+        // Akce nad výběrem
+        let sel = self.selection();
+        ui.add_enabled_ui(!sel.is_empty() && !job_running, |ui| {
+            if self.read_only {
+                return;
+            }
+            ui.label(format!("{} selected", sel.len()));
+            ui.horizontal_wrapped(|ui| {
+                if ui.button("Verify").clicked() {
+                    action = Some(BrowserAction::Batch(BatchAction::Verify, sel.clone()));
+                }
+                if ui.button("Export…").clicked() {
+                    action = Some(BrowserAction::Export(sel.clone()));
+                }
+            });
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_salt("recompress-codec")
+                    .selected_text(self.recompress.compression.as_str())
+                    .show_ui(ui, |ui| {
+                        for c in CompressionId::encoders() {
+                            let on = self.recompress.compression.id() == c.id();
+                            if ui.selectable_label(on, c.as_str()).clicked() {
+                                self.recompress.compression = c;
+                            }
+                        }
+                    });
+                if ui.button("Recompress…").clicked()
+                    && let Some(out_dir) = FileDialog::new().pick_folder()
+                {
+                    action = Some(BrowserAction::Batch(
+                        BatchAction::Recompress {
+                            out_dir,
+                            params: self.recompress,
+                        },
+                        sel.clone(),
+                    ));
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Rate:");
+                for n in 0..=5u8 {
+                    let (txt, label) = if n == 0 {
+                        ("✖".to_string(), "Clear rating".to_string())
+                    } else {
+                        (n.to_string(), format!("Rate {n} stars"))
+                    };
+                    if a11y::icon_button(ui, &txt, &label).clicked() {
+                        action = Some(BrowserAction::Batch(BatchAction::Rate(n), sel.clone()));
+                    }
+                }
+            });
+        });
+        ui.separator();
+
+        // Seznam: klik = otevřít, Ctrl = přidat/odebrat, Shift = rozsah
+        let mods = ui.input(|i| i.modifiers);
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for i in 0..self.entries.len() {
+                let e = &self.entries[i];
+                let name = e.path.file_name().unwrap_or_default().to_string_lossy();
+                let stars = "★".repeat(e.rating.unwrap_or(0) as usize);
+                let is_current = current == Some(e.path.as_path());
+                let mut text = egui::RichText::new(format!("{name} {stars}"));
+                if is_current {
+                    text = text.strong();
+                }
+                let selected = self.selected.contains(&i);
+                let resp = ui.selectable_label(selected, text);
+                resp.widget_info(|| {
+                    let rating = match e.rating {
+                        Some(n) if n > 0 => format!(", rated {n} stars"),
+                        _ => String::new(),
+                    };
+                    egui::WidgetInfo::selected(
+                        egui::WidgetType::SelectableLabel,
+                        true,
+                        selected,
+                        format!("{name}{rating}"),
+                    )
+                });
+                if resp.clicked() {
+                    if mods.shift {
+                        let a = self.anchor.unwrap_or(i);
+                        if !mods.command {
+                            self.selected.clear();
+                        }
+                        self.selected.extend(a.min(i)..=a.max(i));
+                    } else if mods.command {
+                        if !self.selected.remove(&i) {
+                            self.selected.insert(i);
+                        }
+                        self.anchor = Some(i);
+                    } else {
+                        self.selected = BTreeSet::from([i]);
+                        self.anchor = Some(i);
+                        action = Some(BrowserAction::Open(e.path.clone()));
+                    }
+                }
+                if resp.middle_clicked() && !self.read_only {
+                    action = Some(BrowserAction::OpenInNewTab(e.path.clone()));
+                }
+                if !self.read_only {
+                    resp.context_menu(|ui| {
+                        if ui.button("Open in new tab").clicked() {
+                            action = Some(BrowserAction::OpenInNewTab(e.path.clone()));
+                            ui.close();
+                        }
+                        if ui.button("Open in new window").clicked() {
+                            action = Some(BrowserAction::OpenInNewWindow(e.path.clone()));
+                            ui.close();
+                        }
+                    });
+                }
+            }
+            if self.entries.is_empty() {
+                ui.weak("No .cti files");
+            }
+        });
+
+        action
+    }
+
+    /// Soused souboru `current` v seznamu (`delta` = +1 další, -1 předchozí), bez přetečení.
+    pub fn neighbor(&self, current: &Path, delta: isize) -> Option<PathBuf> {
+        let i = self.entries.iter().position(|e| e.path == current)?;
+        let j = i.checked_add_signed(delta)?;
+        self.entries.get(j).map(|e| e.path.clone())
+    }
+
+    fn selection(&self) -> Vec<PathBuf> {
+        self.selected
+            .iter()
+            .filter_map(|&i| self.entries.get(i).map(|e| e.path.clone()))
+            .collect()
+    }
+}
+
+fn is_cti(p: &Path) -> bool {
+    p.extension().is_some_and(|e| e.eq_ignore_ascii_case("cti"))
+}
+
+// --- hodnocení v XMP sidecaru (foo.cti → foo.xmp), kompatibilní s Bridge/Lightroom ---
+
+const RATING_ATTR: &str = "xmp:Rating=\"";
+
+fn sidecar_path(path: &Path) -> PathBuf {
+    path.with_extension("xmp")
+}
+
+pub fn read_rating(path: &Path) -> Option<u8> {
+    let s = std::fs::read_to_string(sidecar_path(path)).ok()?;
+    let start = s.find(RATING_ATTR)? + RATING_ATTR.len();
+    let end = start + s[start..].find('"')?;
+    s[start..end].trim().parse().ok()
+}
+
+pub fn write_rating(path: &Path, rating: u8) -> Result<()> {
+    let side = sidecar_path(path);
+    let text = match std::fs::read_to_string(&side) {
+        Ok(s) => {
+            // existující sidecar: přepíšeme jen hodnotu, zbytek necháme být
+            let Some(pos) = s.find(RATING_ATTR) else {
+                bail!("{} exists without xmp:Rating; not modified", side.display());
+            };
+            let start = pos + RATING_ATTR.len();
+            let Some(len) = s[start..].find('"') else {
+                bail!("{} is malformed", side.display());
+            };
+            format!("{}{}{}", &s[..start], rating, &s[start + len..])
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => format!(
+            "<x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n \
+             <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\n  \
+             <rdf:Description rdf:about=\"\" xmlns:xmp=\"http://ns.adobe.com/xap/1.0/\" \
+             xmp:Rating=\"{rating}\"/>\n \
+             </rdf:RDF>\n\
+             </x:xmpmeta>\n"
+        ),
+        Err(e) => return Err(e.into()),
+    };
+    std::fs::write(side, text)?;
+    Ok(())
+}
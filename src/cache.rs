@@ -0,0 +1,80 @@
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use crate::cti::CTIHeader;
+
+/// LRU cache naposledy dekódovaných obrázků (u sekvencí po snímcích) s limitem velikosti
+/// v bajtech, aby přepínání další/předchozí nemuselo znovu dekódovat.
+#[derive(Default)]
+pub struct ImageCache {
+    budget: usize,
+    entries: VecDeque<Entry>,
+}
+
+struct Entry {
+    path: PathBuf,
+    frame: u32,
+    modified: Option<SystemTime>,
+    hdr: CTIHeader,
+    raw: Arc<Vec<u8>>,
+}
+
+impl ImageCache {
+    pub fn new(budget: usize) -> Self {
+        Self {
+            budget,
+            entries: VecDeque::new(),
+        }
+    }
+
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+        self.evict();
+    }
+
+    /// Vrátí data z cache, pokud se soubor od dekódování nezměnil.
+    pub fn get(&mut self, path: &Path, frame: u32) -> Option<(CTIHeader, Arc<Vec<u8>>)> {
+        let i = self
+            .entries
+            .iter()
+            .position(|e| e.path == path && e.frame == frame)?;
+        let e = self.entries.remove(i)?;
+        if e.modified != modified(path) {
+            return None;
+        }
+        let out = (e.hdr, e.raw.clone());
+        self.entries.push_front(e);
+        Some(out)
+    }
+
+    pub fn insert(&mut self, path: &Path, frame: u32, hdr: CTIHeader, raw: Arc<Vec<u8>>) {
+        self.entries.retain(|e| e.path != path || e.frame != frame);
+        self.entries.push_front(Entry {
+            path: path.to_path_buf(),
+            frame,
+            modified: modified(path),
+            hdr,
+            raw,
+        });
+        self.evict();
+    }
+
+    fn evict(&mut self) {
+        let mut total = 0usize;
+        let keep = self
+            .entries
+            .iter()
+            .take_while(|e| {
+                total += e.raw.len();
+                total <= self.budget
+            })
+            .count();
+        self.entries.truncate(keep);
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
@@ -0,0 +1,1667 @@
+use anyhow::{Context, Result, anyhow, bail, ensure};
+use clap::{ArgGroup, Args, Parser, Subcommand, ValueEnum};
+use eframe::egui;
+use rayon::prelude::*;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::barcode::BarcodeImport;
+use crate::batch;
+use crate::convert;
+use crate::cti::{self, CTIDecoder, CTIMetadata, CompressionId, EncodeParams};
+use crate::diff::QualityReport;
+use crate::dryrun::Plan;
+use crate::dzi::{self, DziFormat, DziOptions};
+use crate::export::{self, BitDepth, ExportFormat, ExportOptions};
+use crate::flatfield::FlatField;
+use crate::iiif;
+use crate::lens::LensCorrection;
+use crate::lint::{self, Issue, Severity};
+use crate::mtf::Mtf;
+use crate::naming::{self, Collision, OutputName, Planned};
+use crate::noise;
+use crate::portable::{self, HtmlOptions};
+use crate::presets::{self, Preset};
+use crate::report::{FileReport, ReportEntry};
+use crate::stitch;
+use crate::testdata;
+use crate::update;
+use crate::upload::Uploader;
+use crate::workdirs::{self, Area};
+
+/// CTI View – bez argumentů spustí prohlížeč, s podpříkazem běží v terminálu.
+#[derive(Parser)]
+#[command(
+    name = "cti-view",
+    version,
+    about = "Viewer and tools for CTI tiled images"
+)]
+#[command(group = ArgGroup::new("input").args(["check", "open"]))]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// Run the viewer locked down for public reading-room terminals: only files from the
+    /// folders in CONFIG (TOML, default `cti-view-kiosk.toml` next to the program) can be
+    /// viewed; export, conversion, editing and settings are disabled
+    #[arg(long, value_name = "CONFIG", num_args = 0..=1)]
+    pub kiosk: Option<Option<PathBuf>>,
+    /// Open a .cti file or a view link (cti-view://view?…) copied from another CTI View
+    #[arg(long, value_name = "FILE|LINK")]
+    pub open: Option<String>,
+    /// Check FILE for damaged tiles without opening a window; the exit status is non-zero
+    /// when the file is damaged (for CI pipelines and scripts)
+    #[arg(long, value_name = "FILE")]
+    pub check: Option<PathBuf>,
+    /// Decode the file from --check or --open to OUT (PNG, TIFF or JPEG by extension)
+    /// without opening a window
+    #[arg(long, value_name = "OUT", requires = "input")]
+    pub decode_to: Option<PathBuf>,
+    /// With --check: print a JSON report (header, per-tile sizes and CRC results, ratios)
+    #[arg(long, requires = "check")]
+    pub json: bool,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Convert a directory of PNG/TIFF images to CTI
+    Convert(ConvertArgs),
+    /// Export a directory of CTI images to PNG/TIFF/JPEG
+    Export(ExportArgs),
+    /// Print or edit the metadata of CTI files without re-encoding tiles
+    Meta(MetaArgs),
+    /// Stitch overlapping CTI captures into one image
+    Stitch(StitchArgs),
+    /// Pack PNG/TIFF images into one multi-frame CTI (scan sequence, multi-page document)
+    Sequence(SequenceArgs),
+    /// Print the header, tile sizes and compression ratios of CTI files
+    Info(InfoArgs),
+    /// Check CTI files for corruption
+    Verify(VerifyArgs),
+    /// Estimate noise in flat regions and check it against a limit
+    Noise(NoiseArgs),
+    /// Measure MTF50 from a slanted edge
+    Mtf(MtfArgs),
+    /// Upload finished CTI masters to the repository set up in a TOML file (S3, WebDAV or
+    /// SWORD); files already in its receipt catalog are skipped
+    Upload(UploadArgs),
+    /// Delete old files from the temp and cache directories (size and age limits)
+    Cleanup(CleanupArgs),
+    /// Look for a newer signed release in the update channel (and install it on Windows)
+    Update(UpdateArgs),
+    /// Serve CTI files from a directory over the IIIF Image API 3.0 for deep-zoom web
+    /// viewers (OpenSeadragon, Mirador…)
+    Serve(ServeArgs),
+    /// Export a CTI file as a DeepZoom (DZI) tile pyramid for OpenSeadragon and other web
+    /// viewers
+    Dzi(DziArgs),
+    /// Export a downsampled tile pyramid with a self-contained HTML viewer that works
+    /// without a server (open the .html from disk, a USB stick or an e-mail)
+    Html(HtmlArgs),
+    /// Recompress the tiles of a CTI file with another codec; pixels are re-encoded only
+    /// when the stored tile data changes (to or from the lossy codec)
+    Transcode(TranscodeArgs),
+    /// Crop, rotate or flip a CTI file into a new one; tiles a tile-aligned crop keeps
+    /// whole are copied without re-encoding
+    Edit(EditArgs),
+    /// Check CTI files for conformance with the format beyond CRC (header, index coverage,
+    /// tile placement) and print coded errors and warnings, for authors of other writers
+    Lint(LintArgs),
+    /// Compare a CTI file with its reference (e.g. the PNG/TIFF original) and print PSNR,
+    /// SSIM and optionally MS-SSIM as JSON, for the whole image or per tile
+    Metrics(MetricsArgs),
+    /// Recognize text in a CTI file with an external OCR program (Tesseract) and print it;
+    /// results are cached in `<file>.ocr.json`
+    #[cfg(feature = "ocr")]
+    Ocr(OcrArgs),
+    /// Write a corpus of synthetic CTI files (every codec and color type, edge tile sizes,
+    /// deliberately broken files) with a JSON manifest, for tests of this and other readers
+    GenTestdata(GenTestdataArgs),
+}
+
+#[derive(Args)]
+pub struct ConvertArgs {
+    /// Input directory with PNG/TIFF files
+    input: PathBuf,
+    /// Output directory (subfolder structure is preserved)
+    output: PathBuf,
+    /// Descend into subdirectories
+    #[arg(short, long)]
+    recursive: bool,
+    #[command(flatten)]
+    encode: EncodeArgs,
+    /// Metadata entry stored in every output file, e.g. `Operator=Jan Novák` (repeatable)
+    #[arg(long = "meta", value_name = "KEY=VALUE", value_parser = parse_meta)]
+    meta: Vec<(String, String)>,
+    /// XMP packet to embed in every output file
+    #[arg(long, value_name = "FILE")]
+    xmp: Option<PathBuf>,
+    #[command(flatten)]
+    barcode: BarcodeArgs,
+    #[command(flatten)]
+    name: NameArgs,
+    /// Only print what would be read, written and overwritten; write nothing
+    #[arg(long)]
+    dry_run: bool,
+    /// Encode a sample of tiles from a few files and print the expected output size and,
+    /// for the lossy codec, PSNR; write nothing
+    #[arg(long, conflicts_with = "dry_run")]
+    estimate: bool,
+    /// Do not check free space in the output directory before writing (the output size is
+    /// estimated from a sample of tiles)
+    #[arg(long)]
+    no_space_check: bool,
+    /// Number of parallel workers (default: all cores)
+    #[arg(short, long)]
+    jobs: Option<usize>,
+}
+
+/// Jméno výstupních souborů a co dělat s existujícími.
+#[derive(Args)]
+pub struct NameArgs {
+    /// Output file name pattern without extension
+    #[arg(long, value_name = "PATTERN", default_value = "{stem}",
+          long_help = naming::PATTERN_HELP)]
+    name: String,
+    /// What to do when an output file already exists (default: overwrite; fail when the
+    /// name comes from a barcode)
+    #[arg(long, value_enum, value_name = "POLICY")]
+    on_collision: Option<CollisionArg>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum CollisionArg {
+    Fail,
+    Skip,
+    Overwrite,
+    /// Append _2, _3 … to the name
+    Number,
+}
+
+impl CollisionArg {
+    fn collision(self) -> Collision {
+        match self {
+            CollisionArg::Fail => Collision::Fail,
+            CollisionArg::Skip => Collision::Skip,
+            CollisionArg::Overwrite => Collision::Overwrite,
+            CollisionArg::Number => Collision::Number,
+        }
+    }
+}
+
+/// Šablona jména; bez volby se přepisuje (jako dřív), jen jméno z kódu nesmí potichu
+/// přepsat jiný objekt se stejným kódem.
+fn output_name(pattern: &str, on_collision: Option<CollisionArg>) -> Result<OutputName> {
+    let mut name = OutputName::new(pattern, Collision::Overwrite);
+    name.collision = match on_collision {
+        Some(c) => c.collision(),
+        None if name.uses("barcode") => Collision::Fail,
+        None => Collision::Overwrite,
+    };
+    name.check()
+        .with_context(|| format!("output name {pattern:?}"))?;
+    Ok(name)
+}
+
+/// Čtení identifikátoru z čárového nebo QR kódu na snímku.
+#[derive(Args)]
+pub struct BarcodeArgs {
+    /// Read a Code 128, Code 39 or QR code from the image and store it under KEY
+    /// (default: Identifier); images without one fail
+    #[arg(long, value_name = "KEY", num_args = 0..=1, require_equals = true,
+          default_missing_value = CTIMetadata::IDENTIFIER)]
+    barcode: Option<String>,
+    /// Name the output file after the code (same as a {barcode} name; implies --barcode)
+    #[arg(long)]
+    name_from_barcode: bool,
+}
+
+impl BarcodeArgs {
+    /// Kód se čte i tehdy, když ho potřebuje jméno výstupu.
+    fn import(&self, name: &OutputName) -> Option<BarcodeImport> {
+        if self.barcode.is_none() && !name.uses("barcode") {
+            return None;
+        }
+        Some(BarcodeImport {
+            key: self
+                .barcode
+                .clone()
+                .unwrap_or_else(|| CTIMetadata::IDENTIFIER.into()),
+        })
+    }
+
+    /// Šablona jména: `{barcode}` s `--name-from-barcode`, jinak `pattern`.
+    fn pattern<'a>(&self, pattern: &'a str) -> &'a str {
+        if self.name_from_barcode {
+            "{barcode}"
+        } else {
+            pattern
+        }
+    }
+}
+
+/// Volby kodéru společné pro podpříkazy, které zapisují CTI.
+#[derive(Args)]
+pub struct EncodeArgs {
+    /// Encode preset: built-in `archival-lossless`, `web-lossy` or `fast`, or a TOML file
+    /// exported from Preferences; explicit options below override it
+    #[arg(long, value_name = "NAME|FILE")]
+    preset: Option<PathBuf>,
+    /// Zstd compression with the given level (default: 9)
+    #[arg(long, value_name = "LEVEL", value_parser = clap::value_parser!(i32).range(1..=22),
+          conflicts_with_all = ["lz4", "brotli", "deflate", "dct", "uncompressed"])]
+    zstd: Option<i32>,
+    /// LZ4 compression
+    #[arg(long, conflicts_with_all = ["brotli", "deflate", "dct", "uncompressed"])]
+    lz4: bool,
+    /// Brotli compression with the given quality (builds with the `brotli` feature)
+    #[arg(long, value_name = "QUALITY", value_parser = clap::value_parser!(i32).range(0..=11),
+          conflicts_with_all = ["deflate", "dct", "uncompressed"])]
+    brotli: Option<i32>,
+    /// Raw Deflate compression with the given level (builds with the `deflate` feature)
+    #[arg(long, value_name = "LEVEL", value_parser = clap::value_parser!(i32).range(0..=9),
+          conflicts_with_all = ["dct", "uncompressed"])]
+    deflate: Option<i32>,
+    /// Lossy DCT compression with the given quality, like JPEG (8-bit L, RGB, RGBA and CMYK)
+    #[arg(long, value_name = "QUALITY", value_parser = clap::value_parser!(u8).range(1..=100),
+          conflicts_with = "uncompressed")]
+    dct: Option<u8>,
+    /// Store tiles uncompressed
+    #[arg(long)]
+    uncompressed: bool,
+    /// Tile size in pixels (default: 256)
+    #[arg(long, value_parser = clap::value_parser!(u32).range(16..=8192))]
+    tile: Option<u32>,
+    /// Disable the reversible color transform
+    #[arg(long)]
+    no_rct: bool,
+    /// Do not append the whole-file SHA-256 trailer
+    #[arg(long)]
+    no_hash: bool,
+    /// Protect tiles with CRC32C instead of CRC32 (not readable by older decoders)
+    #[arg(long)]
+    crc32c: bool,
+    /// Store grayscale as packed 4-bit or 1-bit (bitonal) pixels, e.g. for scanned text;
+    /// color sources are converted to grayscale first
+    #[arg(long, value_name = "BITS", value_parser = parse_gray_bits)]
+    gray_bits: Option<u8>,
+}
+
+impl EncodeArgs {
+    fn preset(&self) -> Result<Option<Preset>> {
+        let Some(path) = self.preset.as_deref() else {
+            return Ok(None);
+        };
+        let builtin = presets::builtin()
+            .iter()
+            .find(|p| path == Path::new(&p.name));
+        if let Some(p) = builtin {
+            return Ok(Some(p.clone()));
+        }
+        Preset::load(path)
+            .with_context(|| format!("preset {}", path.display()))
+            .map(Some)
+    }
+
+    /// Parametry z předvolby (nebo výchozí), přepsané výslovně zadanými volbami.
+    fn params(&self, preset: Option<&Preset>) -> EncodeParams {
+        let mut p = preset.map_or_else(EncodeParams::default, |p| p.encode.params());
+        if let Some(tile) = self.tile {
+            p.tile_size = tile;
+        }
+        if self.no_rct {
+            p.rct = false;
+        }
+        if self.no_hash {
+            p.file_hash = false;
+        }
+        if self.crc32c {
+            p.crc32c = true;
+        }
+        if let Some(bits) = self.gray_bits {
+            p.gray_bits = bits;
+        }
+        if self.lz4 {
+            p.compression = CompressionId::Lz4;
+        } else if self.uncompressed {
+            p.compression = CompressionId::None;
+        } else if let Some(level) = self.zstd {
+            p.compression = CompressionId::Zstd;
+            p.level = level;
+        } else if let Some(level) = self.brotli {
+            p.compression = CompressionId::Brotli;
+            p.level = level;
+        } else if let Some(level) = self.deflate {
+            p.compression = CompressionId::Deflate;
+            p.level = level;
+        } else if let Some(quality) = self.dct {
+            p.compression = CompressionId::Dct;
+            p.quality = quality;
+        }
+        p
+    }
+}
+
+impl ConvertArgs {
+    /// Výslovně zadaná metadata (mají přednost před šablonou z předvolby).
+    fn metadata(&self) -> Result<CTIMetadata> {
+        let mut meta = CTIMetadata::default();
+        for (k, v) in &self.meta {
+            meta.set(k, v.clone());
+        }
+        if let Some(path) = &self.xmp {
+            let xmp = std::fs::read_to_string(path)
+                .with_context(|| format!("read XMP {}", path.display()))?;
+            meta.set(CTIMetadata::XMP, xmp);
+        }
+        Ok(meta)
+    }
+}
+
+fn parse_meta(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((k, v)) if !k.trim().is_empty() => Ok((k.trim().to_string(), v.to_string())),
+        _ => Err(format!("expected KEY=VALUE, got {s:?}")),
+    }
+}
+
+fn parse_gray_bits(s: &str) -> Result<u8, String> {
+    match s {
+        "1" | "4" | "8" => Ok(s.parse().unwrap()),
+        _ => Err(format!("expected 1, 4 or 8, got {s:?}")),
+    }
+}
+
+#[derive(Args)]
+pub struct ExportArgs {
+    /// Input directory with .cti files
+    input: PathBuf,
+    /// Output directory (subfolder structure is preserved)
+    output: PathBuf,
+    /// Descend into subdirectories
+    #[arg(short, long)]
+    recursive: bool,
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = FormatArg::Png)]
+    format: FormatArg,
+    /// Reduce 16-bit images to 8 bits per channel (JPEG is always 8-bit)
+    #[arg(long)]
+    to_8bit: bool,
+    /// JPEG quality
+    #[arg(long, default_value_t = 90, value_parser = clap::value_parser!(u8).range(1..=100))]
+    quality: u8,
+    /// Output file name pattern without extension
+    #[arg(long, value_name = "PATTERN", default_value = "{stem}",
+          long_help = naming::PATTERN_HELP)]
+    name: String,
+    /// What to do when an output file already exists (default: fail)
+    #[arg(long, value_enum, value_name = "POLICY", conflicts_with = "overwrite")]
+    on_collision: Option<CollisionArg>,
+    /// Replace existing output files (same as --on-collision overwrite)
+    #[arg(long)]
+    overwrite: bool,
+    /// Flat-field reference (CTI/PNG/TIFF capture of an evenly lit white target)
+    #[arg(long, value_name = "FILE")]
+    flat_field: Option<PathBuf>,
+    /// Lens correction profile (JSON); a list of profiles is matched by the Device metadata
+    #[arg(long, value_name = "FILE")]
+    lens: Option<PathBuf>,
+    /// Take lens and flat-field correction from a preset (TOML); --lens/--flat-field override it
+    #[arg(long, value_name = "FILE")]
+    preset: Option<PathBuf>,
+    /// Only print what would be read, written and overwritten; write nothing
+    #[arg(long)]
+    dry_run: bool,
+    /// Number of parallel workers (default: all cores)
+    #[arg(short, long)]
+    jobs: Option<usize>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum FormatArg {
+    Png,
+    Tiff,
+    Jpeg,
+}
+
+impl ExportArgs {
+    fn options(&self) -> Result<ExportOptions> {
+        let preset = match &self.preset {
+            Some(path) => {
+                Some(Preset::load(path).with_context(|| format!("preset {}", path.display()))?)
+            }
+            None => None,
+        };
+        let lens = match (&self.lens, &preset) {
+            (Some(path), _) => LensCorrection::load(path)
+                .with_context(|| format!("lens profile {}", path.display()))?,
+            (None, Some(p)) => p.lens_correction(),
+            (None, None) => LensCorrection::Off,
+        };
+        let flat_field = self
+            .flat_field
+            .clone()
+            .or_else(|| preset.and_then(|p| p.flat_field));
+        // chybnou referenci ohlásit hned, ne u každého souboru
+        if let Some(path) = &flat_field {
+            FlatField::cached(path)
+                .with_context(|| format!("flat-field reference {}", path.display()))?;
+        }
+        Ok(ExportOptions {
+            format: match self.format {
+                FormatArg::Png => ExportFormat::Png,
+                FormatArg::Tiff => ExportFormat::Tiff,
+                FormatArg::Jpeg => ExportFormat::Jpeg,
+            },
+            depth: if self.to_8bit {
+                BitDepth::Eight
+            } else {
+                BitDepth::Keep
+            },
+            jpeg_quality: self.quality,
+            name: OutputName::new(
+                self.name.clone(),
+                match self.on_collision {
+                    Some(c) => c.collision(),
+                    None if self.overwrite => Collision::Overwrite,
+                    None => Collision::Fail,
+                },
+            ),
+            flat_field,
+            lens,
+        })
+    }
+}
+
+#[derive(Args)]
+pub struct MetaArgs {
+    /// CTI files
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+    /// Set a metadata entry, e.g. `Device=Phase One` (repeatable)
+    #[arg(long = "set", value_name = "KEY=VALUE", value_parser = parse_meta)]
+    set: Vec<(String, String)>,
+    /// Remove a metadata entry (repeatable)
+    #[arg(long = "unset", value_name = "KEY")]
+    unset: Vec<String>,
+    /// Embed (replace) the XMP packet from a file
+    #[arg(long, value_name = "FILE")]
+    xmp: Option<PathBuf>,
+    /// Only print what would be read, written and overwritten; write nothing
+    #[arg(long)]
+    dry_run: bool,
+}
+
+#[derive(Args)]
+pub struct StitchArgs {
+    /// Output CTI file
+    output: PathBuf,
+    /// Overlapping captures (at least two)
+    #[arg(required = true, num_args = 2..)]
+    inputs: Vec<PathBuf>,
+    #[command(flatten)]
+    encode: EncodeArgs,
+    /// Only print what would be read, written and overwritten; write nothing
+    #[arg(long)]
+    dry_run: bool,
+}
+
+#[derive(Args)]
+pub struct SequenceArgs {
+    /// Output CTI file; its name may use the placeholders of `convert --name`
+    /// ({stem} is the first frame)
+    output: PathBuf,
+    /// Frames in order: PNG/TIFF files, or one directory (files sorted by name)
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
+    #[command(flatten)]
+    encode: EncodeArgs,
+    #[command(flatten)]
+    barcode: BarcodeArgs,
+    /// What to do when the output file already exists (default: overwrite; fail when the
+    /// name comes from a barcode)
+    #[arg(long, value_enum, value_name = "POLICY")]
+    on_collision: Option<CollisionArg>,
+    /// Only print what would be read, written and overwritten; write nothing
+    #[arg(long)]
+    dry_run: bool,
+    /// Do not check free space in the output directory before writing (the output size is
+    /// estimated from a sample of tiles)
+    #[arg(long)]
+    no_space_check: bool,
+}
+
+#[derive(Args)]
+pub struct VerifyArgs {
+    /// CTI files or directories
+    #[arg(required = true)]
+    paths: Vec<PathBuf>,
+    /// Also check the whole-file SHA-256, not only per-tile CRC
+    #[arg(long)]
+    deep: bool,
+    /// Descend into subdirectories
+    #[arg(short, long)]
+    recursive: bool,
+    /// Number of parallel workers (default: all cores)
+    #[arg(short, long)]
+    jobs: Option<usize>,
+    /// Print a JSON report (header, per-tile sizes and CRC results, ratios) instead of text
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Args)]
+pub struct InfoArgs {
+    /// CTI files
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+    /// Print a JSON report (header, per-tile sizes, ratios, metadata) instead of text
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Args)]
+pub struct NoiseArgs {
+    /// CTI files
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+    /// Maximum acceptable noise σ in 8-bit levels
+    #[arg(long, default_value_t = noise::DEFAULT_THRESHOLD)]
+    threshold: f32,
+    /// Number of parallel workers (default: all cores)
+    #[arg(short, long)]
+    jobs: Option<usize>,
+}
+
+#[derive(Args)]
+pub struct MtfArgs {
+    /// CTI files
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+    /// Region with the edge; without it the edge is searched for automatically
+    #[arg(long, value_name = "X,Y,W,H", value_parser = parse_roi)]
+    roi: Option<egui::Rect>,
+}
+
+#[derive(Args)]
+pub struct UploadArgs {
+    /// Repository settings (TOML: target, catalog, retries, chunk_mb)
+    #[arg(long, value_name = "FILE")]
+    config: PathBuf,
+    /// CTI files or directories; files in a directory keep their relative path in the
+    /// repository
+    #[arg(required = true)]
+    paths: Vec<PathBuf>,
+    /// Descend into subdirectories
+    #[arg(short, long)]
+    recursive: bool,
+}
+
+#[derive(Args)]
+pub struct CleanupArgs {
+    /// Which directory to clean (default: both)
+    #[arg(long, value_enum)]
+    area: Option<AreaArg>,
+    /// Delete files not used for this many days (0 = no age limit; default: 7 for temp,
+    /// 30 for cache)
+    #[arg(long, value_name = "DAYS")]
+    max_age: Option<u32>,
+    /// Then delete the least recently used files until the directory fits (0 = no size
+    /// limit; default: 2048 for temp, 10240 for cache)
+    #[arg(long, value_name = "MIB")]
+    max_size: Option<u64>,
+    /// Only print what would be deleted
+    #[arg(long)]
+    dry_run: bool,
+}
+
+#[derive(Args)]
+pub struct UpdateArgs {
+    /// URL or folder with `latest.json` and signed builds (default: the one set at build time)
+    #[arg(long, value_name = "URL|DIR")]
+    channel: Option<String>,
+    /// Download, verify and install the new release (Windows only)
+    #[arg(long)]
+    install: bool,
+}
+
+#[derive(Args)]
+pub struct ServeArgs {
+    /// Directory with CTI files; the image identifier is the path without `.cti`
+    dir: PathBuf,
+    /// Address and port to listen on
+    #[arg(long, default_value = "127.0.0.1:8182")]
+    listen: String,
+    /// Public service address used in info.json (behind a reverse proxy)
+    #[arg(long, value_name = "URL")]
+    base_url: Option<String>,
+    /// Number of request threads (default: all cores)
+    #[arg(short, long)]
+    jobs: Option<usize>,
+    /// Memory for decoded images used for large regions (MiB)
+    #[arg(long, value_name = "MIB", default_value_t = 512)]
+    cache: usize,
+    /// Memory for decoded tiles reused by neighbouring small regions (MiB)
+    #[arg(long, value_name = "MIB", default_value_t = 256)]
+    tile_cache: usize,
+    /// Refuse images with more pixels than this (width × height)
+    #[arg(long, value_name = "PIXELS")]
+    max_pixels: Option<u64>,
+    /// Refuse files with a tile larger than this, compressed or decompressed (KiB)
+    #[arg(long, value_name = "KIB")]
+    max_tile: Option<u64>,
+    /// Refuse to allocate more than this for decoding one request (MiB)
+    #[arg(long, value_name = "MIB")]
+    max_memory: Option<u64>,
+}
+
+#[derive(Args)]
+#[command(group = ArgGroup::new("change")
+    .args(["compression", "tile"])
+    .required(true)
+    .multiple(true))]
+pub struct TranscodeArgs {
+    /// Input .cti file
+    input: PathBuf,
+    /// Output .cti file
+    output: PathBuf,
+    /// Target codec with an optional level: `zstd:19`, `lz4`, `none`, `brotli:11`,
+    /// `deflate:9` or `dct:90` (quality); default: the codec of the input
+    #[arg(long, value_name = "CODEC[:LEVEL]", value_parser = parse_compression)]
+    compression: Option<Compression>,
+    /// Rewrite with another tile size in pixels (decodes and re-encodes the image), e.g.
+    /// the geometry an IIIF server prefers
+    #[arg(long, value_parser = clap::value_parser!(u32).range(16..=8192))]
+    tile: Option<u32>,
+}
+
+#[derive(Args)]
+pub struct LintArgs {
+    /// CTI files
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+    /// Fail on warnings too, not only on errors
+    #[arg(long)]
+    strict: bool,
+    /// Print a JSON report (issues per file with codes, severities and tile numbers)
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Args)]
+pub struct MetricsArgs {
+    /// Reference image (PNG, TIFF or CTI), usually the original before encoding
+    reference: PathBuf,
+    /// Tested image, usually the lossy .cti encode of the reference
+    test: PathBuf,
+    /// Frame of multi-frame CTI files
+    #[arg(long, default_value_t = 0)]
+    frame: u32,
+    /// Also compute MS-SSIM, a multi-scale (more perceptual) variant of SSIM
+    #[arg(long)]
+    ms_ssim: bool,
+    /// Add metrics for every tile (tile grid of the tested CTI file)
+    #[arg(long)]
+    tiles: bool,
+    /// Fail when PSNR of the whole image is below this many dB
+    #[arg(long, value_name = "DB")]
+    min_psnr: Option<f64>,
+    /// Fail when SSIM of the whole image is below this value
+    #[arg(long, value_name = "SSIM")]
+    min_ssim: Option<f64>,
+}
+
+#[derive(Args)]
+#[command(group = ArgGroup::new("edit").args(["crop", "rotate", "flip"]).required(true))]
+pub struct EditArgs {
+    /// Input .cti file
+    input: PathBuf,
+    /// Output .cti file
+    output: PathBuf,
+    /// Crop to a region given as WIDTHxHEIGHT+X+Y, e.g. `4096x3072+512+0`
+    #[arg(long, value_name = "WxH+X+Y", value_parser = parse_crop)]
+    crop: Option<(u32, u32, u32, u32)>,
+    /// Rotate clockwise by 90, 180 or 270 degrees
+    #[arg(long, value_name = "DEGREES", value_parser = ["90", "180", "270"])]
+    rotate: Option<String>,
+    /// Mirror the image
+    #[arg(long, value_enum)]
+    flip: Option<FlipArg>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum FlipArg {
+    /// Left to right
+    Horizontal,
+    /// Top to bottom
+    Vertical,
+}
+
+fn parse_crop(s: &str) -> Result<(u32, u32, u32, u32), String> {
+    let err = || format!("expected WIDTHxHEIGHT+X+Y, got {s:?}");
+    let (size, rest) = s.split_once('+').ok_or_else(err)?;
+    let (x, y) = rest.split_once('+').ok_or_else(err)?;
+    let (w, h) = size.split_once(['x', 'X']).ok_or_else(err)?;
+    let num = |v: &str| v.trim().parse::<u32>().map_err(|_| err());
+    Ok((num(x)?, num(y)?, num(w)?, num(h)?))
+}
+
+#[cfg(feature = "ocr")]
+#[derive(Args)]
+pub struct OcrArgs {
+    /// Input .cti file
+    input: PathBuf,
+    /// Frame of a multi-frame file (from 0)
+    #[arg(long, default_value_t = 0)]
+    frame: u32,
+    /// Recognize only a region given as WIDTHxHEIGHT+X+Y
+    #[arg(long, value_name = "WxH+X+Y", value_parser = parse_crop)]
+    region: Option<(u32, u32, u32, u32)>,
+    /// Tesseract language codes, e.g. `ces+eng`
+    #[arg(long, default_value = "eng")]
+    lang: String,
+    /// OCR command; `{image}` is the temporary PNG, `{lang}` the languages, and it must print
+    /// Tesseract TSV
+    #[arg(long, default_value = crate::ocr::DEFAULT_COMMAND)]
+    command: String,
+    /// Print only words containing TEXT with their position; fail when none is found
+    #[arg(long, value_name = "TEXT")]
+    search: Option<String>,
+}
+
+#[derive(Args)]
+pub struct GenTestdataArgs {
+    /// Output directory (created if missing; existing files of the corpus are replaced)
+    output: PathBuf,
+}
+
+/// Kodek a úroveň (u DCT kvalita) z `--compression`.
+#[derive(Clone, Copy)]
+struct Compression {
+    id: CompressionId,
+    level: i32,
+}
+
+fn parse_compression(s: &str) -> Result<Compression, String> {
+    let (name, level) = match s.split_once(':') {
+        Some((name, level)) => (name, Some(level)),
+        None => (s, None),
+    };
+    let id = match name.to_ascii_lowercase().as_str() {
+        "zstd" => CompressionId::Zstd,
+        "lz4" => CompressionId::Lz4,
+        "none" => CompressionId::None,
+        "brotli" => CompressionId::Brotli,
+        "deflate" => CompressionId::Deflate,
+        "dct" => CompressionId::Dct,
+        _ => return Err(format!("unknown codec {name:?}")),
+    };
+    let (range, default) = level_range(id);
+    let level = match level {
+        Some(_) if range == (0..=0) => return Err(format!("{name} has no level")),
+        Some(l) => l
+            .parse::<i32>()
+            .ok()
+            .filter(|l| range.contains(l))
+            .ok_or_else(|| format!("{name} level must be {}..={}", range.start(), range.end()))?,
+        None => default,
+    };
+    Ok(Compression { id, level })
+}
+
+/// Povolené úrovně kodeku (u DCT kvalita) a výchozí úroveň; `0..=0` = kodek bez úrovně.
+fn level_range(id: CompressionId) -> (std::ops::RangeInclusive<i32>, i32) {
+    match id {
+        CompressionId::Zstd => (1..=22, id.default_level()),
+        CompressionId::Brotli => (0..=11, id.default_level()),
+        CompressionId::Deflate => (0..=9, id.default_level()),
+        CompressionId::Dct => (1..=100, EncodeParams::default().quality.into()),
+        _ => (0..=0, 0),
+    }
+}
+
+#[derive(Args)]
+pub struct DziArgs {
+    /// Input .cti file
+    input: PathBuf,
+    /// Output directory for `<name>.dzi` and `<name>_files/`
+    output: PathBuf,
+    /// Tile size without overlap
+    #[arg(long, default_value_t = 254, value_parser = clap::value_parser!(u32).range(1..))]
+    tile_size: u32,
+    /// Pixels shared with neighbouring tiles
+    #[arg(long, default_value_t = 1)]
+    overlap: u32,
+    /// Tile format
+    #[arg(short, long, value_enum, default_value_t = TileFormatArg::Jpeg)]
+    format: TileFormatArg,
+    /// JPEG quality
+    #[arg(long, default_value_t = 90, value_parser = clap::value_parser!(u8).range(1..=100))]
+    quality: u8,
+    /// Frame of a multi-frame file (from 0)
+    #[arg(long, default_value_t = 0)]
+    frame: u32,
+    /// Replace an existing pyramid
+    #[arg(long)]
+    overwrite: bool,
+}
+
+#[derive(Args)]
+pub struct HtmlArgs {
+    /// Input .cti file
+    input: PathBuf,
+    /// Output directory for `<name>.html` and `<name>_files/`
+    output: PathBuf,
+    /// Longest side of the exported image; larger images are downsampled
+    #[arg(long, value_name = "PX", default_value_t = 8192, value_parser = clap::value_parser!(u32).range(1..))]
+    max_size: u32,
+    /// Put the tiles into the HTML file itself (one file to e-mail)
+    #[arg(long)]
+    single_file: bool,
+    /// Page title (default: file name)
+    #[arg(long)]
+    title: Option<String>,
+    /// Tile size without overlap
+    #[arg(long, default_value_t = 256, value_parser = clap::value_parser!(u32).range(1..))]
+    tile_size: u32,
+    /// Tile format
+    #[arg(short, long, value_enum, default_value_t = TileFormatArg::Jpeg)]
+    format: TileFormatArg,
+    /// JPEG quality
+    #[arg(long, default_value_t = 85, value_parser = clap::value_parser!(u8).range(1..=100))]
+    quality: u8,
+    /// Frame of a multi-frame file (from 0)
+    #[arg(long, default_value_t = 0)]
+    frame: u32,
+    /// Replace an existing package
+    #[arg(long)]
+    overwrite: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum TileFormatArg {
+    Jpeg,
+    Png,
+}
+
+impl From<TileFormatArg> for DziFormat {
+    fn from(format: TileFormatArg) -> Self {
+        match format {
+            TileFormatArg::Jpeg => DziFormat::Jpeg,
+            TileFormatArg::Png => DziFormat::Png,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum AreaArg {
+    /// Temporary files (exports for external tools, printing, clipboard)
+    Temp,
+    /// Disk cache
+    Cache,
+}
+
+impl AreaArg {
+    fn area(self) -> Area {
+        match self {
+            AreaArg::Temp => Area::Temp,
+            AreaArg::Cache => Area::Cache,
+        }
+    }
+}
+
+fn parse_roi(s: &str) -> Result<egui::Rect, String> {
+    let v: Vec<f32> = s
+        .split(',')
+        .map(|p| p.trim().parse::<f32>())
+        .collect::<Result<_, _>>()
+        .map_err(|e| format!("{s:?}: {e}"))?;
+    match v[..] {
+        [x, y, w, h] if w > 0.0 && h > 0.0 => Ok(egui::Rect::from_min_size(
+            egui::pos2(x, y),
+            egui::vec2(w, h),
+        )),
+        _ => Err(format!("expected X,Y,W,H, got {s:?}")),
+    }
+}
+
+impl Cli {
+    /// Běží bez okna: podpříkaz, `--check` nebo `--decode-to`.
+    pub fn is_headless(&self) -> bool {
+        self.command.is_some() || self.check.is_some() || self.decode_to.is_some()
+    }
+}
+
+/// Release build pro Windows je GUI aplikace bez vlastní konzole: výstup v terminálu
+/// (výsledky, `--help`, chyby argumentů) jde do konzole, ze které byl program spuštěn.
+/// Bez ní (spuštění z Průzkumníka) se nic nestane.
+#[cfg(windows)]
+pub fn attach_console() {
+    use windows_sys::Win32::System::Console::{ATTACH_PARENT_PROCESS, AttachConsole};
+    // SAFETY: volání bez ukazatelů; neúspěch (rodič nemá konzoli) je neškodný
+    unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+}
+
+#[cfg(not(windows))]
+pub fn attach_console() {}
+
+/// Spustí podpříkaz; `Ok(false)` = žádný podpříkaz, otevřít GUI.
+pub fn run(cli: Cli) -> Result<bool> {
+    if headless(&cli)? {
+        return Ok(true);
+    }
+    match cli.command {
+        None => Ok(false),
+        Some(Command::Convert(args)) => convert(args).map(|_| true),
+        Some(Command::Export(args)) => export(args).map(|_| true),
+        Some(Command::Meta(args)) => meta(args).map(|_| true),
+        Some(Command::Stitch(args)) => stitch(args).map(|_| true),
+        Some(Command::Sequence(args)) => sequence(args).map(|_| true),
+        Some(Command::Info(args)) => info(args).map(|_| true),
+        Some(Command::Verify(args)) => verify(args).map(|_| true),
+        Some(Command::Noise(args)) => noise(args).map(|_| true),
+        Some(Command::Mtf(args)) => mtf(args).map(|_| true),
+        Some(Command::Upload(args)) => upload(args).map(|_| true),
+        Some(Command::Cleanup(args)) => cleanup(args).map(|_| true),
+        Some(Command::Update(args)) => self_update(args).map(|_| true),
+        Some(Command::Serve(args)) => serve(args).map(|_| true),
+        Some(Command::Dzi(args)) => dzi(args).map(|_| true),
+        Some(Command::Html(args)) => html(args).map(|_| true),
+        Some(Command::Transcode(args)) => transcode(args).map(|_| true),
+        Some(Command::Edit(args)) => edit(args).map(|_| true),
+        Some(Command::Lint(args)) => lint(args).map(|_| true),
+        Some(Command::Metrics(args)) => metrics(args).map(|_| true),
+        #[cfg(feature = "ocr")]
+        Some(Command::Ocr(args)) => ocr(args).map(|_| true),
+        Some(Command::GenTestdata(args)) => gen_testdata(args).map(|_| true),
+    }
+}
+
+/// `--check` / `--decode-to`: jeden soubor bez okna, výsledek jen ve výstupu a návratovém
+/// kódu. `Ok(false)` = ani jeden přepínač, pokračuje se dál.
+fn headless(cli: &Cli) -> Result<bool> {
+    let src = match (&cli.check, &cli.open) {
+        (Some(src), _) => src.clone(),
+        (None, Some(open)) if cli.decode_to.is_some() => PathBuf::from(open),
+        _ => return Ok(false),
+    };
+    if cli.check.is_some() && cli.json {
+        let report = FileReport::build(&src, true, false)?;
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        ensure!(report.is_ok(), "{}: damaged tiles", src.display());
+    } else if cli.check.is_some() {
+        let msg = batch::verify_file(&src, false).with_context(|| src.display().to_string())?;
+        println!("{}: {msg}", src.display());
+    }
+    if let Some(dst) = &cli.decode_to {
+        use image::ImageFormat::{Jpeg, Png, Tiff};
+        let format = match image::ImageFormat::from_path(dst) {
+            Ok(f @ (Png | Tiff | Jpeg)) => f,
+            _ => bail!(
+                "{}: unsupported output format (PNG, TIFF or JPEG)",
+                dst.display()
+            ),
+        };
+        export::export_to(&src, dst, format)
+            .with_context(|| format!("decode {} to {}", src.display(), dst.display()))?;
+        println!("{} -> {}", src.display(), dst.display());
+    }
+    Ok(true)
+}
+
+fn convert(args: ConvertArgs) -> Result<()> {
+    let preset = args.encode.preset()?;
+    let params = args.encode.params(preset.as_ref());
+    let template = preset.map(|p| p.metadata).unwrap_or_default();
+    let extra = args.metadata()?;
+    let name = output_name(
+        args.barcode.pattern(&args.name.name),
+        args.name.on_collision,
+    )?;
+    let barcode = args.barcode.import(&name);
+    let files = convert::collect_files(&args.input, args.recursive, &convert::IMAGE_EXTENSIONS)?;
+    if files.is_empty() {
+        bail!("no PNG/TIFF files in {}", args.input.display());
+    }
+    if args.dry_run {
+        let mut plan = Plan::default();
+        for (i, src) in files.iter().enumerate() {
+            plan.file(src, |plan| {
+                let dir = convert::output_dir(src, &args.input, &args.output)?;
+                convert::plan_file(plan, src, &dir, &name, i + 1, &params, barcode.as_ref())
+            });
+        }
+        return plan.finish();
+    }
+    if args.estimate {
+        let e = convert::estimate_output(&files, &params)?;
+        println!(
+            "estimate: {} file(s) {}, {}",
+            files.len(),
+            params.compression.as_str(),
+            convert::estimate_summary(&e)
+        );
+        return Ok(());
+    }
+    if !args.no_space_check {
+        convert::check_space(&files, &args.output, &params)?;
+    }
+    run_parallel(&args.input, &files, args.jobs, |src, n| {
+        let dir = convert::output_dir(src, &args.input, &args.output)?;
+        let mut meta = presets::render_metadata(&template, src)?;
+        for (k, v) in &extra.entries {
+            meta.set(k, v.clone());
+        }
+        convert::convert_file(src, &dir, &name, n, &params, meta, barcode.as_ref(), &[])
+    })
+}
+
+fn export(args: ExportArgs) -> Result<()> {
+    let options = args.options()?;
+    options
+        .name
+        .check()
+        .with_context(|| format!("output name {:?}", options.name.pattern))?;
+    let files = convert::collect_files(&args.input, args.recursive, &convert::CTI_EXTENSIONS)?;
+    if files.is_empty() {
+        bail!("no .cti files in {}", args.input.display());
+    }
+    if args.dry_run {
+        let mut plan = Plan::default();
+        for (i, src) in files.iter().enumerate() {
+            plan.file(src, |plan| {
+                export::plan_export(plan, src, Some(&args.input), &args.output, i + 1, &options)
+            });
+        }
+        return plan.finish();
+    }
+    run_parallel(&args.input, &files, args.jobs, |src, n| {
+        export::export_into(src, Some(&args.input), &args.output, n, &options)
+    })
+}
+
+/// Bez změn vypíše metadata, jinak je přepíše na místě.
+fn meta(args: MetaArgs) -> Result<()> {
+    let xmp = match &args.xmp {
+        Some(path) => Some(
+            std::fs::read_to_string(path)
+                .with_context(|| format!("read XMP {}", path.display()))?,
+        ),
+        None => None,
+    };
+    let edit = !args.set.is_empty() || !args.unset.is_empty() || xmp.is_some();
+    let mut plan = (edit && args.dry_run).then(Plan::default);
+    for file in &args.files {
+        let mut meta = CTIDecoder::metadata(file).with_context(|| format!("{}", file.display()))?;
+        if !edit {
+            println!("{}:", file.display());
+            for (k, v) in &meta.entries {
+                println!("  {k} = {v}");
+            }
+            continue;
+        }
+        meta.entries.retain(|(k, _)| !args.unset.contains(k));
+        for (k, v) in &args.set {
+            meta.set(k, v.clone());
+        }
+        if let Some(xmp) = &xmp {
+            meta.set(CTIMetadata::XMP, xmp.clone());
+        }
+        if let Some(plan) = &mut plan {
+            plan.file(file, |plan| plan_meta(plan, file, &meta, &args));
+            continue;
+        }
+        cti::rewrite_metadata(file, &meta).with_context(|| format!("{}", file.display()))?;
+        println!("updated {}", file.display());
+    }
+    plan.map_or(Ok(()), Plan::finish)
+}
+
+/// Přepis metadat pro `--dry-run`: soubor se přepíše celý, mění se jen blok metadat.
+fn plan_meta(plan: &mut Plan, file: &Path, meta: &CTIMetadata, args: &MetaArgs) -> Result<()> {
+    let size = plan.read(file, "")?;
+    let block = |m: &CTIMetadata| -> Result<u64> {
+        Ok(if m.is_empty() {
+            0
+        } else {
+            m.to_bytes()?.len() as u64
+        })
+    };
+    let old = block(&CTIDecoder::metadata(file)?)?;
+    let mut what: Vec<String> = args.set.iter().map(|(k, _)| format!("set {k}")).collect();
+    what.extend(args.unset.iter().map(|k| format!("unset {k}")));
+    if args.xmp.is_some() {
+        what.push("set XMP".into());
+    }
+    plan.rewrite(file, size - old + block(meta)?, &what.join(", "));
+    Ok(())
+}
+
+fn stitch(args: StitchArgs) -> Result<()> {
+    let params = args.encode.params(args.encode.preset()?.as_ref());
+    if args.dry_run {
+        // velikost sešitého obrázku závisí na nalezeném překryvu
+        let mut plan = Plan::default();
+        for src in &args.inputs {
+            plan.file(src, |plan| {
+                let hdr = CTIDecoder::info(src)?;
+                plan.read(src, &format!("{}x{}", hdr.width, hdr.height))
+                    .map(|_| ())
+            });
+        }
+        let target = if args.output.exists() {
+            Planned::Overwrite(args.output.clone())
+        } else {
+            Planned::New(args.output.clone())
+        };
+        plan.write(&target, None);
+        return plan.finish();
+    }
+    let total = stitch::steps(args.inputs.len());
+    let mut n = 0;
+    let msg = stitch::stitch_files(&args.inputs, &args.output, &params, &mut |m| {
+        n += 1;
+        println!("[{n}/{total}] {m}");
+        true
+    })?;
+    println!("{msg}");
+    Ok(())
+}
+
+fn sequence(args: SequenceArgs) -> Result<()> {
+    let inputs = match &args.inputs[..] {
+        [dir] if dir.is_dir() => convert::collect_files(dir, false, &convert::IMAGE_EXTENSIONS)?,
+        _ => args.inputs.clone(),
+    };
+    let preset = args.encode.preset()?;
+    let params = args.encode.params(preset.as_ref());
+    // šablona metadat se vyplní podle výstupního souboru
+    let meta = match &preset {
+        Some(p) => presets::render_metadata(&p.metadata, &args.output)?,
+        None => CTIMetadata::default(),
+    };
+    let stem = args
+        .output
+        .file_stem()
+        .ok_or_else(|| anyhow!("{} is not a file name", args.output.display()))?
+        .to_string_lossy();
+    let name = output_name(args.barcode.pattern(&stem), args.on_collision)?;
+    let barcode = args.barcode.import(&name);
+    let dir = args.output.parent().unwrap_or(Path::new(""));
+    if args.dry_run {
+        let mut plan = Plan::default();
+        plan.file(&args.output, |plan| {
+            convert::plan_sequence(plan, &inputs, dir, &name, &params, barcode.as_ref())
+        });
+        return plan.finish();
+    }
+    if !args.no_space_check {
+        convert::check_space(&inputs, dir, &params)?;
+    }
+    let msg = convert::convert_sequence(&inputs, dir, &name, &params, meta, barcode.as_ref())?;
+    println!("{msg}");
+    Ok(())
+}
+
+fn verify(args: VerifyArgs) -> Result<()> {
+    let mut files = Vec::new();
+    for path in &args.paths {
+        if path.is_dir() {
+            files.extend(convert::collect_files(
+                path,
+                args.recursive,
+                &convert::CTI_EXTENSIONS,
+            )?);
+        } else {
+            files.push(path.clone());
+        }
+    }
+    if files.is_empty() {
+        bail!("no .cti files found");
+    }
+    if args.json {
+        return verify_json(&files, args.deep, args.jobs);
+    }
+    run_parallel(Path::new(""), &files, args.jobs, |src, _| {
+        batch::verify_file(src, args.deep)
+    })
+}
+
+/// `verify --json`: pole zpráv v pořadí souborů; nečitelný soubor má místo zprávy `error`.
+fn verify_json(files: &[PathBuf], deep: bool, jobs: Option<usize>) -> Result<()> {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(jobs.unwrap_or(0))
+        .build()?;
+    let reports: Vec<_> = pool.install(|| {
+        files
+            .par_iter()
+            .map(|src| match FileReport::build(src, true, deep) {
+                Ok(report) => ReportEntry::Report(report),
+                Err(e) => ReportEntry::Failed {
+                    path: src.clone(),
+                    error: format!("{e:#}"),
+                },
+            })
+            .collect()
+    });
+    println!("{}", serde_json::to_string_pretty(&reports)?);
+    let failed = reports.iter().filter(|r| !r.is_ok()).count();
+    ensure!(failed == 0, "{failed} of {} files failed", files.len());
+    Ok(())
+}
+
+fn info(args: InfoArgs) -> Result<()> {
+    let reports = args
+        .files
+        .iter()
+        .map(|f| FileReport::build(f, false, false).with_context(|| f.display().to_string()))
+        .collect::<Result<Vec<_>>>()?;
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&reports)?);
+        return Ok(());
+    }
+    for r in &reports {
+        let h = &r.header;
+        println!("{}:", r.path.display());
+        println!(
+            "  {} × {} px, color type {}, {} frame(s)",
+            h.width, h.height, h.color_type, h.frames
+        );
+        let codec = if h.compression == CompressionId::Dct.as_str() {
+            format!("{} quality {}", h.compression, h.quality)
+        } else {
+            h.compression.to_owned()
+        };
+        println!(
+            "  {} × {} tiles of {} px, {codec}, {}",
+            h.tiles_x, h.tiles_y, h.tile_size, h.checksum
+        );
+        let sizes = r.tiles.iter().map(|t| t.compressed_size);
+        let (min, max) = (sizes.clone().min(), sizes.max());
+        println!(
+            "  {} B on disk, {} B raw, ratio {:.2}, tile {}–{} B",
+            r.file_size,
+            r.raw_size,
+            r.ratio,
+            min.unwrap_or(0),
+            max.unwrap_or(0)
+        );
+    }
+    Ok(())
+}
+
+/// Soubor nad limitem (nebo bez ploché oblasti) se počítá jako chyba.
+fn noise(args: NoiseArgs) -> Result<()> {
+    run_parallel(Path::new(""), &args.files, args.jobs, |src, _| {
+        let (hdr, raw) = CTIDecoder::decode_file(src)?;
+        let map = noise::NoiseMap::compute(&hdr, &raw)?;
+        let (measured, over) = map.counts(args.threshold);
+        let Some(sigma) = map.global else {
+            bail!("no flat regions found");
+        };
+        let msg = format!(
+            "σ = {sigma:.2} levels, {measured}/{} tiles measured, {over} over {}",
+            map.sigma.len(),
+            args.threshold
+        );
+        if sigma > args.threshold {
+            bail!("{msg}");
+        }
+        Ok(msg)
+    })
+}
+
+fn mtf(args: MtfArgs) -> Result<()> {
+    run_parallel(Path::new(""), &args.files, None, |src, _| {
+        let (hdr, raw) = CTIDecoder::decode_file(src)?;
+        let m = match args.roi {
+            Some(roi) => Mtf::measure(&hdr, &raw, roi)?,
+            None => Mtf::detect(&hdr, &raw)?,
+        };
+        let fmt = |v: Option<f32>| v.map_or("—".into(), |v| format!("{v:.3}"));
+        Ok(format!(
+            "MTF50 {} cy/px, MTF10 {} cy/px, {:.1} % at Nyquist ({} edge {:+.1}° at {},{} {}×{})",
+            fmt(m.mtf50),
+            fmt(m.mtf10),
+            m.nyquist * 100.0,
+            if m.vertical { "vertical" } else { "horizontal" },
+            m.angle,
+            m.roi.min.x,
+            m.roi.min.y,
+            m.roi.width(),
+            m.roi.height()
+        ))
+    })
+}
+
+/// Nahrává po jednom souboru; jméno v repozitáři je cesta relativní ke vstupní složce.
+fn upload(args: UploadArgs) -> Result<()> {
+    let uploader = Mutex::new(Uploader::new(&args.config)?);
+    let (mut files, mut names) = (Vec::new(), Vec::new());
+    for path in &args.paths {
+        let found = if path.is_dir() {
+            convert::collect_files(path, args.recursive, &convert::CTI_EXTENSIONS)?
+        } else {
+            vec![path.clone()]
+        };
+        for file in found {
+            let rel = match path.is_dir() {
+                true => file.strip_prefix(path).unwrap_or(&file),
+                false => Path::new(file.file_name().unwrap_or_default()),
+            };
+            let name: Vec<_> = rel.iter().map(|c| c.to_string_lossy()).collect();
+            names.push(name.join("/"));
+            files.push(file);
+        }
+    }
+    if files.is_empty() {
+        bail!("no .cti files found");
+    }
+    run_parallel(Path::new(""), &files, Some(1), |src, n| {
+        let mut uploader = uploader.lock().unwrap();
+        uploader.upload(src, &names[n - 1], &mut |msg| {
+            eprintln!("  {}: {msg}", src.display())
+        })
+    })
+}
+
+/// Soubory mladší deseti minut zůstávají vždy (mohou se právě používat).
+fn cleanup(args: CleanupArgs) -> Result<()> {
+    let areas = match args.area {
+        Some(a) => vec![a.area()],
+        None => Area::ALL.to_vec(),
+    };
+    for area in areas {
+        let mut rule = area.default_retention();
+        rule.max_age_days = args.max_age.unwrap_or(rule.max_age_days);
+        rule.max_mb = args.max_size.unwrap_or(rule.max_mb);
+        let dir = area.path();
+        let pruned = workdirs::prune(&dir, rule, args.dry_run)?;
+        let summary = pruned.summary(args.dry_run);
+        println!("{} {}: {summary}", area.label(), dir.display());
+    }
+    Ok(())
+}
+
+/// Najde (a nainstaluje) novější vydání; podpis se ověřuje vždy.
+fn self_update(args: UpdateArgs) -> Result<()> {
+    let channel = args
+        .channel
+        .unwrap_or_else(|| update::DEFAULT_CHANNEL.to_owned());
+    let Some(release) = update::check(&channel)? else {
+        println!("up to date ({})", update::current_version());
+        return Ok(());
+    };
+    let current = update::current_version();
+    println!("{} available (this is {current})", release.version);
+    if !release.notes.is_empty() {
+        println!("{}", release.notes);
+    }
+    if args.install {
+        update::install(&channel, &release)?;
+        println!("installed {}, restart to use it", release.version);
+    }
+    Ok(())
+}
+
+fn serve(args: ServeArgs) -> Result<()> {
+    ensure!(
+        args.dir.is_dir(),
+        "{} is not a directory",
+        args.dir.display()
+    );
+    iiif::serve(iiif::ServeOptions {
+        root: args.dir,
+        listen: args.listen,
+        base_url: args.base_url,
+        threads: args.jobs.unwrap_or_else(rayon::current_num_threads),
+        cache_mb: args.cache,
+        tile_cache_mb: args.tile_cache,
+        limits: cti::DecodeOptions {
+            max_pixels: args.max_pixels,
+            max_tile_bytes: args.max_tile.map(|kib| kib << 10),
+            max_total_bytes: args.max_memory.map(|mib| mib << 20),
+        },
+    })
+}
+
+/// Zpráva `lint --json` o jednom souboru.
+#[derive(serde::Serialize)]
+struct LintReport {
+    path: PathBuf,
+    issues: Vec<Issue>,
+}
+
+fn lint(args: LintArgs) -> Result<()> {
+    let mut reports = Vec::new();
+    for path in args.files {
+        let issues = lint::lint_file(&path).with_context(|| path.display().to_string())?;
+        reports.push(LintReport { path, issues });
+    }
+    let count = |s| {
+        let issues = reports.iter().flat_map(|r| &r.issues);
+        issues.filter(|i| i.severity == s).count()
+    };
+    let (errors, warnings) = (count(Severity::Error), count(Severity::Warning));
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&reports)?);
+    } else {
+        for r in &reports {
+            if r.issues.is_empty() {
+                println!("{}: OK", r.path.display());
+                continue;
+            }
+            println!("{}:", r.path.display());
+            for i in &r.issues {
+                let kind = match i.severity {
+                    Severity::Error => "error",
+                    Severity::Warning => "warning",
+                };
+                let more = match i.tiles.len() {
+                    0 | 1 => String::new(),
+                    n => format!(" (and {} more tiles)", n - 1),
+                };
+                println!("  {} {kind}: {}{more}", i.code, i.message);
+            }
+        }
+    }
+    ensure!(errors == 0, "{errors} error(s), {warnings} warning(s)");
+    ensure!(!args.strict || warnings == 0, "{warnings} warning(s)");
+    Ok(())
+}
+
+/// JSON na výstup vypíše i při nesplněném limitu, ať je vidět, o kolik.
+fn metrics(args: MetricsArgs) -> Result<()> {
+    let (ha, a) = convert::load_image(&args.reference, args.frame)?;
+    let (hb, b) = convert::load_image(&args.test, args.frame)?;
+    let report = QualityReport::compute(
+        (args.reference, &ha, &a),
+        (args.test, &hb, &b),
+        args.ms_ssim,
+        args.tiles.then_some(hb.tile_size),
+    )?;
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    let m = report.metrics;
+    if let Some(min) = args.min_psnr {
+        ensure!(m.psnr >= min, "PSNR {:.2} dB is below {min} dB", m.psnr);
+    }
+    if let Some(min) = args.min_ssim {
+        ensure!(m.ssim >= min, "SSIM {:.4} is below {min}", m.ssim);
+    }
+    Ok(())
+}
+
+fn transcode(args: TranscodeArgs) -> Result<()> {
+    let from =
+        CTIDecoder::info(&args.input).with_context(|| format!("{}", args.input.display()))?;
+    // bez --compression zůstane kodek vstupu (úroveň se ze souboru vyčíst nedá, tak výchozí)
+    let Compression { id, level } = args.compression.unwrap_or_else(|| {
+        let id = CompressionId::from(from.compression);
+        let level = match id {
+            CompressionId::Dct => i32::from(from.quality),
+            _ => level_range(id).1,
+        };
+        Compression { id, level }
+    });
+    // u DCT je číslo za dvojtečkou kvalita, koeficienty se komprimují výchozí úrovní Zstd
+    let (level, quality) = if id.is_lossy() {
+        (EncodeParams::default().level, level as u8)
+    } else {
+        (level, 100)
+    };
+    let (hdr, reencoded) =
+        cti::transcode_file(&args.input, &args.output, id, level, quality, args.tile)
+            .with_context(|| format!("{}", args.input.display()))?;
+    let size = |p: &Path| std::fs::metadata(p).map(|m| m.len()).unwrap_or(0);
+    let how = if reencoded {
+        "pixels re-encoded"
+    } else {
+        "tiles recompressed"
+    };
+    println!(
+        "{} -> {}: {} → {}, tiles {} → {} px, {} → {} B ({})",
+        args.input.display(),
+        args.output.display(),
+        CompressionId::from(from.compression).as_str(),
+        CompressionId::from(hdr.compression).as_str(),
+        from.tile_size,
+        hdr.tile_size,
+        size(&args.input),
+        size(&args.output),
+        how
+    );
+    Ok(())
+}
+
+fn edit(args: EditArgs) -> Result<()> {
+    let edit = match (args.crop, args.rotate.as_deref(), args.flip) {
+        (Some((x, y, w, h)), ..) => cti::Edit::Crop(x, y, w, h),
+        (_, Some("90"), _) => cti::Edit::Rotate(1),
+        (_, Some("180"), _) => cti::Edit::Rotate(2),
+        (_, Some(_), _) => cti::Edit::Rotate(3),
+        (.., Some(FlipArg::Horizontal)) => cti::Edit::FlipHorizontal,
+        (.., Some(FlipArg::Vertical)) => cti::Edit::FlipVertical,
+        (None, None, None) => unreachable!("clap requires one edit"),
+    };
+    let (hdr, copied) = cti::edit_file(&args.input, &args.output, edit)
+        .with_context(|| format!("{}", args.input.display()))?;
+    println!(
+        "{} -> {}: {} × {} px, {} of {} tile(s) copied without re-encoding",
+        args.input.display(),
+        args.output.display(),
+        hdr.width,
+        hdr.height,
+        copied,
+        hdr.tiles_per_frame() * hdr.frames as usize
+    );
+    Ok(())
+}
+
+#[cfg(feature = "ocr")]
+fn ocr(args: OcrArgs) -> Result<()> {
+    let settings = crate::ocr::OcrSettings {
+        command: args.command,
+        language: args.lang,
+    };
+    let result = crate::ocr::recognize(&args.input, args.frame, args.region, &settings)
+        .with_context(|| format!("{}", args.input.display()))?;
+    let Some(query) = &args.search else {
+        println!("{}", result.text());
+        return Ok(());
+    };
+    let mut found = false;
+    for w in result.matches(query) {
+        let [x, y, width, height] = w.rect;
+        println!("{width}x{height}+{x}+{y}\t{:.0}\t{}", w.confidence, w.text);
+        found = true;
+    }
+    ensure!(found, "{query:?} not found");
+    Ok(())
+}
+
+fn gen_testdata(args: GenTestdataArgs) -> Result<()> {
+    let cases = testdata::generate(&args.output)?;
+    let broken = cases.iter().filter(|c| c.defect.is_some()).count();
+    println!(
+        "{} file(s) ({} valid, {broken} broken) -> {}",
+        cases.len(),
+        cases.len() - broken,
+        args.output.join("manifest.json").display()
+    );
+    Ok(())
+}
+
+fn dzi(args: DziArgs) -> Result<()> {
+    let opts = DziOptions {
+        tile_size: args.tile_size,
+        overlap: args.overlap,
+        format: args.format.into(),
+        jpeg_quality: args.quality,
+        frame: args.frame,
+        overwrite: args.overwrite,
+    };
+    std::fs::create_dir_all(&args.output)
+        .with_context(|| format!("create {}", args.output.display()))?;
+    let manifest = dzi::export_dzi(&args.input, &args.output, &opts)?;
+    println!("{}", manifest.display());
+    Ok(())
+}
+
+fn html(args: HtmlArgs) -> Result<()> {
+    let defaults = HtmlOptions::default();
+    let opts = HtmlOptions {
+        max_size: args.max_size,
+        single_file: args.single_file,
+        title: args.title,
+        tiles: DziOptions {
+            tile_size: args.tile_size,
+            format: args.format.into(),
+            jpeg_quality: args.quality,
+            frame: args.frame,
+            overwrite: args.overwrite,
+            ..defaults.tiles
+        },
+    };
+    std::fs::create_dir_all(&args.output)
+        .with_context(|| format!("create {}", args.output.display()))?;
+    let page = portable::export_html(&args.input, &args.output, &opts)?;
+    println!("{}", page.display());
+    Ok(())
+}
+
+/// Zpracuje soubory paralelně a průběžně vypisuje výsledek; chyba, pokud některý selhal.
+/// `job` dostane cestu a pořadí souboru (od 1).
+fn run_parallel(
+    root: &Path,
+    files: &[PathBuf],
+    jobs: Option<usize>,
+    job: impl Fn(&Path, usize) -> Result<String> + Sync,
+) -> Result<()> {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(jobs.unwrap_or(0))
+        .build()?;
+
+    let total = files.len();
+    let (done, failed) = (AtomicUsize::new(0), AtomicUsize::new(0));
+    pool.install(|| {
+        files.par_iter().enumerate().for_each(|(i, src)| {
+            let rel = src.strip_prefix(root).unwrap_or(src);
+            let res = job(src, i + 1);
+            let n = done.fetch_add(1, Ordering::Relaxed) + 1;
+            match res {
+                Ok(msg) => println!("[{n}/{total}] OK   {}: {msg}", rel.display()),
+                Err(e) => {
+                    failed.fetch_add(1, Ordering::Relaxed);
+                    eprintln!("[{n}/{total}] FAIL {}: {e:#}", rel.display());
+                }
+            }
+        });
+    });
+
+    let failed = failed.into_inner();
+    if failed > 0 {
+        bail!("{failed} of {total} files failed");
+    }
+    println!("processed {total} file(s)");
+    Ok(())
+}
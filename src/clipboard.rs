@@ -0,0 +1,97 @@
+use anyhow::{Result, anyhow, ensure};
+use eframe::egui::{self, Rect};
+use std::borrow::Cow;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::cti::{CTIEncoder, CTIHeader, EncodeParams};
+use crate::export;
+use crate::workdirs::Area;
+
+// Na X11/Waylandu obsah schránky patří procesu: se zrušením poslední instance
+// by zkopírovaný obrázek zmizel, proto jednu držíme po celou dobu běhu.
+static CLIPBOARD: Mutex<Option<arboard::Clipboard>> = Mutex::new(None);
+
+fn with_clipboard<T>(f: impl FnOnce(&mut arboard::Clipboard) -> Result<T>) -> Result<T> {
+    let mut guard = CLIPBOARD
+        .lock()
+        .map_err(|_| anyhow!("clipboard lock poisoned"))?;
+    if guard.is_none() {
+        *guard = Some(arboard::Clipboard::new().map_err(|e| anyhow!("clipboard: {e}"))?);
+    }
+    f(guard.as_mut().expect("clipboard initialized above"))
+}
+
+/// Vezme obrázek ze schránky a zakóduje ho do dočasného .cti souboru.
+pub fn paste_to_cti() -> Result<PathBuf> {
+    let img = with_clipboard(|cb| {
+        cb.get_image()
+            .map_err(|e| anyhow!("no image in clipboard: {e}"))
+    })?;
+    let (w, h) = (img.width as u32, img.height as u32);
+
+    // plně neprůhledné screenshoty ukládáme jako RGB8 (s RCT)
+    let opaque = img.bytes.chunks_exact(4).all(|p| p[3] == 255);
+    let (color_type, data) = if opaque {
+        let rgb: Vec<u8> = img
+            .bytes
+            .chunks_exact(4)
+            .flat_map(|p| [p[0], p[1], p[2]])
+            .collect();
+        (3, rgb)
+    } else {
+        (4, img.bytes.into_owned())
+    };
+
+    let dir = Area::Temp.dir()?;
+    let stamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+    let path = dir.join(format!("clipboard-{stamp}.cti"));
+    CTIEncoder::encode_file(&path, w, h, color_type, &data, &EncodeParams::default())?;
+    Ok(path)
+}
+
+/// Zkopíruje obrázek (nebo oblast `area` v pixelech) do schránky jako bitmapu,
+/// otočený stejně jako v náhledu. Vrací rozměr zkopírovaného obrázku.
+pub fn copy_image(
+    hdr: &CTIHeader,
+    raw: &[u8],
+    area: Option<Rect>,
+    rotation: u8,
+) -> Result<(u32, u32)> {
+    let mut img = export::tone_mapped(export::to_dynamic_image(hdr, raw.to_vec())?);
+    if let Some(area) = area {
+        let area = area.intersect(Rect::from_min_size(
+            egui::Pos2::ZERO,
+            egui::vec2(hdr.width as f32, hdr.height as f32),
+        ));
+        ensure!(
+            area.width() >= 1.0 && area.height() >= 1.0,
+            "empty selection"
+        );
+        img = img.crop_imm(
+            area.min.x as u32,
+            area.min.y as u32,
+            area.width() as u32,
+            area.height() as u32,
+        );
+    }
+    let img = match rotation % 4 {
+        1 => img.rotate90(),
+        2 => img.rotate180(),
+        3 => img.rotate270(),
+        _ => img,
+    }
+    .into_rgba8();
+    let (w, h) = img.dimensions();
+    let data = arboard::ImageData {
+        width: w as usize,
+        height: h as usize,
+        bytes: Cow::Owned(img.into_raw()),
+    };
+    with_clipboard(|cb| {
+        cb.set_image(data)
+            .map_err(|e| anyhow!("copy to clipboard: {e}"))
+    })?;
+    Ok((w, h))
+}
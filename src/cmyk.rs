@@ -0,0 +1,74 @@
+//! Zobrazení CMYK8 (tiskové předlohy): převod na RGB naivním vzorcem, nebo přes ICC profil
+//! tisku (soft-proof). Převod je nastavení celého procesu – dekódovaná data zůstávají v CMYK
+//! a převádí se až při zobrazení a exportu.
+
+use anyhow::{Context, Result, anyhow};
+use moxcms::{ColorProfile, Layout, Transform8BitExecutor, TransformOptions};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+/// Převod přes ICC profil tisku do sRGB.
+struct IccTransform {
+    icc: PathBuf,
+    transform: Arc<Transform8BitExecutor>,
+}
+
+/// `None` = naivní převod.
+static PROFILE: RwLock<Option<IccTransform>> = RwLock::new(None);
+
+/// Nastaví převod pro zobrazení: `Some` = ICC profil tisku (CMYK), `None` = naivní vzorec.
+/// Při chybě zůstane naivní převod.
+pub fn set_profile(icc: Option<&Path>) -> Result<()> {
+    let transform = icc.map(load).transpose();
+    let mut profile = PROFILE.write().unwrap();
+    match transform {
+        Ok(t) => {
+            *profile = t;
+            Ok(())
+        }
+        Err(e) => {
+            *profile = None;
+            Err(e)
+        }
+    }
+}
+
+fn load(icc: &Path) -> Result<IccTransform> {
+    let data = std::fs::read(icc).with_context(|| format!("read {}", icc.display()))?;
+    let src =
+        ColorProfile::new_from_slice(&data).map_err(|e| anyhow!("{}: {e:?}", icc.display()))?;
+    // CMYK8 má v moxcms stejné uspořádání jako RGBA8 (4 kanály)
+    let transform = src
+        .create_transform_8bit(
+            Layout::Rgba,
+            &ColorProfile::new_srgb(),
+            Layout::Rgb,
+            TransformOptions::default(),
+        )
+        .map_err(|e| anyhow!("{}: {e:?}", icc.display()))?;
+    Ok(IccTransform {
+        icc: icc.to_path_buf(),
+        transform,
+    })
+}
+
+/// CMYK8 (0 = bez barvy) → RGB8 podle nastaveného převodu.
+pub fn to_rgb(cmyk: &[u8]) -> Vec<u8> {
+    let mut rgb = vec![0u8; cmyk.len() / 4 * 3];
+    if let Some(p) = PROFILE.read().unwrap().as_ref() {
+        match p.transform.transform(cmyk, &mut rgb) {
+            Ok(()) => return rgb,
+            Err(e) => tracing::error!("CMYK profile {}: {e:?}", p.icc.display()),
+        }
+    }
+    for (src, dst) in cmyk.chunks_exact(4).zip(rgb.chunks_exact_mut(3)) {
+        dst.copy_from_slice(&naive(src[0], src[1], src[2], src[3]));
+    }
+    rgb
+}
+
+/// R = (1 − C)(1 − K) atd. – bez ohledu na tiskové barvy, jen pro orientační náhled.
+fn naive(c: u8, m: u8, y: u8, k: u8) -> [u8; 3] {
+    let white = 255 - u32::from(k);
+    [c, m, y].map(|v| ((255 - u32::from(v)) * white / 255) as u8)
+}
@@ -0,0 +1,254 @@
+use anyhow::Result;
+use eframe::egui::{self, Pos2, TextureHandle, TextureOptions};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::align::{self, Alignment};
+use crate::annotations::Annotations;
+use crate::cti::CTIHeader;
+use crate::diff::{self, DiffView, Metrics};
+
+/// Druhý obrázek načtený pro porovnání vedle sebe.
+pub struct CompareImage {
+    pub path: PathBuf,
+    pub hdr: CTIHeader,
+    pub raw: Arc<Vec<u8>>,
+    pub tex: TextureHandle,
+
+    // rozdíl vůči hlavnímu obrázku
+    pub metrics: Result<Metrics, String>,
+    pub diff_view: DiffView,
+    pub gain: f32,
+    diff_tex: Option<TextureHandle>,
+
+    // anotace druhé verze a jejich porovnání s hlavním obrázkem
+    pub annotations: Annotations,
+    pub annotation_diff: bool,
+
+    // zarovnání na hlavní obrázek; `hdr`/`raw` pak drží převzorkovanou kopii
+    pub alignment: Option<Alignment>,
+    original: Option<(CTIHeader, Arc<Vec<u8>>)>,
+
+    // blikání: oba obrázky na celé ploše, mezerník přepíná, který je vidět
+    pub blink: bool,
+    pub showing_second: bool,
+}
+
+/// Požadavek z ovládacích prvků porovnání, který vyřeší aplikace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareCmd {
+    /// Přepočítat texturu rozdílu.
+    Refresh,
+    Align,
+    ResetAlign,
+    /// Uložit zprávu o kvalitě (JSON) s metrikami po dlaždicích.
+    Report,
+}
+
+impl CompareImage {
+    pub fn new(
+        path: PathBuf,
+        hdr: CTIHeader,
+        raw: Arc<Vec<u8>>,
+        tex: TextureHandle,
+        base: (&CTIHeader, &[u8]),
+    ) -> Self {
+        let metrics = diff::metrics(base.0, base.1, &hdr, &raw).map_err(|e| e.to_string());
+        let annotations = Annotations::load(&path).unwrap_or_else(|e| {
+            tracing::error!("annotations error: {e:?}");
+            Annotations::default()
+        });
+        Self {
+            path,
+            hdr,
+            raw,
+            tex,
+            metrics,
+            diff_view: DiffView::Off,
+            gain: 10.0,
+            diff_tex: None,
+            annotation_diff: !annotations.items.is_empty(),
+            annotations,
+            alignment: None,
+            original: None,
+            blink: false,
+            showing_second: false,
+        }
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.hdr.width, self.hdr.height)
+    }
+
+    pub fn name(&self) -> String {
+        self.path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// Textura pravé poloviny: druhý obrázek, nebo jeho rozdíl vůči hlavnímu.
+    pub fn right_tex(&self) -> &TextureHandle {
+        match (&self.diff_view, &self.diff_tex) {
+            (DiffView::Off, _) | (_, None) => &self.tex,
+            (_, Some(t)) => t,
+        }
+    }
+
+    /// Po změně hlavního obrázku přepočítá metriky i rozdíl; zarovnání se zahodí.
+    pub fn rebase(
+        &mut self,
+        ctx: &egui::Context,
+        base: (&CTIHeader, &[u8]),
+        options: TextureOptions,
+    ) -> Result<()> {
+        self.reset_alignment(base);
+        self.update_diff(ctx, base, options)
+    }
+
+    /// Odhadne posun/natočení vůči hlavnímu obrázku a převzorkuje druhý obrázek do jeho
+    /// geometrie. Texturu je pak třeba znovu nahrát.
+    pub fn align(&mut self, base: (&CTIHeader, &[u8])) -> Result<Alignment> {
+        let (hdr, raw) = self
+            .original
+            .clone()
+            .unwrap_or((self.hdr, self.raw.clone()));
+        let al = align::estimate(base.0, base.1, &hdr, &raw)?;
+        let warped = align::warp(base.0, &hdr, &raw, &al)?;
+        self.original = Some((hdr, raw));
+        self.hdr = CTIHeader {
+            width: base.0.width,
+            height: base.0.height,
+            ..hdr
+        };
+        self.raw = Arc::new(warped);
+        self.alignment = Some(al);
+        self.recompute_metrics(base);
+        Ok(al)
+    }
+
+    /// Vrátí původní (nezarovnaný) druhý obrázek.
+    pub fn reset_alignment(&mut self, base: (&CTIHeader, &[u8])) {
+        if let Some((hdr, raw)) = self.original.take() {
+            self.hdr = hdr;
+            self.raw = raw;
+        }
+        self.alignment = None;
+        self.recompute_metrics(base);
+    }
+
+    /// Bod v souřadnicích původního druhého obrázku → souřadnice zobrazeného (zarovnaného).
+    pub fn to_display(&self, p: Pos2) -> Pos2 {
+        self.alignment.map_or(p, |al| al.b_to_a(p))
+    }
+
+    fn recompute_metrics(&mut self, base: (&CTIHeader, &[u8])) {
+        self.metrics =
+            diff::metrics(base.0, base.1, &self.hdr, &self.raw).map_err(|e| e.to_string());
+        if self.metrics.is_err() {
+            self.diff_view = DiffView::Off;
+        }
+    }
+
+    /// Přepočítá texturu rozdílu po změně režimu/zesílení.
+    pub fn update_diff(
+        &mut self,
+        ctx: &egui::Context,
+        base: (&CTIHeader, &[u8]),
+        options: TextureOptions,
+    ) -> Result<()> {
+        self.diff_tex = None;
+        if self.diff_view != DiffView::Off {
+            let img = diff::diff_image(
+                base.0,
+                base.1,
+                &self.hdr,
+                &self.raw,
+                self.diff_view,
+                self.gain,
+            )?;
+            self.diff_tex = Some(ctx.load_texture("cti-diff", img, options));
+        }
+        Ok(())
+    }
+
+    /// Ovládání rozdílu, zarovnání a metriky (do toolbaru).
+    pub fn controls_ui(&mut self, ui: &mut egui::Ui) -> Option<CompareCmd> {
+        let mut cmd = None;
+        match self.alignment {
+            None => {
+                if ui
+                    .button("Align")
+                    .on_hover_text("Estimate shift and rotation against the main image and warp this one onto it")
+                    .clicked()
+                {
+                    cmd = Some(CompareCmd::Align);
+                }
+            }
+            Some(al) => {
+                if ui.button("Undo align").clicked() {
+                    cmd = Some(CompareCmd::ResetAlign);
+                }
+                ui.label(format!(
+                    "Δ {:.1}, {:.1} px · {:.1}°",
+                    al.shift.x, al.shift.y, al.angle
+                ))
+                .on_hover_text(format!("Correlation peak {:.3}", al.score));
+            }
+        }
+        let before = (self.diff_view, self.gain);
+        let comparable = self.metrics.is_ok();
+        ui.add_enabled_ui(comparable, |ui| {
+            egui::ComboBox::from_id_salt("diff-view")
+                .selected_text(self.diff_view.label())
+                .show_ui(ui, |ui| {
+                    for v in DiffView::ALL {
+                        ui.selectable_value(&mut self.diff_view, v, v.label());
+                    }
+                });
+            if self.diff_view == DiffView::Amplified {
+                ui.add(
+                    egui::DragValue::new(&mut self.gain)
+                        .range(1.0..=256.0)
+                        .prefix("×"),
+                );
+            }
+        });
+        match &self.metrics {
+            Ok(m) => {
+                let psnr = if m.psnr.is_infinite() {
+                    "∞".to_string()
+                } else {
+                    format!("{:.2}", m.psnr)
+                };
+                ui.label(format!("PSNR {psnr} dB · SSIM {:.4}", m.ssim))
+                    .on_hover_text(format!(
+                        "max |Δ| = {}\n{} of {} pixels differ",
+                        m.max_abs, m.differing_pixels, m.total_pixels
+                    ));
+            }
+            Err(e) => {
+                ui.colored_label(ui.visuals().warn_fg_color, format!("Not comparable: {e}"));
+            }
+        }
+        if ui
+            .add_enabled(comparable, egui::Button::new("Report…"))
+            .on_hover_text(
+                "Save PSNR, SSIM and MS-SSIM of the whole image and of every tile as JSON",
+            )
+            .clicked()
+        {
+            cmd = Some(CompareCmd::Report);
+        }
+        ui.checkbox(&mut self.blink, "Blink").on_hover_text(
+            "Show both images in one pane with the same zoom and switch between them with Space",
+        );
+        ui.checkbox(&mut self.annotation_diff, "Annotation diff")
+            .on_hover_text("Compare annotations of both versions (added / removed / moved)");
+        if before != (self.diff_view, self.gain) {
+            cmd = cmd.or(Some(CompareCmd::Refresh));
+        }
+        cmd
+    }
+}
@@ -0,0 +1,1037 @@
+use anyhow::{Context, Result, anyhow, bail, ensure};
+use eframe::egui;
+use image::ImageDecoder;
+use rfd::FileDialog;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use crate::a11y;
+use crate::barcode::{self, BarcodeImport};
+use crate::batch::BatchAction;
+use crate::cti::{
+    CTIDecoder, CTIEncoder, CTIHeader, CTIMetadata, CompressionId, EncodeParams, Estimate,
+};
+use crate::diskspace;
+use crate::dryrun::{self, Plan};
+use crate::export::{self, BitDepth, ExportFormat, ExportOptions};
+use crate::lens::{LensCorrection, LensProfile};
+use crate::metaform::{self, FormField};
+use crate::naming::{self, Collision, NameVars, OutputName};
+use crate::presets::{self, EncodeSettings, Preset};
+
+/// Přípony vstupů pro převod do CTI.
+pub const IMAGE_EXTENSIONS: [&str; 3] = ["png", "tif", "tiff"];
+pub const CTI_EXTENSIONS: [&str; 1] = ["cti"];
+
+/// Najde soubory s danými příponami v `dir` (volitelně i v podadresářích), seřazené podle cesty.
+pub fn collect_files(dir: &Path, recursive: bool, extensions: &[&str]) -> Result<Vec<PathBuf>> {
+    let matches = |path: &Path| {
+        path.extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| extensions.iter().any(|x| e.eq_ignore_ascii_case(x)))
+    };
+    let mut out = Vec::new();
+    let mut stack = vec![dir.to_path_buf()];
+    while let Some(d) = stack.pop() {
+        for entry in std::fs::read_dir(&d).with_context(|| format!("read dir {:?}", d))? {
+            let path = entry?.path();
+            if path.is_dir() {
+                if recursive {
+                    stack.push(path);
+                }
+            } else if matches(&path) {
+                out.push(path);
+            }
+        }
+    }
+    out.sort();
+    Ok(out)
+}
+
+/// Cílová složka: relativní umístění vůči `in_root` se zachová.
+pub fn output_dir(src: &Path, in_root: &Path, out_root: &Path) -> Result<PathBuf> {
+    let rel = src
+        .strip_prefix(in_root)
+        .map_err(|_| anyhow!("{:?} is not inside {:?}", src, in_root))?;
+    Ok(out_root.join(rel.parent().unwrap_or(Path::new(""))))
+}
+
+/// Převede jeden PNG/TIFF do CTI (s volitelným blokem metadat) do `dir` pod jménem ze
+/// šablony `name`; `n` = pořadí souboru v dávce. Identifikátor z čárového nebo QR kódu
+/// se doplní do metadat dřív, než se zkontrolují pole formuláře `form`.
+#[allow(clippy::too_many_arguments)]
+pub fn convert_file(
+    src: &Path,
+    dir: &Path,
+    name: &OutputName,
+    n: usize,
+    params: &EncodeParams,
+    mut meta: CTIMetadata,
+    barcode: Option<&BarcodeImport>,
+    form: &[FormField],
+) -> Result<String> {
+    let img = image::open(src).with_context(|| format!("load {:?}", src))?;
+    let (w, h) = (img.width(), img.height());
+    let (id, code) = match barcode {
+        Some(b) => {
+            let (id, code) = read_barcode(&img, &mut meta, b)?;
+            (Some(id), format!("{code}, "))
+        }
+        None => (None, String::new()),
+    };
+    keep_source_dpi(src, &mut meta);
+    metaform::validate(form, &meta)?;
+    let (color_type, raw) = source_raw(src, img, params)?;
+    let vars = NameVars {
+        n: Some(n),
+        page: Some(1),
+        barcode: id.as_deref(),
+        size: Some((w, h)),
+        depth: Some(export::bit_depth(color_type)),
+        ..NameVars::new(src)
+    };
+    let dst = name.write(dir, "cti", &vars, |dst| {
+        if dst.exists() && std::fs::canonicalize(dst)? == std::fs::canonicalize(src)? {
+            bail!("output would overwrite the source");
+        }
+        CTIEncoder::encode_file_with_metadata(dst, w, h, color_type, &raw, params, &meta)?;
+        Ok(())
+    })?;
+    let Some(dst) = dst else {
+        return Ok(format!("{code}skipped, output exists"));
+    };
+    let before = std::fs::metadata(src)?.len();
+    let after = std::fs::metadata(&dst)?.len();
+    Ok(format!(
+        "{code}{w}x{h}, {before} → {after} B → {}",
+        dst.display()
+    ))
+}
+
+/// Najde kód na snímku a zapíše identifikátor do metadat; vrátí identifikátor
+/// a popis nalezeného kódu.
+fn read_barcode(
+    img: &image::DynamicImage,
+    meta: &mut CTIMetadata,
+    barcode: &BarcodeImport,
+) -> Result<(String, String)> {
+    let code = barcode::detect(&img.to_luma8())
+        .filter(|c| !c.text.is_empty())
+        .ok_or_else(|| anyhow!("no barcode or QR code found"))?;
+    meta.set(&barcode.key, code.text.clone());
+    let desc = format!("{} {:?}", code.symbology.as_str(), code.text);
+    Ok((code.text, desc))
+}
+
+/// Zdrojový obrázek → (ColorType ID, RAW). Při sbalené šedi ([`EncodeParams::gray_bits`]) se
+/// nejdřív převede na L8, i když je barevný. CMYK TIFF zůstane v CMYK (CMYK8), `img` je pak
+/// jen jeho RGB podoba pro čtení kódu.
+fn source_raw(
+    src: &Path,
+    img: image::DynamicImage,
+    params: &EncodeParams,
+) -> Result<(u8, Vec<u8>)> {
+    if params.gray_bits < 8 {
+        return Ok((1, img.into_luma8().into_raw()));
+    }
+    if let Some(raw) = cmyk_tiff(src).with_context(|| format!("load {:?}", src))? {
+        return Ok((8, raw));
+    }
+    export::from_dynamic_image(img)
+}
+
+/// Obrázek jiného formátu jako RAW – stejně jako při převodu s výchozími parametry, takže
+/// jde porovnat s výsledným CTI. Hlavička popisuje nekomprimovaný obrázek o jedné dlaždici.
+pub fn load_raw(src: &Path) -> Result<(CTIHeader, Vec<u8>)> {
+    let img = image::open(src).with_context(|| format!("load {:?}", src))?;
+    let (width, height) = (img.width(), img.height());
+    let (color_type, raw) = source_raw(src, img, &EncodeParams::default())?;
+    let hdr = CTIHeader {
+        magic: *b"CTI1",
+        version: 1,
+        flags: 0,
+        width,
+        height,
+        tile_size: width.max(height),
+        tiles_x: 1,
+        tiles_y: 1,
+        color_type,
+        compression: CompressionId::None.id(),
+        quality: 100,
+        frames: 1,
+        chunks_len: 0,
+    };
+    Ok((hdr, raw))
+}
+
+/// Snímek CTI nebo obrázek jiného formátu ([`load_raw`]) podle přípony.
+pub fn load_image(src: &Path, frame: u32) -> Result<(CTIHeader, Vec<u8>)> {
+    let ext = src.extension().and_then(|e| e.to_str()).unwrap_or_default();
+    if CTI_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()) {
+        CTIDecoder::decode_frame(src, frame).with_context(|| format!("decode {:?}", src))
+    } else {
+        load_raw(src)
+    }
+}
+
+/// Data CMYK TIFFu (8 bitů na kanál); `None`, když soubor není CMYK TIFF.
+fn cmyk_tiff(src: &Path) -> Result<Option<Vec<u8>>> {
+    use tiff::decoder::{Decoder, DecodingResult};
+    let ext = src.extension().and_then(|e| e.to_str()).unwrap_or_default();
+    if !matches!(ext.to_ascii_lowercase().as_str(), "tif" | "tiff") {
+        return Ok(None);
+    }
+    let mut decoder = Decoder::new(std::io::BufReader::new(File::open(src)?))?;
+    if decoder.colortype()? != tiff::ColorType::CMYK(8) {
+        return Ok(None);
+    }
+    match decoder.read_image()? {
+        DecodingResult::U8(raw) => Ok(Some(raw)),
+        _ => bail!("unexpected CMYK sample format"),
+    }
+}
+
+/// Rozměr, typ barev a bitů na kanál obrázku (jen z hlavičky, bez dekódování).
+fn image_info(src: &Path) -> Result<(u32, u32, image::ColorType, u8)> {
+    let decoder = image::ImageReader::open(src)
+        .and_then(|r| r.with_guessed_format())
+        .with_context(|| format!("load {:?}", src))?
+        .into_decoder()
+        .with_context(|| format!("load {:?}", src))?;
+    let (w, h) = decoder.dimensions();
+    let color = decoder.color_type();
+    let bits = color.bytes_per_pixel() / color.channel_count() * 8;
+    Ok((w, h, color, bits))
+}
+
+/// Kolik souborů dávky a kolik dlaždic v každém z nich se zkomprimuje pro odhad výstupu.
+const SAMPLE_FILES: usize = 4;
+const SAMPLE_TILES: usize = 16;
+
+/// Odhad převodu `files`: u několika rovnoměrně vybraných souborů se zkomprimuje vzorek
+/// dlaždic a zjištěný poměr se použije na nekomprimovanou velikost všech souborů (ta se
+/// čte jen z hlaviček). PSNR ztrátového kodeku je ze vzorku.
+pub fn estimate_output(files: &[PathBuf], params: &EncodeParams) -> Result<Estimate> {
+    let mut sizes = Vec::with_capacity(files.len());
+    for src in files {
+        let (w, h, color, _) = image_info(src)?;
+        sizes.push(w as u64 * h as u64 * color.bytes_per_pixel() as u64);
+    }
+    let step = files.len().div_ceil(SAMPLE_FILES).max(1);
+    let mut sampled = Estimate::default();
+    for (src, size) in files.iter().zip(&sizes).step_by(step) {
+        let img = image::open(src).with_context(|| format!("load {:?}", src))?;
+        let (w, h) = (img.width(), img.height());
+        let (color_type, data) = source_raw(src, img, params)?;
+        let e = CTIEncoder::estimate(w, h, color_type, &data, params, SAMPLE_TILES)?;
+        sampled.add(&Estimate { raw: *size, ..e });
+    }
+    let total: u64 = sizes.iter().sum();
+    let size = if sampled.raw == 0 {
+        total
+    } else {
+        (total as f64 * sampled.size as f64 / sampled.raw as f64) as u64
+    };
+    Ok(Estimate {
+        raw: total,
+        size,
+        ..sampled
+    })
+}
+
+/// Odhad pro lidi: velikost výstupu, poměr komprese a u ztrátového kodeku PSNR.
+pub fn estimate_summary(e: &Estimate) -> String {
+    let ratio = e.raw as f64 / e.size.max(1) as f64;
+    let quality = match e.psnr() {
+        None => "lossless".into(),
+        Some(p) if p.is_infinite() => "PSNR ∞ (sample unchanged)".into(),
+        Some(p) => format!("PSNR ≈ {p:.1} dB"),
+    };
+    format!(
+        "≈ {} from {} raw (ratio {ratio:.2}), {quality}",
+        dryrun::human(e.size),
+        dryrun::human(e.raw)
+    )
+}
+
+/// Kontrola před dávkou: selže hned, když se odhadnutý výstup `files` nevejde do `out_dir`,
+/// místo aby převod spadl uprostřed.
+pub fn check_space(files: &[PathBuf], out_dir: &Path, params: &EncodeParams) -> Result<()> {
+    let need = estimate_output(files, params).context("estimate output size")?;
+    diskspace::ensure_free(out_dir, need.size)
+}
+
+/// Naplánuje převod jednoho souboru pro `--dry-run`: čte jen hlavičku, celý obrázek
+/// jen kvůli čárovému kódu.
+#[allow(clippy::too_many_arguments)]
+pub fn plan_file(
+    plan: &mut Plan,
+    src: &Path,
+    dir: &Path,
+    name: &OutputName,
+    n: usize,
+    params: &EncodeParams,
+    barcode: Option<&BarcodeImport>,
+) -> Result<()> {
+    let (w, h, color, bits) = image_info(src)?;
+    let (id, code) = match barcode {
+        Some(b) => {
+            let img = image::open(src).with_context(|| format!("load {:?}", src))?;
+            let (id, code) = read_barcode(&img, &mut CTIMetadata::default(), b)?;
+            (Some(id), format!(", {code}"))
+        }
+        None => (None, String::new()),
+    };
+    let size = plan.read(src, &format!("{w}x{h}{code}"))?;
+    let vars = NameVars {
+        n: Some(n),
+        page: Some(1),
+        barcode: id.as_deref(),
+        size: Some((w, h)),
+        depth: Some(bits),
+        ..NameVars::new(src)
+    };
+    let target = name.plan(dir, "cti", &vars, &mut plan.taken)?;
+    let raw = w as u64 * h as u64 * color.bytes_per_pixel() as u64;
+    plan.write(&target, Some(dryrun::cti_estimate(raw, size, params)));
+    Ok(())
+}
+
+/// Naplánuje [`convert_sequence`] pro `--dry-run` (rozměry ze hlaviček, kód z první stránky).
+pub fn plan_sequence(
+    plan: &mut Plan,
+    srcs: &[PathBuf],
+    dir: &Path,
+    name: &OutputName,
+    params: &EncodeParams,
+    barcode: Option<&BarcodeImport>,
+) -> Result<()> {
+    let (mut raw, mut size, mut first, mut id) = (0, 0, None, None);
+    for src in srcs {
+        let (w, h, color, bits) = image_info(src)?;
+        let mut code = String::new();
+        if let Some(b) = barcode
+            && first.is_none()
+        {
+            let img = image::open(src).with_context(|| format!("load {:?}", src))?;
+            let (i, c) = read_barcode(&img, &mut CTIMetadata::default(), b)?;
+            (id, code) = (Some(i), format!(", {c}"));
+        }
+        match first {
+            None => first = Some((w, h, color, bits)),
+            Some(f) if f != (w, h, color, bits) => bail!(
+                "{:?} is {w}x{h} ({color:?}), expected {}x{} ({:?})",
+                src,
+                f.0,
+                f.1,
+                f.2
+            ),
+            Some(_) => {}
+        }
+        size += plan.read(src, &format!("{w}x{h}{code}"))?;
+        raw += w as u64 * h as u64 * color.bytes_per_pixel() as u64;
+    }
+    let Some((w, h, _, bits)) = first else {
+        bail!("no input images");
+    };
+    let vars = NameVars {
+        barcode: id.as_deref(),
+        size: Some((w, h)),
+        depth: Some(bits),
+        ..NameVars::new(&srcs[0])
+    };
+    let target = name.plan(dir, "cti", &vars, &mut plan.taken)?;
+    plan.write(&target, Some(dryrun::cti_estimate(raw, size, params)));
+    Ok(())
+}
+
+/// Složí obrázky (v daném pořadí) do jednoho vícesnímkového CTI v `dir` pod jménem ze
+/// šablony `name` (`{stem}` = první obrázek). Všechny musí mít stejný rozměr i typ barev.
+pub fn convert_sequence(
+    srcs: &[PathBuf],
+    dir: &Path,
+    name: &OutputName,
+    params: &EncodeParams,
+    mut meta: CTIMetadata,
+    barcode: Option<&BarcodeImport>,
+) -> Result<String> {
+    let mut frames = Vec::with_capacity(srcs.len());
+    let mut format = None;
+    let mut id = None;
+    let mut code = String::new();
+    for src in srcs {
+        let img = image::open(src).with_context(|| format!("load {:?}", src))?;
+        let (w, h) = (img.width(), img.height());
+        // kód se hledá jen na první stránce
+        if let Some(b) = barcode
+            && frames.is_empty()
+        {
+            let (i, c) = read_barcode(&img, &mut meta, b).with_context(|| format!("{:?}", src))?;
+            (id, code) = (Some(i), format!("{c}, "));
+        }
+        if frames.is_empty() {
+            keep_source_dpi(src, &mut meta);
+        }
+        let (color_type, raw) = source_raw(src, img, params)?;
+        match format {
+            None => format = Some((w, h, color_type)),
+            Some(f) if f != (w, h, color_type) => bail!(
+                "{:?} is {w}x{h} (color type {color_type}), expected {}x{} (color type {})",
+                src,
+                f.0,
+                f.1,
+                f.2
+            ),
+            Some(_) => {}
+        }
+        frames.push(raw);
+    }
+    let Some((w, h, color_type)) = format else {
+        bail!("no input images");
+    };
+    let vars = NameVars {
+        barcode: id.as_deref(),
+        size: Some((w, h)),
+        depth: Some(export::bit_depth(color_type)),
+        ..NameVars::new(&srcs[0])
+    };
+    let raws: Vec<&[u8]> = frames.iter().map(Vec::as_slice).collect();
+    let dst = name.write(dir, "cti", &vars, |dst| {
+        CTIEncoder::encode_frames_with_metadata(dst, w, h, color_type, &raws, params, &meta)?;
+        Ok(())
+    })?;
+    let Some(dst) = dst else {
+        return Ok(format!("{code}skipped, output exists"));
+    };
+    let after = std::fs::metadata(&dst)?.len();
+    Ok(format!(
+        "{code}{} frames, {w}x{h}, {after} B → {}",
+        frames.len(),
+        dst.display()
+    ))
+}
+
+/// Převezme rozlišení ze zdrojového souboru, pokud ho metadata (předvolba, formulář) neurčují.
+fn keep_source_dpi(src: &Path, meta: &mut CTIMetadata) {
+    if meta.get(CTIMetadata::DPI).is_none()
+        && let Some(dpi) = source_dpi(src)
+    {
+        meta.set_dpi(dpi);
+    }
+}
+
+/// Fyzické rozlišení ve zdrojovém PNG (`pHYs`) nebo TIFF (XResolution/YResolution).
+fn source_dpi(path: &Path) -> Option<(f32, f32)> {
+    let mut f = File::open(path).ok()?;
+    let mut head = [0u8; 8];
+    f.read_exact(&mut head).ok()?;
+    let (x, y) = if &head == b"\x89PNG\r\n\x1a\n" {
+        png_dpi(&mut f)?
+    } else {
+        tiff_dpi(&mut f, &head)?
+    };
+    (x.is_finite() && y.is_finite() && x > 0.0 && y > 0.0).then_some((x, y))
+}
+
+fn png_dpi(f: &mut File) -> Option<(f32, f32)> {
+    loop {
+        let mut chunk = [0u8; 8];
+        f.read_exact(&mut chunk).ok()?;
+        let len = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        match &chunk[4..] {
+            b"pHYs" => {
+                let mut d = [0u8; 9];
+                f.read_exact(&mut d).ok()?;
+                // jednotka 1 = pixely na metr, 0 = jen poměr stran
+                if d[8] != 1 {
+                    return None;
+                }
+                let dpi = |b: [u8; 4]| u32::from_be_bytes(b) as f32 * 0.0254;
+                return Some((dpi([d[0], d[1], d[2], d[3]]), dpi([d[4], d[5], d[6], d[7]])));
+            }
+            // pHYs musí předcházet obrazovým datům
+            b"IDAT" | b"IEND" => return None,
+            _ => {
+                f.seek(SeekFrom::Current(len as i64 + 4)).ok()?;
+            }
+        }
+    }
+}
+
+fn tiff_dpi(f: &mut File, head: &[u8; 8]) -> Option<(f32, f32)> {
+    let le = match &head[..4] {
+        b"II*\0" => true,
+        b"MM\0*" => false,
+        _ => return None,
+    };
+    let u16_at = |b: &[u8]| {
+        let a = [b[0], b[1]];
+        if le {
+            u16::from_le_bytes(a)
+        } else {
+            u16::from_be_bytes(a)
+        }
+    };
+    let u32_at = |b: &[u8]| {
+        let a = [b[0], b[1], b[2], b[3]];
+        if le {
+            u32::from_le_bytes(a)
+        } else {
+            u32::from_be_bytes(a)
+        }
+    };
+    // první IFD: XResolution a YResolution jsou zlomky na offsetu, ResolutionUnit je přímo
+    f.seek(SeekFrom::Start(u32_at(&head[4..]) as u64)).ok()?;
+    let mut count = [0u8; 2];
+    f.read_exact(&mut count).ok()?;
+    let mut entries = vec![0u8; u16_at(&count) as usize * 12];
+    f.read_exact(&mut entries).ok()?;
+    let (mut x, mut y, mut unit) = (None, None, 2);
+    for e in entries.chunks_exact(12) {
+        match u16_at(e) {
+            282 => x = Some(u32_at(&e[8..])),
+            283 => y = Some(u32_at(&e[8..])),
+            296 => unit = u16_at(&e[8..]),
+            _ => {}
+        }
+    }
+    let mut rational = |offset: u32| {
+        f.seek(SeekFrom::Start(offset as u64)).ok()?;
+        let mut r = [0u8; 8];
+        f.read_exact(&mut r).ok()?;
+        let (num, den) = (u32_at(&r[..4]), u32_at(&r[4..]));
+        (den != 0).then(|| num as f32 / den as f32)
+    };
+    let (x, y) = (rational(x?)?, rational(y?)?);
+    match unit {
+        2 => Some((x, y)),
+        3 => Some((x * 2.54, y * 2.54)),
+        _ => None,
+    }
+}
+
+/// Volba kodeku, úrovně, velikosti dlaždic a RCT.
+pub fn params_ui(ui: &mut egui::Ui, params: &mut EncodeParams) {
+    egui::Grid::new("encode-params")
+        .num_columns(2)
+        .spacing([12.0, 6.0])
+        .show(ui, |ui| {
+            ui.label("Compression");
+            egui::ComboBox::from_id_salt("encode-codec")
+                .selected_text(params.compression.as_str())
+                .show_ui(ui, |ui| {
+                    for c in CompressionId::encoders() {
+                        let on = params.compression.id() == c.id();
+                        if ui.selectable_label(on, c.as_str()).clicked() {
+                            params.compression = c;
+                        }
+                    }
+                });
+            ui.end_row();
+
+            ui.label("Level");
+            ui.add_enabled(
+                params.compression.has_level(),
+                egui::Slider::new(&mut params.level, 1..=22),
+            )
+            .on_hover_text(
+                "Zstd 1–22, Brotli up to 11, Deflate up to 9 (higher levels are capped)",
+            );
+            ui.end_row();
+
+            ui.label("Quality");
+            ui.add_enabled(
+                params.compression.is_lossy(),
+                egui::Slider::new(&mut params.quality, 1..=100),
+            )
+            .on_hover_text("Lossy DCT only: lower quality gives smaller files and visible blocks");
+            ui.end_row();
+
+            ui.label("Tile size");
+            egui::ComboBox::from_id_salt("encode-tile")
+                .selected_text(params.tile_size.to_string())
+                .show_ui(ui, |ui| {
+                    for t in [128, 256, 512, 1024] {
+                        ui.selectable_value(&mut params.tile_size, t, t.to_string());
+                    }
+                });
+            ui.end_row();
+
+            ui.label("Color transform");
+            ui.checkbox(&mut params.rct, "RCT (when lossless)");
+            ui.end_row();
+
+            ui.label("Grayscale depth");
+            egui::ComboBox::from_id_salt("encode-gray-bits")
+                .selected_text(gray_bits_label(params.gray_bits))
+                .show_ui(ui, |ui| {
+                    for bits in [8, 4, 1] {
+                        ui.selectable_value(&mut params.gray_bits, bits, gray_bits_label(bits));
+                    }
+                })
+                .response
+                .on_hover_text(
+                    "4 and 1 bit shrink scanned text pages; color sources are converted to \
+                     grayscale first",
+                );
+            ui.end_row();
+
+            ui.label("Integrity");
+            ui.checkbox(&mut params.file_hash, "Whole-file SHA-256");
+            ui.end_row();
+
+            ui.label("");
+            ui.checkbox(&mut params.crc32c, "CRC32C tile checksums")
+                .on_hover_text("Not readable by older CTI decoders");
+            ui.end_row();
+        });
+}
+
+fn gray_bits_label(bits: u8) -> &'static str {
+    match bits {
+        1 => "1 bit (bitonal)",
+        4 => "4 bit",
+        _ => "8 bit",
+    }
+}
+
+/// Dialog „Convert folder → CTI“.
+pub struct ConvertDialog {
+    in_dir: Option<PathBuf>,
+    out_dir: Option<PathBuf>,
+    recursive: bool,
+    params: EncodeParams,
+    /// Šablona metadat a jméno předvolby, ze které pochází.
+    metadata: BTreeMap<String, String>,
+    preset: Option<String>,
+    /// Hodnoty formuláře metadat (podle klíče; zůstávají mezi převody).
+    form_values: BTreeMap<String, String>,
+    read_barcode: bool,
+    barcode: BarcodeImport,
+    name: OutputName,
+    /// Poslední odhad výstupu a nastavení kodéru, pro které platí.
+    estimate: Option<(EncodeSettings, String)>,
+    error: Option<String>,
+}
+
+impl Default for ConvertDialog {
+    fn default() -> Self {
+        Self {
+            in_dir: None,
+            out_dir: None,
+            recursive: true,
+            params: EncodeParams::default(),
+            metadata: BTreeMap::new(),
+            preset: None,
+            form_values: BTreeMap::new(),
+            read_barcode: false,
+            barcode: BarcodeImport::default(),
+            name: OutputName::default(),
+            estimate: None,
+            error: None,
+        }
+    }
+}
+
+impl ConvertDialog {
+    /// Vrátí akci a seznam souborů po kliknutí na Convert. `presets` a `form` (pole metadat)
+    /// jsou z Preferences.
+    pub fn window(
+        &mut self,
+        ctx: &egui::Context,
+        open: &mut bool,
+        presets: &[Preset],
+        form: &[FormField],
+    ) -> Option<(BatchAction, Vec<PathBuf>)> {
+        let mut start = None;
+        a11y::modal_dialog(ctx, "Convert PNG/TIFF → CTI", open, |ui| {
+            egui::Grid::new("convert-dirs")
+                .num_columns(3)
+                .spacing([12.0, 6.0])
+                .show(ui, |ui| {
+                    for (label, dir) in [("Input", &mut self.in_dir), ("Output", &mut self.out_dir)]
+                    {
+                        ui.label(label);
+                        let text = dir
+                            .as_deref()
+                            .map_or("—".into(), |d| d.display().to_string());
+                        ui.monospace(text);
+                        if ui.button("Choose…").clicked()
+                            && let Some(d) = FileDialog::new().pick_folder()
+                        {
+                            *dir = Some(d);
+                        }
+                        ui.end_row();
+                    }
+                });
+            ui.checkbox(&mut self.recursive, "Include subfolders");
+            ui.separator();
+            if let Some(p) = presets::combo(ui, "convert-preset", presets, true) {
+                self.params = p.encode.params();
+                self.metadata = p.metadata.clone();
+                self.preset = Some(p.name.clone());
+                // pole formuláře převezmou hodnoty z předvolby
+                for f in form {
+                    if let Some(v) = self.metadata.remove(&f.key) {
+                        self.form_values.insert(f.key.clone(), v);
+                    }
+                }
+            }
+            params_ui(ui, &mut self.params);
+            if let Some(name) = self.preset.clone()
+                && !self.metadata.is_empty()
+            {
+                ui.horizontal(|ui| {
+                    let keys: Vec<&str> = self.metadata.keys().map(String::as_str).collect();
+                    ui.label(format!("Metadata from {name:?}: {}", keys.join(", ")));
+                    if ui.small_button("Clear").clicked() {
+                        self.metadata.clear();
+                        self.preset = None;
+                    }
+                });
+            }
+            ui.separator();
+            ui.checkbox(&mut self.read_barcode, "Read barcode / QR code")
+                .on_hover_text(
+                    "Code 128, Code 39 or QR code on the image; files without one are reported \
+                     as failed",
+                );
+            ui.add_enabled_ui(self.read_barcode, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Store as");
+                    ui.add(egui::TextEdit::singleline(&mut self.barcode.key).desired_width(120.0));
+                });
+            });
+            ui.separator();
+            egui::Grid::new("convert-name")
+                .num_columns(2)
+                .spacing([12.0, 6.0])
+                .show(ui, |ui| output_name_ui(ui, &mut self.name));
+            let name_error = match self.name.check() {
+                Err(e) => Some(format!("{e:#}")),
+                Ok(()) if self.name.uses("barcode") && !self.read_barcode => {
+                    Some("{barcode} needs \"Read barcode / QR code\"".into())
+                }
+                Ok(()) => None,
+            };
+            if let Some(e) = &name_error {
+                ui.colored_label(ui.visuals().error_fg_color, e);
+            }
+            let mut valid =
+                name_error.is_none() && (!self.read_barcode || !self.barcode.key.trim().is_empty());
+            // pole plněné z kódu se nevyplňuje ručně (jeho pravidla platí pro přečtenou hodnotu)
+            let manual: Vec<FormField> = form
+                .iter()
+                .filter(|f| !self.read_barcode || f.key != self.barcode.key)
+                .cloned()
+                .collect();
+            if !manual.is_empty() {
+                ui.separator();
+                egui::CollapsingHeader::new("Metadata")
+                    .default_open(true)
+                    .show(ui, |ui| {
+                        valid &= metaform::form_ui(ui, &manual, &mut self.form_values);
+                    })
+                    .header_response
+                    .on_hover_text("Fields are configured in Preferences → Metadata form");
+            }
+            ui.separator();
+            if let Some(e) = &self.error {
+                ui.colored_label(ui.visuals().error_fg_color, e);
+            }
+            let settings = EncodeSettings::from_params(&self.params);
+            if let Some((_, text)) = self.estimate.as_ref().filter(|(s, _)| *s == settings) {
+                ui.label(format!("Estimate: {text}"));
+            }
+            let ready = self.in_dir.is_some() && self.out_dir.is_some() && valid;
+            let (mut convert, mut estimate) = (false, false);
+            ui.horizontal(|ui| {
+                convert = ui
+                    .add_enabled(ready, egui::Button::new("Convert"))
+                    .clicked();
+                estimate = ui
+                    .add_enabled(self.in_dir.is_some(), egui::Button::new("Estimate"))
+                    .on_hover_text(
+                        "Encode a sample of tiles from a few files to predict the output size \
+                         and, for the lossy codec, PSNR",
+                    )
+                    .clicked();
+            });
+            if estimate && let Some(in_dir) = &self.in_dir {
+                let result =
+                    collect_files(in_dir, self.recursive, &IMAGE_EXTENSIONS).and_then(|files| {
+                        ensure!(!files.is_empty(), "No PNG/TIFF files found.");
+                        estimate_output(&files, &self.params)
+                    });
+                match result {
+                    Ok(e) => {
+                        self.error = None;
+                        self.estimate = Some((settings, estimate_summary(&e)));
+                    }
+                    Err(e) => self.error = Some(format!("{e:#}")),
+                }
+            }
+            if convert && let (Some(in_dir), Some(out_dir)) = (&self.in_dir, &self.out_dir) {
+                match collect_files(in_dir, self.recursive, &IMAGE_EXTENSIONS) {
+                    Ok(files) if files.is_empty() => {
+                        self.error = Some("No PNG/TIFF files found.".into());
+                    }
+                    Ok(files) => {
+                        self.error = None;
+                        let mut metadata = self.metadata.clone();
+                        for f in &manual {
+                            match self.form_values.get(&f.key).map(|v| v.trim()) {
+                                Some(v) if !v.is_empty() => {
+                                    metadata.insert(f.key.clone(), v.to_string());
+                                }
+                                _ => {}
+                            }
+                        }
+                        let action = BatchAction::Convert {
+                            in_dir: in_dir.clone(),
+                            out_dir: out_dir.clone(),
+                            params: self.params,
+                            metadata,
+                            form: form.to_vec(),
+                            barcode: self.read_barcode.then(|| BarcodeImport {
+                                key: self.barcode.key.trim().to_string(),
+                            }),
+                            name: self.name.clone(),
+                        };
+                        start = Some((action, files));
+                    }
+                    Err(e) => self.error = Some(format!("{e:#}")),
+                }
+            }
+        });
+        if start.is_some() {
+            *open = false;
+        }
+        start
+    }
+}
+
+/// Volby exportu (formát, bitová hloubka, kvalita, šablona jména).
+pub fn export_options_ui(ui: &mut egui::Ui, opts: &mut ExportOptions) {
+    egui::Grid::new("export-options")
+        .num_columns(2)
+        .spacing([12.0, 6.0])
+        .show(ui, |ui| {
+            ui.label("Format");
+            ui.horizontal(|ui| {
+                for f in ExportFormat::ALL {
+                    ui.radio_value(&mut opts.format, f, f.as_str());
+                }
+            });
+            ui.end_row();
+
+            ui.label("Bit depth");
+            ui.add_enabled_ui(opts.format != ExportFormat::Jpeg, |ui| {
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut opts.depth, BitDepth::Keep, "Keep 16-bit");
+                    ui.radio_value(&mut opts.depth, BitDepth::Eight, "Reduce to 8-bit");
+                });
+            });
+            ui.end_row();
+
+            ui.label("JPEG quality");
+            ui.add_enabled(
+                opts.format == ExportFormat::Jpeg,
+                egui::Slider::new(&mut opts.jpeg_quality, 1..=100),
+            );
+            ui.end_row();
+
+            output_name_ui(ui, &mut opts.name);
+        });
+}
+
+/// Řádky mřížky se šablonou jména výstupu a volbou pro existující soubory.
+fn output_name_ui(ui: &mut egui::Ui, name: &mut OutputName) {
+    ui.label("File name");
+    ui.text_edit_singleline(&mut name.pattern)
+        .on_hover_text(naming::PATTERN_HELP);
+    ui.end_row();
+
+    ui.label("If the file exists");
+    egui::ComboBox::from_id_salt(ui.id().with("collision"))
+        .selected_text(name.collision.as_str())
+        .show_ui(ui, |ui| {
+            for c in Collision::ALL {
+                ui.selectable_value(&mut name.collision, c, c.as_str());
+            }
+        });
+    ui.end_row();
+}
+
+/// Výběr korekce objektivu: vypnuto, podle zařízení v metadatech, nebo pevný profil.
+fn lens_ui(ui: &mut egui::Ui, lens: &mut LensCorrection, profiles: &[LensProfile]) {
+    // „podle zařízení“ vždy s aktuálním seznamem z Preferences
+    if let LensCorrection::ByDevice(list) = lens {
+        *list = profiles.to_vec();
+    }
+    let selected = match lens {
+        LensCorrection::Off => "Off".to_string(),
+        LensCorrection::ByDevice(_) => "By Device metadata".to_string(),
+        LensCorrection::Profile(p) => p.name.clone(),
+    };
+    ui.horizontal(|ui| {
+        ui.label("Lens correction");
+        egui::ComboBox::from_id_salt("export-lens")
+            .selected_text(selected)
+            .show_ui(ui, |ui| {
+                ui.selectable_value(lens, LensCorrection::Off, "Off");
+                ui.add_enabled_ui(!profiles.is_empty(), |ui| {
+                    ui.selectable_value(
+                        lens,
+                        LensCorrection::ByDevice(profiles.to_vec()),
+                        "By Device metadata",
+                    );
+                });
+                for p in profiles {
+                    ui.selectable_value(lens, LensCorrection::Profile(p.clone()), &p.name);
+                }
+            })
+            .response
+            .on_hover_text("Profiles are edited in Preferences → Lens profiles");
+    });
+}
+
+/// Volba reference pro korekci osvětlení (flat field).
+fn flat_field_ui(ui: &mut egui::Ui, flat: &mut Option<PathBuf>) {
+    ui.horizontal(|ui| {
+        ui.label("Flat field");
+        let name = flat
+            .as_deref()
+            .and_then(Path::file_name)
+            .map_or("Off".into(), |n| n.to_string_lossy().into_owned());
+        ui.monospace(name);
+        if ui
+            .button("Choose…")
+            .on_hover_text("Capture of an evenly lit white target, same size as the images")
+            .clicked()
+            && let Some(path) = FileDialog::new()
+                .add_filter("Images", &["cti", "png", "tif", "tiff"])
+                .pick_file()
+        {
+            *flat = Some(path);
+        }
+        if flat.is_some() && ui.button("Clear").clicked() {
+            *flat = None;
+        }
+    });
+}
+
+/// Dialog „Export CTI → PNG/TIFF/JPEG“ pro výběr z prohlížeče nebo celou složku.
+#[derive(Default)]
+pub struct ExportDialog {
+    selection: Vec<PathBuf>,
+    from_folder: bool,
+    in_dir: Option<PathBuf>,
+    recursive: bool,
+    out_dir: Option<PathBuf>,
+    options: ExportOptions,
+    error: Option<String>,
+}
+
+impl ExportDialog {
+    /// Připraví dialog pro vybrané soubory (prázdný výběr = export složky).
+    pub fn prepare(&mut self, selection: Vec<PathBuf>, format: ExportFormat) {
+        self.from_folder = selection.is_empty();
+        self.selection = selection;
+        self.options.format = format;
+        self.error = None;
+    }
+
+    /// `profiles` = korekční profily objektivů, `presets` = předvolby z Preferences.
+    pub fn window(
+        &mut self,
+        ctx: &egui::Context,
+        open: &mut bool,
+        profiles: &[LensProfile],
+        presets: &[Preset],
+    ) -> Option<(BatchAction, Vec<PathBuf>)> {
+        let mut start = None;
+        a11y::modal_dialog(ctx, "Export CTI → PNG/TIFF/JPEG", open, |ui| {
+            ui.horizontal(|ui| {
+                ui.add_enabled_ui(!self.selection.is_empty(), |ui| {
+                    ui.radio_value(
+                        &mut self.from_folder,
+                        false,
+                        format!("Selected files ({})", self.selection.len()),
+                    );
+                });
+                ui.radio_value(&mut self.from_folder, true, "Folder");
+            });
+            egui::Grid::new("export-dirs")
+                .num_columns(3)
+                .spacing([12.0, 6.0])
+                .show(ui, |ui| {
+                    let mut rows = vec![("Output", &mut self.out_dir)];
+                    if self.from_folder {
+                        rows.insert(0, ("Input", &mut self.in_dir));
+                    }
+                    for (label, dir) in rows {
+                        ui.label(label);
+                        let text = dir
+                            .as_deref()
+                            .map_or("—".into(), |d| d.display().to_string());
+                        ui.monospace(text);
+                        if ui.button("Choose…").clicked()
+                            && let Some(d) = FileDialog::new().pick_folder()
+                        {
+                            *dir = Some(d);
+                        }
+                        ui.end_row();
+                    }
+                });
+            if self.from_folder {
+                ui.checkbox(&mut self.recursive, "Include subfolders");
+            }
+            ui.separator();
+            export_options_ui(ui, &mut self.options);
+            // z předvolby se převezmou jen korekce, formát exportu zůstává
+            if let Some(p) = presets::combo(ui, "export-preset", presets, false) {
+                self.options.lens = p.lens_correction();
+                self.options.flat_field = p.flat_field.clone();
+            }
+            flat_field_ui(ui, &mut self.options.flat_field);
+            lens_ui(ui, &mut self.options.lens, profiles);
+            ui.separator();
+            if let Some(e) = &self.error {
+                ui.colored_label(ui.visuals().error_fg_color, e);
+            }
+            let ready = self.out_dir.is_some() && (!self.from_folder || self.in_dir.is_some());
+            if ui.add_enabled(ready, egui::Button::new("Export")).clicked()
+                && let Some(out_dir) = &self.out_dir
+            {
+                let files = self.options.name.check().and_then(|_| {
+                    match (&self.in_dir, self.from_folder) {
+                        (Some(dir), true) => collect_files(dir, self.recursive, &CTI_EXTENSIONS),
+                        _ => Ok(self.selection.clone()),
+                    }
+                });
+                match files {
+                    Ok(files) if files.is_empty() => {
+                        self.error = Some("No .cti files found.".into());
+                    }
+                    Ok(files) => {
+                        self.error = None;
+                        let action = BatchAction::Export {
+                            in_root: self.in_dir.clone().filter(|_| self.from_folder),
+                            out_dir: out_dir.clone(),
+                            options: self.options.clone(),
+                        };
+                        start = Some((action, files));
+                    }
+                    Err(e) => self.error = Some(format!("{e:#}")),
+                }
+            }
+        });
+        if start.is_some() {
+            *open = false;
+        }
+        start
+    }
+}
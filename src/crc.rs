@@ -0,0 +1,312 @@
+//! Kontrolní součty dlaždic: CRC32 (IEEE, polynom 0x04C11DB7) a CRC32C (Castagnoli,
+//! 0x1EDC6F41). Obecná varianta zpracuje 16 bajtů na krok („slicing-by-16“); na x86_64
+//! se za běhu vybere skládání přes PCLMULQDQ (CRC32) nebo instrukce SSE4.2 (CRC32C),
+//! na aarch64 instrukce rozšíření CRC.
+
+/// Obrácený polynom CRC32 (IEEE 802.3).
+const POLY_IEEE: u32 = 0xEDB8_8320;
+/// Obrácený polynom CRC32C (Castagnoli).
+const POLY_CASTAGNOLI: u32 = 0x82F6_3B78;
+
+const TABLE_IEEE: [[u32; 256]; 16] = tables(POLY_IEEE);
+const TABLE_CASTAGNOLI: [[u32; 256]; 16] = tables(POLY_CASTAGNOLI);
+
+/// CRC32 (IEEE), jak ho dlaždice nesou odjakživa.
+pub fn crc32(data: &[u8]) -> u32 {
+    #[cfg(target_arch = "x86_64")]
+    if std::arch::is_x86_feature_detected!("pclmulqdq")
+        && std::arch::is_x86_feature_detected!("sse4.1")
+    {
+        // SAFETY: procesor PCLMULQDQ i SSE4.1 podporuje (ověřeno výše)
+        return unsafe { x86::crc32_pclmul(0, data) };
+    }
+    #[cfg(target_arch = "aarch64")]
+    if std::arch::is_aarch64_feature_detected!("crc") {
+        // SAFETY: procesor rozšíření CRC podporuje (ověřeno výše)
+        return unsafe { arm::crc32(0, data) };
+    }
+    update(0, data, &TABLE_IEEE)
+}
+
+/// CRC32C (Castagnoli) pro soubory s [`FLAG_CRC32C`](crate::cti::FLAG_CRC32C).
+pub fn crc32c(data: &[u8]) -> u32 {
+    #[cfg(target_arch = "x86_64")]
+    if std::arch::is_x86_feature_detected!("sse4.2") {
+        // SAFETY: procesor SSE4.2 podporuje (ověřeno výše)
+        return unsafe { x86::crc32c_sse42(0, data) };
+    }
+    #[cfg(target_arch = "aarch64")]
+    if std::arch::is_aarch64_feature_detected!("crc") {
+        // SAFETY: procesor rozšíření CRC podporuje (ověřeno výše)
+        return unsafe { arm::crc32c(0, data) };
+    }
+    update(0, data, &TABLE_CASTAGNOLI)
+}
+
+/// Původní výpočet po bajtech: referenční výsledek pro srovnání.
+pub fn crc32_bytewise(data: &[u8]) -> u32 {
+    update_bytewise(0, data, &TABLE_IEEE[0])
+}
+
+/// Pokračuje v součtu `crc` (hodnota po finální inverzi) bajty `data`.
+fn update(crc: u32, data: &[u8], t: &[[u32; 256]; 16]) -> u32 {
+    let mut crc = !crc;
+    let mut blocks = data.chunks_exact(16);
+    for b in &mut blocks {
+        let b: &[u8; 16] = b.try_into().unwrap();
+        crc ^= u32::from_le_bytes([b[0], b[1], b[2], b[3]]);
+        crc = t[15][(crc & 0xFF) as usize]
+            ^ t[14][((crc >> 8) & 0xFF) as usize]
+            ^ t[13][((crc >> 16) & 0xFF) as usize]
+            ^ t[12][(crc >> 24) as usize]
+            ^ t[11][b[4] as usize]
+            ^ t[10][b[5] as usize]
+            ^ t[9][b[6] as usize]
+            ^ t[8][b[7] as usize]
+            ^ t[7][b[8] as usize]
+            ^ t[6][b[9] as usize]
+            ^ t[5][b[10] as usize]
+            ^ t[4][b[11] as usize]
+            ^ t[3][b[12] as usize]
+            ^ t[2][b[13] as usize]
+            ^ t[1][b[14] as usize]
+            ^ t[0][b[15] as usize];
+    }
+    update_bytewise(!crc, blocks.remainder(), &t[0])
+}
+
+fn update_bytewise(crc: u32, data: &[u8], t: &[u32; 256]) -> u32 {
+    let mut crc = !crc;
+    for &b in data {
+        crc = (crc >> 8) ^ t[((crc ^ b as u32) & 0xFF) as usize];
+    }
+    !crc
+}
+
+/// Tabulky pro slicing-by-16: `t[k][i]` je CRC bajtu `i` následovaného `k` nulovými bajty.
+const fn tables(poly: u32) -> [[u32; 256]; 16] {
+    let mut t = [[0u32; 256]; 16];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut j = 0;
+        while j < 8 {
+            c = if (c & 1) != 0 {
+                poly ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            j += 1;
+        }
+        t[0][i] = c;
+        i += 1;
+    }
+    let mut k = 1;
+    while k < 16 {
+        let mut i = 0;
+        while i < 256 {
+            let prev = t[k - 1][i];
+            t[k][i] = (prev >> 8) ^ t[0][(prev & 0xFF) as usize];
+            i += 1;
+        }
+        k += 1;
+    }
+    t
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use std::arch::x86_64::*;
+
+    // Konstanty skládání pro polynom IEEE (x^n mod P, bitově obrácené), viz Intel,
+    // „Fast CRC Computation for Generic Polynomials Using PCLMULQDQ Instruction“.
+    const K1: i64 = 0x1_5444_2bd4;
+    const K2: i64 = 0x1_c6e4_1596;
+    const K3: i64 = 0x1_7519_97d0;
+    const K4: i64 = 0x0_ccaa_009e;
+    const K5: i64 = 0x1_63cd_6124;
+    const P_X: i64 = 0x1_DB71_0641;
+    const U_PRIME: i64 = 0x1_F701_1641;
+
+    /// CRC32 skládáním po 64 bajtech; kratší data a zbytek dopočítá tabulkami.
+    #[target_feature(enable = "pclmulqdq,sse4.1")]
+    pub fn crc32_pclmul(crc: u32, mut data: &[u8]) -> u32 {
+        if data.len() < 128 {
+            return super::update(crc, data, &super::TABLE_IEEE);
+        }
+        let mut x3 = load(&mut data);
+        let mut x2 = load(&mut data);
+        let mut x1 = load(&mut data);
+        let mut x0 = load(&mut data);
+        x3 = _mm_xor_si128(x3, _mm_cvtsi32_si128(!crc as i32));
+
+        let k1k2 = _mm_set_epi64x(K2, K1);
+        while data.len() >= 64 {
+            x3 = fold(x3, load(&mut data), k1k2);
+            x2 = fold(x2, load(&mut data), k1k2);
+            x1 = fold(x1, load(&mut data), k1k2);
+            x0 = fold(x0, load(&mut data), k1k2);
+        }
+
+        let k3k4 = _mm_set_epi64x(K4, K3);
+        let mut x = fold(x3, x2, k3k4);
+        x = fold(x, x1, k3k4);
+        x = fold(x, x0, k3k4);
+        while data.len() >= 16 {
+            x = fold(x, load(&mut data), k3k4);
+        }
+
+        // 128 → 64 bitů
+        let low32 = _mm_set_epi32(0, 0, 0, !0);
+        x = _mm_xor_si128(_mm_clmulepi64_si128(x, k3k4, 0x10), _mm_srli_si128(x, 8));
+        x = _mm_xor_si128(
+            _mm_clmulepi64_si128(_mm_and_si128(x, low32), _mm_set_epi64x(0, K5), 0x00),
+            _mm_srli_si128(x, 4),
+        );
+
+        // Barrettova redukce na 32 bitů
+        let pu = _mm_set_epi64x(U_PRIME, P_X);
+        let t1 = _mm_clmulepi64_si128(_mm_and_si128(x, low32), pu, 0x10);
+        let t2 = _mm_clmulepi64_si128(_mm_and_si128(t1, low32), pu, 0x00);
+        let c = _mm_extract_epi32(_mm_xor_si128(x, t2), 1) as u32;
+        super::update(!c, data, &super::TABLE_IEEE)
+    }
+
+    #[inline]
+    #[target_feature(enable = "pclmulqdq,sse4.1")]
+    fn fold(a: __m128i, b: __m128i, keys: __m128i) -> __m128i {
+        let t1 = _mm_clmulepi64_si128(a, keys, 0x00);
+        let t2 = _mm_clmulepi64_si128(a, keys, 0x11);
+        _mm_xor_si128(_mm_xor_si128(b, t1), t2)
+    }
+
+    #[inline]
+    #[target_feature(enable = "sse2")]
+    fn load(data: &mut &[u8]) -> __m128i {
+        let (head, rest) = data.split_at(16);
+        *data = rest;
+        // SAFETY: `head` má 16 bajtů, nezarovnané čtení je dovolené
+        unsafe { _mm_loadu_si128(head.as_ptr().cast()) }
+    }
+
+    /// CRC32C instrukcí `crc32` po 8 bajtech.
+    #[target_feature(enable = "sse4.2")]
+    pub fn crc32c_sse42(crc: u32, data: &[u8]) -> u32 {
+        let mut c = (!crc) as u64;
+        let mut words = data.chunks_exact(8);
+        for w in &mut words {
+            c = _mm_crc32_u64(c, u64::from_le_bytes(w.try_into().unwrap()));
+        }
+        let mut c = c as u32;
+        for &b in words.remainder() {
+            c = _mm_crc32_u8(c, b);
+        }
+        !c
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+mod arm {
+    use std::arch::aarch64::*;
+
+    #[target_feature(enable = "crc")]
+    pub fn crc32(crc: u32, data: &[u8]) -> u32 {
+        let mut c = !crc;
+        let mut words = data.chunks_exact(8);
+        for w in &mut words {
+            c = __crc32d(c, u64::from_le_bytes(w.try_into().unwrap()));
+        }
+        for &b in words.remainder() {
+            c = __crc32b(c, b);
+        }
+        !c
+    }
+
+    #[target_feature(enable = "crc")]
+    pub fn crc32c(crc: u32, data: &[u8]) -> u32 {
+        let mut c = !crc;
+        let mut words = data.chunks_exact(8);
+        for w in &mut words {
+            c = __crc32cd(c, u64::from_le_bytes(w.try_into().unwrap()));
+        }
+        for &b in words.remainder() {
+            c = __crc32cb(c, b);
+        }
+        !c
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Výpočet po bitech bez tabulek: reference nezávislá na [`tables`].
+    fn bitwise(data: &[u8], poly: u32) -> u32 {
+        let mut crc = !0u32;
+        for &b in data {
+            crc ^= u32::from(b);
+            for _ in 0..8 {
+                crc = if crc & 1 != 0 {
+                    (crc >> 1) ^ poly
+                } else {
+                    crc >> 1
+                };
+            }
+        }
+        !crc
+    }
+
+    /// Pseudonáhodné bajty (xorshift), ať se neopakují s periodou bloku.
+    fn noise(len: usize) -> Vec<u8> {
+        let mut x = 0x9E37_79B9_u32;
+        (0..len)
+            .map(|_| {
+                x ^= x << 13;
+                x ^= x >> 17;
+                x ^= x << 5;
+                x as u8
+            })
+            .collect()
+    }
+
+    /// Zrychlená i tabulková varianta obou součtů proti referenci.
+    fn check(data: &[u8]) {
+        let ieee = crc32_bytewise(data);
+        assert_eq!(ieee, bitwise(data, POLY_IEEE), "len {}", data.len());
+        assert_eq!(crc32(data), ieee, "crc32, len {}", data.len());
+        assert_eq!(update(0, data, &TABLE_IEEE), ieee, "len {}", data.len());
+        let castagnoli = bitwise(data, POLY_CASTAGNOLI);
+        assert_eq!(crc32c(data), castagnoli, "crc32c, len {}", data.len());
+        let table = update(0, data, &TABLE_CASTAGNOLI);
+        assert_eq!(table, castagnoli, "len {}", data.len());
+    }
+
+    #[test]
+    fn check_values() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32_bytewise(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32c(b"123456789"), 0xE306_9283);
+    }
+
+    /// Délky kolem bloků slicing-by-16, 8B slov SSE4.2/ARM a hranice 128 B skládání
+    /// PCLMULQDQ, plus jedna přes 64 KiB.
+    #[test]
+    fn matches_the_reference_for_every_length() {
+        let data = noise(70_000);
+        for len in 0..=300 {
+            check(&data[..len]);
+        }
+        check(&data[..65_536 + 77]);
+    }
+
+    /// Nezarovnaný začátek: skládání musí správně zpracovat začátek i zbytek za bloky.
+    #[test]
+    fn matches_the_reference_at_unaligned_offsets() {
+        let data = noise(1_200);
+        for start in 1..32 {
+            for len in [15, 16, 17, 63, 64, 127, 128, 129, 255, 256, 1_000] {
+                check(&data[start..start + len]);
+            }
+        }
+    }
+}
@@ -1,7 +1,13 @@
 use anyhow::{anyhow, bail, ensure, Result};
+use sha2::{Digest, Sha256};
 use std::fs::File;
-use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
 use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::tilecache::{FileTiles, TileCache};
+use crate::{crc, dct, simd};
 
 // --- veřejné typy ---
 
@@ -18,6 +24,380 @@ pub struct CTIHeader {
     pub color_type: u8,
     pub compression: u8,
     pub quality: u8,
+    /// Počet snímků (≥ 1); víc než jeden jen s [`FLAG_FRAMES`].
+    pub frames: u32,
+    /// Délka oblasti rozšiřujících bloků za indexem (jen verze 2, jinak 0; viz [`CTIExtensions`]).
+    pub chunks_len: u32,
+}
+
+impl CTIHeader {
+    /// Soubor obsahuje blok metadat za indexem dlaždic.
+    pub fn has_metadata(&self) -> bool {
+        self.flags & FLAG_METADATA != 0
+    }
+
+    /// Soubor končí trailerem s hashem celého souboru.
+    pub fn has_file_hash(&self) -> bool {
+        self.flags & FLAG_FILE_HASH != 0
+    }
+
+    /// Hlavní verze formátu (nižší bajt `version`).
+    pub fn version_major(&self) -> u16 {
+        self.version & 0xFF
+    }
+
+    /// Vedlejší verze formátu (vyšší bajt `version`).
+    pub fn version_minor(&self) -> u16 {
+        self.version >> 8
+    }
+
+    /// Za indexem následují rozšiřující bloky (verze 2) místo samotného bloku metadat.
+    pub fn has_chunks(&self) -> bool {
+        self.version_major() >= VERSION_CHUNKS
+    }
+
+    /// Dlaždice nesou CRC32C místo CRC32.
+    pub fn has_crc32c(&self) -> bool {
+        self.flags & FLAG_CRC32C != 0
+    }
+
+    /// Index dlaždic leží na konci souboru (zápis po řádcích, viz [`CTIAppendEncoder`]).
+    pub fn has_index_at_end(&self) -> bool {
+        self.flags & FLAG_INDEX_AT_END != 0
+    }
+
+    /// Kontrolní součet dlaždice podle toho, který soubor používá.
+    fn tile_crc(&self, tile: &[u8]) -> u32 {
+        if self.has_crc32c() {
+            crc::crc32c(tile)
+        } else {
+            crc::crc32(tile)
+        }
+    }
+
+    /// Počet dlaždic jednoho snímku.
+    pub fn tiles_per_frame(&self) -> usize {
+        (self.tiles_x as usize) * (self.tiles_y as usize)
+    }
+
+    /// Počet položek indexu (dlaždice všech snímků).
+    fn index_len(&self) -> usize {
+        self.tiles_per_frame() * self.frames as usize
+    }
+}
+
+/// Bit v `flags`: za indexem dlaždic následuje blok metadat (viz [`CTIMetadata`]).
+pub const FLAG_METADATA: u16 = 1 << 1;
+/// Bit v `flags`: soubor končí trailerem s hashem všech předchozích bajtů (viz
+/// [`verify_file_hash`]). Trailer: `"CTIS"`, u8 algoritmus (1 = SHA-256), 3 B rezerva, 32 B hash.
+pub const FLAG_FILE_HASH: u16 = 1 << 2;
+/// Bit v `flags`: soubor nese sekvenci snímků stejného rozměru (skeny po sobě, vícestránkový
+/// dokument). Počet snímků je u32 na začátku rezervy hlavičky (bajty 31..35) a index obsahuje
+/// dlaždice snímek po snímku. Dekodér bez podpory snímků tak přečte aspoň první snímek.
+pub const FLAG_FRAMES: u16 = 1 << 3;
+/// Bit v `flags`: pole `crc32` v indexu dlaždic obsahuje CRC32C (Castagnoli) místo CRC32.
+/// Dekodér bez podpory bitu hlásí u všech dlaždic chybu CRC, data ale přečte beze změny.
+pub const FLAG_CRC32C: u16 = 1 << 4;
+/// Bit v `flags` (jen od verze [`VERSION_APPEND`]): index dlaždic neleží za hlavičkou, ale
+/// na konci souboru. Za hlavičkou hned následuje oblast bloků (jako ve verzi 2), pak dlaždice
+/// v pořadí zápisu, index a lokátor `"CTIX"` + u64 offset indexu (před případným trailerem
+/// s hashem). Soubor tak lze zapisovat jen připisováním, jak přibývají řádky dlaždic.
+pub const FLAG_INDEX_AT_END: u16 = 1 << 5;
+/// Všechny příznaky, kterým tato verze rozumí.
+pub(crate) const KNOWN_FLAGS: u16 =
+    FLAG_RCT | FLAG_METADATA | FLAG_FILE_HASH | FLAG_FRAMES | FLAG_CRC32C | FLAG_INDEX_AT_END;
+
+/// Verze formátu s rozšiřujícími bloky za indexem (viz [`CTIExtensions`]).
+pub const VERSION_CHUNKS: u16 = 2;
+/// Verze formátu s indexem na konci ([`FLAG_INDEX_AT_END`]); starší dekodéry, které by
+/// index hledaly za hlavičkou, soubor odmítnou.
+pub const VERSION_APPEND: u16 = 3;
+
+/// Nejvyšší hlavní verze, kterou dekodér přečte. Pole `version` nese v nižším bajtu hlavní
+/// a ve vyšším vedlejší verzi (`0x0102` = 2.1). Novější hlavní verze mění formát
+/// nekompatibilně a soubor se odmítne; novější vedlejší jen přidává příznaky, které starší
+/// dekodér může ignorovat.
+pub const VERSION_MAJOR_MAX: u16 = VERSION_APPEND;
+/// Vedlejší verze, jejíž příznaky dekodér zná.
+pub const VERSION_MINOR: u16 = 0;
+
+/// Bit v `flags`: RGB dlaždice jsou uložené po reverzibilní transformaci barev (RCT).
+pub const FLAG_RCT: u16 = 1;
+
+/// Volitelný blok metadat (páry klíč/hodnota v UTF-8, pořadí se zachovává).
+///
+/// Uložení hned za indexem dlaždic: `"CTIM"`, u32 délka obsahu, u32 počet položek a pro každou
+/// u16 délka klíče, klíč, u32 délka hodnoty, hodnota. Dlaždice se adresují absolutními offsety,
+/// takže starší dekodéry blok jednoduše přeskočí. Celý XMP paket lze uložit pod klíčem `XMP`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CTIMetadata {
+    pub entries: Vec<(String, String)>,
+}
+
+impl CTIMetadata {
+    pub const CAPTURE_DATE: &str = "CaptureDate";
+    pub const DEVICE: &str = "Device";
+    pub const OPERATOR: &str = "Operator";
+    /// Identifikátor objektu (signatura, inventární číslo), např. přečtený z čárového kódu.
+    pub const IDENTIFIER: &str = "Identifier";
+    pub const XMP: &str = "XMP";
+    /// Fyzické rozlišení snímku v bodech na palec: `300`, nebo `300x600` (vodorovně × svisle).
+    pub const DPI: &str = "DPI";
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Rozlišení z pole [`DPI`](Self::DPI) jako (x, y); chybějící nebo nečitelné = `None`.
+    pub fn dpi(&self) -> Option<(f32, f32)> {
+        let v = self.get(Self::DPI)?.trim();
+        let parse = |s: &str| {
+            s.trim()
+                .parse::<f32>()
+                .ok()
+                .filter(|d| d.is_finite() && *d > 0.0)
+        };
+        match v.split_once(['x', 'X', '×']) {
+            Some((x, y)) => Some((parse(x)?, parse(y)?)),
+            None => parse(v).map(|d| (d, d)),
+        }
+    }
+
+    /// Zapíše rozlišení do pole [`DPI`](Self::DPI); stejné X a Y jedním číslem.
+    pub fn set_dpi(&mut self, (x, y): (f32, f32)) {
+        let fmt = |d: f32| {
+            let r = d.round();
+            if (d - r).abs() < 0.05 {
+                format!("{r}")
+            } else {
+                format!("{d:.1}")
+            }
+        };
+        let (x, y) = (fmt(x), fmt(y));
+        let value = if x == y { x } else { format!("{x}x{y}") };
+        self.set(Self::DPI, value);
+    }
+
+    /// Nastaví hodnotu (existující klíč přepíše na místě, jinak přidá na konec).
+    pub fn set(&mut self, key: &str, value: impl Into<String>) {
+        let value = value.into();
+        match self.entries.iter_mut().find(|(k, _)| k == key) {
+            Some((_, v)) => *v = value,
+            None => self.entries.push((key.to_string(), value)),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut body = Vec::new();
+        body.extend_from_slice(&(self.entries.len() as u32).to_le_bytes());
+        for (k, v) in &self.entries {
+            ensure!(
+                !k.is_empty() && k.len() <= u16::MAX as usize,
+                "Invalid metadata key {:?}",
+                k
+            );
+            body.extend_from_slice(&(k.len() as u16).to_le_bytes());
+            body.extend_from_slice(k.as_bytes());
+            body.extend_from_slice(&(v.len() as u32).to_le_bytes());
+            body.extend_from_slice(v.as_bytes());
+        }
+        ensure!(body.len() <= MAX_METADATA_SIZE, "Metadata block too large");
+        let mut out = Vec::with_capacity(body.len() + 8);
+        out.extend_from_slice(METADATA_MAGIC);
+        out.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        out.extend_from_slice(&body);
+        Ok(out)
+    }
+
+    fn read<R: Read>(r: &mut R) -> Result<Self> {
+        let mut magic = [0u8; 4];
+        r.read_exact(&mut magic)?;
+        ensure!(&magic == METADATA_MAGIC, "Bad metadata block magic");
+        let len = read_u32_le(r)? as usize;
+        ensure!(len <= MAX_METADATA_SIZE, "Metadata block too large");
+        Self::parse(&read_vec(r, len)?)
+    }
+
+    /// Obsah bloku (bez `"CTIM"` a délky).
+    fn parse(body: &[u8]) -> Result<Self> {
+        let mut cur = body;
+        let count = read_u32_le(&mut cur)?;
+        let mut entries = Vec::new();
+        for _ in 0..count {
+            let klen = read_u16_le(&mut cur)? as usize;
+            let key = take_utf8(&mut cur, klen)?;
+            let vlen = read_u32_le(&mut cur)? as usize;
+            let value = take_utf8(&mut cur, vlen)?;
+            entries.push((key, value));
+        }
+        Ok(Self { entries })
+    }
+}
+
+/// Rozšiřující bloky souboru verze 2.
+///
+/// Za indexem dlaždic leží posloupnost bloků: 4 B typ, u32 délka obsahu, obsah; končí blokem
+/// `"CEND"` s nulovou délkou. Délku celé oblasti nese hlavička (u32 v rezervě na bajtech
+/// 35..39, viz [`CTIHeader::chunks_len`]). Blok metadat je vždy první a má stejný tvar jako
+/// u verze 1, takže ho starší dekodér s [`FLAG_METADATA`] přečte také. Neznámé typy se
+/// přeskočí (jen se zapamatuje jejich typ), dlaždice se adresují absolutními offsety.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CTIExtensions {
+    /// `"CTIM"`
+    pub metadata: CTIMetadata,
+    /// `"ICCP"`: ICC profil barevného prostoru obrázku.
+    pub icc: Option<Vec<u8>>,
+    /// `"PYRM"`: zmenšené úrovně obrázku (od největší).
+    pub pyramid: Vec<PyramidLevel>,
+    /// Typy přeskočených bloků, kterým tato verze nerozumí.
+    pub unknown: Vec<[u8; 4]>,
+}
+
+/// Úroveň pyramidy: rozměr a vlastní index dlaždic (stejná velikost dlaždice i kodek jako
+/// hlavní obrázek). Obsah bloku: u32 počet úrovní, pro každou u32 šířka, u32 výška a položky
+/// indexu ve stejném tvaru jako hlavní index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PyramidLevel {
+    pub width: u32,
+    pub height: u32,
+    pub tiles: Vec<TileEntry>,
+}
+
+impl CTIExtensions {
+    /// Potřebuje soubor verze 2 (něco navíc k metadatům, která umí i verze 1).
+    pub fn needs_chunks(&self) -> bool {
+        self.icc.is_some() || !self.pyramid.is_empty()
+    }
+
+    /// Oblast bloků včetně koncového `"CEND"`.
+    fn to_bytes(&self, hdr: &CTIHeader) -> Result<Vec<u8>> {
+        let unknown: Vec<String> = self.unknown.iter().map(|k| fourcc(*k)).collect();
+        ensure!(
+            unknown.is_empty(),
+            "File has extension chunks this version does not know ({}); rewriting it would drop them",
+            unknown.join(", ")
+        );
+        let mut out = Vec::new();
+        if !self.metadata.is_empty() {
+            out.extend_from_slice(&self.metadata.to_bytes()?);
+        }
+        if let Some(icc) = &self.icc {
+            push_chunk(&mut out, CHUNK_ICC, icc)?;
+        }
+        if !self.pyramid.is_empty() {
+            let mut body = (self.pyramid.len() as u32).to_le_bytes().to_vec();
+            for level in &self.pyramid {
+                ensure!(
+                    level.tiles.len() == level_tiles(hdr, level.width, level.height),
+                    "Pyramid level {}x{} has a wrong number of tiles",
+                    level.width,
+                    level.height
+                );
+                body.extend_from_slice(&level.width.to_le_bytes());
+                body.extend_from_slice(&level.height.to_le_bytes());
+                for t in &level.tiles {
+                    body.extend_from_slice(&t.offset.to_le_bytes());
+                    body.extend_from_slice(&t.compressed_size.to_le_bytes());
+                    body.extend_from_slice(&t.original_size.to_le_bytes());
+                    body.extend_from_slice(&t.crc.to_le_bytes());
+                }
+            }
+            push_chunk(&mut out, CHUNK_PYRAMID, &body)?;
+        }
+        push_chunk(&mut out, CHUNK_END, &[])?;
+        Ok(out)
+    }
+
+    /// Přečte oblast bloků (čtecí pozice hned za indexem dlaždic).
+    fn read<R: Read>(r: &mut R, hdr: &CTIHeader) -> Result<Self> {
+        let mut ext = Self::default();
+        let mut left = u64::from(hdr.chunks_len);
+        loop {
+            ensure!(left >= 8, "Truncated extension chunks");
+            let mut kind = [0u8; 4];
+            r.read_exact(&mut kind)?;
+            let len = read_u32_le(r)?;
+            left -= 8;
+            if &kind == CHUNK_END {
+                return Ok(ext);
+            }
+            ensure!(
+                u64::from(len) <= left,
+                "Extension chunk {} runs past the chunk area",
+                fourcc(kind)
+            );
+            left -= u64::from(len);
+            match &kind {
+                METADATA_MAGIC => ext.metadata = CTIMetadata::parse(&read_chunk(r, len)?)?,
+                CHUNK_ICC => ext.icc = Some(read_chunk(r, len)?),
+                CHUNK_PYRAMID => ext.pyramid = read_pyramid(&read_chunk(r, len)?, hdr)?,
+                _ => {
+                    let skipped = std::io::copy(&mut r.take(len.into()), &mut std::io::sink())?;
+                    ensure!(skipped == u64::from(len), "Truncated extension chunks");
+                    ext.unknown.push(kind);
+                }
+            }
+        }
+    }
+
+    /// Krátký popis bloků pro okno Info (prázdný, když soubor žádné nemá).
+    pub fn describe(&self) -> Vec<String> {
+        let mut out = Vec::new();
+        if let Some(icc) = &self.icc {
+            out.push(format!("ICC profile ({} B)", icc.len()));
+        }
+        if !self.pyramid.is_empty() {
+            out.push(format!("pyramid ({} levels)", self.pyramid.len()));
+        }
+        for kind in &self.unknown {
+            out.push(format!("unknown {}", fourcc(*kind)));
+        }
+        out
+    }
+}
+
+/// Počet dlaždic úrovně `w`×`h` při velikosti dlaždice z hlavičky.
+fn level_tiles(hdr: &CTIHeader, w: u32, h: u32) -> usize {
+    let ts = hdr.tile_size.max(1);
+    w.div_ceil(ts) as usize * h.div_ceil(ts) as usize
+}
+
+fn read_pyramid(body: &[u8], hdr: &CTIHeader) -> Result<Vec<PyramidLevel>> {
+    let mut cur = body;
+    let count = read_u32_le(&mut cur)?;
+    let mut levels = Vec::new();
+    for _ in 0..count {
+        let width = read_u32_le(&mut cur)?;
+        let height = read_u32_le(&mut cur)?;
+        let n = level_tiles(hdr, width, height);
+        ensure!(
+            n.checked_mul(INDEX_ENTRY_SIZE)
+                .is_some_and(|len| cur.len() >= len),
+            "Truncated pyramid level {width}x{height}"
+        );
+        let tiles = read_indices(&mut cur, n)?
+            .into_iter()
+            .map(|t| TileEntry {
+                offset: t.offset,
+                compressed_size: t.compressed_size,
+                original_size: t.original_size,
+                crc: t.crc32,
+            })
+            .collect();
+        levels.push(PyramidLevel {
+            width,
+            height,
+            tiles,
+        });
+    }
+    Ok(levels)
 }
 
 #[repr(u8)]
@@ -30,6 +410,12 @@ pub enum CompressionId {
     Predictive = 4,
     Zstd = 10,
     Lz4 = 11,
+    /// Jen s funkcí `brotli`.
+    Brotli = 12,
+    /// Raw Deflate (RFC 1951, bez zlib hlavičky); jen s funkcí `deflate`.
+    Deflate = 13,
+    /// Ztrátová DCT (viz [`crate::dct`]) podle [`CTIHeader::quality`].
+    Dct = 20,
     Unknown(u8),
 }
 impl From<u8> for CompressionId {
@@ -42,105 +428,1948 @@ impl From<u8> for CompressionId {
             4 => Self::Predictive,
             10 => Self::Zstd,
             11 => Self::Lz4,
+            12 => Self::Brotli,
+            13 => Self::Deflate,
+            20 => Self::Dct,
             x => Self::Unknown(x),
         }
     }
 }
-impl CompressionId {
-    pub fn as_str(self) -> &'static str {
+impl CompressionId {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CompressionId::None => "None",
+            CompressionId::Rle => "RLE",
+            CompressionId::Lz77 => "LZ77",
+            CompressionId::Delta => "Delta",
+            CompressionId::Predictive => "Predictive",
+            CompressionId::Zstd => "Zstd",
+            CompressionId::Lz4 => "LZ4",
+            CompressionId::Brotli => "Brotli",
+            CompressionId::Deflate => "Deflate",
+            CompressionId::Dct => "DCT",
+            CompressionId::Unknown(_) => "Unknown",
+        }
+    }
+
+    /// Numeric id as stored in the header.
+    pub fn id(self) -> u8 {
+        match self {
+            CompressionId::None => 0,
+            CompressionId::Rle => 1,
+            CompressionId::Lz77 => 2,
+            CompressionId::Delta => 3,
+            CompressionId::Predictive => 4,
+            CompressionId::Zstd => 10,
+            CompressionId::Lz4 => 11,
+            CompressionId::Brotli => 12,
+            CompressionId::Deflate => 13,
+            CompressionId::Dct => 20,
+            CompressionId::Unknown(v) => v,
+        }
+    }
+
+    /// Kodeky, které tohle sestavení umí zapsat (Brotli a Deflate jen s příslušnou funkcí).
+    pub fn encoders() -> Vec<CompressionId> {
+        let mut out = vec![CompressionId::Zstd, CompressionId::Lz4];
+        if cfg!(feature = "brotli") {
+            out.push(CompressionId::Brotli);
+        }
+        if cfg!(feature = "deflate") {
+            out.push(CompressionId::Deflate);
+        }
+        out.push(CompressionId::Dct);
+        out.push(CompressionId::None);
+        out
+    }
+
+    /// Používá kodek úroveň komprese ([`EncodeParams::level`])?
+    pub fn has_level(self) -> bool {
+        matches!(
+            self,
+            CompressionId::Zstd
+                | CompressionId::Brotli
+                | CompressionId::Deflate
+                | CompressionId::Dct
+        )
+    }
+
+    /// Úroveň komprese, když ji nejde převzít ze souboru (do hlavičky se neukládá).
+    pub fn default_level(self) -> i32 {
+        match self {
+            CompressionId::Brotli => 9,
+            CompressionId::Deflate => 6,
+            _ => EncodeParams::default().level,
+        }
+    }
+
+    /// Ztrátový kodek (řídí se [`EncodeParams::quality`]).
+    pub fn is_lossy(self) -> bool {
+        matches!(self, CompressionId::Dct)
+    }
+
+    /// Human-readable description; includes numeric value for Unknown(_).
+    pub fn describe(self) -> String {
+        match self {
+            CompressionId::Unknown(v) => format!("Unknown({v})"),
+            other => other.as_str().to_string(),
+        }
+    }
+}
+
+/// Odkud se soubor čte: lokální soubor, (s funkcí `remote`) URL, nebo libovolný
+/// `Read + Seek` – např. soubor načtený do paměti v prohlížeči (wasm32).
+trait Source: Read + Seek {}
+
+impl<T: Read + Seek> Source for T {}
+
+/// Je `path` adresa HTTP(S)? Takový soubor čte jen sestavení s funkcí `remote`.
+pub fn is_url(path: &Path) -> bool {
+    path.to_str()
+        .is_some_and(|s| s.starts_with("https://") || s.starts_with("http://"))
+}
+
+fn open(path: &Path) -> Result<Box<dyn Source>> {
+    if is_url(path) {
+        #[cfg(feature = "remote")]
+        return Ok(Box::new(crate::remote::RemoteFile::open(
+            path.to_str().unwrap_or_default(),
+        )?));
+        #[cfg(not(feature = "remote"))]
+        bail!("Opening URLs needs a build with the `remote` feature");
+    }
+    Ok(Box::new(File::open(path)?))
+}
+
+pub struct CTIDecoder;
+
+impl CTIDecoder {
+    /// Načte pouze hlavičku (rychlá kontrola metadat).
+    pub fn info<P: AsRef<Path>>(path: P) -> Result<CTIHeader> {
+        Self::info_from(open(path.as_ref())?)
+    }
+
+    /// Jako [`info`](Self::info), čte z `reader` místo souboru.
+    pub fn info_from<R: Read>(reader: R) -> Result<CTIHeader> {
+        read_header(&mut BufReader::new(reader))
+    }
+
+    /// Načte blok metadat (prázdný, když ho soubor nemá).
+    pub fn metadata<P: AsRef<Path>>(path: P) -> Result<CTIMetadata> {
+        Self::metadata_from(open(path.as_ref())?)
+    }
+
+    /// Jako [`metadata`](Self::metadata), čte z `reader` místo souboru.
+    pub fn metadata_from<R: Read + Seek>(reader: R) -> Result<CTIMetadata> {
+        Ok(Self::extensions_from(reader)?.metadata)
+    }
+
+    /// Načte rozšiřující bloky (u verze 1 jen metadata).
+    pub fn extensions<P: AsRef<Path>>(path: P) -> Result<CTIExtensions> {
+        Self::extensions_from(open(path.as_ref())?)
+    }
+
+    /// Jako [`extensions`](Self::extensions), čte z `reader` místo souboru.
+    pub fn extensions_from<R: Read + Seek>(reader: R) -> Result<CTIExtensions> {
+        let mut br = BufReader::new(reader);
+        let hdr = read_header(&mut br)?;
+        if !hdr.has_chunks() && !hdr.has_metadata() {
+            return Ok(CTIExtensions::default());
+        }
+        br.seek(SeekFrom::Start(extensions_offset(&hdr)))?;
+        if hdr.has_chunks() {
+            CTIExtensions::read(&mut br, &hdr)
+        } else {
+            Ok(CTIExtensions {
+                metadata: CTIMetadata::read(&mut br)?,
+                ..Default::default()
+            })
+        }
+    }
+
+    /// SHA-256 hlavičky a indexu dlaždic. Index nese CRC každé dlaždice, takže otisk
+    /// rozliší obsah souboru, aniž by se dlaždice četly (klíč pro cache náhledů).
+    pub fn fingerprint<P: AsRef<Path>>(path: P) -> Result<[u8; 32]> {
+        let mut br = BufReader::new(open(path.as_ref())?);
+        let hdr = read_header(&mut br)?;
+        let offset = index_offset(&mut br, &hdr)?;
+        br.seek(SeekFrom::Start(offset))?;
+        let index = read_vec(&mut br, hdr.index_len() * INDEX_ENTRY_SIZE)?;
+        let mut head = [0u8; HEADER_SIZE];
+        br.seek(SeekFrom::Start(0))?;
+        br.read_exact(&mut head)?;
+        let mut hasher = Sha256::new();
+        hasher.update(head);
+        hasher.update(&index);
+        Ok(hasher.finalize().into())
+    }
+
+    /// Počet snímků v souboru (1 u běžného obrázku).
+    pub fn frame_count<P: AsRef<Path>>(path: P) -> Result<u32> {
+        Ok(Self::info(path)?.frames)
+    }
+
+    /// Dekóduje celý obrázek do RAW bufferu (interleaved) a vrátí (header, data).
+    /// U sekvence jde o první snímek.
+    pub fn decode_file<P: AsRef<Path>>(path: P) -> Result<(CTIHeader, Vec<u8>)> {
+        Self::decode_frame(path, 0)
+    }
+
+    /// Dekóduje snímek `n` (od nuly) sekvence.
+    pub fn decode_frame<P: AsRef<Path>>(path: P, n: u32) -> Result<(CTIHeader, Vec<u8>)> {
+        Self::decode_frame_from(open(path.as_ref())?, n)
+    }
+
+    /// Snímek HDR souboru (L32F, RGB32F) jako vzorky f32 (interleaved).
+    pub fn decode_frame_f32<P: AsRef<Path>>(path: P, n: u32) -> Result<(CTIHeader, Vec<f32>)> {
+        let (hdr, raw) = Self::decode_frame(path, n)?;
+        ensure!(
+            is_float(hdr.color_type),
+            "Color type {} is not a float type",
+            hdr.color_type
+        );
+        Ok((hdr, crate::hdr::samples(&raw)))
+    }
+
+    /// Jako [`decode_frame`](Self::decode_frame), čte z `reader` místo souboru.
+    pub fn decode_frame_from<R: Read + Seek>(reader: R, n: u32) -> Result<(CTIHeader, Vec<u8>)> {
+        Self::decode_frame_from_with_options(reader, n, &DecodeOptions::default())
+    }
+
+    /// Jako [`decode_frame`](Self::decode_frame), ale soubor nad limity `opts` odmítne
+    /// dřív, než alokuje index nebo buffer snímku.
+    pub fn decode_frame_with_options<P: AsRef<Path>>(
+        path: P,
+        n: u32,
+        opts: &DecodeOptions,
+    ) -> Result<(CTIHeader, Vec<u8>)> {
+        Self::decode_frame_from_with_options(open(path.as_ref())?, n, opts)
+    }
+
+    /// Jako [`decode_frame_with_options`](Self::decode_frame_with_options), čte z `reader`
+    /// místo souboru.
+    pub fn decode_frame_from_with_options<R: Read + Seek>(
+        reader: R,
+        n: u32,
+        opts: &DecodeOptions,
+    ) -> Result<(CTIHeader, Vec<u8>)> {
+        Self::decode_impl(reader, n, None, opts, &mut |_, e| Err(e), &mut |_, _| true)
+    }
+
+    /// Jako [`decode_file`](Self::decode_file), ale poškozené dlaždice (CRC, dekomprese,
+    /// zkrácený soubor) nahradí zástupným vzorem a vrátí jejich seznam. Chyba hlavičky nebo
+    /// indexu je dál fatální.
+    pub fn decode_file_lossy<P: AsRef<Path>>(
+        path: P,
+    ) -> Result<(CTIHeader, Vec<u8>, Vec<BadTile>)> {
+        Self::decode_frame_progressive(path, 0, |_, _| true)
+    }
+
+    /// Jako [`decode_file_lossy`](Self::decode_file_lossy) pro snímek `n` sekvence.
+    pub fn decode_frame_lossy<P: AsRef<Path>>(
+        path: P,
+        n: u32,
+    ) -> Result<(CTIHeader, Vec<u8>, Vec<BadTile>)> {
+        Self::decode_frame_progressive(path, n, |_, _| true)
+    }
+
+    /// Jako [`decode_frame_lossy`](Self::decode_frame_lossy) s limity `opts`; soubor nad
+    /// nimi je chyba, ne poškozené dlaždice.
+    pub fn decode_frame_lossy_with_options<P: AsRef<Path>>(
+        path: P,
+        n: u32,
+        opts: &DecodeOptions,
+    ) -> Result<(CTIHeader, Vec<u8>, Vec<BadTile>)> {
+        Self::progressive_impl(open(path.as_ref())?, n, None, opts, |_, _| true)
+    }
+
+    /// Jako [`decode_frame_lossy`](Self::decode_frame_lossy); po každé dlaždici zavolá
+    /// `on_tile(index ve snímku, pixely dlaždice)` (např. pro postupné vykreslení). Vrácené
+    /// `false` dekódování přeruší.
+    pub fn decode_frame_progressive<P: AsRef<Path>>(
+        path: P,
+        n: u32,
+        on_tile: impl FnMut(u32, &[u8]) -> bool,
+    ) -> Result<(CTIHeader, Vec<u8>, Vec<BadTile>)> {
+        Self::decode_frame_progressive_from(open(path.as_ref())?, n, on_tile)
+    }
+
+    /// Jako [`decode_frame_progressive`](Self::decode_frame_progressive), čte z `reader`
+    /// místo souboru.
+    pub fn decode_frame_progressive_from<R: Read + Seek>(
+        reader: R,
+        n: u32,
+        on_tile: impl FnMut(u32, &[u8]) -> bool,
+    ) -> Result<(CTIHeader, Vec<u8>, Vec<BadTile>)> {
+        Self::progressive_impl(reader, n, None, &DecodeOptions::default(), on_tile)
+    }
+
+    /// Jako [`decode_frame_progressive_from`](Self::decode_frame_progressive_from) s limity
+    /// `opts`.
+    pub fn decode_frame_progressive_from_with_options<R: Read + Seek>(
+        reader: R,
+        n: u32,
+        opts: &DecodeOptions,
+        on_tile: impl FnMut(u32, &[u8]) -> bool,
+    ) -> Result<(CTIHeader, Vec<u8>, Vec<BadTile>)> {
+        Self::progressive_impl(reader, n, None, opts, on_tile)
+    }
+
+    /// Jako [`decode_frame_progressive`](Self::decode_frame_progressive); dlaždice, které už
+    /// jsou v `cache`, se nečtou znovu a nově přečtené se do ní uloží.
+    pub fn decode_frame_progressive_cached<P: AsRef<Path>>(
+        path: P,
+        n: u32,
+        cache: &TileCache,
+        on_tile: impl FnMut(u32, &[u8]) -> bool,
+    ) -> Result<(CTIHeader, Vec<u8>, Vec<BadTile>)> {
+        let path = path.as_ref();
+        let limits = DecodeOptions::default();
+        Self::progressive_impl(open(path)?, n, Some(&cache.file(path)), &limits, on_tile)
+    }
+
+    fn progressive_impl<R: Read + Seek>(
+        reader: R,
+        n: u32,
+        cache: Option<&FileTiles>,
+        limits: &DecodeOptions,
+        mut on_tile: impl FnMut(u32, &[u8]) -> bool,
+    ) -> Result<(CTIHeader, Vec<u8>, Vec<BadTile>)> {
+        let mut bad = Vec::new();
+        let (hdr, out) = Self::decode_impl(
+            reader,
+            n,
+            cache,
+            limits,
+            &mut |index, e| {
+                bad.push(BadTile {
+                    index,
+                    error: format!("{e:#}"),
+                });
+                Ok(())
+            },
+            &mut on_tile,
+        )?;
+        Ok((hdr, out, bad))
+    }
+
+    /// Jako [`decode_frame`](Self::decode_frame), ale pixely zapíše přímo do `buf` bez
+    /// mezilehlého bufferu snímku: řádek `y` začíná na `y * stride` (`stride` ≥ šířka ×
+    /// bajty na pixel). Velikost lze zjistit předem z [`info`](Self::info).
+    pub fn decode_into<P: AsRef<Path>>(
+        path: P,
+        frame: u32,
+        buf: &mut [u8],
+        stride: usize,
+    ) -> Result<CTIHeader> {
+        Self::decode_into_from(open(path.as_ref())?, frame, buf, stride)
+    }
+
+    /// Jako [`decode_into`](Self::decode_into), čte z `reader` místo souboru.
+    pub fn decode_into_from<R: Read + Seek>(
+        reader: R,
+        frame: u32,
+        buf: &mut [u8],
+        stride: usize,
+    ) -> Result<CTIHeader> {
+        let limits = DecodeOptions::default();
+        Self::decode_into_from_with_options(reader, frame, buf, stride, &limits)
+    }
+
+    /// Jako [`decode_into_from`](Self::decode_into_from) s limity `opts`; do
+    /// `max_total_bytes` se počítá jen index, buffer dodává volající.
+    pub fn decode_into_from_with_options<R: Read + Seek>(
+        reader: R,
+        frame: u32,
+        buf: &mut [u8],
+        stride: usize,
+        opts: &DecodeOptions,
+    ) -> Result<CTIHeader> {
+        let (hdr, indices, mut file) = open_frame(reader, frame, opts, OutBuffer::Caller)?;
+        let bpp = bytes_per_pixel(hdr.color_type)?;
+        check_buffer(buf.len(), stride, hdr.width, hdr.height, bpp)?;
+        decode_tiles(
+            &mut file,
+            &hdr,
+            &indices,
+            (frame, None),
+            buf,
+            stride,
+            &mut |_, e| Err(e),
+            &mut |_, _| true,
+        )?;
+        Ok(hdr)
+    }
+
+    /// `on_error` rozhodne, zda chyba dlaždice ukončí dekódování (`Err`), nebo se dlaždice
+    /// vyplní zástupným vzorem (`Ok`); `on_tile` dostane každou hotovou dlaždici.
+    fn decode_impl<R: Read + Seek>(
+        reader: R,
+        frame: u32,
+        cache: Option<&FileTiles>,
+        limits: &DecodeOptions,
+        on_error: &mut dyn FnMut(u32, anyhow::Error) -> Result<()>,
+        on_tile: &mut dyn FnMut(u32, &[u8]) -> bool,
+    ) -> Result<(CTIHeader, Vec<u8>)> {
+        let (hdr, indices, mut file) = open_frame(reader, frame, limits, OutBuffer::Frame)?;
+        let bpp = bytes_per_pixel(hdr.color_type)? as usize;
+        let stride = hdr.width as usize * bpp;
+        let mut out = vec![0u8; stride * hdr.height as usize];
+        decode_tiles(
+            &mut file,
+            &hdr,
+            &indices,
+            (frame, cache),
+            &mut out,
+            stride,
+            on_error,
+            on_tile,
+        )?;
+        Ok((hdr, out))
+    }
+
+    /// Dekóduje jen výřez `(x, y, w, h)` snímku `frame`: přečte pouze dlaždice, které ho
+    /// protínají. Vrací hlavičku celého souboru a RAW data výřezu (`w × h` pixelů).
+    pub fn decode_region<P: AsRef<Path>>(
+        path: P,
+        frame: u32,
+        region: (u32, u32, u32, u32),
+    ) -> Result<(CTIHeader, Vec<u8>)> {
+        Self::decode_region_from(open(path.as_ref())?, frame, region)
+    }
+
+    /// Jako [`decode_region`](Self::decode_region), čte z `reader` místo souboru.
+    pub fn decode_region_from<R: Read + Seek>(
+        reader: R,
+        frame: u32,
+        region: (u32, u32, u32, u32),
+    ) -> Result<(CTIHeader, Vec<u8>)> {
+        Self::region_impl(reader, frame, None, region, &DecodeOptions::default())
+    }
+
+    /// Jako [`decode_region`](Self::decode_region) s limity `opts`; `max_pixels` platí
+    /// pro celý obrázek, `max_total_bytes` pro buffer výřezu a index.
+    pub fn decode_region_with_options<P: AsRef<Path>>(
+        path: P,
+        frame: u32,
+        region: (u32, u32, u32, u32),
+        opts: &DecodeOptions,
+    ) -> Result<(CTIHeader, Vec<u8>)> {
+        Self::decode_region_from_with_options(open(path.as_ref())?, frame, region, opts)
+    }
+
+    /// Jako [`decode_region_with_options`](Self::decode_region_with_options), čte
+    /// z `reader` místo souboru.
+    pub fn decode_region_from_with_options<R: Read + Seek>(
+        reader: R,
+        frame: u32,
+        region: (u32, u32, u32, u32),
+        opts: &DecodeOptions,
+    ) -> Result<(CTIHeader, Vec<u8>)> {
+        Self::region_impl(reader, frame, None, region, opts)
+    }
+
+    /// Jako [`decode_region`](Self::decode_region); dlaždice bere z `cache` a přečtené do
+    /// ní ukládá, takže opakované výřezy téže oblasti se nedekódují znovu.
+    pub fn decode_region_cached<P: AsRef<Path>>(
+        path: P,
+        frame: u32,
+        region: (u32, u32, u32, u32),
+        cache: &TileCache,
+    ) -> Result<(CTIHeader, Vec<u8>)> {
+        Self::decode_region_cached_with_options(
+            path,
+            frame,
+            region,
+            cache,
+            &DecodeOptions::default(),
+        )
+    }
+
+    /// Jako [`decode_region_cached`](Self::decode_region_cached) s limity `opts`.
+    pub fn decode_region_cached_with_options<P: AsRef<Path>>(
+        path: P,
+        frame: u32,
+        region: (u32, u32, u32, u32),
+        cache: &TileCache,
+        opts: &DecodeOptions,
+    ) -> Result<(CTIHeader, Vec<u8>)> {
+        let path = path.as_ref();
+        Self::region_impl(open(path)?, frame, Some(&cache.file(path)), region, opts)
+    }
+
+    fn region_impl<R: Read + Seek>(
+        reader: R,
+        frame: u32,
+        cache: Option<&FileTiles>,
+        region: (u32, u32, u32, u32),
+        limits: &DecodeOptions,
+    ) -> Result<(CTIHeader, Vec<u8>)> {
+        let out = OutBuffer::Region(region.2, region.3);
+        let (hdr, indices, mut file) = open_frame(reader, frame, limits, out)?;
+        check_region(&hdr, region)?;
+        let bpp = bytes_per_pixel(hdr.color_type)?;
+        let stride = region.2 as usize * bpp as usize;
+        let mut out = vec![0u8; stride * region.3 as usize];
+        let mut tile = |i: usize| fetch_tile(&mut file, &hdr, &indices[i], i, bpp, (frame, cache));
+        decode_region_tiles(&hdr, &mut tile, region, &mut out, stride)?;
+        Ok((hdr, out))
+    }
+
+    /// Jako [`decode_region`](Self::decode_region), ale výřez zapíše přímo do `buf`
+    /// s řádky po `stride` bajtech (viz [`decode_into`](Self::decode_into)).
+    pub fn decode_region_into<P: AsRef<Path>>(
+        path: P,
+        frame: u32,
+        region: (u32, u32, u32, u32),
+        buf: &mut [u8],
+        stride: usize,
+    ) -> Result<CTIHeader> {
+        Self::decode_region_into_from(open(path.as_ref())?, frame, region, buf, stride)
+    }
+
+    /// Jako [`decode_region_into`](Self::decode_region_into), čte z `reader` místo souboru.
+    pub fn decode_region_into_from<R: Read + Seek>(
+        reader: R,
+        frame: u32,
+        region: (u32, u32, u32, u32),
+        buf: &mut [u8],
+        stride: usize,
+    ) -> Result<CTIHeader> {
+        let limits = DecodeOptions::default();
+        Self::decode_region_into_from_with_options(reader, frame, region, buf, stride, &limits)
+    }
+
+    /// Jako [`decode_region_into_from`](Self::decode_region_into_from) s limity `opts`
+    /// (viz [`decode_into_from_with_options`](Self::decode_into_from_with_options)).
+    pub fn decode_region_into_from_with_options<R: Read + Seek>(
+        reader: R,
+        frame: u32,
+        region: (u32, u32, u32, u32),
+        buf: &mut [u8],
+        stride: usize,
+        opts: &DecodeOptions,
+    ) -> Result<CTIHeader> {
+        let (hdr, indices, mut file) = open_frame(reader, frame, opts, OutBuffer::Caller)?;
+        check_region(&hdr, region)?;
+        let bpp = bytes_per_pixel(hdr.color_type)?;
+        check_buffer(buf.len(), stride, region.2, region.3, bpp)?;
+        let mut tile = |i: usize| fetch_tile(&mut file, &hdr, &indices[i], i, bpp, (frame, None));
+        decode_region_tiles(&hdr, &mut tile, region, buf, stride)?;
+        Ok(hdr)
+    }
+}
+
+/// Stav jedné dlaždice podle [`CTIDecoder::diagnose`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TileStatus {
+    Ok,
+    /// Data dlaždice podle indexu leží (aspoň zčásti) za koncem souboru.
+    OutOfBounds,
+    /// Dlaždice se nedá rozbalit (poškozená komprimovaná data).
+    Decompress(String),
+    /// Rozbalená data nesedí s CRC z indexu.
+    Crc,
+    /// Rozbalená dlaždice má jinou velikost, než odpovídá jejím rozměrům.
+    WrongSize,
+}
+
+impl CTIDecoder {
+    /// Zkontroluje každou dlaždici snímku `frame` zvlášť (poloha v souboru, rozbalení, CRC,
+    /// velikost) a vrátí stavy po řádcích. Chyba jen když nejde přečíst hlavička nebo index.
+    pub fn diagnose<P: AsRef<Path>>(path: P, frame: u32) -> Result<(CTIHeader, Vec<TileStatus>)> {
+        Self::diagnose_with_options(path, frame, &DecodeOptions::default())
+    }
+
+    /// Jako [`diagnose`](Self::diagnose) s limity `opts`; dlaždice nad `max_tile_bytes`
+    /// odmítne celý soubor.
+    pub fn diagnose_with_options<P: AsRef<Path>>(
+        path: P,
+        frame: u32,
+        opts: &DecodeOptions,
+    ) -> Result<(CTIHeader, Vec<TileStatus>)> {
+        let file = open(path.as_ref())?;
+        let (hdr, indices, mut file) = open_frame(file, frame, opts, OutBuffer::Caller)?;
+        let len = file.seek(SeekFrom::End(0))?;
+        let bpp = bytes_per_pixel(hdr.color_type)?;
+        let mut out = Vec::with_capacity(indices.len());
+        for (i, t) in indices.iter().enumerate() {
+            let end = t.offset.checked_add(u64::from(t.compressed_size));
+            if end.is_none_or(|end| end > len) {
+                out.push(TileStatus::OutOfBounds);
+                continue;
+            }
+            let (w, h) = tile_dims(&hdr, i as u32 % hdr.tiles_x, i as u32 / hdr.tiles_x);
+            let stored_len = stored_tile_len(&hdr, w, h, bpp);
+            if t.original_size as usize != stored_len {
+                out.push(TileStatus::WrongSize);
+                continue;
+            }
+            file.seek(SeekFrom::Start(t.offset))?;
+            let mut comp = vec![0u8; t.compressed_size as usize];
+            file.read_exact(&mut comp)?;
+            let tile = match decompress_tile_with_size(hdr.compression, &comp, stored_len) {
+                Ok(tile) => tile,
+                Err(e) => {
+                    out.push(TileStatus::Decompress(format!("{e:#}")));
+                    continue;
+                }
+            };
+            out.push(if hdr.tile_crc(&tile) != t.crc32 {
+                TileStatus::Crc
+            } else if tile.len() != stored_len {
+                TileStatus::WrongSize
+            } else {
+                TileStatus::Ok
+            });
+        }
+        Ok((hdr, out))
+    }
+}
+
+/// Hlavička a index dlaždic snímku `frame`; vrací i čtenář pro čtení dlaždic. Limity
+/// `limits` (pro výstup `out`) ověří dřív, než se index načte.
+fn open_frame<R: Read + Seek>(
+    reader: R,
+    frame: u32,
+    limits: &DecodeOptions,
+    out: OutBuffer,
+) -> Result<(CTIHeader, Vec<TileIndex>, R)> {
+    let mut f = BufReader::new(reader);
+    let hdr = read_header(&mut f)?;
+    ensure!(
+        frame < hdr.frames,
+        "Frame {} out of range ({} frames)",
+        frame,
+        hdr.frames
+    );
+    limits.check_header(&hdr)?;
+    // Index dlaždic (jen požadovaného snímku)
+    let per_frame = hdr.tiles_per_frame();
+    let entries = per_frame * (frame as usize + 1);
+    let bpp = u64::from(bytes_per_pixel(hdr.color_type)?);
+    let out_bytes = match out {
+        OutBuffer::Frame => u64::from(hdr.width) * u64::from(hdr.height) * bpp,
+        OutBuffer::Region(w, h) => u64::from(w) * u64::from(h) * bpp,
+        OutBuffer::Caller => 0,
+    };
+    let index_bytes = (entries * std::mem::size_of::<TileIndex>()) as u64;
+    limits.check_memory(index_bytes.saturating_add(out_bytes))?;
+    seek_index(&mut f, &hdr)?;
+    let indices = read_indices(&mut f, entries)?.split_off(per_frame * frame as usize);
+    if let Some(max) = limits.max_tile_bytes {
+        for (i, t) in indices.iter().enumerate() {
+            let size = t.compressed_size.max(t.original_size);
+            ensure!(
+                u64::from(size) <= max,
+                "Tile {i} of {size} bytes exceeds the decode limit of {max} bytes"
+            );
+        }
+    }
+    Ok((hdr, indices, f.into_inner()))
+}
+
+/// Buffer, do kterého [`open_frame`] povolí dekódovat (počítá se do
+/// [`DecodeOptions::max_total_bytes`]).
+#[derive(Debug, Clone, Copy)]
+enum OutBuffer {
+    /// Dekodér alokuje celý snímek.
+    Frame,
+    /// Dekodér alokuje výřez `w × h`.
+    Region(u32, u32),
+    /// Buffer dodal volající.
+    Caller,
+}
+
+/// Ověří, že se obrázek `w × h` s řádky po `stride` bajtech vejde do bufferu délky `len`.
+fn check_buffer(len: usize, stride: usize, w: u32, h: u32, bpp: u32) -> Result<()> {
+    let row = w as usize * bpp as usize;
+    ensure!(
+        stride >= row,
+        "Stride {stride} is smaller than a row ({row} bytes)"
+    );
+    let needed = stride
+        .checked_mul((h as usize).saturating_sub(1))
+        .and_then(|n| n.checked_add(row))
+        .ok_or_else(|| anyhow!("{h} rows of {stride} bytes do not fit in memory"))?;
+    ensure!(len >= needed, "Buffer too small: {len} < {needed} bytes");
+    Ok(())
+}
+
+/// Ověří, že výřez `(x, y, w, h)` je neprázdný a leží v obrázku.
+fn check_region(hdr: &CTIHeader, (x, y, w, h): (u32, u32, u32, u32)) -> Result<()> {
+    ensure!(
+        w > 0
+            && h > 0
+            && u64::from(x) + u64::from(w) <= u64::from(hdr.width)
+            && u64::from(y) + u64::from(h) <= u64::from(hdr.height),
+        "Region {w}x{h} at {x},{y} is outside the {}x{} image",
+        hdr.width,
+        hdr.height
+    );
+    Ok(())
+}
+
+/// Přímé čtení komprimovaných dlaždic snímku do `out` (řádky po `stride` bajtech).
+/// `cache` = snímek a případná cache dlaždic souboru.
+#[allow(clippy::too_many_arguments)]
+fn decode_tiles(
+    file: &mut dyn Source,
+    hdr: &CTIHeader,
+    indices: &[TileIndex],
+    cache: (u32, Option<&FileTiles>),
+    out: &mut [u8],
+    stride: usize,
+    on_error: &mut dyn FnMut(u32, anyhow::Error) -> Result<()>,
+    on_tile: &mut dyn FnMut(u32, &[u8]) -> bool,
+) -> Result<()> {
+    let bpp = bytes_per_pixel(hdr.color_type)?;
+    for (i, t) in indices.iter().enumerate() {
+        let tx = (i as u32) % hdr.tiles_x;
+        let ty = (i as u32) / hdr.tiles_x;
+        let (tile_w, tile_h) = tile_dims(hdr, tx, ty);
+        let tile = match fetch_tile(file, hdr, t, i, bpp, cache) {
+            Ok(tile) => tile,
+            Err(e) => {
+                on_error(i as u32, e)?;
+                Arc::new(placeholder_tile(tile_w, tile_h, hdr.color_type))
+            }
+        };
+
+        blit_tile(out, stride, &tile, hdr, bpp, tx, ty)?;
+        ensure!(on_tile(i as u32, &tile), "Decoding cancelled");
+    }
+    Ok(())
+}
+
+/// Výřez `(x, y, w, h)` do `out` (řádky po `stride` bajtech); přes `tile` (rozbalená
+/// dlaždice podle pořadí ve snímku) čte jen dlaždice, které ho protínají.
+fn decode_region_tiles(
+    hdr: &CTIHeader,
+    tile: &mut dyn FnMut(usize) -> Result<Arc<Vec<u8>>>,
+    (x, y, w, h): (u32, u32, u32, u32),
+    out: &mut [u8],
+    stride: usize,
+) -> Result<()> {
+    check_region(hdr, (x, y, w, h))?;
+    let bpp = bytes_per_pixel(hdr.color_type)?;
+    let ts = hdr.tile_size;
+    for ty in y / ts..=(y + h - 1) / ts {
+        for tx in x / ts..=(x + w - 1) / ts {
+            let tile = tile((ty * hdr.tiles_x + tx) as usize)?;
+            let (tile_w, tile_h) = tile_dims(hdr, tx, ty);
+            // průnik dlaždice s výřezem v souřadnicích obrázku
+            let (x0, x1) = ((tx * ts).max(x), (tx * ts + tile_w).min(x + w));
+            let (y0, y1) = ((ty * ts).max(y), (ty * ts + tile_h).min(y + h));
+            let px = bpp as usize;
+            let len = (x1 - x0) as usize * px;
+            for py in y0..y1 {
+                let src =
+                    ((py - ty * ts) as usize * tile_w as usize + (x0 - tx * ts) as usize) * px;
+                let dst = (py - y) as usize * stride + (x0 - x) as usize * px;
+                out[dst..dst + len].copy_from_slice(&tile[src..src + len]);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Otevřený soubor pro čtení jednotlivých dlaždic: hlavička a index se načtou jednou,
+/// každá dlaždice pak stojí jeden seek a čtení (pro vlastní skládání, např. C rozhraní).
+pub struct CTITileReader {
+    file: Box<dyn Source>,
+    hdr: CTIHeader,
+    indices: Vec<TileIndex>,
+}
+
+impl CTITileReader {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_reader(open(path.as_ref())?)
+    }
+
+    pub fn from_reader<R: Read + Seek + 'static>(reader: R) -> Result<Self> {
+        let mut f = BufReader::new(reader);
+        let hdr = read_header(&mut f)?;
+        seek_index(&mut f, &hdr)?;
+        let indices = read_indices(&mut f, hdr.index_len())?;
+        Ok(Self {
+            file: Box::new(f.into_inner()),
+            hdr,
+            indices,
+        })
+    }
+
+    pub fn header(&self) -> &CTIHeader {
+        &self.hdr
+    }
+
+    /// Rozbalená dlaždice `(tx, ty)` snímku `frame`: [`tile_dims`] pixelů po řádcích.
+    pub fn decode_tile(&mut self, frame: u32, tx: u32, ty: u32) -> Result<Vec<u8>> {
+        let at = self.index_of(frame, tx, ty)?;
+        let t = self.indices[at];
+        let i = (ty * self.hdr.tiles_x + tx) as usize;
+        let bpp = bytes_per_pixel(self.hdr.color_type)?;
+        read_tile(&mut *self.file, &self.hdr, &t, i, bpp)
+    }
+
+    /// Položka indexu dlaždice `(tx, ty)` snímku `frame` (data se nečtou).
+    pub fn tile_entry(&self, frame: u32, tx: u32, ty: u32) -> Result<TileEntry> {
+        let t = self.indices[self.index_of(frame, tx, ty)?];
+        Ok(TileEntry {
+            offset: t.offset,
+            compressed_size: t.compressed_size,
+            original_size: t.original_size,
+            crc: t.crc32,
+        })
+    }
+
+    /// Pozice dlaždice v indexu (kontroluje rozsah snímku a dlaždice).
+    fn index_of(&self, frame: u32, tx: u32, ty: u32) -> Result<usize> {
+        let hdr = &self.hdr;
+        ensure!(
+            frame < hdr.frames,
+            "Frame {} out of range ({} frames)",
+            frame,
+            hdr.frames
+        );
+        ensure!(
+            tx < hdr.tiles_x && ty < hdr.tiles_y,
+            "Tile {tx},{ty} out of range ({} x {} tiles)",
+            hdr.tiles_x,
+            hdr.tiles_y
+        );
+        let i = (ty * hdr.tiles_x + tx) as usize;
+        Ok(hdr.tiles_per_frame() * frame as usize + i)
+    }
+}
+
+/// Položka indexu dlaždic: kde dlaždice v souboru leží, jak je velká a její kontrolní součet
+/// (CRC32, nebo CRC32C podle [`CTIHeader::has_crc32c`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileEntry {
+    pub offset: u64,
+    pub compressed_size: u32,
+    pub original_size: u32,
+    pub crc: u32,
+}
+
+/// Dlaždice `i` z cache (pokud je), jinak přečtená přes [`read_tile`] a do cache uložená.
+fn fetch_tile(
+    file: &mut dyn Source,
+    hdr: &CTIHeader,
+    t: &TileIndex,
+    i: usize,
+    bpp: u32,
+    (frame, cache): (u32, Option<&FileTiles>),
+) -> Result<Arc<Vec<u8>>> {
+    if let Some(tile) = cache.and_then(|c| c.get(frame, i as u32)) {
+        return Ok(tile);
+    }
+    let tile = Arc::new(read_tile(file, hdr, t, i, bpp)?);
+    if let Some(c) = cache {
+        c.insert(frame, i as u32, tile.clone());
+    }
+    Ok(tile)
+}
+
+/// Přečte, rozbalí a zkontroluje dlaždici `i` (CRC, velikost); RCT vrátí zpět.
+fn read_tile(
+    file: &mut dyn Source,
+    hdr: &CTIHeader,
+    t: &TileIndex,
+    i: usize,
+    bpp: u32,
+) -> Result<Vec<u8>> {
+    unpack_tile(hdr, t, i, bpp, || {
+        file.seek(SeekFrom::Start(t.offset))?;
+        read_vec(file, t.compressed_size as usize)
+    })
+}
+
+/// Jako [`read_tile`], komprimovaná data dodá `payload` (zavolá se až po kontrole
+/// velikosti z indexu).
+fn unpack_tile(
+    hdr: &CTIHeader,
+    t: &TileIndex,
+    i: usize,
+    bpp: u32,
+    payload: impl FnOnce() -> Result<Vec<u8>>,
+) -> Result<Vec<u8>> {
+    let (tile_w, tile_h) = tile_dims(hdr, i as u32 % hdr.tiles_x, i as u32 / hdr.tiles_x);
+    let stored_len = stored_tile_len(hdr, tile_w, tile_h, bpp);
+    // velikost z indexu určuje alokaci při rozbalení, proto se ověří předem
+    ensure!(
+        t.original_size as usize == stored_len,
+        "Wrong size of tile {}",
+        i
+    );
+    let comp = payload()?;
+
+    let mut tile = decompress_tile_with_size(hdr.compression, &comp, stored_len)?;
+    ensure!(hdr.tile_crc(&tile) == t.crc32, "CRC mismatch at tile {}", i);
+    ensure!(tile.len() == stored_len, "Wrong size of tile {}", i);
+    if CompressionId::from(hdr.compression).is_lossy() {
+        return dct::inverse(&tile, tile_w, tile_h, hdr.color_type, hdr.quality);
+    }
+    if let Some(bits) = packed_bits(hdr.color_type) {
+        return Ok(unpack_gray(&tile, tile_w, tile_h, bits));
+    }
+    if (hdr.flags & FLAG_RCT) != 0 {
+        match hdr.color_type {
+            3 => simd::rct_inverse_rgb8(&mut tile),
+            5 => simd::rct_inverse_rgb16(&mut tile),
+            _ => {}
+        }
+    }
+    Ok(tile)
+}
+
+/// Dlaždice, kterou [`CTIDecoder::decode_file_lossy`] nahradil zástupným vzorem.
+#[derive(Debug, Clone)]
+pub struct BadTile {
+    /// Pořadí v indexu (po řádcích).
+    pub index: u32,
+    pub error: String,
+}
+
+/// Limity dekódování jednoho souboru pro služby, které otevírají cizí soubory (obdoba
+/// `image::Limits`). Výchozí hodnota nic neomezuje; překročení je chyba dřív, než se
+/// alokuje index nebo výstupní buffer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DecodeOptions {
+    /// Nejvíc pixelů obrázku (šířka × výška z hlavičky, i při dekódování výřezu).
+    pub max_pixels: Option<u64>,
+    /// Největší dlaždice v bajtech, komprimovaná i rozbalená podle indexu.
+    pub max_tile_bytes: Option<u64>,
+    /// Nejvíc bajtů, které dekódování alokuje: výstupní buffer a index dlaždic.
+    pub max_total_bytes: Option<u64>,
+}
+
+impl DecodeOptions {
+    /// Zkontroluje rozměr obrázku z hlavičky, např. před odpovědí o obrázku, který se
+    /// pak stejně nepůjde dekódovat.
+    pub fn check_header(&self, hdr: &CTIHeader) -> Result<()> {
+        let pixels = u64::from(hdr.width) * u64::from(hdr.height);
+        if let Some(max) = self.max_pixels {
+            ensure!(
+                pixels <= max,
+                "Image of {}x{} pixels exceeds the decode limit of {max} pixels",
+                hdr.width,
+                hdr.height
+            );
+        }
+        Ok(())
+    }
+
+    fn check_memory(&self, bytes: u64) -> Result<()> {
+        if let Some(max) = self.max_total_bytes {
+            ensure!(
+                bytes <= max,
+                "Decoding needs {bytes} bytes, more than the decode limit of {max} bytes"
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Parametry enkodéru.
+#[derive(Debug, Clone, Copy)]
+pub struct EncodeParams {
+    pub tile_size: u32,
+    pub compression: CompressionId,
+    /// Úroveň komprese: Zstd 1..=22, Brotli 0..=11, Deflate 0..=9 (vyšší se ořízne);
+    /// LZ4 ji ignoruje.
+    pub level: i32,
+    /// RCT pro RGB8/RGB16 – použije se jen tehdy, když je pro daný obrázek bezeztrátová.
+    pub rct: bool,
+    /// Připojit trailer s SHA-256 celého souboru.
+    pub file_hash: bool,
+    /// Dlaždice zabezpečit CRC32C ([`FLAG_CRC32C`]); starší dekodéry ho neznají.
+    pub crc32c: bool,
+    /// Kvalita ztrátového kodeku (1..=100, viz [`CompressionId::is_lossy`]); bezeztrátové
+    /// kodeky ji ignorují a do hlavičky zapíšou 100.
+    pub quality: u8,
+    /// Bitová hloubka pro L8 vstup: 8 = beze změny, 1 (bitonální) nebo 4 = uložit sbalené
+    /// jako L1/L4 (typicky skeny textu pro OCR). Barevné obrázky se nemění.
+    pub gray_bits: u8,
+}
+
+impl Default for EncodeParams {
+    fn default() -> Self {
+        Self {
+            tile_size: 256,
+            compression: CompressionId::Zstd,
+            level: 9,
+            rct: true,
+            file_hash: true,
+            crc32c: false,
+            quality: 90,
+            gray_bits: 8,
+        }
+    }
+}
+
+/// Odhad výsledku kódování ze vzorku dlaždic ([`CTIEncoder::estimate`]); odhady více
+/// souborů se sčítají ([`Estimate::add`]).
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Estimate {
+    /// Nekomprimovaná obrazová data.
+    pub raw: u64,
+    /// Odhadnutá velikost souboru.
+    pub size: u64,
+    /// Součet čtverců odchylek a počet porovnaných vzorků (jen ztrátový kodek).
+    pub sq_error: f64,
+    pub samples: u64,
+}
+
+impl Estimate {
+    pub fn add(&mut self, other: &Estimate) {
+        self.raw += other.raw;
+        self.size += other.size;
+        self.sq_error += other.sq_error;
+        self.samples += other.samples;
+    }
+
+    /// PSNR v dB vůči 8bit rozsahu; `None` = bezeztrátové (nic se neporovnávalo),
+    /// nekonečno = vzorek vyšel beze změny.
+    pub fn psnr(&self) -> Option<f64> {
+        if self.samples == 0 {
+            return None;
+        }
+        let mse = self.sq_error / self.samples as f64;
+        Some(if mse == 0.0 {
+            f64::INFINITY
+        } else {
+            10.0 * (255.0 * 255.0 / mse).log10()
+        })
+    }
+}
+
+pub struct CTIEncoder;
+
+impl CTIEncoder {
+    /// Zakóduje RAW buffer (interleaved, stejné uspořádání jako vrací `decode_file`) do souboru.
+    pub fn encode_file<P: AsRef<Path>>(
+        path: P,
+        width: u32,
+        height: u32,
+        color_type: u8,
+        data: &[u8],
+        params: &EncodeParams,
+    ) -> Result<CTIHeader> {
+        let meta = CTIMetadata::default();
+        Self::encode_file_with_metadata(path, width, height, color_type, data, params, &meta)
+    }
+
+    /// Jako [`encode_file`](Self::encode_file), navíc uloží blok metadat (pokud není prázdný).
+    pub fn encode_file_with_metadata<P: AsRef<Path>>(
+        path: P,
+        width: u32,
+        height: u32,
+        color_type: u8,
+        data: &[u8],
+        params: &EncodeParams,
+        meta: &CTIMetadata,
+    ) -> Result<CTIHeader> {
+        Self::encode_frames_with_metadata(path, width, height, color_type, &[data], params, meta)
+    }
+
+    /// Zakóduje sekvenci snímků stejného rozměru a typu do jednoho souboru. Jediný snímek
+    /// dá běžný soubor bez [`FLAG_FRAMES`].
+    pub fn encode_frames_with_metadata<P: AsRef<Path>>(
+        path: P,
+        width: u32,
+        height: u32,
+        color_type: u8,
+        frames: &[&[u8]],
+        params: &EncodeParams,
+        meta: &CTIMetadata,
+    ) -> Result<CTIHeader> {
+        let ext = CTIExtensions {
+            metadata: meta.clone(),
+            ..Default::default()
+        };
+        Self::encode_frames_with_extensions(path, width, height, color_type, frames, params, &ext)
+    }
+
+    /// Jako [`encode_frames_with_metadata`](Self::encode_frames_with_metadata), navíc
+    /// s rozšiřujícími bloky. Verze 2 se zapíše jen tehdy, když je soubor opravdu potřebuje;
+    /// samotná metadata zůstanou ve tvaru verze 1. Úrovně pyramidy enkodér nezapisuje.
+    pub fn encode_frames_with_extensions<P: AsRef<Path>>(
+        path: P,
+        width: u32,
+        height: u32,
+        color_type: u8,
+        frames: &[&[u8]],
+        params: &EncodeParams,
+        ext: &CTIExtensions,
+    ) -> Result<CTIHeader> {
+        let (color_type, bpp) = check_params(width, height, color_type, params)?;
+        ensure!(!frames.is_empty(), "No frames to encode");
+        // index úrovní by odkazoval na dlaždice, které enkodér nezapíše
+        ensure!(
+            ext.pyramid.is_empty(),
+            "Pyramid levels cannot be written by the encoder"
+        );
+        for data in frames {
+            ensure!(
+                data.len() == (width as usize) * (height as usize) * bpp as usize,
+                "Buffer size does not match {}x{} (color type {})",
+                width,
+                height,
+                color_type
+            );
+        }
+
+        let ts = params.tile_size;
+        let tiles_x = width.div_ceil(ts);
+        let tiles_y = height.div_ceil(ts);
+        // RCT je příznak celého souboru – musí být bezeztrátová pro všechny snímky
+        let use_rct = params.rct
+            && !params.compression.is_lossy()
+            && frames.iter().all(|data| match color_type {
+                3 => rct_is_lossless_rgb8(data),
+                5 => rct_is_lossless_rgb16(data),
+                _ => false,
+            });
+
+        let mut flags = use_rct as u16;
+        if !ext.metadata.is_empty() {
+            flags |= FLAG_METADATA;
+        }
+        if params.file_hash {
+            flags |= FLAG_FILE_HASH;
+        }
+        if frames.len() > 1 {
+            flags |= FLAG_FRAMES;
+        }
+        if params.crc32c {
+            flags |= FLAG_CRC32C;
+        }
+
+        let version = if ext.needs_chunks() {
+            VERSION_CHUNKS
+        } else {
+            1
+        };
+        let mut hdr = CTIHeader {
+            magic: *b"CTI1",
+            version,
+            flags,
+            width,
+            height,
+            tile_size: ts,
+            tiles_x,
+            tiles_y,
+            color_type,
+            compression: params.compression.id(),
+            quality: if params.compression.is_lossy() {
+                params.quality
+            } else {
+                100
+            },
+            frames: frames.len() as u32,
+            chunks_len: 0,
+        };
+        let meta_block = if hdr.has_chunks() {
+            let block = ext.to_bytes(&hdr)?;
+            hdr.chunks_len = block.len() as u32;
+            block
+        } else if ext.metadata.is_empty() {
+            Vec::new()
+        } else {
+            ext.metadata.to_bytes()?
+        };
+
+        let path = path.as_ref();
+        let res = write_tiles(path, &hdr, &meta_block, |i| {
+            let data = frames[i / (tiles_x * tiles_y) as usize];
+            let i = (i % (tiles_x * tiles_y) as usize) as u32;
+            let (tx, ty) = (i % tiles_x, i / tiles_x);
+            let tile = extract_tile(data, width, height, ts, bpp, tx, ty);
+            encode_tile(&hdr, params, use_rct, tile, tx, ty)
+        });
+        if let Err(e) = res {
+            // nedopsaný soubor by šel otevřít, ale index by ukazoval na nuly
+            let _ = std::fs::remove_file(path);
+            return Err(e);
+        }
+        Ok(hdr)
+    }
+
+    /// Odhad souboru, který by zapsal [`encode_file`](Self::encode_file): zkomprimuje se jen
+    /// až `sample` rovnoměrně rozložených dlaždic a jejich poměr komprese se použije na celý
+    /// obrázek. U ztrátového kodeku se dlaždice i dekódují a porovnají s originálem (PSNR).
+    pub fn estimate(
+        width: u32,
+        height: u32,
+        color_type: u8,
+        data: &[u8],
+        params: &EncodeParams,
+        sample: usize,
+    ) -> Result<Estimate> {
+        let (color_type, bpp) = check_params(width, height, color_type, params)?;
+        let ts = params.tile_size;
+        let (tiles_x, tiles_y) = (width.div_ceil(ts), height.div_ceil(ts));
+        let count = (tiles_x * tiles_y) as usize;
+        let (mut raw, mut comp) = (0u64, 0u64);
+        let (mut sq_error, mut samples) = (0f64, 0u64);
+        for i in (0..count).step_by(count.div_ceil(sample.max(1))) {
+            let (tx, ty) = (i as u32 % tiles_x, i as u32 / tiles_x);
+            let mut tile = extract_tile(data, width, height, ts, bpp, tx, ty);
+            if params.rct && !params.compression.is_lossy() {
+                match color_type {
+                    3 if rct_is_lossless_rgb8(&tile) => rct_forward_rgb8(&mut tile),
+                    5 if rct_is_lossless_rgb16(&tile) => rct_forward_rgb16(&mut tile),
+                    _ => {}
+                }
+            }
+            raw += tile.len() as u64;
+            let (tile_w, tile_h) = (ts.min(width - tx * ts), ts.min(height - ty * ts));
+            if let Some(bits) = packed_bits(color_type) {
+                tile = pack_gray(&tile, tile_w, bits);
+            }
+            if params.compression.is_lossy() {
+                let coeffs = dct::forward(&tile, tile_w, tile_h, color_type, params.quality)?;
+                let back = dct::inverse(&coeffs, tile_w, tile_h, color_type, params.quality)?;
+                sq_error += tile
+                    .iter()
+                    .zip(&back)
+                    .map(|(&a, &b)| (f64::from(a) - f64::from(b)).powi(2))
+                    .sum::<f64>();
+                samples += tile.len() as u64;
+                tile = coeffs;
+            }
+            comp += compress_tile(params.compression, params.level, &tile)?.len() as u64;
+        }
+        let pixels = data.len() as u64;
+        let body = (pixels * comp).checked_div(raw).unwrap_or(pixels);
+        let trailer = if params.file_hash { TRAILER_SIZE } else { 0 };
+        Ok(Estimate {
+            raw: pixels,
+            size: (HEADER_SIZE + count * INDEX_ENTRY_SIZE + trailer) as u64 + body,
+            sq_error,
+            samples,
+        })
+    }
+}
+
+/// Překóduje soubor `src` do kodeku `compression` (`level`, u ztrátového kodeku `quality`),
+/// případně s jinou velikostí dlaždic `tile_size`, a zapíše ho do `dst`; snímky, RCT,
+/// kontrolní součty i rozšiřující bloky zůstanou. Když se uložená podoba dlaždic nemění
+/// (stejná velikost a bezeztrátový ↔ bezeztrátový kodek, nebo DCT se stejnou kvalitou),
+/// dlaždice se jen rozbalí a znovu zkomprimují beze změny pixelů a CRC; jinak se obrázek
+/// dekóduje a zakóduje znovu (`true` ve výsledku, všechny snímky v paměti).
+pub fn transcode_file<P: AsRef<Path>, Q: AsRef<Path>>(
+    src: P,
+    dst: Q,
+    compression: CompressionId,
+    level: i32,
+    quality: u8,
+    tile_size: Option<u32>,
+) -> Result<(CTIHeader, bool)> {
+    let (src, dst) = (src.as_ref(), dst.as_ref());
+    let source = Rewrite::open(src, dst)?;
+    let hdr = source.hdr;
+    let params = EncodeParams {
+        tile_size: tile_size.unwrap_or(hdr.tile_size),
+        compression,
+        level,
+        rct: hdr.flags & FLAG_RCT != 0,
+        file_hash: hdr.has_file_hash(),
+        crc32c: hdr.has_crc32c(),
+        quality,
+        gray_bits: 8,
+    };
+    let from = CompressionId::from(hdr.compression);
+    let same_payload = params.tile_size == hdr.tile_size
+        && from.is_lossy() == compression.is_lossy()
+        && (!compression.is_lossy() || quality == hdr.quality);
+    if !same_payload {
+        let frames = (0..hdr.frames)
+            .map(|n| Ok(CTIDecoder::decode_frame(src, n)?.1))
+            .collect::<Result<Vec<_>>>()?;
+        let frames: Vec<&[u8]> = frames.iter().map(Vec::as_slice).collect();
+        let (w, h) = (hdr.width, hdr.height);
+        let out = CTIEncoder::encode_frames_with_extensions(
+            dst,
+            w,
+            h,
+            hdr.color_type,
+            &frames,
+            &params,
+            &source.ext,
+        )?;
+        return Ok((out, true));
+    }
+
+    let mut out = hdr;
+    out.compression = compression.id();
+    out.quality = if compression.is_lossy() { quality } else { 100 };
+    let meta_block = extension_block(&mut out, &source.ext)?;
+    let res = write_tiles(dst, &out, &meta_block, |i| {
+        let t = &source.indices[i];
+        let comp = source.payload(i)?;
+        let tile = decompress_tile_with_size(hdr.compression, &comp, t.original_size as usize)?;
+        ensure!(hdr.tile_crc(&tile) == t.crc32, "CRC mismatch at tile {}", i);
+        Ok((
+            compress_tile(compression, level, &tile)?,
+            t.original_size,
+            t.crc32,
+        ))
+    });
+    if let Err(e) = res {
+        let _ = std::fs::remove_file(dst);
+        return Err(e);
+    }
+    Ok((out, false))
+}
+
+/// Úprava celého obrázku pro [`edit_file`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edit {
+    /// Výřez `(x, y, šířka, výška)`.
+    Crop(u32, u32, u32, u32),
+    /// Otočení po směru hodinových ručiček o 90° (1), 180° (2) nebo 270° (3).
+    Rotate(u8),
+    /// Zrcadlení zleva doprava.
+    FlipHorizontal,
+    /// Zrcadlení shora dolů.
+    FlipVertical,
+}
+
+impl Edit {
+    /// Rozměry obrázku `w × h` po úpravě.
+    pub fn output_size(self, w: u32, h: u32) -> (u32, u32) {
         match self {
-            CompressionId::None => "None",
-            CompressionId::Rle => "RLE",
-            CompressionId::Lz77 => "LZ77",
-            CompressionId::Delta => "Delta",
-            CompressionId::Predictive => "Predictive",
-            CompressionId::Zstd => "Zstd",
-            CompressionId::Lz4 => "LZ4",
-            CompressionId::Unknown(_) => "Unknown",
+            Edit::Crop(_, _, cw, ch) => (cw, ch),
+            Edit::Rotate(r) if r % 2 == 1 => (h, w),
+            _ => (w, h),
         }
     }
 
-    /// Human-readable description; includes numeric value for Unknown(_).
-    pub fn describe(self) -> String {
+    /// Obdélník `(x, y, w, h)` výstupu → obdélník zdroje `w × h`, ze kterého vznikne.
+    fn source_rect(
+        self,
+        (w, h): (u32, u32),
+        (x, y, rw, rh): (u32, u32, u32, u32),
+    ) -> (u32, u32, u32, u32) {
         match self {
-            CompressionId::Unknown(v) => format!("Unknown({v})"),
-            other => other.as_str().to_string(),
+            Edit::Crop(cx, cy, _, _) => (cx + x, cy + y, rw, rh),
+            Edit::Rotate(1) => (y, h - x - rw, rh, rw),
+            Edit::Rotate(2) => (w - x - rw, h - y - rh, rw, rh),
+            Edit::Rotate(3) => (w - y - rh, x, rh, rw),
+            Edit::FlipHorizontal => (w - x - rw, y, rw, rh),
+            Edit::FlipVertical => (x, h - y - rh, rw, rh),
+            Edit::Rotate(_) => (x, y, rw, rh),
+        }
+    }
+
+    /// Přeskládá pixely výřezu zdroje `w × h` (`bpp` bajtů na pixel) podle úpravy.
+    fn apply(self, data: &[u8], w: u32, h: u32, bpp: u32) -> Vec<u8> {
+        let (w, h, bpp) = (w as usize, h as usize, bpp as usize);
+        let (ow, oh) = match self {
+            Edit::Rotate(1 | 3) => (h, w),
+            _ => (w, h),
+        };
+        let mut out = Vec::with_capacity(data.len());
+        for v in 0..oh {
+            for u in 0..ow {
+                let (sx, sy) = match self {
+                    Edit::Rotate(1) => (v, h - 1 - u),
+                    Edit::Rotate(2) => (w - 1 - u, h - 1 - v),
+                    Edit::Rotate(3) => (w - 1 - v, u),
+                    Edit::FlipHorizontal => (w - 1 - u, v),
+                    Edit::FlipVertical => (u, h - 1 - v),
+                    Edit::Crop(..) | Edit::Rotate(_) => (u, v),
+                };
+                let i = (sy * w + sx) * bpp;
+                out.extend_from_slice(&data[i..i + bpp]);
+            }
         }
+        out
     }
 }
 
-pub struct CTIDecoder;
+/// Zapíše do `dst` soubor `src` po úpravě `edit` (všechny snímky; kodek, velikost dlaždic,
+/// RCT, kontrolní součty a rozšiřující bloky zůstanou). Každá výstupní dlaždice se dekóduje
+/// jen z části zdroje, ze které vzniká; dlaždice, které výřez zarovnaný na mřížku převezme
+/// beze změny, se zkopírují i s komprimovanými daty (u DCT tedy bez další ztráty). Vrací
+/// hlavičku nového souboru a počet takto zkopírovaných dlaždic.
+pub fn edit_file<P: AsRef<Path>, Q: AsRef<Path>>(
+    src: P,
+    dst: Q,
+    edit: Edit,
+) -> Result<(CTIHeader, usize)> {
+    let (src, dst) = (src.as_ref(), dst.as_ref());
+    let source = Rewrite::open(src, dst)?;
+    let hdr = source.hdr;
+    let bpp = bytes_per_pixel(hdr.color_type)?;
+    match edit {
+        Edit::Crop(x, y, w, h) => ensure!(
+            w > 0
+                && h > 0
+                && x.checked_add(w).is_some_and(|r| r <= hdr.width)
+                && y.checked_add(h).is_some_and(|b| b <= hdr.height),
+            "Crop {w}x{h}+{x}+{y} is outside the {}x{} image",
+            hdr.width,
+            hdr.height
+        ),
+        Edit::Rotate(r) => ensure!(
+            (1..=3).contains(&r),
+            "Rotation must be 1, 2 or 3 quarter turns"
+        ),
+        Edit::FlipHorizontal | Edit::FlipVertical => {}
+    }
+    let params = EncodeParams {
+        tile_size: hdr.tile_size,
+        compression: CompressionId::from(hdr.compression),
+        level: CompressionId::from(hdr.compression).default_level(),
+        rct: hdr.flags & FLAG_RCT != 0,
+        file_hash: hdr.has_file_hash(),
+        crc32c: hdr.has_crc32c(),
+        quality: hdr.quality,
+        gray_bits: 8,
+    };
+    let (width, height) = edit.output_size(hdr.width, hdr.height);
+    let ts = hdr.tile_size;
+    let mut out = hdr;
+    out.width = width;
+    out.height = height;
+    out.tiles_x = width.div_ceil(ts);
+    out.tiles_y = height.div_ceil(ts);
+    let meta_block = extension_block(&mut out, &source.ext)?;
 
-impl CTIDecoder {
-    /// Načte pouze hlavičku (rychlá kontrola metadat).
-    pub fn info<P: AsRef<Path>>(path: P) -> Result<CTIHeader> {
-        let mut br = BufReader::new(File::open(path)?);
-        read_header(&mut br)
+    // výstupní dlaždice → stejná dlaždice zdroje, pokud ji výřez převezme celou
+    let copied_from = |tx: u32, ty: u32| {
+        let Edit::Crop(x, y, _, _) = edit else {
+            return None;
+        };
+        if x % ts != 0 || y % ts != 0 {
+            return None;
+        }
+        let (sx, sy) = (tx + x / ts, ty + y / ts);
+        (tile_dims(&out, tx, ty) == tile_dims(&hdr, sx, sy)).then_some((sx, sy))
+    };
+    let copied = AtomicUsize::new(0);
+    let per_frame = out.tiles_per_frame();
+    let res = write_tiles(dst, &out, &meta_block, |i| {
+        let frame = (i / per_frame) as u32;
+        let n = (i % per_frame) as u32;
+        let (tx, ty) = (n % out.tiles_x, n / out.tiles_x);
+        if let Some((sx, sy)) = copied_from(tx, ty) {
+            let j = frame as usize * hdr.tiles_per_frame() + (sy * hdr.tiles_x + sx) as usize;
+            let t = &source.indices[j];
+            copied.fetch_add(1, Ordering::Relaxed);
+            return Ok((source.payload(j)?, t.original_size, t.crc32));
+        }
+        let (tw, th) = tile_dims(&out, tx, ty);
+        let rect = edit.source_rect((hdr.width, hdr.height), (tx * ts, ty * ts, tw, th));
+        let data = source.region(frame, rect)?;
+        let tile = edit.apply(&data, rect.2, rect.3, bpp);
+        encode_tile(&out, &params, params.rct, tile, tx, ty)
+    });
+    if let Err(e) = res {
+        let _ = std::fs::remove_file(dst);
+        return Err(e);
     }
+    Ok((out, copied.into_inner()))
+}
 
-    /// Dekóduje celý obrázek do RAW bufferu (interleaved) a vrátí (header, data).
-    pub fn decode_file<P: AsRef<Path>>(path: P) -> Result<(CTIHeader, Vec<u8>)> {
-        let p = path.as_ref();
-        let mut f = BufReader::new(File::open(p)?);
+/// Zdroj pro [`transcode_file`] a [`edit_file`]: hlavička, index a rozšiřující bloky
+/// a soubor, ze kterého se čtou komprimované dlaždice.
+struct Rewrite {
+    hdr: CTIHeader,
+    indices: Vec<TileIndex>,
+    ext: CTIExtensions,
+    file: Mutex<BufReader<File>>,
+}
 
+impl Rewrite {
+    fn open(src: &Path, dst: &Path) -> Result<Self> {
+        if let (Ok(a), Ok(b)) = (src.canonicalize(), dst.canonicalize()) {
+            ensure!(a != b, "Output must be a different file than the input");
+        }
+        let mut f = BufReader::new(File::open(src)?);
         let hdr = read_header(&mut f)?;
-        ensure!(&hdr.magic == b"CTI1", "Bad magic");
-
-        // Index dlaždic
-        let total_tiles = (hdr.tiles_x * hdr.tiles_y) as usize;
-        let indices = read_indices(&mut f, total_tiles)?;
-
-        // bpp z color_type
-        let bpp = match hdr.color_type {
-            1 => 1u32, // L8
-            2 => 2u32, // L16
-            3 => 3u32, // RGB8
-            4 => 4u32, // RGBA8
-            5 => 6u32, // RGB16
-            _ => bail!("Unsupported color type id {}", hdr.color_type),
+        seek_index(&mut f, &hdr)?;
+        let indices = read_indices(&mut f, hdr.index_len())?;
+        let ext = CTIDecoder::extensions(src)?;
+        // úrovně pyramidy odkazují do dat původního souboru
+        ensure!(
+            ext.pyramid.is_empty(),
+            "Files with embedded pyramid levels cannot be rewritten"
+        );
+        Ok(Self {
+            hdr,
+            indices,
+            ext,
+            file: Mutex::new(f),
+        })
+    }
+
+    /// Komprimovaná data dlaždice `i` (pořadí indexu).
+    fn payload(&self, i: usize) -> Result<Vec<u8>> {
+        let t = &self.indices[i];
+        let mut f = self.file.lock().unwrap();
+        f.seek(SeekFrom::Start(t.offset))?;
+        read_vec(&mut *f, t.compressed_size as usize)
+    }
+
+    /// Výřez `rect` snímku `frame` z dlaždic podle načteného indexu; zámek souboru drží
+    /// jen čtení komprimovaných dat, rozbalování běží souběžně.
+    fn region(&self, frame: u32, rect: (u32, u32, u32, u32)) -> Result<Vec<u8>> {
+        let bpp = bytes_per_pixel(self.hdr.color_type)?;
+        let stride = rect.2 as usize * bpp as usize;
+        let mut out = vec![0u8; stride * rect.3 as usize];
+        let first = frame as usize * self.hdr.tiles_per_frame();
+        let mut tile = |i: usize| {
+            let j = first + i;
+            let tile = unpack_tile(&self.hdr, &self.indices[j], i, bpp, || self.payload(j))?;
+            Ok(Arc::new(tile))
         };
+        decode_region_tiles(&self.hdr, &mut tile, rect, &mut out, stride)?;
+        Ok(out)
+    }
+}
 
-        let mut out = vec![0u8; (hdr.width * hdr.height * bpp) as usize];
-        let use_rct = (hdr.flags & 1) != 0 && matches!(hdr.color_type, 3 | 5);
+/// Doplní do hlavičky přepisovaného souboru verzi, příznak metadat a délku bloků podle
+/// `ext` (index zase za hlavičkou) a vrátí blok, který se zapíše za index.
+fn extension_block(hdr: &mut CTIHeader, ext: &CTIExtensions) -> Result<Vec<u8>> {
+    hdr.flags &= !(FLAG_INDEX_AT_END | FLAG_METADATA);
+    if !ext.metadata.is_empty() {
+        hdr.flags |= FLAG_METADATA;
+    }
+    hdr.version = if ext.needs_chunks() {
+        VERSION_CHUNKS
+    } else {
+        1
+    };
+    hdr.chunks_len = 0;
+    Ok(if hdr.has_chunks() {
+        let block = ext.to_bytes(hdr)?;
+        hdr.chunks_len = block.len() as u32;
+        block
+    } else if ext.metadata.is_empty() {
+        Vec::new()
+    } else {
+        ext.metadata.to_bytes()?
+    })
+}
 
-        // Přímé čtení komprimovaných dlaždic
-        let mut file = f.into_inner();
-        for (i, t) in indices.iter().enumerate() {
-            file.seek(SeekFrom::Start(t.offset))?;
-            let mut comp = vec![0u8; t.compressed_size as usize];
-            file.read_exact(&mut comp)?;
+/// Zápis po řádcích dlaždic, jak je dodává skener: hlavička a rozšiřující bloky se zapíšou
+/// hned, každý řádek dlaždic se zkomprimuje a připojí a index s lokátorem přijde až ve
+/// [`finish`](Self::finish) (verze [`VERSION_APPEND`] s [`FLAG_INDEX_AT_END`]). Do výstupu se
+/// jen připisuje, takže stačí libovolný `Write` (soubor, roura, socket) a v paměti je vždy
+/// jen jeden řádek. Jediný snímek, bez RCT (jeho bezeztrátovost nejde ověřit předem).
+pub struct CTIAppendEncoder<W: Write> {
+    w: HashWriter<W>,
+    hdr: CTIHeader,
+    params: EncodeParams,
+    offset: u64,
+    index: Vec<u8>,
+    /// Počet zapsaných řádků dlaždic.
+    rows: u32,
+}
 
-            let mut tile = decompress_tile_with_size(hdr.compression, &comp, t.original_size as usize)?;
-            ensure!(crc32(&tile) == t.crc32, "CRC mismatch at tile {}", i);
+impl CTIAppendEncoder<BufWriter<File>> {
+    /// Jako [`new`](Self::new), zapisuje do nového souboru `path`.
+    pub fn create<P: AsRef<Path>>(
+        path: P,
+        width: u32,
+        height: u32,
+        color_type: u8,
+        params: &EncodeParams,
+        ext: &CTIExtensions,
+    ) -> Result<Self> {
+        let file = BufWriter::new(File::create(path)?);
+        Self::new(file, width, height, color_type, params, ext)
+    }
+}
 
-            if use_rct {
-                match hdr.color_type {
-                    3 => rct_inverse_rgb8(&mut tile),
-                    5 => rct_inverse_rgb16(&mut tile),
-                    _ => {}
-                }
-            }
+impl<W: Write> CTIAppendEncoder<W> {
+    /// Zapíše hlavičku obrázku `width × height` a bloky `ext` (bez pyramidy – její dlaždice
+    /// ještě neexistují).
+    pub fn new(
+        writer: W,
+        width: u32,
+        height: u32,
+        color_type: u8,
+        params: &EncodeParams,
+        ext: &CTIExtensions,
+    ) -> Result<Self> {
+        let (color_type, _) = check_params(width, height, color_type, params)?;
+        ensure!(
+            ext.pyramid.is_empty(),
+            "Pyramid levels cannot be written while appending tile rows"
+        );
+        let mut flags = FLAG_INDEX_AT_END;
+        if !ext.metadata.is_empty() {
+            flags |= FLAG_METADATA;
+        }
+        if params.file_hash {
+            flags |= FLAG_FILE_HASH;
+        }
+        if params.crc32c {
+            flags |= FLAG_CRC32C;
+        }
+        let ts = params.tile_size;
+        let mut hdr = CTIHeader {
+            magic: *b"CTI1",
+            version: VERSION_APPEND,
+            flags,
+            width,
+            height,
+            tile_size: ts,
+            tiles_x: width.div_ceil(ts),
+            tiles_y: height.div_ceil(ts),
+            color_type,
+            compression: params.compression.id(),
+            quality: if params.compression.is_lossy() {
+                params.quality
+            } else {
+                100
+            },
+            frames: 1,
+            chunks_len: 0,
+        };
+        let block = ext.to_bytes(&hdr)?;
+        hdr.chunks_len = block.len() as u32;
+        let mut w = HashWriter::new(writer, params.file_hash);
+        write_header(&mut w, &hdr)?;
+        w.write_all(&block)?;
+        Ok(Self {
+            w,
+            hdr,
+            params: *params,
+            offset: (HEADER_SIZE + block.len()) as u64,
+            index: Vec::with_capacity(hdr.index_len() * INDEX_ENTRY_SIZE),
+            rows: 0,
+        })
+    }
 
-            let tx = (i as u32) % hdr.tiles_x;
-            let ty = (i as u32) / hdr.tiles_x;
-            blit_tile(
-                &mut out,
-                &tile,
-                hdr.width,
-                hdr.height,
-                hdr.tile_size,
-                bpp as u32,
-                tx,
-                ty,
-            )?;
+    pub fn header(&self) -> &CTIHeader {
+        &self.hdr
+    }
+
+    /// Výška pásu pixelů pro další [`push_row`](Self::push_row) (velikost dlaždice, poslední
+    /// řádek může být nižší); 0, když jsou zapsané všechny řádky.
+    pub fn next_row_height(&self) -> u32 {
+        if self.rows < self.hdr.tiles_y {
+            tile_dims(&self.hdr, 0, self.rows).1
+        } else {
+            0
         }
+    }
 
-        Ok((hdr, out))
+    /// Zkomprimuje a připojí další řádek dlaždic: pás přes celou šířku obrázku o výšce
+    /// [`next_row_height`](Self::next_row_height), pixely interleaved jako u
+    /// [`CTIEncoder::encode_file`]. Dlaždice řádku se kódují paralelně.
+    pub fn push_row(&mut self, strip: &[u8]) -> Result<()> {
+        use rayon::prelude::*;
+
+        let height = self.next_row_height();
+        ensure!(
+            height > 0,
+            "All {} tile rows are already written",
+            self.hdr.tiles_y
+        );
+        let hdr = &self.hdr;
+        let bpp = bytes_per_pixel(hdr.color_type)?;
+        ensure!(
+            strip.len() as u64 == u64::from(hdr.width) * u64::from(height) * u64::from(bpp),
+            "Tile row must be {}x{height} pixels ({} bytes), got {} bytes",
+            hdr.width,
+            u64::from(hdr.width) * u64::from(height) * u64::from(bpp),
+            strip.len()
+        );
+        let ty = self.rows;
+        let tiles = (0..hdr.tiles_x)
+            .into_par_iter()
+            .map(|tx| {
+                let tile = extract_tile(strip, hdr.width, height, hdr.tile_size, bpp, tx, 0);
+                encode_tile(hdr, &self.params, false, tile, tx, ty)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        for (comp, original_size, crc) in tiles {
+            let len = comp.len() as u32;
+            self.w.write_all(&comp)?;
+            push_index_entry(&mut self.index, self.offset, len, original_size, crc);
+            self.offset += u64::from(len);
+        }
+        self.rows += 1;
+        Ok(())
+    }
+
+    /// Zapíše index, lokátor a případný hash; chyba, pokud chybí některý řádek dlaždic.
+    pub fn finish(mut self) -> Result<CTIHeader> {
+        ensure!(
+            self.rows == self.hdr.tiles_y,
+            "Only {} of {} tile rows were written",
+            self.rows,
+            self.hdr.tiles_y
+        );
+        self.w.write_all(&self.index)?;
+        self.w.write_all(INDEX_LOCATOR)?;
+        self.w.write_all(&self.offset.to_le_bytes())?;
+        self.w.finish()?.flush()?;
+        Ok(self.hdr)
+    }
+}
+
+/// Ověří hash celého souboru. `Ok(false)` = soubor hash nemá; chyba = neshoda (soubor byl
+/// po zápisu změněn nebo je poškozený) nebo chybějící trailer.
+pub fn verify_file_hash<P: AsRef<Path>>(path: P) -> Result<bool> {
+    let mut f = open(path.as_ref())?;
+    let hdr = read_header(&mut f)?;
+    if !hdr.has_file_hash() {
+        return Ok(false);
+    }
+    let len = f.seek(SeekFrom::End(0))?;
+    ensure!(
+        len >= (HEADER_SIZE + TRAILER_SIZE) as u64,
+        "File hash trailer missing"
+    );
+    let body = len - TRAILER_SIZE as u64;
+    f.seek(SeekFrom::Start(body))?;
+    let mut trailer = [0u8; TRAILER_SIZE];
+    f.read_exact(&mut trailer)?;
+    ensure!(&trailer[..4] == HASH_MAGIC, "File hash trailer missing");
+    ensure!(
+        trailer[4] == HASH_SHA256,
+        "Unsupported file hash algorithm {}",
+        trailer[4]
+    );
+    f.seek(SeekFrom::Start(0))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut BufReader::new(f).take(body), &mut hasher)?;
+    ensure!(
+        hasher.finalize().as_slice() == &trailer[8..],
+        "File hash mismatch: the file was modified or is corrupted"
+    );
+    Ok(true)
+}
+
+/// Přepíše blok metadat bez překódování dlaždic: hlavička, index s posunutými offsety, nový
+/// blok (u verze 2 celá oblast bloků s novými metadaty) a beze změny zkopírovaná komprimovaná
+/// data. Soubor s indexem na konci se přitom převede na verzi 2. Zapisuje se do dočasného souboru vedle
+/// originálu, který se nakonec atomicky nahradí. Hash celého souboru (pokud ho soubor má) se
+/// nejdřív ověří a pak spočítá znovu. Soubor s úrovněmi pyramidy odmítne jako [`edit_file`].
+pub fn rewrite_metadata<P: AsRef<Path>>(path: P, meta: &CTIMetadata) -> Result<CTIHeader> {
+    let path = path.as_ref();
+    verify_file_hash(path)?;
+    let mut src = BufReader::new(File::open(path)?);
+    let mut hdr = read_header(&mut src)?;
+    seek_index(&mut src, &hdr)?;
+    let indices = read_indices(&mut src, hdr.index_len())?;
+    let data_start = indices.iter().map(|t| t.offset).min().unwrap_or(0);
+    let data_end = indices
+        .iter()
+        .map(|t| t.offset + t.compressed_size as u64)
+        .max()
+        .unwrap_or(0);
+
+    let block = if hdr.has_chunks() {
+        // ostatní bloky zůstanou, vymění se jen metadata
+        src.seek(SeekFrom::Start(extensions_offset(&hdr)))?;
+        let mut ext = CTIExtensions::read(&mut src, &hdr)?;
+        // úrovně pyramidy odkazují do dat, která se posunou
+        ensure!(
+            ext.pyramid.is_empty(),
+            "Files with embedded pyramid levels cannot be rewritten"
+        );
+        ext.metadata = meta.clone();
+        let block = ext.to_bytes(&hdr)?;
+        hdr.chunks_len = block.len() as u32;
+        block
+    } else if meta.is_empty() {
+        Vec::new()
+    } else {
+        meta.to_bytes()?
+    };
+    // oblast bloků verze 2 má vždy aspoň "CEND", příznak patří jen skutečným metadatům
+    hdr.flags &= !FLAG_METADATA;
+    if !meta.is_empty() {
+        hdr.flags |= FLAG_METADATA;
+    }
+    if hdr.has_index_at_end() {
+        // přepsaný soubor má index zase za hlavičkou
+        hdr.flags &= !FLAG_INDEX_AT_END;
+        hdr.version = VERSION_CHUNKS;
+    }
+    let new_start = (HEADER_SIZE + indices.len() * INDEX_ENTRY_SIZE + block.len()) as u64;
+
+    let tmp = path.with_extension("cti.tmp");
+    let res = (|| -> Result<()> {
+        let mut w = HashWriter::new(BufWriter::new(File::create(&tmp)?), hdr.has_file_hash());
+        write_header(&mut w, &hdr)?;
+        for t in &indices {
+            w.write_all(&(t.offset - data_start + new_start).to_le_bytes())?;
+            w.write_all(&t.compressed_size.to_le_bytes())?;
+            w.write_all(&t.original_size.to_le_bytes())?;
+            w.write_all(&t.crc32.to_le_bytes())?;
+        }
+        w.write_all(&block)?;
+        src.seek(SeekFrom::Start(data_start))?;
+        let copied = std::io::copy(&mut (&mut src).take(data_end - data_start), &mut w)?;
+        ensure!(copied == data_end - data_start, "Truncated tile data");
+        w.finish()?
+            .into_inner()
+            .map_err(|e| e.into_error())?
+            .sync_all()?;
+        Ok(())
+    })();
+    drop(src);
+    if let Err(e) = res {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(e);
+    }
+    std::fs::rename(&tmp, path)?;
+    Ok(hdr)
+}
+
+/// Počet bajtů na pixel pro daný color_type.
+pub fn bytes_per_pixel(color_type: u8) -> Result<u32> {
+    Ok(match color_type {
+        1 => 1,   // L8
+        2 => 2,   // L16
+        3 => 3,   // RGB8
+        4 => 4,   // RGBA8
+        5 => 6,   // RGB16
+        6 => 1,   // L1: v dlaždici 8 pixelů na bajt, dekóduje se do L8 (0 / 255)
+        7 => 1,   // L4: v dlaždici 2 pixely na bajt, dekóduje se do L8 (0, 17 … 255)
+        8 => 4,   // CMYK8 (0 = bez barvy), zobrazuje se přes crate::cmyk
+        9 => 4,   // L32F (f32 little-endian), zobrazuje se přes crate::hdr
+        10 => 12, // RGB32F
+        _ => bail!("Unsupported color type id {}", color_type),
+    })
+}
+
+/// Název typu barev pro výpisy.
+pub fn color_name(color_type: u8) -> &'static str {
+    match color_type {
+        1 => "L8",
+        2 => "L16",
+        3 => "RGB8",
+        4 => "RGBA8",
+        5 => "RGB16",
+        6 => "L1",
+        7 => "L4",
+        8 => "CMYK8",
+        9 => "L32F",
+        10 => "RGB32F",
+        _ => "Unknown",
+    }
+}
+
+/// Vzorky f32 (HDR).
+pub fn is_float(color_type: u8) -> bool {
+    matches!(color_type, 9 | 10)
+}
+
+/// Bitů na pixel u sbalených odstínů šedi (L1, L4); `None` u ostatních typů.
+pub fn packed_bits(color_type: u8) -> Option<u32> {
+    match color_type {
+        6 => Some(1),
+        7 => Some(4),
+        _ => None,
+    }
+}
+
+/// Typ, pod kterým se L8 uloží při dané bitové hloubce ([`EncodeParams::gray_bits`]).
+fn stored_color_type(color_type: u8, gray_bits: u8) -> u8 {
+    match (color_type, gray_bits) {
+        (1, 1) => 6,
+        (1, 4) => 7,
+        _ => color_type,
+    }
+}
+
+/// Ověří, že se snímek vejde do paměti (buffery se počítají v usize, na wasm32 jen 32 bitů)
+/// a největší dlaždice do `original_size` indexu (u32). Po [`read_header`] proto násobení
+/// rozměrů s bajty na pixel v usize nepřeteče.
+pub(crate) fn check_sizes(width: u32, height: u32, tile_size: u32, bpp: u32) -> Result<()> {
+    let bytes = |w: u32, h: u32| (u64::from(w) * u64::from(h)).checked_mul(u64::from(bpp));
+    ensure!(
+        bytes(width, height).is_some_and(|n| usize::try_from(n).is_ok()),
+        "Image of {width}x{height} px is too large for this platform"
+    );
+    let (tile_w, tile_h) = (tile_size.min(width), tile_size.min(height));
+    ensure!(
+        bytes(tile_w, tile_h).is_some_and(|n| u32::try_from(n).is_ok()),
+        "Tiles of {tile_w}x{tile_h} px are too large for the tile index"
+    );
+    Ok(())
+}
+
+/// Velikost rozbalené dlaždice v souboru (sbalené typy mají řádky zarovnané na bajt, ztrátové
+/// dlaždice jsou kvantované koeficienty celých bloků 8×8).
+pub(crate) fn stored_tile_len(hdr: &CTIHeader, w: u32, h: u32, bpp: u32) -> usize {
+    if CompressionId::from(hdr.compression).is_lossy() {
+        return dct::coeff_len(w, h, bpp as usize);
+    }
+    match packed_bits(hdr.color_type) {
+        Some(bits) => (w as usize * bits as usize).div_ceil(8) * h as usize,
+        None => w as usize * h as usize * bpp as usize,
+    }
+}
+
+/// L8 → sbalené L1/L4 (nejvyšší bity první; L1 = práh v polovině, 1 = bílá).
+fn pack_gray(tile: &[u8], w: u32, bits: u32) -> Vec<u8> {
+    let w = w as usize;
+    let per_byte = 8 / bits as usize;
+    let mut out = Vec::with_capacity(tile.len().div_ceil(per_byte));
+    for row in tile.chunks_exact(w) {
+        for px in row.chunks(per_byte) {
+            let mut b = 0u8;
+            for (k, &v) in px.iter().enumerate() {
+                let level = if bits == 1 {
+                    u8::from(v >= 128)
+                } else {
+                    ((u32::from(v) + 8) / 17) as u8
+                };
+                b |= level << (8 - bits as usize * (k + 1));
+            }
+            out.push(b);
+        }
+    }
+    out
+}
+
+/// Sbalené L1/L4 → L8.
+fn unpack_gray(packed: &[u8], w: u32, h: u32, bits: u32) -> Vec<u8> {
+    let (w, bits) = (w as usize, bits as usize);
+    let per_byte = 8 / bits;
+    let (mask, scale) = ((1u8 << bits) - 1, 255 / ((1u8 << bits) - 1));
+    let mut out = Vec::with_capacity(w * h as usize);
+    for row in packed.chunks_exact(w.div_ceil(per_byte)) {
+        for x in 0..w {
+            let shift = 8 - bits * (x % per_byte + 1);
+            out.push((row[x / per_byte] >> shift & mask) * scale);
+        }
     }
+    out
 }
 
 // --- interní formát / IO ---
 
+pub(crate) const HEADER_SIZE: usize = 64;
+pub(crate) const INDEX_ENTRY_SIZE: usize = 20;
+pub(crate) const METADATA_MAGIC: &[u8; 4] = b"CTIM";
+pub(crate) const HASH_MAGIC: &[u8; 4] = b"CTIS";
+const HASH_SHA256: u8 = 1;
+pub(crate) const TRAILER_SIZE: usize = 40;
+pub(crate) const INDEX_LOCATOR: &[u8; 4] = b"CTIX";
+pub(crate) const LOCATOR_SIZE: usize = 12;
+const MAX_METADATA_SIZE: usize = 16 << 20;
+const CHUNK_ICC: &[u8; 4] = b"ICCP";
+const CHUNK_PYRAMID: &[u8; 4] = b"PYRM";
+const CHUNK_END: &[u8; 4] = b"CEND";
+/// Největší známý blok, který se načte do paměti (neznámé se jen přeskočí).
+const MAX_CHUNK_SIZE: u32 = 64 << 20;
+/// Kolik položek indexu se nejvýš předalokuje (víc jen tehdy, když data opravdu jsou).
+const MAX_PREALLOC_ENTRIES: usize = 1 << 16;
+
 #[derive(Debug, Clone, Copy)]
 struct TileIndex {
     offset: u64,
@@ -152,19 +2381,70 @@ struct TileIndex {
 fn read_header<R: Read>(r: &mut R) -> Result<CTIHeader> {
     let mut magic = [0u8; 4];
     r.read_exact(&mut magic)?;
+    ensure!(&magic == b"CTI1", "Bad magic");
     let version = read_u16_le(r)?;
-    let flags = read_u16_le(r)?;
+    let (major, minor) = (version & 0xFF, version >> 8);
+    ensure!(major >= 1, "Invalid CTI format version {major}.{minor}");
+    ensure!(
+        major <= VERSION_MAJOR_MAX,
+        "File requires a newer viewer: CTI format version {major}.{minor}, this viewer reads up \
+         to {VERSION_MAJOR_MAX}.x"
+    );
+    let mut flags = read_u16_le(r)?;
+    let unknown = flags & !KNOWN_FLAGS;
+    if unknown != 0 {
+        // novější vedlejší verze smí přidat příznaky; u známé verze jde o poškozenou hlavičku
+        ensure!(
+            minor > VERSION_MINOR,
+            "Unknown header flags 0x{unknown:04X} in a CTI {major}.{minor} file"
+        );
+        flags &= KNOWN_FLAGS;
+    }
+    ensure!(
+        flags & FLAG_INDEX_AT_END == 0 || major >= VERSION_APPEND,
+        "Index-at-end flag in a CTI {major}.{minor} file"
+    );
     let width = read_u32_le(r)?;
     let height = read_u32_le(r)?;
     let tile_size = read_u32_le(r)?;
     let tiles_x = read_u32_le(r)?;
     let tiles_y = read_u32_le(r)?;
+    ensure!(
+        width > 0 && height > 0 && tile_size > 0,
+        "Empty image or tile size in the header ({width}x{height}, tile {tile_size})"
+    );
+    ensure!(
+        tiles_x == width.div_ceil(tile_size) && tiles_y == height.div_ceil(tile_size),
+        "Tile grid {tiles_x}x{tiles_y} does not match {width}x{height} in {tile_size} px tiles"
+    );
     let color_type = read_u8(r)?;
+    // neznámý typ barev odmítne až dekodér (info() ho ještě vrátí)
+    if let Ok(bpp) = bytes_per_pixel(color_type) {
+        check_sizes(width, height, tile_size, bpp)?;
+    }
     let compression = read_u8(r)?;
     let quality = read_u8(r)?;
-    // reserved 33B
-    let mut _reserved = [0u8; 33];
-    r.read_exact(&mut _reserved)?;
+    // reserved 33B (u sekvence začíná počtem snímků, ve verzi 2 následuje délka bloků)
+    let mut reserved = [0u8; 33];
+    r.read_exact(&mut reserved)?;
+    let frames = if flags & FLAG_FRAMES != 0 {
+        u32::from_le_bytes(reserved[..4].try_into().unwrap())
+    } else {
+        1
+    };
+    ensure!(frames >= 1, "Frame count must be > 0");
+    // délka indexu se počítá v usize (na wasm32 jen 32 bitů)
+    let index_bytes = (u64::from(tiles_x) * u64::from(tiles_y))
+        .checked_mul(u64::from(frames) * INDEX_ENTRY_SIZE as u64);
+    ensure!(
+        index_bytes.is_some_and(|n| usize::try_from(n).is_ok()),
+        "Tile index of {frames} frame(s) with {tiles_x}x{tiles_y} tiles is too large"
+    );
+    let chunks_len = if major >= VERSION_CHUNKS {
+        u32::from_le_bytes(reserved[4..8].try_into().unwrap())
+    } else {
+        0
+    };
     Ok(CTIHeader {
         magic,
         version,
@@ -177,11 +2457,79 @@ fn read_header<R: Read>(r: &mut R) -> Result<CTIHeader> {
         color_type,
         compression,
         quality,
+        frames,
+        chunks_len,
     })
 }
 
+fn write_header<W: Write>(w: &mut W, h: &CTIHeader) -> Result<()> {
+    w.write_all(&h.magic)?;
+    w.write_all(&h.version.to_le_bytes())?;
+    w.write_all(&h.flags.to_le_bytes())?;
+    for v in [h.width, h.height, h.tile_size, h.tiles_x, h.tiles_y] {
+        w.write_all(&v.to_le_bytes())?;
+    }
+    w.write_all(&[h.color_type, h.compression, h.quality])?;
+    let mut reserved = [0u8; 33];
+    if h.flags & FLAG_FRAMES != 0 {
+        reserved[..4].copy_from_slice(&h.frames.to_le_bytes());
+    }
+    if h.has_chunks() {
+        reserved[4..8].copy_from_slice(&h.chunks_len.to_le_bytes());
+    }
+    w.write_all(&reserved)?;
+    Ok(())
+}
+
+/// Offset indexu dlaždic; u souboru s [`FLAG_INDEX_AT_END`] podle lokátoru na konci.
+fn index_offset<R: Read + Seek>(r: &mut R, hdr: &CTIHeader) -> Result<u64> {
+    if !hdr.has_index_at_end() {
+        return Ok(HEADER_SIZE as u64);
+    }
+    let trailer = if hdr.has_file_hash() { TRAILER_SIZE } else { 0 };
+    let len = r.seek(SeekFrom::End(0))?;
+    let locator = len
+        .checked_sub((LOCATOR_SIZE + trailer) as u64)
+        .filter(|&pos| pos >= HEADER_SIZE as u64)
+        .ok_or_else(|| anyhow!("Tile index missing: the file was not finished"))?;
+    r.seek(SeekFrom::Start(locator))?;
+    let mut magic = [0u8; 4];
+    r.read_exact(&mut magic)?;
+    ensure!(
+        &magic == INDEX_LOCATOR,
+        "Tile index missing: the file was not finished"
+    );
+    let offset = read_u64_le(r)?;
+    let index_end = offset.checked_add((hdr.index_len() * INDEX_ENTRY_SIZE) as u64);
+    ensure!(
+        offset >= (HEADER_SIZE as u64 + u64::from(hdr.chunks_len))
+            && index_end.is_some_and(|end| end <= locator),
+        "Bad tile index offset {offset}"
+    );
+    Ok(offset)
+}
+
+/// Přesune čtenáře, který stojí hned za hlavičkou, na začátek indexu dlaždic.
+fn seek_index<R: Read + Seek>(r: &mut R, hdr: &CTIHeader) -> Result<()> {
+    if hdr.has_index_at_end() {
+        let offset = index_offset(r, hdr)?;
+        r.seek(SeekFrom::Start(offset))?;
+    }
+    Ok(())
+}
+
+/// Offset bloku metadat, resp. oblasti rozšiřujících bloků.
+fn extensions_offset(hdr: &CTIHeader) -> u64 {
+    if hdr.has_index_at_end() {
+        HEADER_SIZE as u64
+    } else {
+        (HEADER_SIZE + hdr.index_len() * INDEX_ENTRY_SIZE) as u64
+    }
+}
+
 fn read_indices<R: Read>(r: &mut R, n: usize) -> Result<Vec<TileIndex>> {
-    let mut v = Vec::with_capacity(n);
+    // počet je z hlavičky – u poškozeného souboru skončí čtení na konci dat dřív
+    let mut v = Vec::with_capacity(n.min(MAX_PREALLOC_ENTRIES));
     for _ in 0..n {
         let offset = read_u64_le(r)?;
         let compressed_size = read_u32_le(r)?;
@@ -197,7 +2545,53 @@ fn read_indices<R: Read>(r: &mut R, n: usize) -> Result<Vec<TileIndex>> {
     Ok(v)
 }
 
+/// Vstupní body pro fuzzing (`fuzz/`, jen s funkcí `fuzzing`): parsery a dekomprese dlaždic
+/// nad libovolnými bajty. Nejsou součástí stabilního API.
+#[cfg(feature = "fuzzing")]
+#[doc(hidden)]
+pub mod fuzzing {
+    use super::*;
+    use std::io::Cursor;
+
+    pub fn read_header(data: &[u8]) -> Result<CTIHeader> {
+        super::read_header(&mut &data[..])
+    }
+
+    /// Hlavička a index dlaždic (za ní, nebo podle lokátoru na konci); vrací počet položek.
+    pub fn read_indices(data: &[u8]) -> Result<usize> {
+        let mut r = Cursor::new(data);
+        let hdr = super::read_header(&mut r)?;
+        seek_index(&mut r, &hdr)?;
+        Ok(super::read_indices(&mut r, hdr.index_len())?.len())
+    }
+
+    pub fn decompress_tile_with_size(
+        kind: u8,
+        comp: &[u8],
+        original_size: usize,
+    ) -> Result<Vec<u8>> {
+        super::decompress_tile_with_size(kind, comp, original_size)
+    }
+}
+
 // --- malé IO utily ---
+
+/// Přečte přesně `len` bajtů. Délka pochází ze souboru, takže se alokuje jen tolik, kolik dat
+/// opravdu přijde – podvržená délka nesmí vyčerpat paměť.
+fn read_vec<R: Read + ?Sized>(r: &mut R, len: usize) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    Read::take(r, len as u64).read_to_end(&mut out)?;
+    if out.len() < len {
+        // stejná chyba jako u read_exact (viz errors.rs)
+        let e = std::io::Error::new(
+            std::io::ErrorKind::UnexpectedEof,
+            "failed to fill whole buffer",
+        );
+        return Err(e.into());
+    }
+    Ok(out)
+}
+
 fn read_u8<R: Read>(r: &mut R) -> Result<u8> {
     let mut b = [0u8; 1];
     r.read_exact(&mut b)?;
@@ -219,94 +2613,409 @@ fn read_u64_le<R: Read>(r: &mut R) -> Result<u64> {
     Ok(u64::from_le_bytes(b))
 }
 
+/// Uložený typ barev a bajty na pixel; chyba, když parametry pro obrázek neplatí.
+fn check_params(
+    width: u32,
+    height: u32,
+    color_type: u8,
+    params: &EncodeParams,
+) -> Result<(u8, u32)> {
+    let color_type = stored_color_type(color_type, params.gray_bits);
+    let bpp = bytes_per_pixel(color_type)?;
+    ensure!(width > 0 && height > 0, "Empty image");
+    ensure!(params.tile_size > 0, "Tile size must be > 0");
+    if params.compression.is_lossy() {
+        dct::channels(color_type)?;
+        ensure!(
+            (1..=dct::MAX_QUALITY).contains(&params.quality),
+            "Quality must be 1..={}",
+            dct::MAX_QUALITY
+        );
+    }
+    Ok((color_type, bpp))
+}
+
+/// Připraví dlaždici `(tx, ty)` pro zápis (RCT, sbalení šedi, DCT) a zkomprimuje ji; vrací
+/// komprimovaná data, původní délku a CRC.
+fn encode_tile(
+    hdr: &CTIHeader,
+    params: &EncodeParams,
+    rct: bool,
+    mut tile: Vec<u8>,
+    tx: u32,
+    ty: u32,
+) -> Result<(Vec<u8>, u32, u32)> {
+    if rct {
+        match hdr.color_type {
+            3 => rct_forward_rgb8(&mut tile),
+            5 => rct_forward_rgb16(&mut tile),
+            _ => {}
+        }
+    }
+    if let Some(bits) = packed_bits(hdr.color_type) {
+        tile = pack_gray(&tile, tile_dims(hdr, tx, ty).0, bits);
+    }
+    if params.compression.is_lossy() {
+        let (tile_w, tile_h) = tile_dims(hdr, tx, ty);
+        tile = dct::forward(&tile, tile_w, tile_h, hdr.color_type, hdr.quality)?;
+    }
+    let crc = hdr.tile_crc(&tile);
+    let comp = compress_tile(params.compression, params.level, &tile)?;
+    Ok((comp, tile.len() as u32, crc))
+}
+
+/// Kolik dlaždic na vlákno se kóduje najednou: v paměti je jen tato dávka, hotové dlaždice
+/// se hned zapíší.
+const TILES_PER_THREAD: usize = 4;
+
+/// Zapíše soubor s hlavičkou `hdr` a dlaždicemi od `encode` (index dlaždice → komprimovaná
+/// data, původní délka, CRC). Dlaždice se kódují paralelně po dávkách a zapisují v pořadí,
+/// jak jsou hotové; index se místo nul doplní na konci a hash celého souboru se pak spočítá
+/// z disku, takže paměť nezávisí na velikosti obrázku.
+fn write_tiles(
+    path: &Path,
+    hdr: &CTIHeader,
+    meta_block: &[u8],
+    encode: impl Fn(usize) -> Result<(Vec<u8>, u32, u32)> + Sync,
+) -> Result<()> {
+    use rayon::prelude::*;
+
+    let count = hdr.index_len();
+    // čtení kvůli hashi na konci
+    let file = File::options()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)?;
+    let mut w = BufWriter::new(file);
+    write_header(&mut w, hdr)?;
+    w.write_all(&vec![0u8; count * INDEX_ENTRY_SIZE])?;
+    w.write_all(meta_block)?;
+    let mut offset = (HEADER_SIZE + count * INDEX_ENTRY_SIZE + meta_block.len()) as u64;
+    let mut index = Vec::with_capacity(count * INDEX_ENTRY_SIZE);
+    let batch = rayon::current_num_threads() * TILES_PER_THREAD;
+    for start in (0..count).step_by(batch) {
+        let tiles = (start..count.min(start + batch))
+            .into_par_iter()
+            .map(&encode)
+            .collect::<Result<Vec<_>>>()?;
+        for (comp, original_size, crc) in tiles {
+            push_index_entry(&mut index, offset, comp.len() as u32, original_size, crc);
+            w.write_all(&comp)?;
+            offset += comp.len() as u64;
+        }
+    }
+    let mut f = w.into_inner().map_err(|e| e.into_error())?;
+    f.seek(SeekFrom::Start(HEADER_SIZE as u64))?;
+    f.write_all(&index)?;
+    if hdr.has_file_hash() {
+        f.seek(SeekFrom::Start(0))?;
+        let mut hasher = Sha256::new();
+        std::io::copy(&mut BufReader::new(&mut f), &mut hasher)?;
+        f.seek(SeekFrom::End(0))?;
+        write_trailer(&mut f, &hasher.finalize())?;
+    }
+    Ok(())
+}
+
+/// Položka indexu dlaždic.
+fn push_index_entry(index: &mut Vec<u8>, offset: u64, compressed: u32, original: u32, crc: u32) {
+    index.extend_from_slice(&offset.to_le_bytes());
+    index.extend_from_slice(&compressed.to_le_bytes());
+    index.extend_from_slice(&original.to_le_bytes());
+    index.extend_from_slice(&crc.to_le_bytes());
+}
+
+/// Trailer s hashem celého souboru (viz [`verify_file_hash`]).
+fn write_trailer<W: Write>(w: &mut W, hash: &[u8]) -> Result<()> {
+    w.write_all(HASH_MAGIC)?;
+    w.write_all(&[HASH_SHA256, 0, 0, 0])?;
+    w.write_all(hash)?;
+    Ok(())
+}
+
+/// Zapisovač, který průběžně počítá hash zapsaných bajtů a na konci připojí trailer.
+struct HashWriter<W: Write> {
+    inner: W,
+    hasher: Option<Sha256>,
+}
+
+impl<W: Write> HashWriter<W> {
+    fn new(inner: W, hash: bool) -> Self {
+        Self {
+            inner,
+            hasher: hash.then(Sha256::new),
+        }
+    }
+
+    /// Připojí trailer s hashem všeho dosud zapsaného (pokud se hash počítá).
+    fn finish(mut self) -> Result<W> {
+        if let Some(hasher) = self.hasher.take() {
+            write_trailer(&mut self.inner, &hasher.finalize())?;
+        }
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for HashWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        if let Some(h) = &mut self.hasher {
+            h.update(&buf[..n]);
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Připojí blok typu `kind` (viz [`CTIExtensions`]).
+fn push_chunk(out: &mut Vec<u8>, kind: &[u8; 4], body: &[u8]) -> Result<()> {
+    ensure!(
+        body.len() <= MAX_CHUNK_SIZE as usize,
+        "Extension chunk {} too large",
+        fourcc(*kind)
+    );
+    out.extend_from_slice(kind);
+    out.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    out.extend_from_slice(body);
+    Ok(())
+}
+
+fn read_chunk<R: Read>(r: &mut R, len: u32) -> Result<Vec<u8>> {
+    ensure!(len <= MAX_CHUNK_SIZE, "Extension chunk too large");
+    read_vec(r, len as usize)
+}
+
+/// Typ bloku pro výpis (`"ICCP"`; netisknutelné bajty jako `?`).
+fn fourcc(kind: [u8; 4]) -> String {
+    kind.iter()
+        .map(|&b| if b.is_ascii_graphic() { b as char } else { '?' })
+        .collect()
+}
+
+fn take_utf8(cur: &mut &[u8], len: usize) -> Result<String> {
+    ensure!(cur.len() >= len, "Truncated metadata block");
+    let (s, rest) = cur.split_at(len);
+    *cur = rest;
+    Ok(String::from_utf8(s.to_vec())?)
+}
+
 // --- skládání dlaždic ---
+/// Rozměr dlaždice (krajní dlaždice jsou menší).
+pub fn tile_dims(hdr: &CTIHeader, tx: u32, ty: u32) -> (u32, u32) {
+    let ts = hdr.tile_size;
+    (
+        ts.min(hdr.width - tx * ts),
+        ts.min(hdr.height - ty * ts),
+    )
+}
+
+/// Šachovnice 8×8 (purpurová/tmavě šedá), kterou nelze zaměnit s obsahem snímku.
+fn placeholder_tile(w: u32, h: u32, color_type: u8) -> Vec<u8> {
+    const LIGHT: [u8; 3] = [255, 0, 255];
+    const DARK: [u8; 3] = [64, 64, 64];
+    let mut tile = Vec::new();
+    for y in 0..h {
+        for x in 0..w {
+            let ([r, g, b], l) = if (x / 8 + y / 8) % 2 == 0 {
+                (LIGHT, 200)
+            } else {
+                (DARK, 64)
+            };
+            match color_type {
+                1 | 6 | 7 => tile.push(l),
+                2 => tile.extend_from_slice(&(l as u16 * 257).to_le_bytes()),
+                3 => tile.extend_from_slice(&[r, g, b]),
+                4 => tile.extend_from_slice(&[r, g, b, 255]),
+                8 => tile.extend_from_slice(&[255 - r, 255 - g, 255 - b, 0]),
+                9 => tile.extend_from_slice(&(l as f32 / 255.0).to_le_bytes()),
+                10 => {
+                    for c in [r, g, b] {
+                        tile.extend_from_slice(&(c as f32 / 255.0).to_le_bytes());
+                    }
+                }
+                5 => {
+                    for c in [r, g, b] {
+                        tile.extend_from_slice(&(c as u16 * 257).to_le_bytes());
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    tile
+}
+
+#[allow(clippy::too_many_arguments)]
 fn blit_tile(
     out: &mut [u8],
+    stride: usize,
     tile: &[u8],
-    w: u32,
-    h: u32,
-    ts: u32,
+    hdr: &CTIHeader,
     bpp: u32,
     tx: u32,
     ty: u32,
 ) -> Result<()> {
+    let start_x = tx * hdr.tile_size;
+    let start_y = ty * hdr.tile_size;
+    let (tile_w, tile_h) = tile_dims(hdr, tx, ty);
+
+    let len = tile_w as usize * bpp as usize;
+    for row in 0..tile_h {
+        let dst_off = (start_y + row) as usize * stride + start_x as usize * bpp as usize;
+        let src_off = row as usize * len;
+        out[dst_off..dst_off + len].copy_from_slice(&tile[src_off..src_off + len]);
+    }
+    Ok(())
+}
+
+fn extract_tile(data: &[u8], w: u32, h: u32, ts: u32, bpp: u32, tx: u32, ty: u32) -> Vec<u8> {
     let start_x = tx * ts;
     let start_y = ty * ts;
     let end_x = (start_x + ts).min(w);
     let end_y = (start_y + ts).min(h);
-    let tile_w = end_x - start_x;
-    let tile_h = end_y - start_y;
+    let len = (end_x - start_x) as usize * bpp as usize;
 
-    for row in 0..tile_h {
-        let dst_off = (((start_y + row) * w + start_x) * bpp) as usize;
-        let src_off = (row * tile_w * bpp) as usize;
-        let len = (tile_w * bpp) as usize;
-        out[dst_off..dst_off + len].copy_from_slice(&tile[src_off..src_off + len]);
+    let mut tile = Vec::with_capacity(len * (end_y - start_y) as usize);
+    for y in start_y..end_y {
+        let off = (y as usize * w as usize + start_x as usize) * bpp as usize;
+        tile.extend_from_slice(&data[off..off + len]);
+    }
+    tile
+}
+
+// --- komprese + RCT forward ---
+fn compress_tile(kind: CompressionId, level: i32, raw: &[u8]) -> Result<Vec<u8>> {
+    match kind {
+        CompressionId::None => Ok(raw.to_vec()),
+        // koeficienty DCT se dál komprimují Zstd
+        CompressionId::Zstd | CompressionId::Dct => {
+            zstd::bulk::compress(raw, level).map_err(|e| anyhow!("zstd compress failed: {e}"))
+        }
+        CompressionId::Lz4 => Ok(lz4_flex::block::compress_prepend_size(raw)),
+        #[cfg(feature = "brotli")]
+        CompressionId::Brotli => {
+            let mut out = Vec::new();
+            let quality = level.clamp(0, 11) as u32;
+            let mut w = brotli::CompressorWriter::new(&mut out, 4096, quality, 22);
+            w.write_all(raw)?;
+            drop(w);
+            Ok(out)
+        }
+        #[cfg(feature = "deflate")]
+        CompressionId::Deflate => {
+            let level = flate2::Compression::new(level.clamp(0, 9) as u32);
+            let mut w = flate2::write::DeflateEncoder::new(Vec::new(), level);
+            w.write_all(raw)?;
+            Ok(w.finish()?)
+        }
+        #[cfg(not(feature = "brotli"))]
+        CompressionId::Brotli => bail!("Brotli tiles need a build with the `brotli` feature"),
+        #[cfg(not(feature = "deflate"))]
+        CompressionId::Deflate => bail!("Deflate tiles need a build with the `deflate` feature"),
+        other => bail!("Unsupported compression in encoder: {}", other.as_str()),
+    }
+}
+
+/// Rozbalí stream (Brotli, Deflate), nejvýš však `original_size` bajtů – poškozená nebo
+/// podvržená dlaždice nesmí zabrat libovolně paměti.
+#[cfg(any(feature = "brotli", feature = "deflate"))]
+fn read_limited(r: impl Read, original_size: usize, codec: &str) -> Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(original_size);
+    r.take(original_size as u64 + 1)
+        .read_to_end(&mut out)
+        .map_err(|e| anyhow!("{codec} decompress failed: {e}"))?;
+    ensure!(
+        out.len() <= original_size,
+        "{codec} decompress failed: more data than the tile size"
+    );
+    Ok(out)
+}
+
+// RCT ukládá chroma jako i8/i16 – rozdíly mimo rozsah by se při dekódování ořízly.
+fn rct_is_lossless_rgb8(buf: &[u8]) -> bool {
+    buf.chunks_exact(3).all(|p| {
+        let (r, g, b) = (p[0] as i32, p[1] as i32, p[2] as i32);
+        i8::try_from(b - g).is_ok() && i8::try_from(r - g).is_ok()
+    })
+}
+fn rct_is_lossless_rgb16(buf: &[u8]) -> bool {
+    buf.chunks_exact(6).all(|p| {
+        let r = u16::from_le_bytes([p[0], p[1]]) as i32;
+        let g = u16::from_le_bytes([p[2], p[3]]) as i32;
+        let b = u16::from_le_bytes([p[4], p[5]]) as i32;
+        i16::try_from(b - g).is_ok() && i16::try_from(r - g).is_ok()
+    })
+}
+
+/// Dopředná RCT pro RGB8 (R, G, B → Y, Cb, Cr) na místě, opak
+/// [`simd::rct_inverse_rgb8`]; bezeztrátová jen pro pixely, u nichž se rozdíly vejdou do i8.
+pub fn rct_forward_rgb8(buf: &mut [u8]) {
+    for p in buf.chunks_exact_mut(3) {
+        let (r, g, b) = (p[0] as i32, p[1] as i32, p[2] as i32);
+        let cb = b - g;
+        let cr = r - g;
+        let y = g + ((cb + cr) >> 2);
+        p[0] = y as u8;
+        p[1] = cb as i8 as u8;
+        p[2] = cr as i8 as u8;
+    }
+}
+/// Dopředná RCT pro RGB16 (little-endian), opak [`simd::rct_inverse_rgb16`]; rozdíly se
+/// musí vejít do i16.
+pub fn rct_forward_rgb16(buf: &mut [u8]) {
+    for p in buf.chunks_exact_mut(6) {
+        let r = u16::from_le_bytes([p[0], p[1]]) as i32;
+        let g = u16::from_le_bytes([p[2], p[3]]) as i32;
+        let b = u16::from_le_bytes([p[4], p[5]]) as i32;
+        let cb = b - g;
+        let cr = r - g;
+        let y = g + ((cb + cr) >> 2);
+        p[0..2].copy_from_slice(&(y as u16).to_le_bytes());
+        p[2..4].copy_from_slice(&(cb as i16).to_le_bytes());
+        p[4..6].copy_from_slice(&(cr as i16).to_le_bytes());
     }
-    Ok(())
 }
 
 // --- dekomprese + jednoduché RCT inverse ---
 fn decompress_tile_with_size(kind: u8, comp: &[u8], original_size: usize) -> Result<Vec<u8>> {
     match CompressionId::from(kind) {
         CompressionId::None => Ok(comp.to_vec()),
-        CompressionId::Zstd => zstd::bulk::decompress(comp, original_size)
+        CompressionId::Zstd | CompressionId::Dct => zstd::bulk::decompress(comp, original_size)
             .map_err(|e| anyhow!("zstd decompress failed: {e}")),
-        CompressionId::Lz4 => lz4_flex::block::decompress_size_prepended(comp).map_err(|e| anyhow!(e)),
+        CompressionId::Lz4 => {
+            // velikost na začátku dat určuje alokaci – nesmí přesáhnout velikost dlaždice
+            let (size, data) = comp
+                .split_first_chunk::<4>()
+                .ok_or_else(|| anyhow!("lz4 decompress failed: tile too short"))?;
+            let size = u32::from_le_bytes(*size) as usize;
+            ensure!(
+                size <= original_size,
+                "lz4 decompress failed: more data than the tile size"
+            );
+            lz4_flex::block::decompress(data, size).map_err(|e| anyhow!(e))
+        }
+        #[cfg(feature = "brotli")]
+        CompressionId::Brotli => read_limited(
+            brotli::Decompressor::new(comp, 4096),
+            original_size,
+            "brotli",
+        ),
+        #[cfg(feature = "deflate")]
+        CompressionId::Deflate => read_limited(
+            flate2::read::DeflateDecoder::new(comp),
+            original_size,
+            "deflate",
+        ),
+        #[cfg(not(feature = "brotli"))]
+        CompressionId::Brotli => bail!("Brotli tiles need a build with the `brotli` feature"),
+        #[cfg(not(feature = "deflate"))]
+        CompressionId::Deflate => bail!("Deflate tiles need a build with the `deflate` feature"),
         // viewer je minimalistický – ostatní módy nepodporujeme
         other => bail!("Unsupported compression in viewer: {}", other.as_str()),
     }
 }
-
-fn rct_inverse_rgb8(buf: &mut [u8]) {
-    for p in buf.chunks_exact_mut(3) {
-        let y = p[0] as i32;
-        let cb = (p[1] as i8) as i32;
-        let cr = (p[2] as i8) as i32;
-        let g = y - ((cb + cr) >> 2);
-        let r = cr + g;
-        let b = cb + g;
-        p[0] = r.clamp(0, 255) as u8;
-        p[1] = g.clamp(0, 255) as u8;
-        p[2] = b.clamp(0, 255) as u8;
-    }
-}
-fn rct_inverse_rgb16(buf: &mut [u8]) {
-    for p in buf.chunks_exact_mut(6) {
-        let y = u16::from_le_bytes([p[0], p[1]]) as i32;
-        let cb = (u16::from_le_bytes([p[2], p[3]]) as i16) as i32;
-        let cr = (u16::from_le_bytes([p[4], p[5]]) as i16) as i32;
-        let g = y - ((cb + cr) >> 2);
-        let r = cr + g;
-        let b = cb + g;
-        p[0..2].copy_from_slice(&(r.clamp(0, 65535) as u16).to_le_bytes());
-        p[2..4].copy_from_slice(&(g.clamp(0, 65535) as u16).to_le_bytes());
-        p[4..6].copy_from_slice(&(b.clamp(0, 65535) as u16).to_le_bytes());
-    }
-}
-
-// --- CRC32 ---
-fn crc32(data: &[u8]) -> u32 {
-    const TABLE: [u32; 256] = crc32_table();
-    let mut crc = 0xFFFF_FFFFu32;
-    for &b in data {
-        let idx = ((crc ^ (b as u32)) & 0xFF) as usize;
-        crc = (crc >> 8) ^ TABLE[idx];
-    }
-    !crc
-}
-const fn crc32_table() -> [u32; 256] {
-    let mut t = [0u32; 256];
-    let mut i = 0;
-    while i < 256 {
-        let mut c = i as u32;
-        let mut j = 0;
-        while j < 8 {
-            c = if (c & 1) != 0 { 0xEDB88320 ^ (c >> 1) } else { c >> 1 };
-            j += 1;
-        }
-        t[i] = c;
-        i += 1;
-    }
-    t
-}
@@ -0,0 +1,209 @@
+//! Ztrátový kodek dlaždic ([`CompressionId::Dct`](crate::cti::CompressionId::Dct)): bloky
+//! 8×8 jako v JPEG – DCT, kvantizace tabulkami JPEG škálovanými podle kvality (1..=100)
+//! a kvantované koeficienty (i16 little-endian, pořadí zigzag) pak Zstd. CRC v indexu
+//! chrání koeficienty, takže kontrola integrity zůstává přesná, i když pixely ne.
+//!
+//! Jen 8bit typy (L8, RGB8, RGBA8, CMYK8); RGB se před transformací převede na YCbCr.
+
+use anyhow::{Result, bail};
+use std::sync::OnceLock;
+
+/// Kvalita, od které jsou všechny kvantizační kroky 1 (téměř bezeztrátové).
+pub const MAX_QUALITY: u8 = 100;
+
+const N: usize = 8;
+
+/// Základní tabulky JPEG (ITU T.81, příloha K) pro jas a barevné rozdíly.
+const LUMA: [u16; 64] = [
+    16, 11, 10, 16, 24, 40, 51, 61, 12, 12, 14, 19, 26, 58, 60, 55, 14, 13, 16, 24, 40, 57, 69, 56,
+    14, 17, 22, 29, 51, 87, 80, 62, 18, 22, 37, 56, 68, 109, 103, 77, 24, 35, 55, 64, 81, 104, 113,
+    92, 49, 64, 78, 87, 103, 121, 120, 101, 72, 92, 95, 98, 112, 100, 103, 99,
+];
+const CHROMA: [u16; 64] = [
+    17, 18, 24, 47, 99, 99, 99, 99, 18, 21, 26, 66, 99, 99, 99, 99, 24, 26, 56, 99, 99, 99, 99, 99,
+    47, 66, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99,
+    99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99,
+];
+
+/// Pořadí koeficientů od nízkých frekvencí k vysokým (nuly na konci se lépe komprimují).
+const ZIGZAG: [usize; 64] = [
+    0, 1, 8, 16, 9, 2, 3, 10, 17, 24, 32, 25, 18, 11, 4, 5, 12, 19, 26, 33, 40, 48, 41, 34, 27, 20,
+    13, 6, 7, 14, 21, 28, 35, 42, 49, 56, 57, 50, 43, 36, 29, 22, 15, 23, 30, 37, 44, 51, 58, 59,
+    52, 45, 38, 31, 39, 46, 53, 60, 61, 54, 47, 55, 62, 63,
+];
+
+/// Podporuje kodek daný typ barev? Vrací počet kanálů.
+pub fn channels(color_type: u8) -> Result<usize> {
+    Ok(match color_type {
+        1 => 1,
+        3 => 3,
+        4 | 8 => 4,
+        other => {
+            bail!("The DCT codec supports only 8-bit L, RGB, RGBA and CMYK (color type {other})")
+        }
+    })
+}
+
+/// Délka kvantovaných koeficientů dlaždice `w`×`h` (doplněné na násobky 8).
+pub fn coeff_len(w: u32, h: u32, channels: usize) -> usize {
+    (w as usize).div_ceil(N) * (h as usize).div_ceil(N) * 64 * channels * 2
+}
+
+/// Pixely dlaždice (interleaved) → kvantované koeficienty.
+pub fn forward(tile: &[u8], w: u32, h: u32, color_type: u8, quality: u8) -> Result<Vec<u8>> {
+    let ch = channels(color_type)?;
+    let (w, h) = (w as usize, h as usize);
+    let planes = to_planes(tile, w, h, ch, color_type == 3 || color_type == 4);
+    let (bw, bh) = (w.div_ceil(N), h.div_ceil(N));
+    let mut out = Vec::with_capacity(coeff_len(w as u32, h as u32, ch));
+    for (c, plane) in planes.iter().enumerate() {
+        let q = table(quality, chroma(color_type, c));
+        for by in 0..bh {
+            for bx in 0..bw {
+                let mut block = [0f32; 64];
+                for y in 0..N {
+                    for x in 0..N {
+                        // okraj dlaždice se doplní opakováním posledního pixelu
+                        let (sx, sy) = ((bx * N + x).min(w - 1), (by * N + y).min(h - 1));
+                        block[y * N + x] = plane[sy * w + sx];
+                    }
+                }
+                let coeffs = dct2(&block, false);
+                for &z in &ZIGZAG {
+                    let v = (coeffs[z] / q[z] as f32).round() as i16;
+                    out.extend_from_slice(&v.to_le_bytes());
+                }
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Opak [`forward`]: kvantované koeficienty → pixely dlaždice (interleaved).
+pub fn inverse(coeffs: &[u8], w: u32, h: u32, color_type: u8, quality: u8) -> Result<Vec<u8>> {
+    let ch = channels(color_type)?;
+    let (w, h) = (w as usize, h as usize);
+    let (bw, bh) = (w.div_ceil(N), h.div_ceil(N));
+    let mut planes = vec![vec![0f32; w * h]; ch];
+    let mut values = coeffs
+        .chunks_exact(2)
+        .map(|b| i16::from_le_bytes([b[0], b[1]]));
+    for (c, plane) in planes.iter_mut().enumerate() {
+        let q = table(quality, chroma(color_type, c));
+        for by in 0..bh {
+            for bx in 0..bw {
+                let mut block = [0f32; 64];
+                for &z in &ZIGZAG {
+                    let Some(v) = values.next() else {
+                        bail!("DCT tile is truncated");
+                    };
+                    block[z] = f32::from(v) * q[z] as f32;
+                }
+                let pixels = dct2(&block, true);
+                for y in 0..N.min(h - by * N) {
+                    for x in 0..N.min(w - bx * N) {
+                        plane[(by * N + y) * w + bx * N + x] = pixels[y * N + x];
+                    }
+                }
+            }
+        }
+    }
+    Ok(from_planes(
+        &planes,
+        w * h,
+        color_type == 3 || color_type == 4,
+    ))
+}
+
+/// Kanál `c` se kvantuje tabulkou barevných rozdílů (Cb, Cr z RGB)?
+fn chroma(color_type: u8, c: usize) -> bool {
+    matches!(color_type, 3 | 4) && (c == 1 || c == 2)
+}
+
+/// Tabulka škálovaná podle kvality stejně jako v libjpeg.
+fn table(quality: u8, chroma: bool) -> [u16; 64] {
+    let q = u32::from(quality.clamp(1, MAX_QUALITY));
+    let scale = if q < 50 { 5000 / q } else { 200 - 2 * q };
+    let base = if chroma { &CHROMA } else { &LUMA };
+    base.map(|b| ((u32::from(b) * scale + 50) / 100).clamp(1, 255) as u16)
+}
+
+/// Interleaved pixely → roviny posunuté o −128 (RGB jako YCbCr).
+fn to_planes(tile: &[u8], w: usize, h: usize, ch: usize, ycc: bool) -> Vec<Vec<f32>> {
+    let mut planes = vec![Vec::with_capacity(w * h); ch];
+    for p in tile.chunks_exact(ch) {
+        let mut v = [0f32; 4];
+        for (d, s) in v.iter_mut().zip(p) {
+            *d = f32::from(*s);
+        }
+        if ycc {
+            let (r, g, b) = (v[0], v[1], v[2]);
+            v[0] = 0.299 * r + 0.587 * g + 0.114 * b;
+            v[1] = -0.168_736 * r - 0.331_264 * g + 0.5 * b + 128.0;
+            v[2] = 0.5 * r - 0.418_688 * g - 0.081_312 * b + 128.0;
+        }
+        for (plane, s) in planes.iter_mut().zip(v) {
+            plane.push(s - 128.0);
+        }
+    }
+    planes
+}
+
+/// Opak [`to_planes`].
+fn from_planes(planes: &[Vec<f32>], pixels: usize, ycc: bool) -> Vec<u8> {
+    let ch = planes.len();
+    let mut out = Vec::with_capacity(pixels * ch);
+    for i in 0..pixels {
+        let mut v = [0f32; 4];
+        for (c, plane) in planes.iter().enumerate() {
+            v[c] = plane[i] + 128.0;
+        }
+        if ycc {
+            let (y, cb, cr) = (v[0], v[1] - 128.0, v[2] - 128.0);
+            v[0] = y + 1.402 * cr;
+            v[1] = y - 0.344_136 * cb - 0.714_136 * cr;
+            v[2] = y + 1.772 * cb;
+        }
+        out.extend(v[..ch].iter().map(|s| s.round().clamp(0.0, 255.0) as u8));
+    }
+    out
+}
+
+/// Báze DCT-II: `C[u][x] = c(u)/2 · cos((2x + 1)uπ/16)`.
+fn basis() -> &'static [[f32; N]; N] {
+    static BASIS: OnceLock<[[f32; N]; N]> = OnceLock::new();
+    BASIS.get_or_init(|| {
+        let mut b = [[0f32; N]; N];
+        for (u, row) in b.iter_mut().enumerate() {
+            let c = if u == 0 {
+                std::f32::consts::FRAC_1_SQRT_2
+            } else {
+                1.0
+            };
+            for (x, v) in row.iter_mut().enumerate() {
+                let angle = (2 * x + 1) as f32 * u as f32 * std::f32::consts::PI / 16.0;
+                *v = c / 2.0 * angle.cos();
+            }
+        }
+        b
+    })
+}
+
+/// Oddělitelná 2D DCT bloku 8×8 (`inverse` = DCT-III).
+fn dct2(block: &[f32; 64], inverse: bool) -> [f32; 64] {
+    let b = basis();
+    let k = |i: usize, j: usize| if inverse { b[j][i] } else { b[i][j] };
+    let mut tmp = [0f32; 64];
+    for y in 0..N {
+        for u in 0..N {
+            tmp[y * N + u] = (0..N).map(|x| k(u, x) * block[y * N + x]).sum();
+        }
+    }
+    let mut out = [0f32; 64];
+    for x in 0..N {
+        for v in 0..N {
+            out[v * N + x] = (0..N).map(|y| k(v, y) * tmp[y * N + x]).sum();
+        }
+    }
+    out
+}
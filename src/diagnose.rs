@@ -0,0 +1,188 @@
+//! Režim „Diagnose“: místo obrázku mřížka dlaždic obarvená podle stavu (v pořádku, špatné
+//! CRC, nejde rozbalit, ukazuje mimo soubor), aby bylo u poškozeného archivu vidět, které
+//! části zasáhlo. Kontrola běží na pozadí.
+
+use eframe::egui::{self, Color32, Pos2, Rect, Stroke, Vec2};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+
+use crate::cti::{CTIDecoder, CTIHeader, TileStatus};
+
+type Outcome = Result<(CTIHeader, Vec<TileStatus>), String>;
+
+#[derive(Default)]
+pub struct Diagnose {
+    pub enabled: bool,
+    /// Soubor a snímek, ke kterým patří `result` (nebo rozběhnutá kontrola).
+    key: Option<(PathBuf, u32)>,
+    rx: Option<Receiver<Outcome>>,
+    result: Option<Outcome>,
+}
+
+impl Diagnose {
+    /// Spustí kontrolu, pokud ještě neběží nebo je pro jiný soubor či snímek.
+    pub fn ensure(&mut self, ctx: &egui::Context, path: &Path, frame: u32) {
+        if self
+            .key
+            .as_ref()
+            .is_some_and(|(p, f)| p == path && *f == frame)
+        {
+            return;
+        }
+        self.key = Some((path.to_path_buf(), frame));
+        self.result = None;
+        let (tx, rx) = mpsc::channel();
+        let (path, ctx) = (path.to_path_buf(), ctx.clone());
+        std::thread::spawn(move || {
+            let res = CTIDecoder::diagnose(&path, frame).map_err(|e| format!("{e:#}"));
+            let _ = tx.send(res);
+            ctx.request_repaint();
+        });
+        self.rx = Some(rx);
+    }
+
+    /// Zapomene výsledek (soubor se změnil na disku).
+    pub fn clear(&mut self) {
+        *self = Self {
+            enabled: self.enabled,
+            ..Self::default()
+        };
+    }
+
+    fn poll(&mut self) {
+        if let Some(res) = self.rx.as_ref().and_then(|rx| rx.try_recv().ok()) {
+            self.result = Some(res);
+            self.rx = None;
+        }
+    }
+
+    /// Vykreslí mřížku přes obrázek a legendu; `hover` = ukazatel myši (popisek dlaždice).
+    pub fn paint(
+        &mut self,
+        ui: &egui::Ui,
+        painter: &egui::Painter,
+        image: Rect,
+        hover: Option<Pos2>,
+        to_screen: impl Fn(Pos2) -> Pos2,
+        to_image: impl Fn(Pos2) -> Pos2,
+    ) {
+        self.poll();
+        painter.rect_filled(image, 0.0, Color32::from_gray(90));
+        let (hdr, tiles) = match &self.result {
+            None => {
+                legend(painter, &["Checking tiles…".to_owned()]);
+                return;
+            }
+            Some(Err(e)) => {
+                legend(painter, &[format!("Cannot check tiles: {e}")]);
+                return;
+            }
+            Some(Ok((hdr, tiles))) => (hdr, tiles),
+        };
+        let ts = hdr.tile_size as f32;
+        let cell = |i: usize| {
+            let (tx, ty) = (
+                (i as u32 % hdr.tiles_x) as f32,
+                (i as u32 / hdr.tiles_x) as f32,
+            );
+            let min = Pos2::new(tx * ts, ty * ts);
+            let max = Pos2::new(
+                ((tx + 1.0) * ts).min(hdr.width as f32),
+                ((ty + 1.0) * ts).min(hdr.height as f32),
+            );
+            Rect::from_two_pos(to_screen(min), to_screen(max))
+        };
+        let mut counts = [0usize; 5];
+        for (i, status) in tiles.iter().enumerate() {
+            let k = kind(status);
+            counts[k] += 1;
+            let rect = cell(i);
+            painter.rect_filled(rect, 0.0, COLORS[k].gamma_multiply(0.75));
+            painter.rect_stroke(
+                rect,
+                0.0,
+                Stroke::new(1.0, Color32::from_gray(40)),
+                egui::StrokeKind::Inside,
+            );
+        }
+        let lines: Vec<String> = LABELS
+            .iter()
+            .zip(counts)
+            .filter(|(_, n)| *n > 0)
+            .map(|(label, n)| format!("■ {label}: {n}"))
+            .collect();
+        legend(painter, &lines);
+
+        // popisek dlaždice pod ukazatelem
+        if let Some(pos) = hover.filter(|p| image.contains(*p)) {
+            let p = to_image(pos);
+            let (tx, ty) = ((p.x / ts) as u32, (p.y / ts) as u32);
+            let i = (ty * hdr.tiles_x + tx) as usize;
+            if tx < hdr.tiles_x
+                && let Some(status) = tiles.get(i)
+            {
+                painter.rect_stroke(
+                    cell(i),
+                    0.0,
+                    Stroke::new(2.0, Color32::WHITE),
+                    egui::StrokeKind::Inside,
+                );
+                let text = match status {
+                    TileStatus::Decompress(e) => format!("Tile {tx},{ty}: cannot decompress ({e})"),
+                    s => format!("Tile {tx},{ty}: {}", LABELS[kind(s)].to_lowercase()),
+                };
+                egui::Tooltip::always_open(
+                    ui.ctx().clone(),
+                    ui.layer_id(),
+                    egui::Id::new("diagnose_tile"),
+                    egui::PopupAnchor::Pointer,
+                )
+                .show(|ui| ui.label(text));
+            }
+        }
+    }
+}
+
+const LABELS: [&str; 5] = [
+    "OK",
+    "CRC mismatch",
+    "Cannot decompress",
+    "Offset outside the file",
+    "Wrong size",
+];
+
+const COLORS: [Color32; 5] = [
+    Color32::from_rgb(60, 170, 80),
+    Color32::from_rgb(220, 40, 40),
+    Color32::from_rgb(240, 150, 20),
+    Color32::from_rgb(160, 60, 200),
+    Color32::from_rgb(230, 210, 40),
+];
+
+fn kind(status: &TileStatus) -> usize {
+    match status {
+        TileStatus::Ok => 0,
+        TileStatus::Crc => 1,
+        TileStatus::Decompress(_) => 2,
+        TileStatus::OutOfBounds => 3,
+        TileStatus::WrongSize => 4,
+    }
+}
+
+/// Legenda vlevo nahoře; řádky začínající „■“ dostanou barvu stavu.
+fn legend(painter: &egui::Painter, lines: &[String]) {
+    let font = egui::FontId::proportional(14.0);
+    let mut pos = painter.clip_rect().left_top() + Vec2::new(12.0, 12.0);
+    for line in lines {
+        let color = LABELS
+            .iter()
+            .position(|l| line.starts_with(&format!("■ {l}:")))
+            .map_or(Color32::WHITE, |k| COLORS[k]);
+        let galley = painter.layout_no_wrap(line.clone(), font.clone(), color);
+        let bg = Rect::from_min_size(pos, galley.size()).expand(4.0);
+        painter.rect_filled(bg, 2.0, Color32::from_black_alpha(200));
+        let h = galley.size().y;
+        painter.galley(pos, galley, color);
+        pos.y += h + 8.0;
+    }
+}
@@ -0,0 +1,411 @@
+use anyhow::{Result, ensure};
+use eframe::egui::ColorImage;
+use rayon::prelude::*;
+use serde::Serialize;
+use std::path::PathBuf;
+
+use crate::cti::CTIHeader;
+
+/// Okno SSIM (nepřekrývající se čtverce) a stabilizační konstanty pro rozsah 0..1.
+const WIN: usize = 8;
+const C1: f64 = 0.01 * 0.01;
+const C2: f64 = 0.03 * 0.03;
+
+/// Váhy měřítek MS-SSIM od nejjemnějšího (Wang, Simoncelli, Bovik 2003).
+const MS_WEIGHTS: [f64; 5] = [0.0448, 0.2856, 0.3001, 0.2363, 0.1333];
+
+/// Co se zobrazí v pravé polovině porovnání.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffView {
+    /// Druhý obrázek (bez rozdílu).
+    Off,
+    /// Max |A−B| přes kanály jako teplotní mapa.
+    Heatmap,
+    /// |A−B| po kanálech vynásobený zesílením.
+    Amplified,
+}
+
+impl DiffView {
+    pub const ALL: [DiffView; 3] = [DiffView::Off, DiffView::Heatmap, DiffView::Amplified];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            DiffView::Off => "Side by side",
+            DiffView::Heatmap => "Diff heatmap",
+            DiffView::Amplified => "Amplified diff",
+        }
+    }
+}
+
+/// Souhrnné metriky rozdílu dvou obrázků.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Metrics {
+    /// PSNR v dB (∞ pro shodné obrázky, v JSON `null`).
+    pub psnr: f64,
+    /// Průměrné SSIM na jasové složce (okna 8×8).
+    pub ssim: f64,
+    pub max_abs: u32,
+    pub differing_pixels: u64,
+    pub total_pixels: u64,
+}
+
+/// Uspořádání vzorků RAW bufferu podle ColorType.
+pub struct Layout {
+    pub channels: usize,
+    pub wide: bool, // 16 bit / kanál
+}
+
+pub fn layout(color_type: u8) -> Option<Layout> {
+    let (channels, wide) = match color_type {
+        1 | 6 | 7 => (1, false),
+        2 => (1, true),
+        3 => (3, false),
+        4 => (4, false),
+        5 => (3, true),
+        _ => return None,
+    };
+    Some(Layout { channels, wide })
+}
+
+/// `i`-tý vzorek (kanál) bufferu.
+pub fn sample(raw: &[u8], wide: bool, i: usize) -> u32 {
+    if wide {
+        u16::from_le_bytes([raw[i * 2], raw[i * 2 + 1]]) as u32
+    } else {
+        raw[i] as u32
+    }
+}
+
+fn check(a: &CTIHeader, b: &CTIHeader) -> Result<Layout> {
+    ensure!(
+        a.width == b.width && a.height == b.height,
+        "image sizes differ ({}x{} vs {}x{})",
+        a.width,
+        a.height,
+        b.width,
+        b.height
+    );
+    ensure!(
+        a.color_type == b.color_type,
+        "color types differ ({} vs {})",
+        a.color_type,
+        b.color_type
+    );
+    layout(a.color_type).ok_or_else(|| anyhow::anyhow!("unsupported color type {}", a.color_type))
+}
+
+pub fn metrics(ha: &CTIHeader, a: &[u8], hb: &CTIHeader, b: &[u8]) -> Result<Metrics> {
+    let l = check(ha, hb)?;
+    let (w, h) = (ha.width as usize, ha.height as usize);
+    let peak = if l.wide { 65535.0 } else { 255.0 };
+
+    let mut sq_sum = 0f64;
+    let mut max_abs = 0u32;
+    let mut differing = 0u64;
+    for px in 0..w * h {
+        let mut px_diff = false;
+        for c in 0..l.channels {
+            let i = px * l.channels + c;
+            let d = sample(a, l.wide, i).abs_diff(sample(b, l.wide, i));
+            sq_sum += (d as f64) * (d as f64);
+            max_abs = max_abs.max(d);
+            px_diff |= d != 0;
+        }
+        differing += px_diff as u64;
+    }
+    let mse = sq_sum / (w * h * l.channels) as f64;
+    let psnr = if mse == 0.0 {
+        f64::INFINITY
+    } else {
+        10.0 * (peak * peak / mse).log10()
+    };
+
+    Ok(Metrics {
+        psnr,
+        ssim: ssim_luma(a, b, w, h, &l, peak),
+        max_abs,
+        differing_pixels: differing,
+        total_pixels: (w * h) as u64,
+    })
+}
+
+/// SSIM nad jasem (průměr barevných kanálů, bez alfy) v nepřekrývajících se oknech 8×8.
+fn ssim_luma(a: &[u8], b: &[u8], w: usize, h: usize, l: &Layout, peak: f64) -> f64 {
+    let color = l.channels.min(3);
+    let luma = |raw: &[u8], px: usize| -> f64 {
+        let sum: u32 = (0..color)
+            .map(|c| sample(raw, l.wide, px * l.channels + c))
+            .sum();
+        sum as f64 / color as f64 / peak
+    };
+
+    let mut total = 0f64;
+    let mut windows = 0usize;
+    for by in (0..h).step_by(WIN) {
+        for bx in (0..w).step_by(WIN) {
+            let (ew, eh) = ((bx + WIN).min(w), (by + WIN).min(h));
+            let n = ((ew - bx) * (eh - by)) as f64;
+            let (mut sa, mut sb, mut saa, mut sbb, mut sab) = (0f64, 0f64, 0f64, 0f64, 0f64);
+            for y in by..eh {
+                for x in bx..ew {
+                    let px = y * w + x;
+                    let (va, vb) = (luma(a, px), luma(b, px));
+                    sa += va;
+                    sb += vb;
+                    saa += va * va;
+                    sbb += vb * vb;
+                    sab += va * vb;
+                }
+            }
+            let (ma, mb) = (sa / n, sb / n);
+            let var_a = saa / n - ma * ma;
+            let var_b = sbb / n - mb * mb;
+            let cov = sab / n - ma * mb;
+            total += ((2.0 * ma * mb + C1) * (2.0 * cov + C2))
+                / ((ma * ma + mb * mb + C1) * (var_a + var_b + C2));
+            windows += 1;
+        }
+    }
+    if windows == 0 {
+        1.0
+    } else {
+        total / windows as f64
+    }
+}
+
+/// Metriky jedné dlaždice (obdélník v pixelech).
+#[derive(Debug, Clone, Serialize)]
+pub struct TileMetrics {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    #[serde(flatten)]
+    pub metrics: Metrics,
+}
+
+/// Metriky po dlaždicích `tile_size`×`tile_size` (okrajové jsou menší), po řádcích.
+pub fn tile_metrics(
+    ha: &CTIHeader,
+    a: &[u8],
+    hb: &CTIHeader,
+    b: &[u8],
+    tile_size: u32,
+) -> Result<Vec<TileMetrics>> {
+    let l = check(ha, hb)?;
+    let bpp = l.channels * if l.wide { 2 } else { 1 };
+    let (w, h) = (ha.width, ha.height);
+    let (tx, ty) = (w.div_ceil(tile_size), h.div_ceil(tile_size));
+    (0..tx * ty)
+        .into_par_iter()
+        .map(|i| {
+            let (x, y) = (i % tx * tile_size, i / tx * tile_size);
+            let (tw, th) = (tile_size.min(w - x), tile_size.min(h - y));
+            let crop = |raw: &[u8]| {
+                let mut out = Vec::with_capacity(tw as usize * th as usize * bpp);
+                for row in y..y + th {
+                    let start = (row as usize * w as usize + x as usize) * bpp;
+                    out.extend_from_slice(&raw[start..start + tw as usize * bpp]);
+                }
+                out
+            };
+            let hdr = CTIHeader {
+                width: tw,
+                height: th,
+                ..*ha
+            };
+            Ok(TileMetrics {
+                x,
+                y,
+                width: tw,
+                height: th,
+                metrics: metrics(&hdr, &crop(a), &hdr, &crop(b))?,
+            })
+        })
+        .collect()
+}
+
+/// MS-SSIM nad jasem: SSIM na pěti měřítkách (každé zmenšené na polovinu), vážený součin
+/// kontrastu a struktury jemnějších měřítek a celého SSIM nejhrubšího. Malé obrázky
+/// použijí jen měřítka, na která se vejde okno, s přepočtenými vahami.
+pub fn ms_ssim(ha: &CTIHeader, a: &[u8], hb: &CTIHeader, b: &[u8]) -> Result<f64> {
+    let l = check(ha, hb)?;
+    let (mut w, mut h) = (ha.width as usize, ha.height as usize);
+    let peak = if l.wide { 65535.0 } else { 255.0 };
+    let mut pa = luma_plane(a, w * h, &l, peak);
+    let mut pb = luma_plane(b, w * h, &l, peak);
+    let mut scales = Vec::new();
+    loop {
+        scales.push(ssim_cs(&pa, &pb, w, h));
+        if scales.len() == MS_WEIGHTS.len() || w < 2 * WIN || h < 2 * WIN {
+            break;
+        }
+        (pa, pb) = (half(&pa, w, h), half(&pb, w, h));
+        (w, h) = (w / 2, h / 2);
+    }
+    let weights = &MS_WEIGHTS[..scales.len()];
+    let total: f64 = weights.iter().sum();
+    let last = scales.len() - 1;
+    Ok(scales
+        .iter()
+        .zip(weights)
+        .enumerate()
+        .map(|(i, (&(ssim, cs), wt))| {
+            let v = if i == last { ssim } else { cs };
+            v.max(0.0).powf(wt / total)
+        })
+        .product())
+}
+
+/// Jas (průměr barevných kanálů, bez alfy) v rozsahu 0..1.
+fn luma_plane(raw: &[u8], pixels: usize, l: &Layout, peak: f64) -> Vec<f32> {
+    let color = l.channels.min(3);
+    (0..pixels)
+        .map(|px| {
+            let sum: u32 = (0..color)
+                .map(|c| sample(raw, l.wide, px * l.channels + c))
+                .sum();
+            (sum as f64 / color as f64 / peak) as f32
+        })
+        .collect()
+}
+
+/// Zmenšení na polovinu průměrem 2×2 (lichý okraj se zahodí).
+fn half(plane: &[f32], w: usize, h: usize) -> Vec<f32> {
+    let (hw, hh) = (w / 2, h / 2);
+    let mut out = Vec::with_capacity(hw * hh);
+    for y in 0..hh {
+        for x in 0..hw {
+            let p = |dx, dy| plane[(2 * y + dy) * w + 2 * x + dx];
+            out.push((p(0, 0) + p(1, 0) + p(0, 1) + p(1, 1)) / 4.0);
+        }
+    }
+    out
+}
+
+/// Průměrné SSIM a jeho část kontrast·struktura přes okna roviny jasu.
+fn ssim_cs(a: &[f32], b: &[f32], w: usize, h: usize) -> (f64, f64) {
+    let (mut ssim, mut cs, mut windows) = (0f64, 0f64, 0usize);
+    for by in (0..h).step_by(WIN) {
+        for bx in (0..w).step_by(WIN) {
+            let (ew, eh) = ((bx + WIN).min(w), (by + WIN).min(h));
+            let n = ((ew - bx) * (eh - by)) as f64;
+            let (mut sa, mut sb, mut saa, mut sbb, mut sab) = (0f64, 0f64, 0f64, 0f64, 0f64);
+            for y in by..eh {
+                for x in bx..ew {
+                    let (va, vb) = (f64::from(a[y * w + x]), f64::from(b[y * w + x]));
+                    sa += va;
+                    sb += vb;
+                    saa += va * va;
+                    sbb += vb * vb;
+                    sab += va * vb;
+                }
+            }
+            let (ma, mb) = (sa / n, sb / n);
+            let var_a = saa / n - ma * ma;
+            let var_b = sbb / n - mb * mb;
+            let cov = sab / n - ma * mb;
+            let c = (2.0 * cov + C2) / (var_a + var_b + C2);
+            ssim += (2.0 * ma * mb + C1) / (ma * ma + mb * mb + C1) * c;
+            cs += c;
+            windows += 1;
+        }
+    }
+    if windows == 0 {
+        (1.0, 1.0)
+    } else {
+        (ssim / windows as f64, cs / windows as f64)
+    }
+}
+
+/// Zpráva o kvalitě `test` vůči `reference` (JSON z `cti-view metrics` a porovnání v GUI).
+#[derive(Debug, Serialize)]
+pub struct QualityReport {
+    pub reference: PathBuf,
+    pub test: PathBuf,
+    pub width: u32,
+    pub height: u32,
+    #[serde(flatten)]
+    pub metrics: Metrics,
+    /// Jen na vyžádání (pomalejší než SSIM).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ms_ssim: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tiles: Option<Vec<TileMetrics>>,
+}
+
+impl QualityReport {
+    /// `tile_size` = i metriky po dlaždicích této velikosti.
+    pub fn compute(
+        reference: (PathBuf, &CTIHeader, &[u8]),
+        test: (PathBuf, &CTIHeader, &[u8]),
+        ms_ssim: bool,
+        tile_size: Option<u32>,
+    ) -> Result<Self> {
+        let (ha, a) = (reference.1, reference.2);
+        let (hb, b) = (test.1, test.2);
+        Ok(Self {
+            width: ha.width,
+            height: ha.height,
+            metrics: metrics(ha, a, hb, b)?,
+            ms_ssim: ms_ssim.then(|| self::ms_ssim(ha, a, hb, b)).transpose()?,
+            tiles: tile_size
+                .map(|ts| tile_metrics(ha, a, hb, b, ts))
+                .transpose()?,
+            reference: reference.0,
+            test: test.0,
+        })
+    }
+}
+
+/// Obrázek rozdílu pro zobrazení.
+pub fn diff_image(
+    ha: &CTIHeader,
+    a: &[u8],
+    hb: &CTIHeader,
+    b: &[u8],
+    view: DiffView,
+    gain: f32,
+) -> Result<ColorImage> {
+    let l = check(ha, hb)?;
+    let (w, h) = (ha.width as usize, ha.height as usize);
+    let peak = if l.wide { 65535.0 } else { 255.0 };
+    let color = l.channels.min(3);
+
+    let mut rgba = Vec::with_capacity(w * h * 4);
+    for px in 0..w * h {
+        let d = |c: usize| {
+            let i = px * l.channels + c;
+            sample(a, l.wide, i).abs_diff(sample(b, l.wide, i)) as f32 / peak
+        };
+        match view {
+            DiffView::Amplified => {
+                let v = |c: usize| ((d(c.min(color - 1)) * gain).min(1.0) * 255.0) as u8;
+                rgba.extend_from_slice(&[v(0), v(1), v(2), 255]);
+            }
+            _ => {
+                let m = (0..l.channels).map(d).fold(0f32, f32::max);
+                let [r, g, b] = heat(m);
+                rgba.extend_from_slice(&[r, g, b, 255]);
+            }
+        }
+    }
+    Ok(ColorImage::from_rgba_unmultiplied([w, h], &rgba))
+}
+
+/// Teplotní škála černá → modrá → červená → žlutá → bílá; i malé rozdíly jsou vidět
+/// díky odmocninovému průběhu.
+fn heat(v: f32) -> [u8; 3] {
+    const STOPS: [[f32; 3]; 5] = [
+        [0.0, 0.0, 0.0],
+        [0.0, 0.0, 1.0],
+        [1.0, 0.0, 0.0],
+        [1.0, 1.0, 0.0],
+        [1.0, 1.0, 1.0],
+    ];
+    let t = v.clamp(0.0, 1.0).sqrt() * (STOPS.len() - 1) as f32;
+    let i = (t as usize).min(STOPS.len() - 2);
+    let f = t - i as f32;
+    let mix = |c: usize| ((STOPS[i][c] * (1.0 - f) + STOPS[i + 1][c] * f) * 255.0) as u8;
+    [mix(0), mix(1), mix(2)]
+}
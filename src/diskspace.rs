@@ -0,0 +1,80 @@
+use anyhow::{Context, Result, bail};
+use std::path::Path;
+
+use crate::dryrun::human;
+
+/// Rezerva nad odhad výstupu v procentech (odhad ze vzorku dlaždic není přesný).
+const RESERVE_PERCENT: u64 = 10;
+
+/// Volné místo pro zápis do `path`; cesta ještě nemusí existovat (měří se na nejbližší
+/// existující nadřazené složce). `None` = na této platformě se zjistit nedá.
+pub fn free_space(path: &Path) -> Result<Option<u64>> {
+    let path = if path.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        path
+    };
+    let existing = path
+        .ancestors()
+        .find(|p| p.exists())
+        .unwrap_or(Path::new("."));
+    available(existing).with_context(|| format!("free space of {}", existing.display()))
+}
+
+/// Selže hned, když se `need` bajtů (s rezervou) nevejde do `dir`; nezjistitelné volné
+/// místo kontrolu přeskočí.
+pub fn ensure_free(dir: &Path, need: u64) -> Result<()> {
+    let Some(free) = free_space(dir)? else {
+        return Ok(());
+    };
+    let reserve = need / 100 * RESERVE_PERCENT;
+    if need + reserve > free {
+        bail!(
+            "not enough free space in {}: the output needs about {} (+{RESERVE_PERCENT} % reserve), \
+             only {} is free",
+            dir.display(),
+            human(need),
+            human(free)
+        );
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+#[allow(clippy::unnecessary_cast)] // typy polí statvfs se mezi platformami liší
+fn available(path: &Path) -> std::io::Result<Option<u64>> {
+    use std::os::unix::ffi::OsStrExt;
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes())?;
+    let mut st: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: `path` je platný řetězec zakončený nulou, `st` je zapisovatelná struktura
+    if unsafe { libc::statvfs(path.as_ptr(), &mut st) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(Some(st.f_bavail as u64 * st.f_frsize as u64))
+}
+
+#[cfg(windows)]
+fn available(path: &Path) -> std::io::Result<Option<u64>> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain([0]).collect();
+    let mut free = 0u64;
+    // SAFETY: `wide` je zakončený nulou, nepotřebné výstupy smějí být null
+    let ok = unsafe {
+        GetDiskFreeSpaceExW(
+            wide.as_ptr(),
+            &mut free,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    };
+    if ok == 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(Some(free))
+}
+
+#[cfg(not(any(unix, windows)))]
+fn available(_path: &Path) -> std::io::Result<Option<u64>> {
+    Ok(None)
+}
@@ -0,0 +1,146 @@
+use anyhow::{Context, Result, anyhow};
+use eframe::egui::{self, ColorImage};
+use moxcms::{ColorProfile, Layout, Transform8BitExecutor, TransformOptions};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// ICC profil přiřazený monitoru (širokogamutový QA monitor vs. běžný kancelářský).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MonitorProfile {
+    /// Identifikace monitoru z [`monitor_key`].
+    pub monitor: String,
+    pub icc: PathBuf,
+}
+
+/// Monitor, na kterém je okno: rozlišení v pixelech a škálování OS (např.
+/// „2560×1440 @ 125 %“). Dva stejné monitory se stejným škálováním nerozliší.
+pub fn monitor_key(ctx: &egui::Context) -> Option<String> {
+    let (size, ppp) = ctx.input(|i| {
+        let vp = i.viewport();
+        (vp.monitor_size, vp.native_pixels_per_point)
+    });
+    let px = size? * ppp?;
+    Some(format!(
+        "{}×{} @ {} %",
+        px.x.round(),
+        px.y.round(),
+        (ppp? * 100.0).round()
+    ))
+}
+
+/// Převod zobrazovaného obrázku ze sRGB do profilu monitoru.
+pub struct DisplayTransform {
+    pub icc: PathBuf,
+    transform: Arc<Transform8BitExecutor>,
+}
+
+impl DisplayTransform {
+    pub fn load(icc: &Path) -> Result<Self> {
+        let data = std::fs::read(icc).with_context(|| format!("read {}", icc.display()))?;
+        let dst =
+            ColorProfile::new_from_slice(&data).map_err(|e| anyhow!("{}: {e:?}", icc.display()))?;
+        let transform = ColorProfile::new_srgb()
+            .create_transform_8bit(
+                Layout::Rgba,
+                &dst,
+                Layout::Rgba,
+                TransformOptions::default(),
+            )
+            .map_err(|e| anyhow!("{}: {e:?}", icc.display()))?;
+        Ok(Self {
+            icc: icc.to_path_buf(),
+            transform,
+        })
+    }
+
+    /// Převede pixely textury (alfa zůstává).
+    pub fn apply(&self, image: &mut ColorImage) {
+        let src = image.as_raw().to_vec();
+        if let Err(e) = self.transform.transform(&src, image.as_raw_mut()) {
+            tracing::error!("display profile {}: {e:?}", self.icc.display());
+        }
+    }
+}
+
+/// Nastavení správy barev v Preferences; `monitor` = monitor, na kterém je okno teď.
+pub fn profiles_ui(
+    ui: &mut egui::Ui,
+    enabled: &mut bool,
+    profiles: &mut Vec<MonitorProfile>,
+    monitor: Option<&str>,
+) {
+    ui.checkbox(enabled, "Color-manage the image view")
+        .on_hover_text(
+            "Images are treated as sRGB and converted to the profile of the monitor \
+             the window is on",
+        );
+    ui.label(format!("This monitor: {}", monitor.unwrap_or("unknown")));
+    let mut remove = None;
+    egui::Grid::new("monitor-profiles")
+        .num_columns(3)
+        .spacing([12.0, 6.0])
+        .show(ui, |ui| {
+            for (i, p) in profiles.iter_mut().enumerate() {
+                let mut name = egui::RichText::new(&p.monitor);
+                if monitor == Some(p.monitor.as_str()) {
+                    name = name.strong();
+                }
+                ui.label(name);
+                let file = p.icc.file_name().unwrap_or_default().to_string_lossy();
+                if ui
+                    .button(file)
+                    .on_hover_text(p.icc.display().to_string())
+                    .clicked()
+                    && let Some(path) = pick_icc()
+                {
+                    p.icc = path;
+                }
+                if ui.small_button("Remove").clicked() {
+                    remove = Some(i);
+                }
+                ui.end_row();
+            }
+        });
+    if let Some(i) = remove {
+        profiles.remove(i);
+    }
+    if let Some(monitor) = monitor
+        && !profiles.iter().any(|p| p.monitor == monitor)
+        && ui.button("Assign a profile to this monitor…").clicked()
+        && let Some(icc) = pick_icc()
+    {
+        profiles.push(MonitorProfile {
+            monitor: monitor.to_owned(),
+            icc,
+        });
+    }
+}
+
+/// Převod CMYK souborů pro zobrazení: naivní, nebo přes ICC profil tisku (soft-proof).
+pub fn cmyk_ui(ui: &mut egui::Ui, profile: &mut Option<PathBuf>) {
+    ui.horizontal(|ui| {
+        ui.label("CMYK images:");
+        if ui.radio(profile.is_none(), "Naive").clicked() {
+            *profile = None;
+        }
+        let label = match profile {
+            Some(icc) => icc.file_name().unwrap_or_default().to_string_lossy().into_owned(),
+            None => "ICC profile…".to_owned(),
+        };
+        if ui
+            .radio(profile.is_some(), label)
+            .on_hover_text("Soft-proof through the profile of the print condition (CMYK → sRGB)")
+            .clicked()
+            && let Some(icc) = pick_icc()
+        {
+            *profile = Some(icc);
+        }
+    });
+}
+
+fn pick_icc() -> Option<PathBuf> {
+    rfd::FileDialog::new()
+        .add_filter("ICC profile", &["icc", "icm"])
+        .pick_file()
+}
@@ -0,0 +1,153 @@
+use anyhow::{Context, Result, bail};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::cti::{CompressionId, EncodeParams};
+use crate::export::{BitDepth, ExportFormat, ExportOptions};
+use crate::naming::Planned;
+
+/// Plán dávky pro `--dry-run`: vypíše, co by se přečetlo, zapsalo a přepsalo, ale nic
+/// nezapíše. Velikosti výstupů jsou jen odhady.
+#[derive(Default)]
+pub struct Plan {
+    /// Jména výstupů naplánovaná dřív v téže dávce.
+    pub taken: HashSet<PathBuf>,
+    reads: usize,
+    read: u64,
+    written: u64,
+    /// Výstupy, jejichž velikost předem odhadnout nejde.
+    unknown: usize,
+    new: usize,
+    overwritten: usize,
+    skipped: usize,
+    failed: usize,
+}
+
+impl Plan {
+    /// Naplánuje jeden vstup (nebo výstup); chyba se vypíše a počítá, plán pokračuje dál.
+    pub fn file(&mut self, src: &Path, plan: impl FnOnce(&mut Self) -> Result<()>) {
+        if let Err(e) = plan(self) {
+            self.failed += 1;
+            println!("FAIL      {}: {e:#}", src.display());
+        }
+    }
+
+    /// Čtený soubor (`detail` = rozměr apod.); vrací jeho velikost.
+    pub fn read(&mut self, path: &Path, detail: &str) -> Result<u64> {
+        let size = std::fs::metadata(path)
+            .with_context(|| format!("read {}", path.display()))?
+            .len();
+        self.reads += 1;
+        self.read += size;
+        let detail = if detail.is_empty() {
+            String::new()
+        } else {
+            format!(", {detail}")
+        };
+        println!("read      {} ({}{detail})", path.display(), human(size));
+        Ok(size)
+    }
+
+    /// Výstup podle cíle z [`OutputName::plan`](crate::naming::OutputName::plan);
+    /// `estimate` = odhad velikosti, `None` = předem neznámá.
+    pub fn write(&mut self, target: &Planned, estimate: Option<u64>) {
+        let est = estimate.map_or("size unknown".into(), |e| format!("≈ {}", human(e)));
+        if estimate.is_none() && !matches!(target, Planned::Skip(_)) {
+            self.unknown += 1;
+        }
+        match target {
+            Planned::New(path) => {
+                self.new += 1;
+                self.written += estimate.unwrap_or(0);
+                println!("write     {} (new, {est})", path.display());
+            }
+            Planned::Overwrite(path) => {
+                self.overwritten += 1;
+                self.written += estimate.unwrap_or(0);
+                // soubor může být i výstupem dřívějšího souboru téže dávky
+                let old = std::fs::metadata(path)
+                    .map_or("written earlier in this batch".into(), |m| human(m.len()));
+                println!("overwrite {} ({old} → {est})", path.display());
+            }
+            Planned::Skip(path) => {
+                self.skipped += 1;
+                println!("skip      {} (exists)", path.display());
+            }
+        }
+    }
+
+    /// Soubor přepsaný na místě (např. změna metadat).
+    pub fn rewrite(&mut self, path: &Path, estimate: u64, what: &str) {
+        self.overwritten += 1;
+        self.written += estimate;
+        println!(
+            "rewrite   {} (≈ {}; {what})",
+            path.display(),
+            human(estimate)
+        );
+    }
+
+    /// Souhrn; chyba, pokud by některý soubor selhal.
+    pub fn finish(self) -> Result<()> {
+        let unknown = match self.unknown {
+            0 => String::new(),
+            n => format!(" + {n} of unknown size"),
+        };
+        println!(
+            "dry run: read {} file(s) ({}), write ≈ {}{unknown} ({} new, {} overwritten, \
+             {} skipped, {} failed); nothing was written",
+            self.reads,
+            human(self.read),
+            human(self.written),
+            self.new,
+            self.overwritten,
+            self.skipped,
+            self.failed
+        );
+        if self.failed > 0 {
+            bail!("{} file(s) would fail", self.failed);
+        }
+        Ok(())
+    }
+}
+
+/// Velikost pro lidi (B, KiB, MiB, GiB).
+pub fn human(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut v = bytes as f64;
+    let mut unit = 0;
+    while v >= 1024.0 && unit + 1 < UNITS.len() {
+        v /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{v:.1} {}", UNITS[unit])
+    }
+}
+
+/// Odhad velikosti CTI: bez komprese zhruba surová data, se Zstd zhruba jako zdrojový
+/// PNG/TIFF, s LZ4 o čtvrtinu víc, ztrátová DCT zhruba čtvrtina. `raw` = nekomprimovaná
+/// obrazová data, `source` = zdroj.
+pub fn cti_estimate(raw: u64, source: u64, params: &EncodeParams) -> u64 {
+    match params.compression {
+        CompressionId::None => raw,
+        CompressionId::Lz4 => (source + source / 4).min(raw),
+        CompressionId::Dct => (source / 4).min(raw),
+        _ => source.min(raw),
+    }
+}
+
+/// Odhad velikosti exportovaného snímku: `raw` = jeho nekomprimovaná data v CTI, `bits` =
+/// bitů na kanál, `packed` = podíl snímku na velikosti CTI souboru.
+pub fn export_estimate(raw: u64, bits: u8, packed: u64, opts: &ExportOptions) -> u64 {
+    let eight = opts.format == ExportFormat::Jpeg || opts.depth == BitDepth::Eight;
+    let raw = if eight && bits == 16 { raw / 2 } else { raw };
+    match opts.format {
+        // při obvyklé kvalitě zhruba desetina 8bitových dat
+        ExportFormat::Jpeg => raw / 10,
+        ExportFormat::Tiff => raw,
+        ExportFormat::Png => packed.min(raw),
+    }
+}
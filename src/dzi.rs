@@ -0,0 +1,217 @@
+//! Export DeepZoom (DZI) pyramidy pro OpenSeadragon a podobné webové prohlížeče:
+//! `{stem}.dzi` (XML) a `{stem}_files/{úroveň}/{sloupec}_{řádek}.{jpg|png}`.
+//!
+//! Nejvyšší úroveň se čte po pásech z dlaždic CTI (v paměti je jen jeden pás), zároveň
+//! se skládá obraz v poloviční velikosti, ze kterého se postupným zmenšováním zapíšou
+//! nižší úrovně.
+
+use anyhow::{Context, Result, bail, ensure};
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat};
+use rayon::prelude::*;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use crate::cti::{CTIDecoder, CTIHeader};
+use crate::export::{self, to_dynamic_image};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DziFormat {
+    Jpeg,
+    Png,
+}
+
+impl DziFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            DziFormat::Jpeg => "jpg",
+            DziFormat::Png => "png",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DziOptions {
+    /// Hrana dlaždice bez překryvu (OpenSeadragon doporučuje 254 s překryvem 1).
+    pub tile_size: u32,
+    /// Pixely navíc na každé vnitřní hraně dlaždice.
+    pub overlap: u32,
+    pub format: DziFormat,
+    pub jpeg_quality: u8,
+    /// Snímek vícesnímkového souboru.
+    pub frame: u32,
+    /// Přepsat existující pyramidu.
+    pub overwrite: bool,
+}
+
+impl Default for DziOptions {
+    fn default() -> Self {
+        Self {
+            tile_size: 254,
+            overlap: 1,
+            format: DziFormat::Jpeg,
+            jpeg_quality: 90,
+            frame: 0,
+            overwrite: false,
+        }
+    }
+}
+
+/// Počet úrovní pyramidy: úroveň 0 má 1 × 1 px, nejvyšší plné rozlišení.
+pub fn max_level(width: u32, height: u32) -> u32 {
+    let side = width.max(height).max(1);
+    u32::BITS - (side - 1).leading_zeros()
+}
+
+/// Zapíše pyramidu `src` do `out_dir`; vrací cestu k `.dzi`.
+pub fn export_dzi(src: &Path, out_dir: &Path, opts: &DziOptions) -> Result<PathBuf> {
+    ensure!(opts.tile_size > 0, "tile size must be positive");
+    let hdr = CTIDecoder::info(src)?;
+    ensure!(
+        opts.frame < hdr.frames,
+        "Frame {} out of range ({} frames)",
+        opts.frame,
+        hdr.frames
+    );
+    let stem = src
+        .file_stem()
+        .context("input has no file name")?
+        .to_string_lossy();
+    let manifest = out_dir.join(format!("{stem}.dzi"));
+    let files = out_dir.join(format!("{stem}_files"));
+    if files.exists() || manifest.exists() {
+        if !opts.overwrite {
+            bail!("{} already exists", manifest.display());
+        }
+        if files.exists() {
+            std::fs::remove_dir_all(&files)
+                .with_context(|| format!("remove {}", files.display()))?;
+        }
+    }
+
+    let (w, h) = (hdr.width, hdr.height);
+    let top = max_level(w, h);
+    let mut half: Option<DynamicImage> = None;
+    let ts = opts.tile_size;
+    let top_dir = level_dir(&files, top)?;
+    // pás = dva řádky dlaždic, ať začíná na sudém řádku pixelů i při liché dlaždici
+    let rows = h.div_ceil(ts);
+    for first in (0..rows).step_by(2) {
+        let last = (first + 2).min(rows);
+        let (y0, y1) = (first * ts, (last * ts).min(h));
+        let (ry0, ry1) = (y0.saturating_sub(opts.overlap), (y1 + opts.overlap).min(h));
+        let (_, raw) = CTIDecoder::decode_region(src, opts.frame, (0, ry0, w, ry1 - ry0))?;
+        let band = CTIHeader {
+            width: w,
+            height: ry1 - ry0,
+            ..hdr
+        };
+        let band = to_8bit(to_dynamic_image(&band, raw)?);
+        write_tiles(&band, ry0, (w, h), first..last, &top_dir, opts)?;
+        if top > 0 {
+            let core = band.crop_imm(0, y0 - ry0, w, y1 - y0);
+            let small =
+                core.resize_exact(w.div_ceil(2), (y1 - y0).div_ceil(2), FilterType::Triangle);
+            let half = half.get_or_insert_with(|| {
+                DynamicImage::new(w.div_ceil(2), h.div_ceil(2), small.color())
+            });
+            image::imageops::replace(half, &small, 0, i64::from(y0 / 2));
+        }
+    }
+
+    // nižší úrovně z obrazu v paměti
+    if let Some(half) = half {
+        write_pyramid(half, top - 1, &files, opts)?;
+    }
+
+    // manifest až nakonec: jeho existence znamená hotovou pyramidu
+    let xml = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <Image xmlns=\"http://schemas.microsoft.com/deepzoom/2008\" Format=\"{}\" \
+         Overlap=\"{}\" TileSize=\"{ts}\">\n  <Size Width=\"{w}\" Height=\"{h}\"/>\n</Image>\n",
+        opts.format.extension(),
+        opts.overlap
+    );
+    std::fs::write(&manifest, xml).with_context(|| format!("write {}", manifest.display()))?;
+    Ok(manifest)
+}
+
+/// Zapíše úrovně `top..=0` do `files`: `img` je úroveň `top`, každá nižší má poloviční
+/// rozměr.
+pub fn write_pyramid(
+    mut img: DynamicImage,
+    top: u32,
+    files: &Path,
+    opts: &DziOptions,
+) -> Result<()> {
+    for level in (0..=top).rev() {
+        let (lw, lh) = (img.width(), img.height());
+        let dir = level_dir(files, level)?;
+        let rows = 0..lh.div_ceil(opts.tile_size);
+        write_tiles(&img, 0, (lw, lh), rows, &dir, opts)?;
+        if level > 0 {
+            img = img.resize_exact(lw.div_ceil(2), lh.div_ceil(2), FilterType::Triangle);
+        }
+    }
+    Ok(())
+}
+
+fn level_dir(files: &Path, level: u32) -> Result<PathBuf> {
+    let dir = files.join(level.to_string());
+    std::fs::create_dir_all(&dir).with_context(|| format!("create {}", dir.display()))?;
+    Ok(dir)
+}
+
+/// Zapíše řádky dlaždic `rows` úrovně `(w, h)`; `img` je pás této úrovně od řádku
+/// pixelů `y_offset` (celá úroveň: 0).
+fn write_tiles(
+    img: &DynamicImage,
+    y_offset: u32,
+    (w, h): (u32, u32),
+    rows: std::ops::Range<u32>,
+    dir: &Path,
+    opts: &DziOptions,
+) -> Result<()> {
+    let (ts, ov) = (opts.tile_size, opts.overlap);
+    let span = |i: u32, len: u32| {
+        let start = (i * ts).saturating_sub(ov);
+        (start, ((i + 1) * ts + ov).min(len) - start)
+    };
+    let tiles: Vec<(u32, u32)> = rows
+        .flat_map(|row| (0..w.div_ceil(ts)).map(move |col| (col, row)))
+        .collect();
+    tiles.par_iter().try_for_each(|&(col, row)| {
+        let (x, tw) = span(col, w);
+        let (y, th) = span(row, h);
+        let tile = img.crop_imm(x, y - y_offset, tw, th);
+        let path = dir.join(format!("{col}_{row}.{}", opts.format.extension()));
+        save_tile(tile, &path, opts).with_context(|| format!("write {}", path.display()))
+    })
+}
+
+fn save_tile(img: DynamicImage, path: &Path, opts: &DziOptions) -> Result<()> {
+    match opts.format {
+        DziFormat::Jpeg => {
+            // JPEG bez alfy
+            let img = match img {
+                DynamicImage::ImageRgba8(_) => DynamicImage::ImageRgb8(img.to_rgb8()),
+                img => img,
+            };
+            let out = BufWriter::new(File::create(path)?);
+            img.write_with_encoder(JpegEncoder::new_with_quality(out, opts.jpeg_quality))?;
+        }
+        DziFormat::Png => img.save_with_format(path, ImageFormat::Png)?,
+    }
+    Ok(())
+}
+
+/// Webové prohlížeče pracují s 8 bity na kanál (HDR se namapuje jako při zobrazení).
+pub fn to_8bit(img: DynamicImage) -> DynamicImage {
+    match img {
+        DynamicImage::ImageLuma16(_) => DynamicImage::ImageLuma8(img.to_luma8()),
+        DynamicImage::ImageRgb16(_) => DynamicImage::ImageRgb8(img.to_rgb8()),
+        img => export::tone_mapped(img),
+    }
+}
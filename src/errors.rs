@@ -0,0 +1,139 @@
+//! Dialog s chybou otevření nebo dekódování: celý řetězec příčin a doporučený postup, aby
+//! chyba nezůstala jen v konzoli (ve Windows GUI buildu žádná není).
+
+use eframe::egui;
+use std::path::{Path, PathBuf};
+
+use crate::a11y;
+
+pub struct ErrorReport {
+    title: String,
+    path: Option<PathBuf>,
+    /// Řetězec příčin od nejvyšší (co se dělalo) po nejnižší (proč to selhalo).
+    chain: Vec<String>,
+    hints: Vec<&'static str>,
+}
+
+impl ErrorReport {
+    pub fn new(title: impl Into<String>, path: Option<&Path>, err: &anyhow::Error) -> Self {
+        let chain: Vec<String> = err.chain().map(|c| c.to_string()).collect();
+        let hints = hints(&chain.join("\n"));
+        Self {
+            title: title.into(),
+            path: path.map(Path::to_path_buf),
+            chain,
+            hints,
+        }
+    }
+
+    /// Text pro schránku (hlášení chyby).
+    fn details(&self) -> String {
+        let mut out = self.title.clone();
+        if let Some(path) = &self.path {
+            out += &format!("\n{}", path.display());
+        }
+        for (i, c) in self.chain.iter().enumerate() {
+            out += &format!("\n{}{c}", if i == 0 { "" } else { "caused by: " });
+        }
+        out
+    }
+
+    /// Vrátí `false`, když uživatel dialog zavřel; `show_log` = chce otevřít okno Log.
+    pub fn window(&self, ctx: &egui::Context, show_log: &mut bool) -> bool {
+        let (mut open, mut close) = (true, false);
+        a11y::modal_dialog(ctx, &self.title, &mut open, |ui| {
+            if let Some(path) = &self.path {
+                ui.monospace(path.display().to_string());
+                ui.add_space(4.0);
+            }
+            ui.label(egui::RichText::new(&self.chain[0]).strong());
+            for cause in &self.chain[1..] {
+                ui.label(format!("caused by: {cause}"));
+            }
+            if !self.hints.is_empty() {
+                ui.separator();
+                ui.label(egui::RichText::new("What you can do").strong());
+                for hint in &self.hints {
+                    ui.label(format!("• {hint}"));
+                }
+            }
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui.button("Copy details").clicked() {
+                    ui.ctx().copy_text(self.details());
+                }
+                if ui.button("Show log").clicked() {
+                    *show_log = true;
+                    close = true;
+                }
+                if ui.button("OK").clicked() {
+                    close = true;
+                }
+            });
+        });
+        open && !close
+    }
+}
+
+/// Doporučení podle textu chyby.
+fn hints(text: &str) -> Vec<&'static str> {
+    const HINTS: &[(&[&str], &str)] = &[
+        (
+            &[
+                "Bad magic",
+                "Empty image or tile size",
+                "Tile grid",
+                "Tile index of",
+                "too large for this platform",
+                "too large for the tile index",
+            ],
+            "The file is not a CTI image, or its beginning is damaged. Check that it is the \
+             right file and that it was copied completely.",
+        ),
+        (
+            &[
+                "Unsupported compression",
+                "Unsupported color type",
+                "Unsupported ColorType",
+                "requires a newer viewer",
+            ],
+            "The file was written by a newer encoder. Update CTI View (Preferences → Updates).",
+        ),
+        (
+            &["CRC mismatch", "decompress failed"],
+            "Some tiles are damaged. Run Verify from the file browser, and restore the file \
+             from a backup or re-encode it from the master.",
+        ),
+        (
+            &["failed to fill whole buffer", "Truncated"],
+            "The file is truncated, for example by an unfinished copy or download. Copy it \
+             again from the source.",
+        ),
+        (
+            &["16-bit preview"],
+            "16-bit images cannot be shown yet; export them to TIFF with `cti-view export`.",
+        ),
+        (
+            &["No such file", "cannot find the"],
+            "The file was moved or deleted. Open it again from its new location.",
+        ),
+        (
+            &["Permission denied", "Access is denied"],
+            "CTI View may not read this file. Check its permissions or copy it elsewhere.",
+        ),
+        (
+            &["brotli` feature", "deflate` feature"],
+            "This build of CTI View cannot read the codec of this file; open it in a build \
+             with that feature, or recompress the file with Zstd.",
+        ),
+        (
+            &["remote` feature"],
+            "This build cannot open URLs; download the file and open it from disk.",
+        ),
+    ];
+    HINTS
+        .iter()
+        .filter(|(needles, _)| needles.iter().any(|n| text.contains(n)))
+        .map(|(_, hint)| *hint)
+        .collect()
+}
@@ -0,0 +1,382 @@
+use anyhow::{Result, anyhow, bail};
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, ImageBuffer, ImageFormat};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use crate::cmyk;
+use crate::cti::{
+    CTIDecoder, CTIEncoder, CTIHeader, CTIMetadata, CompressionId, EncodeParams, bytes_per_pixel,
+};
+use crate::dryrun::{self, Plan};
+use crate::flatfield::FlatField;
+use crate::hdr;
+use crate::lens::LensCorrection;
+use crate::naming::{NameVars, OutputName};
+use crate::tilecache::TileCache;
+
+/// Převede dekódovaný RAW buffer na `DynamicImage` se zachováním bitové hloubky.
+pub fn to_dynamic_image(hdr: &CTIHeader, raw: Vec<u8>) -> Result<DynamicImage> {
+    let (w, h) = (hdr.width, hdr.height);
+    let bad = || anyhow!("buffer size does not match {}x{}", w, h);
+    Ok(match hdr.color_type {
+        1 | 6 | 7 => DynamicImage::ImageLuma8(ImageBuffer::from_raw(w, h, raw).ok_or_else(bad)?),
+        2 => DynamicImage::ImageLuma16(ImageBuffer::from_raw(w, h, le_u16(&raw)).ok_or_else(bad)?),
+        3 => DynamicImage::ImageRgb8(ImageBuffer::from_raw(w, h, raw).ok_or_else(bad)?),
+        4 => DynamicImage::ImageRgba8(ImageBuffer::from_raw(w, h, raw).ok_or_else(bad)?),
+        5 => DynamicImage::ImageRgb16(ImageBuffer::from_raw(w, h, le_u16(&raw)).ok_or_else(bad)?),
+        // `image` CMYK nezná – převede se stejně jako pro zobrazení
+        8 => {
+            let rgb = cmyk::to_rgb(&raw);
+            DynamicImage::ImageRgb8(ImageBuffer::from_raw(w, h, rgb).ok_or_else(bad)?)
+        }
+        // `image` nemá šedou ve float – L32F jako RGB32F se stejnými kanály
+        9 => {
+            let rgb = hdr::samples(&raw)
+                .into_iter()
+                .flat_map(|l| [l; 3])
+                .collect();
+            DynamicImage::ImageRgb32F(ImageBuffer::from_raw(w, h, rgb).ok_or_else(bad)?)
+        }
+        10 => DynamicImage::ImageRgb32F(
+            ImageBuffer::from_raw(w, h, hdr::samples(&raw)).ok_or_else(bad)?,
+        ),
+        _ => bail!("Unsupported ColorType ID {}", hdr.color_type),
+    })
+}
+
+/// Opak `to_dynamic_image`: obrázek → (ColorType ID, RAW buffer ve formátu CTI).
+/// Typy bez ekvivalentu v CTI se převedou na nejbližší bezeztrátový (LA → RGBA, RGBA16 bez
+/// průhlednosti → RGB16, RGBA32F bez průhlednosti → RGB32F); 16bit a float obrázky
+/// s průhledností se odmítnou.
+pub fn from_dynamic_image(img: DynamicImage) -> Result<(u8, Vec<u8>)> {
+    let le = |v: &[u16]| v.iter().flat_map(|x| x.to_le_bytes()).collect::<Vec<u8>>();
+    Ok(match img {
+        DynamicImage::ImageLuma8(b) => (1, b.into_raw()),
+        DynamicImage::ImageLuma16(b) => (2, le(&b.into_raw())),
+        DynamicImage::ImageRgb8(b) => (3, b.into_raw()),
+        DynamicImage::ImageRgba8(b) => (4, b.into_raw()),
+        DynamicImage::ImageRgb16(b) => (5, le(&b.into_raw())),
+        DynamicImage::ImageLumaA8(_) => (4, img.to_rgba8().into_raw()),
+        DynamicImage::ImageRgba16(b) => {
+            if b.pixels().any(|p| p.0[3] != u16::MAX) {
+                bail!("16-bit images with transparency are not supported by CTI");
+            }
+            (5, le(&DynamicImage::ImageRgba16(b).to_rgb16().into_raw()))
+        }
+        DynamicImage::ImageLumaA16(b) => {
+            if b.pixels().any(|p| p.0[1] != u16::MAX) {
+                bail!("16-bit images with transparency are not supported by CTI");
+            }
+            (2, le(&DynamicImage::ImageLumaA16(b).to_luma16().into_raw()))
+        }
+        DynamicImage::ImageRgb32F(b) => (10, hdr::to_raw(&b.into_raw())),
+        DynamicImage::ImageRgba32F(b) => {
+            if b.pixels().any(|p| p.0[3] != 1.0) {
+                bail!("float images with transparency are not supported by CTI");
+            }
+            let rgb = DynamicImage::ImageRgba32F(b).to_rgb32f();
+            (10, hdr::to_raw(&rgb.into_raw()))
+        }
+        other => bail!("unsupported pixel format {:?}", other.color()),
+    })
+}
+
+/// Formát derivátů pro export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExportFormat {
+    Png,
+    Tiff,
+    Jpeg,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 3] = [ExportFormat::Png, ExportFormat::Tiff, ExportFormat::Jpeg];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ExportFormat::Png => "PNG",
+            ExportFormat::Tiff => "TIFF",
+            ExportFormat::Jpeg => "JPEG",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Png => "png",
+            ExportFormat::Tiff => "tif",
+            ExportFormat::Jpeg => "jpg",
+        }
+    }
+}
+
+/// Co s 16bitovými daty při exportu.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BitDepth {
+    /// Zachovat (JPEG je vždy 8 bit).
+    Keep,
+    /// Převést na 8 bit.
+    Eight,
+}
+
+/// Volby dávkového exportu CTI → standardní formát.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExportOptions {
+    pub format: ExportFormat,
+    pub depth: BitDepth,
+    pub jpeg_quality: u8,
+    /// Šablona jména bez přípony a chování při kolizi.
+    pub name: OutputName,
+    /// Reference pro korekci osvětlení (viz [`FlatField`]).
+    pub flat_field: Option<PathBuf>,
+    pub lens: LensCorrection,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            format: ExportFormat::Png,
+            depth: BitDepth::Keep,
+            jpeg_quality: 90,
+            name: OutputName::default(),
+            flat_field: None,
+            lens: LensCorrection::Off,
+        }
+    }
+}
+
+/// Cílová složka; při exportu složky (`in_root`) se zachová relativní umístění.
+fn export_dir(src: &Path, in_root: Option<&Path>, out_dir: &Path) -> PathBuf {
+    let sub = in_root
+        .and_then(|root| src.parent()?.strip_prefix(root).ok())
+        .unwrap_or(Path::new(""));
+    out_dir.join(sub)
+}
+
+/// Naplánuje [`export_into`] pro `--dry-run` (jen hlavička a metadata, bez dekódování).
+pub fn plan_export(
+    plan: &mut Plan,
+    src: &Path,
+    in_root: Option<&Path>,
+    out_dir: &Path,
+    n: usize,
+    opts: &ExportOptions,
+) -> Result<()> {
+    let hdr = CTIDecoder::info(src)?;
+    let frames = CTIDecoder::frame_count(src)?;
+    let exported = if opts.name.uses("page") { frames } else { 1 };
+    // chybná reference by selhala u každého souboru
+    if let Some(path) = &opts.flat_field {
+        FlatField::cached(path)?.check(&hdr)?;
+    }
+    let size = plan.read(
+        src,
+        &format!("{}x{}, {frames} frame(s)", hdr.width, hdr.height),
+    )?;
+    let dir = export_dir(src, in_root, out_dir);
+    let meta = CTIDecoder::metadata(src)?;
+    let raw = hdr.width as u64 * hdr.height as u64 * bytes_per_pixel(hdr.color_type)? as u64;
+    let bits = bit_depth(hdr.color_type);
+    let estimate = dryrun::export_estimate(raw, bits, size / frames.max(1) as u64, opts);
+    for frame in 0..exported {
+        let vars = NameVars {
+            n: Some(n),
+            page: Some(frame as usize + 1),
+            barcode: meta.get(CTIMetadata::IDENTIFIER),
+            size: Some((hdr.width, hdr.height)),
+            depth: Some(bits),
+            ..NameVars::new(src)
+        };
+        let ext = opts.format.extension();
+        let target = opts.name.plan(&dir, ext, &vars, &mut plan.taken)?;
+        plan.write(&target, Some(estimate));
+    }
+    Ok(())
+}
+
+/// Bitů na kanál podle typu barev CTI.
+pub fn bit_depth(color_type: u8) -> u8 {
+    match color_type {
+        2 | 5 => 16,
+        9 | 10 => 32,
+        _ => 8,
+    }
+}
+
+/// Dekóduje `src` a uloží derivát do `out_dir` pod jménem ze šablony; `n` = pořadí souboru
+/// v dávce. Šablona se `{page}` vyexportuje každý snímek zvlášť, jinak jen první.
+/// Při exportu složky (`in_root`) se zachová relativní umístění podadresářů.
+pub fn export_into(
+    src: &Path,
+    in_root: Option<&Path>,
+    out_dir: &Path,
+    n: usize,
+    opts: &ExportOptions,
+) -> Result<String> {
+    let frames = if opts.name.uses("page") {
+        CTIDecoder::frame_count(src)?
+    } else {
+        1
+    };
+    let dir = export_dir(src, in_root, out_dir);
+    let meta = CTIDecoder::metadata(src)?;
+    // {barcode} = identifikátor přečtený při převodu do CTI
+    let id = meta.get(CTIMetadata::IDENTIFIER);
+    let mut written = Vec::new();
+    let mut skipped = 0;
+    for frame in 0..frames {
+        let (hdr, mut raw) = CTIDecoder::decode_frame(src, frame)?;
+        // osvětlení před geometrií: reference je snímaná stejnou optikou jako originál
+        if let Some(path) = &opts.flat_field {
+            raw = FlatField::cached(path)?.apply(&hdr, &raw)?;
+        }
+        if opts.lens != LensCorrection::Off
+            && let Some(profile) = opts.lens.profile_for(&meta)
+        {
+            raw = profile.apply(&hdr, &raw)?;
+        }
+        let vars = NameVars {
+            n: Some(n),
+            page: Some(frame as usize + 1),
+            barcode: id,
+            size: Some((hdr.width, hdr.height)),
+            depth: Some(bit_depth(hdr.color_type)),
+            ..NameVars::new(src)
+        };
+        let dst = opts
+            .name
+            .write(&dir, opts.format.extension(), &vars, |dst| {
+                save(to_dynamic_image(&hdr, raw)?, dst, opts)
+            })?;
+        match dst {
+            Some(dst) => written.push(dst.display().to_string()),
+            None => skipped += 1,
+        }
+    }
+    Ok(match (written.is_empty(), skipped) {
+        (_, 0) => format!("→ {}", written.join(", ")),
+        (true, _) => "skipped, output exists".into(),
+        (false, n) => format!("→ {} ({n} skipped, output exists)", written.join(", ")),
+    })
+}
+
+fn save(img: DynamicImage, dst: &Path, opts: &ExportOptions) -> Result<()> {
+    // HDR zůstane ve float jen v TIFF
+    let img = match opts.format {
+        ExportFormat::Tiff => img,
+        _ => tone_mapped(img),
+    };
+    match opts.format {
+        ExportFormat::Jpeg => {
+            // JPEG: 8 bit bez alfy
+            let img = if img.color().has_color() {
+                DynamicImage::ImageRgb8(img.to_rgb8())
+            } else {
+                DynamicImage::ImageLuma8(img.to_luma8())
+            };
+            let out = BufWriter::new(File::create(dst)?);
+            img.write_with_encoder(JpegEncoder::new_with_quality(out, opts.jpeg_quality))?;
+        }
+        fmt => {
+            let img = match (opts.depth, img) {
+                (BitDepth::Eight, DynamicImage::ImageLuma16(b)) => {
+                    DynamicImage::ImageLuma16(b).to_luma8().into()
+                }
+                (BitDepth::Eight, DynamicImage::ImageRgb16(b)) => {
+                    DynamicImage::ImageRgb16(b).to_rgb8().into()
+                }
+                (_, img) => img,
+            };
+            let format = match fmt {
+                ExportFormat::Tiff => ImageFormat::Tiff,
+                _ => ImageFormat::Png,
+            };
+            img.save_with_format(dst, format)?;
+        }
+    }
+    Ok(())
+}
+
+/// HDR (RGB32F) → RGB8 s expozicí a mapováním tónů z [`hdr::tone_map`], tedy jak ho ukazuje
+/// prohlížeč; ostatní obrázky beze změny.
+pub fn tone_mapped(img: DynamicImage) -> DynamicImage {
+    match img {
+        DynamicImage::ImageRgb32F(b) => {
+            let (w, h) = b.dimensions();
+            let rgb = hdr::to_rgb8(&hdr::to_raw(b.as_raw()), 3);
+            DynamicImage::ImageRgb8(ImageBuffer::from_raw(w, h, rgb).expect("same dimensions"))
+        }
+        img => img,
+    }
+}
+
+/// Uloží výřez `(x, y, w, h)` snímku `frame` do `dst`: podle přípony PNG/TIFF, nebo nové
+/// CTI se stejnými metadaty a parametry dlaždic. Čte jen dlaždice, které výřez protínají
+/// a nejsou už v `tiles`.
+pub fn export_region(
+    src: &Path,
+    frame: u32,
+    region: (u32, u32, u32, u32),
+    dst: &Path,
+    tiles: &TileCache,
+) -> Result<()> {
+    let (hdr, raw) = CTIDecoder::decode_region_cached(src, frame, region, tiles)?;
+    let (w, h) = (region.2, region.3);
+    let ext = dst
+        .extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    if ext == "cti" {
+        let params = EncodeParams {
+            tile_size: hdr.tile_size,
+            compression: CompressionId::from(hdr.compression),
+            rct: hdr.flags & 1 != 0,
+            file_hash: hdr.has_file_hash(),
+            crc32c: hdr.has_crc32c(),
+            ..EncodeParams::default()
+        };
+        let meta = CTIDecoder::metadata(src)?;
+        CTIEncoder::encode_file_with_metadata(dst, w, h, hdr.color_type, &raw, &params, &meta)?;
+        return Ok(());
+    }
+    let format = match ext.as_str() {
+        "png" => ImageFormat::Png,
+        "tif" | "tiff" => ImageFormat::Tiff,
+        _ => bail!("unsupported output format {ext:?} (PNG, TIFF or CTI)"),
+    };
+    let crop = CTIHeader {
+        width: w,
+        height: h,
+        ..hdr
+    };
+    let img = to_dynamic_image(&crop, raw)?;
+    let img = if format == ImageFormat::Tiff {
+        img
+    } else {
+        tone_mapped(img)
+    };
+    img.save_with_format(dst, format)?;
+    Ok(())
+}
+
+/// Dekóduje `src` a uloží ho do `dst` v daném formátu.
+pub fn export_to(src: &Path, dst: &Path, fmt: ImageFormat) -> Result<()> {
+    let (hdr, raw) = CTIDecoder::decode_file(src)?;
+    let img = to_dynamic_image(&hdr, raw)?;
+    let img = if fmt == ImageFormat::Tiff {
+        img
+    } else {
+        tone_mapped(img)
+    };
+    img.save_with_format(dst, fmt)?;
+    Ok(())
+}
+
+fn le_u16(raw: &[u8]) -> Vec<u16> {
+    raw.chunks_exact(2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .collect()
+}
@@ -0,0 +1,219 @@
+use anyhow::{Context, Result, bail, ensure};
+use rayon::prelude::*;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use crate::cti::{CTIDecoder, CTIHeader};
+use crate::diff::{self, Layout};
+use crate::export;
+
+/// Strana buňky, po které se reference průměruje (potlačí šum i prach na předloze).
+const CELL: usize = 16;
+/// Strop zesílení – v téměř černých místech reference by korekce jen zesílila šum.
+const MAX_GAIN: f32 = 4.0;
+
+/// Korekce nerovnoměrného osvětlení podle snímku rovnoměrné bílé/šedé předlohy.
+///
+/// Reference se zprůměruje po buňkách 16×16 a pro každou buňku a kanál se uloží zesílení
+/// `průměr reference / průměr buňky`; mezi středy buněk se interpoluje bilineárně. Celkový
+/// jas i barevné vyvážení snímku tak zůstanou zachované, srovná se jen pokles k okrajům.
+pub struct FlatField {
+    pub path: PathBuf,
+    pub width: u32,
+    pub height: u32,
+    cols: usize,
+    rows: usize,
+    gain: Vec<[f32; 3]>,
+    modified: Option<SystemTime>,
+}
+
+/// Naposledy načtená reference (dávkový export ji použije pro každý soubor).
+static LAST: Mutex<Option<Arc<FlatField>>> = Mutex::new(None);
+
+impl FlatField {
+    /// Načte referenci z CTI, PNG nebo TIFF.
+    pub fn load(path: &Path) -> Result<Self> {
+        let is_cti = path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("cti"));
+        let (w, h, color_type, raw) = if is_cti {
+            let (hdr, raw) = CTIDecoder::decode_file(path)?;
+            (hdr.width, hdr.height, hdr.color_type, raw)
+        } else {
+            let img = image::open(path).with_context(|| format!("load {:?}", path))?;
+            let (w, h) = (img.width(), img.height());
+            let (color_type, raw) = export::from_dynamic_image(img)?;
+            (w, h, color_type, raw)
+        };
+        let mut flat = Self::from_raw(w, h, color_type, &raw)?;
+        flat.path = path.to_path_buf();
+        flat.modified = modified(path);
+        Ok(flat)
+    }
+
+    /// Jako [`load`](Self::load), ale stejnou (nezměněnou) referenci načte jen jednou.
+    pub fn cached(path: &Path) -> Result<Arc<Self>> {
+        let mut last = LAST.lock().unwrap();
+        if let Some(f) = last
+            .as_ref()
+            .filter(|f| f.path == path && f.modified == modified(path))
+        {
+            return Ok(f.clone());
+        }
+        let f = Arc::new(Self::load(path)?);
+        *last = Some(f.clone());
+        Ok(f)
+    }
+
+    fn from_raw(width: u32, height: u32, color_type: u8, raw: &[u8]) -> Result<Self> {
+        let Some(layout) = diff::layout(color_type) else {
+            bail!("unsupported color type {}", color_type);
+        };
+        let (w, h) = (width as usize, height as usize);
+        let ch = layout.channels;
+        let color = ch.min(3);
+        let (cols, rows) = (w.div_ceil(CELL), h.div_ceil(CELL));
+
+        let mut sums = vec![[0f64; 3]; cols * rows];
+        let mut counts = vec![0u32; cols * rows];
+        for y in 0..h {
+            for x in 0..w {
+                let cell = (y / CELL) * cols + x / CELL;
+                for (c, s) in sums[cell][..color].iter_mut().enumerate() {
+                    *s += diff::sample(raw, layout.wide, (y * w + x) * ch + c) as f64;
+                }
+                counts[cell] += 1;
+            }
+        }
+        let mut target = [0f64; 3];
+        for s in &sums {
+            for (t, v) in target.iter_mut().zip(s) {
+                *t += v;
+            }
+        }
+        let total = (w * h) as f64;
+        ensure!(
+            target[..color].iter().all(|&t| t > 0.0),
+            "flat-field reference is black"
+        );
+
+        let gain = sums
+            .iter()
+            .zip(&counts)
+            .map(|(s, &n)| {
+                std::array::from_fn(|c| {
+                    // šedá reference: stejné zesílení pro všechny kanály
+                    let k = c.min(color - 1);
+                    let mean = s[k] / n as f64;
+                    if mean > 0.0 {
+                        ((target[k] / total / mean) as f32).min(MAX_GAIN)
+                    } else {
+                        MAX_GAIN
+                    }
+                })
+            })
+            .collect();
+        Ok(Self {
+            path: PathBuf::new(),
+            width,
+            height,
+            cols,
+            rows,
+            gain,
+            modified: None,
+        })
+    }
+
+    /// Chyba, pokud reference nemá rozměr snímku.
+    pub fn check(&self, hdr: &CTIHeader) -> Result<()> {
+        ensure!(
+            self.width == hdr.width && self.height == hdr.height,
+            "flat-field reference is {}x{}, image is {}x{}",
+            self.width,
+            self.height,
+            hdr.width,
+            hdr.height
+        );
+        Ok(())
+    }
+
+    /// Opravený RAW buffer (stejný rozměr i formát).
+    pub fn apply(&self, hdr: &CTIHeader, raw: &[u8]) -> Result<Vec<u8>> {
+        self.check(hdr)?;
+        let layout = self.layout(hdr.color_type)?;
+        let w = hdr.width as usize;
+        let row_len = w * layout.channels * if layout.wide { 2 } else { 1 };
+        let mut out = raw.to_vec();
+        out.par_chunks_mut(row_len)
+            .enumerate()
+            .for_each(|(y, row)| self.correct_row(&layout, 0, y, w, row));
+        Ok(out)
+    }
+
+    /// Opraví na místě dlaždici širokou `tw` pixelů s levým horním rohem `(x0, y0)`.
+    pub fn apply_tile(
+        &self,
+        color_type: u8,
+        (x0, y0): (u32, u32),
+        tw: u32,
+        tile: &mut [u8],
+    ) -> Result<()> {
+        let layout = self.layout(color_type)?;
+        let tw = tw as usize;
+        let row_len = tw * layout.channels * if layout.wide { 2 } else { 1 };
+        for (dy, row) in tile.chunks_mut(row_len).enumerate() {
+            self.correct_row(&layout, x0 as usize, y0 as usize + dy, tw, row);
+        }
+        Ok(())
+    }
+
+    fn layout(&self, color_type: u8) -> Result<Layout> {
+        diff::layout(color_type)
+            .ok_or_else(|| anyhow::anyhow!("unsupported color type {}", color_type))
+    }
+
+    /// Řádek `len` pixelů od `(x0, y)`; alfa zůstane beze změny.
+    fn correct_row(&self, layout: &Layout, x0: usize, y: usize, len: usize, row: &mut [u8]) {
+        let ch = layout.channels;
+        let peak = if layout.wide { 65535.0 } else { 255.0 };
+        let fy = ((y as f32 + 0.5) / CELL as f32 - 0.5).clamp(0.0, (self.rows - 1) as f32);
+        for i in 0..len {
+            let g = self.gain_at(x0 + i, fy);
+            // šedý snímek: průměr zesílení kanálů
+            let gray = (g[0] + g[1] + g[2]) / 3.0;
+            for (c, &gc) in g.iter().enumerate().take(ch.min(3)) {
+                let k = (i * ch + c) * if layout.wide { 2 } else { 1 };
+                let gain = if ch == 1 { gray } else { gc };
+                let v = (diff::sample(row, layout.wide, i * ch + c) as f32 * gain)
+                    .round()
+                    .min(peak);
+                if layout.wide {
+                    row[k..k + 2].copy_from_slice(&(v as u16).to_le_bytes());
+                } else {
+                    row[k] = v as u8;
+                }
+            }
+        }
+    }
+
+    /// Bilineárně interpolované zesílení mezi středy buněk (`fy` už v souřadnicích buněk).
+    fn gain_at(&self, x: usize, fy: f32) -> [f32; 3] {
+        let fx = ((x as f32 + 0.5) / CELL as f32 - 0.5).clamp(0.0, (self.cols - 1) as f32);
+        let (x0, y0) = (fx as usize, fy as usize);
+        let (x1, y1) = ((x0 + 1).min(self.cols - 1), (y0 + 1).min(self.rows - 1));
+        let (tx, ty) = (fx - x0 as f32, fy - y0 as f32);
+        let g = |cx: usize, cy: usize| self.gain[cy * self.cols + cx];
+        let mut out = [0f32; 3];
+        for (c, o) in out.iter_mut().enumerate() {
+            let top = g(x0, y0)[c] * (1.0 - tx) + g(x1, y0)[c] * tx;
+            let bottom = g(x0, y1)[c] * (1.0 - tx) + g(x1, y1)[c] * tx;
+            *o = top * (1.0 - ty) + bottom * ty;
+        }
+        out
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
@@ -0,0 +1,120 @@
+//! Odkazy na pohled (`cti-view://view?file=…&page=…&region=…&rot=…`) pro konzultace na
+//! dálku: kurátor pošle odkaz a restaurátor ve svém prohlížeči otevře stejný soubor na
+//! stejné stránce a stejném místě. Zoom plyne z výřezu, takže sedí i v jinak velkém okně.
+
+use anyhow::{Context, Result, bail, ensure};
+use eframe::egui;
+use std::path::{Path, PathBuf};
+
+use crate::cti;
+use crate::iiif::{percent_decode, percent_encode};
+
+pub const SCHEME: &str = "cti-view://view";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ViewLink {
+    /// Cesta nebo URL souboru.
+    pub file: PathBuf,
+    /// Stránka (snímek) od 0.
+    pub page: u32,
+    /// Viditelný výřez v pixelech `(x, y, w, h)`; `None` = celý obrázek.
+    pub region: Option<(u32, u32, u32, u32)>,
+    /// Otočení po 90° (0..=3).
+    pub rotation: u8,
+}
+
+impl ViewLink {
+    pub fn to_uri(&self) -> String {
+        let file = self.file.to_string_lossy();
+        let mut uri = format!("{SCHEME}?file={}&page={}", percent_encode(&file), self.page);
+        if let Some((x, y, w, h)) = self.region {
+            uri.push_str(&format!("&region={x},{y},{w},{h}"));
+        }
+        if self.rotation != 0 {
+            uri.push_str(&format!("&rot={}", self.rotation));
+        }
+        uri
+    }
+
+    pub fn parse(uri: &str) -> Result<Self> {
+        let Some(query) = uri.trim().strip_prefix(SCHEME) else {
+            bail!("not a {SCHEME} link");
+        };
+        let query = query.strip_prefix('?').unwrap_or(query);
+        let (mut file, mut page, mut region, mut rotation) = (None, 0, None, 0);
+        for pair in query.split('&').filter(|p| !p.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let value = percent_decode(value).context("bad percent-encoding in link")?;
+            let bad = || format!("bad {key} in link: {value:?}");
+            match key {
+                "file" => file = Some(PathBuf::from(&value)),
+                "page" => page = value.parse().with_context(bad)?,
+                "region" => {
+                    let v: Vec<u32> = value
+                        .split(',')
+                        .map(str::parse)
+                        .collect::<Result<_, _>>()
+                        .with_context(bad)?;
+                    let [x, y, w, h] = v[..] else { bail!(bad()) };
+                    ensure!(w > 0 && h > 0, bad());
+                    region = Some((x, y, w, h));
+                }
+                "rot" => rotation = value.parse::<u8>().with_context(bad)? % 4,
+                // neznámé parametry (novější verze) se přeskočí
+                _ => {}
+            }
+        }
+        Ok(Self {
+            file: file.context("link has no file")?,
+            page,
+            region,
+            rotation,
+        })
+    }
+
+    /// Soubor k otevření: cesta z odkazu, nebo soubor stejného jména ve složce `dir`
+    /// (kolega má kopii jinde).
+    pub fn resolve(&self, dir: Option<&Path>) -> Option<PathBuf> {
+        if cti::is_url(&self.file) || self.file.is_file() {
+            return Some(self.file.clone());
+        }
+        let local = dir?.join(self.file.file_name()?);
+        local.is_file().then_some(local)
+    }
+}
+
+/// Dialog „Open link“; vrací rozpoznaný odkaz.
+pub fn open_link_window(
+    ctx: &egui::Context,
+    open: &mut bool,
+    text: &mut String,
+) -> Option<ViewLink> {
+    let mut chosen = None;
+    egui::Window::new("Open view link")
+        .collapsible(false)
+        .resizable(false)
+        .open(open)
+        .show(ctx, |ui| {
+            ui.label("Link copied from another CTI View (opens the same file, page and area):");
+            let edit = ui.add(
+                egui::TextEdit::singleline(text)
+                    .hint_text(SCHEME)
+                    .desired_width(420.0),
+            );
+            let enter = edit.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            let link = ViewLink::parse(text);
+            if let Err(e) = &link
+                && !text.trim().is_empty()
+            {
+                ui.colored_label(ui.visuals().error_fg_color, e.to_string());
+            }
+            let valid = link.is_ok();
+            if (ui.add_enabled(valid, egui::Button::new("Open")).clicked() || enter) && valid {
+                chosen = link.ok();
+            }
+        });
+    if chosen.is_some() {
+        *open = false;
+    }
+    chosen
+}
@@ -0,0 +1,107 @@
+//! Zobrazení HDR snímků (L32F, RGB32F; typicky vědecká data v lineárních hodnotách):
+//! expozice a mapování tónů do 8bit sRGB. Nastavení platí pro celý proces, dekódovaná data
+//! zůstávají ve float a převádí se až při zobrazení a exportu do 8bit formátů.
+
+use serde::{Deserialize, Serialize};
+use std::sync::RwLock;
+
+/// Mapování tónů po expozici.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Operator {
+    /// Hodnoty nad 1.0 se oříznou.
+    Clip,
+    Reinhard,
+    /// Aproximace filmové křivky ACES (Narkowicz).
+    Aces,
+}
+
+impl Operator {
+    pub const ALL: [Operator; 3] = [Operator::Clip, Operator::Reinhard, Operator::Aces];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Operator::Clip => "Clip",
+            Operator::Reinhard => "Reinhard",
+            Operator::Aces => "ACES",
+        }
+    }
+
+    fn apply(self, x: f32) -> f32 {
+        let x = x.max(0.0);
+        match self {
+            Operator::Clip => x.min(1.0),
+            Operator::Reinhard => x / (1.0 + x),
+            Operator::Aces => ((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)).min(1.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ToneMap {
+    /// Expozice ve stupních EV (násobek 2^EV před mapováním).
+    pub exposure: f32,
+    pub operator: Operator,
+}
+
+impl ToneMap {
+    pub const DEFAULT: ToneMap = ToneMap {
+        exposure: 0.0,
+        operator: Operator::Reinhard,
+    };
+}
+
+impl Default for ToneMap {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+static TONE_MAP: RwLock<ToneMap> = RwLock::new(ToneMap::DEFAULT);
+
+/// Nastaví expozici a mapování tónů pro další převody.
+pub fn set_tone_map(tone: ToneMap) {
+    *TONE_MAP.write().unwrap() = tone;
+}
+
+pub fn tone_map() -> ToneMap {
+    *TONE_MAP.read().unwrap()
+}
+
+/// Vzorky f32 (little-endian) z RAW bufferu.
+pub fn samples(raw: &[u8]) -> Vec<f32> {
+    raw.chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+/// Opak [`samples`].
+pub fn to_raw(samples: &[f32]) -> Vec<u8> {
+    samples.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+/// L32F (`channels` = 1) nebo RGB32F (3) → RGB8 podle nastavené expozice a mapování tónů.
+pub fn to_rgb8(raw: &[u8], channels: usize) -> Vec<u8> {
+    let tone = tone_map();
+    let gain = tone.exposure.exp2();
+    let map = |v: f32| (linear_to_srgb(tone.operator.apply(v * gain)) * 255.0 + 0.5) as u8;
+    let mut rgb = vec![0u8; raw.len() / 4 / channels * 3];
+    for (d, s) in rgb.chunks_exact_mut(3).zip(raw.chunks_exact(4 * channels)) {
+        let v = |c: usize| f32::from_le_bytes(s[c * 4..c * 4 + 4].try_into().unwrap());
+        if channels == 1 {
+            d.fill(map(v(0)));
+        } else {
+            for (c, out) in d.iter_mut().enumerate() {
+                *out = map(v(c));
+            }
+        }
+    }
+    rgb
+}
+
+fn linear_to_srgb(v: f32) -> f32 {
+    if v <= 0.003_130_8 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    }
+}
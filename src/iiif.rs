@@ -0,0 +1,648 @@
+//! Server IIIF Image API 3.0 (`cti-view serve <dir>`): CTI mastery ze složky pro webové
+//! deep-zoom prohlížeče (OpenSeadragon, Mirador, Leaflet-IIIF…).
+//!
+//! - `/{id}/info.json` – popis obrázku (dlaždice = dlaždice CTI),
+//! - `/{id}/{region}/{size}/{rotation}/{quality}.{format}` – výřez,
+//!
+//! kde `id` je cesta k souboru ve složce bez přípony `.cti` (`/` jako `%2F`). Výřez se
+//! dekóduje jen z dlaždic, které protíná; velké výřezy (náhledy celého obrázku) z celého
+//! snímku v cache. Podporuje level 2 (otočení po 90° se zrcadlením, kvality `default`,
+//! `color`, `gray`, `bitonal`, formáty `jpg`, `png`, `tif`).
+
+use anyhow::{Result, anyhow};
+use image::{DynamicImage, ImageFormat};
+use serde_json::json;
+use std::io::Cursor;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::cache::ImageCache;
+use crate::cti::{self, CTIDecoder, CTIHeader, DecodeOptions};
+use crate::export;
+use crate::tilecache::TileCache;
+
+/// Největší vydaný obrázek (pixely); uvádí se v `info.json` jako `maxArea`.
+const MAX_AREA: u64 = 64_000_000;
+
+/// Kvalita JPEG odpovědí.
+const JPEG_QUALITY: u8 = 90;
+
+pub struct ServeOptions {
+    pub root: PathBuf,
+    /// Adresa a port, např. `127.0.0.1:8182`.
+    pub listen: String,
+    /// Veřejná adresa služby v `info.json` (za reverzní proxy); jinak podle hlavičky `Host`.
+    pub base_url: Option<String>,
+    pub threads: usize,
+    /// Cache dekódovaných snímků pro velké výřezy (MiB).
+    pub cache_mb: usize,
+    /// Cache rozbalených dlaždic pro malé výřezy (MiB).
+    pub tile_cache_mb: usize,
+    /// Limity dekódování jednoho souboru; obrázek nad `max_pixels` dostane 403.
+    pub limits: DecodeOptions,
+}
+
+/// Chyba s HTTP stavem pro klienta.
+#[derive(Debug)]
+struct Status(u16, String);
+
+impl Status {
+    fn bad(msg: impl Into<String>) -> Self {
+        Self(400, msg.into())
+    }
+}
+
+enum Reply {
+    Info(String),
+    Image(Vec<u8>, &'static str),
+    Redirect(String),
+}
+
+/// Spustí server a obsluhuje požadavky, dokud proces neukončí uživatel.
+pub fn serve(opts: ServeOptions) -> Result<()> {
+    let server =
+        Server::http(&opts.listen).map_err(|e| anyhow!("listen on {}: {e}", opts.listen))?;
+    let service = Service {
+        root: opts.root,
+        base_url: opts.base_url.map(|b| b.trim_end_matches('/').to_owned()),
+        cache: Mutex::new(ImageCache::new(opts.cache_mb << 20)),
+        tiles: TileCache::new(opts.tile_cache_mb << 20),
+        limits: opts.limits,
+    };
+    tracing::info!(
+        "Serving {} over IIIF Image API 3.0 on http://{}/",
+        service.root.display(),
+        server.server_addr()
+    );
+    let example = find_example(&service.root);
+    if let Some(id) = example {
+        tracing::info!("e.g. http://{}/{id}/info.json", server.server_addr());
+    }
+    std::thread::scope(|s| {
+        for _ in 0..opts.threads.max(1) {
+            s.spawn(|| {
+                for request in server.incoming_requests() {
+                    service.respond(request);
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+/// Identifikátor prvního CTI ve složce (pro ukázkovou adresu).
+fn find_example(root: &Path) -> Option<String> {
+    let mut entries: Vec<PathBuf> = std::fs::read_dir(root)
+        .ok()?
+        .flatten()
+        .map(|e| e.path())
+        .collect();
+    entries.sort();
+    let file = entries
+        .iter()
+        .find(|p| p.extension().is_some_and(|e| e.eq_ignore_ascii_case("cti")))?;
+    Some(percent_encode(&file.file_stem()?.to_string_lossy()))
+}
+
+struct Service {
+    root: PathBuf,
+    base_url: Option<String>,
+    cache: Mutex<ImageCache>,
+    tiles: TileCache,
+    limits: DecodeOptions,
+}
+
+impl Service {
+    fn respond(&self, request: Request) {
+        let started = Instant::now();
+        let url = request.url().to_owned();
+        let reply = match request.method() {
+            Method::Get | Method::Head => self.handle(&request),
+            _ => Err(Status(405, "Only GET and HEAD are supported".into())),
+        };
+        let code = match &reply {
+            Ok(Reply::Redirect(_)) => 303,
+            Ok(_) => 200,
+            Err(Status(code, _)) => *code,
+        };
+        let response = match reply {
+            Ok(Reply::Info(body)) => Response::from_string(body)
+                .with_header(header(
+                    "Content-Type",
+                    "application/ld+json;profile=\"http://iiif.io/api/image/3/context.json\"",
+                ))
+                .with_header(header(
+                    "Link",
+                    "<http://iiif.io/api/image/3/level2.json>;rel=\"profile\"",
+                )),
+            Ok(Reply::Image(data, mime)) => Response::from_data(data)
+                .with_header(header("Content-Type", mime))
+                .with_header(header("Cache-Control", "public, max-age=86400")),
+            Ok(Reply::Redirect(to)) => Response::from_string("")
+                .with_status_code(303)
+                .with_header(header("Location", &to)),
+            Err(Status(code, msg)) => Response::from_string(msg)
+                .with_status_code(code)
+                .with_header(header("Content-Type", "text/plain; charset=utf-8")),
+        };
+        let response = response.with_header(header("Access-Control-Allow-Origin", "*"));
+        if let Err(e) = request.respond(response) {
+            tracing::warn!("{url}: {e}");
+        }
+        tracing::info!("{code} {url} ({} ms)", started.elapsed().as_millis());
+    }
+
+    fn handle(&self, request: &Request) -> Result<Reply, Status> {
+        let path = request.url().split('?').next().unwrap_or_default();
+        let segments: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+        let id = segments.first().copied().unwrap_or_default();
+        if id.is_empty() {
+            return Err(Status(404, "Add an image identifier to the URL".into()));
+        }
+        let file = self.resolve(id)?;
+        let base = match &self.base_url {
+            Some(b) => b.clone(),
+            None => {
+                let host = request
+                    .headers()
+                    .iter()
+                    .find(|h| h.field.equiv("Host"))
+                    .map(|h| h.value.as_str().to_owned())
+                    .unwrap_or_else(|| "localhost".into());
+                format!("http://{host}")
+            }
+        };
+        let id_url = format!("{base}/{id}");
+        match segments[1..] {
+            [] | [""] => Ok(Reply::Redirect(format!("{id_url}/info.json"))),
+            ["info.json"] => self.info(&file, &id_url),
+            [region, size, rotation, last] => {
+                let (quality, format) = last
+                    .rsplit_once('.')
+                    .ok_or_else(|| Status::bad("Missing format extension"))?;
+                let req = ImageRequest {
+                    region: Region::parse(region)?,
+                    size: Size::parse(size)?,
+                    rotation: Rotation::parse(rotation)?,
+                    quality: Quality::parse(quality)?,
+                    format: Format::parse(format)?,
+                };
+                self.image(&file, &req)
+            }
+            _ => Err(Status::bad(
+                "Expected {id}/info.json or {id}/{region}/{size}/{rotation}/{quality}.{format}",
+            )),
+        }
+    }
+
+    /// Identifikátor → soubor ve složce (bez výstupu mimo ni).
+    fn resolve(&self, id: &str) -> Result<PathBuf, Status> {
+        let decoded = percent_decode(id).ok_or_else(|| Status::bad("Bad identifier encoding"))?;
+        let rel = Path::new(&decoded);
+        let safe = rel.components().all(|c| matches!(c, Component::Normal(_)));
+        let mut file = self.root.join(rel).into_os_string();
+        file.push(".cti");
+        let file = PathBuf::from(file);
+        if !safe || decoded.contains('\\') || !file.is_file() {
+            return Err(Status(404, format!("No image {decoded}")));
+        }
+        Ok(file)
+    }
+
+    fn info(&self, file: &Path, id_url: &str) -> Result<Reply, Status> {
+        let hdr = CTIDecoder::info(file).map_err(internal)?;
+        self.limits.check_header(&hdr).map_err(over_limit)?;
+        let scale_factors = scale_factors(hdr.width, hdr.height, hdr.tile_size);
+        let info = json!({
+            "@context": "http://iiif.io/api/image/3/context.json",
+            "id": id_url,
+            "type": "ImageService3",
+            "protocol": "http://iiif.io/api/image",
+            "profile": "level2",
+            "width": hdr.width,
+            "height": hdr.height,
+            "maxArea": MAX_AREA,
+            "tiles": [{ "width": hdr.tile_size, "scaleFactors": scale_factors }],
+            "extraQualities": ["color", "gray", "bitonal"],
+            "extraFormats": ["tif"],
+            "extraFeatures": ["mirroring", "regionSquare", "sizeUpscaling"],
+        });
+        Ok(Reply::Info(
+            serde_json::to_string_pretty(&info).map_err(|e| internal(e.into()))?,
+        ))
+    }
+
+    fn image(&self, file: &Path, req: &ImageRequest) -> Result<Reply, Status> {
+        let hdr = CTIDecoder::info(file).map_err(internal)?;
+        self.limits.check_header(&hdr).map_err(over_limit)?;
+        let (x, y, w, h) = req.region.resolve(hdr.width, hdr.height)?;
+        let (tw, th) = req.size.resolve(w, h)?;
+        let raw = self.decode(file, &hdr, (x, y, w, h)).map_err(internal)?;
+        let region = CTIHeader {
+            width: w,
+            height: h,
+            ..hdr
+        };
+        let mut img = export::to_dynamic_image(&region, raw).map_err(internal)?;
+        img = export::tone_mapped(img);
+        if (tw, th) != (w, h) {
+            img = img.resize_exact(tw, th, image::imageops::FilterType::Triangle);
+        }
+        if req.rotation.mirror {
+            img = img.fliph();
+        }
+        img = match req.rotation.degrees {
+            90 => img.rotate90(),
+            180 => img.rotate180(),
+            270 => img.rotate270(),
+            _ => img,
+        };
+        img = req.quality.apply(img);
+        let data = req.format.encode(img).map_err(internal)?;
+        Ok(Reply::Image(data, req.format.mime()))
+    }
+
+    /// RAW data výřezu. Výřez přes aspoň čtvrtinu obrázku se bere z celého snímku v cache
+    /// (náhledy v malých měřítkách), menší jen z dlaždic, které protíná (přes cache
+    /// dlaždic, protože sousední požadavky prohlížeče sdílejí okrajové dlaždice).
+    fn decode(
+        &self,
+        file: &Path,
+        hdr: &CTIHeader,
+        (x, y, w, h): (u32, u32, u32, u32),
+    ) -> Result<Vec<u8>> {
+        let area = u64::from(w) * u64::from(h);
+        let full = u64::from(hdr.width) * u64::from(hdr.height);
+        if area * 4 < full {
+            let region = (x, y, w, h);
+            let decoded = CTIDecoder::decode_region_cached_with_options(
+                file,
+                0,
+                region,
+                &self.tiles,
+                &self.limits,
+            )?;
+            return Ok(decoded.1);
+        }
+        let cached = self.cache.lock().unwrap().get(file, 0);
+        let raw = match cached {
+            Some((_, raw)) => raw,
+            None => {
+                let (_, raw) = CTIDecoder::decode_frame_with_options(file, 0, &self.limits)?;
+                let raw = Arc::new(raw);
+                self.cache
+                    .lock()
+                    .unwrap()
+                    .insert(file, 0, *hdr, raw.clone());
+                raw
+            }
+        };
+        let bpp = cti::bytes_per_pixel(hdr.color_type)? as usize;
+        let (row, stride) = (w as usize * bpp, hdr.width as usize * bpp);
+        let mut out = Vec::with_capacity(row * h as usize);
+        for py in y as usize..(y + h) as usize {
+            let start = py * stride + x as usize * bpp;
+            out.extend_from_slice(&raw[start..start + row]);
+        }
+        Ok(out)
+    }
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).expect("valid header")
+}
+
+fn internal(e: anyhow::Error) -> Status {
+    Status(500, format!("{e:#}"))
+}
+
+fn over_limit(e: anyhow::Error) -> Status {
+    Status(403, format!("{e:#}"))
+}
+
+struct ImageRequest {
+    region: Region,
+    size: Size,
+    rotation: Rotation,
+    quality: Quality,
+    format: Format,
+}
+
+enum Region {
+    Full,
+    Square,
+    Pixels(u32, u32, u32, u32),
+    Percent(f64, f64, f64, f64),
+}
+
+/// Čtyři čísla oddělená čárkou.
+fn four<T: std::str::FromStr>(s: &str) -> Option<(T, T, T, T)> {
+    let v: Vec<T> = s
+        .split(',')
+        .map(|p| p.parse().ok())
+        .collect::<Option<_>>()?;
+    match <[T; 4]>::try_from(v) {
+        Ok([a, b, c, d]) => Some((a, b, c, d)),
+        Err(_) => None,
+    }
+}
+
+impl Region {
+    fn parse(s: &str) -> Result<Self, Status> {
+        let bad = || Status::bad(format!("Bad region {s}"));
+        Ok(match s {
+            "full" => Region::Full,
+            "square" => Region::Square,
+            _ => match s.strip_prefix("pct:") {
+                Some(p) => {
+                    let (x, y, w, h) = four(p).ok_or_else(bad)?;
+                    Region::Percent(x, y, w, h)
+                }
+                None => {
+                    let (x, y, w, h) = four(s).ok_or_else(bad)?;
+                    Region::Pixels(x, y, w, h)
+                }
+            },
+        })
+    }
+
+    /// Výřez v pixelech oříznutý na obrázek.
+    fn resolve(&self, width: u32, height: u32) -> Result<(u32, u32, u32, u32), Status> {
+        let (x, y, w, h) = match *self {
+            Region::Full => (0, 0, width, height),
+            Region::Square => {
+                let side = width.min(height);
+                ((width - side) / 2, (height - side) / 2, side, side)
+            }
+            Region::Pixels(x, y, w, h) => (x, y, w, h),
+            Region::Percent(x, y, w, h) => {
+                let px = |v: f64, of: u32| (v / 100.0 * f64::from(of)).round().max(0.0) as u32;
+                (px(x, width), px(y, height), px(w, width), px(h, height))
+            }
+        };
+        if x >= width || y >= height || w == 0 || h == 0 {
+            return Err(Status::bad("Region is outside the image"));
+        }
+        Ok((x, y, w.min(width - x), h.min(height - y)))
+    }
+}
+
+struct Size {
+    /// `^`: smí se zvětšit nad rozměr výřezu.
+    upscale: bool,
+    kind: SizeKind,
+}
+
+enum SizeKind {
+    Max,
+    Width(u32),
+    Height(u32),
+    Percent(f64),
+    Exact(u32, u32),
+    /// `!w,h`: největší rozměr, který se vejde do `w × h`.
+    Confined(u32, u32),
+}
+
+impl Size {
+    fn parse(s: &str) -> Result<Self, Status> {
+        let bad = || Status::bad(format!("Bad size {s}"));
+        let (upscale, rest) = match s.strip_prefix('^') {
+            Some(r) => (true, r),
+            None => (false, s),
+        };
+        let kind = if rest == "max" {
+            SizeKind::Max
+        } else if let Some(p) = rest.strip_prefix("pct:") {
+            SizeKind::Percent(p.parse().map_err(|_| bad())?)
+        } else {
+            let (confined, dims) = match rest.strip_prefix('!') {
+                Some(d) => (true, d),
+                None => (false, rest),
+            };
+            let (w, h) = dims.split_once(',').ok_or_else(bad)?;
+            let num = |v: &str| v.parse::<u32>().map_err(|_| bad());
+            match (w.is_empty(), h.is_empty(), confined) {
+                (false, true, false) => SizeKind::Width(num(w)?),
+                (true, false, false) => SizeKind::Height(num(h)?),
+                (false, false, false) => SizeKind::Exact(num(w)?, num(h)?),
+                (false, false, true) => SizeKind::Confined(num(w)?, num(h)?),
+                _ => return Err(bad()),
+            }
+        };
+        Ok(Self { upscale, kind })
+    }
+
+    /// Výsledný rozměr pro výřez `w × h`. `^max` vrací výřez v plné velikosti (bez
+    /// zvětšování až do `maxArea`), obojí případně zmenšené do `maxArea`.
+    fn resolve(&self, w: u32, h: u32) -> Result<(u32, u32), Status> {
+        let (fw, fh) = (f64::from(w), f64::from(h));
+        let scaled = |k: f64| ((fw * k).round() as u32, (fh * k).round() as u32);
+        let (tw, th) = match self.kind {
+            SizeKind::Max => {
+                let area = fw * fh;
+                let k = (MAX_AREA as f64 / area).sqrt().min(1.0);
+                scaled(k)
+            }
+            SizeKind::Width(tw) => (tw, (fh * f64::from(tw) / fw).round() as u32),
+            SizeKind::Height(th) => ((fw * f64::from(th) / fh).round() as u32, th),
+            SizeKind::Percent(p) => scaled(p / 100.0),
+            SizeKind::Exact(tw, th) => (tw, th),
+            SizeKind::Confined(bw, bh) => {
+                let mut k = (f64::from(bw) / fw).min(f64::from(bh) / fh);
+                if !self.upscale {
+                    k = k.min(1.0);
+                }
+                scaled(k)
+            }
+        };
+        if tw == 0 || th == 0 {
+            return Err(Status::bad("Requested size is zero"));
+        }
+        if !self.upscale && (tw > w || th > h) {
+            return Err(Status::bad(
+                "Size is larger than the region; use ^ to upscale",
+            ));
+        }
+        if u64::from(tw) * u64::from(th) > MAX_AREA {
+            return Err(Status::bad(format!("Size exceeds maxArea ({MAX_AREA})")));
+        }
+        Ok((tw, th))
+    }
+}
+
+struct Rotation {
+    mirror: bool,
+    degrees: u32,
+}
+
+impl Rotation {
+    fn parse(s: &str) -> Result<Self, Status> {
+        let (mirror, rest) = match s.strip_prefix('!') {
+            Some(r) => (true, r),
+            None => (false, s),
+        };
+        let deg: f64 = rest
+            .parse()
+            .map_err(|_| Status::bad(format!("Bad rotation {s}")))?;
+        if !(0.0..=360.0).contains(&deg) {
+            return Err(Status::bad(format!("Bad rotation {s}")));
+        }
+        if deg % 90.0 != 0.0 {
+            return Err(Status(
+                501,
+                "Only rotation by multiples of 90° is supported".into(),
+            ));
+        }
+        Ok(Self {
+            mirror,
+            degrees: deg as u32 % 360,
+        })
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Quality {
+    Default,
+    Color,
+    Gray,
+    Bitonal,
+}
+
+impl Quality {
+    fn parse(s: &str) -> Result<Self, Status> {
+        Ok(match s {
+            "default" => Quality::Default,
+            "color" => Quality::Color,
+            "gray" => Quality::Gray,
+            "bitonal" => Quality::Bitonal,
+            _ => return Err(Status::bad(format!("Bad quality {s}"))),
+        })
+    }
+
+    fn apply(self, img: DynamicImage) -> DynamicImage {
+        let deep = matches!(
+            img,
+            DynamicImage::ImageLuma16(_) | DynamicImage::ImageRgb16(_)
+        );
+        match self {
+            Quality::Default | Quality::Color => img,
+            Quality::Gray if deep => DynamicImage::ImageLuma16(img.to_luma16()),
+            Quality::Gray => DynamicImage::ImageLuma8(img.to_luma8()),
+            Quality::Bitonal => {
+                let mut luma = img.to_luma8();
+                for p in luma.pixels_mut() {
+                    p.0[0] = if p.0[0] >= 128 { 255 } else { 0 };
+                }
+                DynamicImage::ImageLuma8(luma)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Format {
+    Jpg,
+    Png,
+    Tif,
+}
+
+impl Format {
+    fn parse(s: &str) -> Result<Self, Status> {
+        Ok(match s {
+            "jpg" => Format::Jpg,
+            "png" => Format::Png,
+            "tif" => Format::Tif,
+            "gif" | "jp2" | "pdf" | "webp" => {
+                return Err(Status(501, format!("Format {s} is not supported")));
+            }
+            _ => return Err(Status::bad(format!("Bad format {s}"))),
+        })
+    }
+
+    fn mime(self) -> &'static str {
+        match self {
+            Format::Jpg => "image/jpeg",
+            Format::Png => "image/png",
+            Format::Tif => "image/tiff",
+        }
+    }
+
+    fn encode(self, img: DynamicImage) -> Result<Vec<u8>> {
+        let mut out = Cursor::new(Vec::new());
+        match self {
+            Format::Jpg => {
+                // JPEG jen 8 bitů a bez alfy
+                let img = match img {
+                    DynamicImage::ImageLuma8(_) | DynamicImage::ImageLuma16(_) => {
+                        DynamicImage::ImageLuma8(img.to_luma8())
+                    }
+                    _ => DynamicImage::ImageRgb8(img.to_rgb8()),
+                };
+                let encoder =
+                    image::codecs::jpeg::JpegEncoder::new_with_quality(&mut out, JPEG_QUALITY);
+                img.write_with_encoder(encoder)?;
+            }
+            Format::Png => img.write_to(&mut out, ImageFormat::Png)?,
+            Format::Tif => img.write_to(&mut out, ImageFormat::Tiff)?,
+        }
+        Ok(out.into_inner())
+    }
+}
+
+/// Měřítka (mocniny dvou), dokud se obrázek nevejde do jedné dlaždice: `ceil(log2(dlaždic
+/// na delší straně))` úrovní, nejvýš do 2^31 (víc `u32` neunese; 2^31+1 px v dlaždicích
+/// po 1 px hlavička dovolí).
+fn scale_factors(width: u32, height: u32, tile_size: u32) -> Vec<u32> {
+    let tiles = width.max(height).div_ceil(tile_size).max(1);
+    let levels = (u32::BITS - (tiles - 1).leading_zeros()).min(31);
+    (0..=levels).map(|i| 1 << i).collect()
+}
+
+/// Znaky mimo `A-Z a-z 0-9 - . _ ~` jako `%XX` (UTF-8).
+pub fn percent_encode(s: &str) -> String {
+    let mut out = String::new();
+    for b in s.bytes() {
+        if b.is_ascii_alphanumeric() || b"-._~".contains(&b) {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{b:02X}"));
+        }
+    }
+    out
+}
+
+pub fn percent_decode(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            out.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scale_factors_end_at_one_tile() {
+        assert_eq!(scale_factors(100, 50, 256), [1]);
+        assert_eq!(scale_factors(256, 256, 256), [1]);
+        assert_eq!(scale_factors(257, 10, 256), [1, 2]);
+        assert_eq!(scale_factors(1000, 3000, 256), [1, 2, 4, 8, 16]);
+        // 2^31+1 px v dlaždicích 1 px: zdvojení po 2^31 by přeteklo
+        let huge = scale_factors((1 << 31) + 1, 1, 1);
+        assert_eq!(huge.len(), 32);
+        assert_eq!(huge.last(), Some(&(1 << 31)));
+    }
+}
@@ -0,0 +1,179 @@
+//! CTI pro ekosystém crate `image`: [`CtiImageDecoder`] implementuje `ImageDecoder`
+//! (a `ImageDecoderRect` pro výřezy, které čtou jen potřebné dlaždice). Po [`register`]
+//! otevře soubory `.cti` i `image::open` / `ImageReader`.
+
+use anyhow::{Result, ensure};
+use image::error::{DecodingError, ImageFormatHint, LimitError, LimitErrorKind};
+use image::{ColorType, ImageDecoder, ImageDecoderRect, ImageError, ImageResult, Limits};
+use std::io::{Read, Seek};
+
+use crate::cti::{self, CTIDecoder, CTIHeader, DecodeOptions};
+
+/// Dekodér snímku CTI z `reader` (čte se od začátku, podle potřeby opakovaně).
+pub struct CtiImageDecoder<R: Read + Seek> {
+    reader: R,
+    hdr: CTIHeader,
+    frame: u32,
+    /// Limity z [`ImageDecoder::set_limits`].
+    limits: DecodeOptions,
+}
+
+impl<R: Read + Seek> CtiImageDecoder<R> {
+    pub fn new(mut reader: R) -> Result<Self> {
+        reader.rewind()?;
+        let hdr = CTIDecoder::info_from(&mut reader)?;
+        ensure!(&hdr.magic == b"CTI1", "Bad magic");
+        cti::bytes_per_pixel(hdr.color_type)?;
+        // `image` nemá typ barev pro CMYK ani šedou ve float
+        ensure!(
+            hdr.color_type != 8,
+            "CMYK8 has no equivalent in the image crate"
+        );
+        ensure!(
+            hdr.color_type != 9,
+            "L32F has no equivalent in the image crate"
+        );
+        Ok(Self {
+            reader,
+            hdr,
+            frame: 0,
+            limits: DecodeOptions::default(),
+        })
+    }
+
+    /// Snímek vícesnímkového souboru (výchozí první).
+    pub fn with_frame(mut self, frame: u32) -> Result<Self> {
+        ensure!(
+            frame < self.hdr.frames,
+            "Frame {} out of range ({} frames)",
+            frame,
+            self.hdr.frames
+        );
+        self.frame = frame;
+        Ok(self)
+    }
+
+    pub fn header(&self) -> &CTIHeader {
+        &self.hdr
+    }
+
+    fn bytes_per_pixel(&self) -> usize {
+        self.color_type().bytes_per_pixel() as usize
+    }
+}
+
+/// Zaregistruje příponu `.cti` a signaturu `CTI1` v crate `image`; `false`, pokud už
+/// dekodér pro `.cti` registrovaný je.
+pub fn register() -> bool {
+    image::hooks::register_format_detection_hook("cti".into(), b"CTI1", None);
+    image::hooks::register_decoding_hook(
+        "cti".into(),
+        Box::new(|reader| Ok(Box::new(CtiImageDecoder::new(reader).map_err(decoding)?))),
+    )
+}
+
+fn decoding(e: anyhow::Error) -> ImageError {
+    ImageError::Decoding(DecodingError::new(ImageFormatHint::Name("CTI".into()), e))
+}
+
+/// CTI ukládá 16bitové a float vzorky little-endian, `image` je chce v pořadí platformy.
+fn to_native_endian(color: ColorType, buf: &mut [u8]) {
+    let size = (color.bytes_per_pixel() / color.channel_count()) as usize;
+    if cfg!(target_endian = "big") && size > 1 {
+        for s in buf.chunks_exact_mut(size) {
+            s.reverse();
+        }
+    }
+}
+
+impl<R: Read + Seek> ImageDecoder for CtiImageDecoder<R> {
+    fn dimensions(&self) -> (u32, u32) {
+        (self.hdr.width, self.hdr.height)
+    }
+
+    fn color_type(&self) -> ColorType {
+        match self.hdr.color_type {
+            1 | 6 | 7 => ColorType::L8,
+            2 => ColorType::L16,
+            3 => ColorType::Rgb8,
+            4 => ColorType::Rgba8,
+            10 => ColorType::Rgb32F,
+            // ostatní odmítne `new` (bytes_per_pixel)
+            _ => ColorType::Rgb16,
+        }
+    }
+
+    fn read_image(mut self, buf: &mut [u8]) -> ImageResult<()> {
+        assert_eq!(u64::try_from(buf.len()), Ok(self.total_bytes()));
+        self.reader.rewind()?;
+        let stride = self.hdr.width as usize * self.bytes_per_pixel();
+        CTIDecoder::decode_into_from_with_options(
+            &mut self.reader,
+            self.frame,
+            buf,
+            stride,
+            &self.limits,
+        )
+        .map_err(decoding)?;
+        to_native_endian(self.color_type(), buf);
+        Ok(())
+    }
+
+    fn read_image_boxed(self: Box<Self>, buf: &mut [u8]) -> ImageResult<()> {
+        (*self).read_image(buf)
+    }
+
+    fn set_limits(&mut self, limits: Limits) -> ImageResult<()> {
+        limits.check_support(&image::LimitSupport::default())?;
+        let (width, height) = self.dimensions();
+        limits.check_dimensions(width, height)?;
+        // snímek se dekóduje rovnou do výsledného bufferu
+        if limits.max_alloc.is_some_and(|max| self.total_bytes() > max) {
+            return Err(ImageError::Limits(LimitError::from_kind(
+                LimitErrorKind::InsufficientMemory,
+            )));
+        }
+        // index a dlaždice alokuje dekodér sám, hlídá je až on
+        self.limits = DecodeOptions {
+            max_pixels: limits
+                .max_image_width
+                .zip(limits.max_image_height)
+                .map(|(w, h)| u64::from(w) * u64::from(h)),
+            max_tile_bytes: limits.max_alloc,
+            max_total_bytes: limits.max_alloc,
+        };
+        Ok(())
+    }
+}
+
+impl<R: Read + Seek> ImageDecoderRect for CtiImageDecoder<R> {
+    fn read_rect(
+        &mut self,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        buf: &mut [u8],
+        row_pitch: usize,
+    ) -> ImageResult<()> {
+        let row = width as usize * self.bytes_per_pixel();
+        assert!(
+            row_pitch >= row,
+            "row pitch smaller than the rectangle width"
+        );
+        self.reader.rewind()?;
+        CTIDecoder::decode_region_into_from_with_options(
+            &mut self.reader,
+            self.frame,
+            (x, y, width, height),
+            buf,
+            row_pitch,
+            &self.limits,
+        )
+        .map_err(decoding)?;
+        for dst in buf.chunks_mut(row_pitch).take(height as usize) {
+            to_native_endian(self.color_type(), &mut dst[..row]);
+        }
+        Ok(())
+    }
+}
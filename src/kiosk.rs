@@ -0,0 +1,105 @@
+//! Režim kiosku pro veřejné terminály ve studovně (`--kiosk [CONFIG]`): jen prohlížení
+//! souborů z povolených složek – bez exportu, převodů, tisku (není-li povolen),
+//! schránky, úprav metadat a anotací i bez změn nastavení.
+//!
+//! Konfigurace je TOML; `cti-view-kiosk.toml` vedle programu zapne kiosek i bez
+//! přepínače, takže ho uživatel terminálu nevypne jiným spuštěním.
+
+use anyhow::{Context, Result, ensure};
+use rfd::FileDialog;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// Konfigurace vedle programu (a výchozí pro `--kiosk` bez cesty).
+pub const CONFIG_FILE: &str = "cti-view-kiosk.toml";
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KioskConfig {
+    /// Složky, ze kterých se smí otevírat (včetně podsložek).
+    pub allowed_dirs: Vec<PathBuf>,
+    /// Soubor nebo složka otevřená po spuštění (jinak první povolená složka).
+    pub start: Option<PathBuf>,
+    /// Povolit tisk zobrazeného snímku.
+    pub allow_print: bool,
+}
+
+pub struct Kiosk {
+    pub config: KioskConfig,
+    /// Povolené složky jako kanonické cesty.
+    allowed: Vec<PathBuf>,
+}
+
+impl Kiosk {
+    /// Kiosek podle přepínače `--kiosk` (`Some(None)` = bez cesty ke konfiguraci)
+    /// nebo konfigurace vedle programu; `None` = běžný režim.
+    pub fn from_args(flag: Option<Option<PathBuf>>) -> Result<Option<Self>> {
+        let beside = std::env::current_exe()
+            .ok()
+            .and_then(|exe| Some(exe.parent()?.join(CONFIG_FILE)))
+            .filter(|p| p.is_file());
+        let path = match (flag, beside) {
+            (Some(Some(path)), _) => Some(path),
+            (Some(None), beside) | (None, beside @ Some(_)) => beside,
+            (None, None) => return Ok(None),
+        };
+        let config = match path {
+            Some(path) => {
+                let text = std::fs::read_to_string(&path)
+                    .with_context(|| format!("read {}", path.display()))?;
+                toml::from_str(&text).with_context(|| format!("parse {}", path.display()))?
+            }
+            None => KioskConfig::default(),
+        };
+        Self::new(config).map(Some)
+    }
+
+    fn new(config: KioskConfig) -> Result<Self> {
+        let allowed = config
+            .allowed_dirs
+            .iter()
+            .map(|d| {
+                d.canonicalize()
+                    .with_context(|| format!("kiosk folder {}", d.display()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        ensure!(
+            !allowed.is_empty(),
+            "kiosk mode needs at least one folder in allowed_dirs"
+        );
+        Ok(Self { config, allowed })
+    }
+
+    /// Smí se soubor otevřít (leží v některé povolené složce)?
+    pub fn allows(&self, path: &Path) -> bool {
+        path.canonicalize()
+            .is_ok_and(|p| self.allowed.iter().any(|d| p.starts_with(d)))
+    }
+
+    /// Co otevřít po spuštění: soubor, nebo složka pro seznam souborů.
+    pub fn start(&self) -> PathBuf {
+        self.config
+            .start
+            .clone()
+            .filter(|p| self.allows(p))
+            .unwrap_or_else(|| self.allowed[0].clone())
+    }
+
+    /// Dialog pro výběr CTI začínající ve složce `near` (je-li povolená); soubor mimo
+    /// povolené složky se odmítne.
+    pub fn pick_file(&self, near: Option<&Path>) -> Option<PathBuf> {
+        let dir = near
+            .filter(|d| self.allows(d))
+            .map_or_else(|| self.allowed[0].clone(), Path::to_path_buf);
+        let path = FileDialog::new()
+            .add_filter("CTI images", &["cti"])
+            .set_directory(dir)
+            .pick_file()?;
+        if self.allows(&path) {
+            Some(path)
+        } else {
+            tracing::warn!("kiosk: {} is outside the allowed folders", path.display());
+            None
+        }
+    }
+}
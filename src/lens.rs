@@ -0,0 +1,295 @@
+use anyhow::{Result, bail, ensure};
+use eframe::egui;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::align::Luma;
+use crate::cti::{CTIHeader, CTIMetadata};
+use crate::diff;
+
+/// Korekční profil snímacího zařízení: radiální zkreslení a pokles osvětlení k okrajům.
+///
+/// Poloměr `r` je normalizovaný na polovinu úhlopříčky (roh = 1). Zkreslení je Brownův
+/// model: výstupní pixel ve vzdálenosti `r` se bere ze zdroje ve vzdálenosti
+/// `r · (1 + k1·r² + k2·r⁴)`. Vinětace je poměr jasu ke středu
+/// `1 + v2·r² + v4·r⁴ + v6·r⁶` (typicky záporné koeficienty), korekce ho vydělí.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LensProfile {
+    pub name: String,
+    /// Hodnota pole Device v metadatech, pro kterou se profil vybere automaticky.
+    pub device: String,
+    pub k1: f32,
+    pub k2: f32,
+    pub vignetting: [f32; 3],
+}
+
+impl Default for LensProfile {
+    fn default() -> Self {
+        Self {
+            name: "New profile".into(),
+            device: String::new(),
+            k1: 0.0,
+            k2: 0.0,
+            vignetting: [0.0; 3],
+        }
+    }
+}
+
+/// Korekce objektivu při exportu derivátů.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub enum LensCorrection {
+    #[default]
+    Off,
+    /// Profil podle pole Device v metadatech; soubory bez shody se exportují beze změny.
+    ByDevice(Vec<LensProfile>),
+    Profile(LensProfile),
+}
+
+impl LensCorrection {
+    pub fn profile_for(&self, meta: &CTIMetadata) -> Option<&LensProfile> {
+        match self {
+            LensCorrection::Off => None,
+            LensCorrection::Profile(p) => Some(p),
+            LensCorrection::ByDevice(list) => {
+                let device = meta.get(CTIMetadata::DEVICE)?.trim();
+                list.iter().find(|p| {
+                    !p.device.trim().is_empty() && p.device.trim().eq_ignore_ascii_case(device)
+                })
+            }
+        }
+    }
+
+    /// Načte profil nebo seznam profilů (pak se vybírá podle zařízení) z JSON souboru.
+    pub fn load(path: &std::path::Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        let value: serde_json::Value = serde_json::from_str(&text)?;
+        Ok(if value.is_array() {
+            LensCorrection::ByDevice(serde_json::from_value(value)?)
+        } else {
+            LensCorrection::Profile(serde_json::from_value(value)?)
+        })
+    }
+}
+
+impl LensProfile {
+    fn has_distortion(&self) -> bool {
+        self.k1 != 0.0 || self.k2 != 0.0
+    }
+
+    /// Zesílení vyrovnávající vinětaci ve vzdálenosti `r²` od středu.
+    fn gain(&self, r2: f32) -> f32 {
+        let [v2, v4, v6] = self.vignetting;
+        let falloff = 1.0 + r2 * (v2 + r2 * (v4 + r2 * v6));
+        1.0 / falloff.max(0.05)
+    }
+
+    /// Opraví RAW buffer; výsledek má stejný rozměr i formát. Místa, kam po opravě
+    /// zkreslení nic nepadne, zůstanou černá (u RGBA průhledná).
+    pub fn apply(&self, hdr: &CTIHeader, raw: &[u8]) -> Result<Vec<u8>> {
+        let Some(layout) = diff::layout(hdr.color_type) else {
+            bail!("unsupported color type {}", hdr.color_type);
+        };
+        let (w, h) = (hdr.width as usize, hdr.height as usize);
+        let ch = layout.channels;
+        let bps = if layout.wide { 2 } else { 1 };
+        let peak = if layout.wide { 65535.0 } else { 255.0 };
+        let color = ch.min(3);
+        let (cx, cy) = (w as f32 / 2.0, h as f32 / 2.0);
+        let norm = 1.0 / (cx * cx + cy * cy).max(1.0);
+
+        let mut out = vec![0u8; raw.len()];
+        out.par_chunks_mut(w * ch * bps)
+            .enumerate()
+            .for_each(|(y, row)| {
+                let mut px = [0f32; 4];
+                for x in 0..w {
+                    let (dx, dy) = (x as f32 + 0.5 - cx, y as f32 + 0.5 - cy);
+                    let r2 = (dx * dx + dy * dy) * norm;
+                    let (sx, sy, src_r2) = if self.has_distortion() {
+                        let k = 1.0 + r2 * (self.k1 + r2 * self.k2);
+                        (cx + dx * k - 0.5, cy + dy * k - 0.5, r2 * k * k)
+                    } else {
+                        (x as f32, y as f32, r2)
+                    };
+                    if !bilinear(raw, w, h, ch, layout.wide, sx, sy, &mut px[..ch]) {
+                        continue;
+                    }
+                    let g = self.gain(src_r2);
+                    for (c, v) in px[..ch].iter().enumerate() {
+                        let v = if c < color { (v * g).min(peak) } else { *v };
+                        let v = v.round() as u32;
+                        let i = (x * ch + c) * bps;
+                        if layout.wide {
+                            row[i..i + 2].copy_from_slice(&(v as u16).to_le_bytes());
+                        } else {
+                            row[i] = v as u8;
+                        }
+                    }
+                }
+            });
+        Ok(out)
+    }
+
+    /// Odhadne vinětaci z rovnoměrně osvětlené bílé/šedé předlohy: proloží jas
+    /// `c0 + c1·r² + c2·r⁴ + c3·r⁶` metodou nejmenších čtverců a normalizuje na střed.
+    pub fn fit_vignetting(&mut self, hdr: &CTIHeader, raw: &[u8]) -> Result<()> {
+        const GRID: usize = 64;
+        let luma = Luma::new(hdr, raw)?;
+        let step = luma.w.max(luma.h).div_ceil(GRID).max(1);
+        let g = luma.downsample(step);
+        let (cx, cy) = (luma.w as f64 / 2.0, luma.h as f64 / 2.0);
+        let norm = 1.0 / (cx * cx + cy * cy);
+
+        let mut ata = [[0f64; 4]; 4];
+        let mut atb = [0f64; 4];
+        for gy in 0..g.h {
+            for gx in 0..g.w {
+                let v = g.at(gx, gy) as f64;
+                if !(0.02..0.98).contains(&v) {
+                    continue;
+                }
+                let dx = ((gx as f64 + 0.5) * step as f64).min(luma.w as f64) - cx;
+                let dy = ((gy as f64 + 0.5) * step as f64).min(luma.h as f64) - cy;
+                let r2 = (dx * dx + dy * dy) * norm;
+                let basis = [1.0, r2, r2 * r2, r2 * r2 * r2];
+                for i in 0..4 {
+                    for j in 0..4 {
+                        ata[i][j] += basis[i] * basis[j];
+                    }
+                    atb[i] += basis[i] * v;
+                }
+            }
+        }
+        let c = solve4(ata, atb)
+            .ok_or_else(|| anyhow::anyhow!("image is not a usable flat capture"))?;
+        ensure!(c[0] > 0.0, "image is not a usable flat capture");
+        self.vignetting = [
+            (c[1] / c[0]) as f32,
+            (c[2] / c[0]) as f32,
+            (c[3] / c[0]) as f32,
+        ];
+        Ok(())
+    }
+}
+
+/// Bilineární vzorek všech kanálů; `false` mimo obraz.
+#[allow(clippy::too_many_arguments)]
+fn bilinear(
+    raw: &[u8],
+    w: usize,
+    h: usize,
+    ch: usize,
+    wide: bool,
+    x: f32,
+    y: f32,
+    out: &mut [f32],
+) -> bool {
+    if x < 0.0 || y < 0.0 || x > (w - 1) as f32 || y > (h - 1) as f32 {
+        return false;
+    }
+    let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+    let (x1, y1) = ((x0 + 1).min(w - 1), (y0 + 1).min(h - 1));
+    let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+    let s = |xx: usize, yy: usize, c: usize| diff::sample(raw, wide, (yy * w + xx) * ch + c) as f32;
+    for (c, o) in out.iter_mut().enumerate() {
+        let top = s(x0, y0, c) * (1.0 - fx) + s(x1, y0, c) * fx;
+        let bottom = s(x0, y1, c) * (1.0 - fx) + s(x1, y1, c) * fx;
+        *o = top * (1.0 - fy) + bottom * fy;
+    }
+    true
+}
+
+/// Gaussova eliminace 4×4 s částečnou pivotací.
+fn solve4(mut a: [[f64; 4]; 4], mut b: [f64; 4]) -> Option<[f64; 4]> {
+    for col in 0..4 {
+        let pivot = (col..4).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+        if a[pivot][col].abs() < 1e-12 {
+            return None;
+        }
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+        let top = a[col];
+        for row in col + 1..4 {
+            let f = a[row][col] / top[col];
+            for (v, t) in a[row][col..].iter_mut().zip(&top[col..]) {
+                *v -= f * t;
+            }
+            b[row] -= f * b[col];
+        }
+    }
+    let mut x = [0f64; 4];
+    for row in (0..4).rev() {
+        let s: f64 = (row + 1..4).map(|k| a[row][k] * x[k]).sum();
+        x[row] = (b[row] - s) / a[row][row];
+    }
+    Some(x)
+}
+
+/// Seznam profilů v Preferences; odhad vinětace z otevřeného snímku (bílá předloha).
+pub fn profiles_ui(
+    ui: &mut egui::Ui,
+    profiles: &mut Vec<LensProfile>,
+    image: Option<(&CTIHeader, &[u8])>,
+) {
+    let error_id = egui::Id::new("lens-fit-error");
+    let mut error: Option<String> = ui.data(|d| d.get_temp(error_id)).flatten();
+    let mut remove = None;
+    for (i, p) in profiles.iter_mut().enumerate() {
+        egui::CollapsingHeader::new(p.name.clone())
+            .id_salt(("lens", i))
+            .show(ui, |ui| {
+                egui::Grid::new(("lens-grid", i))
+                    .num_columns(2)
+                    .spacing([12.0, 4.0])
+                    .show(ui, |ui| {
+                        ui.label("Name");
+                        ui.text_edit_singleline(&mut p.name);
+                        ui.end_row();
+                        ui.label("Device");
+                        ui.text_edit_singleline(&mut p.device)
+                            .on_hover_text("Matched against the Device metadata field");
+                        ui.end_row();
+                        ui.label("Distortion k1, k2");
+                        ui.horizontal(|ui| {
+                            for k in [&mut p.k1, &mut p.k2] {
+                                ui.add(egui::DragValue::new(k).speed(0.001).range(-1.0..=1.0));
+                            }
+                        });
+                        ui.end_row();
+                        ui.label("Vignetting v2, v4, v6");
+                        ui.horizontal(|ui| {
+                            for v in &mut p.vignetting {
+                                ui.add(egui::DragValue::new(v).speed(0.001).range(-1.0..=1.0));
+                            }
+                        });
+                        ui.end_row();
+                    });
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(image.is_some(), egui::Button::new("Fit vignetting"))
+                        .on_hover_text(
+                            "Estimate from the open image (an evenly lit white or gray target)",
+                        )
+                        .clicked()
+                        && let Some((hdr, raw)) = image
+                    {
+                        error = p.fit_vignetting(hdr, raw).err().map(|e| format!("{e:#}"));
+                    }
+                    if ui.button("Remove").clicked() {
+                        remove = Some(i);
+                    }
+                });
+            });
+    }
+    if let Some(i) = remove {
+        profiles.remove(i);
+    }
+    if ui.button("Add profile").clicked() {
+        profiles.push(LensProfile::default());
+    }
+    if let Some(e) = &error {
+        ui.colored_label(ui.visuals().error_fg_color, e);
+    }
+    ui.data_mut(|d| d.insert_temp(error_id, error));
+}
@@ -0,0 +1,19 @@
+//! Jádro CTI View použitelné i mimo desktopovou aplikaci: čtení a zápis formátu CTI,
+//! dekodér pro crate `image`, sdílená cache dlaždic, zobrazení obrázku v egui a (na wasm32)
+//! prohlížeč běžící v prohlížeči.
+
+pub mod cmyk;
+pub mod crc;
+pub mod cti;
+pub mod dct;
+pub mod hdr;
+pub mod imagecodec;
+pub mod lint;
+#[cfg(feature = "remote")]
+pub mod remote;
+pub mod simd;
+pub mod testdata;
+pub mod tilecache;
+pub mod view;
+#[cfg(target_arch = "wasm32")]
+pub mod web;
@@ -0,0 +1,541 @@
+//! Kontrola souladu souboru CTI se specifikací – pro autory jiných enkodérů (`cti-view lint`).
+//! Na rozdíl od ověření CRC kontroluje strukturu souboru: hlavičku, rozsah indexu a umístění
+//! dlaždic. Každé zjištění má stálý kód, chyby (`E…`) dekodér odmítne nebo přečte špatně,
+//! varování (`W…`) snese, ale jiný čtenář nemusí.
+//!
+//! | kód  | pravidlo |
+//! |------|----------|
+//! | E001 | magic `CTI1` a celá 64B hlavička |
+//! | E002 | hlavní verze 1..=[`VERSION_MAJOR_MAX`] |
+//! | E003 | jen známé příznaky (u novější vedlejší verze jen varování W003) |
+//! | E004 | [`FLAG_INDEX_AT_END`] až od verze [`VERSION_APPEND`] |
+//! | E005 | nenulová šířka, výška a velikost dlaždice |
+//! | E006 | `tiles_x`/`tiles_y` odpovídají rozměru a velikosti dlaždice |
+//! | E007 | podporovaný typ barev |
+//! | E008 | podporovaný kodek |
+//! | E009 | ztrátový kodek jen s 8bit typem a kvalitou 1..=100 |
+//! | W010 | bezeztrátový kodek zapisuje kvalitu 100 |
+//! | E011 | s [`FLAG_FRAMES`] aspoň jeden snímek |
+//! | W012 | nevyužité bajty rezervy hlavičky jsou nulové |
+//! | W013 | [`FLAG_RCT`] jen u bezeztrátového RGB8/RGB16 |
+//! | E014 | snímek se vejde do paměti a rozbalená dlaždice do `original_size` (u32) |
+//! | E020 | lokátor indexu na konci souboru ukazuje na celý index |
+//! | E021 | index se všemi dlaždicemi všech snímků se vejde do souboru |
+//! | E030 | dlaždice leží celá v souboru (před indexem na konci a trailerem) |
+//! | E031 | dlaždice nezasahuje do hlavičky, indexu ani oblasti bloků |
+//! | E032 | dlaždice se nepřekrývají (ani s úrovněmi pyramidy) |
+//! | W033 | offsety dlaždic v pořadí indexu rostou |
+//! | E034 | `original_size` odpovídá rozměru dlaždice a typu barev |
+//! | E035 | bez komprese `compressed_size` = `original_size` |
+//! | E036 | dlaždice nejsou prázdné |
+//! | W037 | mezi dlaždicemi a za nimi nejsou nevyužité bajty |
+//! | E040 | metadata a rozšiřující bloky jdou přečíst a vejdou se do souboru |
+//! | W041 | [`FLAG_METADATA`] jen se skutečnými metadaty |
+//! | E050 | s [`FLAG_FILE_HASH`] soubor končí trailerem `CTIS` |
+
+use anyhow::Result;
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::cti::{
+    self, CTIDecoder, CTIHeader, CompressionId, FLAG_FILE_HASH, FLAG_FRAMES, FLAG_INDEX_AT_END,
+    FLAG_METADATA, FLAG_RCT, HASH_MAGIC, HEADER_SIZE, INDEX_ENTRY_SIZE, INDEX_LOCATOR, KNOWN_FLAGS,
+    LOCATOR_SIZE, METADATA_MAGIC, TRAILER_SIZE, VERSION_APPEND, VERSION_CHUNKS, VERSION_MAJOR_MAX,
+    VERSION_MINOR,
+};
+use crate::dct;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// Jedno zjištění; stejné pravidlo porušené více dlaždicemi je jedno zjištění se seznamem.
+#[derive(Debug, Clone, Serialize)]
+pub struct Issue {
+    pub code: &'static str,
+    pub severity: Severity,
+    /// Popis (u dlaždic podle první z nich).
+    pub message: String,
+    /// Pořadí dlaždic v indexu, kterých se zjištění týká (prázdné = celý soubor).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tiles: Vec<u32>,
+}
+
+#[derive(Default)]
+struct Lint {
+    issues: Vec<Issue>,
+}
+
+impl Lint {
+    fn push(&mut self, code: &'static str, severity: Severity, message: String) {
+        self.issues.push(Issue {
+            code,
+            severity,
+            message,
+            tiles: Vec::new(),
+        });
+    }
+
+    fn error(&mut self, code: &'static str, message: String) {
+        self.push(code, Severity::Error, message);
+    }
+
+    fn warn(&mut self, code: &'static str, message: String) {
+        self.push(code, Severity::Warning, message);
+    }
+
+    /// Zjištění u dlaždice `tile` (`None` = dlaždice úrovně pyramidy); `message` se zavolá
+    /// jen u první dlaždice s tímto kódem.
+    fn tile(
+        &mut self,
+        code: &'static str,
+        severity: Severity,
+        tile: Option<u32>,
+        message: impl FnOnce() -> String,
+    ) {
+        if let Some(issue) = self.issues.iter_mut().find(|i| i.code == code) {
+            issue.tiles.extend(tile);
+            return;
+        }
+        let message = match tile {
+            Some(n) => format!("tile {n}: {}", message()),
+            None => format!("pyramid tile: {}", message()),
+        };
+        self.issues.push(Issue {
+            code,
+            severity,
+            message,
+            tiles: tile.into_iter().collect(),
+        });
+    }
+}
+
+/// Zkontroluje soubor; chyba jen při chybě čtení, nálezy jsou v seznamu (prázdný = v pořádku).
+/// Po chybě, kvůli které nejde pokračovat (např. neznámá geometrie indexu), se kontrola
+/// zastaví.
+pub fn lint_file<P: AsRef<Path>>(path: P) -> Result<Vec<Issue>> {
+    let path = path.as_ref();
+    let mut f = BufReader::new(File::open(path)?);
+    let len = f.seek(SeekFrom::End(0))?;
+    let mut lint = Lint::default();
+    if len < HEADER_SIZE as u64 {
+        lint.error(
+            "E001",
+            format!("file has {len} B, shorter than the {HEADER_SIZE} B header"),
+        );
+        return Ok(lint.issues);
+    }
+    let mut raw = [0u8; HEADER_SIZE];
+    f.seek(SeekFrom::Start(0))?;
+    f.read_exact(&mut raw)?;
+    let Some(hdr) = header(&mut lint, &raw) else {
+        return Ok(lint.issues);
+    };
+    structure(&mut lint, path, &mut f, len, &hdr)?;
+    Ok(lint.issues)
+}
+
+/// Pravidla hlavičky; `None` = dál nejde pokračovat.
+fn header(lint: &mut Lint, raw: &[u8; HEADER_SIZE]) -> Option<CTIHeader> {
+    let u16_at = |i: usize| u16::from_le_bytes([raw[i], raw[i + 1]]);
+    let u32_at = |i: usize| u32::from_le_bytes(raw[i..i + 4].try_into().unwrap());
+    if &raw[..4] != b"CTI1" {
+        lint.error(
+            "E001",
+            format!("bad magic {:?}", String::from_utf8_lossy(&raw[..4])),
+        );
+        return None;
+    }
+    let version = u16_at(4);
+    let (major, minor) = (version & 0xFF, version >> 8);
+    if major == 0 || major > VERSION_MAJOR_MAX {
+        lint.error(
+            "E002",
+            format!("format version {major}.{minor} (supported 1..={VERSION_MAJOR_MAX})"),
+        );
+        return None;
+    }
+    let flags = u16_at(6);
+    let unknown = flags & !KNOWN_FLAGS;
+    if unknown != 0 && minor > VERSION_MINOR {
+        lint.warn(
+            "W003",
+            format!("flags 0x{unknown:04X} from a newer minor version {major}.{minor} are ignored"),
+        );
+    } else if unknown != 0 {
+        lint.error(
+            "E003",
+            format!("unknown flags 0x{unknown:04X} in a {major}.{minor} file"),
+        );
+        return None;
+    }
+    if flags & FLAG_INDEX_AT_END != 0 && major < VERSION_APPEND {
+        lint.error(
+            "E004",
+            format!("index-at-end flag in a version {major} file (needs {VERSION_APPEND})"),
+        );
+        return None;
+    }
+    let hdr = CTIHeader {
+        magic: *b"CTI1",
+        version,
+        flags: flags & KNOWN_FLAGS,
+        width: u32_at(8),
+        height: u32_at(12),
+        tile_size: u32_at(16),
+        tiles_x: u32_at(20),
+        tiles_y: u32_at(24),
+        color_type: raw[28],
+        compression: raw[29],
+        quality: raw[30],
+        frames: if flags & FLAG_FRAMES != 0 {
+            u32_at(31)
+        } else {
+            1
+        },
+        chunks_len: if major >= VERSION_CHUNKS {
+            u32_at(35)
+        } else {
+            0
+        },
+    };
+    if hdr.width == 0 || hdr.height == 0 || hdr.tile_size == 0 {
+        lint.error(
+            "E005",
+            format!(
+                "zero dimension: {}x{} px, tile {} px",
+                hdr.width, hdr.height, hdr.tile_size
+            ),
+        );
+        return None;
+    }
+    let expected = (
+        hdr.width.div_ceil(hdr.tile_size),
+        hdr.height.div_ceil(hdr.tile_size),
+    );
+    if (hdr.tiles_x, hdr.tiles_y) != expected {
+        lint.error(
+            "E006",
+            format!(
+                "{}x{} tiles do not cover {}x{} px in {} px tiles exactly (expected {}x{})",
+                hdr.tiles_x,
+                hdr.tiles_y,
+                hdr.width,
+                hdr.height,
+                hdr.tile_size,
+                expected.0,
+                expected.1
+            ),
+        );
+        return None;
+    }
+    let color_ok = cti::bytes_per_pixel(hdr.color_type).is_ok();
+    if !color_ok {
+        lint.error("E007", format!("unknown color type {}", hdr.color_type));
+    } else if let Err(e) = cti::check_sizes(
+        hdr.width,
+        hdr.height,
+        hdr.tile_size,
+        cti::bytes_per_pixel(hdr.color_type).unwrap_or(1),
+    ) {
+        // další pravidla by počítala velikosti dlaždic, které nejde uložit
+        lint.error("E014", format!("{e:#}"));
+        return None;
+    }
+    let codec = CompressionId::from(hdr.compression);
+    // Brotli a Deflate jsou ve specifikaci, i když je tento build neumí
+    let supported = matches!(
+        codec,
+        CompressionId::None
+            | CompressionId::Zstd
+            | CompressionId::Lz4
+            | CompressionId::Brotli
+            | CompressionId::Deflate
+            | CompressionId::Dct
+    );
+    if !supported {
+        lint.error(
+            "E008",
+            format!(
+                "unsupported compression {} ({})",
+                hdr.compression,
+                codec.as_str()
+            ),
+        );
+    }
+    if codec.is_lossy() {
+        if let Err(e) = dct::channels(hdr.color_type) {
+            lint.error("E009", format!("{e:#}"));
+        }
+        if !(1..=dct::MAX_QUALITY).contains(&hdr.quality) {
+            lint.error(
+                "E009",
+                format!("lossy quality {} outside 1..=100", hdr.quality),
+            );
+        }
+    } else if hdr.quality != 100 {
+        lint.warn(
+            "W010",
+            format!(
+                "quality {} with a lossless codec (expected 100)",
+                hdr.quality
+            ),
+        );
+    }
+    if hdr.frames == 0 {
+        lint.error("E011", "frames flag with a frame count of 0".into());
+        return None;
+    }
+    // rezerva 31..64: počet snímků jen s FLAG_FRAMES, délka bloků jen od verze 2
+    let used = |i: usize| {
+        (31..35).contains(&i) && flags & FLAG_FRAMES != 0
+            || (35..39).contains(&i) && major >= VERSION_CHUNKS
+    };
+    let dirty: Vec<usize> = (31..HEADER_SIZE)
+        .filter(|&i| raw[i] != 0 && !used(i))
+        .collect();
+    if let (Some(first), Some(last)) = (dirty.first(), dirty.last()) {
+        lint.warn(
+            "W012",
+            format!(
+                "{} reserved header byte(s) not zero (offsets {first}..={last})",
+                dirty.len()
+            ),
+        );
+    }
+    if hdr.flags & FLAG_RCT != 0 && (codec.is_lossy() || !matches!(hdr.color_type, 3 | 5)) {
+        lint.warn(
+            "W013",
+            format!(
+                "RCT flag is ignored with color type {} and {}",
+                hdr.color_type,
+                codec.as_str()
+            ),
+        );
+    }
+    Some(hdr)
+}
+
+/// Úsek souboru obsazený dlaždicí (`tile` = pořadí v indexu, `None` = úroveň pyramidy).
+struct Span {
+    start: u64,
+    end: u64,
+    tile: Option<u32>,
+}
+
+/// Pravidla indexu, oblasti bloků, dlaždic a traileru.
+fn structure(
+    lint: &mut Lint,
+    path: &Path,
+    f: &mut BufReader<File>,
+    len: u64,
+    hdr: &CTIHeader,
+) -> Result<()> {
+    let count = hdr.tiles_per_frame() as u64 * u64::from(hdr.frames);
+    let index_len = count * INDEX_ENTRY_SIZE as u64;
+    let header = HEADER_SIZE as u64;
+
+    // trailer s hashem
+    let mut end = len;
+    let mut gap = 0;
+    if hdr.flags & FLAG_FILE_HASH != 0 {
+        let mut magic = [0u8; 4];
+        let found = len >= header + TRAILER_SIZE as u64 && {
+            f.seek(SeekFrom::Start(len - TRAILER_SIZE as u64))?;
+            f.read_exact(&mut magic)?;
+            &magic == HASH_MAGIC
+        };
+        if found {
+            end -= TRAILER_SIZE as u64;
+        } else {
+            lint.error(
+                "E050",
+                "file hash flag set but the CTIS trailer is missing".into(),
+            );
+        }
+    }
+
+    // index: za hlavičkou, nebo podle lokátoru na konci
+    let index_at = if hdr.has_index_at_end() {
+        let Some(locator) = end
+            .checked_sub(LOCATOR_SIZE as u64)
+            .filter(|&l| l >= header)
+        else {
+            lint.error("E020", "index locator missing (file too short)".into());
+            return Ok(());
+        };
+        let mut buf = [0u8; LOCATOR_SIZE];
+        f.seek(SeekFrom::Start(locator))?;
+        f.read_exact(&mut buf)?;
+        let offset = u64::from_le_bytes(buf[4..].try_into().unwrap());
+        if &buf[..4] != INDEX_LOCATOR {
+            lint.error(
+                "E020",
+                "index locator CTIX missing: the file was not finished".into(),
+            );
+            return Ok(());
+        }
+        if offset < header + u64::from(hdr.chunks_len)
+            || offset.checked_add(index_len).is_none_or(|e| e > locator)
+        {
+            lint.error(
+                "E020",
+                format!(
+                    "index offset {offset} with {index_len} B of entries does not fit \
+                     before the locator at {locator}"
+                ),
+            );
+            return Ok(());
+        }
+        // mezera mezi indexem a lokátorem se počítá k nevyužitým bajtům
+        gap = locator - offset - index_len;
+        end = offset;
+        offset
+    } else {
+        if header + index_len > end {
+            lint.error(
+                "E021",
+                format!("index of {count} tiles needs {index_len} B, the file has only {len} B"),
+            );
+            return Ok(());
+        }
+        header
+    };
+    let mut entries = vec![0u8; index_len as usize];
+    f.seek(SeekFrom::Start(index_at))?;
+    f.read_exact(&mut entries)?;
+
+    // metadata (verze 1) nebo oblast bloků hned za hlavičkou / indexem
+    let ext_at = if hdr.has_index_at_end() {
+        header
+    } else {
+        header + index_len
+    };
+    let ext_len = if hdr.has_chunks() {
+        u64::from(hdr.chunks_len)
+    } else if hdr.flags & FLAG_METADATA != 0 && ext_at + 8 <= end {
+        let mut buf = [0u8; 8];
+        f.seek(SeekFrom::Start(ext_at))?;
+        f.read_exact(&mut buf)?;
+        if &buf[..4] == METADATA_MAGIC {
+            8 + u64::from(u32::from_le_bytes(buf[4..].try_into().unwrap()))
+        } else {
+            0
+        }
+    } else {
+        0
+    };
+    let start = ext_at + ext_len;
+    let mut spans = Vec::new();
+    if start > end {
+        lint.error(
+            "E040",
+            format!("extension area of {ext_len} B at {ext_at} runs past the tile data end {end}"),
+        );
+    } else {
+        match CTIDecoder::extensions(path) {
+            Ok(ext) => {
+                if hdr.flags & FLAG_METADATA != 0 && ext.metadata.is_empty() {
+                    lint.warn("W041", "metadata flag set but there is no metadata".into());
+                }
+                let pyramid = ext.pyramid.iter().flat_map(|l| &l.tiles);
+                spans.extend(pyramid.map(|t| Span {
+                    start: t.offset,
+                    end: t.offset + u64::from(t.compressed_size),
+                    tile: None,
+                }));
+            }
+            Err(e) => lint.error("E040", format!("{e:#}")),
+        }
+    }
+
+    // položky indexu
+    let codec = CompressionId::from(hdr.compression);
+    let bpp = cti::bytes_per_pixel(hdr.color_type).ok();
+    let tpf = hdr.tiles_per_frame();
+    let mut prev = None::<u64>;
+    for (i, e) in entries.chunks_exact(INDEX_ENTRY_SIZE).enumerate() {
+        let offset = u64::from_le_bytes(e[..8].try_into().unwrap());
+        let compressed = u32::from_le_bytes(e[8..12].try_into().unwrap());
+        let original = u32::from_le_bytes(e[12..16].try_into().unwrap());
+        let n = i as u32;
+        let j = (i % tpf) as u32;
+        if compressed == 0 {
+            lint.tile("E036", Severity::Error, Some(n), || {
+                "compressed size is 0".into()
+            });
+        }
+        let tile_end = offset.saturating_add(u64::from(compressed));
+        if offset < start {
+            lint.tile("E031", Severity::Error, Some(n), || {
+                format!(
+                    "offset {offset} lies in the header, index or extension area \
+                     (tile data start at {start})"
+                )
+            });
+        } else if tile_end > end {
+            lint.tile("E030", Severity::Error, Some(n), || {
+                format!("{offset}..{tile_end} runs past the tile data end {end}")
+            });
+        }
+        if prev.is_some_and(|p| offset < p) {
+            lint.tile("W033", Severity::Warning, Some(n), || {
+                format!("offset {offset} is lower than that of the previous tile")
+            });
+        }
+        prev = Some(offset);
+        if let Some(bpp) = bpp {
+            let (w, h) = cti::tile_dims(hdr, j % hdr.tiles_x, j / hdr.tiles_x);
+            let expected = cti::stored_tile_len(hdr, w, h, bpp);
+            if original as usize != expected {
+                lint.tile("E034", Severity::Error, Some(n), || {
+                    format!("original size {original} B, a {w}x{h} tile needs {expected} B")
+                });
+            }
+        }
+        if matches!(codec, CompressionId::None) && compressed != original {
+            lint.tile("E035", Severity::Error, Some(n), || {
+                format!(
+                    "uncompressed tile with compressed size {compressed} B \
+                     ≠ original size {original} B"
+                )
+            });
+        }
+        spans.push(Span {
+            start: offset,
+            end: tile_end,
+            tile: Some(n),
+        });
+    }
+
+    // překryvy a nevyužité bajty v oblasti dlaždic
+    spans.sort_by_key(|s| (s.start, s.end));
+    let mut reach = start;
+    let mut unused = gap;
+    for s in spans.iter().filter(|s| s.start >= start && s.end <= end) {
+        if s.start < reach {
+            lint.tile("E032", Severity::Error, s.tile, || {
+                format!(
+                    "{}..{} overlaps another tile (which ends at {reach})",
+                    s.start, s.end
+                )
+            });
+        } else {
+            unused += s.start - reach;
+        }
+        reach = reach.max(s.end);
+    }
+    unused += end.saturating_sub(reach);
+    if unused > 0 {
+        lint.warn(
+            "W037",
+            format!("{unused} byte(s) of the file belong to no structure"),
+        );
+    }
+    Ok(())
+}
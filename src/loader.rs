@@ -0,0 +1,125 @@
+use std::borrow::Cow;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::time::Instant;
+
+use anyhow::Result;
+use eframe::egui::{self, ColorImage, TextureHandle};
+
+use crate::cti::{self, BadTile, CTIDecoder, CTIHeader};
+use crate::display::DisplayTransform;
+use crate::flatfield::FlatField;
+use crate::tilecache::TileCache;
+
+/// Výsledek celého dekódování (hlavička, RAW data, poškozené dlaždice).
+pub type Decoded = (CTIHeader, Vec<u8>, Vec<BadTile>);
+
+enum LoadEvent {
+    /// Hotová dlaždice převedená pro GPU; `pos` je levý horní roh v pixelech.
+    Tile {
+        pos: [usize; 2],
+        image: ColorImage,
+    },
+    Done(Result<Decoded>),
+}
+
+/// Dekódování souboru na pozadí; hotové dlaždice se průběžně nahrávají do textury,
+/// takže obrázek se objevuje postupně.
+pub struct Loader {
+    pub path: PathBuf,
+    pub frame: u32,
+    rx: Receiver<LoadEvent>,
+    cancel: Arc<AtomicBool>,
+    pub done: usize,
+    pub total: usize,
+    pub started: Instant,
+}
+
+impl Loader {
+    /// Dlaždice se berou z `tiles` a nově dekódované se do ní ukládají.
+    /// `flat` = korekce osvětlení, která se použije na každou dlaždici před nahráním,
+    /// `display` = převod do profilu monitoru.
+    pub fn spawn(
+        ctx: &egui::Context,
+        path: PathBuf,
+        frame: u32,
+        hdr: &CTIHeader,
+        tiles: TileCache,
+        flat: Option<Arc<FlatField>>,
+        display: Option<Arc<DisplayTransform>>,
+    ) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (p, h, cn, ctx) = (path.clone(), *hdr, cancel.clone(), ctx.clone());
+        std::thread::spawn(move || {
+            let _span = tracing::info_span!("decode", path = %p.display(), frame).entered();
+            let res =
+                CTIDecoder::decode_frame_progressive_cached(&p, frame, &tiles, |index, tile| {
+                    if cn.load(Ordering::Relaxed) {
+                        return false;
+                    }
+                    let (col, row) = (index % h.tiles_x, index / h.tiles_x);
+                    let (width, height) = cti::tile_dims(&h, col, row);
+                    let tile_hdr = CTIHeader { width, height, ..h };
+                    let (x0, y0) = (col * h.tile_size, row * h.tile_size);
+                    let mut tile = Cow::Borrowed(tile);
+                    if let Some(flat) = &flat {
+                        let _ = flat.apply_tile(h.color_type, (x0, y0), width, tile.to_mut());
+                    }
+                    // nepřevoditelná dlaždice (16 bitů) se jen nezobrazí průběžně
+                    if let Ok(mut image) = crate::to_color_image(&tile_hdr, &tile) {
+                        if let Some(display) = &display {
+                            display.apply(&mut image);
+                        }
+                        let pos = [x0 as usize, y0 as usize];
+                        let _ = tx.send(LoadEvent::Tile { pos, image });
+                        ctx.request_repaint();
+                    }
+                    true
+                });
+            let _ = tx.send(LoadEvent::Done(res));
+            ctx.request_repaint();
+        });
+        Self {
+            path,
+            frame,
+            rx,
+            cancel,
+            done: 0,
+            total: hdr.tiles_per_frame(),
+            started: Instant::now(),
+        }
+    }
+
+    /// Nahraje do `tex` dlaždice dekódované od minulého snímku; po doběhnutí vrátí výsledek.
+    pub fn poll(
+        &mut self,
+        tex: &mut TextureHandle,
+        options: egui::TextureOptions,
+    ) -> Option<Result<Decoded>> {
+        let mut events = self.rx.try_iter().peekable();
+        events.peek()?;
+        // nahrání dlaždic tohoto snímku do textury (doba je v záznamu)
+        let span = tracing::debug_span!("upload", tiles = tracing::field::Empty).entered();
+        let before = self.done;
+        for event in events {
+            match event {
+                LoadEvent::Tile { pos, image } => {
+                    tex.set_partial(pos, image, options);
+                    self.done += 1;
+                }
+                LoadEvent::Done(res) => return Some(res),
+            }
+        }
+        span.record("tiles", self.done - before);
+        None
+    }
+}
+
+impl Drop for Loader {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
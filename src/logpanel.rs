@@ -0,0 +1,260 @@
+//! Záznam událostí přes `tracing`: vše jde na stderr a zároveň do paměti, odkud ho ukáže
+//! okno „Log“. Chyby tak nezapadnou v konzoli, kterou na Windows nikdo nevidí. Spany
+//! (otevření, dekódování, nahrání textury) se zapíšou při zavření i s dobou trvání.
+
+use eframe::egui;
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Instant;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::{Layer, util::SubscriberInitExt};
+
+/// Kolik posledních záznamů se drží v paměti.
+const CAPACITY: usize = 2000;
+
+pub struct Entry {
+    /// Sekundy od spuštění.
+    pub time: f32,
+    pub level: Level,
+    pub target: String,
+    pub message: String,
+}
+
+#[derive(Default)]
+struct Inner {
+    entries: VecDeque<Entry>,
+    /// Chyby zapsané od posledního otevření okna.
+    unseen_errors: usize,
+    ctx: Option<egui::Context>,
+}
+
+/// Sdílený záznam (jeden na proces, viz [`buffer`]).
+#[derive(Clone, Default)]
+pub struct LogBuffer(Arc<Mutex<Inner>>);
+
+impl LogBuffer {
+    fn push(&self, entry: Entry) {
+        let mut inner = self.0.lock().unwrap();
+        if entry.level == Level::ERROR {
+            inner.unseen_errors += 1;
+            // chyba z vlákna na pozadí se má ukázat hned (počítadlo v liště)
+            if let Some(ctx) = &inner.ctx {
+                ctx.request_repaint();
+            }
+        }
+        if inner.entries.len() == CAPACITY {
+            inner.entries.pop_front();
+        }
+        inner.entries.push_back(entry);
+    }
+
+    /// Kontext, který se po chybě překreslí.
+    pub fn set_ctx(&self, ctx: &egui::Context) {
+        self.0.lock().unwrap().ctx = Some(ctx.clone());
+    }
+
+    pub fn unseen_errors(&self) -> usize {
+        self.0.lock().unwrap().unseen_errors
+    }
+}
+
+static BUFFER: OnceLock<LogBuffer> = OnceLock::new();
+static START: OnceLock<Instant> = OnceLock::new();
+
+/// Záznam v paměti (prázdný, dokud neproběhl [`init`]).
+pub fn buffer() -> &'static LogBuffer {
+    BUFFER.get_or_init(LogBuffer::default)
+}
+
+/// Zapne záznam: události aplikace od úrovně DEBUG do okna, od INFO na stderr; knihovny
+/// jen varování a chyby.
+pub fn init() {
+    START.get_or_init(Instant::now);
+    let filter = |app: Level| {
+        Targets::new()
+            .with_target("cti_view", app)
+            .with_default(Level::WARN)
+    };
+    let stderr = tracing_subscriber::fmt::layer()
+        .with_writer(std::io::stderr)
+        .with_span_events(FmtSpan::CLOSE)
+        .with_filter(filter(Level::INFO));
+    let capture = Capture(buffer().clone()).with_filter(filter(Level::DEBUG));
+    if let Err(e) = tracing_subscriber::registry()
+        .with(stderr)
+        .with(capture)
+        .try_init()
+    {
+        eprintln!("logging: {e}");
+    }
+}
+
+/// Vrstva, která události a zavřené spany ukládá do [`LogBuffer`].
+struct Capture(LogBuffer);
+
+/// Začátek spanu a jeho pole (pro záznam při zavření).
+struct Started(Instant, String);
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for Capture {
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut fields = Fields::default();
+        attrs.record(&mut fields);
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut()
+                .insert(Started(Instant::now(), fields.rest));
+        }
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let mut fields = Fields::default();
+        values.record(&mut fields);
+        if let Some(span) = ctx.span(id)
+            && let Some(started) = span.extensions_mut().get_mut::<Started>()
+        {
+            started.1.push_str(&fields.rest);
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let ext = span.extensions();
+        let Some(Started(at, fields)) = ext.get::<Started>() else {
+            return;
+        };
+        let meta = span.metadata();
+        self.0.push(Entry {
+            time: since_start(),
+            level: *meta.level(),
+            target: meta.target().to_owned(),
+            message: format!(
+                "{}{fields} took {:.1} ms",
+                meta.name(),
+                at.elapsed().as_secs_f64() * 1000.0
+            ),
+        });
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        // název nadřazených spanů jako prefix („open:decode: …“)
+        let mut message = String::new();
+        if let Some(scope) = ctx.event_scope(event) {
+            for span in scope.from_root() {
+                let _ = write!(message, "{}: ", span.name());
+            }
+        }
+        message.push_str(&fields.message);
+        message.push_str(&fields.rest);
+        let meta = event.metadata();
+        self.0.push(Entry {
+            time: since_start(),
+            level: *meta.level(),
+            target: meta.target().to_owned(),
+            message,
+        });
+    }
+}
+
+fn since_start() -> f32 {
+    START.get_or_init(Instant::now).elapsed().as_secs_f32()
+}
+
+/// Pole události: `message` zvlášť, ostatní jako ` klíč=hodnota`.
+#[derive(Default)]
+struct Fields {
+    message: String,
+    rest: String,
+}
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{value:?}");
+        } else {
+            let _ = write!(self.rest, " {}={value:?}", field.name());
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            let _ = write!(self.rest, " {}={value}", field.name());
+        }
+    }
+}
+
+/// Okno „Log“ s posledními událostmi.
+pub struct LogWindow {
+    min_level: Level,
+}
+
+impl Default for LogWindow {
+    fn default() -> Self {
+        Self {
+            min_level: Level::INFO,
+        }
+    }
+}
+
+impl LogWindow {
+    pub fn window(&mut self, ctx: &egui::Context, open: &mut bool) {
+        let log = buffer();
+        egui::Window::new("Log")
+            .open(open)
+            .default_size([640.0, 320.0])
+            .show(ctx, |ui| {
+                let mut inner = log.0.lock().unwrap();
+                inner.unseen_errors = 0;
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_label("Level")
+                        .selected_text(self.min_level.as_str())
+                        .show_ui(ui, |ui| {
+                            for l in [Level::ERROR, Level::WARN, Level::INFO, Level::DEBUG] {
+                                ui.selectable_value(&mut self.min_level, l, l.as_str());
+                            }
+                        });
+                    if ui.button("Copy").clicked() {
+                        let text: Vec<String> = inner
+                            .entries
+                            .iter()
+                            .filter(|e| e.level <= self.min_level)
+                            .map(line)
+                            .collect();
+                        ctx.copy_text(text.join("\n"));
+                    }
+                    if ui.button("Clear").clicked() {
+                        inner.entries.clear();
+                    }
+                });
+                ui.separator();
+                egui::ScrollArea::both()
+                    .stick_to_bottom(true)
+                    .auto_shrink(false)
+                    .show(ui, |ui| {
+                        for e in inner.entries.iter().filter(|e| e.level <= self.min_level) {
+                            let color = match e.level {
+                                Level::ERROR => ui.visuals().error_fg_color,
+                                Level::WARN => ui.visuals().warn_fg_color,
+                                _ => ui.visuals().text_color(),
+                            };
+                            ui.label(egui::RichText::new(line(e)).monospace().color(color));
+                        }
+                    });
+            });
+    }
+}
+
+fn line(e: &Entry) -> String {
+    format!("{:>9.3} {:5} {}: {}", e.time, e.level, e.target, e.message)
+}
@@ -1,21 +1,145 @@
 #![cfg_attr(all(target_os = "windows", not(debug_assertions)), windows_subsystem = "windows")]
 
 use anyhow::{bail, Context, Result};
-use eframe::egui::{self as egui, ColorImage, TextureFilter, TextureHandle, Vec2, Key, Modifiers};
+use eframe::egui::{self as egui, ColorImage, Rect, TextureFilter, TextureHandle, Vec2};
 use eframe::{self};
 use rfd::FileDialog;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 
-mod cti;
-use cti::{CTIDecoder, CTIHeader, CompressionId};
+mod a11y;
+mod align;
+mod annotations;
+mod barcode;
+mod batch;
+mod browser;
+mod cache;
+mod cli;
+mod clipboard;
+mod compare;
+mod convert;
+mod diagnose;
+mod diff;
+mod diskspace;
+mod display;
+mod dryrun;
+mod dzi;
+mod errors;
+mod export;
+mod flatfield;
+mod handoff;
+mod iiif;
+mod kiosk;
+mod lens;
+mod loader;
+mod logpanel;
+mod measure;
+mod metadata;
+mod metaform;
+mod mtf;
+mod naming;
+mod noise;
+#[cfg(feature = "ocr")]
+mod ocr;
+mod pages;
+mod patches;
+mod playback;
+mod portable;
+mod prefs;
+mod presets;
+mod previews;
+mod print;
+mod recovery;
+mod report;
+mod selection;
+mod shortcuts;
+mod stats;
+mod stitch;
+mod tabs;
+mod textlayer;
+mod tilegrid;
+mod toast;
+mod tools;
+mod update;
+mod upload;
+mod watch;
+mod workdirs;
+use annotations::{AnnotationDiff, Annotations, Annotator, Change};
+use batch::BatchJob;
+use browser::{BrowserAction, FileBrowser};
+use cache::ImageCache;
+use clap::Parser;
+use compare::{CompareCmd, CompareImage};
+use convert::{ConvertDialog, ExportDialog};
+use cti::{BadTile, CTIDecoder, CTIHeader, CTIMetadata, CompressionId, Edit};
+#[cfg(feature = "remote")]
+use cti_view::remote;
+use cti_view::{cmyk, cti, hdr, lint, testdata, tilecache, view};
+use diagnose::Diagnose;
+use diff::QualityReport;
+use display::DisplayTransform;
+use errors::ErrorReport;
+use flatfield::FlatField;
+use handoff::ViewLink;
+use kiosk::Kiosk;
+use loader::Loader;
+use logpanel::LogWindow;
+use measure::MeasurePanel;
+use metadata::MetadataEditor;
+use mtf::MtfPanel;
+use noise::NoisePanel;
+use pages::{PageMemory, PageThumbs};
+use patches::{PatchSession, PatchSetup};
+use playback::Playback;
+use prefs::{Background, OpenZoom, Preferences, Theme};
+use print::{PrintDialog, PrintSettings, PrintTarget};
+use report::FileStats;
+use recovery::{Heartbeat, LastView, PendingBatch, RecoveryPrompt, Session};
+use selection::Selection;
+use shortcuts::{Action, Shortcuts};
+use stats::UsageStats;
+use stitch::StitchDialog;
+use tabs::{Tab, TabAction};
+use tilegrid::TileGrid;
+use toast::Toasts;
+use tilecache::TileCache;
+use tools::ExternalTool;
+use update::Updater;
+use view::{View, ZoomBox, check_previewable, to_color_image};
+use watch::FileWatcher;
 
 fn main() -> Result<()> {
-    let native_options = eframe::NativeOptions::default();
+    logpanel::init();
+    // podpříkazy (např. `convert`) běží bez okna
+    let mut args = match cli::Cli::try_parse() {
+        Ok(args) => args,
+        Err(e) => {
+            cli::attach_console();
+            e.exit();
+        }
+    };
+    if args.is_headless() {
+        cli::attach_console();
+    }
+    let kiosk = args.kiosk.take();
+    // `--open` potřebuje i `--decode-to` (běží bez okna)
+    let open = args.open.clone();
+    if cli::run(args)? {
+        return Ok(());
+    }
+    let kiosk = Kiosk::from_args(kiosk)?;
+    let native_options = eframe::NativeOptions {
+        // poloha a velikost okna se obnoví na monitoru, kde bylo naposledy (odpojený
+        // monitor → hlavní); profil monitoru viz `update_display`
+        persist_window: true,
+        ..Default::default()
+    };
     // Nepropagujeme eframe::Error přes `?` (není Send/Sync); mapneme na anyhow::Error (string).
     eframe::run_native(
         "CTI View",
         native_options,
-        Box::new(|_cc| Ok(Box::<App>::default())),
+        Box::new(|cc| Ok(Box::new(App::new(cc, kiosk, open)))),
     )
     .map_err(|e| anyhow::anyhow!(e.to_string()))?;
     Ok(())
@@ -24,133 +148,1028 @@ fn main() -> Result<()> {
 #[derive(Default)]
 struct App {
     image_tex: Option<TextureHandle>,
+    preview_tex: Option<TextureHandle>, // náhled z diskové cache pod postupně načítaným obrázkem
     image_size: Option<(u32, u32)>,
+    image_rect: Option<Rect>,  // kam se obrázek naposledy vykreslil
+    image_clip: Option<Rect>,  // viditelná plocha panelu s obrázkem
+    raw: Option<Arc<Vec<u8>>>, // dekódovaná data (pro vzorkování pixelů)
+    loader: Option<Loader>,    // dekódování na pozadí (obrázek se vykresluje postupně)
+    playback: Playback,        // snímky vícesnímkového souboru
+    pages: PageMemory,         // naposledy zobrazená stránka po souborech
+    thumbs: PageThumbs,
+
+    // korekce nerovnoměrného osvětlení (jen zobrazení, originál se nemění)
+    flat: Option<Arc<FlatField>>,
+    flat_view: bool,
+    cache: ImageCache,
+    tiles: TileCache, // rozbalené dlaždice sdílené s dekodérem na pozadí a výřezy
     last_path: Option<PathBuf>,
+    // sledování otevřeného souboru (znovu načtení po změně na disku)
+    watcher: Option<FileWatcher>,
+    watched: Option<PathBuf>,
+    toasts: Toasts,
 
-    // zoom & režimy zobrazení
-    zoom: f32,            // 1.0 = 100%
-    fit_to_window: bool,  // true = obsah se přizpůsobí oknu
+    // zoom, posun & otočení (sdílené i s porovnávaným obrázkem)
+    view: View,
+    zoom_box: ZoomBox,
+
+    // porovnání dvou souborů vedle sebe
+    compare: Option<CompareImage>,
+
+    // anotace (tahy perem) uložené v sidecaru
+    annotator: Annotator,
+
+    // obdélníkový výběr (Shift + tažení) pro kopírování do schránky
+    selection: Selection,
 
     // info dialog
     show_info: bool,
+    file_stats: Option<(PathBuf, Result<FileStats, String>)>, // statistiky z indexu, podle souboru
+    decode_time: Option<Duration>,                            // `None` = obrázek z cache
     last_hdr: Option<CTIHeader>,
+    bad_tiles: Vec<BadTile>, // dlaždice nahrazené vzorem (poškozený soubor)
+    diagnose: Diagnose,      // mřížka dlaždic obarvená podle stavu místo obrázku
+    tile_grid: TileGrid,     // hranice dlaždic přes obrázek
+    metadata: CTIMetadata,
+    metadata_editor: MetadataEditor,
+
+    // prohlížeč souborů + dávkové operace
+    browser: FileBrowser,
+    batch: Option<BatchJob>,
+    convert: ConvertDialog,
+    show_convert: bool,
+    stitch: StitchDialog,
+    show_stitch: bool,
+    export: ExportDialog,
+    show_export: bool,
+    print: PrintDialog,
+    show_print: bool,
+
+    // otevření souboru z URL (čte se po částech)
+    #[cfg(feature = "remote")]
+    show_open_url: bool,
+    #[cfg(feature = "remote")]
+    open_url: String,
+
+    // externí nástroje ("Open with…")
+    tools: Vec<ExternalTool>,
+    show_tools: bool,
+
+    // nastavení + klávesové zkratky
+    prefs: Preferences,
+    shortcuts: Shortcuts,
+    show_prefs: bool,
+
+    // QA plošky pro kontrolu kalibrace monitoru
+    patch_setup: PatchSetup,
+    show_patches: bool,
+    patch_session: Option<PatchSession>,
+
+    // analýzy kvality (šum, MTF) a měření
+    noise: NoisePanel,
+    show_noise: bool,
+    #[cfg(feature = "ocr")]
+    ocr: ocr::OcrPanel,
+    #[cfg(feature = "ocr")]
+    show_ocr: bool,
+    mtf: MtfPanel,
+    show_mtf: bool,
+    measure: MeasurePanel,
+    // textová vrstva ALTO/hOCR vedle obrázku
+    text: textlayer::TextPanel,
+    show_measure: bool,
+
+    // průběžný stav pro obnovu po pádu a nabídka obnovy po spuštění
+    heartbeat: Heartbeat,
+    recovery: Option<RecoveryPrompt>,
+
+    // kontrola podepsaných vydání
+    updater: Updater,
+
+    // poslední události a chyby (okno „Log“)
+    log: LogWindow,
+    show_log: bool,
+    // chyba otevření/dekódování, kterou musí uživatel vidět
+    error: Option<ErrorReport>,
+
+    // místní statistiky používání (jen se zapnutou předvolbou)
+    usage: UsageStats,
+
+    // převod zobrazení do ICC profilu monitoru, na kterém je okno
+    monitor: Option<String>,
+    display: Option<Arc<DisplayTransform>>,
+
+    // režim kiosku (jen prohlížení povolených složek)
+    kiosk: Option<Kiosk>,
+
+    // odkazy na pohled; výřez z odkazu se nastaví při vykreslení (až je znám viewport)
+    show_open_link: bool,
+    open_link: String,
+    pending_region: Option<Rect>,
+
+    // karty s dalšími otevřenými soubory (prázdné = jen jeden soubor, bez lišty karet);
+    // aktivní karta je v polích výše, její místo v `tabs` je prázdné
+    tabs: Vec<Tab>,
+    active_tab: usize,
+
+    // další okna se samostatným stavem (vlastní soubor, pohled, dialogy)
+    windows: Vec<ChildWindow>,
+    next_window: u64,
+}
+
+/// Další okno aplikace; kreslí se jako samostatný viewport v rámci téhož procesu, takže
+/// sdílí cache dlaždic, ale ne otevřený soubor ani pohled.
+struct ChildWindow {
+    id: egui::ViewportId,
+    app: Box<App>,
 }
 
+const TOOLS_KEY: &str = "external_tools";
+const SHORTCUTS_KEY: &str = "shortcuts";
+const PREFS_KEY: &str = "preferences";
+const PAGES_KEY: &str = "last_pages";
+const STATS_KEY: &str = "usage_stats";
+const LAST_VIEW_KEY: &str = "last_view";
+
 impl eframe::App for App {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        // kiosek nastavení nemění (přepnutí motivu apod. platí jen do zavření)
+        if self.kiosk.is_none() {
+            eframe::set_value(storage, TOOLS_KEY, &self.tools);
+            eframe::set_value(storage, SHORTCUTS_KEY, &self.shortcuts);
+            eframe::set_value(storage, PREFS_KEY, &self.prefs);
+            eframe::set_value(storage, LAST_VIEW_KEY, &self.last_view());
+        }
+        eframe::set_value(storage, PAGES_KEY, &self.pages);
+        eframe::set_value(storage, STATS_KEY, &self.usage);
+    }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.heartbeat.finish();
+    }
+
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        if self.heartbeat.due(ctx) {
+            self.heartbeat.write(self.session());
+        }
+        if let Some(prompt) = &mut self.recovery
+            && let Some(restore) = prompt.window(ctx)
+            && let Some(prompt) = self.recovery.take()
+            && restore
+        {
+            self.restore_session(ctx, prompt);
+        }
+        self.updater.window(ctx);
+
+        self.show(ctx);
+        self.show_windows(ctx);
+    }
+}
+
+impl App {
+    /// Obsah jednoho okna (hlavního i dalšího otevřeného přes „Open in new window“).
+    fn show(&mut self, ctx: &egui::Context) {
+        // Měření plošek překryje celé okno
+        if let Some(session) = &mut self.patch_session {
+            if !session.show(ctx) {
+                ctx.send_viewport_cmd(egui::ViewportCommand::Fullscreen(false));
+                self.patch_session = None;
+            }
+            return;
+        }
+
+        self.update_display(ctx, false);
+        self.poll_loader(ctx);
+        self.poll_watcher(ctx);
+        if let Some(frame) = self.playback.tick(ctx, self.loader.is_none())
+            && let Err(e) = self.show_frame(ctx, frame)
+        {
+            tracing::error!("frame error: {e:?}");
+        }
+
+        // Prezentační režim = fullscreen bez toolbaru a panelů
+        let presenting = ctx.input(|i| i.viewport().fullscreen.unwrap_or(false));
+
         // Top toolbar
-        egui::TopBottomPanel::top("top").show(ctx, |ui| {
+        egui::TopBottomPanel::top("top").show_animated(ctx, !presenting, |ui| {
             ui.horizontal(|ui| {
-                if ui.button("Open…").clicked() {
-                    let dir = self
-                        .last_path
-                        .as_deref()
-                        .and_then(Path::parent)
-                        .unwrap_or_else(|| Path::new("."));
-                    let file = FileDialog::new()
-                        .add_filter("CTI images", &["cti"])
-                        .set_directory(dir)
-                        .pick_file();
-
-                    if let Some(path) = file {
-                        if let Err(e) = self.load_cti(ctx, &path) {
-                            eprintln!("open error: {e:?}");
-                        } else {
-                            self.last_path = Some(path);
+                let has_image = self.image_tex.is_some();
+                // v kiosku jen prohlížení (viz `kiosk`)
+                let kiosk = self.kiosk.is_some();
+                if self.tool_button(ui, true, "Open…", Action::Open) {
+                    self.run_action(ctx, Action::Open);
+                }
+                if !kiosk && self.tool_button(ui, true, "New window…", Action::OpenInNewWindow) {
+                    self.run_action(ctx, Action::OpenInNewWindow);
+                }
+                #[cfg(feature = "remote")]
+                if !kiosk && ui.button("Open URL…").clicked() {
+                    self.show_open_url = true;
+                }
+
+                if self.tool_button(ui, has_image, "Info", Action::Info) {
+                    self.run_action(ctx, Action::Info);
+                }
+                let hover = self.action_hover(ctx, Action::Diagnose);
+                let diagnose = egui::Button::new("Diagnose").selected(self.diagnose.enabled);
+                let resp = ui.add_enabled(has_image, diagnose).on_hover_text(&hover);
+                resp.widget_info(|| {
+                    egui::WidgetInfo::labeled(egui::WidgetType::Button, has_image, &hover)
+                });
+                if resp.clicked() {
+                    self.run_action(ctx, Action::Diagnose);
+                }
+                ui.add_enabled_ui(has_image, |ui| {
+                    ui.menu_button("Tiles", |ui| {
+                        let hover = self.action_hover(ctx, Action::TileGrid);
+                        ui.checkbox(&mut self.tile_grid.enabled, "Show tile grid")
+                            .on_hover_text(hover);
+                        ui.add_enabled(
+                            self.tile_grid.enabled,
+                            egui::Checkbox::new(&mut self.tile_grid.indices, "Show tile indices"),
+                        );
+                        ui.checkbox(&mut self.tile_grid.heatmap, "Compression heatmap")
+                            .on_hover_text(
+                                "Color tiles by compressed / original size \
+                                 (green compresses well, red poorly)",
+                            );
+                    });
+                });
+                // expozice a mapování tónů jen u HDR (float) snímků
+                if self.last_hdr.is_some_and(|h| cti::is_float(h.color_type)) {
+                    let mut tone = hdr::tone_map();
+                    ui.menu_button("HDR", |ui| {
+                        ui.add(
+                            egui::Slider::new(&mut tone.exposure, -10.0..=10.0)
+                                .step_by(0.1)
+                                .suffix(" EV")
+                                .text("Exposure"),
+                        );
+                        ui.horizontal(|ui| {
+                            ui.label("Tone mapping:");
+                            for op in hdr::Operator::ALL {
+                                ui.selectable_value(&mut tone.operator, op, op.label());
+                            }
+                        });
+                        if ui.button("Reset").clicked() {
+                            tone = hdr::ToneMap::DEFAULT;
+                        }
+                    });
+                    if tone != hdr::tone_map() {
+                        hdr::set_tone_map(tone);
+                        if let Err(e) = self.redisplay(ctx) {
+                            tracing::error!("texture error: {e:?}");
                         }
                     }
                 }
 
-                if ui
-                    .add_enabled(self.image_tex.is_some(), egui::Button::new("Info"))
-                    .clicked()
-                {
-                    self.show_info = true;
+                // odkaz na pohled pro kolegu (stejný soubor, stránka a místo)
+                ui.menu_button("Link", |ui| {
+                    let copy = ui
+                        .add_enabled(has_image && !kiosk, egui::Button::new("Copy view link"))
+                        .on_hover_text("Link that opens this file, page and area in CTI View");
+                    if copy.clicked() {
+                        if let Some(link) = self.view_link() {
+                            ctx.copy_text(link.to_uri());
+                        }
+                        ui.close();
+                    }
+                    if ui.button("Open view link…").clicked() {
+                        self.show_open_link = true;
+                        ui.close();
+                    }
+                });
+
+                if !kiosk {
+                    ui.menu_button("Open with", |ui| {
+                        let mut chosen = None;
+                        for t in &self.tools {
+                            if ui
+                                .add_enabled(self.last_path.is_some(), egui::Button::new(&t.name))
+                                .clicked()
+                            {
+                                chosen = Some(t.clone());
+                                ui.close();
+                            }
+                        }
+                        if let (Some(tool), Some(path)) = (chosen, self.last_path.clone()) {
+                            self.launch_tool(ctx, tool, path);
+                        }
+                        ui.separator();
+                        if ui.button("Configure…").clicked() {
+                            self.show_tools = true;
+                            ui.close();
+                        }
+                    });
+                }
+
+                if self.can_print() && self.tool_button(ui, has_image, "Print…", Action::Print) {
+                    self.run_action(ctx, Action::Print);
+                }
+
+                if !kiosk {
+                    let hover = "Copy visible image or selection to the clipboard (Ctrl+C)";
+                    if a11y::icon_button_enabled(ui, has_image, "Copy", hover).clicked() {
+                        self.copy_image();
+                    }
+
+                    // přepínač: tažení bez Shiftu vybírá místo posouvání
+                    let hover = self.action_hover(ctx, Action::SelectMode);
+                    let select = egui::Button::new("Select").selected(self.selection.mode);
+                    let resp = ui.add_enabled(has_image, select).on_hover_text(&hover);
+                    resp.widget_info(|| {
+                        egui::WidgetInfo::labeled(egui::WidgetType::Button, has_image, &hover)
+                    });
+                    if resp.clicked() {
+                        self.run_action(ctx, Action::SelectMode);
+                    }
+
+                    let has_sel = has_image && self.selection.rect.is_some();
+                    if self.tool_button(ui, has_sel, "Export selection…", Action::ExportSelection)
+                    {
+                        self.run_action(ctx, Action::ExportSelection);
+                    }
+
+                    // úpravy se ukládají do nového CTI, otevřený soubor zůstane
+                    let running = self.batch.as_ref().is_some_and(|j| !j.is_finished());
+                    ui.add_enabled_ui(has_image && !running, |ui| {
+                        ui.menu_button("Edit", |ui| {
+                            let mut edit = None;
+                            let crop = ui
+                                .add_enabled(has_sel, egui::Button::new("Crop to selection…"))
+                                .on_hover_text(
+                                    "Tiles fully inside a tile-aligned selection are copied \
+                                     as stored",
+                                );
+                            if crop.clicked() {
+                                edit = self
+                                    .selection
+                                    .region()
+                                    .map(|(x, y, w, h)| Edit::Crop(x, y, w, h));
+                            }
+                            ui.separator();
+                            for (label, e) in [
+                                ("Rotate 90° clockwise…", Edit::Rotate(1)),
+                                ("Rotate 90° counter-clockwise…", Edit::Rotate(3)),
+                                ("Rotate 180°…", Edit::Rotate(2)),
+                                ("Flip horizontally…", Edit::FlipHorizontal),
+                                ("Flip vertically…", Edit::FlipVertical),
+                            ] {
+                                if ui.button(label).clicked() {
+                                    edit = Some(e);
+                                }
+                            }
+                            if let Some(edit) = edit {
+                                ui.close();
+                                self.edit_image(ctx, edit);
+                            }
+                        });
+                    });
                 }
 
                 ui.separator();
 
                 // Fit to window
-                if ui
-                    .add_enabled(self.image_tex.is_some(), egui::Button::new("Fit"))
-                    .clicked()
-                {
-                    self.fit_to_window = true;
+                if self.tool_button(ui, has_image, "Fit", Action::Fit) {
+                    self.run_action(ctx, Action::Fit);
                 }
 
                 // 1:1 (100 %)
-                if ui
-                    .add_enabled(self.image_tex.is_some(), egui::Button::new("1:1"))
-                    .clicked()
-                {
-                    self.fit_to_window = false;
-                    self.zoom = 1.0;
+                if self.tool_button(ui, has_image, "1:1", Action::ActualSize) {
+                    self.run_action(ctx, Action::ActualSize);
+                }
+
+                // skutečná fyzická velikost podle DPI v metadatech
+                let has_dpi = has_image && self.metadata.dpi().is_some();
+                if self.tool_button(ui, has_dpi, "Print size", Action::PrintSize) {
+                    self.run_action(ctx, Action::PrintSize);
                 }
 
                 ui.separator();
 
                 // Zoom - / +
-                if ui
-                    .add_enabled(self.image_tex.is_some(), egui::Button::new("Zoom -"))
-                    .clicked()
+                if self.tool_button(ui, has_image, "Zoom -", Action::ZoomOut) {
+                    self.run_action(ctx, Action::ZoomOut);
+                }
+                if self.tool_button(ui, has_image, "Zoom +", Action::ZoomIn) {
+                    self.run_action(ctx, Action::ZoomIn);
+                }
+                let zoom = self.current_scale().unwrap_or(self.view.zoom);
+                ui.label(format!("Zoom: {:.1}×", zoom));
+
+                ui.separator();
+
+                // Otočení
+                if self.tool_button(ui, has_image, "⟲", Action::RotateCcw) {
+                    self.run_action(ctx, Action::RotateCcw);
+                }
+                if self.tool_button(ui, has_image, "⟳", Action::RotateCw) {
+                    self.run_action(ctx, Action::RotateCw);
+                }
+
+                if !kiosk {
+                    ui.separator();
+                    let changed = ui
+                        .add_enabled_ui(has_image, |ui| self.annotator.controls_ui(ui))
+                        .inner;
+                    if changed {
+                        self.save_annotations();
+                    }
+                }
+
+                ui.separator();
+                if self.compare.is_none() {
+                    if ui
+                        .add_enabled(has_image, egui::Button::new("Compare…"))
+                        .on_hover_text(
+                            "Open a second file (CTI, or the PNG/TIFF original) side by side",
+                        )
+                        .clicked()
+                    {
+                        self.open_compare_dialog(ctx);
+                    }
+                } else if ui.button("Close compare").clicked() {
+                    self.compare = None;
+                }
+                if let Some(cmd) = self.compare.as_mut().and_then(|c| c.controls_ui(ui))
+                    && let Err(e) = self.compare_command(ctx, cmd)
                 {
-                    self.fit_to_window = false;
-                    self.zoom = (self.zoom * 0.9).max(0.05);
+                    tracing::error!("compare error: {e:?}");
+                }
+                if !kiosk {
+                    let running = self.batch.as_ref().is_some_and(|j| !j.is_finished());
+                    ui.add_enabled_ui(!running, |ui| {
+                        ui.menu_button("Convert", |ui| {
+                            if ui.button("PNG/TIFF → CTI…").clicked() {
+                                self.show_convert = true;
+                                ui.close();
+                            }
+                            if ui.button("CTI → PNG/TIFF/JPEG…").clicked() {
+                                self.export.prepare(Vec::new(), self.prefs.export_format);
+                                self.show_export = true;
+                                ui.close();
+                            }
+                            ui.separator();
+                            if ui.button("Stitch captures…").clicked() {
+                                self.show_stitch = true;
+                                ui.close();
+                            }
+                        });
+                    });
+                    if ui
+                        .button("Patches…")
+                        .on_hover_text("Monitor QA color patches")
+                        .clicked()
+                    {
+                        self.show_patches = true;
+                    }
+                    let mut redisplay = false;
+                    ui.menu_button("Flat field", |ui| {
+                        if ui
+                            .button("Load reference…")
+                            .on_hover_text("Capture of an evenly lit white target")
+                            .clicked()
+                        {
+                            ui.close();
+                            if let Some(path) = FileDialog::new()
+                                .add_filter("Images", &["cti", "png", "tif", "tiff"])
+                                .pick_file()
+                            {
+                                match FlatField::load(&path) {
+                                    Ok(f) => {
+                                        self.flat = Some(Arc::new(f));
+                                        self.flat_view = true;
+                                        redisplay = true;
+                                    }
+                                    Err(e) => tracing::error!("flat-field error: {e:?}"),
+                                }
+                            }
+                        }
+                        let Some(flat) = self.flat.clone() else {
+                            ui.weak("No reference loaded");
+                            return;
+                        };
+                        redisplay |= ui
+                            .checkbox(&mut self.flat_view, "Apply to view")
+                            .changed();
+                        let name = flat.path.file_name().unwrap_or_default().to_string_lossy();
+                        ui.weak(format!("{name} ({}x{})", flat.width, flat.height));
+                        if let Some(hdr) = &self.last_hdr
+                            && let Err(e) = flat.check(hdr)
+                        {
+                            ui.colored_label(ui.visuals().warn_fg_color, e.to_string());
+                        }
+                        if ui.button("Clear").clicked() {
+                            self.flat = None;
+                            redisplay = true;
+                            ui.close();
+                        }
+                    });
+                    if redisplay && let Err(e) = self.redisplay(ctx) {
+                        tracing::error!("flat-field error: {e:?}");
+                    }
+                }
+                ui.menu_button("Analyze", |ui| {
+                    if ui.button("Noise…").clicked() {
+                        self.show_noise = true;
+                        ui.close();
+                    }
+                    if ui.button("MTF (slanted edge)…").clicked() {
+                        self.show_mtf = true;
+                        ui.close();
+                    }
+                    if ui.button("Measure…").clicked() {
+                        self.show_measure = true;
+                        self.measure.measuring = true;
+                        ui.close();
+                    }
+                    #[cfg(feature = "ocr")]
+                    if ui.button("OCR…").clicked() {
+                        self.show_ocr = true;
+                        ui.close();
+                    }
+                    if self.text.layer.is_some() {
+                        ui.separator();
+                        ui.checkbox(&mut self.text.show_boxes, "Show text boxes");
+                        if ui.button("Find text…").clicked() {
+                            self.run_action(ctx, Action::FindText);
+                            ui.close();
+                        }
+                    }
+                });
+                egui::ComboBox::from_id_salt("background")
+                    .selected_text(self.prefs.background.label())
+                    .show_ui(ui, |ui| {
+                        for b in Background::PRESETS {
+                            ui.selectable_value(&mut self.prefs.background, b, b.label());
+                        }
+                    })
+                    .response
+                    .on_hover_text("Canvas background");
+                let dark = ctx.theme() == egui::Theme::Dark;
+                let (icon, label) = if dark {
+                    ("☀", "Switch to light theme")
+                } else {
+                    ("🌙", "Switch to dark theme")
+                };
+                if a11y::icon_button(ui, icon, label).clicked() {
+                    self.prefs.theme = if dark { Theme::Light } else { Theme::Dark };
+                    self.prefs.apply_appearance(ctx);
+                }
+                if !kiosk && self.tool_button(ui, true, "⚙", Action::Preferences) {
+                    self.run_action(ctx, Action::Preferences);
+                }
+                // počet chyb od posledního otevření okna
+                let errors = logpanel::buffer().unseen_errors();
+                let mut text = egui::RichText::new("Log");
+                if errors > 0 {
+                    text = egui::RichText::new(format!("Log ({errors})"))
+                        .color(ui.visuals().error_fg_color);
                 }
                 if ui
-                    .add_enabled(self.image_tex.is_some(), egui::Button::new("Zoom +"))
+                    .add(egui::Button::new(text).selected(self.show_log))
+                    .on_hover_text("Recent events and errors")
                     .clicked()
                 {
-                    self.fit_to_window = false;
-                    self.zoom = (self.zoom * 1.1).min(50.0);
+                    self.show_log = !self.show_log;
                 }
-                ui.label(format!("Zoom: {:.1}×", self.zoom));
             });
         });
 
-        // Klávesová zkratka Cmd/Ctrl+0 → 1:1
-        if ctx.input_mut(|i| i.consume_key(Modifiers::COMMAND, Key::Num0)) {
-            self.fit_to_window = false;
-            self.zoom = 1.0;
+        // Klávesové zkratky (konfigurovatelné v Preferences)
+        for action in self.shortcuts.triggered(ctx) {
+            self.run_action(ctx, action);
+        }
+        if presenting && ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::Escape)) {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Fullscreen(false));
+        }
+
+        // Cmd/Ctrl+V → obrázek ze schránky do dočasného CTI.
+        // egui-winit stisk Cmd+V spolkne (posílá jen Event::Paste s textem),
+        // proto reagujeme na uvolnění klávesy a zkratka není v mapě.
+        let paste = ctx.input(|i| {
+            i.events.iter().any(|e| {
+                matches!(e, egui::Event::Key { key: egui::Key::V, pressed: false, modifiers, .. }
+                    if modifiers.command)
+            })
+        });
+        // vložený odkaz na pohled (text) se otevře
+        let link = ctx.input(|i| {
+            i.events.iter().find_map(|e| match e {
+                egui::Event::Paste(text) => ViewLink::parse(text).ok(),
+                _ => None,
+            })
+        });
+        if let Some(link) = link.filter(|_| !ctx.wants_keyboard_input()) {
+            self.open_link(ctx, &link);
+        } else if paste && !ctx.wants_keyboard_input() && self.kiosk.is_none() {
+            match clipboard::paste_to_cti() {
+                Ok(path) => self.open_path(ctx, path),
+                Err(e) => tracing::error!("paste error: {e:?}"),
+            }
+        }
+
+        // Cmd/Ctrl+C → výběr (nebo viditelná část obrázku) do schránky;
+        // i tady egui-winit posílá jen Event::Copy.
+        let copy = ctx.input(|i| i.events.iter().any(|e| matches!(e, egui::Event::Copy)));
+        if copy && !ctx.wants_keyboard_input() && self.image_tex.is_some() && self.kiosk.is_none() {
+            self.copy_image();
         }
 
-        // Scroll zoom (egui 0.32 API): posun kolečka mění zoom; také vypne Fit
+        // Scroll zoom (egui 0.32 API): posun kolečka mění zoom kolem kurzoru; také vypne Fit
         if ctx.input(|i| i.raw_scroll_delta.y != 0.0) && self.image_tex.is_some() {
             let delta = ctx.input(|i| i.raw_scroll_delta.y);
             let factor = if delta > 0.0 { 1.1 } else { 0.9 };
-            self.fit_to_window = false;
-            self.zoom = (self.zoom * factor).clamp(0.05, 50.0);
+            let scale = self.current_scale();
+            match (ctx.pointer_hover_pos(), self.image_clip, scale) {
+                (Some(pos), Some(clip), Some(scale)) if clip.contains(pos) => {
+                    self.view.zoom_at(factor, scale, clip, pos);
+                }
+                _ => self.view.zoom_by(factor, scale),
+            }
+        }
+
+        // Levý panel se soubory
+        let mut browser_action = None;
+        egui::SidePanel::left("browser")
+            .resizable(true)
+            .default_width(220.0)
+            .show_animated(ctx, !presenting, |ui| {
+                let running = self.batch.as_ref().is_some_and(|j| !j.is_finished());
+                browser_action = self.browser.ui(ui, self.last_path.as_deref(), running);
+            });
+        match browser_action {
+            Some(BrowserAction::Open(path)) => self.open_path(ctx, path),
+            Some(BrowserAction::OpenInNewWindow(path)) => self.open_in_new_window(ctx, path),
+            Some(BrowserAction::OpenInNewTab(path)) => self.open_in_new_tab(ctx, path),
+            Some(BrowserAction::Batch(action, files)) => {
+                self.batch = Some(BatchJob::spawn(ctx, action, files));
+            }
+            Some(BrowserAction::Export(files)) => {
+                self.export.prepare(files, self.prefs.export_format);
+                self.show_export = true;
+            }
+            None => {}
+        }
+
+        // Ovládání sekvence snímků
+        let mut goto = None;
+        egui::TopBottomPanel::bottom("frames").show_animated(
+            ctx,
+            self.playback.is_sequence() && !presenting,
+            |ui| goto = self.playback.ui(ui),
+        );
+        // Náhledy stránek
+        let show_thumbs = self.playback.is_sequence() && self.playback.thumbnails && !presenting;
+        egui::SidePanel::right("pages")
+            .resizable(true)
+            .default_width(140.0)
+            .show_animated(ctx, show_thumbs, |ui| {
+                if let Some(path) = &self.last_path {
+                    self.thumbs.ensure(ctx, path, self.playback.count);
+                }
+                if let Some(n) = self.thumbs.ui(ui, self.playback.frame) {
+                    goto = Some(n);
+                }
+            });
+        if let Some(frame) = goto
+            && let Err(e) = self.show_frame(ctx, frame)
+        {
+            tracing::error!("frame error: {e:?}");
+        }
+
+        // Karty otevřených souborů (jen když je jich víc)
+        let mut tab_action = None;
+        egui::TopBottomPanel::top("tabs").show_animated(
+            ctx,
+            !self.tabs.is_empty() && !presenting,
+            |ui| {
+                let paths: Vec<_> = (0..self.tabs.len())
+                    .map(|i| {
+                        if i == self.active_tab {
+                            self.last_path.as_deref()
+                        } else {
+                            self.tabs[i].path.as_deref()
+                        }
+                    })
+                    .collect();
+                tab_action = tabs::bar(ui, &paths, self.active_tab);
+            },
+        );
+        match tab_action {
+            Some(TabAction::Select(i)) => self.switch_tab(i),
+            Some(TabAction::Close(i)) => self.close_tab(i),
+            None => {}
         }
 
         // Střední panel s obrázkem
-        egui::CentralPanel::default().show(ctx, |ui| {
-            if let (Some(tex), Some((w, h))) = (&self.image_tex, self.image_size) {
-                let desired_size = if self.fit_to_window {
-                    // Přizpůsobit oknu: neukládej scale do self.zoom, ať 1:1 zůstane přesné při přepnutí
-                    let avail = ui.available_size();
-                    let scale = (avail.x / w as f32).min(avail.y / h as f32);
-                    Vec2::new(w as f32 * scale, h as f32 * scale)
+        let mut central = egui::CentralPanel::default();
+        if presenting {
+            central = central.frame(egui::Frame::new().fill(egui::Color32::BLACK));
+        } else if let Some(fill) = self.prefs.background.fill() {
+            central = central.frame(egui::Frame::central_panel(&ctx.style()).fill(fill));
+        }
+        central.show(ctx, |ui| {
+            if !presenting {
+                self.prefs.background.paint_pattern(ui.painter(), ui.max_rect());
+            }
+            if let (Some(tex), Some(size)) = (&self.image_tex, self.image_size) {
+                let full = ui.max_rect();
+                let resp = ui.allocate_rect(full, egui::Sense::click_and_drag());
+
+                // při porovnání se plocha dělí na dvě poloviny se společným zoomem/posunem
+                // (kromě režimu blikání, kdy se obrázky střídají na celé ploše)
+                let panes = if self.compare.as_ref().is_some_and(|c| !c.blink) {
+                    let (l, r) = full.split_left_right_at_fraction(0.5);
+                    [l.shrink2(Vec2::new(1.0, 0.0)), r.shrink2(Vec2::new(1.0, 0.0))]
                 } else {
-                    Vec2::new(w as f32 * self.zoom, h as f32 * self.zoom)
+                    [full, Rect::NOTHING]
                 };
+                // výřez z odkazu na pohled
+                if let Some(area) = self.pending_region.take() {
+                    self.view.show_region(panes[0], size, area);
+                    self.view.stop_animation();
+                }
+                let (scale, rect) = self.view.animate(ctx, panes[0], size);
+                if let Some(preview) = self.preview_tex.as_ref().filter(|_| self.loader.is_some()) {
+                    view::paint_image(ui, panes[0], preview, rect, self.view.rotation);
+                }
+                match self
+                    .compare
+                    .as_ref()
+                    .filter(|c| c.blink && c.showing_second)
+                {
+                    Some(cmp) => {
+                        let rect2 = self.view.shown_rect(panes[0], cmp.size());
+                        view::paint_image(ui, panes[0], &cmp.tex, rect2, self.view.rotation);
+                    }
+                    None => view::paint_image(ui, panes[0], tex, rect, self.view.rotation),
+                }
+                self.image_rect = Some(rect);
+                self.image_clip = Some(panes[0]);
 
-                // Nové API: Image::new(&TextureHandle).fit_to_exact_size(size)
-                ui.add(egui::Image::new(tex).fit_to_exact_size(desired_size));
+                let rotation = self.view.rotation;
+                if let Some(hdr) = &self.last_hdr
+                    && !self.bad_tiles.is_empty()
+                {
+                    view::paint_bad_tiles(
+                        &ui.painter_at(panes[0]),
+                        hdr,
+                        &self.bad_tiles,
+                        |p| view::image_to_screen(rect, rotation, size, p),
+                    );
+                }
+                if self.diagnose.enabled
+                    && let Some(path) = &self.last_path
+                {
+                    self.diagnose.ensure(ctx, path, self.playback.frame);
+                    self.diagnose.paint(
+                        ui,
+                        &ui.painter_at(panes[0]),
+                        rect,
+                        resp.hover_pos(),
+                        |p| view::image_to_screen(rect, rotation, size, p),
+                        |p| view::screen_to_image(rect, rotation, size, p),
+                    );
+                }
+                if self.tile_grid.visible()
+                    && let Some(path) = &self.last_path
+                {
+                    self.tile_grid.ensure(path, self.playback.frame);
+                    self.tile_grid.paint(
+                        ui,
+                        &ui.painter_at(rect.intersect(panes[0])),
+                        resp.hover_pos().filter(|p| panes[0].contains(*p)),
+                        |p| view::image_to_screen(rect, rotation, size, p),
+                        |p| view::screen_to_image(rect, rotation, size, p),
+                    );
+                }
+                if let Some(loader) = &self.loader {
+                    view::paint_loading(&ui.painter_at(panes[0]), loader.done, loader.total);
+                }
+                if self.show_noise {
+                    self.noise
+                        .paint(&ui.painter_at(rect.intersect(panes[0])), |p| {
+                            view::image_to_screen(rect, rotation, size, p)
+                        });
+                }
+                if self.show_mtf {
+                    self.mtf.paint(&ui.painter_at(panes[0]), |p| {
+                        view::image_to_screen(rect, rotation, size, p)
+                    });
+                }
+                #[cfg(feature = "ocr")]
+                if self.show_ocr {
+                    self.ocr
+                        .paint(&ui.painter_at(rect.intersect(panes[0])), |p| {
+                            view::image_to_screen(rect, rotation, size, p)
+                        });
+                }
+                self.text.paint(
+                    &ui.painter_at(rect.intersect(panes[0])),
+                    self.playback.frame,
+                    |p| view::image_to_screen(rect, rotation, size, p),
+                );
+                if !self.compare.as_ref().is_some_and(|c| c.annotation_diff && !c.blink) {
+                    self.annotator
+                        .paint(&ui.painter_at(panes[0]), scale, |p| {
+                            view::image_to_screen(rect, rotation, size, p)
+                        });
+                }
+                self.selection.paint(&ui.painter_at(panes[0]), |p| {
+                    view::image_to_screen(rect, rotation, size, p)
+                });
+                if self.show_measure {
+                    let dpi = self.metadata.dpi();
+                    self.measure.paint(&ui.painter_at(panes[0]), dpi, |p| {
+                        view::image_to_screen(rect, rotation, size, p)
+                    });
+                }
+                let measuring = self.show_measure && self.measure.measuring;
+                let selecting = self.show_mtf && self.mtf.selecting;
+                if selecting {
+                    if let (Some(hdr), Some(raw)) = (&self.last_hdr, &self.raw) {
+                        self.mtf.handle_input(&resp, (hdr, raw), |pos| {
+                            view::screen_to_image(rect, rotation, size, pos)
+                        });
+                    }
+                } else if measuring {
+                    self.measure.handle_input(&resp, |pos| {
+                        view::screen_to_image(rect, rotation, size, pos)
+                    });
+                } else if self.annotator.enabled {
+                    if self
+                        .annotator
+                        .handle_input(&resp, &self.prefs.author, |pos| {
+                            view::screen_to_image(rect, rotation, size, pos)
+                        })
+                    {
+                        self.save_annotations();
+                    }
+                } else {
+                    // Ctrl/Cmd + tažení přiblíží rámeček, dvojklik přiblíží (se Shiftem oddálí)
+                    if !self.selection.mode {
+                        let target = self.view.scale(panes[0], size);
+                        if let Some(area) = self.zoom_box.handle_input(&resp) {
+                            self.view.zoom_to(target, panes[0], area);
+                        }
+                        if resp.double_clicked()
+                            && let Some(pos) = resp.interact_pointer_pos()
+                        {
+                            let shift = ctx.input(|i| i.modifiers.shift);
+                            let factor = if shift { 0.5 } else { 2.0 };
+                            self.view.zoom_at(factor, target, panes[0], pos);
+                        }
+                    }
+                    self.selection.handle_input(&resp, size, |pos| {
+                        view::screen_to_image(rect, rotation, size, pos)
+                    });
+                }
+                self.zoom_box.paint(&ui.painter_at(panes[0]));
+
+                if let Some(cmp) = self.compare.as_ref().filter(|c| c.blink) {
+                    let (tag, name) = if cmp.showing_second {
+                        ("B", cmp.name())
+                    } else {
+                        ("A", self.file_name())
+                    };
+                    ui.painter().text(
+                        panes[0].left_top() + Vec2::splat(6.0),
+                        egui::Align2::LEFT_TOP,
+                        format!("{tag}: {name} (Space switches)"),
+                        egui::FontId::proportional(13.0),
+                        ui.visuals().strong_text_color(),
+                    );
+                } else if let Some(cmp) = &self.compare {
+                    let rect2 = self.view.shown_rect(panes[1], cmp.size());
+                    view::paint_image(ui, panes[1], cmp.right_tex(), rect2, self.view.rotation);
+                    if cmp.annotation_diff {
+                        // pravé souřadnice → levé podle poměru velikostí
+                        let (cw, ch) = cmp.size();
+                        let k = Vec2::new(size.0 as f32 / cw as f32, size.1 as f32 / ch as f32);
+                        let left = &self.annotator.doc;
+                        let d = AnnotationDiff::compute(left, &cmp.annotations, |p| {
+                            let p = cmp.to_display(p);
+                            egui::pos2(p.x * k.x, p.y * k.y)
+                        });
+                        let to_screen = |r, sz| move |p| view::image_to_screen(r, rotation, sz, p);
+                        annotations::paint_diff(
+                            &ui.painter_at(panes[0]),
+                            left,
+                            &d.left,
+                            scale,
+                            to_screen(rect, size),
+                        );
+                        annotations::paint_diff(
+                            &ui.painter_at(panes[1]),
+                            &cmp.annotations,
+                            &d.right,
+                            scale,
+                            |p| to_screen(rect2, cmp.size())(cmp.to_display(p)),
+                        );
+                        ui.painter().text(
+                            full.center_bottom() - Vec2::new(0.0, 6.0),
+                            egui::Align2::CENTER_BOTTOM,
+                            format!(
+                                "Annotations: +{} added · −{} removed · ↔{} moved · {} unchanged",
+                                d.count(Change::Added),
+                                d.count(Change::Removed),
+                                d.count(Change::Moved),
+                                d.count(Change::Unchanged),
+                            ),
+                            egui::FontId::proportional(13.0),
+                            ui.visuals().strong_text_color(),
+                        );
+                    }
+                    let stroke = ui.visuals().widgets.noninteractive.bg_stroke;
+                    ui.painter().vline(full.center().x, full.y_range(), stroke);
+                    let right = match cmp.diff_view {
+                        diff::DiffView::Off => cmp.name(),
+                        v => format!("{} ({})", v.label(), cmp.name()),
+                    };
+                    for (pane, name) in [(panes[0], self.file_name()), (panes[1], right)] {
+                        ui.painter().text(
+                            pane.left_top() + Vec2::splat(6.0),
+                            egui::Align2::LEFT_TOP,
+                            name,
+                            egui::FontId::proportional(13.0),
+                            ui.visuals().strong_text_color(),
+                        );
+                    }
+                }
+
+                if self.prefs.navigator
+                    && !presenting
+                    && let Some(pan) = view::navigator(ui, panes[0], tex, rect, rotation, 180.0)
+                {
+                    self.view.pan += pan;
+                }
+
+                let (w, h) = size;
+                resp.widget_info(|| {
+                    egui::WidgetInfo::labeled(
+                        egui::WidgetType::Image,
+                        true,
+                        format!("Image {}, {w} by {h} pixels", self.file_name()),
+                    )
+                });
+
+                // tažením se posouvá (mimo režim Fit a kreslení anotací)
+                if resp.dragged()
+                    && !self.view.fit
+                    && !self.annotator.enabled
+                    && !selecting
+                    && !measuring
+                    && !self.selection.mode
+                    && !self.selection.dragging()
+                    && !self.zoom_box.dragging()
+                {
+                    self.view.pan += resp.drag_delta();
+                }
+
+                if self.patch_setup.picking
+                    && resp.clicked()
+                    && let Some(pos) = resp.interact_pointer_pos()
+                    && let Some((x, y)) = self.screen_to_pixel(pos)
+                    && let (Some(hdr), Some(raw)) = (&self.last_hdr, &self.raw)
+                {
+                    self.patch_setup.pick(hdr, raw, x, y);
+                }
             } else {
                 ui.centered_and_justified(|ui| ui.label("Open a .cti file"));
             }
         });
 
         // Info okno
+        let mut save_meta = None;
         if self.show_info {
+            if let Some(path) = &self.last_path
+                && self.file_stats.as_ref().is_none_or(|(p, _)| p != path)
+            {
+                let stats = FileStats::compute(path).map_err(|e| format!("{e:#}"));
+                self.file_stats = Some((path.clone(), stats));
+            }
             egui::Window::new("CTI Info")
                 .collapsible(false)
                 .resizable(true)
                 .open(&mut self.show_info)
                 .show(ctx, |ui| {
                     if let Some(h) = self.last_hdr {
-                        ui.monospace(format!("Version    : {}", h.version));
+                        ui.monospace(format!(
+                            "Version    : {}.{}",
+                            h.version_major(),
+                            h.version_minor()
+                        ));
                         ui.monospace(format!("Size       : {} x {}", h.width, h.height));
+                        if let Some((dx, dy)) = self.metadata.dpi() {
+                            let (wi, hi) = (h.width as f32 / dx, h.height as f32 / dy);
+                            ui.monospace(format!("Resolution : {dx} x {dy} dpi"));
+                            ui.monospace(format!(
+                                "Print size : {:.1} x {:.1} mm  ({wi:.2} x {hi:.2} in)",
+                                wi * 25.4,
+                                hi * 25.4
+                            ));
+                        }
                         ui.monospace(format!(
                             "Tiles      : {} x {}  (tile={})",
                             h.tiles_x, h.tiles_y, h.tile_size
@@ -158,7 +1177,7 @@ impl eframe::App for App {
                         ui.monospace(format!(
                             "ColorType  : {} ({})",
                             h.color_type,
-                            color_name(h.color_type)
+                            cti::color_name(h.color_type)
                         ));
                         let comp = CompressionId::from(h.compression);
                         ui.monospace(format!(
@@ -167,88 +1186,1467 @@ impl eframe::App for App {
                             comp.describe()
                         ));
                         ui.monospace(format!("Quality    : {}", h.quality));
+                        if h.frames > 1 {
+                            ui.monospace(format!(
+                                "Frames     : {}  (showing {})",
+                                h.frames,
+                                self.playback.frame + 1
+                            ));
+                        }
                         ui.monospace(format!(
-                            "Flags      : 0x{:04X}  (RCT:{})",
+                            "Flags      : 0x{:04X}  (RCT:{}, SHA-256:{}, CRC32C:{}, index at end:{})",
                             h.flags,
-                            (h.flags & 1) != 0
+                            (h.flags & 1) != 0,
+                            h.has_file_hash(),
+                            h.has_crc32c(),
+                            h.has_index_at_end()
                         ));
+                        ui.separator();
+                        match self.file_stats.as_ref().map(|(_, s)| s) {
+                            Some(Ok(s)) => {
+                                ui.monospace(format!(
+                                    "File size  : {}",
+                                    dryrun::human(s.file_size)
+                                ));
+                                ui.monospace(format!(
+                                    "Compressed : {} of {}  (ratio {:.2}:1)",
+                                    dryrun::human(s.compressed_size),
+                                    dryrun::human(s.raw_size),
+                                    s.ratio
+                                ));
+                                ui.monospace(format!(
+                                    "Tile size  : {} avg, {} median",
+                                    dryrun::human(s.mean_tile),
+                                    dryrun::human(s.median_tile)
+                                ));
+                                if !s.chunks.is_empty() {
+                                    ui.monospace(format!("Chunks     : {}", s.chunks.join(", ")));
+                                }
+                            }
+                            Some(Err(e)) => {
+                                ui.colored_label(
+                                    ui.visuals().error_fg_color,
+                                    format!("Statistics : {e}"),
+                                );
+                            }
+                            None => {}
+                        }
+                        match (self.decode_time, &self.loader) {
+                            (_, Some(_)) => ui.monospace("Decode time: decoding…"),
+                            (Some(t), None) => ui.monospace(format!(
+                                "Decode time: {:.1} ms",
+                                t.as_secs_f64() * 1000.0
+                            )),
+                            (None, None) => ui.monospace("Decode time: from cache"),
+                        };
+                        if let Some(raw) = &self.raw {
+                            ui.monospace(format!(
+                                "Memory     : {}  (decoded buffer)",
+                                dryrun::human(raw.len() as u64)
+                            ));
+                        }
+                        if !self.bad_tiles.is_empty() {
+                            ui.colored_label(
+                                ui.visuals().error_fg_color,
+                                format!(
+                                    "Damaged    : {} of {} tiles",
+                                    self.bad_tiles.len(),
+                                    h.tiles_x * h.tiles_y
+                                ),
+                            );
+                            ui.collapsing("Damaged tiles", |ui| {
+                                for t in &self.bad_tiles {
+                                    ui.monospace(format!(
+                                        "{},{}: {}",
+                                        t.index % h.tiles_x,
+                                        t.index / h.tiles_x,
+                                        t.error
+                                    ));
+                                }
+                            });
+                        }
+                        ui.separator();
+                        if let Some(meta) = self.metadata_editor.ui(ui, &self.metadata) {
+                            save_meta = Some(meta);
+                        }
                     } else {
                         ui.label("No file loaded.");
                     }
                 });
         }
-    }
-}
+        if let Some(meta) = save_meta {
+            self.save_metadata(meta);
+        }
 
-impl App {
-    fn load_cti(&mut self, ctx: &egui::Context, path: &PathBuf) -> Result<()> {
-        // Načíst hlavičku pro Info
-        let hdr_only = CTIDecoder::info(path)?;
-        let (hdr, raw) =
-            CTIDecoder::decode_file(path).with_context(|| format!("decode {:?}", path))?;
-        debug_assert_eq!(hdr_only.width, hdr.width);
-        self.last_hdr = Some(hdr);
+        if self.show_patches
+            && let Some(session) = self.patch_setup.window(ctx, &mut self.show_patches)
+        {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Fullscreen(true));
+            self.patch_session = Some(session);
+        }
+
+        if self.show_noise {
+            let image = self.last_hdr.as_ref().zip(self.raw.as_deref().map(Vec::as_slice));
+            self.noise.window(ctx, &mut self.show_noise, image);
+        }
+        if self.show_mtf {
+            let image = self.last_hdr.as_ref().zip(self.raw.as_deref().map(Vec::as_slice));
+            self.mtf.window(ctx, &mut self.show_mtf, image);
+        }
+        if self.show_measure {
+            let dpi = self.metadata.dpi();
+            self.measure.window(ctx, &mut self.show_measure, dpi);
+        }
+        #[cfg(feature = "ocr")]
+        if self.show_ocr {
+            let image = self.last_path.as_deref().map(|p| (p, self.playback.frame));
+            self.ocr.window(
+                ctx,
+                &mut self.show_ocr,
+                &mut self.prefs.ocr,
+                image,
+                self.selection.region(),
+            );
+        }
+        if let Some(area) = self.text.find_window(ctx, self.playback.frame) {
+            self.pending_region = Some(area);
+        }
+        #[cfg(feature = "remote")]
+        if self.show_open_url
+            && let Some(url) =
+                remote::open_url_window(ctx, &mut self.show_open_url, &mut self.open_url)
+        {
+            self.open_path(ctx, url);
+        }
+        if self.show_open_link
+            && let Some(link) =
+                handoff::open_link_window(ctx, &mut self.show_open_link, &mut self.open_link)
+        {
+            self.open_link(ctx, &link);
+        }
+
+        if self.show_prefs {
+            let before = self.prefs.clone();
+            let image = self.last_hdr.as_ref().zip(self.raw.as_deref().map(Vec::as_slice));
+            prefs::prefs_window(
+                ctx,
+                &mut self.show_prefs,
+                &mut self.prefs,
+                &mut self.shortcuts,
+                &mut self.updater,
+                &mut self.usage,
+                image,
+            );
+            if (before.theme, before.high_contrast, before.ui_scale)
+                != (self.prefs.theme, self.prefs.high_contrast, self.prefs.ui_scale)
+            {
+                self.prefs.apply_appearance(ctx);
+            }
+            if before.filter != self.prefs.filter
+                && let Err(e) = self.refresh_texture(ctx)
+            {
+                tracing::error!("texture error: {e:?}");
+            }
+            if before.cache_mb != self.prefs.cache_mb {
+                self.cache.set_budget((self.prefs.cache_mb as usize) << 20);
+            }
+            if before.tile_cache_mb != self.prefs.tile_cache_mb {
+                self.tiles
+                    .set_budget((self.prefs.tile_cache_mb as usize) << 20);
+            }
+            if (before.color_management, &before.monitor_profiles)
+                != (self.prefs.color_management, &self.prefs.monitor_profiles)
+            {
+                self.update_display(ctx, true);
+            }
+            if before.cmyk_profile != self.prefs.cmyk_profile {
+                set_cmyk_profile(&self.prefs);
+                if self.image_tex.is_some()
+                    && let Err(e) = self.redisplay(ctx)
+                {
+                    tracing::error!("texture error: {e:?}");
+                }
+            }
+        }
+
+        if self.annotator.show_list
+            && self.annotator.list_window(ctx, self.last_path.as_deref())
+        {
+            self.save_annotations();
+        }
+        if std::mem::take(&mut self.annotator.embed_requested) {
+            self.embed_annotations();
+        }
 
-        let image = match hdr.color_type {
-            1 => {
-                // L8 → RGBA8
-                let mut rgba = Vec::with_capacity((hdr.width * hdr.height * 4) as usize);
-                for &l in &raw {
-                    rgba.extend_from_slice(&[l, l, l, 255]);
+        if self.show_convert
+            && let Some((action, files)) = self.convert.window(
+                ctx,
+                &mut self.show_convert,
+                &self.prefs.presets,
+                &self.prefs.metadata_form,
+            )
+        {
+            self.batch = Some(BatchJob::spawn(ctx, action, files));
+        }
+        if self.show_export
+            && let Some((action, files)) = self.export.window(
+                ctx,
+                &mut self.show_export,
+                &self.prefs.lens_profiles,
+                &self.prefs.presets,
+            )
+        {
+            self.batch = Some(BatchJob::spawn(ctx, action, files));
+        }
+        if self.show_print
+            && let Some((settings, target)) = self.print.window(ctx, &mut self.show_print)
+            && let Err(e) = self.print_image(&settings, &target)
+        {
+            // dialog zůstane otevřený s chybou
+            self.print.error = Some(format!("{e:#}"));
+            self.show_print = true;
+        }
+        if self.show_stitch
+            && let Some((files, out, params)) = self.stitch.window(ctx, &mut self.show_stitch)
+        {
+            let steps = stitch::steps(files.len());
+            self.batch = Some(BatchJob::spawn_task(ctx, "Stitch".into(), steps, move |p| {
+                stitch::stitch_files(&files, &out, &params, &mut |msg| {
+                    p.step(msg);
+                    !p.is_cancelled()
+                })
+            }));
+        }
+
+        if self.show_tools {
+            tools::tools_window(ctx, &mut self.show_tools, &mut self.tools);
+        }
+        if self.show_log {
+            self.log.window(ctx, &mut self.show_log);
+        }
+        if let Some(err) = &self.error
+            && !err.window(ctx, &mut self.show_log)
+        {
+            self.error = None;
+        }
+
+        // Průběh dávkového jobu
+        if let Some(job) = &self.batch {
+            let finished = job.is_finished();
+            if !batch::job_window(ctx, job) {
+                if !finished {
+                    job.cancel();
                 }
-                ColorImage::from_rgba_unmultiplied(
-                    [hdr.width as usize, hdr.height as usize],
-                    &rgba,
-                )
+                self.batch = None;
+                // hodnocení/nové soubory se mohly změnit
+                self.browser.rescan();
             }
-            3 => {
-                // RGB8 → RGBA8
-                let mut rgba = Vec::with_capacity((hdr.width * hdr.height * 4) as usize);
-                for px in raw.chunks_exact(3) {
-                    rgba.extend_from_slice(&[px[0], px[1], px[2], 255]);
+        }
+
+        self.toasts.show(ctx);
+    }
+
+    /// `open` = soubor nebo odkaz na pohled z příkazové řádky.
+    fn new(cc: &eframe::CreationContext<'_>, kiosk: Option<Kiosk>, open: Option<String>) -> Self {
+        let tools = cc
+            .storage
+            .and_then(|s| eframe::get_value(s, TOOLS_KEY))
+            .unwrap_or_else(tools::default_tools);
+        let shortcuts = cc
+            .storage
+            .and_then(|s| eframe::get_value(s, SHORTCUTS_KEY))
+            .unwrap_or_default();
+        let prefs: Preferences = cc
+            .storage
+            .and_then(|s| eframe::get_value(s, PREFS_KEY))
+            .unwrap_or_default();
+        let pages = cc
+            .storage
+            .and_then(|s| eframe::get_value(s, PAGES_KEY))
+            .unwrap_or_default();
+        let usage = cc
+            .storage
+            .and_then(|s| eframe::get_value(s, STATS_KEY))
+            .unwrap_or_default();
+        prefs.apply_appearance(&cc.egui_ctx);
+        set_cmyk_profile(&prefs);
+        logpanel::buffer().set_ctx(&cc.egui_ctx);
+        workdirs::prune_in_background(prefs.temp_files, prefs.disk_cache);
+        update::remove_leftovers();
+        let mut updater = Updater::default();
+        if kiosk.is_none() && prefs.check_updates && !prefs.update_channel.trim().is_empty() {
+            updater.check(&cc.egui_ctx, &prefs.update_channel, false);
+        }
+        // Cmd +/-/0 patří zoomu obrázku, ne zvětšení GUI
+        cc.egui_ctx.options_mut(|o| o.zoom_with_keyboard = false);
+        let mut app = Self {
+            tools,
+            shortcuts,
+            cache: ImageCache::new((prefs.cache_mb as usize) << 20),
+            tiles: TileCache::new((prefs.tile_cache_mb as usize) << 20),
+            prefs,
+            pages,
+            usage,
+            recovery: kiosk.is_none().then(RecoveryPrompt::find).flatten(),
+            updater,
+            ..Default::default()
+        };
+        if let Some(kiosk) = kiosk {
+            app.browser.read_only = true;
+            app.metadata_editor.read_only = true;
+            let start = kiosk.start();
+            app.kiosk = Some(kiosk);
+            if start.is_dir() {
+                app.browser.set_dir(&start);
+            } else if open.is_none() {
+                app.open_path(&cc.egui_ctx, start);
+            }
+        }
+        // nabídka obnovy po pádu má přednost
+        let restore = app.kiosk.is_none() && app.recovery.is_none() && app.prefs.restore_last_view;
+        match open.map(|o| (ViewLink::parse(&o), o)) {
+            Some((Ok(link), _)) => app.open_link(&cc.egui_ctx, &link),
+            Some((Err(_), path)) => app.open_checked(&cc.egui_ctx, PathBuf::from(path)),
+            None if restore => {
+                let last = cc
+                    .storage
+                    .and_then(|s| eframe::get_value::<Option<LastView>>(s, LAST_VIEW_KEY))
+                    .flatten();
+                if let Some(last) = last {
+                    app.restore_last_view(&cc.egui_ctx, last);
                 }
-                ColorImage::from_rgba_unmultiplied(
-                    [hdr.width as usize, hdr.height as usize],
-                    &rgba,
-                )
             }
-            4 => {
-                // RGBA8 (přímo)
-                ColorImage::from_rgba_unmultiplied(
-                    [hdr.width as usize, hdr.height as usize],
-                    &raw,
+            None => {}
+        }
+        app
+    }
+
+    /// Otevře soubor v novém okně. Okno převezme nastavení, zkratky a nástroje; jejich
+    /// změny v něm platí jen do zavření (ukládá se stav hlavního okna).
+    fn open_in_new_window(&mut self, ctx: &egui::Context, path: PathBuf) {
+        let mut app = App {
+            tools: self.tools.clone(),
+            shortcuts: self.shortcuts.clone(),
+            cache: ImageCache::new((self.prefs.cache_mb as usize) << 20),
+            tiles: self.tiles.clone(),
+            prefs: self.prefs.clone(),
+            pages: self.pages.clone(),
+            ..Default::default()
+        };
+        app.open_path(ctx, path);
+        if app.last_path.is_none() {
+            return;
+        }
+        self.next_window += 1;
+        self.windows.push(ChildWindow {
+            id: egui::ViewportId::from_hash_of(("window", self.next_window)),
+            app: Box::new(app),
+        });
+    }
+
+    /// Otevře soubor v nové kartě; prázdná aktivní karta se použije rovnou.
+    fn open_in_new_tab(&mut self, ctx: &egui::Context, path: PathBuf) {
+        if self.tabs.is_empty() {
+            self.tabs.push(Tab::default());
+            self.active_tab = 0;
+        }
+        if self.last_path.is_some() {
+            self.tabs[self.active_tab] = self.stash_tab();
+            self.tabs.push(Tab::default());
+            self.active_tab = self.tabs.len() - 1;
+            self.unstash_tab(Tab::default());
+        }
+        self.open_path(ctx, path);
+        if self.last_path.is_none() && self.tabs.len() > 1 {
+            self.close_tab(self.active_tab);
+        }
+    }
+
+    fn switch_tab(&mut self, i: usize) {
+        if i == self.active_tab || i >= self.tabs.len() {
+            return;
+        }
+        self.tabs[self.active_tab] = self.stash_tab();
+        let next = std::mem::take(&mut self.tabs[i]);
+        self.active_tab = i;
+        self.unstash_tab(next);
+    }
+
+    fn close_tab(&mut self, i: usize) {
+        if i >= self.tabs.len() {
+            return;
+        }
+        self.tabs.remove(i);
+        if i == self.active_tab {
+            // zavřená karta je aktivní: její stav zahodíme a převezmeme sousední
+            drop(self.stash_tab());
+            self.active_tab = i.min(self.tabs.len().saturating_sub(1));
+            let next = self.tabs.get_mut(self.active_tab).map(std::mem::take);
+            self.unstash_tab(next.unwrap_or_default());
+        } else if i < self.active_tab {
+            self.active_tab -= 1;
+        }
+        if self.tabs.len() < 2 {
+            self.tabs.clear();
+            self.active_tab = 0;
+        }
+    }
+
+    /// Vyjme stav aktivního souboru (aplikace zůstane prázdná).
+    fn stash_tab(&mut self) -> Tab {
+        Tab {
+            path: self.last_path.take(),
+            image_tex: self.image_tex.take(),
+            preview_tex: self.preview_tex.take(),
+            image_size: self.image_size.take(),
+            image_rect: self.image_rect.take(),
+            image_clip: self.image_clip.take(),
+            raw: self.raw.take(),
+            loader: self.loader.take(),
+            decode_time: self.decode_time.take(),
+            last_hdr: self.last_hdr.take(),
+            bad_tiles: std::mem::take(&mut self.bad_tiles),
+            metadata: std::mem::take(&mut self.metadata),
+            frame: self.playback.frame,
+            frames: self.playback.count,
+            view: std::mem::take(&mut self.view),
+            compare: self.compare.take(),
+            annotations: std::mem::take(&mut self.annotator.doc),
+            pending_region: self.pending_region.take(),
+        }
+    }
+
+    /// Udělá z `tab` aktivní soubor; panely vázané na předchozí obrázek se vyprázdní.
+    fn unstash_tab(&mut self, tab: Tab) {
+        if let Some(dir) = tab.path.as_deref().and_then(Path::parent)
+            && !tab.path.as_deref().is_some_and(cti::is_url)
+        {
+            self.browser.set_dir(dir);
+        }
+        self.last_path = tab.path;
+        self.image_tex = tab.image_tex;
+        self.preview_tex = tab.preview_tex;
+        self.image_size = tab.image_size;
+        self.image_rect = tab.image_rect;
+        self.image_clip = tab.image_clip;
+        self.raw = tab.raw;
+        self.loader = tab.loader;
+        self.decode_time = tab.decode_time;
+        self.last_hdr = tab.last_hdr;
+        self.bad_tiles = tab.bad_tiles;
+        self.metadata = tab.metadata;
+        if let Some(path) = &self.last_path {
+            self.text.load(path, self.metadata.dpi());
+        }
+        self.playback.reset(tab.frames);
+        self.playback.frame = tab.frame;
+        self.view = tab.view;
+        self.compare = tab.compare;
+        self.annotator.doc = tab.annotations;
+        self.pending_region = tab.pending_region;
+        self.selection.clear();
+        self.thumbs.clear();
+        self.metadata_editor.cancel();
+        self.noise.clear();
+        self.mtf.clear();
+        #[cfg(feature = "ocr")]
+        self.ocr.clear();
+        self.measure.clear();
+    }
+
+    /// Vykreslí další okna; zavřené se zahodí.
+    fn show_windows(&mut self, ctx: &egui::Context) {
+        self.windows.retain_mut(|w| {
+            let title = w.app.last_path.as_deref().and_then(Path::file_name).map_or_else(
+                || "CTI View".to_owned(),
+                |n| format!("{} – CTI View", n.to_string_lossy()),
+            );
+            let builder = egui::ViewportBuilder::default()
+                .with_title(title)
+                .with_inner_size([1000.0, 750.0]);
+            ctx.show_viewport_immediate(w.id, builder, |ctx, _class| {
+                w.app.show(ctx);
+                w.app.show_windows(ctx);
+                !ctx.input(|i| i.viewport().close_requested())
+            })
+        });
+    }
+
+    /// Otevře soubor, v kiosku jen z povolených složek.
+    fn open_checked(&mut self, ctx: &egui::Context, path: PathBuf) {
+        match &self.kiosk {
+            Some(kiosk) if !kiosk.allows(&path) => {
+                tracing::warn!("kiosk: {} is outside the allowed folders", path.display());
+            }
+            _ => self.open_path(ctx, path),
+        }
+    }
+
+    /// Odkaz na to, co je právě vidět (soubor, stránka, výřez, otočení).
+    fn view_link(&self) -> Option<ViewLink> {
+        let file = self.last_path.as_ref()?;
+        let file = if cti::is_url(file) {
+            file.clone()
+        } else {
+            file.canonicalize().unwrap_or_else(|_| file.clone())
+        };
+        let region = (!self.view.fit)
+            .then(|| self.visible_area())
+            .flatten()
+            .map(|r| {
+                (
+                    r.min.x as u32,
+                    r.min.y as u32,
+                    r.width() as u32,
+                    r.height() as u32,
                 )
+            });
+        Some(ViewLink {
+            file,
+            page: self.playback.frame,
+            region,
+            rotation: self.view.rotation,
+        })
+    }
+
+    /// Otevře soubor z odkazu na stejné stránce a stejném místě.
+    fn open_link(&mut self, ctx: &egui::Context, link: &ViewLink) {
+        let dir = self.last_path.as_deref().and_then(Path::parent);
+        let Some(path) = link.resolve(dir) else {
+            tracing::warn!("link: {} not found", link.file.display());
+            return;
+        };
+        if self.last_path.as_ref() != Some(&path) {
+            self.open_checked(ctx, path.clone());
+        }
+        if self.last_path.as_ref() != Some(&path) {
+            return;
+        }
+        if link.page != self.playback.frame
+            && link.page < self.playback.count
+            && let Err(e) = self.show_frame(ctx, link.page)
+        {
+            tracing::error!("frame error: {e:?}");
+        }
+        self.view.rotation = link.rotation % 4;
+        match link.region {
+            Some((x, y, w, h)) => {
+                let min = egui::pos2(x as f32, y as f32);
+                self.pending_region = Some(Rect::from_min_size(min, Vec2::new(w as f32, h as f32)));
             }
-            2 | 5 => {
-                bail!("16-bit preview not implemented yet (L16/RGB16).");
+            None => self.view.set_fit(),
+        }
+    }
+
+    /// Otevřený soubor a pohled pro příští spuštění.
+    fn last_view(&self) -> Option<LastView> {
+        Some(LastView {
+            file: self.last_path.clone()?,
+            frame: self.playback.frame,
+            zoom: (!self.view.fit).then_some(self.view.zoom),
+            pan: self.view.pan.into(),
+            rotation: self.view.rotation,
+        })
+    }
+
+    /// Znovu otevře soubor z minulého spuštění na stejné stránce se stejným zoomem,
+    /// posunem a otočením. Soubor, který mezitím zmizel, se přeskočí.
+    fn restore_last_view(&mut self, ctx: &egui::Context, last: LastView) {
+        if !last.file.exists() && !cti::is_url(&last.file) {
+            return;
+        }
+        self.open_path(ctx, last.file.clone());
+        if self.last_path.as_ref() != Some(&last.file) {
+            return;
+        }
+        if last.frame != self.playback.frame
+            && last.frame < self.playback.count
+            && let Err(e) = self.show_frame(ctx, last.frame)
+        {
+            tracing::error!("frame error: {e:?}");
+        }
+        match last.zoom {
+            Some(zoom) => self.view.set_zoom(zoom),
+            None => self.view.set_fit(),
+        }
+        self.view.pan = last.pan.into();
+        self.view.rotation = last.rotation % 4;
+        self.view.stop_animation();
+    }
+
+    /// Stav pro obnovu po pádu.
+    fn session(&self) -> Session {
+        let batch = self.batch.as_ref().and_then(|j| j.remaining());
+        Session {
+            file: self.last_path.clone(),
+            frame: self.playback.frame,
+            zoom: (!self.view.fit).then_some(self.view.zoom),
+            rotation: self.view.rotation,
+            annotations: self.last_path.as_ref().map(|_| self.annotator.doc.clone()),
+            batch: batch.map(|(action, files)| PendingBatch { action, files }),
+            ..Default::default()
+        }
+    }
+
+    /// Obnoví, co si uživatel v nabídce po pádu vybral.
+    fn restore_session(&mut self, ctx: &egui::Context, prompt: RecoveryPrompt) {
+        let session = prompt.session;
+        if prompt.restore_file
+            && let Some(path) = session.file
+        {
+            self.open_path(ctx, path);
+            if session.frame > 0
+                && let Err(e) = self.show_frame(ctx, session.frame)
+            {
+                tracing::error!("frame error: {e:?}");
+            }
+            match session.zoom {
+                Some(zoom) => self.view.set_zoom(zoom),
+                None => self.view.set_fit(),
             }
-            _ => bail!("Unsupported ColorType ID {}", hdr.color_type),
+            self.view.rotation = session.rotation % 4;
+            if prompt.restore_annotations
+                && let Some(doc) = session.annotations
+            {
+                self.annotator.doc = doc;
+                self.save_annotations();
+            }
+        }
+        if prompt.restore_batch
+            && let Some(batch) = session.batch
+        {
+            self.batch = Some(BatchJob::spawn(ctx, batch.action, batch.files));
+        }
+    }
+
+    /// Tlačítko toolbaru s nápovědou obsahující aktuální zkratku.
+    fn tool_button(&self, ui: &mut egui::Ui, enabled: bool, text: &str, action: Action) -> bool {
+        let hover = self.action_hover(ui.ctx(), action);
+        // jméno pro čtečku = popis akce (text tlačítka může být jen ikona)
+        a11y::icon_button_enabled(ui, enabled, text, &hover).clicked()
+    }
+
+    /// Popis akce s klávesovou zkratkou (tooltip tlačítka).
+    fn action_hover(&self, ctx: &egui::Context, action: Action) -> String {
+        let mut hover = action.label().to_string();
+        if let Some(sc) = self.shortcuts.get(action) {
+            hover = format!("{hover} ({})", ctx.format_shortcut(sc));
+        }
+        hover
+    }
+
+    fn save_annotations(&self) {
+        // u souboru z URL zůstávají anotace jen v paměti
+        if let Some(path) = &self.last_path
+            && !cti::is_url(path)
+            && let Err(e) = self.annotator.doc.save(path)
+        {
+            tracing::error!("annotations error: {e:?}");
+        }
+    }
+
+    /// Zapíše upravená metadata do otevřeného souboru (dlaždice se nepřekódují).
+    /// Vloží anotace do metadat otevřeného CTI (sidecar zůstává).
+    fn embed_annotations(&mut self) {
+        let mut meta = self.metadata.clone();
+        let res = self
+            .annotator
+            .doc
+            .embed_into(&mut meta)
+            .and_then(|()| self.write_metadata(meta));
+        if let Err(e) = res {
+            tracing::error!("annotations error: {e:?}");
+        }
+    }
+
+    fn save_metadata(&mut self, meta: CTIMetadata) {
+        match self.write_metadata(meta) {
+            Ok(()) => self.metadata_editor.cancel(),
+            Err(e) => self.metadata_editor.error = Some(format!("Save failed: {e:#}")),
+        }
+    }
+
+    /// Přepíše metadata otevřeného souboru (bez překódování dlaždic).
+    fn write_metadata(&mut self, meta: CTIMetadata) -> Result<()> {
+        let Some(path) = self.last_path.clone() else {
+            return Ok(());
         };
+        let hdr = cti::rewrite_metadata(&path, &meta)?;
+        self.last_hdr = Some(hdr);
+        if let Some(raw) = &self.raw
+            && self.bad_tiles.is_empty()
+        {
+            self.cache
+                .insert(&path, self.playback.frame, hdr, raw.clone());
+        }
+        self.metadata = meta;
+        Ok(())
+    }
 
-        let tex = ctx.load_texture(
-            "cti-image",
-            image,
-            egui::TextureOptions {
-                magnification: TextureFilter::Linear,
-                minification: TextureFilter::Linear,
-                ..Default::default()
-            },
-        );
-        self.image_tex = Some(tex);
+    /// Pozice na obrazovce → souřadnice pixelu v obrázku (zohledňuje otočení).
+    fn screen_to_pixel(&self, pos: egui::Pos2) -> Option<(u32, u32)> {
+        view::screen_to_pixel(self.image_rect?, self.view.rotation, self.image_size?, pos)
+    }
+
+    /// Skutečné měřítko posledního vykreslení (i v režimu Fit).
+    fn current_scale(&self) -> Option<f32> {
+        Some(self.view.scale(self.image_clip?, self.image_size?))
+    }
+
+    /// Zoom na fyzickou velikost podle DPI v metadatech (u různého DPI X/Y podle X);
+    /// `false` = soubor DPI nemá.
+    fn set_print_size(&mut self, ctx: &egui::Context) -> bool {
+        let Some((dpi, _)) = self.metadata.dpi() else {
+            return false;
+        };
+        let ppp = ctx.pixels_per_point();
+        self.view
+            .set_zoom(view::print_size_zoom(dpi, self.prefs.screen_ppi, ppp));
+        true
+    }
+
+    fn file_name(&self) -> String {
+        self.last_path
+            .as_deref()
+            .and_then(Path::file_name)
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    fn run_action(&mut self, ctx: &egui::Context, action: Action) {
+        let has_image = self.image_tex.is_some();
+        match action {
+            Action::Preferences
+            | Action::SelectMode
+            | Action::ExportSelection
+            | Action::OpenInNewWindow
+            | Action::OpenInNewTab
+                if self.kiosk.is_some() => {}
+            Action::Print if !self.can_print() => {}
+            Action::Open if self.kiosk.is_some() => {
+                let dir = self.last_path.as_deref().and_then(Path::parent);
+                if let Some(path) = self.kiosk.as_ref().and_then(|k| k.pick_file(dir)) {
+                    self.open_path(ctx, path);
+                }
+            }
+            Action::Open => {
+                if let Some(path) = self.pick_file() {
+                    self.open_path(ctx, path);
+                }
+            }
+            Action::OpenInNewWindow => {
+                if let Some(path) = self.pick_file() {
+                    self.open_in_new_window(ctx, path);
+                }
+            }
+            Action::OpenInNewTab => {
+                if let Some(path) = self.pick_file() {
+                    self.open_in_new_tab(ctx, path);
+                }
+            }
+            Action::NextTab | Action::PrevTab if self.tabs.len() > 1 => {
+                let n = self.tabs.len();
+                let step = if action == Action::NextTab { 1 } else { n - 1 };
+                self.switch_tab((self.active_tab + step) % n);
+            }
+            Action::NextTab | Action::PrevTab => {}
+            Action::NextFile | Action::PrevFile => {
+                let delta = if action == Action::NextFile { 1 } else { -1 };
+                if let Some(next) = self
+                    .last_path
+                    .as_deref()
+                    .and_then(|p| self.browser.neighbor(p, delta))
+                {
+                    self.open_path(ctx, next);
+                }
+            }
+            Action::Preferences => self.show_prefs = true,
+            Action::Fullscreen => {
+                let fs = ctx.input(|i| i.viewport().fullscreen.unwrap_or(false));
+                ctx.send_viewport_cmd(egui::ViewportCommand::Fullscreen(!fs));
+            }
+            _ if !has_image => {}
+            Action::Info => self.show_info = true,
+            Action::Diagnose => self.diagnose.enabled = !self.diagnose.enabled,
+            Action::TileGrid => self.tile_grid.enabled = !self.tile_grid.enabled,
+            Action::FindText => self.text.open_find(),
+            Action::Print => self.open_print(),
+            Action::Fit => self.view.set_fit(),
+            Action::ActualSize => self.view.set_actual_size(),
+            Action::PrintSize => {
+                self.set_print_size(ctx);
+            }
+            Action::ZoomIn => {
+                let scale = self.current_scale();
+                self.view.zoom_by(1.1, scale);
+            }
+            Action::ZoomOut => {
+                let scale = self.current_scale();
+                self.view.zoom_by(0.9, scale);
+            }
+            Action::SelectMode => {
+                self.selection.mode ^= true;
+                // výběr a kreslení anotací sdílejí tažení myší
+                if self.selection.mode {
+                    self.annotator.enabled = false;
+                }
+            }
+            Action::ExportSelection => self.export_selection(),
+            Action::Navigator => self.prefs.navigator = !self.prefs.navigator,
+            Action::RotateCw => self.view.rotation = (self.view.rotation + 1) % 4,
+            Action::RotateCcw => self.view.rotation = (self.view.rotation + 3) % 4,
+            Action::PlayPause if self.compare.as_ref().is_some_and(|c| c.blink) => {
+                if let Some(cmp) = &mut self.compare {
+                    cmp.showing_second ^= true;
+                }
+            }
+            Action::NextFrame
+            | Action::PrevFrame
+            | Action::FirstFrame
+            | Action::LastFrame
+            | Action::PlayPause => {
+                let count = self.playback.count as i64;
+                let frame = match action {
+                    Action::NextFrame => self.playback.step(1),
+                    Action::PrevFrame => self.playback.step(-1),
+                    Action::FirstFrame => self.playback.step(-count),
+                    Action::LastFrame => self.playback.step(count),
+                    _ => self.playback.toggle(ctx.input(|i| i.time)),
+                };
+                if let Some(frame) = frame
+                    && let Err(e) = self.show_frame(ctx, frame)
+                {
+                    tracing::error!("frame error: {e:?}");
+                }
+            }
+        }
+    }
+
+    /// Dialog pro výběr souboru ve složce naposledy otevřeného.
+    fn pick_file(&self) -> Option<PathBuf> {
+        let dir = self
+            .last_path
+            .as_deref()
+            .filter(|p| !cti::is_url(p))
+            .and_then(Path::parent)
+            .unwrap_or_else(|| Path::new("."));
+        FileDialog::new()
+            .add_filter("CTI images", &["cti"])
+            .set_directory(dir)
+            .pick_file()
+    }
+
+    fn open_path(&mut self, ctx: &egui::Context, path: PathBuf) {
+        if let Err(e) = self.load_cti(ctx, &path) {
+            tracing::error!("open error: {e:?}");
+            self.error = Some(ErrorReport::new("Cannot open file", Some(&path), &e));
+        } else {
+            if let Some(dir) = path.parent()
+                && !cti::is_url(&path)
+            {
+                self.browser.set_dir(dir);
+            }
+            self.annotator.doc = Annotations::load(&path).unwrap_or_else(|e| {
+                tracing::error!("annotations error: {e:?}");
+                Annotations::default()
+            });
+            if self.prefs.usage_stats
+                && let Some(hdr) = self.last_hdr
+            {
+                let codec = CompressionId::from(hdr.compression).as_str();
+                self.usage.record_open(&path, codec);
+            }
+            self.last_path = Some(path);
+        }
+    }
+
+    /// Soubor z cache se zobrazí hned; jinak se dekóduje na pozadí a dlaždice se do textury
+    /// nahrávají průběžně (viz [`poll_loader`](Self::poll_loader)).
+    /// U vícestránkového souboru se otevře naposledy zobrazená stránka.
+    fn load_cti(&mut self, ctx: &egui::Context, path: &Path) -> Result<()> {
+        let _span = tracing::info_span!("open", path = %path.display()).entered();
+        self.selection.clear();
+        let options = self.texture_options();
+        let info = CTIDecoder::info(path)?;
+        let frame = self
+            .pages
+            .last_page(path)
+            .filter(|&n| n < info.frames)
+            .unwrap_or(0);
+        let hdr = if let Some((hdr, raw)) = self.cache.get(path, frame) {
+            tracing::debug!(frame, "from cache");
+            self.decode_time = None;
+            let span = tracing::debug_span!("texture", w = hdr.width, h = hdr.height).entered();
+            let image = self.display_image(&hdr, &raw)?;
+            self.loader = None;
+            self.image_tex = Some(ctx.load_texture("cti-image", image, options));
+            drop(span);
+            self.preview_tex = None;
+            self.finish_load(ctx, hdr, raw, Vec::new())?;
+            hdr
+        } else {
+            let hdr = info;
+            check_previewable(hdr.color_type)?;
+            let blank = ColorImage::filled(
+                [hdr.width as usize, hdr.height as usize],
+                egui::Color32::TRANSPARENT,
+            );
+            self.image_tex = Some(ctx.load_texture("cti-image", blank, options));
+            self.preview_tex = self.load_preview(ctx, path, frame, &hdr);
+            self.last_hdr = Some(hdr);
+            self.raw = None;
+            self.bad_tiles.clear();
+            let flat = self.flat_for(&hdr);
+            let (path, display) = (path.to_path_buf(), self.display.clone());
+            let tiles = self.tiles.clone();
+            self.loader = Some(Loader::spawn(
+                ctx, path, frame, &hdr, tiles, flat, display,
+            ));
+            hdr
+        };
         self.image_size = Some((hdr.width, hdr.height));
-        self.zoom = 1.0;
-        self.fit_to_window = true; // po otevření defaultně vyplň okno
+        self.playback.reset(hdr.frames);
+        self.playback.frame = frame;
+        self.thumbs.clear();
+        self.metadata_editor.cancel();
+        self.noise.clear();
+        self.mtf.clear();
+        #[cfg(feature = "ocr")]
+        self.ocr.clear();
+        self.measure.clear();
+        self.metadata = CTIDecoder::metadata(path).unwrap_or_else(|e| {
+            tracing::error!("metadata error: {e:?}");
+            CTIMetadata::default()
+        });
+        self.text.load(path, self.metadata.dpi());
+        self.view.rotation = 0;
+        match self.prefs.open_zoom {
+            OpenZoom::Fit => self.view.set_fit(),
+            OpenZoom::ActualSize => self.view.set_actual_size(),
+            OpenZoom::PrintSize => {
+                if !self.set_print_size(ctx) {
+                    self.view.set_fit();
+                }
+            }
+            OpenZoom::Keep => {}
+        }
+        self.view.stop_animation();
+        Ok(())
+    }
+
+    /// Přepne na jiný snímek sekvence. Textura se nezahazuje – nový snímek postupně
+    /// přepíše předchozí, takže přehrávání nebliká.
+    fn show_frame(&mut self, ctx: &egui::Context, frame: u32) -> Result<()> {
+        let (Some(path), Some(hdr)) = (self.last_path.clone(), self.last_hdr) else {
+            return Ok(());
+        };
+        self.playback.frame = frame;
+        self.pages.remember(&path, frame);
+        self.noise.clear();
+        self.mtf.clear();
+        #[cfg(feature = "ocr")]
+        self.ocr.clear();
+        if let Some((hdr, raw)) = self.cache.get(&path, frame) {
+            self.loader = None;
+            self.decode_time = None;
+            let image = self.display_image(&hdr, &raw)?;
+            let options = self.texture_options();
+            if let Some(tex) = &mut self.image_tex {
+                tex.set(image, options);
+            }
+            self.finish_load(ctx, hdr, raw, Vec::new())
+        } else {
+            self.raw = None;
+            self.bad_tiles.clear();
+            let flat = self.flat_for(&hdr);
+            let (tiles, display) = (self.tiles.clone(), self.display.clone());
+            self.loader = Some(Loader::spawn(
+                ctx, path, frame, &hdr, tiles, flat, display,
+            ));
+            Ok(())
+        }
+    }
+
+    /// Uloží dekódovaná data hlavního obrázku a přepočítá porovnání.
+    fn finish_load(
+        &mut self,
+        ctx: &egui::Context,
+        hdr: CTIHeader,
+        raw: Arc<Vec<u8>>,
+        bad: Vec<BadTile>,
+    ) -> Result<()> {
+        self.last_hdr = Some(hdr);
+        self.bad_tiles = bad;
+        self.raw = Some(raw.clone());
+        let options = self.texture_options();
+        if let Some(cmp) = &mut self.compare {
+            let was_aligned = cmp.alignment.is_some();
+            cmp.rebase(ctx, (&hdr, &raw), options)?;
+            if was_aligned {
+                self.reload_compare_texture(ctx)?;
+            }
+        }
         Ok(())
     }
+
+    /// Nahraje nově dekódované dlaždice do textury; po doběhnutí převezme výsledek.
+    fn poll_loader(&mut self, ctx: &egui::Context) {
+        let options = self.texture_options();
+        let (Some(loader), Some(tex)) = (&mut self.loader, &mut self.image_tex) else {
+            return;
+        };
+        let Some(res) = loader.poll(tex, options) else {
+            return;
+        };
+        let (path, frame) = (loader.path.clone(), loader.frame);
+        let took = loader.started.elapsed();
+        let tiles = loader.total;
+        self.loader = None;
+        self.preview_tex = None;
+        if res.is_ok() {
+            self.decode_time = Some(took);
+            let ms = took.as_secs_f64() * 1000.0;
+            tracing::info!(path = %path.display(), frame, tiles, "decoded in {ms:.1} ms");
+        }
+        if self.prefs.usage_stats && res.is_ok() {
+            self.usage.record_decode(took);
+        }
+        let res = res.and_then(|(hdr, raw, bad)| {
+            let raw = Arc::new(raw);
+            if bad.is_empty() {
+                self.cache.insert(&path, frame, hdr, raw.clone());
+                if self.prefs.preview_cache && previews::worth_storing(&hdr) {
+                    previews::store_in_background(path, frame, hdr, raw.clone());
+                }
+            }
+            self.finish_load(ctx, hdr, raw, bad)
+        });
+        if let Err(e) = res {
+            tracing::error!("decode error: {e:?}");
+            let path = self.last_path.clone();
+            self.error = Some(ErrorReport::new("Cannot decode file", path.as_deref(), &e));
+        }
+    }
+
+    /// Přesune sledování na právě otevřený soubor; po dokončeném zápisu soubor znovu načte.
+    fn poll_watcher(&mut self, ctx: &egui::Context) {
+        let path = self
+            .last_path
+            .as_ref()
+            .filter(|p| self.prefs.reload_on_change && !cti::is_url(p));
+        if self.watched.as_ref() != path {
+            self.watched = path.cloned();
+            self.watcher = path.and_then(|p| {
+                FileWatcher::new(ctx, p)
+                    .map_err(|e| tracing::error!("watch error: {e:?}"))
+                    .ok()
+            });
+        }
+        if self.watcher.as_mut().is_some_and(|w| w.poll(ctx)) {
+            self.reload(ctx);
+        }
+    }
+
+    /// Znovu načte otevřený soubor se stejnou stránkou, zoomem, posunem a otočením.
+    fn reload(&mut self, ctx: &egui::Context) {
+        let Some(path) = self.last_path.clone() else {
+            return;
+        };
+        let (view, frame) = (self.view, self.playback.frame);
+        self.diagnose.clear();
+        self.tile_grid.clear();
+        self.file_stats = None;
+        if let Err(e) = self.load_cti(ctx, &path) {
+            tracing::error!("reload error: {e:?}");
+            return;
+        }
+        if frame != self.playback.frame
+            && frame < self.playback.count
+            && let Err(e) = self.show_frame(ctx, frame)
+        {
+            tracing::error!("frame error: {e:?}");
+        }
+        self.view = view;
+        self.view.stop_animation();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        self.toasts.push(ctx, format!("Reloaded {name}"));
+    }
+
+    /// Poškozené dlaždice se nahradí vzorem a vrátí v seznamu; takový obrázek se necachuje.
+    fn decode_cached(&mut self, path: &Path) -> Result<(CTIHeader, Arc<Vec<u8>>, Vec<BadTile>)> {
+        if let Some((hdr, raw)) = self.cache.get(path, 0) {
+            return Ok((hdr, raw, Vec::new()));
+        }
+        // Načíst hlavičku pro Info
+        let hdr_only = CTIDecoder::info(path)?;
+        let (hdr, raw, bad) =
+            CTIDecoder::decode_file_lossy(path).with_context(|| format!("decode {:?}", path))?;
+        debug_assert_eq!(hdr_only.width, hdr.width);
+        let raw = Arc::new(raw);
+        if bad.is_empty() {
+            self.cache.insert(path, 0, hdr, raw.clone());
+        }
+        Ok((hdr, raw, bad))
+    }
+
+    fn open_compare_dialog(&mut self, ctx: &egui::Context) {
+        let dir = self.last_path.as_deref().and_then(Path::parent);
+        let path = match &self.kiosk {
+            Some(kiosk) => kiosk.pick_file(dir),
+            None => FileDialog::new()
+                .add_filter("Images", &["cti", "png", "tif", "tiff"])
+                .set_directory(dir.unwrap_or_else(|| Path::new(".")))
+                .pick_file(),
+        };
+        let Some(path) = path else {
+            return;
+        };
+        if let Err(e) = self.load_compare(ctx, path) {
+            tracing::error!("compare open error: {e:?}");
+        }
+    }
+
+    fn load_compare(&mut self, ctx: &egui::Context, path: PathBuf) -> Result<()> {
+        let is_cti = path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("cti"));
+        let (hdr, raw) = if is_cti {
+            let (hdr, raw, bad) = self.decode_cached(&path)?;
+            if !bad.is_empty() {
+                tracing::warn!("compare: {} damaged tile(s) in {path:?}", bad.len());
+            }
+            (hdr, raw)
+        } else {
+            // reference v jiném formátu (originál před převodem do CTI)
+            let (hdr, raw) = convert::load_raw(&path)?;
+            (hdr, Arc::new(raw))
+        };
+        let mut image = to_color_image(&hdr, &raw)?;
+        self.color_manage(&mut image);
+        let tex = ctx.load_texture("cti-compare", image, self.texture_options());
+        let (Some(base_hdr), Some(base_raw)) = (&self.last_hdr, &self.raw) else {
+            bail!("no image loaded");
+        };
+        self.compare = Some(CompareImage::new(path, hdr, raw, tex, (base_hdr, base_raw)));
+        Ok(())
+    }
+
+    /// Znovu nahraje texturu z RAW dat (např. po změně filtru).
+    fn refresh_texture(&mut self, ctx: &egui::Context) -> Result<()> {
+        if let (Some(hdr), Some(raw)) = (&self.last_hdr, &self.raw) {
+            let image = self.display_image(hdr, raw)?;
+            self.image_tex = Some(ctx.load_texture("cti-image", image, self.texture_options()));
+        }
+        self.reload_compare_texture(ctx)
+    }
+
+    /// Náhled z diskové cache, než se dekódují dlaždice (bez korekce osvětlení, ta by
+    /// se s ním neshodovala).
+    fn load_preview(
+        &self,
+        ctx: &egui::Context,
+        path: &Path,
+        frame: u32,
+        hdr: &CTIHeader,
+    ) -> Option<TextureHandle> {
+        if !self.prefs.preview_cache || !previews::worth_storing(hdr) || self.flat_for(hdr).is_some()
+        {
+            return None;
+        }
+        let (preview, raw) = previews::load(path, frame)?;
+        let mut image = to_color_image(&preview, &raw).ok()?;
+        self.color_manage(&mut image);
+        Some(ctx.load_texture("cti-preview", image, self.texture_options()))
+    }
+
+    /// Reference pro korekci osvětlení zobrazení, pokud je zapnutá a sedí rozměr.
+    fn flat_for(&self, hdr: &CTIHeader) -> Option<Arc<FlatField>> {
+        self.flat
+            .clone()
+            .filter(|f| self.flat_view && f.check(hdr).is_ok())
+    }
+
+    /// Viditelná část obrázku v pixelech (zaokrouhlená na celé pixely).
+    fn visible_area(&self) -> Option<Rect> {
+        let (rect, clip, (w, h)) = (self.image_rect?, self.image_clip?, self.image_size?);
+        let vis = rect.intersect(clip);
+        if !vis.is_positive() {
+            return None;
+        }
+        let r = self.view.rotation;
+        let a = view::screen_to_image(rect, r, (w, h), vis.min);
+        let b = view::screen_to_image(rect, r, (w, h), vis.max);
+        let px = Rect::from_two_pos(a, b);
+        let whole = Rect::from_min_size(egui::Pos2::ZERO, Vec2::new(w as f32, h as f32));
+        Some(Rect::from_min_max(px.min.floor(), px.max.ceil()).intersect(whole))
+    }
+
+    /// Zkopíruje výběr, případně viditelnou část obrázku, do schránky (jak je zobrazený).
+    fn copy_image(&self) {
+        let (Some(hdr), Some(raw)) = (&self.last_hdr, &self.raw) else {
+            return;
+        };
+        let area = self.selection.rect.or_else(|| self.visible_area());
+        let rotation = self.view.rotation;
+        let res = match self.flat_for(hdr) {
+            Some(flat) => flat
+                .apply(hdr, raw)
+                .and_then(|raw| clipboard::copy_image(hdr, &raw, area, rotation)),
+            None => clipboard::copy_image(hdr, raw, area, rotation),
+        };
+        if let Err(e) = res {
+            tracing::error!("copy error: {e:?}");
+        }
+    }
+
+    /// Uloží výběr do nového souboru (PNG, TIFF nebo oříznuté CTI); dekódují se jen
+    /// dlaždice, které výběr protínají.
+    fn export_selection(&self) {
+        let (Some(path), Some(region)) = (&self.last_path, self.selection.region()) else {
+            return;
+        };
+        let stem = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let Some(dst) = FileDialog::new()
+            .add_filter("PNG", &["png"])
+            .add_filter("TIFF", &["tif", "tiff"])
+            .add_filter("CTI", &["cti"])
+            .set_directory(path.parent().unwrap_or_else(|| Path::new(".")))
+            .set_file_name(format!("{stem}_crop.png"))
+            .save_file()
+        else {
+            return;
+        };
+        if let Err(e) = export::export_region(path, self.playback.frame, region, &dst, &self.tiles) {
+            tracing::error!("export error: {e:?}");
+        }
+    }
+
+    /// Spustí externí nástroj na pozadí: export souboru nebo výběru pro `{export}`
+    /// a `{region}` u velkých obrázků trvá.
+    fn launch_tool(&mut self, ctx: &egui::Context, tool: ExternalTool, path: PathBuf) {
+        let selection = self.selection.region().map(|r| (self.playback.frame, r));
+        let tiles = self.tiles.clone();
+        self.batch = Some(BatchJob::spawn_task(ctx, tool.name.clone(), 1, move |_| {
+            tool.launch(&path, selection, &tiles)
+        }));
+    }
+
+    /// Uloží upravený obrázek (všechny snímky) do nového CTI na pozadí.
+    fn edit_image(&mut self, ctx: &egui::Context, edit: Edit) {
+        let Some(path) = self.last_path.clone() else {
+            return;
+        };
+        let stem = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let suffix = match edit {
+            Edit::Crop(..) => "crop",
+            Edit::Rotate(_) => "rotated",
+            Edit::FlipHorizontal | Edit::FlipVertical => "flipped",
+        };
+        let Some(dst) = FileDialog::new()
+            .add_filter("CTI", &["cti"])
+            .set_directory(path.parent().unwrap_or_else(|| Path::new(".")))
+            .set_file_name(format!("{stem}_{suffix}.cti"))
+            .save_file()
+        else {
+            return;
+        };
+        self.batch = Some(BatchJob::spawn_task(ctx, "Edit".into(), 1, move |_| {
+            let (hdr, copied) = cti::edit_file(&path, &dst, edit)?;
+            Ok(format!(
+                "{}: {} × {} px, {copied} tile(s) copied without re-encoding",
+                dst.display(),
+                hdr.width,
+                hdr.height
+            ))
+        }));
+    }
+
+    /// Tisk je v kiosku jen s povolením v konfiguraci.
+    fn can_print(&self) -> bool {
+        self.kiosk.as_ref().is_none_or(|k| k.config.allow_print)
+    }
+
+    fn open_print(&mut self) {
+        let Some(size) = self.image_size else {
+            return;
+        };
+        let title = self
+            .last_path
+            .as_deref()
+            .and_then(Path::file_name)
+            .map_or_else(String::new, |n| n.to_string_lossy().into_owned());
+        let visible = self.visible_area();
+        let dpi = self.metadata.dpi();
+        self.print
+            .prepare(title, size, dpi, self.view.rotation, visible);
+        self.show_print = true;
+    }
+
+    /// Vytiskne zobrazený snímek (včetně korekce osvětlení, je-li zapnutá).
+    fn print_image(&self, settings: &PrintSettings, target: &PrintTarget) -> Result<()> {
+        let (Some(hdr), Some(raw)) = (&self.last_hdr, &self.raw) else {
+            bail!("the image is still loading");
+        };
+        match self.flat_for(hdr) {
+            Some(flat) => print::print(hdr, &flat.apply(hdr, raw)?, settings, target),
+            None => print::print(hdr, raw, settings, target),
+        }
+    }
+
+    /// Obrázek pro texturu hlavního panelu (s korekcí osvětlení, je-li zapnutá).
+    fn display_image(&self, hdr: &CTIHeader, raw: &[u8]) -> Result<ColorImage> {
+        let mut image = match self.flat_for(hdr) {
+            Some(flat) => to_color_image(hdr, &flat.apply(hdr, raw)?)?,
+            None => to_color_image(hdr, raw)?,
+        };
+        self.color_manage(&mut image);
+        Ok(image)
+    }
+
+    /// Převede texturu do profilu monitoru (se zapnutou správou barev).
+    fn color_manage(&self, image: &mut ColorImage) {
+        if let Some(display) = &self.display {
+            display.apply(image);
+        }
+    }
+
+    /// Po přesunu okna na jiný monitor (nebo změně nastavení, `force`) vybere profil
+    /// monitoru a obrázky znovu převede.
+    fn update_display(&mut self, ctx: &egui::Context, force: bool) {
+        let monitor = display::monitor_key(ctx);
+        if !force && monitor == self.monitor {
+            return;
+        }
+        let icc = monitor.as_ref().and_then(|m| {
+            self.prefs
+                .monitor_profiles
+                .iter()
+                .find(|p| self.prefs.color_management && &p.monitor == m)
+                .map(|p| p.icc.clone())
+        });
+        self.monitor = monitor;
+        if !force && icc.as_ref() == self.display.as_ref().map(|d| &d.icc) {
+            return;
+        }
+        self.display = icc.and_then(|icc| match DisplayTransform::load(&icc) {
+            Ok(d) => Some(Arc::new(d)),
+            Err(e) => {
+                tracing::error!("display profile error: {e:?}");
+                None
+            }
+        });
+        if self.image_tex.is_some()
+            && let Err(e) = self.redisplay(ctx)
+        {
+            tracing::error!("texture error: {e:?}");
+        }
+    }
+
+    /// Zobrazí znovu aktuální snímek (po změně korekce osvětlení).
+    fn redisplay(&mut self, ctx: &egui::Context) -> Result<()> {
+        if self.loader.is_some() {
+            // rozpracované dekódování začne znovu s novým nastavením
+            self.show_frame(ctx, self.playback.frame)
+        } else {
+            self.refresh_texture(ctx)
+        }
+    }
+
+    /// Znovu nahraje texturu druhého obrázku i rozdílu (po změně filtru nebo zarovnání).
+    fn reload_compare_texture(&mut self, ctx: &egui::Context) -> Result<()> {
+        let options = self.texture_options();
+        let display = self.display.clone();
+        if let Some(cmp) = &mut self.compare {
+            let mut image = to_color_image(&cmp.hdr, &cmp.raw)?;
+            if let Some(display) = &display {
+                display.apply(&mut image);
+            }
+            cmp.tex = ctx.load_texture("cti-compare", image, options);
+            if let (Some(hdr), Some(raw)) = (&self.last_hdr, &self.raw) {
+                cmp.update_diff(ctx, (hdr, raw), options)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Provede požadavek z ovládání porovnání.
+    fn compare_command(&mut self, ctx: &egui::Context, cmd: CompareCmd) -> Result<()> {
+        if cmd == CompareCmd::Report {
+            self.save_quality_report(ctx);
+            return Ok(());
+        }
+        let options = self.texture_options();
+        let (Some(cmp), Some(hdr), Some(raw)) = (&mut self.compare, &self.last_hdr, &self.raw)
+        else {
+            return Ok(());
+        };
+        match cmd {
+            CompareCmd::Refresh => return cmp.update_diff(ctx, (hdr, raw), options),
+            CompareCmd::Align => {
+                cmp.align((hdr, raw))?;
+            }
+            CompareCmd::ResetAlign => cmp.reset_alignment((hdr, raw)),
+            CompareCmd::Report => {}
+        }
+        self.reload_compare_texture(ctx)
+    }
+
+    /// Zpráva o kvalitě hlavního obrázku vůči porovnávanému (typicky originálu) do JSON;
+    /// dlaždice jsou mřížka hlavního obrázku.
+    fn save_quality_report(&mut self, ctx: &egui::Context) {
+        let (Some(cmp), Some(path), Some(hdr), Some(raw)) =
+            (&self.compare, &self.last_path, self.last_hdr, &self.raw)
+        else {
+            return;
+        };
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let Some(dst) = FileDialog::new()
+            .add_filter("JSON", &["json"])
+            .set_directory(path.parent().unwrap_or(Path::new(".")))
+            .set_file_name(format!("{stem}.metrics.json"))
+            .save_file()
+        else {
+            return;
+        };
+        let reference = (cmp.path.clone(), cmp.hdr, cmp.raw.clone());
+        let (path, raw) = (path.clone(), raw.clone());
+        self.batch = Some(BatchJob::spawn_task(ctx, "Report".into(), 1, move |_| {
+            let report = QualityReport::compute(
+                (reference.0, &reference.1, &reference.2),
+                (path, &hdr, &raw),
+                true,
+                Some(hdr.tile_size),
+            )?;
+            std::fs::write(&dst, serde_json::to_string_pretty(&report)?)?;
+            let m = report.metrics;
+            Ok(format!(
+                "{}: PSNR {:.2} dB, SSIM {:.4}, MS-SSIM {:.4}",
+                dst.display(),
+                m.psnr,
+                m.ssim,
+                report.ms_ssim.unwrap_or_default()
+            ))
+        }));
+    }
+
+    fn texture_options(&self) -> egui::TextureOptions {
+        egui::TextureOptions {
+            magnification: self.prefs.filter.texture_filter(),
+            minification: TextureFilter::Linear,
+            ..Default::default()
+        }
+    }
 }
 
-fn color_name(id: u8) -> &'static str {
-    match id {
-        1 => "L8",
-        2 => "L16",
-        3 => "RGB8",
-        4 => "RGBA8",
-        5 => "RGB16",
-        _ => "Unknown",
+/// Převod CMYK pro zobrazení podle Preferences (chybný profil = naivní převod).
+fn set_cmyk_profile(prefs: &Preferences) {
+    if let Err(e) = cmyk::set_profile(prefs.cmyk_profile.as_deref()) {
+        tracing::error!("CMYK profile error: {e:?}");
     }
 }
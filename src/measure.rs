@@ -0,0 +1,275 @@
+use eframe::egui::{self, Color32, Key, Pos2, Response, Stroke, Vec2};
+
+/// Milimetrů na palec.
+const MM_PER_INCH: f32 = 25.4;
+
+/// Co se měří.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Vzdálenost dvou bodů (tažením).
+    Distance,
+    /// Plocha a obvod mnohoúhelníku (klikáním vrcholů).
+    Area,
+}
+
+/// Okno měření vzdáleností a ploch; se záznamem DPI v metadatech i v mm a palcích.
+/// Body jsou v souřadnicích neotočeného obrázku.
+pub struct MeasurePanel {
+    /// Tažením (vzdálenost) nebo klikáním (plocha) v obrázku se měří.
+    pub measuring: bool,
+    mode: Mode,
+    points: Vec<Pos2>,
+    /// Mnohoúhelník je uzavřený (dvojklik nebo Enter).
+    closed: bool,
+    dragging: bool,
+}
+
+impl Default for MeasurePanel {
+    fn default() -> Self {
+        Self {
+            measuring: false,
+            mode: Mode::Distance,
+            points: Vec::new(),
+            closed: false,
+            dragging: false,
+        }
+    }
+}
+
+impl MeasurePanel {
+    /// Zahodí měření (po otevření jiného souboru).
+    pub fn clear(&mut self) {
+        self.points.clear();
+        self.closed = false;
+        self.dragging = false;
+    }
+
+    /// Úseky lomené čáry (u uzavřeného tvaru i zpět k prvnímu bodu).
+    fn edges(&self) -> Vec<Vec2> {
+        let mut edges: Vec<Vec2> = self.points.windows(2).map(|w| w[1] - w[0]).collect();
+        if self.closed
+            && let (Some(first), Some(last)) = (self.points.first(), self.points.last())
+        {
+            edges.push(*first - *last);
+        }
+        edges
+    }
+
+    /// `dpi` = rozlišení (x, y) z metadat, pokud je známé.
+    pub fn window(&mut self, ctx: &egui::Context, open: &mut bool, dpi: Option<(f32, f32)>) {
+        egui::Window::new("Measure")
+            .resizable(false)
+            .open(open)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    let hover = match self.mode {
+                        Mode::Distance => "Drag a line in the image",
+                        Mode::Area => {
+                            "Click the corners in the image; double-click or Enter closes"
+                        }
+                    };
+                    ui.toggle_value(&mut self.measuring, "Measure")
+                        .on_hover_text(hover);
+                    for (mode, label) in [(Mode::Distance, "Distance"), (Mode::Area, "Area")] {
+                        if ui.radio_value(&mut self.mode, mode, label).changed() {
+                            self.clear();
+                        }
+                    }
+                    if ui
+                        .add_enabled(!self.points.is_empty(), egui::Button::new("Clear"))
+                        .clicked()
+                    {
+                        self.clear();
+                    }
+                });
+                ui.separator();
+                match self.mode {
+                    Mode::Distance if self.points.len() == 2 => {
+                        let d = self.points[1] - self.points[0];
+                        ui.monospace(format!("Length : {}", length_text(&[d], dpi)));
+                        ui.monospace(format!("Δx, Δy : {:.1}, {:.1} px", d.x, d.y));
+                        ui.monospace(format!("Angle  : {:.1}°", (-d.y).atan2(d.x).to_degrees()));
+                    }
+                    Mode::Area if self.points.len() >= 3 => {
+                        ui.monospace(format!("Area      : {}", area_text(&self.points, dpi)));
+                        ui.monospace(format!("Perimeter : {}", length_text(&self.edges(), dpi)));
+                        if !self.closed {
+                            ui.weak("Double-click or press Enter to close the shape.");
+                        }
+                    }
+                    Mode::Distance => {
+                        ui.weak("Turn on Measure and drag a line in the image.");
+                    }
+                    Mode::Area => {
+                        ui.weak("Turn on Measure and click at least three corners.");
+                    }
+                }
+                match dpi {
+                    Some((x, y)) if x == y => {
+                        ui.weak(format!("Units from the DPI metadata ({x} dpi)."));
+                    }
+                    Some((x, y)) => {
+                        ui.weak(format!("Units from the DPI metadata ({x} × {y} dpi)."));
+                    }
+                    None => {
+                        ui.weak("No DPI metadata: pixels only.");
+                    }
+                }
+            });
+        if !*open {
+            self.measuring = false;
+        }
+    }
+
+    /// Zpracuje vstup nad obrázkem; `to_image` převádí pozici na obrazovce do souřadnic
+    /// obrázku. Esc měření zruší.
+    pub fn handle_input(&mut self, resp: &Response, to_image: impl Fn(Pos2) -> Pos2) {
+        let pos = resp.interact_pointer_pos().map(to_image);
+        let (enter, escape) = resp
+            .ctx
+            .input(|i| (i.key_pressed(Key::Enter), i.key_pressed(Key::Escape)));
+        if escape {
+            self.clear();
+        }
+        match self.mode {
+            Mode::Distance => {
+                if resp.drag_started()
+                    && let Some(p) = pos
+                {
+                    self.points = vec![p, p];
+                    self.dragging = true;
+                }
+                if self.dragging
+                    && let Some(p) = pos
+                {
+                    self.points[1] = p;
+                }
+                if resp.drag_stopped() {
+                    self.dragging = false;
+                }
+            }
+            Mode::Area => {
+                // klik přidá vrchol (po uzavření začne nový tvar), dvojklik nebo Enter uzavře
+                if resp.clicked()
+                    && let Some(p) = pos
+                {
+                    if self.closed {
+                        self.clear();
+                    }
+                    if self.points.last().is_none_or(|l| l.distance(p) > 0.5) {
+                        self.points.push(p);
+                    }
+                }
+                if (resp.double_clicked() || enter) && self.points.len() >= 3 {
+                    self.closed = true;
+                }
+            }
+        }
+    }
+
+    /// Vykreslí měřenou úsečku nebo mnohoúhelník s popiskem (se známým DPI v mm).
+    pub fn paint(
+        &self,
+        painter: &egui::Painter,
+        dpi: Option<(f32, f32)>,
+        to_screen: impl Fn(Pos2) -> Pos2,
+    ) {
+        if self.points.is_empty() {
+            return;
+        }
+        let pts: Vec<Pos2> = self.points.iter().map(|&p| to_screen(p)).collect();
+        let stroke = Stroke::new(1.5, Color32::from_rgb(255, 200, 0));
+        let label = |pos: Pos2, text: String| {
+            let galley =
+                painter.layout_no_wrap(text, egui::FontId::proportional(12.0), Color32::WHITE);
+            let rect = egui::Align2::CENTER_BOTTOM
+                .anchor_size(pos - Vec2::new(0.0, 6.0), galley.size())
+                .expand(2.0);
+            painter.rect_filled(rect, 2.0, Color32::from_black_alpha(160));
+            painter.galley(rect.shrink(2.0).min, galley, Color32::WHITE);
+        };
+        match self.mode {
+            Mode::Distance => {
+                let [a, b] = [pts[0], pts[pts.len() - 1]];
+                painter.line_segment([a, b], stroke);
+                // příčné značky na koncích
+                let n = (b - a).normalized().rot90() * 5.0;
+                for p in [a, b] {
+                    painter.line_segment([p - n, p + n], stroke);
+                }
+                let d = self.points[self.points.len() - 1] - self.points[0];
+                if d.length() >= 1.0 {
+                    let text = match dpi {
+                        Some(dpi) => format!("{:.1} mm", inches(d, dpi) * MM_PER_INCH),
+                        None => format!("{:.1} px", d.length()),
+                    };
+                    label(a.lerp(b, 0.5), text);
+                }
+            }
+            Mode::Area => {
+                let mut line = pts.clone();
+                if self.closed {
+                    line.push(pts[0]);
+                }
+                painter.add(egui::Shape::line(line, stroke));
+                for &p in &pts {
+                    painter.circle_filled(p, 2.5, stroke.color);
+                }
+                if self.points.len() >= 3 {
+                    let c = pts.iter().fold(Vec2::ZERO, |s, p| s + p.to_vec2()) / pts.len() as f32;
+                    let px = shoelace(&self.points).abs();
+                    let text = match dpi {
+                        Some((dx, dy)) => {
+                            let mm2 = px / (dx * dy) * MM_PER_INCH * MM_PER_INCH;
+                            format!("{mm2:.0} mm²")
+                        }
+                        None => format!("{px:.0} px²"),
+                    };
+                    label(c.to_pos2(), text);
+                }
+            }
+        }
+    }
+}
+
+/// Délka úsečky `d` (v pixelech) v palcích; DPI v ose X a Y se může lišit.
+fn inches(d: Vec2, (dx, dy): (f32, f32)) -> f32 {
+    Vec2::new(d.x / dx, d.y / dy).length()
+}
+
+/// Délka lomené čáry (úseky `edges`) v pixelech a se známým DPI i v mm a palcích.
+fn length_text(edges: &[Vec2], dpi: Option<(f32, f32)>) -> String {
+    let px: f32 = edges.iter().map(|d| d.length()).sum();
+    let Some(dpi) = dpi else {
+        return format!("{px:.1} px");
+    };
+    let inch: f32 = edges.iter().map(|&d| inches(d, dpi)).sum();
+    format!("{px:.1} px = {:.2} mm = {inch:.3} in", inch * MM_PER_INCH)
+}
+
+/// Plocha v pixelech a se známým DPI i v mm² (nad 100 cm² v cm²) a čtverečních palcích.
+fn area_text(points: &[Pos2], dpi: Option<(f32, f32)>) -> String {
+    let px = shoelace(points).abs();
+    let Some((dx, dy)) = dpi else {
+        return format!("{px:.0} px²");
+    };
+    let sq_in = px / (dx * dy);
+    let mm2 = sq_in * MM_PER_INCH * MM_PER_INCH;
+    if mm2 >= 10_000.0 {
+        format!("{px:.0} px² = {:.2} cm² = {sq_in:.2} in²", mm2 / 100.0)
+    } else {
+        format!("{px:.0} px² = {mm2:.1} mm² = {sq_in:.3} in²")
+    }
+}
+
+/// Orientovaná plocha mnohoúhelníku (Gaussův vzorec), pro tvar bez protínajících se stran.
+fn shoelace(points: &[Pos2]) -> f32 {
+    let n = points.len();
+    (0..n)
+        .map(|i| {
+            let (a, b) = (points[i], points[(i + 1) % n]);
+            a.x * b.y - b.x * a.y
+        })
+        .sum::<f32>()
+        / 2.0
+}
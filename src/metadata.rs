@@ -0,0 +1,153 @@
+use eframe::egui;
+
+use crate::annotations;
+use crate::cti::CTIMetadata;
+
+/// Pole digitalizace, která se v Info zobrazují (a v editoru nabízejí) vždy.
+const FIELDS: [&str; 4] = [
+    CTIMetadata::CAPTURE_DATE,
+    CTIMetadata::DEVICE,
+    CTIMetadata::OPERATOR,
+    CTIMetadata::DPI,
+];
+
+/// Blok metadat v okně Info s možností úprav.
+#[derive(Default)]
+pub struct MetadataEditor {
+    /// Rozpracované položky; `None` = jen zobrazení.
+    draft: Option<Vec<(String, String)>>,
+    pub error: Option<String>,
+    /// Bez úprav (kiosek).
+    pub read_only: bool,
+}
+
+impl MetadataEditor {
+    /// Zahodí rozpracované úpravy (např. po otevření jiného souboru).
+    pub fn cancel(&mut self) {
+        self.draft = None;
+        self.error = None;
+    }
+
+    /// Vrátí nová metadata po kliknutí na Save.
+    pub fn ui(&mut self, ui: &mut egui::Ui, meta: &CTIMetadata) -> Option<CTIMetadata> {
+        ui.horizontal(|ui| {
+            ui.strong("Metadata");
+            if self.draft.is_none() && !self.read_only && ui.small_button("Edit").clicked() {
+                let mut draft = meta.entries.clone();
+                for key in FIELDS.iter().rev() {
+                    if meta.get(key).is_none() {
+                        draft.insert(0, (key.to_string(), String::new()));
+                    }
+                }
+                self.draft = Some(draft);
+            }
+        });
+        match &mut self.draft {
+            None => {
+                view_ui(ui, meta);
+                None
+            }
+            Some(draft) => {
+                let save = edit_ui(ui, draft);
+                if let Some(e) = &self.error {
+                    ui.colored_label(ui.visuals().error_fg_color, e);
+                }
+                match save {
+                    Some(true) => {
+                        let meta = CTIMetadata {
+                            entries: draft
+                                .iter()
+                                .map(|(k, v)| (k.trim().to_string(), v.clone()))
+                                .filter(|(k, v)| !k.is_empty() && !v.is_empty())
+                                .collect(),
+                        };
+                        if meta.get(CTIMetadata::DPI).is_some() && meta.dpi().is_none() {
+                            self.error = Some("DPI must be a number such as 300 or 300x600".into());
+                            return None;
+                        }
+                        Some(meta)
+                    }
+                    Some(false) => {
+                        self.cancel();
+                        None
+                    }
+                    None => None,
+                }
+            }
+        }
+    }
+}
+
+fn view_ui(ui: &mut egui::Ui, meta: &CTIMetadata) {
+    for key in FIELDS {
+        ui.monospace(format!("{key:<11}: {}", meta.get(key).unwrap_or("—")));
+    }
+    for (k, v) in &meta.entries {
+        if !FIELDS.contains(&k.as_str()) && k != CTIMetadata::XMP {
+            ui.monospace(format!("{k:<11}: {v}"));
+        }
+    }
+    if let Some(xmp) = meta.get(CTIMetadata::XMP) {
+        ui.collapsing(format!("XMP ({} B)", xmp.len()), |ui| {
+            let text = egui::RichText::new(xmp).monospace();
+            egui::ScrollArea::vertical()
+                .max_height(240.0)
+                .show(ui, |ui| ui.add(egui::Label::new(text).selectable(true)));
+        });
+    }
+}
+
+/// Tabulka klíč/hodnota; `Some(true)` = uložit, `Some(false)` = zrušit.
+fn edit_ui(ui: &mut egui::Ui, draft: &mut Vec<(String, String)>) -> Option<bool> {
+    let mut remove = None;
+    egui::Grid::new("metadata-edit")
+        .num_columns(3)
+        .spacing([8.0, 4.0])
+        .show(ui, |ui| {
+            for (i, (k, v)) in draft.iter_mut().enumerate() {
+                ui.add(egui::TextEdit::singleline(k).desired_width(110.0));
+                if k.as_str() == CTIMetadata::XMP {
+                    ui.add(
+                        egui::TextEdit::multiline(v)
+                            .code_editor()
+                            .desired_rows(4)
+                            .desired_width(280.0),
+                    );
+                } else {
+                    ui.add(egui::TextEdit::singleline(v).desired_width(280.0));
+                }
+                ui.horizontal(|ui| {
+                    if k.as_str() == CTIMetadata::CAPTURE_DATE
+                        && ui
+                            .small_button("Now")
+                            .on_hover_text("Current time (UTC)")
+                            .clicked()
+                    {
+                        *v = annotations::now_rfc3339();
+                    }
+                    if ui.small_button("🗑").on_hover_text("Remove field").clicked() {
+                        remove = Some(i);
+                    }
+                });
+                ui.end_row();
+            }
+        });
+    if let Some(i) = remove {
+        draft.remove(i);
+    }
+    let mut result = None;
+    ui.horizontal(|ui| {
+        if ui.button("Add field").clicked() {
+            draft.push((String::new(), String::new()));
+        }
+        ui.separator();
+        if ui.button("Save").clicked() {
+            result = Some(true);
+        }
+        if ui.button("Cancel").clicked() {
+            result = Some(false);
+        }
+    });
+    ui.weak("Empty fields are dropped. Tile data is not re-encoded.");
+    result
+}
@@ -0,0 +1,239 @@
+use anyhow::{Result, bail};
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::cti::CTIMetadata;
+use crate::presets;
+
+/// Nápověda k masce hodnoty.
+pub const PATTERN_HELP: &str = "# digit, A letter, ? any character, * any text, \\ literal next \
+     character; empty = anything";
+
+/// Pole formuláře metadat vyplňovaného při převodu do CTI.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FormField {
+    /// Klíč v bloku metadat.
+    pub key: String,
+    /// Předvyplněná hodnota; smí obsahovat zástupné symboly předvoleb (`{stem}`, `{date}` …).
+    pub template: String,
+    pub required: bool,
+    /// Maska hodnoty (viz [`PATTERN_HELP`]).
+    pub pattern: String,
+    /// Povolené hodnoty oddělené čárkou; prázdné = volný text.
+    pub choices: String,
+}
+
+impl Default for FormField {
+    fn default() -> Self {
+        Self {
+            key: "Field".into(),
+            template: String::new(),
+            required: false,
+            pattern: String::new(),
+            choices: String::new(),
+        }
+    }
+}
+
+/// Výchozí formulář: instituce, sbírka, signatura, operátor.
+pub fn default_form() -> Vec<FormField> {
+    let field = |key: &str, template: &str, required: bool| FormField {
+        key: key.into(),
+        template: template.into(),
+        required,
+        ..Default::default()
+    };
+    vec![
+        field("Institution", "", true),
+        field("Collection", "", false),
+        field("Shelfmark", "{stem}", true),
+        field(CTIMetadata::OPERATOR, "{user}", true),
+    ]
+}
+
+impl FormField {
+    pub fn choices(&self) -> Vec<&str> {
+        self.choices
+            .split(',')
+            .map(str::trim)
+            .filter(|c| !c.is_empty())
+            .collect()
+    }
+
+    /// Zkontroluje hotovou hodnotu (po dosazení zástupných symbolů).
+    pub fn check(&self, value: &str) -> Result<()> {
+        let value = value.trim();
+        if value.is_empty() {
+            if self.required {
+                bail!("{}: required", self.key);
+            }
+            return Ok(());
+        }
+        let choices = self.choices();
+        if !choices.is_empty() && !choices.contains(&value) {
+            bail!(
+                "{}: {value:?} is not one of {}",
+                self.key,
+                choices.join(", ")
+            );
+        }
+        if !self.pattern.is_empty() && !matches(&self.pattern, value) {
+            bail!("{}: {value:?} does not match {:?}", self.key, self.pattern);
+        }
+        Ok(())
+    }
+
+    /// Chyba už při vyplňování; hodnoty se zástupnými symboly se kontrolují až u souboru.
+    fn check_entry(&self, value: &str) -> Option<String> {
+        if value.contains('{') {
+            return presets::check_template(value)
+                .err()
+                .map(|e| format!("{e:#}"));
+        }
+        self.check(value).err().map(|e| format!("{e:#}"))
+    }
+}
+
+/// Zkontroluje metadata souboru podle všech polí formuláře.
+pub fn validate(form: &[FormField], meta: &CTIMetadata) -> Result<()> {
+    for f in form {
+        f.check(meta.get(&f.key).unwrap_or_default())?;
+    }
+    Ok(())
+}
+
+/// Porovná hodnotu s maskou (celou, ne jen část).
+fn matches(pattern: &str, value: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let v: Vec<char> = value.chars().collect();
+    match_at(&p, &v)
+}
+
+fn match_at(p: &[char], v: &[char]) -> bool {
+    match p {
+        [] => v.is_empty(),
+        ['*', rest @ ..] => (0..=v.len()).any(|i| match_at(rest, &v[i..])),
+        ['\\', c, rest @ ..] => v.first() == Some(c) && match_at(rest, &v[1..]),
+        [c, rest @ ..] => {
+            let Some(&x) = v.first() else {
+                return false;
+            };
+            let ok = match c {
+                '#' => x.is_ascii_digit(),
+                'A' => x.is_alphabetic(),
+                '?' => true,
+                c => *c == x,
+            };
+            ok && match_at(rest, &v[1..])
+        }
+    }
+}
+
+/// Vyplňování formuláře v dialogu převodu; vrátí `false`, pokud některé pole neprojde.
+pub fn form_ui(
+    ui: &mut egui::Ui,
+    form: &[FormField],
+    values: &mut BTreeMap<String, String>,
+) -> bool {
+    let mut valid = true;
+    egui::Grid::new("metadata-form")
+        .num_columns(3)
+        .spacing([8.0, 4.0])
+        .show(ui, |ui| {
+            for f in form {
+                let value = values
+                    .entry(f.key.clone())
+                    .or_insert_with(|| f.template.clone());
+                let label = if f.required {
+                    format!("{} *", f.key)
+                } else {
+                    f.key.clone()
+                };
+                ui.label(label);
+                let choices = f.choices();
+                if choices.is_empty() {
+                    ui.add(egui::TextEdit::singleline(value).desired_width(240.0))
+                        .on_hover_text(presets::METADATA_HELP);
+                } else {
+                    egui::ComboBox::from_id_salt(("metadata-form", &f.key))
+                        .width(240.0)
+                        .selected_text(value.as_str())
+                        .show_ui(ui, |ui| {
+                            for c in choices {
+                                ui.selectable_value(value, c.to_string(), c);
+                            }
+                        });
+                }
+                match f.check_entry(value) {
+                    Some(e) => {
+                        valid = false;
+                        ui.colored_label(ui.visuals().error_fg_color, e);
+                    }
+                    None => {
+                        ui.label("");
+                    }
+                }
+                ui.end_row();
+            }
+        });
+    valid
+}
+
+/// Nastavení polí formuláře v Preferences.
+pub fn config_ui(ui: &mut egui::Ui, form: &mut Vec<FormField>) {
+    let mut remove = None;
+    let mut swap = None;
+    for (i, f) in form.iter_mut().enumerate() {
+        egui::CollapsingHeader::new(f.key.clone())
+            .id_salt(("form-field", i))
+            .show(ui, |ui| {
+                egui::Grid::new(("form-field-grid", i))
+                    .num_columns(2)
+                    .spacing([12.0, 4.0])
+                    .show(ui, |ui| {
+                        ui.label("Key");
+                        ui.text_edit_singleline(&mut f.key);
+                        ui.end_row();
+                        ui.label("Default");
+                        ui.text_edit_singleline(&mut f.template)
+                            .on_hover_text(presets::METADATA_HELP);
+                        ui.end_row();
+                        ui.label("Pattern");
+                        ui.text_edit_singleline(&mut f.pattern)
+                            .on_hover_text(PATTERN_HELP);
+                        ui.end_row();
+                        ui.label("Choices");
+                        ui.text_edit_singleline(&mut f.choices)
+                            .on_hover_text("Comma-separated; empty = free text");
+                        ui.end_row();
+                        ui.label("");
+                        ui.checkbox(&mut f.required, "Required");
+                        ui.end_row();
+                    });
+                ui.horizontal(|ui| {
+                    if ui.small_button("⏶").on_hover_text("Move up").clicked() && i > 0 {
+                        swap = Some(i - 1);
+                    }
+                    if ui.button("Remove").clicked() {
+                        remove = Some(i);
+                    }
+                });
+            });
+    }
+    if let Some(i) = swap {
+        form.swap(i, i + 1);
+    }
+    if let Some(i) = remove {
+        form.remove(i);
+    }
+    ui.horizontal(|ui| {
+        if ui.button("Add field").clicked() {
+            form.push(FormField::default());
+        }
+        if ui.button("Reset to defaults").clicked() {
+            *form = default_form();
+        }
+    });
+}
@@ -0,0 +1,492 @@
+use anyhow::{Result, bail, ensure};
+use eframe::egui::{self, Color32, Pos2, Rect, Response, Stroke, Vec2};
+use rustfft::FftPlanner;
+use rustfft::num_complex::Complex32;
+
+use crate::align::Luma;
+use crate::cti::CTIHeader;
+
+/// Převzorkování ESF (binů na pixel), jako v ISO 12233.
+const OVERSAMPLE: usize = 4;
+/// Nejmenší rozměr výběru v pixelech.
+const MIN_ROI: usize = 20;
+/// Nejmenší kontrast hrany (rozdíl jasů 0..1).
+const MIN_CONTRAST: f64 = 0.05;
+/// Největší odklon hrany od svislice/vodorovné ve stupních.
+const MAX_TILT: f64 = 25.0;
+/// Největší odchylka polohy hrany v řádcích od proložené přímky (px).
+const MAX_EDGE_RMS: f64 = 1.0;
+/// Okno hledání hrany na zmenšenině (posouvá se o polovinu).
+const DETECT_WIN: usize = 32;
+/// Delší strana zmenšeniny pro hledání hrany.
+const DETECT_SIZE: usize = 1024;
+
+/// Výsledek měření MTF (SFR) ze šikmé hrany.
+#[derive(Debug, Clone)]
+pub struct Mtf {
+    /// Frekvence v cyklech na pixel (0..1).
+    pub freq: Vec<f32>,
+    pub mtf: Vec<f32>,
+    pub mtf50: Option<f32>,
+    pub mtf10: Option<f32>,
+    /// MTF na Nyquistově frekvenci (0,5 cy/px).
+    pub nyquist: f32,
+    /// Odklon hrany od osy ve stupních.
+    pub angle: f32,
+    /// `true` = svislá hrana (měří se vodorovné rozlišení).
+    pub vertical: bool,
+    pub roi: Rect,
+}
+
+impl Mtf {
+    /// Změří MTF hrany ve výběru `roi` (v pixelech obrázku).
+    ///
+    /// Postup podle ISO 12233: poloha hrany v každém řádku (těžiště derivace), lineární
+    /// proložení, 4× převzorkovaná ESF podle kolmé vzdálenosti od hrany, derivace (LSF)
+    /// s Hammingovým oknem a DFT normalizovaná na nulovou frekvenci.
+    pub fn measure(hdr: &CTIHeader, raw: &[u8], roi: Rect) -> Result<Self> {
+        let luma = Luma::new(hdr, raw)?;
+        let roi = roi.intersect(Rect::from_min_size(
+            Pos2::ZERO,
+            Vec2::new(luma.w as f32, luma.h as f32),
+        ));
+        let (x0, y0) = (roi.min.x.max(0.0) as usize, roi.min.y.max(0.0) as usize);
+        let (x1, y1) = (roi.max.x as usize, roi.max.y as usize);
+        ensure!(
+            x1 >= x0 + MIN_ROI && y1 >= y0 + MIN_ROI,
+            "Selection is too small (at least {MIN_ROI}×{MIN_ROI} px)"
+        );
+        let (w, h) = (x1 - x0, y1 - y0);
+        let px: Vec<f64> = (y0..y1)
+            .flat_map(|y| (x0..x1).map(move |x| (x, y)))
+            .map(|(x, y)| luma.at(x, y) as f64)
+            .collect();
+
+        // svislá hrana = větší změny ve směru x; jinak se výběr transponuje
+        let (mut sx, mut sy) = (0f64, 0f64);
+        for y in 0..h - 1 {
+            for x in 0..w - 1 {
+                let p = px[y * w + x];
+                sx += (px[y * w + x + 1] - p).abs();
+                sy += (px[(y + 1) * w + x] - p).abs();
+            }
+        }
+        let vertical = sx >= sy;
+        let (rows, cols) = if vertical { (h, w) } else { (w, h) };
+        let at = |r: usize, c: usize| {
+            if vertical {
+                px[r * w + c]
+            } else {
+                px[c * w + r]
+            }
+        };
+
+        let mean = |c: usize| (0..rows).map(|r| at(r, c)).sum::<f64>() / rows as f64;
+        let polarity = if mean(cols - 1) >= mean(0) { 1.0 } else { -1.0 };
+        let (lo, hi) = (0..cols)
+            .map(mean)
+            .fold((f64::MAX, f64::MIN), |(a, b), v| (a.min(v), b.max(v)));
+        ensure!(hi - lo >= MIN_CONTRAST, "Edge contrast is too low");
+
+        // poloha hrany v řádcích a lineární proložení c = a + b·r
+        let centers: Vec<(f64, f64)> = (0..rows)
+            .filter_map(|r| {
+                let (mut s, mut sc) = (0f64, 0f64);
+                for c in 1..cols - 1 {
+                    let d = ((at(r, c + 1) - at(r, c - 1)) * polarity).max(0.0);
+                    s += d;
+                    sc += d * c as f64;
+                }
+                (s > 0.0).then(|| (r as f64, sc / s))
+            })
+            .collect();
+        ensure!(centers.len() >= MIN_ROI, "No edge found in the selection");
+        let (a, b) = fit_line(&centers);
+        let rms = (centers
+            .iter()
+            .map(|&(r, c)| (c - a - b * r).powi(2))
+            .sum::<f64>()
+            / centers.len() as f64)
+            .sqrt();
+        ensure!(
+            rms <= MAX_EDGE_RMS,
+            "Selection does not contain a single straight edge"
+        );
+        let angle = b.atan().to_degrees();
+        if angle.abs() > MAX_TILT {
+            bail!("Edge is tilted {angle:.1}°; use a near-vertical or near-horizontal edge");
+        }
+        ensure!(
+            (b * rows as f64).abs() >= 1.0,
+            "Edge is too close to the pixel grid ({angle:.2}°); tilt it a few degrees or select a longer edge"
+        );
+
+        // ESF v binech 1/4 px podle kolmé vzdálenosti od hrany
+        let cos = 1.0 / (1.0 + b * b).sqrt();
+        let margin = (0..rows)
+            .map(|r| {
+                let e = a + b * r as f64;
+                e.min(cols as f64 - 1.0 - e)
+            })
+            .fold(f64::MAX, f64::min)
+            * cos;
+        let half = margin.floor() as usize;
+        ensure!(half >= 4, "Edge is too close to the selection border");
+        let n = 2 * half * OVERSAMPLE;
+        let (mut sum, mut count) = (vec![0f64; n], vec![0u32; n]);
+        for r in 0..rows {
+            for c in 0..cols {
+                let d = (c as f64 - a - b * r as f64) * cos;
+                let bin = ((d + half as f64) * OVERSAMPLE as f64).floor();
+                if bin >= 0.0 && (bin as usize) < n {
+                    sum[bin as usize] += at(r, c);
+                    count[bin as usize] += 1;
+                }
+            }
+        }
+        let esf = fill_bins(&sum, &count)?;
+
+        // LSF s Hammingovým oknem kolem těžiště
+        let mut lsf = vec![0f64; n];
+        for i in 1..n - 1 {
+            lsf[i] = (esf[i + 1] - esf[i - 1]) / 2.0 * polarity;
+        }
+        let total: f64 = lsf.iter().sum();
+        ensure!(total > 0.0, "No edge found in the selection");
+        let center = lsf
+            .iter()
+            .enumerate()
+            .map(|(i, v)| i as f64 * v)
+            .sum::<f64>()
+            / total;
+        let span = center.max(n as f64 - 1.0 - center).max(1.0);
+        let mut buf: Vec<Complex32> = lsf
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                let t = (i as f64 - center) / span;
+                let win = 0.54 + 0.46 * (std::f64::consts::PI * t).cos();
+                Complex32::new((v * win) as f32, 0.0)
+            })
+            .collect();
+        FftPlanner::<f32>::new()
+            .plan_fft_forward(n)
+            .process(&mut buf);
+
+        let dc = buf[0].norm().max(f32::EPSILON);
+        let step = OVERSAMPLE as f32 / n as f32;
+        let (mut freq, mut mtf) = (Vec::new(), Vec::new());
+        for (k, v) in buf.iter().enumerate().take(n / 2 + 1) {
+            let f = k as f32 * step;
+            if f > 1.0 {
+                break;
+            }
+            // korekce odezvy centrální diference (krok 1/4 px)
+            let x = 2.0 * std::f32::consts::PI * f / OVERSAMPLE as f32;
+            let corr = if x > 0.0 {
+                (x / x.sin()).min(10.0)
+            } else {
+                1.0
+            };
+            freq.push(f);
+            mtf.push(v.norm() / dc * corr);
+        }
+
+        Ok(Self {
+            mtf50: crossing(&freq, &mtf, 0.5),
+            mtf10: crossing(&freq, &mtf, 0.1),
+            nyquist: interpolate(&freq, &mtf, 0.5),
+            freq,
+            mtf,
+            angle: angle as f32,
+            vertical,
+            roi: Rect::from_min_max(
+                Pos2::new(x0 as f32, y0 as f32),
+                Pos2::new(x1 as f32, y1 as f32),
+            ),
+        })
+    }
+
+    /// Najde v obrázku šikmou hranu a změří ji.
+    pub fn detect(hdr: &CTIHeader, raw: &[u8]) -> Result<Self> {
+        let luma = Luma::new(hdr, raw)?;
+        let step = luma.w.max(luma.h).div_ceil(DETECT_SIZE).max(1);
+        let g = luma.downsample(step);
+        let mut candidates = Vec::new();
+        let stride = DETECT_WIN / 2;
+        for wy in (0..g.h.saturating_sub(DETECT_WIN)).step_by(stride) {
+            for wx in (0..g.w.saturating_sub(DETECT_WIN)).step_by(stride) {
+                let (mut jxx, mut jyy, mut jxy, mut mag) = (0f32, 0f32, 0f32, 0f32);
+                let (mut cx, mut cy) = (0f32, 0f32);
+                for y in wy + 1..wy + DETECT_WIN - 1 {
+                    for x in wx + 1..wx + DETECT_WIN - 1 {
+                        let gx = (g.at(x + 1, y) - g.at(x - 1, y)) / 2.0;
+                        let gy = (g.at(x, y + 1) - g.at(x, y - 1)) / 2.0;
+                        jxx += gx * gx;
+                        jyy += gy * gy;
+                        jxy += gx * gy;
+                        let m = (gx * gx + gy * gy).sqrt();
+                        mag += m;
+                        cx += m * (x - wx) as f32;
+                        cy += m * (y - wy) as f32;
+                    }
+                }
+                if mag <= 0.0 {
+                    continue;
+                }
+                let trace = jxx + jyy;
+                let coherence = ((jxx - jyy).powi(2) + 4.0 * jxy * jxy).sqrt() / trace;
+                // úhel gradientu → odklon hrany od nejbližší osy
+                let theta = 0.5 * (2.0 * jxy).atan2(jxx - jyy);
+                let tilt = (theta.to_degrees().rem_euclid(90.0))
+                    .min(90.0 - theta.to_degrees().rem_euclid(90.0));
+                let mid = DETECT_WIN as f32 / 2.0;
+                let off = Vec2::new(cx / mag - mid, cy / mag - mid).length();
+                if coherence > 0.8 && (1.5..=15.0).contains(&tilt) && off < mid / 3.0 {
+                    let n = ((DETECT_WIN - 2) * (DETECT_WIN - 2)) as f32;
+                    candidates.push((coherence * coherence * mag / n, wx, wy));
+                }
+            }
+        }
+        candidates.sort_by(|a, b| b.0.total_cmp(&a.0));
+        // výběr se zkusí zvětšit, dokud obsahuje jen tu jednu hranu
+        for &(_, wx, wy) in candidates.iter().take(8) {
+            let center = Pos2::new(
+                ((wx + DETECT_WIN / 2) * step) as f32,
+                ((wy + DETECT_WIN / 2) * step) as f32,
+            );
+            for k in [4, 3, 2, 1] {
+                let roi =
+                    Rect::from_center_size(center, Vec2::splat((k * DETECT_WIN * step) as f32));
+                if let Ok(m) = Self::measure(hdr, raw, roi) {
+                    return Ok(m);
+                }
+            }
+        }
+        bail!("No slanted edge found; select one manually")
+    }
+}
+
+/// Lineární regrese `y = a + b·x`.
+fn fit_line(pts: &[(f64, f64)]) -> (f64, f64) {
+    let n = pts.len() as f64;
+    let (sx, sy) = pts
+        .iter()
+        .fold((0.0, 0.0), |(sx, sy), &(x, y)| (sx + x, sy + y));
+    let (mx, my) = (sx / n, sy / n);
+    let (mut sxy, mut sxx) = (0.0, 0.0);
+    for &(x, y) in pts {
+        sxy += (x - mx) * (y - my);
+        sxx += (x - mx) * (x - mx);
+    }
+    let b = if sxx > 0.0 { sxy / sxx } else { 0.0 };
+    (my - b * mx, b)
+}
+
+/// Průměry binů; prázdné biny se doplní lineární interpolací sousedů.
+fn fill_bins(sum: &[f64], count: &[u32]) -> Result<Vec<f64>> {
+    let known: Vec<usize> = (0..sum.len()).filter(|&i| count[i] > 0).collect();
+    ensure!(known.len() >= 2, "Not enough samples along the edge");
+    let value = |i: usize| sum[i] / count[i] as f64;
+    Ok((0..sum.len())
+        .map(|i| {
+            if count[i] > 0 {
+                return value(i);
+            }
+            let next = known.partition_point(|&k| k < i);
+            match (next.checked_sub(1).map(|p| known[p]), known.get(next)) {
+                (Some(l), Some(&r)) => {
+                    let t = (i - l) as f64 / (r - l) as f64;
+                    value(l) * (1.0 - t) + value(r) * t
+                }
+                (Some(l), None) => value(l),
+                (None, Some(&r)) => value(r),
+                (None, None) => 0.0,
+            }
+        })
+        .collect())
+}
+
+/// První frekvence, kde MTF klesne pod `level`.
+fn crossing(freq: &[f32], mtf: &[f32], level: f32) -> Option<f32> {
+    mtf.windows(2).zip(freq.windows(2)).find_map(|(m, f)| {
+        (m[0] >= level && m[1] < level)
+            .then(|| f[0] + (f[1] - f[0]) * (m[0] - level) / (m[0] - m[1]))
+    })
+}
+
+fn interpolate(freq: &[f32], mtf: &[f32], f: f32) -> f32 {
+    let i = freq.partition_point(|&x| x < f);
+    match (i.checked_sub(1), freq.get(i)) {
+        (Some(l), Some(&fr)) => {
+            let t = (f - freq[l]) / (fr - freq[l]);
+            mtf[l] * (1.0 - t) + mtf[i] * t
+        }
+        (None, Some(_)) => mtf[0],
+        _ => mtf.last().copied().unwrap_or(0.0),
+    }
+}
+
+/// Okno měření MTF: výběr hrany tažením v obrázku nebo automatické hledání.
+#[derive(Default)]
+pub struct MtfPanel {
+    /// Tažením v obrázku se vybírá oblast s hranou.
+    pub selecting: bool,
+    drag: Option<(Pos2, Pos2)>,
+    result: Option<Result<Mtf, String>>,
+}
+
+impl MtfPanel {
+    /// Zahodí výsledek (po otevření jiného souboru).
+    pub fn clear(&mut self) {
+        self.result = None;
+        self.drag = None;
+    }
+
+    pub fn window(
+        &mut self,
+        ctx: &egui::Context,
+        open: &mut bool,
+        image: Option<(&CTIHeader, &[u8])>,
+    ) {
+        egui::Window::new("MTF (slanted edge)")
+            .resizable(false)
+            .open(open)
+            .show(ctx, |ui| {
+                ui.add_enabled_ui(image.is_some(), |ui| {
+                    ui.horizontal(|ui| {
+                        ui.toggle_value(&mut self.selecting, "Select edge")
+                            .on_hover_text("Drag a rectangle around a slanted edge in the image");
+                        if ui.button("Find edge").clicked()
+                            && let Some((hdr, raw)) = image
+                        {
+                            self.result = Some(Mtf::detect(hdr, raw).map_err(|e| e.to_string()));
+                        }
+                    });
+                });
+                match &self.result {
+                    None => {
+                        ui.weak(
+                            "Select a dark/light edge tilted 2–10° from vertical or horizontal.",
+                        );
+                    }
+                    Some(Err(e)) => {
+                        ui.colored_label(ui.visuals().error_fg_color, e);
+                    }
+                    Some(Ok(m)) => result_ui(ui, m),
+                }
+            });
+        if !*open {
+            self.selecting = false;
+        }
+    }
+
+    /// Tažení v obrázku; po puštění změří vybranou oblast.
+    pub fn handle_input(
+        &mut self,
+        resp: &Response,
+        image: (&CTIHeader, &[u8]),
+        to_image: impl Fn(Pos2) -> Pos2,
+    ) {
+        let pos = resp.interact_pointer_pos().map(to_image);
+        if resp.drag_started()
+            && let Some(p) = pos
+        {
+            self.drag = Some((p, p));
+        }
+        if let Some((_, b)) = &mut self.drag
+            && let Some(p) = pos
+        {
+            *b = p;
+        }
+        if resp.drag_stopped()
+            && let Some((a, b)) = self.drag.take()
+        {
+            let roi = Rect::from_two_pos(a, b);
+            self.result = Some(Mtf::measure(image.0, image.1, roi).map_err(|e| e.to_string()));
+            self.selecting = false;
+        }
+    }
+
+    /// Vykreslí vybranou (nebo právě taženou) oblast.
+    pub fn paint(&self, painter: &egui::Painter, to_screen: impl Fn(Pos2) -> Pos2) {
+        let roi = match (&self.drag, &self.result) {
+            (Some((a, b)), _) => Rect::from_two_pos(*a, *b),
+            (None, Some(Ok(m))) => m.roi,
+            _ => return,
+        };
+        let rect = Rect::from_two_pos(to_screen(roi.min), to_screen(roi.max));
+        painter.rect_stroke(
+            rect,
+            0.0,
+            Stroke::new(1.5, Color32::YELLOW),
+            egui::StrokeKind::Outside,
+        );
+    }
+}
+
+fn result_ui(ui: &mut egui::Ui, m: &Mtf) {
+    let fmt = |v: Option<f32>| v.map_or("—".into(), |v| format!("{v:.3} cy/px"));
+    ui.monospace(format!("MTF50      : {}", fmt(m.mtf50)));
+    ui.monospace(format!("MTF10      : {}", fmt(m.mtf10)));
+    ui.monospace(format!("At Nyquist : {:.1} %", m.nyquist * 100.0));
+    ui.monospace(format!(
+        "Edge       : {} {:+.1}°, {}×{} px",
+        if m.vertical { "vertical" } else { "horizontal" },
+        m.angle,
+        m.roi.width(),
+        m.roi.height()
+    ));
+    plot(ui, m);
+}
+
+/// Graf MTF (0..1 cy/px) s vyznačením Nyquistovy frekvence a úrovně 50 %.
+fn plot(ui: &mut egui::Ui, m: &Mtf) {
+    let (resp, painter) = ui.allocate_painter(Vec2::new(320.0, 180.0), egui::Sense::hover());
+    let r = resp.rect.shrink2(Vec2::new(28.0, 14.0));
+    let top = m.mtf.iter().copied().fold(1.0f32, f32::max).min(1.5);
+    let to =
+        |f: f32, v: f32| Pos2::new(r.left() + f * r.width(), r.bottom() - v / top * r.height());
+    let visuals = ui.visuals();
+    let grid = Stroke::new(1.0, visuals.widgets.noninteractive.bg_stroke.color);
+    let text = visuals.weak_text_color();
+    let font = egui::FontId::monospace(10.0);
+    for i in 0..=4 {
+        let f = i as f32 / 4.0;
+        painter.line_segment([to(f, 0.0), to(f, top)], grid);
+        painter.text(
+            to(f, 0.0) + Vec2::new(0.0, 2.0),
+            egui::Align2::CENTER_TOP,
+            format!("{f}"),
+            font.clone(),
+            text,
+        );
+    }
+    for v in [0.0, 0.5, 1.0] {
+        painter.line_segment([to(0.0, v), to(1.0, v)], grid);
+        painter.text(
+            to(0.0, v) - Vec2::new(4.0, 0.0),
+            egui::Align2::RIGHT_CENTER,
+            format!("{v}"),
+            font.clone(),
+            text,
+        );
+    }
+    let accent = visuals.selection.bg_fill;
+    painter.add(egui::Shape::dashed_line(
+        &[to(0.5, 0.0), to(0.5, top)],
+        Stroke::new(1.0, text),
+        4.0,
+        3.0,
+    ));
+    let points: Vec<Pos2> = m
+        .freq
+        .iter()
+        .zip(&m.mtf)
+        .map(|(&f, &v)| to(f, v.min(top)))
+        .collect();
+    painter.add(egui::Shape::line(points, Stroke::new(2.0, accent)));
+    if let Some(f) = m.mtf50 {
+        painter.circle_filled(to(f, 0.5), 3.5, accent);
+    }
+}
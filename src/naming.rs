@@ -0,0 +1,302 @@
+use anyhow::{Context, Result, anyhow, bail, ensure};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::OpenOptions;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use crate::annotations;
+
+/// Zástupné symboly šablony jména (pro nápovědu v UI a CLI).
+// {n} se v nápovědě nepíše: clap ho v textu nápovědy nahradí koncem řádku.
+pub const PATTERN_HELP: &str = "{stem} (or {source_stem}) source file name without extension, \
+     {dir} parent folder, {index} position in the batch (0001…), {page} page number, {barcode} code read \
+     from the image (on export the Identifier metadata), {date} today, {time} now (HHMMSS, UTC), {w}/{h} size, {depth} bits per \
+     channel; numbers take a width, e.g. {page:04}";
+
+/// Co udělat, když cílový soubor už existuje.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Collision {
+    /// Soubor nahlásit jako chybu.
+    #[default]
+    Fail,
+    /// Soubor přeskočit (existující zůstane).
+    Skip,
+    Overwrite,
+    /// Přidat k jménu `_2`, `_3` … až na volné jméno.
+    Number,
+}
+
+impl Collision {
+    pub const ALL: [Collision; 4] = [
+        Collision::Fail,
+        Collision::Skip,
+        Collision::Overwrite,
+        Collision::Number,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Collision::Fail => "Fail",
+            Collision::Skip => "Skip",
+            Collision::Overwrite => "Overwrite",
+            Collision::Number => "Add number",
+        }
+    }
+}
+
+/// Co by [`OutputName::write`] s cílem udělal (pro `--dry-run`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Planned {
+    New(PathBuf),
+    Overwrite(PathBuf),
+    Skip(PathBuf),
+}
+
+/// Hodnoty zástupných symbolů pro jeden výstup; `None` = v tomto místě nejsou k dispozici.
+#[derive(Debug, Clone, Default)]
+pub struct NameVars<'a> {
+    pub src: Option<&'a Path>,
+    /// Pořadí souboru v dávce (od 1).
+    pub n: Option<usize>,
+    /// Stránka (snímek) souboru (od 1).
+    pub page: Option<usize>,
+    pub barcode: Option<&'a str>,
+    pub size: Option<(u32, u32)>,
+    pub depth: Option<u8>,
+}
+
+impl<'a> NameVars<'a> {
+    pub fn new(src: &'a Path) -> Self {
+        Self {
+            src: Some(src),
+            ..Default::default()
+        }
+    }
+
+    /// Ukázkové hodnoty všech symbolů (kontrola šablony před spuštěním dávky).
+    fn sample() -> Self {
+        Self {
+            src: Some(Path::new("dir/stem.png")),
+            n: Some(1),
+            page: Some(1),
+            barcode: Some("code"),
+            size: Some((1, 1)),
+            depth: Some(8),
+        }
+    }
+}
+
+/// Šablona jména výstupního souboru (bez přípony) a chování při kolizi.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutputName {
+    /// Šablona, viz [`PATTERN_HELP`].
+    pub pattern: String,
+    pub collision: Collision,
+}
+
+impl Default for OutputName {
+    fn default() -> Self {
+        Self {
+            pattern: "{stem}".into(),
+            collision: Collision::Fail,
+        }
+    }
+}
+
+impl OutputName {
+    pub fn new(pattern: impl Into<String>, collision: Collision) -> Self {
+        Self {
+            pattern: pattern.into(),
+            collision,
+        }
+    }
+
+    /// Kontrola šablony ještě před spuštěním dávky (neznámé symboly, neuzavřené závorky).
+    pub fn check(&self) -> Result<()> {
+        render(&self.pattern, &NameVars::sample()).map(|_| ())
+    }
+
+    /// Používá šablona symbol `key` (např. `barcode` nebo `page`)?
+    pub fn uses(&self, key: &str) -> bool {
+        let mut used = false;
+        let _ = expand(&self.pattern, |k| {
+            used |= k.split(':').next() == Some(key);
+            Some(String::new())
+        });
+        used
+    }
+
+    /// Dosadí do šablony, vyřeší kolizi v `dir` a zapíše výstup funkcí `write`.
+    /// Vrací zapsanou cestu, nebo `None`, když byl existující soubor přeskočen.
+    /// Nepovedený zápis po sobě nenechá prázdný (zabraný) soubor.
+    pub fn write(
+        &self,
+        dir: &Path,
+        ext: &str,
+        vars: &NameVars,
+        write: impl FnOnce(&Path) -> Result<()>,
+    ) -> Result<Option<PathBuf>> {
+        let name = render(&self.pattern, vars)?;
+        std::fs::create_dir_all(dir).with_context(|| format!("create {}", dir.display()))?;
+        let Some((path, claimed)) = self.claim(dir, &name, ext)? else {
+            return Ok(None);
+        };
+        match write(&path) {
+            Ok(()) => Ok(Some(path)),
+            Err(e) => {
+                if claimed {
+                    let _ = std::fs::remove_file(&path);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Cíl, který by zvolil [`write`](Self::write), ale bez zápisu; `taken` = jména
+    /// naplánovaná dřív v téže dávce (kolize mezi soubory dávky se tak projeví také).
+    pub fn plan(
+        &self,
+        dir: &Path,
+        ext: &str,
+        vars: &NameVars,
+        taken: &mut HashSet<PathBuf>,
+    ) -> Result<Planned> {
+        let name = render(&self.pattern, vars)?;
+        let occupied = |p: &Path, taken: &HashSet<PathBuf>| p.exists() || taken.contains(p);
+        for k in 1.. {
+            let path = match k {
+                1 => dir.join(format!("{name}.{ext}")),
+                k => dir.join(format!("{name}_{k}.{ext}")),
+            };
+            if !occupied(&path, taken) {
+                taken.insert(path.clone());
+                return Ok(Planned::New(path));
+            }
+            match self.collision {
+                Collision::Fail if path.exists() => bail!("{} already exists", path.display()),
+                Collision::Fail => bail!("{} is also the output of another file", path.display()),
+                Collision::Skip => return Ok(Planned::Skip(path)),
+                Collision::Overwrite => return Ok(Planned::Overwrite(path)),
+                Collision::Number => {}
+            }
+        }
+        unreachable!()
+    }
+
+    /// Zabere cílové jméno vytvořením prázdného souboru, takže ho souběžně běžící
+    /// soubory dávky nemohou dostat také; vrací cestu a zda byla zabrána.
+    fn claim(&self, dir: &Path, name: &str, ext: &str) -> Result<Option<(PathBuf, bool)>> {
+        let path = dir.join(format!("{name}.{ext}"));
+        if self.collision == Collision::Overwrite {
+            return Ok(Some((path, false)));
+        }
+        for k in 1.. {
+            let path = match k {
+                1 => path.clone(),
+                k => dir.join(format!("{name}_{k}.{ext}")),
+            };
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(_) => return Ok(Some((path, true))),
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => match self.collision {
+                    Collision::Fail => bail!("{} already exists", path.display()),
+                    Collision::Skip => return Ok(None),
+                    _ => {}
+                },
+                Err(e) => return Err(e).with_context(|| format!("create {}", path.display())),
+            }
+        }
+        unreachable!()
+    }
+}
+
+/// Dosadí do šablony jména; výsledek nesmí obsahovat oddělovače cest.
+pub fn render(pattern: &str, vars: &NameVars) -> Result<String> {
+    let os = |s: Option<&std::ffi::OsStr>| s.unwrap_or_default().to_string_lossy().into_owned();
+    let now = annotations::now_rfc3339();
+    let mut missing = None;
+    let out = expand(pattern, |key| {
+        let (key, width) = match key.split_once(':') {
+            Some((k, w)) => (k, Some(w)),
+            None => (key, None),
+        };
+        let number = |v: usize| match width {
+            Some(w) => pad(v, w),
+            None => Some(v.to_string()),
+        };
+        let value = match key {
+            "stem" | "source_stem" => vars.src.map(|s| Some(os(s.file_stem()))),
+            "dir" => vars
+                .src
+                .map(|s| Some(os(s.parent().and_then(Path::file_name)))),
+            // bez šířky zůstává dřívější podoba 0001
+            "n" | "index" => vars.n.map(|n| pad(n, width.unwrap_or("04"))),
+            "page" => vars.page.map(number),
+            "barcode" => vars.barcode.map(|b| Some(sanitize(b))),
+            "date" => Some(Some(now.get(..10).unwrap_or(&now).to_string())),
+            "time" => Some(Some(now.get(11..19).unwrap_or(&now).replace(':', ""))),
+            "w" => vars.size.map(|s| number(s.0 as usize)),
+            "h" => vars.size.map(|s| number(s.1 as usize)),
+            "depth" => vars.depth.map(|d| number(d as usize)),
+            _ => return None,
+        };
+        let value = match value {
+            Some(v) => v?,
+            None => {
+                missing.get_or_insert_with(|| key.to_string());
+                String::new()
+            }
+        };
+        // šířku mají jen čísla
+        let numeric = matches!(key, "n" | "index" | "page" | "w" | "h" | "depth");
+        (width.is_none() || numeric).then_some(value)
+    })?;
+    if let Some(key) = missing {
+        bail!("{{{key}}} is not available here");
+    }
+    ensure!(
+        !out.trim().is_empty() && !out.contains(['/', '\\']) && out != "." && out != "..",
+        "invalid output file name {out:?}"
+    );
+    Ok(out)
+}
+
+/// Číslo doplněné nulami na šířku `0N` (nebo `N`); jiná šířka je chyba (`None`).
+fn pad(v: usize, width: &str) -> Option<String> {
+    let w: usize = width.parse().ok()?;
+    Some(format!("{v:0w$}"))
+}
+
+/// Hodnota jako část jména souboru: znaky nepovolené v cestách se nahradí `_`.
+fn sanitize(value: &str) -> String {
+    value
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_control() || "/\\:*?\"<>|".contains(c) {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+/// Dosadí hodnoty za `{symbol}`; neznámý symbol (`value` vrátí `None`) je chyba.
+pub fn expand(template: &str, mut value: impl FnMut(&str) -> Option<String>) -> Result<String> {
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let len = rest[start..]
+            .find('}')
+            .ok_or_else(|| anyhow!("unclosed '{{' in template"))?;
+        let key = &rest[start + 1..start + len];
+        let v = value(key).ok_or_else(|| anyhow!("unknown placeholder {{{key}}}"))?;
+        out.push_str(&v);
+        rest = &rest[start + len + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
@@ -0,0 +1,297 @@
+use anyhow::Result;
+use eframe::egui::{self, Color32, Pos2, Rect};
+use rayon::prelude::*;
+
+use crate::align::Luma;
+use crate::cti::CTIHeader;
+
+/// Strana bloku, ve kterém se odhaduje šum.
+const BLOCK: usize = 16;
+/// Blok je „plochý“, když jeho směrodatná odchylka je vysvětlena šumem (σ bloku ≤ poměr × σ šumu).
+const FLAT_RATIO: f32 = 1.5;
+/// Minimum plochých bloků, aby měl odhad dlaždice smysl.
+const MIN_FLAT_BLOCKS: usize = 3;
+/// Bloky u černé/bílé se vynechají (ořez potlačuje šum).
+const CLIP: f32 = 0.02;
+/// Výchozí limit σ (8bitové úrovně).
+pub const DEFAULT_THRESHOLD: f32 = 2.0;
+
+/// Odhad šumu po dlaždicích souboru.
+#[derive(Debug, Clone)]
+pub struct NoiseMap {
+    pub tile: u32,
+    pub cols: u32,
+    /// σ jasu v 8bitových úrovních (0..255) po řádcích; `None` = dlaždice bez ploché oblasti.
+    pub sigma: Vec<Option<f32>>,
+    /// Medián σ přes měřené dlaždice.
+    pub global: Option<f32>,
+    /// Podíl bloků, které prošly jako ploché.
+    pub flat_fraction: f32,
+}
+
+impl NoiseMap {
+    /// Odhadne šum (σ jasu) v plochých oblastech každé dlaždice.
+    ///
+    /// V blocích 16×16 se šum měří Immerkærovým filtrem (odezva na laplacián druhého řádu,
+    /// která necitlivě reaguje na plynulé přechody); blok se započítá jen tehdy, když
+    /// jeho celková směrodatná odchylka není o mnoho větší než samotný šum, tj. neobsahuje
+    /// kresbu. σ dlaždice je medián plochých bloků.
+    pub fn compute(hdr: &CTIHeader, raw: &[u8]) -> Result<Self> {
+        let luma = Luma::new(hdr, raw)?;
+        let tile = hdr.tile_size.max(BLOCK as u32);
+        let cols = hdr.width.div_ceil(tile);
+        let rows = hdr.height.div_ceil(tile);
+        let per_tile: Vec<(Option<f32>, usize, usize)> = (0..cols * rows)
+            .into_par_iter()
+            .map(|i| {
+                let (tx, ty) = ((i % cols * tile) as usize, (i / cols * tile) as usize);
+                let (tw, th) = (
+                    (tile as usize).min(luma.w - tx),
+                    (tile as usize).min(luma.h - ty),
+                );
+                tile_sigma(&luma, tx, ty, tw, th)
+            })
+            .collect();
+
+        let (flat, total) = per_tile
+            .iter()
+            .fold((0, 0), |(f, t), &(_, bf, bt)| (f + bf, t + bt));
+        let sigma: Vec<Option<f32>> = per_tile.into_iter().map(|(s, _, _)| s).collect();
+        let global = median(sigma.iter().flatten().copied().collect());
+        Ok(Self {
+            tile,
+            cols,
+            sigma,
+            global,
+            flat_fraction: flat as f32 / total.max(1) as f32,
+        })
+    }
+
+    /// Počet měřených dlaždic a z nich těch nad limitem.
+    pub fn counts(&self, threshold: f32) -> (usize, usize) {
+        let measured = self.sigma.iter().flatten();
+        let over = measured.clone().filter(|&&s| s > threshold).count();
+        (measured.count(), over)
+    }
+
+    /// Nejhorší dlaždice (sloupec, řádek, σ).
+    pub fn worst(&self) -> Option<(u32, u32, f32)> {
+        self.sigma
+            .iter()
+            .enumerate()
+            .filter_map(|(i, s)| s.map(|s| (i as u32 % self.cols, i as u32 / self.cols, s)))
+            .max_by(|a, b| a.2.total_cmp(&b.2))
+    }
+}
+
+/// (σ dlaždice, počet plochých bloků, počet bloků).
+fn tile_sigma(
+    luma: &Luma,
+    tx: usize,
+    ty: usize,
+    tw: usize,
+    th: usize,
+) -> (Option<f32>, usize, usize) {
+    let mut flat = Vec::new();
+    let mut blocks = 0;
+    for by in (ty..ty + th).step_by(BLOCK) {
+        for bx in (tx..tx + tw).step_by(BLOCK) {
+            let (bw, bh) = (BLOCK.min(tx + tw - bx), BLOCK.min(ty + th - by));
+            if bw < 4 || bh < 4 {
+                continue;
+            }
+            blocks += 1;
+            if let Some(s) = block_noise(luma, bx, by, bw, bh) {
+                flat.push(s * 255.0);
+            }
+        }
+    }
+    let n = flat.len();
+    let sigma = if n >= MIN_FLAT_BLOCKS {
+        median(flat)
+    } else {
+        None
+    };
+    (sigma, n, blocks)
+}
+
+/// σ šumu bloku, pokud je blok plochý.
+fn block_noise(luma: &Luma, bx: usize, by: usize, bw: usize, bh: usize) -> Option<f32> {
+    let (mut sum, mut sum_sq) = (0f64, 0f64);
+    for y in by..by + bh {
+        for x in bx..bx + bw {
+            let v = luma.at(x, y) as f64;
+            sum += v;
+            sum_sq += v * v;
+        }
+    }
+    let n = (bw * bh) as f64;
+    let mean = sum / n;
+    if mean < CLIP as f64 || mean > 1.0 - CLIP as f64 {
+        return None;
+    }
+    let std = (sum_sq / n - mean * mean).max(0.0).sqrt() as f32;
+
+    // Immerkær: σ = √(π/2) · Σ|I ∗ N| / (6 · počet), N = [1 −2 1; −2 4 −2; 1 −2 1]
+    let mut acc = 0f64;
+    let mut count = 0usize;
+    for y in by + 1..by + bh - 1 {
+        for x in bx + 1..bx + bw - 1 {
+            let p = |dx: usize, dy: usize| luma.at(x + dx - 1, y + dy - 1) as f64;
+            let r = p(0, 0) + p(2, 0) + p(0, 2) + p(2, 2)
+                - 2.0 * (p(1, 0) + p(0, 1) + p(2, 1) + p(1, 2))
+                + 4.0 * p(1, 1);
+            acc += r.abs();
+            count += 1;
+        }
+    }
+    let sigma = ((std::f64::consts::FRAC_PI_2).sqrt() * acc / (6.0 * count as f64)) as f32;
+    (std <= FLAT_RATIO * sigma + 1e-4).then_some(sigma)
+}
+
+fn median(mut v: Vec<f32>) -> Option<f32> {
+    if v.is_empty() {
+        return None;
+    }
+    v.sort_by(f32::total_cmp);
+    let m = v.len() / 2;
+    Some(if v.len().is_multiple_of(2) {
+        (v[m - 1] + v[m]) / 2.0
+    } else {
+        v[m]
+    })
+}
+
+/// Barva dlaždice podle poměru σ/limit: zelená → žlutá (na limitu) → červená (1,5× limit).
+fn tile_color(ratio: f32) -> Color32 {
+    let (r, g) = if ratio <= 1.0 {
+        (ratio.clamp(0.0, 1.0), 1.0)
+    } else {
+        (1.0, (1.0 - (ratio - 1.0) * 2.0).clamp(0.0, 1.0))
+    };
+    Color32::from_rgba_unmultiplied((r * 255.0) as u8, (g * 200.0) as u8, 0, 90)
+}
+
+/// Okno analýzy šumu a překryvná teplotní mapa.
+pub struct NoisePanel {
+    pub map: Option<NoiseMap>,
+    pub overlay: bool,
+    pub threshold: f32,
+    error: Option<String>,
+}
+
+impl Default for NoisePanel {
+    fn default() -> Self {
+        Self {
+            map: None,
+            overlay: true,
+            threshold: DEFAULT_THRESHOLD,
+            error: None,
+        }
+    }
+}
+
+impl NoisePanel {
+    /// Zahodí výsledek (po otevření jiného souboru).
+    pub fn clear(&mut self) {
+        self.map = None;
+        self.error = None;
+    }
+
+    pub fn window(
+        &mut self,
+        ctx: &egui::Context,
+        open: &mut bool,
+        image: Option<(&CTIHeader, &[u8])>,
+    ) {
+        egui::Window::new("Noise analysis")
+            .resizable(false)
+            .open(open)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(image.is_some(), egui::Button::new("Analyze"))
+                        .clicked()
+                        && let Some((hdr, raw)) = image
+                    {
+                        match NoiseMap::compute(hdr, raw) {
+                            Ok(map) => {
+                                self.map = Some(map);
+                                self.error = None;
+                            }
+                            Err(e) => self.error = Some(format!("{e:#}")),
+                        }
+                    }
+                    ui.label("Limit σ");
+                    ui.add(
+                        egui::DragValue::new(&mut self.threshold)
+                            .range(0.1..=50.0)
+                            .speed(0.05)
+                            .fixed_decimals(2),
+                    )
+                    .on_hover_text(
+                        "Maximum acceptable noise in 8-bit levels, from your digitization guideline",
+                    );
+                });
+                if let Some(e) = &self.error {
+                    ui.colored_label(ui.visuals().error_fg_color, e);
+                }
+                let Some(map) = &self.map else {
+                    ui.weak("Estimates luminance noise in flat regions of each tile.");
+                    return;
+                };
+                let (measured, over) = map.counts(self.threshold);
+                match map.global {
+                    Some(g) => {
+                        let (text, color) = if g <= self.threshold {
+                            ("PASS", Color32::from_rgb(40, 160, 60))
+                        } else {
+                            ("FAIL", ui.visuals().error_fg_color)
+                        };
+                        ui.horizontal(|ui| {
+                            ui.strong(format!("σ = {g:.2} levels"));
+                            ui.colored_label(color, text);
+                        });
+                    }
+                    None => {
+                        ui.colored_label(ui.visuals().warn_fg_color, "No flat regions found");
+                    }
+                }
+                ui.monospace(format!(
+                    "Tiles      : {measured} of {} measured, {over} over limit",
+                    map.sigma.len()
+                ));
+                ui.monospace(format!("Flat blocks: {:.0} %", map.flat_fraction * 100.0));
+                if let Some((c, r, s)) = map.worst() {
+                    ui.monospace(format!("Worst tile : {c},{r} (σ {s:.2})"));
+                }
+                ui.checkbox(&mut self.overlay, "Heatmap overlay");
+            });
+    }
+
+    /// Vykreslí mapu přes obrázek; `to_screen` převádí souřadnice obrázku na obrazovku.
+    pub fn paint(&self, painter: &egui::Painter, to_screen: impl Fn(Pos2) -> Pos2) {
+        let Some(map) = self.map.as_ref().filter(|_| self.overlay) else {
+            return;
+        };
+        let t = map.tile as f32;
+        for (i, s) in map.sigma.iter().enumerate() {
+            let Some(s) = *s else { continue };
+            let (c, r) = ((i as u32 % map.cols) as f32, (i as u32 / map.cols) as f32);
+            let rect = Rect::from_two_pos(
+                to_screen(Pos2::new(c * t, r * t)),
+                to_screen(Pos2::new((c + 1.0) * t, (r + 1.0) * t)),
+            );
+            painter.rect_filled(rect, 0.0, tile_color(s / self.threshold));
+            if rect.width() > 48.0 && rect.height() > 20.0 {
+                painter.text(
+                    rect.center(),
+                    egui::Align2::CENTER_CENTER,
+                    format!("{s:.2}"),
+                    egui::FontId::monospace(11.0),
+                    Color32::WHITE,
+                );
+            }
+        }
+    }
+}
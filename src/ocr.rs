@@ -0,0 +1,421 @@
+//! Rozpoznání textu (OCR) pro rychlou kontrolu skenů: dekódovaný snímek nebo výběr se uloží
+//! jako dočasné PNG a předá externímu programu (výchozí `tesseract`, výstup TSV se slovy
+//! a jejich obdélníky). Slova se zobrazí přes obrázek a dají se hledat. Výsledky se ukládají
+//! do sidecaru `<soubor>.ocr.json` a platí, dokud se nezmění obsah souboru
+//! ([`CTIDecoder::fingerprint`]).
+
+use anyhow::{Context, Result, anyhow, bail, ensure};
+use eframe::egui::{self, Color32, Pos2, Rect, Stroke};
+use image::ImageFormat;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+
+use crate::cti::{CTIDecoder, CTIHeader};
+use crate::export;
+use crate::tools;
+use crate::workdirs::Area;
+
+/// Výchozí příkaz; `{image}` = dočasné PNG, `{lang}` = jazyky OCR.
+pub const DEFAULT_COMMAND: &str = "tesseract {image} stdout -l {lang} tsv";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OcrSettings {
+    /// Program s argumenty; na standardní výstup musí vypsat TSV ve formátu tesseractu.
+    pub command: String,
+    /// Jazyky ve tvaru tesseractu, např. `ces+eng`.
+    pub language: String,
+}
+
+impl Default for OcrSettings {
+    fn default() -> Self {
+        Self {
+            command: DEFAULT_COMMAND.to_owned(),
+            language: "eng".to_owned(),
+        }
+    }
+}
+
+/// Rozpoznané slovo; obdélník `[x, y, šířka, výška]` v pixelech neotočeného obrázku.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Word {
+    pub rect: [u32; 4],
+    pub text: String,
+    /// Jistota 0–100 podle OCR programu.
+    pub confidence: f32,
+    /// Pořadí řádku ve výsledku (pro složení textu).
+    pub line: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OcrResult {
+    pub frame: u32,
+    /// Výřez `(x, y, w, h)`; `None` = celý snímek.
+    pub region: Option<(u32, u32, u32, u32)>,
+    pub language: String,
+    pub words: Vec<Word>,
+}
+
+impl OcrResult {
+    /// Rozpoznaný text po řádcích.
+    pub fn text(&self) -> String {
+        let mut out = String::new();
+        let mut line = None;
+        for w in &self.words {
+            match line {
+                Some(l) if l == w.line => out.push(' '),
+                Some(_) => out.push('\n'),
+                None => {}
+            }
+            out.push_str(&w.text);
+            line = Some(w.line);
+        }
+        out
+    }
+
+    /// Slova obsahující `query` (bez ohledu na velikost písmen).
+    pub fn matches<'a>(&'a self, query: &str) -> impl Iterator<Item = &'a Word> {
+        let query = query.trim().to_lowercase();
+        self.words
+            .iter()
+            .filter(move |w| !query.is_empty() && w.text.to_lowercase().contains(&query))
+    }
+
+    fn mean_confidence(&self) -> f32 {
+        let sum: f32 = self.words.iter().map(|w| w.confidence).sum();
+        sum / self.words.len().max(1) as f32
+    }
+}
+
+/// Výsledky uložené u jednoho souboru.
+#[derive(Default, Serialize, Deserialize)]
+struct Sidecar {
+    /// Otisk obsahu CTI (hex); jiný = výsledky patří starší verzi souboru.
+    fingerprint: String,
+    results: Vec<OcrResult>,
+}
+
+pub fn sidecar_path(image: &Path) -> PathBuf {
+    let mut name = image.file_name().unwrap_or_default().to_os_string();
+    name.push(".ocr.json");
+    image.with_file_name(name)
+}
+
+fn fingerprint(image: &Path) -> Result<String> {
+    Ok(CTIDecoder::fingerprint(image)?
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect())
+}
+
+/// Sidecar platný pro aktuální obsah souboru (jinak prázdný).
+fn load_sidecar(image: &Path, fingerprint: &str) -> Sidecar {
+    let sidecar = std::fs::read(sidecar_path(image))
+        .ok()
+        .and_then(|bytes| serde_json::from_slice::<Sidecar>(&bytes).ok())
+        .filter(|s| s.fingerprint == fingerprint);
+    sidecar.unwrap_or_else(|| Sidecar {
+        fingerprint: fingerprint.to_owned(),
+        results: Vec::new(),
+    })
+}
+
+fn same_request(
+    r: &OcrResult,
+    frame: u32,
+    region: Option<(u32, u32, u32, u32)>,
+    lang: &str,
+) -> bool {
+    r.frame == frame && r.region == region && r.language == lang
+}
+
+/// Uložený výsledek pro snímek, výřez a jazyk, pokud se soubor od té doby nezměnil.
+pub fn cached(
+    image: &Path,
+    frame: u32,
+    region: Option<(u32, u32, u32, u32)>,
+    language: &str,
+) -> Option<OcrResult> {
+    let sidecar = load_sidecar(image, &fingerprint(image).ok()?);
+    sidecar
+        .results
+        .into_iter()
+        .find(|r| same_request(r, frame, region, language))
+}
+
+fn store(image: &Path, result: &OcrResult) -> Result<()> {
+    let mut sidecar = load_sidecar(image, &fingerprint(image)?);
+    sidecar
+        .results
+        .retain(|r| !same_request(r, result.frame, result.region, &result.language));
+    sidecar.results.push(result.clone());
+    let path = sidecar_path(image);
+    std::fs::write(&path, serde_json::to_vec_pretty(&sidecar)?)
+        .with_context(|| format!("write {}", path.display()))
+}
+
+/// Rozpozná text ve snímku `frame` souboru `image` (jen ve výřezu `region`, je-li zadán).
+/// Uložený výsledek se vrátí bez spuštění OCR, nový se uloží do sidecaru.
+pub fn recognize(
+    image: &Path,
+    frame: u32,
+    region: Option<(u32, u32, u32, u32)>,
+    settings: &OcrSettings,
+) -> Result<OcrResult> {
+    if let Some(r) = cached(image, frame, region, &settings.language) {
+        return Ok(r);
+    }
+    let (hdr, raw) = match region {
+        Some(r) => {
+            let (hdr, raw) = CTIDecoder::decode_region(image, frame, r)?;
+            let crop = CTIHeader {
+                width: r.2,
+                height: r.3,
+                ..hdr
+            };
+            (crop, raw)
+        }
+        None => CTIDecoder::decode_frame(image, frame)?,
+    };
+    let img = export::tone_mapped(export::to_dynamic_image(&hdr, raw)?);
+    let png = Area::Temp
+        .dir()?
+        .join(format!("ocr-{}.png", std::process::id()));
+    img.save_with_format(&png, ImageFormat::Png)?;
+    let output = run(settings, &png);
+    let _ = std::fs::remove_file(&png);
+    let origin = region.map_or((0, 0), |r| (r.0, r.1));
+    let result = OcrResult {
+        frame,
+        region,
+        language: settings.language.clone(),
+        words: parse_tsv(&output?, origin)?,
+    };
+    if let Err(e) = store(image, &result) {
+        tracing::warn!("OCR sidecar: {e:#}");
+    }
+    Ok(result)
+}
+
+fn run(settings: &OcrSettings, png: &Path) -> Result<String> {
+    let args: Vec<String> = tools::split_args(&settings.command)?
+        .iter()
+        .map(|a| {
+            a.replace("{image}", &png.to_string_lossy())
+                .replace("{lang}", settings.language.trim())
+        })
+        .collect();
+    let (prog, rest) = args
+        .split_first()
+        .ok_or_else(|| anyhow!("empty OCR command"))?;
+    let output = Command::new(prog)
+        .args(rest)
+        .output()
+        .map_err(|e| anyhow!("failed to start {prog:?}: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!("{prog} failed ({}) {}", output.status, stderr.trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// TSV tesseractu: `level page_num block_num par_num line_num word_num left top width
+/// height conf text`; slova mají level 5. `origin` posune obdélníky výřezu do obrázku.
+fn parse_tsv(tsv: &str, (ox, oy): (u32, u32)) -> Result<Vec<Word>> {
+    let mut lines = tsv.lines();
+    let header = lines.next().unwrap_or_default();
+    ensure!(
+        header.starts_with("level"),
+        "OCR output is not Tesseract TSV (expected a `level …` header)"
+    );
+    let mut words = Vec::new();
+    let (mut line, mut line_key) = (0, None);
+    for l in lines {
+        let cols: Vec<&str> = l.splitn(12, '\t').collect();
+        if cols.len() < 12 || cols[0] != "5" || cols[11].trim().is_empty() {
+            continue;
+        }
+        let num = |i: usize| cols[i].trim().parse::<u32>().unwrap_or(0);
+        let key = (num(1), num(2), num(3), num(4));
+        if line_key.is_some_and(|k| k != key) {
+            line += 1;
+        }
+        line_key = Some(key);
+        words.push(Word {
+            rect: [ox + num(6), oy + num(7), num(8), num(9)],
+            text: cols[11].trim().to_owned(),
+            confidence: cols[10].trim().parse().unwrap_or(0.0),
+            line,
+        });
+    }
+    Ok(words)
+}
+
+/// Okno OCR a překryv s rozpoznanými slovy.
+pub struct OcrPanel {
+    pub result: Option<OcrResult>,
+    pub overlay: bool,
+    query: String,
+    running: Option<Receiver<Result<OcrResult>>>,
+    error: Option<String>,
+}
+
+impl Default for OcrPanel {
+    fn default() -> Self {
+        Self {
+            result: None,
+            overlay: true,
+            query: String::new(),
+            running: None,
+            error: None,
+        }
+    }
+}
+
+impl OcrPanel {
+    /// Zahodí výsledek (po otevření jiného souboru nebo snímku).
+    pub fn clear(&mut self) {
+        self.result = None;
+        self.running = None;
+        self.error = None;
+    }
+
+    /// `image` = otevřený soubor a snímek, `region` = výběr (jinak celý snímek).
+    pub fn window(
+        &mut self,
+        ctx: &egui::Context,
+        open: &mut bool,
+        settings: &mut OcrSettings,
+        image: Option<(&Path, u32)>,
+        region: Option<(u32, u32, u32, u32)>,
+    ) {
+        if let Some(rx) = &self.running {
+            match rx.try_recv() {
+                Ok(Ok(result)) => {
+                    self.result = Some(result);
+                    self.error = None;
+                    self.running = None;
+                }
+                Ok(Err(e)) => {
+                    self.error = Some(format!("{e:#}"));
+                    self.running = None;
+                }
+                Err(TryRecvError::Empty) => {}
+                Err(TryRecvError::Disconnected) => self.running = None,
+            }
+        }
+        egui::Window::new("OCR")
+            .default_width(360.0)
+            .open(open)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Language");
+                    ui.add(egui::TextEdit::singleline(&mut settings.language).desired_width(80.0))
+                        .on_hover_text("Tesseract language codes, e.g. ces+eng");
+                    let label = if region.is_some() {
+                        "Recognize selection"
+                    } else {
+                        "Recognize page"
+                    };
+                    let enabled = image.is_some() && self.running.is_none();
+                    if ui.add_enabled(enabled, egui::Button::new(label)).clicked()
+                        && let Some((path, frame)) = image
+                    {
+                        self.start(ctx, path, frame, region, settings.clone());
+                    }
+                    if self.running.is_some() {
+                        ui.spinner();
+                    }
+                });
+                ui.collapsing("Command", |ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut settings.command)
+                            .desired_width(f32::INFINITY),
+                    );
+                    ui.weak("{image} = temporary PNG, {lang} = language; must print Tesseract TSV");
+                    if ui.small_button("Default").clicked() {
+                        settings.command = DEFAULT_COMMAND.to_owned();
+                    }
+                });
+                if let Some(e) = &self.error {
+                    ui.colored_label(ui.visuals().error_fg_color, e);
+                }
+                let Some(result) = &self.result else {
+                    ui.weak(
+                        "Recognizes text on the page or selection; results are kept next to \
+                         the file.",
+                    );
+                    return;
+                };
+                ui.label(format!(
+                    "{} word(s), mean confidence {:.0} %",
+                    result.words.len(),
+                    result.mean_confidence()
+                ));
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.query)
+                            .hint_text("Search text")
+                            .desired_width(180.0),
+                    );
+                    if !self.query.trim().is_empty() {
+                        ui.label(format!("{} match(es)", result.matches(&self.query).count()));
+                    }
+                });
+                ui.checkbox(&mut self.overlay, "Show words over the image");
+                egui::ScrollArea::vertical()
+                    .max_height(240.0)
+                    .show(ui, |ui| {
+                        let text = result.text();
+                        ui.add(
+                            egui::TextEdit::multiline(&mut text.as_str())
+                                .desired_width(f32::INFINITY)
+                                .font(egui::TextStyle::Monospace),
+                        );
+                    });
+            });
+    }
+
+    fn start(
+        &mut self,
+        ctx: &egui::Context,
+        path: &Path,
+        frame: u32,
+        region: Option<(u32, u32, u32, u32)>,
+        settings: OcrSettings,
+    ) {
+        let (tx, rx) = mpsc::channel();
+        let (ctx, path) = (ctx.clone(), path.to_path_buf());
+        std::thread::spawn(move || {
+            let _ = tx.send(recognize(&path, frame, region, &settings));
+            ctx.request_repaint();
+        });
+        self.running = Some(rx);
+        self.error = None;
+    }
+
+    /// Vykreslí obdélníky slov (nalezená zvýrazní); `to_screen` převádí souřadnice obrázku
+    /// na obrazovku.
+    pub fn paint(&self, painter: &egui::Painter, to_screen: impl Fn(Pos2) -> Pos2) {
+        let Some(result) = self.result.as_ref().filter(|_| self.overlay) else {
+            return;
+        };
+        let rect = |w: &Word| {
+            let [x, y, ww, wh] = w.rect.map(|v| v as f32);
+            Rect::from_two_pos(
+                to_screen(Pos2::new(x, y)),
+                to_screen(Pos2::new(x + ww, y + wh)),
+            )
+        };
+        let stroke = Stroke::new(1.0, Color32::from_rgba_unmultiplied(60, 140, 255, 160));
+        for w in &result.words {
+            painter.rect_stroke(rect(w), 0.0, stroke, egui::StrokeKind::Outside);
+        }
+        let found = Color32::from_rgba_unmultiplied(255, 220, 0, 90);
+        for w in result.matches(&self.query) {
+            painter.rect_filled(rect(w), 0.0, found);
+        }
+    }
+}
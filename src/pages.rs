@@ -0,0 +1,176 @@
+use eframe::egui::{self, Color32, ColorImage, TextureHandle};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+
+use crate::cti::CTIDecoder;
+
+/// Delší strana náhledu stránky v pixelech.
+const THUMB_SIZE: usize = 160;
+/// Kolik souborů si pamatuje naposledy zobrazenou stránku.
+const REMEMBERED_FILES: usize = 500;
+
+/// Naposledy zobrazená stránka vícestránkových souborů (nejnovější první).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PageMemory {
+    recent: VecDeque<(PathBuf, u32)>,
+}
+
+impl PageMemory {
+    pub fn last_page(&self, path: &Path) -> Option<u32> {
+        self.recent
+            .iter()
+            .find(|(p, _)| p == path)
+            .map(|(_, page)| *page)
+    }
+
+    pub fn remember(&mut self, path: &Path, page: u32) {
+        self.recent.retain(|(p, _)| p != path);
+        self.recent.push_front((path.to_path_buf(), page));
+        self.recent.truncate(REMEMBERED_FILES);
+    }
+}
+
+/// Náhledy stránek otevřeného souboru; dekódují se postupně ve vlákně na pozadí.
+#[derive(Default)]
+pub struct PageThumbs {
+    path: Option<PathBuf>,
+    thumbs: Vec<Option<TextureHandle>>,
+    rx: Option<Receiver<(u32, ColorImage)>>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl PageThumbs {
+    /// Začne generovat náhledy pro `path` (pokud už neběží nebo nejsou hotové).
+    pub fn ensure(&mut self, ctx: &egui::Context, path: &Path, count: u32) {
+        if self.path.as_deref() == Some(path) && self.thumbs.len() == count as usize {
+            return;
+        }
+        self.cancel.store(true, Ordering::Relaxed);
+        self.cancel = Arc::new(AtomicBool::new(false));
+        self.path = Some(path.to_path_buf());
+        self.thumbs = vec![None; count as usize];
+
+        let (tx, rx) = mpsc::channel();
+        self.rx = Some(rx);
+        let (p, cn, ctx) = (path.to_path_buf(), self.cancel.clone(), ctx.clone());
+        std::thread::spawn(move || {
+            for n in 0..count {
+                if cn.load(Ordering::Relaxed) {
+                    return;
+                }
+                // stránka, kterou nejde dekódovat ani zobrazit, zůstane bez náhledu
+                let Ok((hdr, raw, _)) = CTIDecoder::decode_frame_lossy(&p, n) else {
+                    continue;
+                };
+                let Ok(image) = crate::to_color_image(&hdr, &raw) else {
+                    continue;
+                };
+                if tx.send((n, shrink(&image, THUMB_SIZE))).is_err() {
+                    return;
+                }
+                ctx.request_repaint();
+            }
+        });
+    }
+
+    /// Zahodí náhledy (soubor bez stránek nebo zavřený).
+    pub fn clear(&mut self) {
+        // původní hodnota se zahodí a Drop zastaví vlákno
+        *self = Self::default();
+    }
+
+    /// Sloupec náhledů; vrátí stránku, na kterou uživatel klikl.
+    pub fn ui(&mut self, ui: &mut egui::Ui, current: u32) -> Option<u32> {
+        if let Some(rx) = &self.rx {
+            while let Ok((n, image)) = rx.try_recv() {
+                let tex = ui.ctx().load_texture(
+                    format!("page-thumb-{n}"),
+                    image,
+                    egui::TextureOptions::LINEAR,
+                );
+                if let Some(slot) = self.thumbs.get_mut(n as usize) {
+                    *slot = Some(tex);
+                }
+            }
+        }
+        let mut goto = None;
+        let width = ui.available_width().min(THUMB_SIZE as f32);
+        let row = egui::vec2(width, width * 1.3 + 18.0);
+        egui::ScrollArea::vertical().show_rows(ui, row.y, self.thumbs.len(), |ui, range| {
+            for n in range {
+                let selected = n as u32 == current;
+                let (rect, resp) = ui.allocate_exact_size(row, egui::Sense::click());
+                let visuals = ui.style().interact_selectable(&resp, selected);
+                if selected || resp.hovered() {
+                    ui.painter().rect_filled(rect, 4.0, visuals.weak_bg_fill);
+                }
+                let area = egui::Rect::from_min_size(
+                    rect.min + egui::vec2(4.0, 4.0),
+                    egui::vec2(rect.width() - 8.0, rect.height() - 26.0),
+                );
+                match &self.thumbs[n] {
+                    Some(tex) => {
+                        let size = tex.size_vec2();
+                        let scale = (area.width() / size.x).min(area.height() / size.y);
+                        let img = egui::Rect::from_center_size(area.center(), size * scale);
+                        egui::Image::new(tex).paint_at(ui, img);
+                    }
+                    None => {
+                        ui.painter().rect_filled(
+                            area,
+                            2.0,
+                            Color32::from_gray(60).gamma_multiply(0.5),
+                        );
+                    }
+                }
+                ui.painter().text(
+                    egui::pos2(rect.center().x, rect.bottom() - 11.0),
+                    egui::Align2::CENTER_CENTER,
+                    format!("{}", n + 1),
+                    egui::FontId::proportional(13.0),
+                    visuals.text_color(),
+                );
+                if resp.on_hover_text(format!("Page {}", n + 1)).clicked() {
+                    goto = Some(n as u32);
+                }
+            }
+        });
+        goto.filter(|&n| n != current)
+    }
+}
+
+impl Drop for PageThumbs {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Zmenší obrázek průměrováním bloků tak, aby delší strana měla nejvýš `max` pixelů.
+fn shrink(image: &ColorImage, max: usize) -> ColorImage {
+    let [w, h] = image.size;
+    let step = w.max(h).div_ceil(max).max(1);
+    let (tw, th) = (w.div_ceil(step), h.div_ceil(step));
+    let mut pixels = Vec::with_capacity(tw * th);
+    for ty in 0..th {
+        for tx in 0..tw {
+            let mut sum = [0u32; 4];
+            let mut n = 0;
+            for y in ty * step..((ty + 1) * step).min(h) {
+                for x in tx * step..((tx + 1) * step).min(w) {
+                    let p = image.pixels[y * w + x].to_array();
+                    for (s, v) in sum.iter_mut().zip(p) {
+                        *s += v as u32;
+                    }
+                    n += 1;
+                }
+            }
+            let [r, g, b, a] = sum.map(|s| (s / n) as u8);
+            pixels.push(Color32::from_rgba_premultiplied(r, g, b, a));
+        }
+    }
+    ColorImage::new([tw, th], pixels)
+}
@@ -0,0 +1,267 @@
+use eframe::egui::{self, Color32, Key};
+
+use crate::cmyk;
+use crate::cti::CTIHeader;
+use crate::hdr;
+
+/// Jedna referenční ploška pro měření kolorimetrem (sRGB 8 bit).
+#[derive(Debug, Clone)]
+pub struct Patch {
+    pub name: String,
+    pub rgb: [u8; 3],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchSet {
+    GrayRamp,
+    Primaries,
+    ColorChecker,
+    Picked,
+}
+
+impl PatchSet {
+    pub const ALL: [PatchSet; 4] = [
+        PatchSet::GrayRamp,
+        PatchSet::Primaries,
+        PatchSet::ColorChecker,
+        PatchSet::Picked,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PatchSet::GrayRamp => "Gray ramp (11 steps)",
+            PatchSet::Primaries => "Primaries + secondaries",
+            PatchSet::ColorChecker => "ColorChecker 24 (sRGB)",
+            PatchSet::Picked => "Picked from image",
+        }
+    }
+
+    fn patches(self, picked: &[Patch]) -> Vec<Patch> {
+        let p = |name: &str, rgb: [u8; 3]| Patch {
+            name: name.into(),
+            rgb,
+        };
+        match self {
+            PatchSet::GrayRamp => (0..=10u32)
+                .map(|i| {
+                    let v = (i * 255 / 10) as u8;
+                    p(&format!("{}%", i * 10), [v, v, v])
+                })
+                .collect(),
+            PatchSet::Primaries => vec![
+                p("White", [255, 255, 255]),
+                p("Red", [255, 0, 0]),
+                p("Green", [0, 255, 0]),
+                p("Blue", [0, 0, 255]),
+                p("Cyan", [0, 255, 255]),
+                p("Magenta", [255, 0, 255]),
+                p("Yellow", [255, 255, 0]),
+                p("Black", [0, 0, 0]),
+            ],
+            PatchSet::ColorChecker => COLORCHECKER.iter().map(|&(n, rgb)| p(n, rgb)).collect(),
+            PatchSet::Picked => picked.to_vec(),
+        }
+    }
+}
+
+// X-Rite ColorChecker Classic, přibližné sRGB hodnoty (D65)
+const COLORCHECKER: [(&str, [u8; 3]); 24] = [
+    ("Dark skin", [115, 82, 68]),
+    ("Light skin", [194, 150, 130]),
+    ("Blue sky", [98, 122, 157]),
+    ("Foliage", [87, 108, 67]),
+    ("Blue flower", [133, 128, 177]),
+    ("Bluish green", [103, 189, 170]),
+    ("Orange", [214, 126, 44]),
+    ("Purplish blue", [80, 91, 166]),
+    ("Moderate red", [193, 90, 99]),
+    ("Purple", [94, 60, 108]),
+    ("Yellow green", [157, 188, 64]),
+    ("Orange yellow", [224, 163, 46]),
+    ("Blue", [56, 61, 150]),
+    ("Green", [70, 148, 73]),
+    ("Red", [175, 54, 60]),
+    ("Yellow", [231, 199, 31]),
+    ("Magenta", [187, 86, 149]),
+    ("Cyan", [8, 133, 161]),
+    ("White 9.5", [243, 243, 242]),
+    ("Neutral 8", [200, 200, 200]),
+    ("Neutral 6.5", [160, 160, 160]),
+    ("Neutral 5", [122, 122, 121]),
+    ("Neutral 3.5", [85, 85, 85]),
+    ("Black 2", [52, 52, 52]),
+];
+
+/// Nastavení QA plošek (výběr sady, plošky nabrané z obrázku).
+pub struct PatchSetup {
+    pub set: PatchSet,
+    pub picked: Vec<Patch>,
+    /// Klik do obrázku přidá barvu pixelu do `picked`.
+    pub picking: bool,
+}
+
+impl Default for PatchSetup {
+    fn default() -> Self {
+        Self {
+            set: PatchSet::GrayRamp,
+            picked: Vec::new(),
+            picking: false,
+        }
+    }
+}
+
+/// Běžící měření: plošky se zobrazují přes celou obrazovku.
+pub struct PatchSession {
+    patches: Vec<Patch>,
+    index: usize,
+    show_label: bool,
+}
+
+impl PatchSetup {
+    /// Okno nastavení; vrátí novou session po kliknutí na Start.
+    pub fn window(&mut self, ctx: &egui::Context, open: &mut bool) -> Option<PatchSession> {
+        let mut start = None;
+        egui::Window::new("Monitor QA patches")
+            .collapsible(false)
+            .resizable(false)
+            .open(open)
+            .show(ctx, |ui| {
+                for set in PatchSet::ALL {
+                    let label = if set == PatchSet::Picked {
+                        format!("{} ({})", set.label(), self.picked.len())
+                    } else {
+                        set.label().to_string()
+                    };
+                    ui.radio_value(&mut self.set, set, label);
+                }
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.toggle_value(&mut self.picking, "Pick from image")
+                        .on_hover_text("Click on the image to add the pixel color");
+                    if ui.button("Clear picked").clicked() {
+                        self.picked.clear();
+                    }
+                });
+                ui.separator();
+                let patches = self.set.patches(&self.picked);
+                ui.add_enabled_ui(!patches.is_empty(), |ui| {
+                    if ui.button("Start fullscreen").clicked() {
+                        start = Some(PatchSession {
+                            patches,
+                            index: 0,
+                            show_label: true,
+                        });
+                    }
+                });
+                ui.weak("→/Space next, ← previous, L label, Esc exit");
+            });
+        if start.is_some() {
+            self.picking = false;
+        }
+        start
+    }
+
+    pub fn pick(&mut self, hdr: &CTIHeader, raw: &[u8], x: u32, y: u32) {
+        if let Some(rgb) = rgb8_at(hdr, raw, x, y) {
+            self.picked.push(Patch {
+                name: format!("Pixel {x},{y}"),
+                rgb,
+            });
+        }
+    }
+}
+
+impl PatchSession {
+    /// Vykreslí aktuální plošku; vrací `false`, když uživatel měření ukončil.
+    pub fn show(&mut self, ctx: &egui::Context) -> bool {
+        let (next, prev, label, exit) = ctx.input(|i| {
+            (
+                i.key_pressed(Key::ArrowRight) || i.key_pressed(Key::Space),
+                i.key_pressed(Key::ArrowLeft),
+                i.key_pressed(Key::L),
+                i.key_pressed(Key::Escape),
+            )
+        });
+        if next {
+            self.index = (self.index + 1) % self.patches.len();
+        }
+        if prev {
+            self.index = (self.index + self.patches.len() - 1) % self.patches.len();
+        }
+        if label {
+            self.show_label = !self.show_label;
+        }
+
+        let patch = &self.patches[self.index];
+        let [r, g, b] = patch.rgb;
+        let fill = Color32::from_rgb(r, g, b);
+        egui::CentralPanel::default()
+            .frame(egui::Frame::new().fill(fill))
+            .show(ctx, |ui| {
+                if self.show_label {
+                    // popisek v rohu, ať nepřekáží sondě uprostřed
+                    let luma = 0.2126 * r as f32 + 0.7152 * g as f32 + 0.0722 * b as f32;
+                    let text = if luma > 128.0 {
+                        Color32::BLACK
+                    } else {
+                        Color32::WHITE
+                    };
+                    ui.colored_label(
+                        text,
+                        format!(
+                            "{}/{}  {}  RGB {} {} {}",
+                            self.index + 1,
+                            self.patches.len(),
+                            patch.name,
+                            r,
+                            g,
+                            b
+                        ),
+                    );
+                }
+            });
+        !exit
+    }
+}
+
+/// Hodnota pixelu převedená na 8bit RGB (16bit kanály se zkrátí na horní bajt, HDR se
+/// namapuje jako při zobrazení).
+pub fn rgb8_at(hdr: &CTIHeader, raw: &[u8], x: u32, y: u32) -> Option<[u8; 3]> {
+    if x >= hdr.width || y >= hdr.height {
+        return None;
+    }
+    let i = (y as usize) * (hdr.width as usize) + x as usize;
+    let hi = |off: usize| raw.get(off + 1).copied();
+    Some(match hdr.color_type {
+        1 | 6 | 7 => {
+            let l = *raw.get(i)?;
+            [l, l, l]
+        }
+        2 => {
+            let l = hi(i * 2)?;
+            [l, l, l]
+        }
+        3 => {
+            let p = raw.get(i * 3..i * 3 + 3)?;
+            [p[0], p[1], p[2]]
+        }
+        4 => {
+            let p = raw.get(i * 4..i * 4 + 3)?;
+            [p[0], p[1], p[2]]
+        }
+        5 => [hi(i * 6)?, hi(i * 6 + 2)?, hi(i * 6 + 4)?],
+        8 => {
+            let p = cmyk::to_rgb(raw.get(i * 4..i * 4 + 4)?);
+            [p[0], p[1], p[2]]
+        }
+        9 => {
+            let p = hdr::to_rgb8(raw.get(i * 4..i * 4 + 4)?, 1);
+            [p[0], p[1], p[2]]
+        }
+        10 => {
+            let p = hdr::to_rgb8(raw.get(i * 12..i * 12 + 12)?, 3);
+            [p[0], p[1], p[2]]
+        }
+        _ => return None,
+    })
+}
@@ -0,0 +1,123 @@
+use eframe::egui;
+
+/// Přehrávání a listování vícesnímkových souborů (sekvence skenů, stránky dokumentu).
+pub struct Playback {
+    pub count: u32,
+    pub frame: u32,
+    pub playing: bool,
+    pub fps: f32,
+    pub looping: bool,
+    /// Zobrazit sloupec náhledů stránek.
+    pub thumbnails: bool,
+    /// Čas (egui `input.time`), kdy se má přejít na další snímek.
+    next_at: f64,
+}
+
+impl Default for Playback {
+    fn default() -> Self {
+        Self {
+            count: 1,
+            frame: 0,
+            playing: false,
+            fps: 4.0,
+            looping: true,
+            thumbnails: true,
+            next_at: 0.0,
+        }
+    }
+}
+
+impl Playback {
+    /// Nový soubor: první snímek, zastaveno.
+    pub fn reset(&mut self, count: u32) {
+        self.count = count.max(1);
+        self.frame = 0;
+        self.playing = false;
+    }
+
+    pub fn is_sequence(&self) -> bool {
+        self.count > 1
+    }
+
+    /// Snímek posunutý o `delta` (na okrajích se zastaví).
+    pub fn step(&self, delta: i64) -> Option<u32> {
+        let n = (self.frame as i64 + delta).clamp(0, self.count as i64 - 1) as u32;
+        (n != self.frame).then_some(n)
+    }
+
+    /// Spustí/zastaví přehrávání; bez smyčky se z posledního snímku začne znovu od prvního.
+    pub fn toggle(&mut self, now: f64) -> Option<u32> {
+        self.playing = !self.playing && self.is_sequence();
+        self.next_at = now + 1.0 / self.fps as f64;
+        (self.playing && !self.looping && self.frame + 1 == self.count).then_some(0)
+    }
+
+    /// Při přehrávání vrátí další snímek, když uplynul jeho čas. `ready` = aktuální snímek
+    /// je celý načtený (pomalé dekódování přehrávání zpomalí, snímky se nepřeskakují).
+    pub fn tick(&mut self, ctx: &egui::Context, ready: bool) -> Option<u32> {
+        if !self.playing || !ready {
+            return None;
+        }
+        let now = ctx.input(|i| i.time);
+        if now < self.next_at {
+            ctx.request_repaint_after(std::time::Duration::from_secs_f64(self.next_at - now));
+            return None;
+        }
+        let next = if self.frame + 1 < self.count {
+            self.frame + 1
+        } else if self.looping {
+            0
+        } else {
+            self.playing = false;
+            return None;
+        };
+        self.next_at = now + 1.0 / self.fps as f64;
+        Some(next)
+    }
+
+    /// Ovládací lišta; vrátí snímek, na který se má přepnout.
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> Option<u32> {
+        let mut goto = None;
+        ui.horizontal(|ui| {
+            if ui.button("⏮").on_hover_text("First page").clicked() {
+                goto = Some(0);
+            }
+            if ui.button("⏴").on_hover_text("Previous page").clicked() {
+                goto = self.step(-1);
+            }
+            let icon = if self.playing { "⏸" } else { "▶" };
+            if ui.button(icon).on_hover_text("Play / pause").clicked() {
+                goto = self.toggle(ui.input(|i| i.time));
+            }
+            if ui.button("⏵").on_hover_text("Next page").clicked() {
+                goto = self.step(1);
+            }
+            if ui.button("⏭").on_hover_text("Last page").clicked() {
+                goto = Some(self.count - 1);
+            }
+            let mut shown = self.frame + 1;
+            ui.label("Page");
+            let spinner = ui.add(
+                egui::DragValue::new(&mut shown)
+                    .range(1..=self.count)
+                    .speed(0.05),
+            );
+            ui.label(format!("of {}", self.count));
+            let slider = ui.add(egui::Slider::new(&mut shown, 1..=self.count).show_value(false));
+            if spinner.changed() || slider.changed() {
+                goto = Some(shown - 1);
+            }
+            ui.separator();
+            ui.add(
+                egui::DragValue::new(&mut self.fps)
+                    .range(0.5..=60.0)
+                    .speed(0.1)
+                    .suffix(" fps"),
+            );
+            ui.checkbox(&mut self.looping, "Loop");
+            ui.separator();
+            ui.checkbox(&mut self.thumbnails, "Thumbnails");
+        });
+        goto.filter(|&n| n != self.frame)
+    }
+}
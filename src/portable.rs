@@ -0,0 +1,180 @@
+//! Přenosný balíček pro hluboký zoom bez serveru: `{stem}.html` s vestavěným prohlížečem
+//! (viewer.html) a zmenšená pyramida dlaždic ve stejném tvaru jako DZI (`{stem}_files/`).
+//! Varianta do jednoho souboru vloží dlaždice do HTML jako data URI – pro e-mail.
+
+use anyhow::{Context, Result, bail, ensure};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use image::imageops::FilterType;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::cti::CTIDecoder;
+use crate::dzi::{self, DziFormat, DziOptions};
+use crate::export::to_dynamic_image;
+use crate::iiif::percent_encode;
+
+const TEMPLATE: &str = include_str!("viewer.html");
+
+#[derive(Debug, Clone)]
+pub struct HtmlOptions {
+    /// Delší strana zmenšeného obrázku (větší se zmenší, menší zůstane).
+    pub max_size: u32,
+    /// Všechno v jednom HTML.
+    pub single_file: bool,
+    /// Titulek stránky (jinak název souboru).
+    pub title: Option<String>,
+    /// Dlaždice, formát, snímek a přepsání jako u DZI.
+    pub tiles: DziOptions,
+}
+
+impl Default for HtmlOptions {
+    fn default() -> Self {
+        Self {
+            max_size: 8192,
+            single_file: false,
+            title: None,
+            tiles: DziOptions {
+                tile_size: 256,
+                overlap: 1,
+                jpeg_quality: 85,
+                ..DziOptions::default()
+            },
+        }
+    }
+}
+
+/// Nastavení prohlížeče vložené do stránky jako `CONFIG`.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ViewerConfig {
+    name: String,
+    width: u32,
+    height: u32,
+    source_width: u32,
+    tile_size: u32,
+    overlap: u32,
+    format: &'static str,
+    max_level: u32,
+    /// Relativní cesta k pyramidě (`{stem}_files/`).
+    base: String,
+    /// Dlaždice jako data URI (`"úroveň/sloupec_řádek"`), jen v jednom souboru.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tiles: Option<BTreeMap<String, String>>,
+}
+
+/// Zapíše balíček `src` do `out_dir`; vrací cestu k HTML.
+pub fn export_html(src: &Path, out_dir: &Path, opts: &HtmlOptions) -> Result<PathBuf> {
+    ensure!(opts.max_size > 0, "maximum size must be positive");
+    ensure!(opts.tiles.tile_size > 0, "tile size must be positive");
+    let name = src
+        .file_name()
+        .context("input has no file name")?
+        .to_string_lossy()
+        .into_owned();
+    let stem = src.file_stem().unwrap_or_default().to_string_lossy();
+    let html_path = out_dir.join(format!("{stem}.html"));
+    let files = out_dir.join(format!("{stem}_files"));
+    if html_path.exists() || files.exists() {
+        if !opts.tiles.overwrite {
+            bail!("{} already exists", html_path.display());
+        }
+        if files.exists() {
+            std::fs::remove_dir_all(&files)
+                .with_context(|| format!("remove {}", files.display()))?;
+        }
+    }
+
+    let (hdr, raw) = CTIDecoder::decode_frame(src, opts.tiles.frame)?;
+    let mut img = dzi::to_8bit(to_dynamic_image(&hdr, raw)?);
+    let longest = hdr.width.max(hdr.height);
+    if longest > opts.max_size {
+        let k = f64::from(opts.max_size) / f64::from(longest);
+        let w = ((f64::from(hdr.width) * k).round() as u32).max(1);
+        let h = ((f64::from(hdr.height) * k).round() as u32).max(1);
+        img = img.resize_exact(w, h, FilterType::Lanczos3);
+    }
+    let (w, h) = (img.width(), img.height());
+    let top = dzi::max_level(w, h);
+
+    // do jednoho souboru: dlaždice přes dočasnou složku
+    let pyramid = if opts.single_file {
+        out_dir.join(format!(".{stem}_files.tmp"))
+    } else {
+        files
+    };
+    if pyramid.exists() {
+        std::fs::remove_dir_all(&pyramid)
+            .with_context(|| format!("remove {}", pyramid.display()))?;
+    }
+    let written = dzi::write_pyramid(img, top, &pyramid, &opts.tiles);
+    let tiles = match written {
+        Ok(()) if opts.single_file => {
+            let tiles = inline_tiles(&pyramid, top, opts.tiles.format);
+            let _ = std::fs::remove_dir_all(&pyramid);
+            Some(tiles?)
+        }
+        Ok(()) => None,
+        Err(e) => {
+            let _ = std::fs::remove_dir_all(&pyramid);
+            return Err(e);
+        }
+    };
+
+    let config = ViewerConfig {
+        name: name.clone(),
+        width: w,
+        height: h,
+        source_width: hdr.width,
+        tile_size: opts.tiles.tile_size,
+        overlap: opts.tiles.overlap,
+        format: opts.tiles.format.extension(),
+        max_level: top,
+        base: format!("{}/", percent_encode(&format!("{stem}_files"))),
+        tiles,
+    };
+    // `</` by v <script> ukončilo skript
+    let config = serde_json::to_string(&config)?.replace("</", "<\\/");
+    let title = opts.title.as_deref().unwrap_or(&name);
+    let (head, rest) = TEMPLATE
+        .split_once("{{TITLE}}")
+        .context("bad viewer template")?;
+    let (mid, tail) = rest
+        .split_once("{{CONFIG}}")
+        .context("bad viewer template")?;
+    let html = format!("{head}{}{mid}{config}{tail}", escape_html(title));
+    std::fs::write(&html_path, html).with_context(|| format!("write {}", html_path.display()))?;
+    Ok(html_path)
+}
+
+/// Dlaždice všech úrovní jako data URI.
+fn inline_tiles(pyramid: &Path, top: u32, format: DziFormat) -> Result<BTreeMap<String, String>> {
+    let mime = match format {
+        DziFormat::Jpeg => "image/jpeg",
+        DziFormat::Png => "image/png",
+    };
+    let mut tiles = BTreeMap::new();
+    for level in 0..=top {
+        let dir = pyramid.join(level.to_string());
+        for entry in std::fs::read_dir(&dir).with_context(|| format!("read {}", dir.display()))? {
+            let path = entry?.path();
+            let Some(key) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            let data = std::fs::read(&path).with_context(|| format!("read {}", path.display()))?;
+            tiles.insert(
+                format!("{level}/{key}"),
+                format!("data:{mime};base64,{}", BASE64.encode(data)),
+            );
+        }
+    }
+    Ok(tiles)
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
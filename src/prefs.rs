@@ -0,0 +1,518 @@
+use eframe::egui::{self, Color32, Rect, Stroke, TextureFilter, ThemePreference, Visuals};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::a11y;
+use crate::cti::CTIHeader;
+use crate::display::{self, MonitorProfile};
+use crate::lens::{self, LensProfile};
+use crate::metaform::{self, FormField};
+use crate::export::ExportFormat;
+use crate::presets::{self, Preset};
+use crate::shortcuts::Shortcuts;
+use crate::stats::{self, UsageStats};
+use crate::update::{self, Updater};
+use crate::workdirs::{self, Area, Retention};
+
+/// Uživatelská nastavení (ukládají se přes `eframe::Storage`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Preferences {
+    /// Zoom po otevření souboru.
+    pub open_zoom: OpenZoom,
+    /// Po spuštění znovu otevřít poslední soubor se stejnou stránkou a pohledem.
+    pub restore_last_view: bool,
+    /// Znovu načíst otevřený soubor, když se změní na disku.
+    pub reload_on_change: bool,
+    /// Fyzické rozlišení monitoru (pixely na palec) pro zoom „Print size“.
+    pub screen_ppi: f32,
+    /// Světlý/tmavý motiv UI.
+    pub theme: Theme,
+    /// Vysoký kontrast (WCAG AAA, ≥ 7:1).
+    pub high_contrast: bool,
+    /// Zvětšení UI nezávislé na škálování OS.
+    pub ui_scale: f32,
+    /// Pozadí plátna pod obrázkem.
+    pub background: Background,
+    /// Filtrování textury při zvětšení.
+    pub filter: Filter,
+    /// Náhled celého obrázku s vyznačenou viditelnou částí (jen když se obrázek nevejde).
+    pub navigator: bool,
+    /// Limit cache dekódovaných obrázků (MiB).
+    pub cache_mb: u32,
+    /// Limit cache rozbalených dlaždic pro výřezy a opakované dekódování (MiB).
+    pub tile_cache_mb: u32,
+    /// Ukládat zmenšené náhledy velkých souborů na disk (rychlé znovuotevření).
+    pub preview_cache: bool,
+    /// Úklid dočasných souborů a diskové cache (také při spuštění).
+    pub temp_files: Retention,
+    pub disk_cache: Retention,
+    /// Výchozí formát pro export derivátů.
+    pub export_format: ExportFormat,
+    /// Autor nových anotací.
+    pub author: String,
+    /// Korekční profily objektivů pro export.
+    pub lens_profiles: Vec<LensProfile>,
+    /// Předvolby kodéru, metadat a korekcí (import/export jako TOML).
+    pub presets: Vec<Preset>,
+    /// Pole metadat vyplňovaná při převodu do CTI.
+    pub metadata_form: Vec<FormField>,
+    /// Podepsaná vydání (URL nebo složka); prázdné = bez aktualizací.
+    pub update_channel: String,
+    /// Hledat nové vydání při spuštění.
+    pub check_updates: bool,
+    /// Zapisovat místní statistiky používání.
+    pub usage_stats: bool,
+    /// Převádět zobrazení do ICC profilu monitoru, na kterém je okno.
+    pub color_management: bool,
+    pub monitor_profiles: Vec<MonitorProfile>,
+    /// ICC profil tisku pro zobrazení CMYK souborů; `None` = naivní převod.
+    pub cmyk_profile: Option<PathBuf>,
+    /// Příkaz a jazyky OCR.
+    #[cfg(feature = "ocr")]
+    pub ocr: crate::ocr::OcrSettings,
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Self {
+            open_zoom: OpenZoom::Fit,
+            restore_last_view: true,
+            reload_on_change: true,
+            screen_ppi: 96.0,
+            theme: Theme::System,
+            high_contrast: false,
+            ui_scale: 1.0,
+            background: Background::Theme,
+            filter: Filter::Linear,
+            navigator: true,
+            cache_mb: 512,
+            tile_cache_mb: 256,
+            preview_cache: true,
+            temp_files: Area::Temp.default_retention(),
+            disk_cache: Area::Cache.default_retention(),
+            export_format: ExportFormat::Png,
+            author: std::env::var("USER")
+                .or_else(|_| std::env::var("USERNAME"))
+                .unwrap_or_default(),
+            lens_profiles: Vec::new(),
+            presets: Vec::new(),
+            metadata_form: metaform::default_form(),
+            update_channel: update::DEFAULT_CHANNEL.to_owned(),
+            check_updates: true,
+            usage_stats: false,
+            color_management: false,
+            monitor_profiles: Vec::new(),
+            cmyk_profile: None,
+            #[cfg(feature = "ocr")]
+            ocr: Default::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OpenZoom {
+    Fit,
+    ActualSize,
+    /// Fyzická velikost podle DPI (soubory bez DPI jako Fit).
+    PrintSize,
+    /// Ponechat zoom předchozího obrázku.
+    Keep,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Theme {
+    System,
+    Dark,
+    Light,
+}
+
+impl Theme {
+    pub fn preference(self) -> ThemePreference {
+        match self {
+            Theme::System => ThemePreference::System,
+            Theme::Dark => ThemePreference::Dark,
+            Theme::Light => ThemePreference::Light,
+        }
+    }
+}
+
+impl Preferences {
+    /// Promítne motiv, kontrast a měřítko UI do kontextu.
+    pub fn apply_appearance(&self, ctx: &egui::Context) {
+        ctx.set_theme(self.theme.preference());
+        for (theme, base) in [
+            (egui::Theme::Dark, Visuals::dark()),
+            (egui::Theme::Light, Visuals::light()),
+        ] {
+            let v = if self.high_contrast {
+                high_contrast(base)
+            } else {
+                base
+            };
+            ctx.set_visuals_of(theme, v);
+        }
+        ctx.set_zoom_factor(self.ui_scale.clamp(0.5, 3.0));
+    }
+}
+
+/// Černobílý motiv s výrazným akcentem: text 21:1, akcent ≥ 7:1 vůči pozadí.
+fn high_contrast(mut v: Visuals) -> Visuals {
+    let (fg, bg, accent) = if v.dark_mode {
+        (
+            Color32::WHITE,
+            Color32::BLACK,
+            Color32::from_rgb(255, 255, 0),
+        )
+    } else {
+        (Color32::BLACK, Color32::WHITE, Color32::from_rgb(0, 0, 160))
+    };
+    v.panel_fill = bg;
+    v.window_fill = bg;
+    v.extreme_bg_color = bg;
+    v.faint_bg_color = bg;
+    v.code_bg_color = bg;
+    v.window_stroke = Stroke::new(2.0, fg);
+    v.weak_text_color = Some(fg);
+    v.hyperlink_color = accent;
+    v.disabled_alpha = 0.75;
+    for w in [
+        &mut v.widgets.noninteractive,
+        &mut v.widgets.inactive,
+        &mut v.widgets.hovered,
+        &mut v.widgets.active,
+        &mut v.widgets.open,
+    ] {
+        w.bg_fill = bg;
+        w.weak_bg_fill = bg;
+        w.bg_stroke = Stroke::new(1.5, fg);
+        w.fg_stroke = Stroke::new(1.5, fg);
+    }
+    v.widgets.hovered.bg_stroke = Stroke::new(2.5, accent);
+    v.widgets.active.bg_stroke = Stroke::new(2.5, accent);
+    // vybrané položky: text v barvě pozadí na akcentu
+    v.selection.bg_fill = accent;
+    v.selection.stroke = Stroke::new(2.0, bg);
+    v
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Background {
+    Theme,
+    Black,
+    Gray,
+    White,
+    Checkerboard,
+    Custom([u8; 3]),
+}
+
+impl Background {
+    /// Předvolby nabízené v toolbaru (bez vlastní barvy).
+    pub const PRESETS: [Background; 5] = [
+        Background::Theme,
+        Background::Black,
+        Background::Gray,
+        Background::White,
+        Background::Checkerboard,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Background::Theme => "Theme",
+            Background::Black => "Black",
+            Background::Gray => "Gray",
+            Background::White => "White",
+            Background::Checkerboard => "Checkerboard",
+            Background::Custom(_) => "Custom",
+        }
+    }
+
+    /// Barva výplně; `None` = ponechat výchozí z motivu.
+    pub fn fill(self) -> Option<Color32> {
+        match self {
+            Background::Theme => None,
+            Background::Black => Some(Color32::BLACK),
+            Background::Gray => Some(Color32::from_gray(128)),
+            Background::White => Some(Color32::WHITE),
+            Background::Checkerboard => Some(Color32::from_gray(204)),
+            Background::Custom([r, g, b]) => Some(Color32::from_rgb(r, g, b)),
+        }
+    }
+
+    /// Vzor přes plnou výplň (jen šachovnice).
+    pub fn paint_pattern(self, painter: &egui::Painter, rect: Rect) {
+        if self != Background::Checkerboard {
+            return;
+        }
+        const CELL: f32 = 16.0;
+        let dark = Color32::from_gray(153);
+        let cols = (rect.width() / CELL).ceil() as i32;
+        let rows = (rect.height() / CELL).ceil() as i32;
+        for row in 0..rows {
+            for col in (row % 2..cols).step_by(2) {
+                let min = rect.min + egui::vec2(col as f32 * CELL, row as f32 * CELL);
+                let cell = Rect::from_min_size(min, egui::Vec2::splat(CELL)).intersect(rect);
+                painter.rect_filled(cell, 0.0, dark);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Filter {
+    Linear,
+    Nearest,
+}
+
+impl Filter {
+    pub fn texture_filter(self) -> TextureFilter {
+        match self {
+            Filter::Linear => TextureFilter::Linear,
+            Filter::Nearest => TextureFilter::Nearest,
+        }
+    }
+}
+
+/// Obrys platební karty (ISO/IEC 7810 ID-1, 85,60 × 53,98 mm) v rozměru podle `ppi`;
+/// přiložená karta musí přesně lícovat.
+fn calibration_ui(ui: &mut egui::Ui, ppi: f32) {
+    const CARD_MM: egui::Vec2 = egui::vec2(85.60, 53.98);
+    let points_per_mm = ppi / 25.4 / ui.ctx().pixels_per_point();
+    ui.vertical(|ui| {
+        ui.weak("Hold a bank card against the screen and adjust until it matches the outline");
+        let (rect, _) = ui.allocate_exact_size(CARD_MM * points_per_mm, egui::Sense::hover());
+        let stroke = ui.visuals().widgets.noninteractive.fg_stroke;
+        ui.painter().rect_stroke(
+            rect,
+            3.18 * points_per_mm,
+            Stroke::new(1.0, stroke.color),
+            egui::StrokeKind::Inside,
+        );
+    });
+}
+
+/// Okno Preferences.
+pub fn prefs_window(
+    ctx: &egui::Context,
+    open: &mut bool,
+    prefs: &mut Preferences,
+    shortcuts: &mut Shortcuts,
+    updater: &mut Updater,
+    usage: &mut UsageStats,
+    image: Option<(&CTIHeader, &[u8])>,
+) {
+    a11y::modal_dialog(ctx, "Preferences", open, |ui| {
+        egui::CollapsingHeader::new("General")
+            .default_open(true)
+            .show(ui, |ui| general_ui(ui, prefs, updater));
+        egui::CollapsingHeader::new("Display color")
+            .default_open(false)
+            .show(ui, |ui| {
+                display::profiles_ui(
+                    ui,
+                    &mut prefs.color_management,
+                    &mut prefs.monitor_profiles,
+                    display::monitor_key(ctx).as_deref(),
+                );
+                ui.separator();
+                display::cmyk_ui(ui, &mut prefs.cmyk_profile);
+            });
+        egui::CollapsingHeader::new("Lens profiles")
+            .default_open(false)
+            .show(ui, |ui| lens::profiles_ui(ui, &mut prefs.lens_profiles, image));
+        egui::CollapsingHeader::new("Presets")
+            .default_open(false)
+            .show(ui, |ui| presets::presets_ui(ui, &mut prefs.presets, &prefs.lens_profiles));
+        egui::CollapsingHeader::new("Metadata form")
+            .default_open(false)
+            .show(ui, |ui| metaform::config_ui(ui, &mut prefs.metadata_form));
+        egui::CollapsingHeader::new("Keyboard shortcuts")
+            .default_open(false)
+            .show(ui, |ui| shortcuts.ui(ui));
+        egui::CollapsingHeader::new("Usage statistics")
+            .default_open(false)
+            .show(ui, |ui| stats::stats_ui(ui, &mut prefs.usage_stats, usage));
+    });
+}
+
+fn general_ui(ui: &mut egui::Ui, prefs: &mut Preferences, updater: &mut Updater) {
+    egui::Grid::new("prefs-general")
+        .num_columns(2)
+        .spacing([12.0, 6.0])
+        .show(ui, |ui| {
+            ui.label("Zoom on open");
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut prefs.open_zoom, OpenZoom::Fit, "Fit");
+                ui.radio_value(&mut prefs.open_zoom, OpenZoom::ActualSize, "1:1");
+                ui.radio_value(&mut prefs.open_zoom, OpenZoom::PrintSize, "Print size")
+                    .on_hover_text("Files without DPI open fitted to the window");
+                ui.radio_value(&mut prefs.open_zoom, OpenZoom::Keep, "Keep previous");
+            });
+            ui.end_row();
+
+            ui.label("On startup");
+            ui.checkbox(&mut prefs.restore_last_view, "Reopen the last file and view")
+                .on_hover_text("Same page, zoom, position and rotation as at closing");
+            ui.end_row();
+
+            ui.label("File changes");
+            ui.checkbox(&mut prefs.reload_on_change, "Reload when the file changes on disk")
+                .on_hover_text("Keeps page, zoom and position; useful with an encoder running in a loop");
+            ui.end_row();
+
+            ui.label("Screen resolution");
+            ui.add(
+                egui::DragValue::new(&mut prefs.screen_ppi)
+                    .range(50.0..=600.0)
+                    .speed(0.2)
+                    .fixed_decimals(1)
+                    .suffix(" ppi"),
+            )
+            .on_hover_text("Physical pixels per inch of this monitor, used by Print size zoom");
+            ui.end_row();
+
+            ui.label("");
+            calibration_ui(ui, prefs.screen_ppi);
+            ui.end_row();
+
+            ui.label("Theme");
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut prefs.theme, Theme::System, "System");
+                ui.radio_value(&mut prefs.theme, Theme::Dark, "Dark");
+                ui.radio_value(&mut prefs.theme, Theme::Light, "Light");
+                ui.checkbox(&mut prefs.high_contrast, "High contrast");
+            });
+            ui.end_row();
+
+            ui.label("UI scale");
+            ui.add(
+                egui::Slider::new(&mut prefs.ui_scale, 0.5..=3.0)
+                    .step_by(0.05)
+                    .fixed_decimals(2)
+                    .suffix("×"),
+            );
+            ui.end_row();
+
+            ui.label("Background");
+            ui.horizontal_wrapped(|ui| {
+                let bg = &mut prefs.background;
+                for b in Background::PRESETS {
+                    ui.radio_value(bg, b, b.label());
+                }
+                let mut rgb = match *bg {
+                    Background::Custom(c) => c,
+                    _ => [64, 64, 64],
+                };
+                let custom = matches!(bg, Background::Custom(_));
+                if ui.radio(custom, "Custom").clicked() || custom {
+                    ui.color_edit_button_srgb(&mut rgb);
+                    *bg = Background::Custom(rgb);
+                }
+            });
+            ui.end_row();
+
+            ui.label("Magnification filter");
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut prefs.filter, Filter::Linear, "Smooth");
+                ui.radio_value(&mut prefs.filter, Filter::Nearest, "Pixelated");
+            });
+            ui.end_row();
+
+            ui.label("Navigator");
+            ui.checkbox(&mut prefs.navigator, "Show an overview when zoomed in")
+                .on_hover_text("Click or drag in the overview to move the visible area");
+            ui.end_row();
+
+            ui.label("Image cache");
+            ui.add(egui::Slider::new(&mut prefs.cache_mb, 0..=8192).suffix(" MiB"));
+            ui.end_row();
+
+            ui.label("Tile cache");
+            ui.add(egui::Slider::new(&mut prefs.tile_cache_mb, 0..=8192).suffix(" MiB"))
+                .on_hover_text("Decoded tiles reused by crops and when reopening a page");
+            ui.end_row();
+
+            ui.label("Preview cache");
+            ui.checkbox(&mut prefs.preview_cache, "Keep previews of large files on disk")
+                .on_hover_text("Shown immediately when the file is opened again");
+            ui.end_row();
+
+            ui.label("Temporary files");
+            retention_ui(ui, Area::Temp, &mut prefs.temp_files);
+            ui.end_row();
+
+            ui.label("Disk cache");
+            retention_ui(ui, Area::Cache, &mut prefs.disk_cache);
+            ui.end_row();
+
+            ui.label("Export format");
+            ui.horizontal(|ui| {
+                for f in ExportFormat::ALL {
+                    ui.radio_value(&mut prefs.export_format, f, f.as_str());
+                }
+            });
+            ui.end_row();
+
+            ui.label("Annotation author");
+            ui.text_edit_singleline(&mut prefs.author);
+            ui.end_row();
+
+            ui.label("Updates");
+            ui.vertical(|ui| {
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut prefs.update_channel)
+                            .hint_text("URL or folder with signed releases"),
+                    );
+                    let set = !prefs.update_channel.trim().is_empty();
+                    let check = ui.add_enabled(set, egui::Button::new("Check now"));
+                    if check.clicked() {
+                        updater.check(ui.ctx(), &prefs.update_channel, true);
+                    }
+                });
+                ui.checkbox(&mut prefs.check_updates, "Check on startup");
+                if let Some(status) = updater.status() {
+                    ui.weak(status);
+                }
+            });
+            ui.end_row();
+        });
+}
+
+/// Limity úklidu složky a tlačítko pro okamžitý úklid (výsledek vedle něj).
+fn retention_ui(ui: &mut egui::Ui, area: Area, rule: &mut Retention) {
+    ui.horizontal(|ui| {
+        let hint = "0 = no limit; least recently used files go first";
+        ui.label("delete after");
+        ui.add(
+            egui::DragValue::new(&mut rule.max_age_days)
+                .range(0..=3650)
+                .suffix(" days"),
+        )
+        .on_hover_text(hint);
+        ui.label("or above");
+        ui.add(
+            egui::DragValue::new(&mut rule.max_mb)
+                .range(0..=1 << 20)
+                .speed(16)
+                .suffix(" MiB"),
+        )
+        .on_hover_text(hint);
+        let id = ui.id().with(area.label());
+        let dir = area.path();
+        if ui
+            .button("Clean up now")
+            .on_hover_text(dir.display().to_string())
+            .clicked()
+        {
+            let msg = match workdirs::prune(&dir, *rule, false) {
+                Ok(p) => p.summary(false),
+                Err(e) => format!("{e:#}"),
+            };
+            ui.data_mut(|d| d.insert_temp(id, msg));
+        }
+        if let Some(msg) = ui.data(|d| d.get_temp::<String>(id)) {
+            ui.weak(msg);
+        }
+    });
+}
@@ -0,0 +1,472 @@
+use anyhow::{Context, Result, ensure};
+use eframe::egui;
+use rfd::FileDialog;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use crate::annotations;
+use crate::cti::{CTIMetadata, CompressionId, EncodeParams};
+use crate::lens::{LensCorrection, LensProfile};
+use crate::naming;
+
+/// Zástupné symboly v hodnotách metadat předvolby.
+pub const METADATA_HELP: &str = "{stem} file name without extension, {dir} parent folder, \
+     {date} today, {datetime} now (UTC), {user} login name";
+
+/// Pojmenovaná sada nastavení sdílená mezi pracovišti: parametry kodéru, šablona metadat
+/// a korekce pro export. Ukládá se jako přenositelný TOML soubor, aby všechna pracoviště
+/// kódovala stejně.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Preset {
+    pub name: String,
+    pub description: String,
+    pub encode: EncodeSettings,
+    /// Metadata zapsaná do každého souboru; hodnoty mohou obsahovat [`METADATA_HELP`].
+    pub metadata: BTreeMap<String, String>,
+    /// Korekce objektivu při exportu.
+    pub lens: Option<LensProfile>,
+    /// Reference pro korekci osvětlení při exportu.
+    pub flat_field: Option<PathBuf>,
+}
+
+impl Default for Preset {
+    fn default() -> Self {
+        Self {
+            name: "New preset".into(),
+            description: String::new(),
+            encode: EncodeSettings::default(),
+            metadata: BTreeMap::new(),
+            lens: None,
+            flat_field: None,
+        }
+    }
+}
+
+/// Parametry kodéru v čitelné podobě (kodek jménem, ne číslem z formátu).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EncodeSettings {
+    pub codec: Codec,
+    pub level: i32,
+    pub tile_size: u32,
+    pub rct: bool,
+    pub file_hash: bool,
+    pub crc32c: bool,
+    /// Kvalita ztrátového kodeku (1..=100).
+    pub quality: u8,
+    /// Bitová hloubka šedotónových obrázků (8, 4 nebo 1).
+    pub gray_bits: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Codec {
+    Zstd,
+    Lz4,
+    Brotli,
+    Deflate,
+    /// Ztrátová DCT.
+    Dct,
+    None,
+}
+
+impl Default for EncodeSettings {
+    fn default() -> Self {
+        Self::from_params(&EncodeParams::default())
+    }
+}
+
+impl EncodeSettings {
+    pub fn from_params(p: &EncodeParams) -> Self {
+        let codec = match p.compression {
+            CompressionId::Lz4 => Codec::Lz4,
+            CompressionId::Brotli => Codec::Brotli,
+            CompressionId::Deflate => Codec::Deflate,
+            CompressionId::Dct => Codec::Dct,
+            CompressionId::None => Codec::None,
+            _ => Codec::Zstd,
+        };
+        Self {
+            codec,
+            level: p.level,
+            tile_size: p.tile_size,
+            rct: p.rct,
+            file_hash: p.file_hash,
+            crc32c: p.crc32c,
+            quality: p.quality,
+            gray_bits: p.gray_bits,
+        }
+    }
+
+    pub fn params(&self) -> EncodeParams {
+        EncodeParams {
+            tile_size: self.tile_size,
+            compression: match self.codec {
+                Codec::Zstd => CompressionId::Zstd,
+                Codec::Lz4 => CompressionId::Lz4,
+                Codec::Brotli => CompressionId::Brotli,
+                Codec::Deflate => CompressionId::Deflate,
+                Codec::Dct => CompressionId::Dct,
+                Codec::None => CompressionId::None,
+            },
+            level: self.level,
+            rct: self.rct,
+            file_hash: self.file_hash,
+            crc32c: self.crc32c,
+            quality: self.quality,
+            gray_bits: self.gray_bits,
+        }
+    }
+}
+
+/// Serde pro [`EncodeParams`] v podobě [`EncodeSettings`] (`#[serde(with = …)]`).
+pub mod encode_params {
+    use super::EncodeSettings;
+    use crate::cti::EncodeParams;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(params: &EncodeParams, s: S) -> Result<S::Ok, S::Error> {
+        EncodeSettings::from_params(params).serialize(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<EncodeParams, D::Error> {
+        EncodeSettings::deserialize(d).map(|s| s.params())
+    }
+}
+
+/// Vestavěné předvolby kodéru: v nabídce předvoleb vždy na začátku, v CLI jménem místo
+/// souboru (`--preset web-lossy`).
+pub fn builtin() -> &'static [Preset] {
+    static BUILTIN: LazyLock<Vec<Preset>> = LazyLock::new(|| {
+        let base = EncodeSettings::default();
+        let preset = |name: &str, description: &str, encode| Preset {
+            name: name.into(),
+            description: description.into(),
+            encode,
+            ..Preset::default()
+        };
+        vec![
+            preset(
+                "archival-lossless",
+                "Lossless Zstd at a high level with the whole-file hash, for long-term storage",
+                EncodeSettings {
+                    codec: Codec::Zstd,
+                    level: 19,
+                    rct: true,
+                    file_hash: true,
+                    ..base
+                },
+            ),
+            preset(
+                "web-lossy",
+                "Lossy DCT at quality 80 without the file hash, for web delivery \
+                 (8-bit images only)",
+                EncodeSettings {
+                    codec: Codec::Dct,
+                    quality: 80,
+                    file_hash: false,
+                    ..base
+                },
+            ),
+            preset(
+                "fast",
+                "LZ4 without the file hash: quickest to write and read, larger files",
+                EncodeSettings {
+                    codec: Codec::Lz4,
+                    file_hash: false,
+                    ..base
+                },
+            ),
+        ]
+    });
+    &BUILTIN
+}
+
+impl Preset {
+    /// Načte předvolbu z TOML a zkontroluje rozsahy hodnot.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        let preset: Preset = toml::from_str(&text)?;
+        preset.check()?;
+        Ok(preset)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    fn check(&self) -> Result<()> {
+        let e = &self.encode;
+        ensure!(
+            (16..=8192).contains(&e.tile_size),
+            "tile_size {} is out of range 16..=8192",
+            e.tile_size
+        );
+        let levels = match e.codec {
+            Codec::Brotli => 0..=11,
+            Codec::Deflate => 0..=9,
+            _ => 1..=22,
+        };
+        ensure!(
+            levels.contains(&e.level),
+            "level {} is out of range {levels:?}",
+            e.level
+        );
+        ensure!(
+            (1..=100).contains(&e.quality),
+            "quality {} is out of range 1..=100",
+            e.quality
+        );
+        for (key, value) in &self.metadata {
+            ensure!(!key.trim().is_empty(), "empty metadata key");
+            check_template(value).with_context(|| format!("metadata {key}"))?;
+        }
+        Ok(())
+    }
+
+    /// Korekce objektivu pro export.
+    pub fn lens_correction(&self) -> LensCorrection {
+        self.lens
+            .clone()
+            .map_or(LensCorrection::Off, LensCorrection::Profile)
+    }
+}
+
+/// Metadata pro soubor převáděný z `src` (dosadí zástupné symboly).
+pub fn render_metadata(template: &BTreeMap<String, String>, src: &Path) -> Result<CTIMetadata> {
+    let now = annotations::now_rfc3339();
+    let mut meta = CTIMetadata::default();
+    for (key, value) in template {
+        meta.set(key, expand(value, src, &now)?);
+    }
+    Ok(meta)
+}
+
+/// Kontrola hodnoty se zástupnými symboly (neznámé symboly, neuzavřené závorky).
+pub fn check_template(value: &str) -> Result<()> {
+    expand(value, Path::new("x"), "x").map(|_| ())
+}
+
+fn expand(value: &str, src: &Path, now: &str) -> Result<String> {
+    let os = |s: Option<&std::ffi::OsStr>| s.unwrap_or_default().to_string_lossy().into_owned();
+    naming::expand(value, |key| {
+        Some(match key {
+            "stem" => os(src.file_stem()),
+            "dir" => os(src.parent().and_then(Path::file_name)),
+            "date" => now.get(..10).unwrap_or(now).to_string(),
+            "datetime" => now.to_string(),
+            "user" => std::env::var("USER")
+                .or_else(|_| std::env::var("USERNAME"))
+                .unwrap_or_default(),
+            _ => return None,
+        })
+    })
+}
+
+/// Výběr předvolby v dialozích; vrátí zvolenou předvolbu. `with_builtin` = nabídnout
+/// i [`builtin`] předvolby (mají jen parametry kodéru).
+pub fn combo<'a>(
+    ui: &mut egui::Ui,
+    id: &str,
+    presets: &'a [Preset],
+    with_builtin: bool,
+) -> Option<&'a Preset> {
+    let builtin = if with_builtin { builtin() } else { &[] };
+    let mut chosen = None;
+    ui.horizontal(|ui| {
+        ui.label("Preset");
+        ui.add_enabled_ui(!presets.is_empty() || !builtin.is_empty(), |ui| {
+            egui::ComboBox::from_id_salt(id)
+                .selected_text("Apply…")
+                .show_ui(ui, |ui| {
+                    for (i, p) in builtin.iter().chain(presets).enumerate() {
+                        if i == builtin.len() && i > 0 {
+                            ui.separator();
+                        }
+                        let item = ui.selectable_label(false, &p.name);
+                        let item = if p.description.is_empty() {
+                            item
+                        } else {
+                            item.on_hover_text(&p.description)
+                        };
+                        if item.clicked() {
+                            chosen = Some(p);
+                        }
+                    }
+                })
+                .response
+                .on_hover_text("Presets are managed in Preferences → Presets");
+        });
+    });
+    chosen
+}
+
+/// Správa předvoleb v Preferences: úpravy, import a export TOML.
+pub fn presets_ui(ui: &mut egui::Ui, presets: &mut Vec<Preset>, lens_profiles: &[LensProfile]) {
+    let error_id = egui::Id::new("presets-error");
+    let mut error: Option<String> = ui.data(|d| d.get_temp(error_id)).flatten();
+    let mut remove = None;
+    for (i, p) in presets.iter_mut().enumerate() {
+        egui::CollapsingHeader::new(p.name.clone())
+            .id_salt(("preset", i))
+            .show(ui, |ui| {
+                preset_ui(ui, i, p, lens_profiles);
+                ui.horizontal(|ui| {
+                    if ui.button("Export…").clicked()
+                        && let Some(path) = FileDialog::new()
+                            .add_filter("Preset", &["toml"])
+                            .set_file_name(format!("{}.toml", p.name))
+                            .save_file()
+                    {
+                        error = p.save(&path).err().map(|e| format!("{e:#}"));
+                    }
+                    if ui.button("Remove").clicked() {
+                        remove = Some(i);
+                    }
+                });
+            });
+    }
+    if let Some(i) = remove {
+        presets.remove(i);
+    }
+    ui.horizontal(|ui| {
+        if ui.button("Add preset").clicked() {
+            presets.push(Preset::default());
+        }
+        if ui.button("Import…").clicked()
+            && let Some(paths) = FileDialog::new()
+                .add_filter("Preset", &["toml"])
+                .pick_files()
+        {
+            error = None;
+            for path in paths {
+                match Preset::load(&path) {
+                    // stejné jméno = novější verze sdílené předvolby
+                    Ok(p) => match presets.iter_mut().find(|q| q.name == p.name) {
+                        Some(q) => *q = p,
+                        None => presets.push(p),
+                    },
+                    Err(e) => error = Some(format!("{}: {e:#}", path.display())),
+                }
+            }
+        }
+    });
+    if let Some(e) = &error {
+        ui.colored_label(ui.visuals().error_fg_color, e);
+    }
+    ui.data_mut(|d| d.insert_temp(error_id, error));
+}
+
+fn preset_ui(ui: &mut egui::Ui, i: usize, p: &mut Preset, lens_profiles: &[LensProfile]) {
+    egui::Grid::new(("preset-grid", i))
+        .num_columns(2)
+        .spacing([12.0, 4.0])
+        .show(ui, |ui| {
+            ui.label("Name");
+            ui.text_edit_singleline(&mut p.name);
+            ui.end_row();
+            ui.label("Description");
+            ui.text_edit_singleline(&mut p.description);
+            ui.end_row();
+        });
+    ui.push_id(("preset-encode", i), |ui| {
+        let mut params = p.encode.params();
+        crate::convert::params_ui(ui, &mut params);
+        p.encode = EncodeSettings::from_params(&params);
+    });
+
+    ui.label("Metadata").on_hover_text(METADATA_HELP);
+    let mut rename = None;
+    let mut remove = None;
+    for (key, value) in p.metadata.iter_mut() {
+        ui.horizontal(|ui| {
+            let mut k = key.clone();
+            if ui
+                .add(egui::TextEdit::singleline(&mut k).desired_width(120.0))
+                .lost_focus()
+                && k != *key
+            {
+                rename = Some((key.clone(), k));
+            }
+            ui.text_edit_singleline(value).on_hover_text(METADATA_HELP);
+            if ui.small_button("🗑").on_hover_text("Remove").clicked() {
+                remove = Some(key.clone());
+            }
+        });
+    }
+    if let Some((old, new)) = rename
+        && !new.trim().is_empty()
+        && !p.metadata.contains_key(&new)
+        && let Some(v) = p.metadata.remove(&old)
+    {
+        p.metadata.insert(new, v);
+    }
+    if let Some(key) = remove {
+        p.metadata.remove(&key);
+    }
+    ui.horizontal(|ui| {
+        for key in [
+            CTIMetadata::DEVICE,
+            CTIMetadata::OPERATOR,
+            CTIMetadata::CAPTURE_DATE,
+            CTIMetadata::DPI,
+        ] {
+            if !p.metadata.contains_key(key) && ui.small_button(format!("+ {key}")).clicked() {
+                let value = if key == CTIMetadata::CAPTURE_DATE {
+                    "{date}"
+                } else {
+                    ""
+                };
+                p.metadata.insert(key.into(), value.into());
+            }
+        }
+        if ui.small_button("+ Entry").clicked() {
+            let key = (1..)
+                .map(|n| format!("Key{n}"))
+                .find(|k| !p.metadata.contains_key(k))
+                .unwrap_or_default();
+            p.metadata.insert(key, String::new());
+        }
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Lens correction");
+        let selected = p
+            .lens
+            .as_ref()
+            .map_or("Off", |l| l.name.as_str())
+            .to_string();
+        egui::ComboBox::from_id_salt(("preset-lens", i))
+            .selected_text(selected)
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut p.lens, None, "Off");
+                for l in lens_profiles {
+                    ui.selectable_value(&mut p.lens, Some(l.clone()), &l.name);
+                }
+            })
+            .response
+            .on_hover_text("The profile is copied into the preset so it travels with the file");
+    });
+    ui.horizontal(|ui| {
+        ui.label("Flat field");
+        let name = p
+            .flat_field
+            .as_deref()
+            .map_or("Off".into(), |f| f.display().to_string());
+        ui.monospace(name);
+        if ui.button("Choose…").clicked()
+            && let Some(path) = FileDialog::new()
+                .add_filter("Images", &["cti", "png", "tif", "tiff"])
+                .pick_file()
+        {
+            p.flat_field = Some(path);
+        }
+        if p.flat_field.is_some() && ui.button("Clear").clicked() {
+            p.flat_field = None;
+        }
+    });
+}
@@ -0,0 +1,74 @@
+//! Zmenšené náhledy velkých souborů v diskové cache. Při dalším otevření se náhled
+//! zobrazí hned a dlaždice plného rozlišení ho postupně překryjí. Klíčem je otisk obsahu
+//! ([`CTIDecoder::fingerprint`]), takže přejmenovaný soubor náhled najde a přepsaný ne.
+//! Náhled je sám CTI (LZ4, bez hashe), ať se čte stejným dekodérem.
+
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::cti::{CTIDecoder, CTIEncoder, CTIHeader, CompressionId, EncodeParams};
+use crate::export;
+use crate::workdirs::Area;
+
+/// Delší strana uloženého náhledu; menší soubory se dekódují dost rychle i bez něj.
+pub const MAX_SIDE: u32 = 4096;
+
+fn dir() -> PathBuf {
+    Area::Cache.path().join("previews")
+}
+
+fn preview_path(src: &Path, frame: u32) -> Result<PathBuf> {
+    let key: String = CTIDecoder::fingerprint(src)?
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    Ok(dir().join(format!("{key}-{frame}.cti")))
+}
+
+/// Uložený náhled snímku `frame` (hlavička s rozměry náhledu a RAW data).
+pub fn load(src: &Path, frame: u32) -> Option<(CTIHeader, Vec<u8>)> {
+    let path = preview_path(src, frame).ok()?;
+    CTIDecoder::decode_file(path).ok()
+}
+
+/// Má smysl náhled pro obrázek `hdr` ukládat?
+pub fn worth_storing(hdr: &CTIHeader) -> bool {
+    hdr.width.max(hdr.height) > MAX_SIDE && matches!(hdr.color_type, 1 | 3 | 4 | 6 | 7)
+}
+
+/// Zmenší dekódovaný snímek na [`MAX_SIDE`] a uloží ho (existující náhled se nepřepisuje).
+pub fn store(src: &Path, frame: u32, hdr: &CTIHeader, raw: &[u8]) -> Result<()> {
+    let path = preview_path(src, frame)?;
+    if path.exists() {
+        return Ok(());
+    }
+    let img = export::to_dynamic_image(hdr, raw.to_vec())?;
+    let k = MAX_SIDE as f32 / hdr.width.max(hdr.height) as f32;
+    let (w, h) = (
+        ((hdr.width as f32 * k).round() as u32).max(1),
+        ((hdr.height as f32 * k).round() as u32).max(1),
+    );
+    let (color_type, data) = export::from_dynamic_image(img.thumbnail_exact(w, h))?;
+    let params = EncodeParams {
+        compression: CompressionId::Lz4,
+        rct: false,
+        file_hash: false,
+        ..EncodeParams::default()
+    };
+    std::fs::create_dir_all(dir()).with_context(|| format!("create {}", dir().display()))?;
+    // zápis přes dočasný soubor, ať souběžné otevření nenajde poloviční náhled
+    let tmp = path.with_extension("cti.tmp");
+    CTIEncoder::encode_file(&tmp, w, h, color_type, &data, &params)?;
+    std::fs::rename(&tmp, &path)?;
+    Ok(())
+}
+
+/// Uloží náhled na pozadí; chyba se jen zapíše (náhled je jen zrychlení).
+pub fn store_in_background(src: PathBuf, frame: u32, hdr: CTIHeader, raw: Arc<Vec<u8>>) {
+    std::thread::spawn(move || {
+        if let Err(e) = store(&src, frame, &hdr, &raw) {
+            tracing::error!("preview cache error: {e:#}");
+        }
+    });
+}
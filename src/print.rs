@@ -0,0 +1,534 @@
+use anyhow::{Result, anyhow, ensure};
+use eframe::egui::{self, Rect};
+use flate2::Compression;
+use flate2::write::ZlibEncoder;
+use image::DynamicImage;
+use image::imageops::FilterType;
+use rfd::FileDialog;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::a11y;
+use crate::cti::CTIHeader;
+use crate::export;
+use crate::workdirs::Area;
+
+/// Body PDF (1/72 palce) na milimetr.
+const PT_PER_MM: f32 = 72.0 / 25.4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Paper {
+    A4,
+    A3,
+    Letter,
+    Legal,
+    /// Stránka přesně podle tištěného obrázku (plus okraje).
+    Image,
+}
+
+impl Paper {
+    pub const ALL: [Paper; 5] = [
+        Paper::A4,
+        Paper::A3,
+        Paper::Letter,
+        Paper::Legal,
+        Paper::Image,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Paper::A4 => "A4",
+            Paper::A3 => "A3",
+            Paper::Letter => "Letter",
+            Paper::Legal => "Legal",
+            Paper::Image => "Image size",
+        }
+    }
+
+    /// Rozměr na výšku v mm; `None` = podle obrázku.
+    fn size_mm(self) -> Option<(f32, f32)> {
+        match self {
+            Paper::A4 => Some((210.0, 297.0)),
+            Paper::A3 => Some((297.0, 420.0)),
+            Paper::Letter => Some((215.9, 279.4)),
+            Paper::Legal => Some((215.9, 355.6)),
+            Paper::Image => None,
+        }
+    }
+}
+
+/// Jak velký bude obrázek na papíře.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrintScale {
+    /// Skutečná velikost podle rozlišení uloženého v metadatech.
+    Stored,
+    /// Skutečná velikost při zvoleném DPI.
+    Dpi,
+    /// Přes celou tisknutelnou plochu stránky.
+    Fit,
+}
+
+#[derive(Debug, Clone)]
+pub struct PrintSettings {
+    /// Tištěná oblast v pixelech obrázku; `None` = celý obrázek.
+    pub area: Option<Rect>,
+    /// Otočení po 90° jako v prohlížeči.
+    pub rotation: u8,
+    pub scale: PrintScale,
+    /// DPI pro [`PrintScale::Dpi`].
+    pub dpi: f32,
+    /// Rozlišení z metadat (x, y).
+    pub stored_dpi: Option<(f32, f32)>,
+    /// Nejvyšší rozlišení obrázku vloženého do PDF (větší se převzorkuje).
+    pub raster_dpi: f32,
+    pub paper: Paper,
+    pub margin_mm: f32,
+    pub title: String,
+}
+
+impl Default for PrintSettings {
+    fn default() -> Self {
+        Self {
+            area: None,
+            rotation: 0,
+            scale: PrintScale::Fit,
+            dpi: 300.0,
+            stored_dpi: None,
+            raster_dpi: 300.0,
+            paper: Paper::A4,
+            margin_mm: 10.0,
+            title: String::new(),
+        }
+    }
+}
+
+/// Rozvržení stránky v bodech PDF (počátek vlevo dole).
+#[derive(Debug, Clone, Copy)]
+pub struct PageLayout {
+    pub page: (f32, f32),
+    pub image: Rect,
+    /// Obrázek se při zvoleném DPI na papír nevešel a byl zmenšen.
+    pub scaled_down: bool,
+}
+
+impl PrintSettings {
+    /// Rozměr tištěné oblasti v pixelech (po otočení).
+    pub fn pixels(&self, (w, h): (u32, u32)) -> (u32, u32) {
+        let (w, h) = self
+            .area
+            .map_or((w, h), |a| (a.width() as u32, a.height() as u32));
+        if self.rotation % 2 == 1 {
+            (h, w)
+        } else {
+            (w, h)
+        }
+    }
+
+    /// Umístění obrázku o `pixels` (už otočeného) na stránce.
+    pub fn layout(&self, (pw, ph): (u32, u32)) -> PageLayout {
+        let (dx, dy) = match (self.scale, self.stored_dpi) {
+            (PrintScale::Stored, Some((x, y))) if self.rotation % 2 == 1 => (y, x),
+            (PrintScale::Stored, Some(d)) => d,
+            _ => (self.dpi, self.dpi),
+        };
+        let natural = (pw as f32 / dx * 72.0, ph as f32 / dy * 72.0);
+        let margin = self.margin_mm * PT_PER_MM;
+        let Some((a, b)) = self.paper.size_mm() else {
+            let page = (natural.0 + 2.0 * margin, natural.1 + 2.0 * margin);
+            return PageLayout {
+                page,
+                image: Rect::from_min_size(egui::pos2(margin, margin), natural.into()),
+                scaled_down: false,
+            };
+        };
+        // orientace stránky podle obrázku
+        let (a, b) = (a * PT_PER_MM, b * PT_PER_MM);
+        let page = if pw > ph { (b, a) } else { (a, b) };
+        let avail = (page.0 - 2.0 * margin, page.1 - 2.0 * margin);
+        let fit = (avail.0 / natural.0).min(avail.1 / natural.1);
+        let k = if self.scale == PrintScale::Fit {
+            fit
+        } else {
+            fit.min(1.0)
+        };
+        let size = egui::vec2(natural.0 * k, natural.1 * k);
+        let center = egui::pos2(page.0 / 2.0, page.1 / 2.0);
+        PageLayout {
+            page,
+            image: Rect::from_center_size(center, size),
+            scaled_down: self.scale != PrintScale::Fit && k < 1.0,
+        }
+    }
+}
+
+/// Vykreslí obrázek (nebo jeho oblast) do jednostránkového PDF.
+pub fn render_pdf(hdr: &CTIHeader, raw: &[u8], settings: &PrintSettings) -> Result<Vec<u8>> {
+    let mut img = export::tone_mapped(export::to_dynamic_image(hdr, raw.to_vec())?);
+    if let Some(area) = settings.area {
+        let area = area.intersect(Rect::from_min_size(
+            egui::Pos2::ZERO,
+            egui::vec2(hdr.width as f32, hdr.height as f32),
+        ));
+        ensure!(
+            area.width() >= 1.0 && area.height() >= 1.0,
+            "empty print area"
+        );
+        img = img.crop_imm(
+            area.min.x as u32,
+            area.min.y as u32,
+            area.width() as u32,
+            area.height() as u32,
+        );
+    }
+    img = match settings.rotation % 4 {
+        1 => img.rotate90(),
+        2 => img.rotate180(),
+        3 => img.rotate270(),
+        _ => img,
+    };
+    let layout = settings.layout((img.width(), img.height()));
+
+    // víc pixelů, než tiskárna při raster_dpi zobrazí, by jen zvětšovalo PDF
+    let max_w = (layout.image.width() / 72.0 * settings.raster_dpi)
+        .round()
+        .max(1.0) as u32;
+    if img.width() > max_w {
+        let max_h = (layout.image.height() / 72.0 * settings.raster_dpi)
+            .round()
+            .max(1.0) as u32;
+        img = img.resize_exact(max_w, max_h, FilterType::CatmullRom);
+    }
+    let (w, h) = (img.width(), img.height());
+    let (gray, pixels) = on_white(img);
+    pdf_bytes(&layout, (w, h), gray, &pixels, &settings.title)
+}
+
+/// 8bit šedá nebo RGB; průhledná místa se složí na bílý papír.
+fn on_white(img: DynamicImage) -> (bool, Vec<u8>) {
+    let blend = |c: u8, a: u8| ((c as u32 * a as u32 + 255 * (255 - a as u32)) / 255) as u8;
+    match (img.color().has_color(), img.color().has_alpha()) {
+        (false, false) => (true, img.into_luma8().into_raw()),
+        (false, true) => (
+            true,
+            img.into_luma_alpha8()
+                .pixels()
+                .map(|p| blend(p[0], p[1]))
+                .collect(),
+        ),
+        (true, false) => (false, img.into_rgb8().into_raw()),
+        (true, true) => (
+            false,
+            img.into_rgba8()
+                .pixels()
+                .flat_map(|p| [0, 1, 2].map(|c| blend(p[c], p[3])))
+                .collect(),
+        ),
+    }
+}
+
+/// Minimální PDF 1.4: jedna stránka s jedním obrázkem (FlateDecode).
+fn pdf_bytes(
+    layout: &PageLayout,
+    (w, h): (u32, u32),
+    gray: bool,
+    pixels: &[u8],
+    title: &str,
+) -> Result<Vec<u8>> {
+    let mut z = ZlibEncoder::new(Vec::new(), Compression::default());
+    z.write_all(pixels)?;
+    let data = z.finish()?;
+    let r = layout.image;
+    let content = format!(
+        "q {:.3} 0 0 {:.3} {:.3} {:.3} cm /Im0 Do Q",
+        r.width(),
+        r.height(),
+        r.min.x,
+        r.min.y
+    );
+    // titul jako UTF-16BE s BOM v hexadecimálním řetězci (bez escapování)
+    let title: String = [0xFEFF]
+        .into_iter()
+        .chain(title.encode_utf16())
+        .map(|u| format!("{u:04X}"))
+        .collect();
+
+    let mut out: Vec<u8> = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec();
+    let mut offsets = Vec::new();
+    let mut object = |out: &mut Vec<u8>, body: &[u8]| {
+        offsets.push(out.len());
+        out.extend_from_slice(format!("{} 0 obj\n", offsets.len()).as_bytes());
+        out.extend_from_slice(body);
+        out.extend_from_slice(b"\nendobj\n");
+    };
+    object(&mut out, b"<< /Type /Catalog /Pages 2 0 R >>");
+    object(&mut out, b"<< /Type /Pages /Kids [3 0 R] /Count 1 >>");
+    object(
+        &mut out,
+        format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.3} {:.3}] \
+             /Resources << /XObject << /Im0 4 0 R >> >> /Contents 5 0 R >>",
+            layout.page.0, layout.page.1
+        )
+        .as_bytes(),
+    );
+    let mut image = format!(
+        "<< /Type /XObject /Subtype /Image /Width {w} /Height {h} /ColorSpace /{} \
+         /BitsPerComponent 8 /Filter /FlateDecode /Length {} >>\nstream\n",
+        if gray { "DeviceGray" } else { "DeviceRGB" },
+        data.len()
+    )
+    .into_bytes();
+    image.extend_from_slice(&data);
+    image.extend_from_slice(b"\nendstream");
+    object(&mut out, &image);
+    object(
+        &mut out,
+        format!(
+            "<< /Length {} >>\nstream\n{content}\nendstream",
+            content.len()
+        )
+        .as_bytes(),
+    );
+    object(
+        &mut out,
+        format!("<< /Title <{title}> /Producer (CTI View) >>").as_bytes(),
+    );
+
+    let xref = out.len();
+    let mut tail = format!("xref\n0 {}\n0000000000 65535 f \n", offsets.len() + 1);
+    for off in &offsets {
+        tail.push_str(&format!("{off:010} 00000 n \n"));
+    }
+    tail.push_str(&format!(
+        "trailer\n<< /Size {} /Root 1 0 R /Info {} 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+        offsets.len() + 1,
+        offsets.len()
+    ));
+    out.extend_from_slice(tail.as_bytes());
+    Ok(out)
+}
+
+/// Kam výsledek poslat.
+#[derive(Debug, Clone)]
+pub enum PrintTarget {
+    Pdf(PathBuf),
+    /// Jméno tiskárny (prázdné = výchozí).
+    Printer(String),
+}
+
+/// Vykreslí PDF a uloží ho, nebo pošle k tisku.
+pub fn print(
+    hdr: &CTIHeader,
+    raw: &[u8],
+    settings: &PrintSettings,
+    target: &PrintTarget,
+) -> Result<()> {
+    let pdf = render_pdf(hdr, raw, settings)?;
+    match target {
+        PrintTarget::Pdf(path) => std::fs::write(path, pdf)?,
+        PrintTarget::Printer(printer) => {
+            let dir = Area::Temp.dir()?;
+            let stem = Path::new(&settings.title)
+                .file_stem()
+                .map_or("print".into(), |s| s.to_string_lossy().into_owned());
+            let path = dir.join(format!("{stem}-print.pdf"));
+            std::fs::write(&path, pdf)?;
+            send_to_printer(&path, printer)?;
+        }
+    }
+    Ok(())
+}
+
+/// Windows: tisk přes aplikaci registrovanou pro PDF (ukáže svůj dialog tisku).
+#[cfg(windows)]
+fn send_to_printer(pdf: &Path, _printer: &str) -> Result<()> {
+    let quoted = pdf.display().to_string().replace('\'', "''");
+    Command::new("powershell")
+        .args(["-NoProfile", "-Command"])
+        .arg(format!("Start-Process -Verb Print -FilePath '{quoted}'"))
+        .spawn()
+        .map_err(|e| anyhow!("failed to start powershell: {e}"))?;
+    Ok(())
+}
+
+/// macOS/Linux: tiskový systém CUPS (`lp`).
+#[cfg(not(windows))]
+fn send_to_printer(pdf: &Path, printer: &str) -> Result<()> {
+    let mut cmd = Command::new("lp");
+    if !printer.trim().is_empty() {
+        cmd.args(["-d", printer.trim()]);
+    }
+    let out = cmd
+        .arg(pdf)
+        .output()
+        .map_err(|e| anyhow!("failed to start lp: {e}"))?;
+    ensure!(
+        out.status.success(),
+        "lp: {}",
+        String::from_utf8_lossy(&out.stderr).trim()
+    );
+    Ok(())
+}
+
+/// Dialog „Print…“.
+#[derive(Default)]
+pub struct PrintDialog {
+    settings: PrintSettings,
+    size: (u32, u32),
+    /// Viditelná část obrázku v prohlížeči (pixely).
+    visible: Option<Rect>,
+    only_visible: bool,
+    printer: String,
+    pub error: Option<String>,
+}
+
+impl PrintDialog {
+    /// Připraví dialog pro otevřený obrázek.
+    pub fn prepare(
+        &mut self,
+        title: String,
+        size: (u32, u32),
+        stored_dpi: Option<(f32, f32)>,
+        rotation: u8,
+        visible: Option<Rect>,
+    ) {
+        let s = &mut self.settings;
+        s.title = title;
+        s.rotation = rotation;
+        s.stored_dpi = stored_dpi;
+        s.scale = match (stored_dpi, s.scale) {
+            (Some(_), _) => PrintScale::Stored,
+            (None, PrintScale::Stored) => PrintScale::Fit,
+            (None, scale) => scale,
+        };
+        self.size = size;
+        // celý obrázek je vidět = není co ořezávat
+        let whole = Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(size.0 as f32, size.1 as f32));
+        self.visible = visible.filter(|v| !v.contains_rect(whole));
+        self.only_visible &= self.visible.is_some();
+        self.error = None;
+    }
+
+    /// Vrátí nastavení a cíl po kliknutí na Print / Save PDF.
+    pub fn window(
+        &mut self,
+        ctx: &egui::Context,
+        open: &mut bool,
+    ) -> Option<(PrintSettings, PrintTarget)> {
+        let mut start = None;
+        a11y::modal_dialog(ctx, "Print", open, |ui| {
+            let s = &mut self.settings;
+            egui::Grid::new("print-options")
+                .num_columns(2)
+                .spacing([12.0, 6.0])
+                .show(ui, |ui| {
+                    ui.label("Area");
+                    ui.horizontal(|ui| {
+                        ui.radio_value(&mut self.only_visible, false, "Whole image");
+                        ui.add_enabled_ui(self.visible.is_some(), |ui| {
+                            ui.radio_value(&mut self.only_visible, true, "Visible area");
+                        });
+                    });
+                    ui.end_row();
+
+                    ui.label("Size");
+                    ui.vertical(|ui| {
+                        let stored = match s.stored_dpi {
+                            Some((x, y)) if x == y => format!("Actual size ({x} DPI)"),
+                            Some((x, y)) => format!("Actual size ({x}×{y} DPI)"),
+                            None => "Actual size (no DPI in metadata)".into(),
+                        };
+                        ui.add_enabled_ui(s.stored_dpi.is_some(), |ui| {
+                            ui.radio_value(&mut s.scale, PrintScale::Stored, stored);
+                        });
+                        ui.horizontal(|ui| {
+                            ui.radio_value(&mut s.scale, PrintScale::Dpi, "At");
+                            ui.add(
+                                egui::DragValue::new(&mut s.dpi)
+                                    .range(10.0..=4800.0)
+                                    .suffix(" DPI"),
+                            );
+                        });
+                        ui.radio_value(&mut s.scale, PrintScale::Fit, "Fit to page");
+                    });
+                    ui.end_row();
+
+                    ui.label("Paper");
+                    egui::ComboBox::from_id_salt("print-paper")
+                        .selected_text(s.paper.label())
+                        .show_ui(ui, |ui| {
+                            for p in Paper::ALL {
+                                ui.selectable_value(&mut s.paper, p, p.label());
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label("Margins");
+                    ui.add(
+                        egui::DragValue::new(&mut s.margin_mm)
+                            .range(0.0..=50.0)
+                            .suffix(" mm"),
+                    );
+                    ui.end_row();
+
+                    ui.label("Resolution");
+                    ui.add(
+                        egui::DragValue::new(&mut s.raster_dpi)
+                            .range(72.0..=1200.0)
+                            .suffix(" DPI"),
+                    )
+                    .on_hover_text("The image is resampled down to this resolution on paper");
+                    ui.end_row();
+
+                    if !cfg!(windows) {
+                        ui.label("Printer");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.printer)
+                                .hint_text("system default"),
+                        );
+                        ui.end_row();
+                    }
+                });
+            s.area = self.visible.filter(|_| self.only_visible);
+
+            let layout = s.layout(s.pixels(self.size));
+            let mm = |pt: f32| pt / PT_PER_MM;
+            ui.label(format!(
+                "Printed size {:.0} × {:.0} mm on a {:.0} × {:.0} mm page",
+                mm(layout.image.width()),
+                mm(layout.image.height()),
+                mm(layout.page.0),
+                mm(layout.page.1)
+            ));
+            if layout.scaled_down {
+                ui.colored_label(
+                    ui.visuals().warn_fg_color,
+                    "The image does not fit the page at this size and will be scaled down.",
+                );
+            }
+            ui.separator();
+            if let Some(e) = &self.error {
+                ui.colored_label(ui.visuals().error_fg_color, e);
+            }
+            ui.horizontal(|ui| {
+                if ui.button("Print").clicked() {
+                    start = Some(PrintTarget::Printer(self.printer.clone()));
+                }
+                if ui.button("Save PDF…").clicked()
+                    && let Some(path) = FileDialog::new()
+                        .add_filter("PDF", &["pdf"])
+                        .set_file_name(Path::new(&s.title).with_extension("pdf").to_string_lossy())
+                        .save_file()
+                {
+                    start = Some(PrintTarget::Pdf(path));
+                }
+            });
+        });
+        let start = start.map(|t| (self.settings.clone(), t));
+        if start.is_some() {
+            *open = false;
+        }
+        start
+    }
+}
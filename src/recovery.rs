@@ -0,0 +1,286 @@
+use anyhow::{Context, Result};
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::annotations::Annotations;
+use crate::batch::BatchAction;
+use crate::workdirs::Area;
+
+/// Jak často se stav zapisuje.
+const HEARTBEAT: Duration = Duration::from_secs(5);
+
+/// Stav starší než tohle patří spadlé instanci, i kdyby její PID mezitím dostal
+/// jiný proces.
+const STALE: Duration = Duration::from_secs(10 * 60);
+
+/// Stav prohlížeče pro obnovu po pádu; zapisuje se průběžně a při řádném ukončení
+/// se smaže.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Session {
+    /// Vyplní [`Heartbeat::write`].
+    pub pid: u32,
+    /// Poslední zápis (sekundy od epochy).
+    pub heartbeat: u64,
+    pub file: Option<PathBuf>,
+    pub frame: u32,
+    /// `None` = přizpůsobit oknu.
+    pub zoom: Option<f32>,
+    pub rotation: u8,
+    /// Anotace otevřeného souboru (uložené se s nimi porovnají až při obnově).
+    pub annotations: Option<Annotations>,
+    /// Nedokončený dávkový job.
+    pub batch: Option<PendingBatch>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingBatch {
+    pub action: BatchAction,
+    /// Soubory, které se ještě nezpracovaly.
+    pub files: Vec<PathBuf>,
+}
+
+/// Soubor a pohled při řádném zavření; při dalším spuštění se obnoví (ukládá se přes
+/// `eframe::Storage`, na rozdíl od [`Session`], která slouží jen pro obnovu po pádu).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LastView {
+    pub file: PathBuf,
+    pub frame: u32,
+    /// `None` = přizpůsobit oknu.
+    pub zoom: Option<f32>,
+    /// Posun středu obrázku (body obrazovky).
+    pub pan: [f32; 2],
+    pub rotation: u8,
+}
+
+fn sessions_dir() -> PathBuf {
+    Area::Cache.path().join("sessions")
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// Průběžný zápis stavu této instance (každá instance má svůj soubor).
+pub struct Heartbeat {
+    path: PathBuf,
+    last: Option<Instant>,
+}
+
+impl Default for Heartbeat {
+    fn default() -> Self {
+        Self {
+            path: sessions_dir().join(format!("session-{}.json", std::process::id())),
+            last: None,
+        }
+    }
+}
+
+impl Heartbeat {
+    /// Volá se každý snímek: je čas zapsat stav? Naplánuje překreslení, aby stav
+    /// zůstal čerstvý i v nečinném okně.
+    pub fn due(&mut self, ctx: &egui::Context) -> bool {
+        ctx.request_repaint_after(HEARTBEAT);
+        if self.last.is_some_and(|t| t.elapsed() < HEARTBEAT) {
+            return false;
+        }
+        self.last = Some(Instant::now());
+        true
+    }
+
+    pub fn write(&self, mut session: Session) {
+        session.pid = std::process::id();
+        session.heartbeat = unix_now();
+        if let Err(e) = write(&self.path, &session) {
+            tracing::error!("session snapshot error: {e:#}");
+        }
+    }
+
+    /// Řádné ukončení: stav se smaže.
+    pub fn finish(&self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Zápis přes dočasný soubor, aby pád uprostřed zápisu nenechal poškozený stav.
+fn write(path: &Path, session: &Session) -> Result<()> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    std::fs::create_dir_all(dir).with_context(|| format!("create {}", dir.display()))?;
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, serde_json::to_vec(session)?)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// Stavy spadlých instancí, nejnovější první (s cestou k souboru stavu).
+/// Nečitelné stavy se smažou.
+pub fn crashed() -> Vec<(PathBuf, Session)> {
+    let Ok(entries) = std::fs::read_dir(sessions_dir()) else {
+        return Vec::new();
+    };
+    let mut out = Vec::new();
+    for path in entries.flatten().map(|e| e.path()) {
+        if path.extension().is_none_or(|e| e != "json") {
+            continue;
+        }
+        let session: Session = match std::fs::read(&path)
+            .ok()
+            .and_then(|b| serde_json::from_slice(&b).ok())
+        {
+            Some(s) => s,
+            None => {
+                let _ = std::fs::remove_file(&path);
+                continue;
+            }
+        };
+        let age = Duration::from_secs(unix_now().saturating_sub(session.heartbeat));
+        let own = session.pid == std::process::id();
+        if !own && (age > STALE || !alive(session.pid)) {
+            out.push((path, session));
+        }
+    }
+    out.sort_by_key(|(_, s)| std::cmp::Reverse(s.heartbeat));
+    out
+}
+
+/// Běží proces `pid`?
+#[cfg(unix)]
+fn alive(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    // SAFETY: signál 0 jen ověří existenci procesu, nic se neposílá
+    let res = unsafe { libc::kill(pid, 0) };
+    // EPERM = proces existuje, jen patří jinému uživateli
+    res == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(windows)]
+fn alive(pid: u32) -> bool {
+    use windows_sys::Win32::Foundation::{CloseHandle, STILL_ACTIVE};
+    use windows_sys::Win32::System::Threading::{
+        GetExitCodeProcess, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+    // SAFETY: handle se použije jen po úspěšném otevření a hned se zavře
+    unsafe {
+        let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if handle.is_null() {
+            return false;
+        }
+        let mut code = 0u32;
+        let ok = GetExitCodeProcess(handle, &mut code) != 0;
+        CloseHandle(handle);
+        ok && code == STILL_ACTIVE as u32
+    }
+}
+
+/// Bez zjištění procesu rozhoduje jen stáří stavu.
+#[cfg(not(any(unix, windows)))]
+fn alive(_pid: u32) -> bool {
+    true
+}
+
+/// Co obnovit z posledního stavu spadlé instance.
+pub struct RecoveryPrompt {
+    pub session: Session,
+    /// Soubory stavů spadlých instancí (po rozhodnutí se smažou).
+    paths: Vec<PathBuf>,
+    /// Anotace se liší od uložených.
+    annotations_differ: bool,
+    pub restore_file: bool,
+    pub restore_annotations: bool,
+    pub restore_batch: bool,
+}
+
+impl RecoveryPrompt {
+    /// Nabídka podle nejnovějšího spadlého stavu; `None`, když není co obnovit.
+    pub fn find() -> Option<Self> {
+        let crashed = crashed();
+        let paths: Vec<PathBuf> = crashed.iter().map(|(p, _)| p.clone()).collect();
+        let session = crashed.into_iter().next()?.1;
+        let annotations_differ = match (&session.file, &session.annotations) {
+            (Some(file), Some(doc)) => {
+                let saved = Annotations::load(file).unwrap_or_default();
+                serde_json::to_value(&saved).ok() != serde_json::to_value(doc).ok()
+            }
+            _ => false,
+        };
+        let file_exists = session.file.as_ref().is_some_and(|f| f.exists());
+        let batch = session.batch.as_ref().is_some_and(|b| !b.files.is_empty());
+        if !file_exists && !batch {
+            discard_all(&paths);
+            return None;
+        }
+        Some(Self {
+            paths,
+            annotations_differ: annotations_differ && file_exists,
+            restore_file: file_exists,
+            restore_annotations: annotations_differ && file_exists,
+            restore_batch: batch,
+            session,
+        })
+    }
+
+    /// Dialog po spuštění; `Some(true)` = obnovit vybrané, `Some(false)` = zahodit.
+    pub fn window(&mut self, ctx: &egui::Context) -> Option<bool> {
+        let mut choice = None;
+        egui::Modal::new(egui::Id::new("recovery")).show(ctx, |ui| {
+            ui.heading("Restore previous session?");
+            ui.label("CTI View did not shut down properly last time.");
+            ui.separator();
+            if let Some(file) = &self.session.file
+                && file.exists()
+            {
+                let name = file.file_name().unwrap_or_default().to_string_lossy();
+                let page = match self.session.frame {
+                    0 => String::new(),
+                    n => format!(", page {}", n + 1),
+                };
+                ui.checkbox(&mut self.restore_file, format!("Reopen {name}{page}"))
+                    .on_hover_text(file.display().to_string());
+                if self.annotations_differ {
+                    ui.add_enabled(
+                        self.restore_file,
+                        egui::Checkbox::new(
+                            &mut self.restore_annotations,
+                            "Restore unsaved annotations",
+                        ),
+                    )
+                    .on_hover_text("The annotations differ from the ones saved next to the file");
+                }
+            }
+            if let Some(batch) = &self.session.batch
+                && !batch.files.is_empty()
+            {
+                let text = format!(
+                    "Resume batch job \"{}\" ({} file(s) left)",
+                    batch.action.label(),
+                    batch.files.len()
+                );
+                ui.checkbox(&mut self.restore_batch, text);
+            }
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui.button("Restore").clicked() {
+                    choice = Some(true);
+                }
+                if ui.button("Discard").clicked() {
+                    choice = Some(false);
+                }
+            });
+        });
+        if choice.is_some() {
+            discard_all(&self.paths);
+        }
+        choice
+    }
+}
+
+fn discard_all(paths: &[PathBuf]) {
+    for path in paths {
+        let _ = std::fs::remove_file(path);
+    }
+}
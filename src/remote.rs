@@ -0,0 +1,171 @@
+//! Čtení CTI přímo z HTTP(S) (funkce `remote`): soubor se stahuje po úsecích přes
+//! `Range`, takže z objektového úložiště přijde jen hlavička, index a dlaždice, které
+//! dekodér opravdu čte (u sekvence jen zobrazený snímek, u výřezu jen jeho dlaždice).
+
+use anyhow::{Context, Result, bail, ensure};
+use eframe::egui;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::Duration;
+use ureq::Agent;
+
+/// Nejmenší stahovaný úsek (hlavička a index menšího souboru v jednom požadavku).
+const MIN_FETCH: u64 = 64 << 10;
+/// Strop úseku při souvislém čtení (dekódování celého snímku po dlaždicích).
+const MAX_FETCH: u64 = 8 << 20;
+
+/// Sdílený klient, ať se spojení mezi požadavky (a soubory) znovu používá.
+fn agent() -> &'static Agent {
+    static AGENT: OnceLock<Agent> = OnceLock::new();
+    AGENT.get_or_init(|| {
+        Agent::config_builder()
+            .http_status_as_error(false)
+            .timeout_connect(Some(Duration::from_secs(30)))
+            .build()
+            .new_agent()
+    })
+}
+
+/// Vzdálený soubor s `Read + Seek`; drží v paměti naposledy stažený úsek.
+pub struct RemoteFile {
+    url: String,
+    len: u64,
+    pos: u64,
+    /// Stažený úsek začínající na `start`.
+    start: u64,
+    buf: Vec<u8>,
+    /// Délka dalšího úseku; při souvislém čtení se zdvojnásobuje.
+    ahead: u64,
+}
+
+impl RemoteFile {
+    /// Otevře URL; první úsek zjistí i velikost souboru.
+    pub fn open(url: &str) -> Result<Self> {
+        let mut file = Self {
+            url: url.to_owned(),
+            len: 0,
+            pos: 0,
+            start: 0,
+            buf: Vec::new(),
+            ahead: MIN_FETCH,
+        };
+        file.fetch(0, MIN_FETCH)?;
+        Ok(file)
+    }
+
+    /// Stáhne `len` bajtů od `start` (server, který `Range` nepodporuje, pošle celý soubor).
+    fn fetch(&mut self, start: u64, len: u64) -> Result<()> {
+        let range = format!("bytes={start}-{}", start + len - 1);
+        let mut resp = agent()
+            .get(&self.url)
+            .header("Range", &range)
+            .call()
+            .with_context(|| format!("GET {}", self.url))?;
+        let status = resp.status().as_u16();
+        let total = resp
+            .headers()
+            .get("Content-Range")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.rsplit('/').next())
+            .and_then(|v| v.parse::<u64>().ok());
+        match status {
+            206 => {
+                let total = total.context("206 response without the file size")?;
+                self.buf = resp
+                    .body_mut()
+                    .with_config()
+                    // limit musí být větší než nejdelší povolená odpověď
+                    .limit(len + 1)
+                    .read_to_vec()
+                    .with_context(|| format!("GET {} ({range})", self.url))?;
+                self.start = start;
+                self.len = total;
+            }
+            200 => {
+                self.buf = resp
+                    .body_mut()
+                    .with_config()
+                    .limit(u64::MAX)
+                    .read_to_vec()
+                    .with_context(|| format!("GET {}", self.url))?;
+                self.start = 0;
+                self.len = self.buf.len() as u64;
+            }
+            // prázdný soubor nebo čtení za koncem
+            416 => {
+                self.buf.clear();
+                self.start = start;
+                self.len = total.unwrap_or(start);
+            }
+            s => bail!("{}: HTTP {s}", self.url),
+        }
+        ensure!(
+            self.start <= start,
+            "{}: server returned a different range",
+            self.url
+        );
+        Ok(())
+    }
+}
+
+impl Read for RemoteFile {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        if out.is_empty() || self.pos >= self.len {
+            return Ok(0);
+        }
+        let end = self.start + self.buf.len() as u64;
+        if self.pos < self.start || self.pos >= end {
+            self.ahead = if self.pos == end {
+                (self.ahead * 2).min(MAX_FETCH)
+            } else {
+                MIN_FETCH
+            };
+            let want = (out.len() as u64).max(self.ahead).min(self.len - self.pos);
+            self.fetch(self.pos, want).map_err(io::Error::other)?;
+        }
+        let from = (self.pos - self.start) as usize;
+        let n = out.len().min(self.buf.len().saturating_sub(from));
+        out[..n].copy_from_slice(&self.buf[from..from + n]);
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl Seek for RemoteFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let pos = match pos {
+            SeekFrom::Start(p) => Some(p),
+            SeekFrom::End(d) => self.len.checked_add_signed(d),
+            SeekFrom::Current(d) => self.pos.checked_add_signed(d),
+        };
+        self.pos = pos.ok_or_else(|| io::Error::other("invalid seek"))?;
+        Ok(self.pos)
+    }
+}
+
+/// Dialog „Open URL“; vrací adresu k otevření.
+pub fn open_url_window(ctx: &egui::Context, open: &mut bool, url: &mut String) -> Option<PathBuf> {
+    let mut chosen = None;
+    egui::Window::new("Open URL")
+        .collapsible(false)
+        .resizable(false)
+        .open(open)
+        .show(ctx, |ui| {
+            ui.label("HTTP(S) address of a .cti file (e.g. a pre-signed object storage link):");
+            let edit = ui.add(
+                egui::TextEdit::singleline(url)
+                    .hint_text("https://")
+                    .desired_width(420.0),
+            );
+            let enter = edit.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            let valid = crate::cti::is_url(url.trim().as_ref());
+            if (ui.add_enabled(valid, egui::Button::new("Open")).clicked() || enter) && valid {
+                chosen = Some(PathBuf::from(url.trim()));
+            }
+        });
+    if chosen.is_some() {
+        *open = false;
+    }
+    chosen
+}
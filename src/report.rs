@@ -0,0 +1,241 @@
+//! Strojově čitelná zpráva o souboru CTI (`info --json`, `verify --json`, `--check --json`)
+//! pro systémy správy archivu: pole hlavičky, velikosti dlaždic, výsledky CRC a kompresní
+//! poměry.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::cti::{self, CTIDecoder, CTIHeader, CTITileReader, CompressionId};
+
+#[derive(Serialize)]
+pub struct FileReport {
+    pub path: PathBuf,
+    pub file_size: u64,
+    pub header: HeaderReport,
+    /// Velikost dekódovaných dat všech snímků.
+    pub raw_size: u64,
+    /// Součet komprimovaných dlaždic.
+    pub compressed_size: u64,
+    pub ratio: f64,
+    /// Dlaždice, jejichž kontrola selhala (`null` = dlaždice se nekontrolovaly).
+    pub damaged: Option<usize>,
+    /// Hash celého souboru: `true` = sedí, `false` = nesedí nebo je trailer poškozený,
+    /// `null` = nekontroloval se nebo ho soubor nemá (viz `header.has_file_hash`).
+    pub file_hash: Option<bool>,
+    /// Proč kontrola hashe selhala.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_hash_error: Option<String>,
+    pub metadata: BTreeMap<String, String>,
+    pub tiles: Vec<TileReport>,
+}
+
+#[derive(Serialize)]
+pub struct HeaderReport {
+    pub version: u16,
+    pub flags: u16,
+    pub width: u32,
+    pub height: u32,
+    pub tile_size: u32,
+    pub tiles_x: u32,
+    pub tiles_y: u32,
+    pub color_type: u8,
+    pub compression: &'static str,
+    pub quality: u8,
+    pub frames: u32,
+    pub checksum: &'static str,
+    pub has_metadata: bool,
+    pub has_file_hash: bool,
+}
+
+impl From<&CTIHeader> for HeaderReport {
+    fn from(hdr: &CTIHeader) -> Self {
+        Self {
+            version: hdr.version,
+            flags: hdr.flags,
+            width: hdr.width,
+            height: hdr.height,
+            tile_size: hdr.tile_size,
+            tiles_x: hdr.tiles_x,
+            tiles_y: hdr.tiles_y,
+            color_type: hdr.color_type,
+            compression: CompressionId::from(hdr.compression).as_str(),
+            quality: hdr.quality,
+            frames: hdr.frames,
+            checksum: if hdr.has_crc32c() { "crc32c" } else { "crc32" },
+            has_metadata: hdr.has_metadata(),
+            has_file_hash: hdr.has_file_hash(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct TileReport {
+    pub frame: u32,
+    pub x: u32,
+    pub y: u32,
+    pub offset: u64,
+    pub compressed_size: u32,
+    pub original_size: u32,
+    /// Kontrolní součet z indexu (hex).
+    pub crc: String,
+    pub ratio: f64,
+    /// Výsledek kontroly (`null` = nekontrolovala se).
+    pub ok: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl FileReport {
+    /// `check` = rozbalit dlaždice a ověřit CRC, `deep` = navíc hash celého souboru.
+    pub fn build(path: &Path, check: bool, deep: bool) -> Result<Self> {
+        let file_size = std::fs::metadata(path)
+            .with_context(|| path.display().to_string())?
+            .len();
+        let mut reader = CTITileReader::open(path)?;
+        let hdr = *reader.header();
+        let bpp = u64::from(cti::bytes_per_pixel(hdr.color_type)?);
+        let mut tiles = Vec::with_capacity(hdr.tiles_per_frame() * hdr.frames as usize);
+        for frame in 0..hdr.frames {
+            for y in 0..hdr.tiles_y {
+                for x in 0..hdr.tiles_x {
+                    let e = reader.tile_entry(frame, x, y)?;
+                    let result = check.then(|| reader.decode_tile(frame, x, y));
+                    tiles.push(TileReport {
+                        frame,
+                        x,
+                        y,
+                        offset: e.offset,
+                        compressed_size: e.compressed_size,
+                        original_size: e.original_size,
+                        crc: format!("{:08x}", e.crc),
+                        ratio: ratio(e.original_size.into(), e.compressed_size.into()),
+                        ok: result.as_ref().map(Result::is_ok),
+                        error: result.and_then(|r| r.err()).map(|e| format!("{e:#}")),
+                    });
+                }
+            }
+        }
+        let raw_size = u64::from(hdr.width) * u64::from(hdr.height) * bpp * u64::from(hdr.frames);
+        let compressed_size = tiles.iter().map(|t| u64::from(t.compressed_size)).sum();
+        // neshoda hashe je výsledek kontroly jako vadná dlaždice, ne chyba zprávy
+        let hash = deep.then(|| cti::verify_file_hash(path));
+        let (file_hash, file_hash_error) = match hash {
+            None | Some(Ok(false)) => (None, None),
+            Some(Ok(true)) => (Some(true), None),
+            Some(Err(e)) => (Some(false), Some(format!("{e:#}"))),
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            file_size,
+            header: HeaderReport::from(&hdr),
+            raw_size,
+            compressed_size,
+            ratio: ratio(raw_size, compressed_size),
+            damaged: check.then(|| tiles.iter().filter(|t| t.ok == Some(false)).count()),
+            file_hash,
+            file_hash_error,
+            metadata: CTIDecoder::metadata(path)?.entries.into_iter().collect(),
+            tiles,
+        })
+    }
+
+    /// Soubor prošel kontrolou (žádná poškozená dlaždice ani neshoda hashe souboru).
+    pub fn is_ok(&self) -> bool {
+        self.damaged.unwrap_or(0) == 0 && self.file_hash != Some(false)
+    }
+}
+
+/// Souhrnné statistiky souboru pro okno Info (jen z indexu, dlaždice se nečtou).
+pub struct FileStats {
+    pub file_size: u64,
+    pub compressed_size: u64,
+    pub raw_size: u64,
+    pub ratio: f64,
+    /// Průměrná a střední velikost komprimované dlaždice.
+    pub mean_tile: u64,
+    pub median_tile: u64,
+    /// Rozšiřující bloky souboru verze 2 (popis pro výpis).
+    pub chunks: Vec<String>,
+}
+
+impl FileStats {
+    pub fn compute(path: &Path) -> Result<Self> {
+        let file_size = std::fs::metadata(path)
+            .with_context(|| path.display().to_string())?
+            .len();
+        let reader = CTITileReader::open(path)?;
+        let hdr = *reader.header();
+        let bpp = u64::from(cti::bytes_per_pixel(hdr.color_type)?);
+        let mut sizes = Vec::with_capacity(hdr.tiles_per_frame() * hdr.frames as usize);
+        for frame in 0..hdr.frames {
+            for y in 0..hdr.tiles_y {
+                for x in 0..hdr.tiles_x {
+                    sizes.push(u64::from(reader.tile_entry(frame, x, y)?.compressed_size));
+                }
+            }
+        }
+        sizes.sort_unstable();
+        let compressed_size: u64 = sizes.iter().sum();
+        let raw_size = u64::from(hdr.width) * u64::from(hdr.height) * bpp * u64::from(hdr.frames);
+        Ok(Self {
+            file_size,
+            compressed_size,
+            raw_size,
+            ratio: ratio(raw_size, compressed_size),
+            mean_tile: compressed_size / sizes.len().max(1) as u64,
+            median_tile: sizes.get(sizes.len() / 2).copied().unwrap_or(0),
+            chunks: CTIDecoder::extensions(path)?.describe(),
+        })
+    }
+}
+
+/// Položka `verify --json`: zpráva, nebo chyba souboru, který nešel přečíst.
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum ReportEntry {
+    Report(FileReport),
+    Failed { path: PathBuf, error: String },
+}
+
+impl ReportEntry {
+    pub fn is_ok(&self) -> bool {
+        matches!(self, ReportEntry::Report(r) if r.is_ok())
+    }
+}
+
+fn ratio(raw: u64, compressed: u64) -> f64 {
+    if compressed == 0 {
+        0.0
+    } else {
+        raw as f64 / compressed as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cti::{CTIEncoder, EncodeParams};
+
+    /// Změněný bajt za daty dlaždic (v traileru s hashem) neshodí zprávu, jen ji označí.
+    #[test]
+    fn hash_mismatch_is_reported_not_raised() {
+        let path = std::env::temp_dir().join(format!("cti-report-{}.cti", std::process::id()));
+        CTIEncoder::encode_file(&path, 4, 3, 1, &[7; 12], &EncodeParams::default()).unwrap();
+        let report = FileReport::build(&path, true, true).unwrap();
+        assert_eq!(report.file_hash, Some(true));
+        assert!(report.is_ok());
+
+        let mut bytes = std::fs::read(&path).unwrap();
+        *bytes.last_mut().unwrap() ^= 1;
+        std::fs::write(&path, &bytes).unwrap();
+        let report = FileReport::build(&path, true, true).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(report.damaged, Some(0));
+        assert_eq!(report.file_hash, Some(false));
+        let error = report.file_hash_error.as_deref().unwrap_or_default();
+        assert!(error.contains("mismatch"), "{error}");
+        assert!(!report.is_ok());
+    }
+}
@@ -0,0 +1,86 @@
+use eframe::egui::{self, Color32, Pos2, Rect, Response, Stroke, Vec2};
+
+/// Obdélníkový výběr v obrázku (Shift + tažení, v režimu výběru i bez Shiftu);
+/// souřadnice v pixelech neotočeného obrázku.
+#[derive(Default)]
+pub struct Selection {
+    pub rect: Option<Rect>,
+    /// Režim výběru: tažení vybírá místo posunu obrázku.
+    pub mode: bool,
+    drag_start: Option<Pos2>,
+}
+
+impl Selection {
+    pub fn clear(&mut self) {
+        self.rect = None;
+        self.drag_start = None;
+    }
+
+    /// Probíhá právě tažení výběru (obrázkem se pak neposouvá).
+    pub fn dragging(&self) -> bool {
+        self.drag_start.is_some()
+    }
+
+    /// Výběr v celých pixelech jako `(x, y, šířka, výška)`.
+    pub fn region(&self) -> Option<(u32, u32, u32, u32)> {
+        let r = self.rect?;
+        Some((
+            r.min.x as u32,
+            r.min.y as u32,
+            r.width() as u32,
+            r.height() as u32,
+        ))
+    }
+
+    /// Zpracuje tažení nad obrázkem (Shift, nebo v režimu výběru); Esc výběr zruší.
+    /// `to_image` převádí pozici na obrazovce do souřadnic obrázku.
+    pub fn handle_input(
+        &mut self,
+        resp: &Response,
+        (w, h): (u32, u32),
+        to_image: impl Fn(Pos2) -> Pos2,
+    ) {
+        let (shift, escape) = resp
+            .ctx
+            .input(|i| (i.modifiers.shift, i.key_pressed(egui::Key::Escape)));
+        if escape {
+            self.clear();
+        }
+        let whole = Rect::from_min_size(Pos2::ZERO, Vec2::new(w as f32, h as f32));
+        let pos = resp
+            .interact_pointer_pos()
+            .map(|p| to_image(p).clamp(whole.min, whole.max));
+        if resp.drag_started() && (shift || self.mode) {
+            self.drag_start = pos;
+        }
+        if let (Some(a), Some(b)) = (self.drag_start, pos) {
+            let r = Rect::from_two_pos(a, b);
+            self.rect = Some(Rect::from_min_max(r.min.round(), r.max.round()));
+        }
+        if resp.drag_stopped() && self.drag_start.take().is_some() {
+            // pouhé cvaknutí (bez tažení) výběr zruší
+            self.rect = self.rect.filter(|r| r.width() >= 1.0 && r.height() >= 1.0);
+        }
+    }
+
+    pub fn paint(&self, painter: &egui::Painter, to_screen: impl Fn(Pos2) -> Pos2) {
+        let Some(r) = self.rect else {
+            return;
+        };
+        let screen = Rect::from_two_pos(to_screen(r.min), to_screen(r.max));
+        painter.rect_filled(screen, 0.0, Color32::from_white_alpha(24));
+        painter.rect_stroke(
+            screen,
+            0.0,
+            Stroke::new(1.0, Color32::WHITE),
+            egui::StrokeKind::Middle,
+        );
+        painter.text(
+            screen.left_top() - Vec2::new(0.0, 2.0),
+            egui::Align2::LEFT_BOTTOM,
+            format!("{} × {}", r.width(), r.height()),
+            egui::FontId::proportional(12.0),
+            Color32::WHITE,
+        );
+    }
+}
@@ -0,0 +1,341 @@
+use eframe::egui::{self, Key, KeyboardShortcut, Modifiers};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
+
+/// Akce, na které lze navázat klávesovou zkratku.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Action {
+    Open,
+    OpenInNewWindow,
+    OpenInNewTab,
+    NextTab,
+    PrevTab,
+    Fit,
+    ActualSize,
+    ZoomIn,
+    ZoomOut,
+    NextFile,
+    PrevFile,
+    RotateCw,
+    RotateCcw,
+    Info,
+    Fullscreen,
+    Preferences,
+    NextFrame,
+    PrevFrame,
+    FirstFrame,
+    LastFrame,
+    PlayPause,
+    Print,
+    PrintSize,
+    SelectMode,
+    ExportSelection,
+    Navigator,
+    Diagnose,
+    TileGrid,
+    FindText,
+}
+
+impl Action {
+    pub const ALL: [Action; 29] = [
+        Action::Open,
+        Action::OpenInNewWindow,
+        Action::OpenInNewTab,
+        Action::NextTab,
+        Action::PrevTab,
+        Action::Fit,
+        Action::ActualSize,
+        Action::PrintSize,
+        Action::ZoomIn,
+        Action::ZoomOut,
+        Action::NextFile,
+        Action::PrevFile,
+        Action::RotateCw,
+        Action::RotateCcw,
+        Action::Info,
+        Action::Fullscreen,
+        Action::Preferences,
+        Action::NextFrame,
+        Action::PrevFrame,
+        Action::FirstFrame,
+        Action::LastFrame,
+        Action::PlayPause,
+        Action::Print,
+        Action::SelectMode,
+        Action::ExportSelection,
+        Action::Navigator,
+        Action::Diagnose,
+        Action::TileGrid,
+        Action::FindText,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::Open => "Open file",
+            Action::OpenInNewWindow => "Open file in new window",
+            Action::OpenInNewTab => "Open file in new tab",
+            Action::NextTab => "Next tab",
+            Action::PrevTab => "Previous tab",
+            Action::Fit => "Fit to window",
+            Action::ActualSize => "Actual size (1:1)",
+            Action::PrintSize => "Print size (physical dimensions)",
+            Action::ZoomIn => "Zoom in",
+            Action::ZoomOut => "Zoom out",
+            Action::NextFile => "Next file",
+            Action::PrevFile => "Previous file",
+            Action::RotateCw => "Rotate clockwise",
+            Action::RotateCcw => "Rotate counter-clockwise",
+            Action::Info => "Info",
+            Action::Fullscreen => "Toggle fullscreen",
+            Action::Preferences => "Preferences",
+            Action::NextFrame => "Next page",
+            Action::PrevFrame => "Previous page",
+            Action::FirstFrame => "First page",
+            Action::LastFrame => "Last page",
+            Action::PlayPause => "Play / pause sequence (switch images when blinking)",
+            Action::Print => "Print",
+            Action::SelectMode => "Selection mode (drag selects)",
+            Action::ExportSelection => "Export selection",
+            Action::Navigator => "Show / hide navigator",
+            Action::Diagnose => "Diagnose damaged tiles",
+            Action::TileGrid => "Show / hide tile grid",
+            Action::FindText => "Find in text layer (ALTO/hOCR)",
+        }
+    }
+
+    fn default_shortcuts(self) -> Vec<KeyboardShortcut> {
+        let sc = KeyboardShortcut::new;
+        match self {
+            Action::Open => vec![sc(Modifiers::COMMAND, Key::O)],
+            Action::OpenInNewWindow => vec![sc(Modifiers::COMMAND | Modifiers::SHIFT, Key::O)],
+            Action::OpenInNewTab => vec![sc(Modifiers::COMMAND, Key::T)],
+            Action::NextTab => vec![sc(Modifiers::CTRL, Key::Tab)],
+            Action::PrevTab => vec![sc(Modifiers::CTRL | Modifiers::SHIFT, Key::Tab)],
+            Action::Fit => vec![sc(Modifiers::NONE, Key::F)],
+            Action::ActualSize => vec![sc(Modifiers::COMMAND, Key::Num0)],
+            Action::PrintSize => vec![sc(Modifiers::COMMAND | Modifiers::SHIFT, Key::Num0)],
+            Action::ZoomIn => vec![sc(Modifiers::COMMAND, Key::Plus)],
+            Action::ZoomOut => vec![sc(Modifiers::COMMAND, Key::Minus)],
+            Action::NextFile => vec![sc(Modifiers::NONE, Key::ArrowRight)],
+            Action::PrevFile => vec![sc(Modifiers::NONE, Key::ArrowLeft)],
+            Action::RotateCw => vec![sc(Modifiers::NONE, Key::R)],
+            Action::RotateCcw => vec![sc(Modifiers::SHIFT, Key::R)],
+            Action::Info => vec![sc(Modifiers::NONE, Key::I)],
+            Action::Fullscreen => vec![
+                sc(Modifiers::NONE, Key::F11),
+                sc(Modifiers::COMMAND, Key::Enter),
+            ],
+            Action::Preferences => vec![sc(Modifiers::COMMAND, Key::Comma)],
+            Action::NextFrame => vec![sc(Modifiers::NONE, Key::PageDown)],
+            Action::PrevFrame => vec![sc(Modifiers::NONE, Key::PageUp)],
+            Action::FirstFrame => vec![sc(Modifiers::NONE, Key::Home)],
+            Action::LastFrame => vec![sc(Modifiers::NONE, Key::End)],
+            Action::PlayPause => vec![sc(Modifiers::NONE, Key::Space)],
+            Action::Print => vec![sc(Modifiers::COMMAND, Key::P)],
+            Action::SelectMode => vec![sc(Modifiers::NONE, Key::S)],
+            Action::ExportSelection => vec![sc(Modifiers::COMMAND | Modifiers::SHIFT, Key::S)],
+            Action::Navigator => vec![sc(Modifiers::NONE, Key::N)],
+            Action::Diagnose => vec![sc(Modifiers::NONE, Key::D)],
+            Action::TileGrid => vec![sc(Modifiers::NONE, Key::G)],
+            Action::FindText => vec![sc(Modifiers::COMMAND, Key::F)],
+        }
+    }
+}
+
+/// Mapa akce → zkratky (akce, které uživatel zkratky smazal, mají prázdný seznam).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Shortcuts {
+    #[serde(deserialize_with = "with_defaults")]
+    map: BTreeMap<Action, Vec<KeyboardShortcut>>,
+    #[serde(skip)]
+    capturing: Option<Action>,
+}
+
+impl Default for Shortcuts {
+    fn default() -> Self {
+        Self {
+            map: Action::ALL
+                .iter()
+                .map(|&a| (a, a.default_shortcuts()))
+                .collect(),
+            capturing: None,
+        }
+    }
+}
+
+impl Shortcuts {
+    /// Vrátí akce, jejichž zkratka byla v tomto snímku stisknuta (a zkratky spotřebuje).
+    pub fn triggered(&self, ctx: &egui::Context) -> Vec<Action> {
+        // při zachytávání, psaní do textu nebo s otevřeným modálním dialogem nic nespouštíme
+        let modal = ctx.memory(|m| m.top_modal_layer().is_some());
+        if self.capturing.is_some() || modal || ctx.wants_keyboard_input() {
+            return Vec::new();
+        }
+        // zkratky s více modifikátory první, ať Shift+R nespustí i R
+        let mut bindings: Vec<_> = self
+            .map
+            .iter()
+            .flat_map(|(a, scs)| scs.iter().map(move |sc| (*a, sc)))
+            .collect();
+        bindings.sort_by_key(|(_, sc)| std::cmp::Reverse(modifier_count(sc.modifiers)));
+        let mut out: Vec<Action> = Vec::new();
+        for (a, sc) in bindings {
+            if ctx.input_mut(|i| i.consume_shortcut(sc)) && !out.contains(&a) {
+                out.push(a);
+            }
+        }
+        out
+    }
+
+    /// Primární (první) zkratka akce.
+    pub fn get(&self, action: Action) -> Option<&KeyboardShortcut> {
+        self.map.get(&action).and_then(|v| v.first())
+    }
+
+    /// Editor zkratek (sekce okna Preferences).
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        if let Some(action) = self.capturing {
+            let pressed = ui.input(|i| {
+                i.events.iter().find_map(|e| match e {
+                    egui::Event::Key {
+                        key,
+                        pressed: true,
+                        modifiers,
+                        ..
+                    } => Some(KeyboardShortcut::new(*modifiers, *key)),
+                    _ => None,
+                })
+            });
+            if let Some(sc) = pressed {
+                if sc.logical_key == Key::Escape {
+                    // Esc jen ruší zachytávání, nezavírá dialog
+                    ui.input_mut(|i| i.consume_key(Modifiers::NONE, Key::Escape));
+                } else {
+                    // jedna zkratka = jedna akce
+                    for v in self.map.values_mut() {
+                        v.retain(|x| *x != sc);
+                    }
+                    self.map.entry(action).or_default().push(sc);
+                }
+                self.capturing = None;
+            }
+        }
+
+        egui::Grid::new("shortcuts")
+            .num_columns(3)
+            .striped(true)
+            .show(ui, |ui| {
+                for action in Action::ALL {
+                    ui.label(action.label());
+                    let text = if self.capturing == Some(action) {
+                        "Press a key… (Esc cancels)".to_string()
+                    } else {
+                        let scs = self.map.get(&action).map(Vec::as_slice).unwrap_or_default();
+                        if scs.is_empty() {
+                            "—".into()
+                        } else {
+                            scs.iter()
+                                .map(|sc| ui.ctx().format_shortcut(sc))
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        }
+                    };
+                    ui.monospace(text);
+                    ui.horizontal(|ui| {
+                        let add = ui.small_button("Add");
+                        add.widget_info(|| {
+                            egui::WidgetInfo::labeled(
+                                egui::WidgetType::Button,
+                                true,
+                                format!("Add shortcut for {}", action.label()),
+                            )
+                        });
+                        if add.clicked() {
+                            self.capturing = Some(action);
+                        }
+                        let clear = ui.small_button("Clear");
+                        clear.widget_info(|| {
+                            egui::WidgetInfo::labeled(
+                                egui::WidgetType::Button,
+                                true,
+                                format!("Clear shortcuts for {}", action.label()),
+                            )
+                        });
+                        if clear.clicked() {
+                            self.map.insert(action, Vec::new());
+                        }
+                    });
+                    ui.end_row();
+                }
+            });
+        if ui.button("Reset to defaults").clicked() {
+            *self = Self::default();
+        }
+    }
+}
+
+/// Uložená mapa doplněná o akce, které v ní chybí (přibyly v novější verzi), s jejich
+/// výchozími zkratkami; zkratku, kterou už má jiná akce, nepřidá.
+fn with_defaults<'de, D: Deserializer<'de>>(
+    d: D,
+) -> Result<BTreeMap<Action, Vec<KeyboardShortcut>>, D::Error> {
+    let mut map = BTreeMap::<Action, Vec<KeyboardShortcut>>::deserialize(d)?;
+    for action in Action::ALL {
+        if map.contains_key(&action) {
+            continue;
+        }
+        let free = action
+            .default_shortcuts()
+            .into_iter()
+            .filter(|sc| !map.values().flatten().any(|x| x == sc))
+            .collect();
+        map.insert(action, free);
+    }
+    Ok(map)
+}
+
+fn modifier_count(m: Modifiers) -> u8 {
+    m.alt as u8 + m.ctrl as u8 + m.shift as u8 + m.mac_cmd as u8 + m.command as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Uložené zkratky verze, která akci `FindText` ještě neznala.
+    fn stored(map: BTreeMap<Action, Vec<KeyboardShortcut>>) -> Shortcuts {
+        let json = serde_json::to_string(&Shortcuts {
+            map,
+            capturing: None,
+        })
+        .unwrap();
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn missing_actions_get_default_shortcuts() {
+        let mut map = Shortcuts::default().map;
+        map.remove(&Action::FindText);
+        let loaded = stored(map);
+        assert_eq!(
+            loaded.get(Action::FindText),
+            Action::FindText.default_shortcuts().first()
+        );
+    }
+
+    #[test]
+    fn cleared_and_taken_shortcuts_stay_as_saved() {
+        let mut map = Shortcuts::default().map;
+        map.insert(Action::Diagnose, Vec::new());
+        map.remove(&Action::FindText);
+        // výchozí zkratku FindText (Ctrl+F) si uživatel dal na Fit
+        map.insert(Action::Fit, Action::FindText.default_shortcuts());
+        let loaded = stored(map);
+        assert_eq!(loaded.get(Action::Diagnose), None);
+        assert_eq!(loaded.map[&Action::FindText], Vec::new());
+        assert_eq!(
+            loaded.get(Action::Fit),
+            Action::FindText.default_shortcuts().first()
+        );
+    }
+}
@@ -0,0 +1,180 @@
+//! Vektorizované smyčky nad pixely (zpětná RCT, rozšíření na RGBA). Obecné varianty
+//! zpracují bloky pevné délky, které překladač převede na SIMD; na x86_64 se za běhu
+//! vybere varianta přeložená pro AVX2, jinak platí základní sada (SSE2, NEON).
+
+/// Pixelů v jednom bloku.
+const LANES: usize = 32;
+
+/// Zavolá `$generic(args)` přeloženou s AVX2, pokud ho procesor má.
+macro_rules! dispatch {
+    ($generic:ident, $avx2:ident, $($arg:ident: $ty:ty),*) => {
+        #[cfg(target_arch = "x86_64")]
+        #[target_feature(enable = "avx2")]
+        fn $avx2($($arg: $ty),*) {
+            $generic($($arg),*)
+        }
+
+        #[cfg(target_arch = "x86_64")]
+        if std::arch::is_x86_feature_detected!("avx2") {
+            // SAFETY: procesor AVX2 podporuje (ověřeno výše)
+            return unsafe { $avx2($($arg),*) };
+        }
+        $generic($($arg),*)
+    };
+}
+
+/// Zpětná RCT pro RGB8 (Y, Cb, Cr → R, G, B) na místě.
+pub fn rct_inverse_rgb8(buf: &mut [u8]) {
+    dispatch!(rct_inverse_rgb8_blocks, rct_inverse_rgb8_avx2, buf: &mut [u8]);
+}
+
+/// Zpětná RCT pro RGB16 (little-endian) na místě.
+pub fn rct_inverse_rgb16(buf: &mut [u8]) {
+    dispatch!(rct_inverse_rgb16_blocks, rct_inverse_rgb16_avx2, buf: &mut [u8]);
+}
+
+/// L8 → RGBA8 s neprůhlednou alfou; `out` má čtyřnásobnou délku `src`.
+pub fn l8_to_rgba(src: &[u8], out: &mut [u8]) {
+    dispatch!(l8_to_rgba_blocks, l8_to_rgba_avx2, src: &[u8], out: &mut [u8]);
+}
+
+/// RGB8 → RGBA8 s neprůhlednou alfou; `out` má délku `src / 3 * 4`.
+pub fn rgb8_to_rgba(src: &[u8], out: &mut [u8]) {
+    dispatch!(rgb8_to_rgba_blocks, rgb8_to_rgba_avx2, src: &[u8], out: &mut [u8]);
+}
+
+#[inline(always)]
+fn rct_inverse_rgb8_px(p: &mut [u8]) {
+    // v i16 se výpočet vejde: g ∈ -63..=318, r, b ∈ -191..=445
+    let y = p[0] as i16;
+    let cb = (p[1] as i8) as i16;
+    let cr = (p[2] as i8) as i16;
+    let g = y - ((cb + cr) >> 2);
+    p[0] = (cr + g).clamp(0, 255) as u8;
+    p[1] = g.clamp(0, 255) as u8;
+    p[2] = (cb + g).clamp(0, 255) as u8;
+}
+
+#[inline(always)]
+fn rct_inverse_rgb8_blocks(buf: &mut [u8]) {
+    let mut blocks = buf.chunks_exact_mut(3 * LANES);
+    for block in &mut blocks {
+        let block: &mut [u8; 3 * LANES] = block.try_into().unwrap();
+        for p in block.chunks_exact_mut(3) {
+            rct_inverse_rgb8_px(p);
+        }
+    }
+    for p in blocks.into_remainder().chunks_exact_mut(3) {
+        rct_inverse_rgb8_px(p);
+    }
+}
+
+#[inline(always)]
+fn rct_inverse_rgb16_px(p: &mut [u8]) {
+    let y = u16::from_le_bytes([p[0], p[1]]) as i32;
+    let cb = (u16::from_le_bytes([p[2], p[3]]) as i16) as i32;
+    let cr = (u16::from_le_bytes([p[4], p[5]]) as i16) as i32;
+    let g = y - ((cb + cr) >> 2);
+    p[0..2].copy_from_slice(&((cr + g).clamp(0, 65535) as u16).to_le_bytes());
+    p[2..4].copy_from_slice(&(g.clamp(0, 65535) as u16).to_le_bytes());
+    p[4..6].copy_from_slice(&((cb + g).clamp(0, 65535) as u16).to_le_bytes());
+}
+
+#[inline(always)]
+fn rct_inverse_rgb16_blocks(buf: &mut [u8]) {
+    let mut blocks = buf.chunks_exact_mut(6 * LANES);
+    for block in &mut blocks {
+        let block: &mut [u8; 6 * LANES] = block.try_into().unwrap();
+        for p in block.chunks_exact_mut(6) {
+            rct_inverse_rgb16_px(p);
+        }
+    }
+    for p in blocks.into_remainder().chunks_exact_mut(6) {
+        rct_inverse_rgb16_px(p);
+    }
+}
+
+#[inline(always)]
+fn l8_to_rgba_blocks(src: &[u8], out: &mut [u8]) {
+    let mut blocks = src.chunks_exact(LANES);
+    let mut outs = out.chunks_exact_mut(4 * LANES);
+    for (block, out) in (&mut blocks).zip(&mut outs) {
+        let block: &[u8; LANES] = block.try_into().unwrap();
+        let out: &mut [u8; 4 * LANES] = out.try_into().unwrap();
+        for (&l, px) in block.iter().zip(out.chunks_exact_mut(4)) {
+            px.copy_from_slice(&[l, l, l, 255]);
+        }
+    }
+    let rest = outs.into_remainder();
+    for (&l, px) in blocks.remainder().iter().zip(rest.chunks_exact_mut(4)) {
+        px.copy_from_slice(&[l, l, l, 255]);
+    }
+}
+
+#[inline(always)]
+fn rgb8_to_rgba_blocks(src: &[u8], out: &mut [u8]) {
+    let mut blocks = src.chunks_exact(3 * LANES);
+    let mut outs = out.chunks_exact_mut(4 * LANES);
+    for (block, out) in (&mut blocks).zip(&mut outs) {
+        let block: &[u8; 3 * LANES] = block.try_into().unwrap();
+        let out: &mut [u8; 4 * LANES] = out.try_into().unwrap();
+        for (p, px) in block.chunks_exact(3).zip(out.chunks_exact_mut(4)) {
+            px.copy_from_slice(&[p[0], p[1], p[2], 255]);
+        }
+    }
+    let rest = outs.into_remainder();
+    for (p, px) in blocks
+        .remainder()
+        .chunks_exact(3)
+        .zip(rest.chunks_exact_mut(4))
+    {
+        px.copy_from_slice(&[p[0], p[1], p[2], 255]);
+    }
+}
+
+/// Původní smyčky po pixelech: referenční výsledek a srovnání v `benches/simd.rs`.
+pub mod scalar {
+    pub fn rct_inverse_rgb8(buf: &mut [u8]) {
+        for p in buf.chunks_exact_mut(3) {
+            let y = p[0] as i32;
+            let cb = (p[1] as i8) as i32;
+            let cr = (p[2] as i8) as i32;
+            let g = y - ((cb + cr) >> 2);
+            let r = cr + g;
+            let b = cb + g;
+            p[0] = r.clamp(0, 255) as u8;
+            p[1] = g.clamp(0, 255) as u8;
+            p[2] = b.clamp(0, 255) as u8;
+        }
+    }
+
+    pub fn rct_inverse_rgb16(buf: &mut [u8]) {
+        for p in buf.chunks_exact_mut(6) {
+            let y = u16::from_le_bytes([p[0], p[1]]) as i32;
+            let cb = (u16::from_le_bytes([p[2], p[3]]) as i16) as i32;
+            let cr = (u16::from_le_bytes([p[4], p[5]]) as i16) as i32;
+            let g = y - ((cb + cr) >> 2);
+            let r = cr + g;
+            let b = cb + g;
+            p[0..2].copy_from_slice(&(r.clamp(0, 65535) as u16).to_le_bytes());
+            p[2..4].copy_from_slice(&(g.clamp(0, 65535) as u16).to_le_bytes());
+            p[4..6].copy_from_slice(&(b.clamp(0, 65535) as u16).to_le_bytes());
+        }
+    }
+
+    pub fn l8_to_rgba(src: &[u8]) -> Vec<u8> {
+        let mut rgba = Vec::with_capacity(src.len() * 4);
+        for &l in src {
+            rgba.extend_from_slice(&[l, l, l, 255]);
+        }
+        rgba
+    }
+
+    pub fn rgb8_to_rgba(src: &[u8]) -> Vec<u8> {
+        let mut rgba = Vec::with_capacity(src.len() / 3 * 4);
+        for px in src.chunks_exact(3) {
+            rgba.extend_from_slice(&[px[0], px[1], px[2], 255]);
+        }
+        rgba
+    }
+}
@@ -0,0 +1,148 @@
+use anyhow::{Context, Result};
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::annotations::now_rfc3339;
+
+/// Místní statistiky práce s prohlížečem (jen na tomto počítači, nikam se neposílají);
+/// zapisují se jen se zapnutou předvolbou a exportují do CSV.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UsageStats {
+    /// Po dnech (`YYYY-MM-DD`, UTC).
+    days: BTreeMap<String, Day>,
+    /// Hashe souborů otevřených dnes (bez cest), ať se opakované otevření nepočítá.
+    seen: BTreeSet<u64>,
+    seen_day: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Day {
+    /// Různé prohlédnuté soubory.
+    files: u32,
+    /// Všechna otevření.
+    opens: u32,
+    decodes: u32,
+    decode_ms: u64,
+    /// Prohlédnuté soubory podle komprese dlaždic.
+    codecs: BTreeMap<String, u32>,
+}
+
+fn today() -> String {
+    now_rfc3339()[..10].to_owned()
+}
+
+impl UsageStats {
+    /// Otevřený soubor s kompresí `codec`.
+    pub fn record_open(&mut self, path: &Path, codec: &str) {
+        let date = today();
+        if self.seen_day != date {
+            self.seen.clear();
+            self.seen_day = date.clone();
+        }
+        let hash = Sha256::digest(path.as_os_str().as_encoded_bytes());
+        let first = self
+            .seen
+            .insert(u64::from_le_bytes(hash[..8].try_into().unwrap()));
+        let day = self.days.entry(date).or_default();
+        day.opens += 1;
+        if first {
+            day.files += 1;
+            *day.codecs.entry(codec.to_owned()).or_default() += 1;
+        }
+    }
+
+    /// Doba dekódování (od otevření po poslední dlaždici).
+    pub fn record_decode(&mut self, took: Duration) {
+        let day = self.days.entry(today()).or_default();
+        day.decodes += 1;
+        day.decode_ms += took.as_millis() as u64;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.days.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Den po řádku; komprese ve sloupcích podle všech, které se vyskytly.
+    pub fn to_csv(&self) -> String {
+        let codecs: BTreeSet<&str> = self
+            .days
+            .values()
+            .flat_map(|d| d.codecs.keys().map(String::as_str))
+            .collect();
+        let mut out = String::from("date,files_reviewed,opens,decodes,avg_decode_ms");
+        for c in &codecs {
+            out.push_str(&format!(",codec_{c}"));
+        }
+        out.push('\n');
+        for (date, d) in &self.days {
+            let avg = d.decode_ms.checked_div(u64::from(d.decodes)).unwrap_or(0);
+            out.push_str(&format!(
+                "{date},{},{},{},{avg}",
+                d.files, d.opens, d.decodes
+            ));
+            for c in &codecs {
+                out.push_str(&format!(",{}", d.codecs.get(*c).copied().unwrap_or(0)));
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    fn export(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, self.to_csv()).with_context(|| format!("write {}", path.display()))
+    }
+
+    /// Součty za celé období pro nastavení.
+    fn summary(&self) -> String {
+        let files: u32 = self.days.values().map(|d| d.files).sum();
+        let decodes: u32 = self.days.values().map(|d| d.decodes).sum();
+        let ms: u64 = self.days.values().map(|d| d.decode_ms).sum();
+        let avg = ms.checked_div(u64::from(decodes)).unwrap_or(0);
+        format!(
+            "{files} file(s) reviewed on {} day(s), average decode {avg} ms",
+            self.days.len()
+        )
+    }
+}
+
+/// Zapnutí, přehled, export a smazání statistik v nastavení.
+pub fn stats_ui(ui: &mut egui::Ui, enabled: &mut bool, stats: &mut UsageStats) {
+    ui.checkbox(enabled, "Record usage statistics on this computer")
+        .on_hover_text("Files reviewed per day, decode times and codecs; nothing is sent anywhere");
+    if stats.is_empty() {
+        ui.weak("No statistics recorded.");
+        return;
+    }
+    ui.weak(stats.summary());
+    ui.horizontal(|ui| {
+        let id = ui.id().with("stats-export");
+        if ui.button("Export CSV…").clicked()
+            && let Some(path) = rfd::FileDialog::new()
+                .add_filter("CSV", &["csv"])
+                .set_file_name("cti-view-usage.csv")
+                .save_file()
+        {
+            let msg = match stats.export(&path) {
+                Ok(()) => format!("Saved {}", path.display()),
+                Err(e) => format!("{e:#}"),
+            };
+            ui.data_mut(|d| d.insert_temp(id, msg));
+        }
+        if ui.button("Clear").clicked() {
+            stats.clear();
+        }
+        if let Some(msg) = ui.data(|d| d.get_temp::<String>(id)) {
+            ui.weak(msg);
+        }
+    });
+}
@@ -0,0 +1,408 @@
+use anyhow::{Context, Result, bail, ensure};
+use eframe::egui;
+use rayon::prelude::*;
+use rfd::FileDialog;
+use std::path::{Path, PathBuf};
+
+use crate::a11y;
+use crate::align::{self, Gray, Luma};
+use crate::convert;
+use crate::cti::{self, CTIDecoder, CTIEncoder, CTIHeader, EncodeParams};
+use crate::diff::{self, Layout};
+
+/// Hrubá registrace dvojic probíhá na zmenšeninách s delší stranou nejvýš tolik pixelů.
+const COARSE_SIZE: usize = 512;
+/// Největší výřez překryvu pro zpřesnění v plném rozlišení.
+const FINE_SIZE: usize = 512;
+/// Minimální normalizovaná korelace překryvu, aby se dvojice považovala za sousední.
+const MIN_NCC: f32 = 0.5;
+/// Minimální překryv jako podíl plochy menšího snímku.
+const MIN_OVERLAP: f32 = 0.03;
+/// Počet řádků výstupu skládaných najednou.
+const BAND: usize = 256;
+
+/// Ověřená dvojice překrývajících se snímků: `offset` = poloha `j` vůči `i` v pixelech.
+struct Edge {
+    i: usize,
+    j: usize,
+    offset: (i64, i64),
+    ncc: f32,
+}
+
+/// Počet kroků průběhu pro `n` vstupů (dekódování, registrace, skládání, zápis).
+pub fn steps(n: usize) -> usize {
+    n + 3
+}
+
+/// Sešije překrývající se snímky (jen posun, bez natočení) do jednoho CTI s prolnutím ve švech.
+/// `progress` dostává zprávy po krocích; `false` = zrušit.
+pub fn stitch_files(
+    files: &[PathBuf],
+    out: &Path,
+    params: &EncodeParams,
+    progress: &mut dyn FnMut(String) -> bool,
+) -> Result<String> {
+    ensure!(files.len() >= 2, "select at least two captures");
+    let mut images = Vec::with_capacity(files.len());
+    for f in files {
+        let (hdr, raw) =
+            CTIDecoder::decode_file(f).with_context(|| format!("decode {}", f.display()))?;
+        if let Some((first, _)) = images.first() {
+            let first: &CTIHeader = first;
+            ensure!(
+                hdr.color_type == first.color_type,
+                "{} has a different color type than {}",
+                f.display(),
+                files[0].display()
+            );
+        }
+        images.push((hdr, raw));
+        if !progress(format!("loaded {}", name(f))) {
+            bail!("cancelled");
+        }
+    }
+
+    let edges = register(&images)?;
+    let positions = place(&edges, files)?;
+    if !progress(format!("registered {} overlaps", edges.len())) {
+        bail!("cancelled");
+    }
+
+    let (w, h, raw) = composite(&images, &positions)?;
+    if !progress(format!("composited {w}x{h}")) {
+        bail!("cancelled");
+    }
+
+    let mut meta = CTIDecoder::metadata(&files[0])?;
+    let names: Vec<String> = files.iter().map(|f| name(f)).collect();
+    meta.set("StitchedFrom", names.join("; "));
+    if let Some(parent) = out.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    CTIEncoder::encode_file_with_metadata(
+        out,
+        w as u32,
+        h as u32,
+        images[0].0.color_type,
+        &raw,
+        params,
+        &meta,
+    )?;
+    let size = std::fs::metadata(out)?.len();
+    Ok(format!(
+        "{} captures → {} ({w}x{h}, {size} B)",
+        files.len(),
+        out.display()
+    ))
+}
+
+fn name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Registrace všech dvojic: fázová korelace zmenšenin, ověření překryvu korelací
+/// a zpřesnění posunu v plném rozlišení.
+fn register(images: &[(CTIHeader, Vec<u8>)]) -> Result<Vec<Edge>> {
+    let longest = images
+        .iter()
+        .map(|(h, _)| h.width.max(h.height) as usize)
+        .max()
+        .unwrap_or(1);
+    let step = longest.div_ceil(COARSE_SIZE).max(1);
+    let lumas = images
+        .iter()
+        .map(|(h, raw)| Luma::new(h, raw))
+        .collect::<Result<Vec<_>>>()?;
+    let grids: Vec<Gray> = lumas.par_iter().map(|l| l.downsample(step)).collect();
+
+    let pairs: Vec<(usize, usize)> = (0..images.len())
+        .flat_map(|i| (i + 1..images.len()).map(move |j| (i, j)))
+        .collect();
+    Ok(pairs
+        .par_iter()
+        .filter_map(|&(i, j)| {
+            let (dx, dy) = coarse_offset(&grids[i], &grids[j]);
+            let ncc = overlap_ncc(&grids[i], &grids[j], dx, dy)?;
+            if ncc < MIN_NCC {
+                return None;
+            }
+            let coarse = (dx * step as i64, dy * step as i64);
+            let offset = refine(&lumas[i], &lumas[j], coarse);
+            Some(Edge { i, j, offset, ncc })
+        })
+        .collect())
+}
+
+/// Posun `b` vůči `a` ve zmenšenině. Snímky se okénkují každý zvlášť (okraje, kde leží
+/// překryv, se tlumí jen málo) a vloží do plátna, na kterém je posun jednoznačný.
+fn coarse_offset(a: &Gray, b: &Gray) -> (i64, i64) {
+    let (w, h) = (
+        (a.w + b.w).next_multiple_of(16),
+        (a.h + b.h).next_multiple_of(16),
+    );
+    let canvas = |g: &Gray| {
+        let win = align::windowed(&g.px, g.w, g.h, 0.2);
+        let mut out = vec![0f32; w * h];
+        for y in 0..g.h {
+            out[y * w..y * w + g.w].copy_from_slice(&win[y * g.w..(y + 1) * g.w]);
+        }
+        out
+    };
+    let (d, _) = align::correlate(&canvas(a), &canvas(b), w, h);
+    let unwrap = |v: f32, n: usize, hi: usize, lo: usize| {
+        let mut v = v.round() as i64;
+        if v > hi as i64 {
+            v -= n as i64;
+        } else if v < -(lo as i64) {
+            v += n as i64;
+        }
+        v
+    };
+    (unwrap(d.x, w, a.w, b.w), unwrap(d.y, h, a.h, b.h))
+}
+
+/// Normalizovaná korelace překryvu `a` a `b` posunutého o (dx, dy); `None` = malý překryv.
+fn overlap_ncc(a: &Gray, b: &Gray, dx: i64, dy: i64) -> Option<f32> {
+    let x0 = dx.max(0);
+    let x1 = (dx + b.w as i64).min(a.w as i64);
+    let y0 = dy.max(0);
+    let y1 = (dy + b.h as i64).min(a.h as i64);
+    let area = (x1 - x0).max(0) * (y1 - y0).max(0);
+    let smaller = (a.w * a.h).min(b.w * b.h) as f32;
+    if area < 16 || (area as f32) < MIN_OVERLAP * smaller {
+        return None;
+    }
+    let (mut sa, mut sb, mut saa, mut sbb, mut sab) = (0f64, 0f64, 0f64, 0f64, 0f64);
+    for y in y0..y1 {
+        for x in x0..x1 {
+            let va = a.at(x as usize, y as usize) as f64;
+            let vb = b.at((x - dx) as usize, (y - dy) as usize) as f64;
+            sa += va;
+            sb += vb;
+            saa += va * va;
+            sbb += vb * vb;
+            sab += va * vb;
+        }
+    }
+    let n = area as f64;
+    let cov = sab - sa * sb / n;
+    let var = (saa - sa * sa / n) * (sbb - sb * sb / n);
+    (var > 1e-12).then(|| (cov / var.sqrt()) as f32)
+}
+
+/// Zpřesní posun na výřezu ze středu překryvu v plném rozlišení.
+fn refine(a: &Luma, b: &Luma, (dx, dy): (i64, i64)) -> (i64, i64) {
+    let x0 = dx.max(0);
+    let x1 = (dx + b.w as i64).min(a.w as i64);
+    let y0 = dy.max(0);
+    let y1 = (dy + b.h as i64).min(a.h as i64);
+    let cw = ((x1 - x0).max(0) as usize).min(FINE_SIZE);
+    let ch = ((y1 - y0).max(0) as usize).min(FINE_SIZE);
+    if cw < 32 || ch < 32 {
+        return (dx, dy);
+    }
+    let cx = (x0 + x1) / 2 - cw as i64 / 2;
+    let cy = (y0 + y1) / 2 - ch as i64 / 2;
+    let mut crop_a = Vec::with_capacity(cw * ch);
+    let mut crop_b = Vec::with_capacity(cw * ch);
+    for y in cy..cy + ch as i64 {
+        for x in cx..cx + cw as i64 {
+            crop_a.push(a.at(x as usize, y as usize));
+            crop_b.push(b.at((x - dx) as usize, (y - dy) as usize));
+        }
+    }
+    let (r, _) = align::correlate(
+        &align::windowed(&crop_a, cw, ch, 1.0),
+        &align::windowed(&crop_b, cw, ch, 1.0),
+        cw,
+        ch,
+    );
+    (dx + r.x.round() as i64, dy + r.y.round() as i64)
+}
+
+/// Polohy snímků z nejspolehlivějších dvojic (maximální kostra grafu překryvů).
+fn place(edges: &[Edge], files: &[PathBuf]) -> Result<Vec<(i64, i64)>> {
+    let n = files.len();
+    let mut order: Vec<&Edge> = edges.iter().collect();
+    order.sort_by(|a, b| b.ncc.total_cmp(&a.ncc));
+
+    let mut pos: Vec<Option<(i64, i64)>> = vec![None; n];
+    pos[0] = Some((0, 0));
+    // přidávat hrany, dokud některá napojí další snímek na už umístěné
+    loop {
+        let next = order.iter().find_map(|e| match (pos[e.i], pos[e.j]) {
+            (Some((x, y)), None) => Some((e.j, (x + e.offset.0, y + e.offset.1))),
+            (None, Some((x, y))) => Some((e.i, (x - e.offset.0, y - e.offset.1))),
+            _ => None,
+        });
+        match next {
+            Some((k, p)) => pos[k] = Some(p),
+            None => break,
+        }
+    }
+    let missing: Vec<String> = (0..n)
+        .filter(|&k| pos[k].is_none())
+        .map(|k| name(&files[k]))
+        .collect();
+    if !missing.is_empty() {
+        bail!("no overlap found for: {}", missing.join(", "));
+    }
+    Ok(pos.into_iter().flatten().collect())
+}
+
+/// Složí snímky na plátno; v překryvech vážený průměr s vahou rostoucí od okraje snímku.
+fn composite(
+    images: &[(CTIHeader, Vec<u8>)],
+    positions: &[(i64, i64)],
+) -> Result<(usize, usize, Vec<u8>)> {
+    let min_x = positions.iter().map(|p| p.0).min().unwrap_or(0);
+    let min_y = positions.iter().map(|p| p.1).min().unwrap_or(0);
+    let placed: Vec<(usize, usize, &CTIHeader, &[u8])> = images
+        .iter()
+        .zip(positions)
+        .map(|((h, raw), p)| ((p.0 - min_x) as usize, (p.1 - min_y) as usize, h, &raw[..]))
+        .collect();
+    let w = placed
+        .iter()
+        .map(|(x, _, h, _)| x + h.width as usize)
+        .max()
+        .unwrap_or(0);
+    let h = placed
+        .iter()
+        .map(|(_, y, h, _)| y + h.height as usize)
+        .max()
+        .unwrap_or(0);
+    ensure!(
+        w <= u32::MAX as usize && h <= u32::MAX as usize,
+        "stitched image too large"
+    );
+    let Layout { channels, wide } =
+        diff::layout(images[0].0.color_type).context("unsupported color type")?;
+    let bpp = cti::bytes_per_pixel(images[0].0.color_type)? as usize;
+    let mut out = vec![0u8; w * h * bpp];
+
+    out.par_chunks_mut(BAND * w * bpp)
+        .enumerate()
+        .for_each(|(band, rows)| {
+            let y0 = band * BAND;
+            let bh = rows.len() / (w * bpp);
+            let mut acc = vec![0f32; w * bh * channels];
+            let mut weight = vec![0f32; w * bh];
+            for &(ox, oy, hdr, raw) in &placed {
+                let (iw, ih) = (hdr.width as usize, hdr.height as usize);
+                let ys = y0.max(oy)..(y0 + bh).min(oy + ih);
+                for y in ys {
+                    let sy = y - oy;
+                    let fy = (sy + 1).min(ih - sy);
+                    for sx in 0..iw {
+                        let wt = (sx + 1).min(iw - sx).min(fy) as f32;
+                        let o = (y - y0) * w + ox + sx;
+                        let i = (sy * iw + sx) * channels;
+                        for c in 0..channels {
+                            acc[o * channels + c] += wt * diff::sample(raw, wide, i + c) as f32;
+                        }
+                        weight[o] += wt;
+                    }
+                }
+            }
+            for (o, &wt) in weight.iter().enumerate() {
+                if wt == 0.0 {
+                    continue;
+                }
+                for c in 0..channels {
+                    let v = (acc[o * channels + c] / wt).round();
+                    let k = o * channels + c;
+                    if wide {
+                        rows[k * 2..k * 2 + 2].copy_from_slice(&(v as u16).to_le_bytes());
+                    } else {
+                        rows[k] = v as u8;
+                    }
+                }
+            }
+        });
+    Ok((w, h, out))
+}
+
+/// Dialog „Stitch captures“: výběr snímků, výstupní soubor a parametry kódování.
+#[derive(Default)]
+pub struct StitchDialog {
+    files: Vec<PathBuf>,
+    out: Option<PathBuf>,
+    params: EncodeParams,
+}
+
+impl StitchDialog {
+    /// Vrátí vstupy, výstup a parametry po kliknutí na Stitch.
+    pub fn window(
+        &mut self,
+        ctx: &egui::Context,
+        open: &mut bool,
+    ) -> Option<(Vec<PathBuf>, PathBuf, EncodeParams)> {
+        let mut start = None;
+        a11y::modal_dialog(ctx, "Stitch captures → CTI", open, |ui| {
+            ui.label("Overlapping sections of one object (shift only, no rotation).");
+            let mut remove = None;
+            egui::ScrollArea::vertical()
+                .max_height(200.0)
+                .show(ui, |ui| {
+                    for (i, f) in self.files.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            if ui.small_button("✕").on_hover_text("Remove").clicked() {
+                                remove = Some(i);
+                            }
+                            ui.monospace(f.display().to_string());
+                        });
+                    }
+                });
+            if let Some(i) = remove {
+                self.files.remove(i);
+            }
+            ui.horizontal(|ui| {
+                if ui.button("Add files…").clicked()
+                    && let Some(files) = FileDialog::new().add_filter("CTI", &["cti"]).pick_files()
+                {
+                    for f in files {
+                        if !self.files.contains(&f) {
+                            self.files.push(f);
+                        }
+                    }
+                }
+                if ui.button("Clear").clicked() {
+                    self.files.clear();
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Output");
+                let text = self
+                    .out
+                    .as_deref()
+                    .map_or("—".into(), |p| p.display().to_string());
+                ui.monospace(text);
+                if ui.button("Save as…").clicked()
+                    && let Some(p) = FileDialog::new()
+                        .add_filter("CTI", &["cti"])
+                        .set_file_name("stitched.cti")
+                        .save_file()
+                {
+                    self.out = Some(p);
+                }
+            });
+            ui.separator();
+            convert::params_ui(ui, &mut self.params);
+            ui.separator();
+            let ready = self.files.len() >= 2 && self.out.is_some();
+            if ui.add_enabled(ready, egui::Button::new("Stitch")).clicked()
+                && let Some(out) = &self.out
+            {
+                start = Some((self.files.clone(), out.clone(), self.params));
+            }
+        });
+        if start.is_some() {
+            *open = false;
+        }
+        start
+    }
+}
@@ -0,0 +1,78 @@
+//! Karty s více otevřenými soubory v jednom okně. Aktivní karta žije přímo v polích
+//! aplikace; ostatní čekají zde jako [`Tab`] a při přepnutí se vymění (dekódování na
+//! pozadí mezitím běží dál).
+
+use eframe::egui::{self, Rect, TextureHandle};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::annotations::Annotations;
+use crate::compare::CompareImage;
+use crate::cti::{BadTile, CTIHeader, CTIMetadata};
+use crate::loader::Loader;
+use crate::view::View;
+
+/// Stav jednoho otevřeného souboru mimo aktivní kartu.
+#[derive(Default)]
+pub struct Tab {
+    pub path: Option<PathBuf>,
+    pub image_tex: Option<TextureHandle>,
+    pub preview_tex: Option<TextureHandle>,
+    pub image_size: Option<(u32, u32)>,
+    pub image_rect: Option<Rect>,
+    pub image_clip: Option<Rect>,
+    pub raw: Option<Arc<Vec<u8>>>,
+    pub loader: Option<Loader>,
+    pub decode_time: Option<Duration>,
+    pub last_hdr: Option<CTIHeader>,
+    pub bad_tiles: Vec<BadTile>,
+    pub metadata: CTIMetadata,
+    /// Zobrazená stránka a počet stránek.
+    pub frame: u32,
+    pub frames: u32,
+    pub view: View,
+    pub compare: Option<CompareImage>,
+    pub annotations: Annotations,
+    pub pending_region: Option<Rect>,
+}
+
+/// Co uživatel v liště karet udělal.
+pub enum TabAction {
+    Select(usize),
+    Close(usize),
+}
+
+/// Název karty podle souboru.
+pub fn title(path: Option<&Path>) -> String {
+    path.and_then(Path::file_name).map_or_else(
+        || "(empty)".to_owned(),
+        |n| n.to_string_lossy().into_owned(),
+    )
+}
+
+/// Lišta karet; prostřední tlačítko nebo „×“ kartu zavře.
+pub fn bar(ui: &mut egui::Ui, tabs: &[Option<&Path>], active: usize) -> Option<TabAction> {
+    let mut action = None;
+    egui::ScrollArea::horizontal().show(ui, |ui| {
+        ui.horizontal(|ui| {
+            for (i, path) in tabs.iter().enumerate() {
+                let resp = ui.selectable_label(i == active, title(*path));
+                let resp = match path {
+                    Some(p) => resp.on_hover_text(p.display().to_string()),
+                    None => resp,
+                };
+                if resp.clicked() {
+                    action = Some(TabAction::Select(i));
+                }
+                if resp.middle_clicked()
+                    || ui.small_button("×").on_hover_text("Close tab").clicked()
+                {
+                    action = Some(TabAction::Close(i));
+                }
+                ui.separator();
+            }
+        });
+    });
+    action
+}
@@ -0,0 +1,347 @@
+//! Syntetická sada souborů CTI pro testy (`cti-view gen-testdata`): platné soubory pro každý
+//! kodek a typ barev, okrajové rozměry dlaždic a varianty formátu, k tomu záměrně poškozené
+//! soubory (useknuté, špatné CRC, poškozená hlavička…). `manifest.json` u každého souboru
+//! uvádí parametry, vadu, SHA-256 dekódovaných pixelů a kódy z [`crate::lint`], takže podle
+//! něj jde ověřit i jiná implementace formátu. Obsah je deterministický: stejná verze
+//! programu vytvoří bajtově stejné soubory.
+
+use anyhow::{Context, Result, ensure};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+use crate::cti::{
+    self, CTIAppendEncoder, CTIDecoder, CTIEncoder, CTIExtensions, CTIMetadata, CTITileReader,
+    CompressionId, EncodeParams, HEADER_SIZE,
+};
+use crate::{dct, lint};
+
+/// Typy barev vstupu (L1 a L4 vzniknou z L8 přes [`EncodeParams::gray_bits`]).
+const COLOR_TYPES: [u8; 8] = [1, 2, 3, 4, 5, 8, 9, 10];
+
+/// Rozměr obrázků matice kodek × typ barev: 3×2 dlaždice, krajní jsou neúplné.
+const MATRIX_SIZE: (u32, u32) = (70, 45);
+const MATRIX_TILE: u32 = 32;
+
+/// Okrajové rozměry (šířka, výška, velikost dlaždice).
+const EDGES: [(&str, u32, u32, u32); 7] = [
+    ("1x1", 1, 1, 256),
+    ("exact", 64, 64, 32),
+    ("plus1", 65, 33, 32),
+    ("below-tile", 31, 31, 32),
+    ("row", 300, 1, 16),
+    ("column", 1, 300, 16),
+    ("tile1", 5, 3, 1),
+];
+
+/// Varianty formátu nad RGB8 z matice.
+const FEATURES: [&str; 6] = [
+    "frames",
+    "metadata",
+    "crc32c",
+    "no-hash",
+    "no-rct",
+    "index-at-end",
+];
+
+/// Záměrná vada souboru.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Defect {
+    /// Soubor končí uprostřed poslední dlaždice.
+    Truncated,
+    /// Jeden bajt dat první dlaždice je změněný, CRC v indexu nesedí.
+    BadCrc,
+    /// Dlaždice jsou v pořádku, nesedí jen SHA-256 v traileru.
+    BadHash,
+    /// Místo `CTI1` je v hlavičce jiný magic.
+    BadMagic,
+    /// Neznámé id kodeku v hlavičce.
+    UnknownCodec,
+    /// Soubor kratší než hlavička.
+    ShortHeader,
+    /// Položka indexu první dlaždice ukazuje za konec souboru.
+    OffsetPastEnd,
+}
+
+impl Defect {
+    pub const ALL: [Defect; 7] = [
+        Defect::Truncated,
+        Defect::BadCrc,
+        Defect::BadHash,
+        Defect::BadMagic,
+        Defect::UnknownCodec,
+        Defect::ShortHeader,
+        Defect::OffsetPastEnd,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Defect::Truncated => "truncated",
+            Defect::BadCrc => "bad-crc",
+            Defect::BadHash => "bad-hash",
+            Defect::BadMagic => "bad-magic",
+            Defect::UnknownCodec => "unknown-codec",
+            Defect::ShortHeader => "short-header",
+            Defect::OffsetPastEnd => "offset-past-end",
+        }
+    }
+}
+
+/// Položka manifestu.
+#[derive(Debug, Clone, Serialize)]
+pub struct Case {
+    /// Název souboru ve výstupní složce.
+    pub file: String,
+    pub width: u32,
+    pub height: u32,
+    pub tile_size: u32,
+    /// Typ barev uložený v hlavičce (`L8` s `gray_bits` 1 je `L1`).
+    pub color_type: &'static str,
+    pub compression: &'static str,
+    pub frames: u32,
+    /// Zvláštnosti souboru (`crc32c`, `metadata`, `index-at-end`…).
+    pub features: Vec<&'static str>,
+    /// `None` = platný soubor, který musí jít dekódovat.
+    pub defect: Option<Defect>,
+    /// SHA-256 dekódovaných pixelů všech snímků ve tvaru `decode_frame` (ztrátový kodek
+    /// podle tohoto dekodéru); jen u platných souborů.
+    pub pixels_sha256: Option<String>,
+    /// Kódy z `cti-view lint`.
+    pub lint: Vec<&'static str>,
+}
+
+/// Vytvoří sadu ve složce `dir` včetně `manifest.json` a vrátí její položky.
+pub fn generate(dir: &Path) -> Result<Vec<Case>> {
+    std::fs::create_dir_all(dir).with_context(|| format!("create {}", dir.display()))?;
+    let mut cases = Vec::new();
+    let (w, h) = MATRIX_SIZE;
+    for codec in CompressionId::encoders() {
+        for color_type in COLOR_TYPES {
+            if codec.is_lossy() && dct::channels(color_type).is_err() {
+                continue;
+            }
+            let spec = Spec::new(w, h, color_type, codec);
+            let name = format!(
+                "{}-{}",
+                codec.as_str().to_ascii_lowercase(),
+                cti::color_name(color_type).to_ascii_lowercase()
+            );
+            cases.push(spec.write(dir, &name)?);
+        }
+    }
+    for bits in [1, 4] {
+        let mut spec = Spec::new(w, h, 1, CompressionId::Zstd);
+        spec.params.gray_bits = bits;
+        cases.push(spec.write(dir, &format!("zstd-l{bits}"))?);
+    }
+    for (name, w, h, tile) in EDGES {
+        let mut spec = Spec::new(w, h, 3, CompressionId::Zstd);
+        spec.params.tile_size = tile;
+        cases.push(spec.write(dir, &format!("edge-{name}"))?);
+    }
+    for feature in FEATURES {
+        let mut spec = Spec::new(w, h, 3, CompressionId::Zstd);
+        match feature {
+            "frames" => spec.frames = 3,
+            "metadata" => {
+                spec.meta.set("Title", "Synthetic test image");
+                spec.meta.set_dpi((300.0, 300.0));
+            }
+            "crc32c" => spec.params.crc32c = true,
+            "no-hash" => spec.params.file_hash = false,
+            "no-rct" => spec.params.rct = false,
+            _ => spec.append = true,
+        }
+        spec.features.push(feature);
+        cases.push(spec.write(dir, &format!("feature-{feature}"))?);
+    }
+    for defect in Defect::ALL {
+        let mut spec = Spec::new(w, h, 3, CompressionId::Zstd);
+        // jedna vada na soubor: hash by jinak nesouhlasil u každé z nich
+        spec.params.file_hash = defect == Defect::BadHash;
+        cases.push(spec.write_broken(dir, defect)?);
+    }
+    let manifest = dir.join("manifest.json");
+    std::fs::write(&manifest, serde_json::to_string_pretty(&cases)?)
+        .with_context(|| format!("write {}", manifest.display()))?;
+    Ok(cases)
+}
+
+/// Parametry jednoho souboru.
+struct Spec {
+    width: u32,
+    height: u32,
+    color_type: u8,
+    frames: u32,
+    params: EncodeParams,
+    meta: CTIMetadata,
+    /// Zapsat po řádcích přes [`CTIAppendEncoder`] (index na konci).
+    append: bool,
+    features: Vec<&'static str>,
+}
+
+impl Spec {
+    fn new(width: u32, height: u32, color_type: u8, compression: CompressionId) -> Self {
+        Self {
+            width,
+            height,
+            color_type,
+            frames: 1,
+            params: EncodeParams {
+                tile_size: MATRIX_TILE,
+                compression,
+                level: compression.default_level(),
+                ..EncodeParams::default()
+            },
+            meta: CTIMetadata::default(),
+            append: false,
+            features: Vec::new(),
+        }
+    }
+
+    /// Zapíše platný soubor a ověří, že bezeztrátový kodek vrátí stejné pixely.
+    fn write(&self, dir: &Path, name: &str) -> Result<Case> {
+        let path = dir.join(format!("{name}.cti"));
+        let frames: Vec<Vec<u8>> = (0..self.frames).map(|n| self.pixels(n)).collect();
+        if self.append {
+            let ext = CTIExtensions {
+                metadata: self.meta.clone(),
+                ..Default::default()
+            };
+            let (w, h) = (self.width, self.height);
+            let mut enc =
+                CTIAppendEncoder::create(&path, w, h, self.color_type, &self.params, &ext)?;
+            let row = w as usize * cti::bytes_per_pixel(self.color_type)? as usize;
+            let mut y = 0;
+            while enc.next_row_height() > 0 {
+                let rows = enc.next_row_height() as usize;
+                enc.push_row(&frames[0][y * row..(y + rows) * row])?;
+                y += rows;
+            }
+            enc.finish()?;
+        } else {
+            let refs: Vec<&[u8]> = frames.iter().map(Vec::as_slice).collect();
+            CTIEncoder::encode_frames_with_metadata(
+                &path,
+                self.width,
+                self.height,
+                self.color_type,
+                &refs,
+                &self.params,
+                &self.meta,
+            )?;
+        }
+        let mut hash = Sha256::new();
+        for (n, source) in frames.iter().enumerate() {
+            let (_, pixels) = CTIDecoder::decode_frame(&path, n as u32)
+                .with_context(|| format!("decode {}", path.display()))?;
+            ensure!(
+                self.params.compression.is_lossy() || pixels == *source,
+                "{}: frame {n} does not round-trip",
+                path.display()
+            );
+            hash.update(&pixels);
+        }
+        let mut case = self.case(&path)?;
+        case.pixels_sha256 = Some(hex(&hash.finalize()));
+        Ok(case)
+    }
+
+    /// Zapíše platný soubor a poškodí ho podle `defect`.
+    fn write_broken(&self, dir: &Path, defect: Defect) -> Result<Case> {
+        let name = format!("broken-{}", defect.name());
+        self.write(dir, &name)?;
+        let path = dir.join(format!("{name}.cti"));
+        let (first, last) = {
+            let reader = CTITileReader::open(&path)?;
+            let hdr = *reader.header();
+            (
+                reader.tile_entry(0, 0, 0)?,
+                reader.tile_entry(0, hdr.tiles_x - 1, hdr.tiles_y - 1)?,
+            )
+        };
+        let mut bytes = std::fs::read(&path)?;
+        let len = bytes.len();
+        match defect {
+            Defect::Truncated => {
+                bytes.truncate((last.offset + u64::from(last.compressed_size) / 2) as usize)
+            }
+            Defect::BadCrc => {
+                bytes[(first.offset + u64::from(first.compressed_size) / 2) as usize] ^= 0x55
+            }
+            Defect::BadHash => bytes[len - 1] ^= 0x55,
+            Defect::BadMagic => bytes[..4].copy_from_slice(b"CTX1"),
+            // bajt 29 = kodek (za typem barev)
+            Defect::UnknownCodec => bytes[29] = 0xEE,
+            Defect::ShortHeader => bytes.truncate(HEADER_SIZE / 2),
+            Defect::OffsetPastEnd => {
+                let past = (len as u64 + 4096).to_le_bytes();
+                bytes[HEADER_SIZE..HEADER_SIZE + 8].copy_from_slice(&past);
+            }
+        }
+        std::fs::write(&path, bytes)?;
+        let mut case = self.case(&path)?;
+        case.defect = Some(defect);
+        Ok(case)
+    }
+
+    /// Položka manifestu bez hashe pixelů a vady.
+    fn case(&self, path: &Path) -> Result<Case> {
+        let stored = match (self.color_type, self.params.gray_bits) {
+            (1, 1) => 6,
+            (1, 4) => 7,
+            (other, _) => other,
+        };
+        Ok(Case {
+            file: path.file_name().unwrap().to_string_lossy().into_owned(),
+            width: self.width,
+            height: self.height,
+            tile_size: self.params.tile_size,
+            color_type: cti::color_name(stored),
+            compression: self.params.compression.as_str(),
+            frames: self.frames,
+            features: self.features.clone(),
+            defect: None,
+            pixels_sha256: None,
+            lint: lint::lint_file(path)?.iter().map(|i| i.code).collect(),
+        })
+    }
+
+    /// Plynulý přechod se šumem, každý snímek jinak posunutý; float typy v rozsahu 0..4
+    /// (HDR), šedá pro L1/L4 je dvouúrovňová, ať se po sbalení nemění.
+    fn pixels(&self, frame: u32) -> Vec<u8> {
+        let samples = match self.color_type {
+            2 | 9 => 1,
+            5 | 10 => 3,
+            other => cti::bytes_per_pixel(other).unwrap() as usize,
+        };
+        let count = self.width as usize * self.height as usize * samples;
+        let mut x = 0x2545_f491_u32 ^ frame;
+        let mut noise = move || {
+            x ^= x << 13;
+            x ^= x >> 17;
+            x ^= x << 5;
+            x
+        };
+        let ramp = |i: usize| {
+            let p = (i / samples) as u32;
+            (p % self.width + p / self.width + frame * 7) as f32 / (self.width + self.height) as f32
+        };
+        let mut out = Vec::new();
+        for i in 0..count {
+            let v = (ramp(i) + (noise() & 0x0F) as f32 / 256.0).fract();
+            match self.color_type {
+                2 | 5 => out.extend_from_slice(&((v * 65535.0) as u16).to_le_bytes()),
+                9 | 10 => out.extend_from_slice(&(v * 4.0).to_le_bytes()),
+                1 if self.params.gray_bits != 8 => out.push(if v < 0.5 { 0 } else { 255 }),
+                _ => out.push((v * 255.0) as u8),
+            }
+        }
+        out
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
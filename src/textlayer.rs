@@ -0,0 +1,369 @@
+//! Textová vrstva z OCR uložená vedle obrázku se stejným jménem: ALTO (`<stem>.xml`,
+//! `<stem>.alto.xml`) nebo hOCR (`<stem>.hocr`, `<stem>.html`). Obdélníky slov se dají
+//! zobrazit přes obrázek a text prohledávat (Ctrl+F), pohled přejede na nalezené slovo –
+//! pro korektury výstupu OCR. Stránky souboru (`Page`, `ocr_page`) odpovídají snímkům.
+
+use anyhow::{Context, Result, bail};
+use eframe::egui::{self, Color32, Pos2, Rect, Stroke, Vec2};
+use quick_xml::XmlVersion;
+use quick_xml::events::{BytesStart, Event};
+use std::path::{Path, PathBuf};
+
+/// Přípony hledaných souborů v pořadí priority.
+const EXTENSIONS: [&str; 4] = ["alto.xml", "xml", "hocr", "html"];
+
+/// Slovo textové vrstvy; obdélník v pixelech neotočeného obrázku.
+#[derive(Debug, Clone)]
+pub struct TextWord {
+    pub page: u32,
+    pub rect: Rect,
+    pub text: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct TextLayer {
+    pub source: PathBuf,
+    pub words: Vec<TextWord>,
+}
+
+/// Soubor s textovou vrstvou vedle `image`, pokud nějaký existuje.
+pub fn find(image: &Path) -> Option<PathBuf> {
+    let stem = image.file_stem()?.to_string_lossy();
+    EXTENSIONS
+        .iter()
+        .map(|ext| image.with_file_name(format!("{stem}.{ext}")))
+        .find(|p| p.is_file())
+}
+
+impl TextLayer {
+    /// Načte vrstvu vedle `image` (`None` = žádná není). `dpi` obrázku je potřeba jen pro
+    /// ALTO v jiných jednotkách než pixelech.
+    pub fn load(image: &Path, dpi: Option<(f32, f32)>) -> Result<Option<Self>> {
+        let Some(source) = find(image) else {
+            return Ok(None);
+        };
+        let text = std::fs::read_to_string(&source)
+            .with_context(|| format!("read {}", source.display()))?;
+        let words = if text.contains("ocrx_word") || text.contains("ocr_page") {
+            parse_hocr(&text)
+        } else {
+            parse_alto(&text, dpi)
+        }
+        .with_context(|| format!("parse {}", source.display()))?;
+        Ok(Some(Self { source, words }))
+    }
+}
+
+/// Atributy elementu (jméno bez jmenného prostoru → hodnota).
+fn attributes(e: &BytesStart) -> Vec<(String, String)> {
+    e.html_attributes()
+        .flatten()
+        .map(|a| {
+            let key = String::from_utf8_lossy(a.key.local_name().into_inner()).into_owned();
+            let value = a
+                .normalized_value(XmlVersion::Implicit1_0)
+                .map(|v| v.into_owned())
+                .unwrap_or_default();
+            (key, value)
+        })
+        .collect()
+}
+
+fn attr<'a>(attrs: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    attrs
+        .iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, v)| v.as_str())
+}
+
+/// ALTO: slova jsou `String` s `HPOS`, `VPOS`, `WIDTH`, `HEIGHT` a `CONTENT`.
+fn parse_alto(xml: &str, dpi: Option<(f32, f32)>) -> Result<Vec<TextWord>> {
+    let mut reader = quick_xml::Reader::from_str(xml);
+    let mut words = Vec::new();
+    let mut page = None::<u32>;
+    // pixely na jednotku souřadnic (MeasurementUnit)
+    let mut scale = (1.0, 1.0);
+    let mut unit = String::new();
+    loop {
+        let (e, start) = match reader.read_event()? {
+            Event::Start(e) => (e, true),
+            Event::Empty(e) => (e, false),
+            Event::Text(t) if unit == "-" => {
+                let name = t.decode()?.trim().to_owned();
+                scale = match (name.as_str(), dpi) {
+                    ("pixel", _) => (1.0, 1.0),
+                    ("mm10", Some((x, y))) => (x / 254.0, y / 254.0),
+                    ("inch1200", Some((x, y))) => (x / 1200.0, y / 1200.0),
+                    (other, _) => {
+                        bail!("ALTO in {other:?} units needs the image DPI in metadata")
+                    }
+                };
+                unit.clear();
+                continue;
+            }
+            Event::Eof => break,
+            _ => continue,
+        };
+        match e.local_name().into_inner() {
+            b"MeasurementUnit" if start => unit = "-".to_owned(),
+            b"Page" => page = Some(page.map_or(0, |p| p + 1)),
+            b"String" => {
+                let attrs = attributes(&e);
+                let num = |k| attr(&attrs, k).and_then(|v| v.parse::<f32>().ok());
+                let (Some(x), Some(y), Some(w), Some(h)) =
+                    (num("HPOS"), num("VPOS"), num("WIDTH"), num("HEIGHT"))
+                else {
+                    continue;
+                };
+                let text = attr(&attrs, "CONTENT").unwrap_or_default().trim();
+                if text.is_empty() {
+                    continue;
+                }
+                words.push(TextWord {
+                    page: page.unwrap_or(0),
+                    rect: Rect::from_min_size(
+                        Pos2::new(x * scale.0, y * scale.1),
+                        Vec2::new(w * scale.0, h * scale.1),
+                    ),
+                    text: text.to_owned(),
+                });
+            }
+            _ => {}
+        }
+    }
+    Ok(words)
+}
+
+/// hOCR: slova jsou elementy s třídou `ocrx_word` (`ocr_word`) a `title="bbox x0 y0 x1 y1; …"`.
+fn parse_hocr(html: &str) -> Result<Vec<TextWord>> {
+    let mut reader = quick_xml::Reader::from_str(html);
+    // HTML nemusí mít párové značky (<meta>, <br>)
+    reader.config_mut().check_end_names = false;
+    let mut words = Vec::new();
+    let mut page = None::<u32>;
+    // rozpracované slovo a hloubka jeho elementu
+    let mut current: Option<(TextWord, usize)> = None;
+    let mut depth = 0usize;
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) => {
+                depth += 1;
+                let attrs = attributes(&e);
+                let class = attr(&attrs, "class").unwrap_or_default();
+                let classes = || class.split_whitespace();
+                if classes().any(|c| c == "ocr_page") {
+                    page = Some(page.map_or(0, |p| p + 1));
+                }
+                if current.is_none()
+                    && classes().any(|c| c == "ocrx_word" || c == "ocr_word")
+                    && let Some(rect) = attr(&attrs, "title").and_then(bbox)
+                {
+                    let word = TextWord {
+                        page: page.unwrap_or(0),
+                        rect,
+                        text: String::new(),
+                    };
+                    current = Some((word, depth));
+                }
+            }
+            Event::End(_) => {
+                if let Some((_, d)) = &current
+                    && *d == depth
+                {
+                    let (mut word, _) = current.take().unwrap();
+                    word.text = word.text.trim().to_owned();
+                    if !word.text.is_empty() {
+                        words.push(word);
+                    }
+                }
+                depth = depth.saturating_sub(1);
+            }
+            Event::Text(t) => {
+                if let Some((word, _)) = &mut current {
+                    word.text.push_str(&t.decode()?);
+                }
+            }
+            Event::GeneralRef(r) => {
+                if let Some((word, _)) = &mut current {
+                    let name = r.decode()?;
+                    match r.resolve_char_ref()? {
+                        Some(c) => word.text.push(c),
+                        // hOCR je HTML, takže i &nbsp;, &eacute; apod.
+                        None => word.text.push_str(
+                            quick_xml::escape::resolve_html5_entity(&name).unwrap_or(" "),
+                        ),
+                    }
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(words)
+}
+
+/// `bbox x0 y0 x1 y1` z atributu `title` hOCR.
+fn bbox(title: &str) -> Option<Rect> {
+    let props = title.split(';').map(str::trim);
+    let values = props.clone().find_map(|p| p.strip_prefix("bbox "))?;
+    let v: Vec<f32> = values
+        .split_whitespace()
+        .filter_map(|s| s.parse().ok())
+        .collect();
+    let [x0, y0, x1, y1] = v[..] else {
+        return None;
+    };
+    Some(Rect::from_min_max(Pos2::new(x0, y0), Pos2::new(x1, y1)))
+}
+
+/// Zobrazení textové vrstvy a hledání v ní.
+#[derive(Default)]
+pub struct TextPanel {
+    pub layer: Option<TextLayer>,
+    pub show_boxes: bool,
+    pub find_open: bool,
+    query: String,
+    /// Indexy nalezených slov na aktuální stránce a pro jaký dotaz a stránku platí.
+    hits: Vec<usize>,
+    hits_for: Option<(String, u32)>,
+    current: usize,
+    /// Je potřeba ukázat aktuální výsledek (nový dotaz, další/předchozí).
+    focus: bool,
+    /// Po otevření hledání dostane pole dotazu fokus.
+    grab_focus: bool,
+    error: Option<String>,
+}
+
+impl TextPanel {
+    /// Načte vrstvu k nově otevřenému obrázku (chyba se ukáže v okně hledání).
+    pub fn load(&mut self, image: &Path, dpi: Option<(f32, f32)>) {
+        self.hits_for = None;
+        self.hits.clear();
+        match TextLayer::load(image, dpi) {
+            Ok(layer) => {
+                self.layer = layer;
+                self.error = None;
+            }
+            Err(e) => {
+                tracing::warn!("text layer: {e:#}");
+                self.layer = None;
+                self.error = Some(format!("{e:#}"));
+            }
+        }
+    }
+
+    /// Otevře hledání (Ctrl+F) a zapne obdélníky slov.
+    pub fn open_find(&mut self) {
+        self.find_open = true;
+        self.show_boxes = true;
+        self.grab_focus = true;
+    }
+
+    /// Přepočítá výsledky pro nový dotaz nebo stránku; vrací, zda se změnily.
+    fn update_hits(&mut self, page: u32) -> bool {
+        let key = (self.query.trim().to_lowercase(), page);
+        if self.hits_for.as_ref() == Some(&key) {
+            return false;
+        }
+        self.hits = match &self.layer {
+            Some(layer) if !key.0.is_empty() => layer
+                .words
+                .iter()
+                .enumerate()
+                .filter(|(_, w)| w.page == page && w.text.to_lowercase().contains(&key.0))
+                .map(|(i, _)| i)
+                .collect(),
+            _ => Vec::new(),
+        };
+        self.current = 0;
+        self.focus = !self.hits.is_empty();
+        self.hits_for = Some(key);
+        true
+    }
+
+    /// Okno hledání; vrací výřez obrázku, na který má pohled přejet.
+    pub fn find_window(&mut self, ctx: &egui::Context, page: u32) -> Option<Rect> {
+        if !self.find_open {
+            return None;
+        }
+        let mut open = true;
+        egui::Window::new("Find text")
+            .resizable(false)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                let Some(layer) = &self.layer else {
+                    match &self.error {
+                        Some(e) => ui.colored_label(ui.visuals().error_fg_color, e),
+                        None => ui.weak("No ALTO or hOCR file next to this image."),
+                    };
+                    return;
+                };
+                let name = layer.source.file_name().unwrap_or_default();
+                ui.weak(format!(
+                    "{} ({} words)",
+                    name.to_string_lossy(),
+                    layer.words.len()
+                ));
+                let resp = ui.add(
+                    egui::TextEdit::singleline(&mut self.query)
+                        .hint_text("Search text")
+                        .desired_width(220.0),
+                );
+                if std::mem::take(&mut self.grab_focus) {
+                    resp.request_focus();
+                }
+                let enter = resp.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                // Enter u nového dotazu ukáže první výsledek, jinak přejde na další
+                let fresh = self.update_hits(page);
+                ui.horizontal(|ui| {
+                    let n = self.hits.len();
+                    let prev = ui.add_enabled(n > 1, egui::Button::new("◀ Previous"));
+                    let next = ui.add_enabled(n > 1, egui::Button::new("Next ▶"));
+                    if prev.clicked() {
+                        self.current = (self.current + n - 1) % n;
+                        self.focus = true;
+                    }
+                    if (next.clicked() || enter && !fresh) && n > 0 {
+                        self.current = (self.current + 1) % n;
+                        self.focus = true;
+                    }
+                    if enter {
+                        resp.request_focus();
+                    }
+                    if n > 0 {
+                        ui.label(format!("{} of {n}", self.current + 1));
+                    } else if !self.query.trim().is_empty() {
+                        ui.label("No matches on this page");
+                    }
+                });
+                ui.checkbox(&mut self.show_boxes, "Show text boxes");
+            });
+        self.find_open = open;
+        let word = self.hits.get(self.current)?;
+        if !std::mem::take(&mut self.focus) {
+            return None;
+        }
+        let rect = self.layer.as_ref()?.words[*word].rect;
+        // slovo s okolím (část řádku nad a pod ním)
+        Some(rect.expand2(Vec2::new(rect.width() * 2.0, rect.height() * 4.0)))
+    }
+
+    /// Obdélníky slov aktuální stránky (nalezená zvýrazněná); `to_screen` převádí souřadnice
+    /// obrázku na obrazovku.
+    pub fn paint(&self, painter: &egui::Painter, page: u32, to_screen: impl Fn(Pos2) -> Pos2) {
+        let Some(layer) = self.layer.as_ref().filter(|_| self.show_boxes) else {
+            return;
+        };
+        let screen = |r: Rect| Rect::from_two_pos(to_screen(r.min), to_screen(r.max));
+        let stroke = Stroke::new(1.0, Color32::from_rgba_unmultiplied(60, 140, 255, 160));
+        for w in layer.words.iter().filter(|w| w.page == page) {
+            painter.rect_stroke(screen(w.rect), 0.0, stroke, egui::StrokeKind::Outside);
+        }
+        let hits = self.hits_for.as_ref().is_some_and(|(_, p)| *p == page);
+        for (n, &i) in self.hits.iter().enumerate().filter(|_| hits) {
+            let alpha = if n == self.current { 140 } else { 70 };
+            let fill = Color32::from_rgba_unmultiplied(255, 220, 0, alpha);
+            painter.rect_filled(screen(layer.words[i].rect), 0.0, fill);
+        }
+    }
+}
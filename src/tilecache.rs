@@ -0,0 +1,128 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+/// LRU cache rozbalených dlaždic (po zpětné RCT) s limitem velikosti v bajtech. Klíčem je
+/// soubor (cesta a čas změny, takže přepsaný soubor se nepoplete), snímek a pořadí
+/// dlaždice. Klon sdílí tutéž cache, takže ji může plnit dekodér na pozadí a číst výřezy
+/// prohlížeč nebo server.
+#[derive(Clone, Default)]
+pub struct TileCache(Arc<Mutex<Inner>>);
+
+#[derive(Default)]
+struct Inner {
+    budget: usize,
+    used: usize,
+    /// Pořadí posledního použití → klíč; nejstarší první.
+    lru: BTreeMap<u64, Key>,
+    entries: HashMap<Key, Slot>,
+    tick: u64,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct Key {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    frame: u32,
+    tile: u32,
+}
+
+struct Slot {
+    tick: u64,
+    data: Arc<Vec<u8>>,
+}
+
+impl TileCache {
+    pub fn new(budget: usize) -> Self {
+        Self(Arc::new(Mutex::new(Inner {
+            budget,
+            ..Inner::default()
+        })))
+    }
+
+    pub fn set_budget(&self, budget: usize) {
+        let mut inner = self.0.lock().unwrap();
+        inner.budget = budget;
+        inner.evict();
+    }
+
+    /// Obsazená paměť (bajty dlaždic).
+    pub fn used(&self) -> usize {
+        self.0.lock().unwrap().used
+    }
+
+    /// Dlaždice souboru `path`; čas změny se zjistí jednou pro všechna další volání.
+    pub fn file(&self, path: &Path) -> FileTiles<'_> {
+        FileTiles {
+            cache: self,
+            path: path.to_path_buf(),
+            modified: std::fs::metadata(path).and_then(|m| m.modified()).ok(),
+        }
+    }
+}
+
+/// Pohled na dlaždice jednoho souboru v [`TileCache`].
+pub struct FileTiles<'a> {
+    cache: &'a TileCache,
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl FileTiles<'_> {
+    fn key(&self, frame: u32, tile: u32) -> Key {
+        Key {
+            path: self.path.clone(),
+            modified: self.modified,
+            frame,
+            tile,
+        }
+    }
+
+    /// Dlaždice `tile` (pořadí po řádcích) snímku `frame`, pokud je v cache.
+    pub fn get(&self, frame: u32, tile: u32) -> Option<Arc<Vec<u8>>> {
+        let key = self.key(frame, tile);
+        let mut inner = self.cache.0.lock().unwrap();
+        let tick = inner.next_tick();
+        let slot = inner.entries.get_mut(&key)?;
+        let old = std::mem::replace(&mut slot.tick, tick);
+        let data = slot.data.clone();
+        inner.lru.remove(&old);
+        inner.lru.insert(tick, key);
+        Some(data)
+    }
+
+    pub fn insert(&self, frame: u32, tile: u32, data: Arc<Vec<u8>>) {
+        let key = self.key(frame, tile);
+        let mut inner = self.cache.0.lock().unwrap();
+        if data.len() > inner.budget {
+            return;
+        }
+        let tick = inner.next_tick();
+        inner.used += data.len();
+        if let Some(old) = inner.entries.insert(key.clone(), Slot { tick, data }) {
+            inner.used -= old.data.len();
+            inner.lru.remove(&old.tick);
+        }
+        inner.lru.insert(tick, key);
+        inner.evict();
+    }
+}
+
+impl Inner {
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    fn evict(&mut self) {
+        while self.used > self.budget {
+            let Some((_, key)) = self.lru.pop_first() else {
+                break;
+            };
+            if let Some(slot) = self.entries.remove(&key) {
+                self.used -= slot.data.len();
+            }
+        }
+    }
+}
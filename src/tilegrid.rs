@@ -0,0 +1,258 @@
+//! Mřížka dlaždic přes obrázek: hranice dlaždic, volitelně jejich indexy a pod ukazatelem
+//! velikost dlaždice v souboru a kompresní poměr (ladění nastavení kodéru). Teplotní mapa
+//! obarví dlaždice podle poměru komprimované a původní velikosti, takže je vidět, které
+//! oblasti se komprimují špatně.
+
+use eframe::egui::{self, Color32, Pos2, Rect, Stroke};
+use std::path::{Path, PathBuf};
+
+use crate::cti::{CTIHeader, CTITileReader, TileEntry};
+use crate::dryrun::human;
+
+#[derive(Default)]
+pub struct TileGrid {
+    pub enabled: bool,
+    /// Vypsat do dlaždic jejich souřadnice.
+    pub indices: bool,
+    /// Obarvit dlaždice podle kompresního poměru (nezávisle na mřížce).
+    pub heatmap: bool,
+    /// Soubor a snímek, ke kterým patří `index`.
+    key: Option<(PathBuf, u32)>,
+    index: Option<(CTIHeader, Vec<TileEntry>)>,
+}
+
+/// Pod tuto velikost dlaždice na obrazovce (px) se indexy nevypisují.
+const MIN_LABEL_SIZE: f32 = 48.0;
+
+impl TileGrid {
+    /// Načte index dlaždic souboru (jen hlavička a index, data dlaždic se nečtou).
+    pub fn ensure(&mut self, path: &Path, frame: u32) {
+        if self
+            .key
+            .as_ref()
+            .is_some_and(|(p, f)| p == path && *f == frame)
+        {
+            return;
+        }
+        self.key = Some((path.to_path_buf(), frame));
+        self.index = match load(path, frame) {
+            Ok(index) => Some(index),
+            Err(e) => {
+                tracing::warn!("tile index of {}: {e:#}", path.display());
+                None
+            }
+        };
+    }
+
+    /// Zapomene načtený index (soubor se změnil na disku).
+    pub fn clear(&mut self) {
+        self.key = None;
+        self.index = None;
+    }
+
+    /// Je co kreslit (mřížka nebo teplotní mapa).
+    pub fn visible(&self) -> bool {
+        self.enabled || self.heatmap
+    }
+
+    pub fn paint(
+        &self,
+        ui: &egui::Ui,
+        painter: &egui::Painter,
+        hover: Option<Pos2>,
+        to_screen: impl Fn(Pos2) -> Pos2,
+        to_image: impl Fn(Pos2) -> Pos2,
+    ) {
+        let Some((hdr, entries)) = &self.index else {
+            return;
+        };
+        let ts = hdr.tile_size as f32;
+        let (w, h) = (hdr.width as f32, hdr.height as f32);
+        let cell = |tx: u32, ty: u32| {
+            let min = Pos2::new(tx as f32 * ts, ty as f32 * ts);
+            let max = Pos2::new((min.x + ts).min(w), (min.y + ts).min(h));
+            Rect::from_two_pos(to_screen(min), to_screen(max))
+        };
+
+        if self.heatmap {
+            for (i, e) in entries.iter().enumerate() {
+                let (tx, ty) = (i as u32 % hdr.tiles_x, i as u32 / hdr.tiles_x);
+                painter.rect_filled(cell(tx, ty), 0.0, heat(fraction(e)).gamma_multiply(0.55));
+            }
+            heat_legend(painter);
+        }
+        if self.enabled {
+            self.paint_grid(painter, hdr, &cell, &to_screen);
+        }
+        self.hover_info(ui, painter, hdr, entries, hover, &cell, &to_image);
+    }
+
+    /// Hranice dlaždic a (při dostatečném zvětšení) jejich souřadnice.
+    fn paint_grid(
+        &self,
+        painter: &egui::Painter,
+        hdr: &CTIHeader,
+        cell: &impl Fn(u32, u32) -> Rect,
+        to_screen: &impl Fn(Pos2) -> Pos2,
+    ) {
+        let ts = hdr.tile_size as f32;
+        let (w, h) = (hdr.width as f32, hdr.height as f32);
+        let stroke = Stroke::new(1.0, Color32::from_rgba_unmultiplied(0, 255, 255, 160));
+        for tx in 1..hdr.tiles_x {
+            let x = tx as f32 * ts;
+            painter.line_segment(
+                [to_screen(Pos2::new(x, 0.0)), to_screen(Pos2::new(x, h))],
+                stroke,
+            );
+        }
+        for ty in 1..hdr.tiles_y {
+            let y = ty as f32 * ts;
+            painter.line_segment(
+                [to_screen(Pos2::new(0.0, y)), to_screen(Pos2::new(w, y))],
+                stroke,
+            );
+        }
+
+        if self.indices && cell(0, 0).size().min_elem() >= MIN_LABEL_SIZE {
+            let font = egui::FontId::monospace(11.0);
+            let clip = painter.clip_rect();
+            for ty in 0..hdr.tiles_y {
+                for tx in 0..hdr.tiles_x {
+                    let rect = cell(tx, ty);
+                    if !clip.intersects(rect) {
+                        continue;
+                    }
+                    let galley =
+                        painter.layout_no_wrap(format!("{tx},{ty}"), font.clone(), Color32::WHITE);
+                    let pos = rect.left_top() + egui::vec2(4.0, 4.0);
+                    let bg = Rect::from_min_size(pos, galley.size()).expand(2.0);
+                    painter.rect_filled(bg, 2.0, Color32::from_black_alpha(150));
+                    painter.galley(pos, galley, Color32::WHITE);
+                }
+            }
+        }
+    }
+
+    /// Velikost a poměr dlaždice pod ukazatelem.
+    #[allow(clippy::too_many_arguments)]
+    fn hover_info(
+        &self,
+        ui: &egui::Ui,
+        painter: &egui::Painter,
+        hdr: &CTIHeader,
+        entries: &[TileEntry],
+        hover: Option<Pos2>,
+        cell: &impl Fn(u32, u32) -> Rect,
+        to_image: &impl Fn(Pos2) -> Pos2,
+    ) {
+        let Some(pos) = hover else {
+            return;
+        };
+        let ts = hdr.tile_size as f32;
+        let p = to_image(pos);
+        if p.x < 0.0 || p.y < 0.0 || p.x >= hdr.width as f32 || p.y >= hdr.height as f32 {
+            return;
+        }
+        let (tx, ty) = ((p.x / ts) as u32, (p.y / ts) as u32);
+        let Some(e) = entries.get((ty * hdr.tiles_x + tx) as usize) else {
+            return;
+        };
+        painter.rect_stroke(
+            cell(tx, ty),
+            0.0,
+            Stroke::new(2.0, Color32::from_rgb(0, 255, 255)),
+            egui::StrokeKind::Inside,
+        );
+        let ratio = if e.compressed_size == 0 {
+            0.0
+        } else {
+            f64::from(e.original_size) / f64::from(e.compressed_size)
+        };
+        let text = format!(
+            "Tile {tx},{ty}\nCompressed: {} ({:.0} %)\nOriginal: {}\nRatio: {ratio:.2}:1\nOffset: {}",
+            human(e.compressed_size.into()),
+            fraction(e) * 100.0,
+            human(e.original_size.into()),
+            e.offset
+        );
+        egui::Tooltip::always_open(
+            ui.ctx().clone(),
+            ui.layer_id(),
+            egui::Id::new("tile_grid"),
+            egui::PopupAnchor::Pointer,
+        )
+        .show(|ui| ui.monospace(text));
+    }
+}
+
+/// Komprimovaná / původní velikost (0 = komprese výborná, 1 a víc = žádná).
+fn fraction(e: &TileEntry) -> f32 {
+    if e.original_size == 0 {
+        0.0
+    } else {
+        e.compressed_size as f32 / e.original_size as f32
+    }
+}
+
+/// Barva teplotní mapy: zelená (dobře komprimováno) → žlutá → červená (nekomprimovatelné).
+fn heat(fraction: f32) -> Color32 {
+    let t = fraction.clamp(0.0, 1.0);
+    if t < 0.5 {
+        Color32::from_rgb((t * 2.0 * 255.0) as u8, 200, 40)
+    } else {
+        Color32::from_rgb(255, ((1.0 - t) * 2.0 * 200.0) as u8, 40)
+    }
+}
+
+/// Stupnice teplotní mapy vlevo dole.
+fn heat_legend(painter: &egui::Painter) {
+    const STEPS: usize = 20;
+    let clip = painter.clip_rect();
+    let bar = Rect::from_min_size(
+        clip.left_bottom() + egui::vec2(12.0, -40.0),
+        egui::vec2(200.0, 12.0),
+    );
+    let bg = bar
+        .expand2(egui::vec2(8.0, 22.0))
+        .translate(egui::vec2(0.0, -8.0));
+    painter.rect_filled(bg, 4.0, Color32::from_black_alpha(180));
+    for i in 0..STEPS {
+        let x0 = bar.left() + bar.width() * i as f32 / STEPS as f32;
+        let x1 = bar.left() + bar.width() * (i + 1) as f32 / STEPS as f32;
+        let r = Rect::from_x_y_ranges(x0..=x1, bar.y_range());
+        painter.rect_filled(r, 0.0, heat((i as f32 + 0.5) / STEPS as f32));
+    }
+    let font = egui::FontId::proportional(12.0);
+    let above = bar.left_top() - egui::vec2(0.0, 16.0);
+    painter.text(
+        above,
+        egui::Align2::LEFT_TOP,
+        "Compressed / original size",
+        font.clone(),
+        Color32::WHITE,
+    );
+    for (x, text, align) in [
+        (bar.left(), "0 %", egui::Align2::LEFT_TOP),
+        (bar.right(), "100 %", egui::Align2::RIGHT_TOP),
+    ] {
+        painter.text(
+            egui::pos2(x, bar.bottom() + 2.0),
+            align,
+            text,
+            font.clone(),
+            Color32::WHITE,
+        );
+    }
+}
+
+fn load(path: &Path, frame: u32) -> anyhow::Result<(CTIHeader, Vec<TileEntry>)> {
+    let reader = CTITileReader::open(path)?;
+    let hdr = *reader.header();
+    let mut entries = Vec::with_capacity(hdr.tiles_per_frame());
+    for ty in 0..hdr.tiles_y {
+        for tx in 0..hdr.tiles_x {
+            entries.push(reader.tile_entry(frame, tx, ty)?);
+        }
+    }
+    Ok((hdr, entries))
+}
@@ -0,0 +1,38 @@
+//! Krátká oznámení v rohu okna, která po chvíli sama zmizí.
+
+use eframe::egui;
+
+/// Jak dlouho oznámení zůstane vidět (s).
+const SHOW_FOR: f64 = 3.0;
+
+#[derive(Default)]
+pub struct Toasts {
+    items: Vec<(String, f64)>,
+}
+
+impl Toasts {
+    pub fn push(&mut self, ctx: &egui::Context, text: impl Into<String>) {
+        let until = ctx.input(|i| i.time) + SHOW_FOR;
+        self.items.push((text.into(), until));
+        ctx.request_repaint();
+    }
+
+    /// Vykreslí platná oznámení vpravo dole nad obsahem okna.
+    pub fn show(&mut self, ctx: &egui::Context) {
+        let now = ctx.input(|i| i.time);
+        self.items.retain(|(_, until)| *until > now);
+        let Some(next) = self.items.iter().map(|(_, until)| *until).reduce(f64::min) else {
+            return;
+        };
+        ctx.request_repaint_after_secs((next - now) as f32);
+        egui::Area::new(egui::Id::new("toasts"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, [-12.0, -12.0])
+            .order(egui::Order::Foreground)
+            .interactable(false)
+            .show(ctx, |ui| {
+                for (text, _) in &self.items {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| ui.label(text));
+                }
+            });
+    }
+}
@@ -0,0 +1,245 @@
+use anyhow::{Result, anyhow, bail};
+use eframe::egui;
+use image::ImageFormat;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::a11y;
+use crate::export;
+use crate::tilecache::TileCache;
+use crate::workdirs::Area;
+
+/// Externí nástroj spouštěný nad aktuálním souborem ("Open with…").
+///
+/// Příkaz podporuje zástupné symboly `{file}`, `{dir}`, `{name}`, `{stem}`,
+/// `{export}` (cesta k dočasně exportovanému PNG/TIFF) a `{region}` (totéž jen
+/// pro aktuální výběr).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExternalTool {
+    pub name: String,
+    pub command: String,
+    pub export: ExportKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExportKind {
+    None,
+    Png,
+    Tiff,
+}
+
+impl ExportKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            ExportKind::None => "none",
+            ExportKind::Png => "PNG",
+            ExportKind::Tiff => "TIFF",
+        }
+    }
+}
+
+pub fn default_tools() -> Vec<ExternalTool> {
+    vec![ExternalTool {
+        name: "Open in GIMP (TIFF)".into(),
+        command: "gimp {export}".into(),
+        export: ExportKind::Tiff,
+    }]
+}
+
+/// Aktuální výběr pro `{region}`: snímek a výřez `(x, y, w, h)`.
+pub type Selection = (u32, (u32, u32, u32, u32));
+
+/// Dočasné exporty dosazované do příkazu.
+#[derive(Default)]
+struct Exports {
+    file: Option<PathBuf>,
+    region: Option<PathBuf>,
+}
+
+impl ExternalTool {
+    /// Spustí nástroj nad `path` (`selection` pro `{region}`, dlaždice výběru se berou
+    /// z `tiles`); na dokončení čeká vlastní vlákno, nenulový návratový kód zapíše do logu
+    /// jako chybu. Exportuje, proto patří do úlohy na pozadí.
+    pub fn launch(
+        &self,
+        path: &Path,
+        selection: Option<Selection>,
+        tiles: &TileCache,
+    ) -> Result<String> {
+        let mut exports = Exports::default();
+        if self.export != ExportKind::None && self.command.contains("{export}") {
+            exports.file = Some(export_temp(path, self.export)?);
+        }
+        if self.command.contains("{region}") {
+            let Some((frame, region)) = selection else {
+                bail!("{{region}} used but nothing is selected");
+            };
+            if self.export == ExportKind::None {
+                bail!("{{region}} used but the tool has no export format set");
+            }
+            exports.region = Some(export_region_temp(path, self.export, frame, region, tiles)?);
+        }
+        let args = expand(&self.command, path, &exports)?;
+        let (prog, rest) = args.split_first().ok_or_else(|| anyhow!("empty command"))?;
+        let mut child = Command::new(prog)
+            .args(rest)
+            .spawn()
+            .map_err(|e| anyhow!("failed to start {prog:?}: {e}"))?;
+        // nástroj může běžet dlouho, úloha na něj nečeká; bez `wait` by po něm na Unixu
+        // zůstal zombie proces až do konce prohlížeče
+        let started = format!("{}: started {prog}", self.name);
+        let (name, prog) = (self.name.clone(), prog.clone());
+        std::thread::spawn(move || match child.wait() {
+            Ok(status) if status.success() => tracing::info!("{name}: {prog} finished"),
+            Ok(status) => tracing::error!("{name}: {prog} failed ({status})"),
+            Err(e) => tracing::error!("{name}: waiting for {prog} failed: {e}"),
+        });
+        Ok(started)
+    }
+}
+
+/// Cesta v dočasné složce pro export `path` s příponou `suffix` (bez formátu `None`).
+fn temp_path(path: &Path, suffix: &str, kind: ExportKind) -> Result<PathBuf> {
+    let ext = match kind {
+        ExportKind::Png => "png",
+        _ => "tif",
+    };
+    let stem = path.file_stem().ok_or_else(|| anyhow!("no file name"))?;
+    let name = format!("{}{suffix}.{ext}", stem.to_string_lossy());
+    Ok(Area::Temp.dir()?.join(name))
+}
+
+fn export_temp(path: &Path, kind: ExportKind) -> Result<PathBuf> {
+    let fmt = match kind {
+        ExportKind::Png => ImageFormat::Png,
+        _ => ImageFormat::Tiff,
+    };
+    let dst = temp_path(path, "", kind)?;
+    export::export_to(path, &dst, fmt)?;
+    Ok(dst)
+}
+
+fn export_region_temp(
+    path: &Path,
+    kind: ExportKind,
+    frame: u32,
+    region: (u32, u32, u32, u32),
+    tiles: &TileCache,
+) -> Result<PathBuf> {
+    // formát určí přípona
+    let dst = temp_path(path, "_region", kind)?;
+    export::export_region(path, frame, region, &dst, tiles)?;
+    Ok(dst)
+}
+
+/// Rozdělí příkaz na argumenty (uvozovky drží mezery) a dosadí zástupné symboly.
+/// Dosazuje se až po rozdělení, takže cesty s mezerami zůstanou jedním argumentem.
+fn expand(command: &str, path: &Path, exports: &Exports) -> Result<Vec<String>> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let subst = |arg: &str| -> Result<String> {
+        let mut out = arg
+            .replace("{file}", &path.to_string_lossy())
+            .replace("{dir}", &dir.to_string_lossy())
+            .replace(
+                "{name}",
+                &path.file_name().unwrap_or_default().to_string_lossy(),
+            )
+            .replace(
+                "{stem}",
+                &path.file_stem().unwrap_or_default().to_string_lossy(),
+            );
+        if out.contains("{export}") {
+            let Some(e) = &exports.file else {
+                bail!("{{export}} used but the tool has no export format set");
+            };
+            out = out.replace("{export}", &e.to_string_lossy());
+        }
+        if let Some(r) = &exports.region {
+            out = out.replace("{region}", &r.to_string_lossy());
+        }
+        Ok(out)
+    };
+    split_args(command)?.iter().map(|a| subst(a)).collect()
+}
+
+/// Rozdělí příkaz na argumenty; uvozovky drží mezery.
+pub fn split_args(s: &str) -> Result<Vec<String>> {
+    let mut args = Vec::new();
+    let mut cur = String::new();
+    let mut quote: Option<char> = None;
+    let mut in_arg = false;
+    for c in s.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => cur.push(c),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                in_arg = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut cur));
+                    in_arg = false;
+                }
+            }
+            (None, c) => {
+                cur.push(c);
+                in_arg = true;
+            }
+        }
+    }
+    if quote.is_some() {
+        bail!("unterminated quote in command");
+    }
+    if in_arg {
+        args.push(cur);
+    }
+    Ok(args)
+}
+
+/// Editor seznamu nástrojů.
+pub fn tools_window(ctx: &egui::Context, open: &mut bool, tools: &mut Vec<ExternalTool>) {
+    a11y::modal_dialog(ctx, "External tools", open, |ui| {
+        ui.label("Placeholders: {file} {dir} {name} {stem} {export} {region}");
+        ui.separator();
+        let mut remove = None;
+        for (i, t) in tools.iter_mut().enumerate() {
+            ui.push_id(i, |ui| {
+                ui.horizontal(|ui| {
+                    let name = ui.label("Name");
+                    ui.add(egui::TextEdit::singleline(&mut t.name).desired_width(160.0))
+                        .labelled_by(name.id);
+                    egui::ComboBox::from_id_salt("export")
+                        .selected_text(t.export.as_str())
+                        .show_ui(ui, |ui| {
+                            for k in [ExportKind::None, ExportKind::Png, ExportKind::Tiff] {
+                                ui.selectable_value(&mut t.export, k, k.as_str());
+                            }
+                        });
+                    if a11y::icon_button(ui, "🗑", "Remove tool").clicked() {
+                        remove = Some(i);
+                    }
+                });
+                let cmd = ui.label("Command");
+                ui.add(
+                    egui::TextEdit::singleline(&mut t.command)
+                        .font(egui::TextStyle::Monospace)
+                        .desired_width(f32::INFINITY),
+                )
+                .labelled_by(cmd.id);
+                ui.separator();
+            });
+        }
+        if let Some(i) = remove {
+            tools.remove(i);
+        }
+        if ui.button("Add tool").clicked() {
+            tools.push(ExternalTool {
+                name: "New tool".into(),
+                command: "program {file}".into(),
+                export: ExportKind::None,
+            });
+        }
+    });
+}
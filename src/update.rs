@@ -0,0 +1,384 @@
+//! Kontrola aktualizací z podepsaného kanálu (a instalace na Windows).
+//!
+//! Kanál je URL nebo složka (i síťová sdílená, pro stanice bez internetu) s:
+//! - `latest.json` + `latest.json.minisig`: verze, poznámky a soubory pro platformy
+//!   (`{"version": "0.2.0", "notes": "…", "assets": {"windows-x86_64":
+//!   {"file": "cti-view-0.2.0.exe", "sha256": "…"}}}`),
+//! - soubory verzí + jejich `.minisig`.
+//!
+//! Všechno se ověřuje podpisem [minisign](https://jedisct1.github.io/minisign/) proti
+//! veřejnému klíči ze sestavení (`CTI_VIEW_UPDATE_KEY`) nebo ze souboru
+//! `cti-view-update.pub` vedle programu; bez klíče se aktualizace nehledají.
+
+use anyhow::{Context, Result, anyhow, bail};
+use eframe::egui;
+use minisign_verify::{PublicKey, Signature};
+use semver::Version;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+
+/// Kanál zadaný při sestavení (výchozí hodnota v nastavení).
+pub const DEFAULT_CHANNEL: &str = match option_env!("CTI_VIEW_UPDATE_CHANNEL") {
+    Some(c) => c,
+    None => "",
+};
+
+/// Veřejný klíč vydání (base64 z `minisign.pub`) zadaný při sestavení.
+const BUILD_KEY: Option<&str> = option_env!("CTI_VIEW_UPDATE_KEY");
+
+/// Klíč vedle programu, pro archivy, které si program sestavují a podepisují samy.
+const KEY_FILE: &str = "cti-view-update.pub";
+
+const MANIFEST: &str = "latest.json";
+
+/// Větší soubor v kanálu nebude program.
+const MAX_DOWNLOAD: u64 = 512 << 20;
+
+#[derive(Debug, Deserialize)]
+struct Manifest {
+    version: String,
+    #[serde(default)]
+    notes: String,
+    #[serde(default)]
+    assets: BTreeMap<String, Asset>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Asset {
+    /// Jméno souboru v kanálu.
+    pub file: String,
+    pub sha256: String,
+}
+
+/// Novější vydání nalezené v kanálu.
+#[derive(Debug, Clone)]
+pub struct Release {
+    pub version: Version,
+    pub notes: String,
+    /// Soubor pro tuto platformu (nemusí být).
+    pub asset: Option<Asset>,
+}
+
+/// Verze tohoto programu.
+pub fn current_version() -> Version {
+    Version::parse(env!("CARGO_PKG_VERSION")).expect("package version")
+}
+
+/// Označení platformy v `assets` (např. `windows-x86_64`).
+pub fn platform() -> String {
+    format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH)
+}
+
+/// Umí tato platforma novou verzi nainstalovat sama?
+pub const CAN_INSTALL: bool = cfg!(windows);
+
+/// Veřejný klíč vydání; bez klíče aktualizace nejdou ověřit.
+fn public_key() -> Result<PublicKey> {
+    if let Some(key) = BUILD_KEY {
+        return PublicKey::from_base64(key.trim())
+            .map_err(|e| anyhow!("invalid built-in update key: {e}"));
+    }
+    let path = std::env::current_exe()?.with_file_name(KEY_FILE);
+    if !path.exists() {
+        bail!("this build has no release signing key ({KEY_FILE} next to the program)");
+    }
+    PublicKey::from_file(&path).map_err(|e| anyhow!("{}: {e}", path.display()))
+}
+
+/// Přečte soubor `name` z kanálu (HTTP(S) nebo složka).
+fn fetch(channel: &str, name: &str) -> Result<Vec<u8>> {
+    let channel = channel.trim();
+    if channel.starts_with("http://") || channel.starts_with("https://") {
+        let url = format!("{}/{name}", channel.trim_end_matches('/'));
+        let agent = ureq::Agent::config_builder()
+            .timeout_connect(Some(Duration::from_secs(30)))
+            .build()
+            .new_agent();
+        let mut resp = agent
+            .get(&url)
+            .call()
+            .with_context(|| format!("GET {url}"))?;
+        return resp
+            .body_mut()
+            .with_config()
+            .limit(MAX_DOWNLOAD)
+            .read_to_vec()
+            .with_context(|| format!("GET {url}"));
+    }
+    let path = Path::new(channel).join(name);
+    let file = std::fs::File::open(&path).with_context(|| format!("open {}", path.display()))?;
+    let mut data = Vec::new();
+    file.take(MAX_DOWNLOAD + 1).read_to_end(&mut data)?;
+    if data.len() as u64 > MAX_DOWNLOAD {
+        bail!("{}: file too large", path.display());
+    }
+    Ok(data)
+}
+
+/// Přečte `name` z kanálu a ověří jeho podpis `name.minisig`.
+fn fetch_signed(channel: &str, name: &str, key: &PublicKey) -> Result<Vec<u8>> {
+    let data = fetch(channel, name)?;
+    let sig = fetch(channel, &format!("{name}.minisig"))?;
+    let sig = Signature::decode(&String::from_utf8_lossy(&sig))
+        .map_err(|e| anyhow!("{name}.minisig: {e}"))?;
+    key.verify(&data, &sig, false)
+        .map_err(|e| anyhow!("{name}: signature verification failed ({e})"))?;
+    Ok(data)
+}
+
+/// Novější vydání v kanálu; `None`, když je program aktuální.
+pub fn check(channel: &str) -> Result<Option<Release>> {
+    if channel.trim().is_empty() {
+        bail!("no update channel configured");
+    }
+    let key = public_key()?;
+    let manifest: Manifest = serde_json::from_slice(&fetch_signed(channel, MANIFEST, &key)?)
+        .with_context(|| format!("parse {MANIFEST}"))?;
+    let version = Version::parse(manifest.version.trim_start_matches('v'))
+        .with_context(|| format!("{MANIFEST}: version {}", manifest.version))?;
+    if version <= current_version() {
+        return Ok(None);
+    }
+    Ok(Some(Release {
+        version,
+        notes: manifest.notes,
+        asset: manifest.assets.get(&platform()).cloned(),
+    }))
+}
+
+/// Stáhne, ověří (podpis + SHA-256 z podepsaného manifestu) a nainstaluje vydání místo
+/// běžícího programu; změna platí po restartu.
+pub fn install(channel: &str, release: &Release) -> Result<()> {
+    let Some(asset) = &release.asset else {
+        bail!(
+            "release {} has no build for {}",
+            release.version,
+            platform()
+        );
+    };
+    if !CAN_INSTALL {
+        bail!(
+            "automatic installation is only available on Windows; install {} manually",
+            asset.file
+        );
+    }
+    // jméno z manifestu nesmí ukazovat mimo kanál
+    if asset.file.contains(['/', '\\']) || asset.file.starts_with('.') {
+        bail!("invalid file name in {MANIFEST}: {}", asset.file);
+    }
+    let key = public_key()?;
+    let data = fetch_signed(channel, &asset.file, &key)?;
+    let digest: String = Sha256::digest(&data)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    if !digest.eq_ignore_ascii_case(asset.sha256.trim()) {
+        bail!("{}: SHA-256 mismatch", asset.file);
+    }
+    replace_exe(&data)
+}
+
+/// Starý program vedle nového (smaže se při příštím spuštění).
+fn old_exe(exe: &Path) -> PathBuf {
+    exe.with_extension("old")
+}
+
+/// Běžící program na Windows nejde přepsat, ale jde přejmenovat.
+fn replace_exe(data: &[u8]) -> Result<()> {
+    let exe = std::env::current_exe()?;
+    let new = exe.with_extension("new");
+    let old = old_exe(&exe);
+    std::fs::write(&new, data).with_context(|| format!("write {}", new.display()))?;
+    std::fs::set_permissions(&new, std::fs::metadata(&exe)?.permissions())?;
+    let _ = std::fs::remove_file(&old);
+    if let Err(e) = std::fs::rename(&exe, &old) {
+        let _ = std::fs::remove_file(&new);
+        return Err(e).with_context(|| format!("rename {}", exe.display()));
+    }
+    if let Err(e) = std::fs::rename(&new, &exe) {
+        // vrátit původní program
+        let _ = std::fs::rename(&old, &exe);
+        let _ = std::fs::remove_file(&new);
+        return Err(e).with_context(|| format!("rename {}", new.display()));
+    }
+    Ok(())
+}
+
+/// Smaže program, který nahradila poslední aktualizace.
+pub fn remove_leftovers() {
+    if let Ok(exe) = std::env::current_exe() {
+        let _ = std::fs::remove_file(old_exe(&exe));
+    }
+}
+
+enum State {
+    Idle,
+    Checking(Receiver<Result<Option<Release>>>),
+    UpToDate,
+    Available(Release),
+    Installing(Release, Receiver<Result<()>>),
+    Installed(Version),
+    Failed(String),
+}
+
+/// Kontrola a instalace na pozadí s oknem, když je co nabídnout.
+pub struct Updater {
+    state: State,
+    channel: String,
+    /// Okno s výsledkem (automatická kontrola ukáže jen nalezené vydání).
+    show: bool,
+}
+
+impl Default for Updater {
+    fn default() -> Self {
+        Self {
+            state: State::Idle,
+            channel: String::new(),
+            show: false,
+        }
+    }
+}
+
+impl Updater {
+    /// `manual` = výsledek ukázat i když je program aktuální nebo kontrola selže.
+    pub fn check(&mut self, ctx: &egui::Context, channel: &str, manual: bool) {
+        if matches!(self.state, State::Checking(_) | State::Installing(..)) {
+            return;
+        }
+        let (tx, rx) = mpsc::channel();
+        let (ch, ctx) = (channel.to_owned(), ctx.clone());
+        std::thread::spawn(move || {
+            let _ = tx.send(check(&ch));
+            ctx.request_repaint();
+        });
+        self.channel = channel.to_owned();
+        self.state = State::Checking(rx);
+        self.show = manual;
+    }
+
+    /// Krátký stav pro nastavení.
+    pub fn status(&self) -> Option<String> {
+        match &self.state {
+            State::Idle => None,
+            State::Checking(_) => Some("checking…".into()),
+            State::UpToDate => Some(format!("up to date ({})", current_version())),
+            State::Available(r) => Some(format!("{} available", r.version)),
+            State::Installing(r, _) => Some(format!("installing {}…", r.version)),
+            State::Installed(v) => Some(format!("{v} installed, restart to use it")),
+            State::Failed(e) => Some(e.clone()),
+        }
+    }
+
+    fn poll(&mut self) {
+        match &self.state {
+            State::Checking(rx) => {
+                let Ok(res) = rx.try_recv() else { return };
+                self.state = match res {
+                    Ok(Some(release)) => {
+                        self.show = true;
+                        State::Available(release)
+                    }
+                    Ok(None) => State::UpToDate,
+                    Err(e) => {
+                        tracing::error!("update check error: {e:#}");
+                        State::Failed(format!("{e:#}"))
+                    }
+                };
+            }
+            State::Installing(release, rx) => {
+                let Ok(res) = rx.try_recv() else { return };
+                self.state = match res {
+                    Ok(()) => State::Installed(release.version.clone()),
+                    Err(e) => State::Failed(format!("{e:#}")),
+                };
+            }
+            _ => {}
+        }
+    }
+
+    /// Okno s nalezeným vydáním, průběhem instalace nebo chybou.
+    pub fn window(&mut self, ctx: &egui::Context) {
+        self.poll();
+        if !self.show || matches!(self.state, State::Idle | State::Checking(_)) {
+            return;
+        }
+        let mut open = true;
+        let mut to_install = None;
+        let mut restart = false;
+        egui::Window::new("Update")
+            .collapsible(false)
+            .resizable(false)
+            .open(&mut open)
+            .show(ctx, |ui| match &self.state {
+                State::Available(release) => {
+                    ui.label(format!(
+                        "CTI View {} is available (this is {}).",
+                        release.version,
+                        current_version()
+                    ));
+                    if !release.notes.is_empty() {
+                        ui.separator();
+                        egui::ScrollArea::vertical()
+                            .max_height(200.0)
+                            .show(ui, |ui| ui.label(&release.notes));
+                    }
+                    ui.separator();
+                    match &release.asset {
+                        Some(_) if CAN_INSTALL => {
+                            if ui.button("Install").clicked() {
+                                to_install = Some(release.clone());
+                            }
+                        }
+                        Some(asset) => {
+                            ui.weak(format!("Install {} from the update channel.", asset.file));
+                        }
+                        None => {
+                            ui.weak(format!("No build for {} in this release.", platform()));
+                        }
+                    }
+                }
+                State::Installing(release, _) => {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label(format!("Downloading and verifying {}…", release.version));
+                    });
+                }
+                State::Installed(version) => {
+                    ui.label(format!("CTI View {version} is installed."));
+                    if ui.button("Restart now").clicked() {
+                        restart = true;
+                    }
+                }
+                State::UpToDate => {
+                    ui.label(format!("CTI View {} is up to date.", current_version()));
+                }
+                State::Failed(e) => {
+                    ui.colored_label(ui.visuals().error_fg_color, e);
+                }
+                State::Idle | State::Checking(_) => {}
+            });
+        if let Some(release) = to_install {
+            let (tx, rx) = mpsc::channel();
+            let (ch, r, ctx) = (self.channel.clone(), release.clone(), ctx.clone());
+            std::thread::spawn(move || {
+                let _ = tx.send(install(&ch, &r));
+                ctx.request_repaint();
+            });
+            self.state = State::Installing(release, rx);
+        }
+        if restart {
+            match std::env::current_exe().and_then(|exe| std::process::Command::new(exe).spawn()) {
+                Ok(_) => ctx.send_viewport_cmd(egui::ViewportCommand::Close),
+                Err(e) => self.state = State::Failed(format!("restart: {e}")),
+            }
+        }
+        if !open {
+            self.show = false;
+        }
+    }
+}
@@ -0,0 +1,480 @@
+use anyhow::{Result, bail};
+use eframe::egui::{self, Color32, ColorImage, Pos2, Rect, Stroke, TextureHandle, Ui, Vec2};
+
+use crate::cmyk;
+use crate::cti::{BadTile, CTIHeader};
+use crate::hdr;
+use crate::simd;
+
+/// Délka přechodu mezi kroky zoomu (s).
+const ZOOM_ANIMATION: f32 = 0.15;
+
+/// Zoom, posun a otočení – sdílené všemi panely s obrázkem (např. při porovnání).
+/// `zoom`/`fit`/`pan` jsou cílový stav; zobrazený k němu po krocích zoomu plynule dojede.
+#[derive(Debug, Clone, Copy)]
+pub struct View {
+    pub zoom: f32,    // 1.0 = 100%
+    pub fit: bool,    // true = obsah se přizpůsobí oknu
+    pub rotation: u8, // otočení po 90° (0..=3, po směru hodin)
+    pub pan: Vec2,    // posun středu obrázku od středu viewportu (body obrazovky)
+    shown: Option<Shown>,
+    animation: Option<ZoomAnimation>,
+}
+
+/// Měřítko a posun středu obrázku, jak se naposledy vykreslily.
+#[derive(Debug, Clone, Copy)]
+struct Shown {
+    scale: f32,
+    offset: Vec2,
+}
+
+/// Přechod od zobrazeného stavu `from` k cílovému.
+#[derive(Debug, Clone, Copy)]
+struct ZoomAnimation {
+    from: Shown,
+    /// Čas egui prvního vykreslení přechodu.
+    start: Option<f64>,
+}
+
+impl Default for View {
+    fn default() -> Self {
+        Self {
+            zoom: 1.0,
+            fit: true,
+            rotation: 0,
+            pan: Vec2::ZERO,
+            shown: None,
+            animation: None,
+        }
+    }
+}
+
+impl View {
+    /// Rozměr po otočení (90°/270° prohodí šířku a výšku).
+    pub fn rotated_size(&self, (w, h): (u32, u32)) -> Vec2 {
+        if self.rotation % 2 == 1 {
+            Vec2::new(h as f32, w as f32)
+        } else {
+            Vec2::new(w as f32, h as f32)
+        }
+    }
+
+    /// Měřítko (body obrazovky na pixel) pro obrázek dané velikosti ve viewportu.
+    pub fn scale(&self, viewport: Rect, size: (u32, u32)) -> f32 {
+        if self.fit {
+            // Přizpůsobit oknu: neukládej scale do zoom, ať 1:1 zůstane přesné při přepnutí
+            let s = self.rotated_size(size);
+            (viewport.width() / s.x).min(viewport.height() / s.y)
+        } else {
+            self.zoom
+        }
+    }
+
+    /// Obdélník (na obrazovce), kam se vykreslí otočený obrázek v daném měřítku.
+    pub fn image_rect(&self, viewport: Rect, size: (u32, u32), scale: f32) -> Rect {
+        let pan = if self.fit { Vec2::ZERO } else { self.pan };
+        Rect::from_center_size(viewport.center() + pan, self.rotated_size(size) * scale)
+    }
+
+    /// Měřítko a obdélník obrázku pro tento snímek: během přechodu mezi aktuálním
+    /// a cílovým stavem, jinak cílový stav.
+    pub fn animate(
+        &mut self,
+        ctx: &egui::Context,
+        viewport: Rect,
+        size: (u32, u32),
+    ) -> (f32, Rect) {
+        let scale = self.scale(viewport, size);
+        let target = Shown {
+            scale,
+            offset: self.image_rect(viewport, size, scale).center() - viewport.center(),
+        };
+        let mut shown = target;
+        if let Some(anim) = &mut self.animation {
+            let now = ctx.input(|i| i.time);
+            let t = (now - *anim.start.get_or_insert(now)) as f32 / ZOOM_ANIMATION;
+            if t < 1.0 {
+                shown = interpolate(anim.from, target, egui::emath::easing::cubic_out(t));
+                ctx.request_repaint();
+            } else {
+                self.animation = None;
+            }
+        }
+        self.shown = Some(shown);
+        let rect = Rect::from_center_size(
+            viewport.center() + shown.offset,
+            self.rotated_size(size) * shown.scale,
+        );
+        (shown.scale, rect)
+    }
+
+    /// Obdélník jiného obrázku (porovnání) ve stejném zobrazeném měřítku a posunu.
+    pub fn shown_rect(&self, viewport: Rect, size: (u32, u32)) -> Rect {
+        match self.shown {
+            Some(s) => Rect::from_center_size(
+                viewport.center() + s.offset,
+                self.rotated_size(size) * s.scale,
+            ),
+            None => self.image_rect(viewport, size, self.scale(viewport, size)),
+        }
+    }
+
+    /// Další krok zoomu dojede plynule z toho, co je právě vidět.
+    fn start_animation(&mut self) {
+        self.animation = self.shown.map(|from| ZoomAnimation { from, start: None });
+    }
+
+    /// Nový obrázek se ukáže rovnou v cílovém zoomu.
+    pub fn stop_animation(&mut self) {
+        self.animation = None;
+        self.shown = None;
+    }
+
+    /// Změní zoom; z režimu Fit navazuje na aktuální měřítko.
+    pub fn zoom_by(&mut self, factor: f32, current_scale: Option<f32>) {
+        self.start_animation();
+        if self.fit {
+            self.zoom = current_scale.unwrap_or(self.zoom);
+            self.fit = false;
+            self.pan = Vec2::ZERO;
+        }
+        self.zoom = (self.zoom * factor).clamp(0.05, 50.0);
+    }
+
+    /// Změní zoom tak, aby bod `anchor` na obrazovce zůstal na místě.
+    pub fn zoom_at(&mut self, factor: f32, current_scale: f32, viewport: Rect, anchor: Pos2) {
+        let center = viewport.center() + if self.fit { Vec2::ZERO } else { self.pan };
+        self.zoom_by(factor, Some(current_scale));
+        let ratio = self.zoom / current_scale;
+        self.pan = anchor - (anchor - center) * ratio - viewport.center();
+    }
+
+    /// Přiblíží obdélník `area` (na obrazovce) přes celý viewport a vystředí ho.
+    pub fn zoom_to(&mut self, current_scale: f32, viewport: Rect, area: Rect) {
+        if area.width() < 1.0 || area.height() < 1.0 {
+            return;
+        }
+        let center = viewport.center() + if self.fit { Vec2::ZERO } else { self.pan };
+        let factor = (viewport.width() / area.width()).min(viewport.height() / area.height());
+        self.zoom_by(factor, Some(current_scale));
+        let ratio = self.zoom / current_scale;
+        self.pan = -(area.center() - center) * ratio;
+    }
+
+    /// Ukáže výřez `area` (v pixelech obrázku) přes celý viewport a vystředí ho.
+    pub fn show_region(&mut self, viewport: Rect, size: (u32, u32), area: Rect) {
+        let r = if self.rotation % 2 == 1 {
+            Vec2::new(area.height(), area.width())
+        } else {
+            area.size()
+        };
+        if r.x < 1.0 || r.y < 1.0 {
+            return;
+        }
+        self.set_zoom((viewport.width() / r.x).min(viewport.height() / r.y));
+        let rect = self.image_rect(viewport, size, self.zoom);
+        let center = image_to_screen(rect, self.rotation, size, area.center());
+        self.pan = viewport.center() - center;
+    }
+
+    pub fn set_fit(&mut self) {
+        self.start_animation();
+        self.fit = true;
+        self.pan = Vec2::ZERO;
+    }
+
+    pub fn set_actual_size(&mut self) {
+        self.set_zoom(1.0);
+    }
+
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.start_animation();
+        self.fit = false;
+        self.zoom = zoom.clamp(0.05, 50.0);
+        self.pan = Vec2::ZERO;
+    }
+}
+
+/// Mezistav přechodu v čase `t` (0..=1). Měřítko se mění geometricky a pevný bod
+/// zvětšení (např. bod pod kurzorem) zůstává celou dobu na místě.
+fn interpolate(from: Shown, to: Shown, t: f32) -> Shown {
+    let ratio = to.scale / from.scale;
+    if (ratio - 1.0).abs() < 1e-3 {
+        // jen posun
+        return Shown {
+            scale: egui::lerp(from.scale..=to.scale, t),
+            offset: from.offset + (to.offset - from.offset) * t,
+        };
+    }
+    // střed stejnolehlosti, která převádí `from` na `to`
+    let anchor = (to.offset - from.offset * ratio) / (1.0 - ratio);
+    let k = ratio.powf(t);
+    Shown {
+        scale: from.scale * k,
+        offset: anchor + (from.offset - anchor) * k,
+    }
+}
+
+/// Rámeček pro přiblížení (Ctrl/Cmd + tažení), v souřadnicích obrazovky.
+#[derive(Default)]
+pub struct ZoomBox {
+    start: Option<Pos2>,
+    end: Pos2,
+}
+
+impl ZoomBox {
+    pub fn dragging(&self) -> bool {
+        self.start.is_some()
+    }
+
+    /// Zpracuje tažení s Ctrl/Cmd; po puštění vrací obdélník k přiblížení. Esc ruší.
+    pub fn handle_input(&mut self, resp: &egui::Response) -> Option<Rect> {
+        let (command, escape) = resp
+            .ctx
+            .input(|i| (i.modifiers.command, i.key_pressed(egui::Key::Escape)));
+        if escape {
+            self.start = None;
+        }
+        let pos = resp.interact_pointer_pos();
+        if resp.drag_started() && command {
+            self.start = pos;
+        }
+        if let Some(p) = pos {
+            self.end = p;
+        }
+        if resp.drag_stopped() {
+            return self.start.take().map(|s| Rect::from_two_pos(s, self.end));
+        }
+        None
+    }
+
+    pub fn paint(&self, painter: &egui::Painter) {
+        if let Some(start) = self.start {
+            let rect = Rect::from_two_pos(start, self.end);
+            painter.rect_filled(rect, 0.0, Color32::from_rgba_unmultiplied(80, 160, 255, 30));
+            painter.rect_stroke(
+                rect,
+                0.0,
+                Stroke::new(1.0, Color32::from_rgb(80, 160, 255)),
+                egui::StrokeKind::Middle,
+            );
+        }
+    }
+}
+
+/// Měřítko (body obrazovky na pixel), ve kterém má snímek s rozlišením `dpi` na monitoru
+/// s `screen_ppi` fyzickými pixely na palec svou skutečnou velikost.
+pub fn print_size_zoom(dpi: f32, screen_ppi: f32, pixels_per_point: f32) -> f32 {
+    screen_ppi / dpi / pixels_per_point
+}
+
+/// Vykreslí texturu otočenou o `rotation` × 90° do `rect` (rect je už otočený rozměr).
+pub fn paint_image(ui: &mut Ui, clip: Rect, tex: &TextureHandle, rect: Rect, rotation: u8) {
+    // Image::rotate neovlivní layout – kreslíme neotočený rozměr kolem středu
+    let unrotated = if rotation % 2 == 1 {
+        Vec2::new(rect.height(), rect.width())
+    } else {
+        rect.size()
+    };
+    let angle = rotation as f32 * std::f32::consts::FRAC_PI_2;
+    let mut child = ui.new_child(egui::UiBuilder::new().max_rect(clip));
+    child.set_clip_rect(clip.intersect(ui.clip_rect()));
+    egui::Image::new(tex)
+        .rotate(angle, Vec2::splat(0.5))
+        .paint_at(&child, Rect::from_center_size(rect.center(), unrotated));
+}
+
+/// Navigátor v pravém dolním rohu `pane`: celý obrázek zmenšený na nejvýš `size` bodů
+/// s rámečkem viditelné části. Kliknutím se na místo přesune střed pohledu, tažením
+/// rámečku se posouvá; vrací změnu posunu (`View::pan`). Kreslí se jen, když se obrázek
+/// (`rect`) do `pane` nevejde.
+pub fn navigator(
+    ui: &mut Ui,
+    pane: Rect,
+    tex: &TextureHandle,
+    rect: Rect,
+    rotation: u8,
+    size: f32,
+) -> Option<Vec2> {
+    if pane.expand(0.5).contains_rect(rect) {
+        return None;
+    }
+    let k = (size / rect.width()).min(size / rect.height());
+    let margin = Vec2::splat(10.0);
+    let mini = Rect::from_min_size(pane.max - margin - rect.size() * k, rect.size() * k);
+    let painter = ui.painter_at(pane);
+    painter.rect_filled(mini.expand(3.0), 3.0, Color32::from_black_alpha(160));
+    paint_image(ui, mini, tex, mini, rotation);
+
+    // viditelná část obrázku v souřadnicích navigátoru
+    let to_mini = |p: Pos2| mini.min + (p - rect.min) * k;
+    let visible = rect.intersect(pane);
+    let frame = Rect::from_min_max(to_mini(visible.min), to_mini(visible.max));
+    painter.rect_stroke(
+        frame,
+        0.0,
+        Stroke::new(1.5, Color32::from_rgb(255, 200, 0)),
+        egui::StrokeKind::Outside,
+    );
+
+    let id = ui.id().with("navigator");
+    let resp = ui.interact(mini, id, egui::Sense::click_and_drag());
+    resp.widget_info(|| egui::WidgetInfo::labeled(egui::WidgetType::Other, true, "Navigator"));
+    // bod navigátoru → posun, po kterém bude tento bod uprostřed plochy
+    let center_on = |m: Pos2| pane.center() - (rect.min + (m - mini.min) / k);
+    let pointer = resp.interact_pointer_pos()?;
+    if (resp.drag_started() && !frame.contains(pointer)) || resp.clicked() {
+        Some(center_on(pointer))
+    } else if resp.dragged() {
+        Some(-resp.drag_delta() / k)
+    } else {
+        None
+    }
+}
+
+/// Pozice na obrazovce → souřadnice pixelu v obrázku (zohledňuje otočení).
+pub fn screen_to_pixel(
+    rect: Rect,
+    rotation: u8,
+    (w, h): (u32, u32),
+    pos: Pos2,
+) -> Option<(u32, u32)> {
+    if !rect.contains(pos) {
+        return None;
+    }
+    let p = screen_to_image(rect, rotation, (w, h), pos);
+    let x = (p.x as u32).min(w - 1);
+    let y = (p.y as u32).min(h - 1);
+    Some((x, y))
+}
+
+/// Pozice na obrazovce → spojité souřadnice v obrázku (v pixelech, bez ořezu).
+pub fn screen_to_image(rect: Rect, rotation: u8, (w, h): (u32, u32), pos: Pos2) -> Pos2 {
+    let u = (pos.x - rect.min.x) / rect.width();
+    let v = (pos.y - rect.min.y) / rect.height();
+    let (su, sv) = match rotation {
+        1 => (v, 1.0 - u),
+        2 => (1.0 - u, 1.0 - v),
+        3 => (1.0 - v, u),
+        _ => (u, v),
+    };
+    Pos2::new(su * w as f32, sv * h as f32)
+}
+
+/// Opak `screen_to_image`: souřadnice v obrázku → pozice na obrazovce.
+pub fn image_to_screen(rect: Rect, rotation: u8, (w, h): (u32, u32), p: Pos2) -> Pos2 {
+    let (su, sv) = (p.x / w as f32, p.y / h as f32);
+    let (u, v) = match rotation {
+        1 => (1.0 - sv, su),
+        2 => (1.0 - su, 1.0 - sv),
+        3 => (sv, 1.0 - su),
+        _ => (su, sv),
+    };
+    Pos2::new(
+        rect.min.x + u * rect.width(),
+        rect.min.y + v * rect.height(),
+    )
+}
+
+/// Zvýrazní poškozené dlaždice a nahoře vypíše varování.
+pub fn paint_bad_tiles(
+    painter: &egui::Painter,
+    hdr: &CTIHeader,
+    bad: &[BadTile],
+    to_screen: impl Fn(Pos2) -> Pos2,
+) {
+    let ts = hdr.tile_size as f32;
+    let stroke = Stroke::new(2.0, Color32::RED);
+    for t in bad {
+        let (tx, ty) = (
+            (t.index % hdr.tiles_x) as f32,
+            (t.index / hdr.tiles_x) as f32,
+        );
+        let min = Pos2::new(tx * ts, ty * ts);
+        let max = Pos2::new(
+            ((tx + 1.0) * ts).min(hdr.width as f32),
+            ((ty + 1.0) * ts).min(hdr.height as f32),
+        );
+        let rect = Rect::from_two_pos(to_screen(min), to_screen(max));
+        painter.rect_filled(rect, 0.0, Color32::from_rgba_unmultiplied(255, 0, 0, 40));
+        painter.rect_stroke(rect, 0.0, stroke, egui::StrokeKind::Inside);
+    }
+    let clip = painter.clip_rect();
+    let text = format!(
+        "⚠ {} damaged tile(s) shown as placeholders — see Info",
+        bad.len()
+    );
+    let galley = painter.layout_no_wrap(text, egui::FontId::proportional(14.0), Color32::WHITE);
+    let pos = clip.center_top() + Vec2::new(-galley.size().x / 2.0, 28.0);
+    let bg = Rect::from_min_size(pos, galley.size()).expand(6.0);
+    painter.rect_filled(bg, 4.0, Color32::from_rgba_unmultiplied(160, 0, 0, 220));
+    painter.galley(pos, galley, Color32::WHITE);
+}
+
+/// Průběh postupného načítání dole uprostřed plochy.
+pub fn paint_loading(painter: &egui::Painter, done: usize, total: usize) {
+    let clip = painter.clip_rect();
+    let text = format!("Loading… {done} / {total} tiles");
+    let galley = painter.layout_no_wrap(text, egui::FontId::proportional(14.0), Color32::WHITE);
+    let pos = clip.center_bottom() + Vec2::new(-galley.size().x / 2.0, -galley.size().y - 28.0);
+    let bg = Rect::from_min_size(pos, galley.size()).expand(6.0);
+    painter.rect_filled(bg, 4.0, Color32::from_black_alpha(180));
+    let frac = done as f32 / total.max(1) as f32;
+    let bar = Rect::from_min_max(bg.left_bottom() - Vec2::new(0.0, 2.0), bg.right_bottom());
+    painter.rect_filled(
+        Rect::from_min_size(bar.min, Vec2::new(bar.width() * frac, bar.height())),
+        0.0,
+        Color32::from_rgb(80, 160, 255),
+    );
+    painter.galley(pos, galley, Color32::WHITE);
+}
+
+/// Dekódovaná data (8 bitů na kanál) jako textura pro egui.
+pub fn to_color_image(hdr: &CTIHeader, raw: &[u8]) -> Result<ColorImage> {
+    check_previewable(hdr.color_type)?;
+    let size = [hdr.width as usize, hdr.height as usize];
+    Ok(match hdr.color_type {
+        1 | 6 | 7 => {
+            // L8 (i rozbalené L1/L4) → RGBA8 rovnou do pixelů textury (neprůhledné, takže bez přenásobení)
+            let mut rgba = vec![0u8; size[0] * size[1] * 4];
+            simd::l8_to_rgba(raw, &mut rgba);
+            ColorImage::new(size, bytemuck::allocation::cast_vec(rgba))
+        }
+        3 => {
+            // RGB8 → RGBA8
+            let mut rgba = vec![0u8; size[0] * size[1] * 4];
+            simd::rgb8_to_rgba(raw, &mut rgba);
+            ColorImage::new(size, bytemuck::allocation::cast_vec(rgba))
+        }
+        4 => {
+            // RGBA8 (přímo)
+            ColorImage::from_rgba_unmultiplied(
+                [hdr.width as usize, hdr.height as usize],
+                raw,
+            )
+        }
+        8 => {
+            // CMYK8 → RGB8 (nastavený převod) → RGBA8
+            let mut rgba = vec![0u8; size[0] * size[1] * 4];
+            simd::rgb8_to_rgba(&cmyk::to_rgb(raw), &mut rgba);
+            ColorImage::new(size, bytemuck::allocation::cast_vec(rgba))
+        }
+        9 | 10 => {
+            // L32F/RGB32F → RGB8 (expozice a mapování tónů) → RGBA8
+            let channels = if hdr.color_type == 9 { 1 } else { 3 };
+            let mut rgba = vec![0u8; size[0] * size[1] * 4];
+            simd::rgb8_to_rgba(&hdr::to_rgb8(raw, channels), &mut rgba);
+            ColorImage::new(size, bytemuck::allocation::cast_vec(rgba))
+        }
+        _ => unreachable!("checked by check_previewable"),
+    })
+}
+
+/// Ověří, že [`to_color_image`] umí daný typ barev zobrazit.
+pub fn check_previewable(color_type: u8) -> Result<()> {
+    match color_type {
+        1 | 3 | 4 | 6 | 7 | 8 | 9 | 10 => Ok(()),
+        2 | 5 => bail!("16-bit preview not implemented yet (L16/RGB16)."),
+        _ => bail!("Unsupported ColorType ID {}", color_type),
+    }
+}
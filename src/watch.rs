@@ -0,0 +1,70 @@
+//! Sledování otevřeného souboru na disku: když ho jiný program (typicky kodér spuštěný ve
+//! smyčce) přepíše, aplikace ho znovu načte. Sleduje se nadřazená složka, protože zápis přes
+//! dočasný soubor a přejmenování nahradí i samotný soubor.
+
+use anyhow::{Context, Result};
+use eframe::egui;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, channel};
+use std::time::{Duration, Instant};
+
+/// Jak dlouho musí být soubor v klidu, než se načte (zápis velkého souboru trvá).
+const SETTLE: Duration = Duration::from_millis(400);
+
+pub struct FileWatcher {
+    pub path: PathBuf,
+    rx: Receiver<notify::Result<Event>>,
+    changed_at: Option<Instant>,
+    _watcher: RecommendedWatcher,
+}
+
+impl FileWatcher {
+    pub fn new(ctx: &egui::Context, path: &Path) -> Result<Self> {
+        let (tx, rx) = channel();
+        let ctx = ctx.clone();
+        let mut watcher = notify::recommended_watcher(move |ev| {
+            let _ = tx.send(ev);
+            ctx.request_repaint();
+        })?;
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        watcher
+            .watch(dir, RecursiveMode::NonRecursive)
+            .with_context(|| format!("watch {}", dir.display()))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            rx,
+            changed_at: None,
+            _watcher: watcher,
+        })
+    }
+
+    /// Volá se každý snímek: vrátí `true`, když se soubor změnil a zápis už skončil.
+    pub fn poll(&mut self, ctx: &egui::Context) -> bool {
+        let name = self.path.file_name();
+        while let Ok(ev) = self.rx.try_recv() {
+            match ev {
+                Ok(ev)
+                    if matches!(ev.kind, EventKind::Create(_) | EventKind::Modify(_))
+                        && ev.paths.iter().any(|p| p.file_name() == name) =>
+                {
+                    self.changed_at = Some(Instant::now());
+                }
+                Ok(_) => {}
+                Err(e) => tracing::error!("watch error: {e}"),
+            }
+        }
+        let Some(at) = self.changed_at else {
+            return false;
+        };
+        if at.elapsed() < SETTLE || !self.path.exists() {
+            ctx.request_repaint_after(SETTLE);
+            return false;
+        }
+        self.changed_at = None;
+        true
+    }
+}
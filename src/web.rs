@@ -0,0 +1,206 @@
+//! Prohlížeč CTI v prohlížeči (wasm32, `trunk build`): soubor se vybere dialogem nebo
+//! přetáhne do okna, načte se celý do paměti a dekóduje přes `Read + Seek` nad bajty –
+//! nic se neodesílá na server. Zoom, posun a otočení jsou stejné jako na desktopu.
+
+use std::io::Cursor;
+use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver, Sender};
+
+use anyhow::Result;
+use eframe::egui::{self, TextureHandle};
+
+use crate::cti::{BadTile, CTIDecoder, CTIHeader};
+use crate::view::{self, View};
+
+/// Otevřený soubor: bajty v paměti a zobrazený snímek.
+struct Document {
+    name: String,
+    bytes: Arc<[u8]>,
+    hdr: CTIHeader,
+    frame: u32,
+    tex: TextureHandle,
+    bad_tiles: Vec<BadTile>,
+}
+
+impl Document {
+    fn load(ctx: &egui::Context, name: String, bytes: Arc<[u8]>, frame: u32) -> Result<Self> {
+        let (hdr, raw, bad_tiles) =
+            CTIDecoder::decode_frame_progressive_from(Cursor::new(&bytes[..]), frame, |_, _| true)?;
+        let image = view::to_color_image(&hdr, &raw)?;
+        let tex = ctx.load_texture(&name, image, egui::TextureOptions::LINEAR);
+        Ok(Self {
+            name,
+            bytes,
+            hdr,
+            frame,
+            tex,
+            bad_tiles,
+        })
+    }
+
+    fn size(&self) -> (u32, u32) {
+        (self.hdr.width, self.hdr.height)
+    }
+}
+
+pub struct WebViewer {
+    doc: Option<Document>,
+    view: View,
+    error: Option<String>,
+    /// Soubory z dialogu (čtou se asynchronně): (název, obsah).
+    picked_tx: Sender<(String, Vec<u8>)>,
+    picked_rx: Receiver<(String, Vec<u8>)>,
+}
+
+impl WebViewer {
+    pub fn new(_cc: &eframe::CreationContext<'_>) -> Self {
+        let (picked_tx, picked_rx) = mpsc::channel();
+        Self {
+            doc: None,
+            view: View::default(),
+            error: None,
+            picked_tx,
+            picked_rx,
+        }
+    }
+
+    fn open(&mut self, ctx: &egui::Context, name: String, bytes: Arc<[u8]>) {
+        match Document::load(ctx, name, bytes, 0) {
+            Ok(doc) => {
+                self.doc = Some(doc);
+                self.view = View::default();
+                self.error = None;
+            }
+            Err(e) => self.error = Some(format!("{e:#}")),
+        }
+    }
+
+    fn show_frame(&mut self, ctx: &egui::Context, frame: u32) {
+        let Some(doc) = &self.doc else { return };
+        match Document::load(ctx, doc.name.clone(), doc.bytes.clone(), frame) {
+            Ok(doc) => self.doc = Some(doc),
+            Err(e) => self.error = Some(format!("{e:#}")),
+        }
+    }
+
+    /// Dialog prohlížeče pro výběr souboru; výsledek přijde přes `picked_rx`.
+    fn pick_file(&self, ctx: &egui::Context) {
+        let (tx, ctx) = (self.picked_tx.clone(), ctx.clone());
+        wasm_bindgen_futures::spawn_local(async move {
+            let Some(file) = rfd::AsyncFileDialog::new()
+                .add_filter("CTI images", &["cti"])
+                .pick_file()
+                .await
+            else {
+                return;
+            };
+            let _ = tx.send((file.file_name(), file.read().await));
+            ctx.request_repaint();
+        });
+    }
+
+    fn toolbar(&mut self, ctx: &egui::Context, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if ui.button("Open…").clicked() {
+                self.pick_file(ctx);
+            }
+            let Some(doc) = &self.doc else {
+                ui.label("or drop a .cti file here");
+                return;
+            };
+            ui.separator();
+            let h = &doc.hdr;
+            ui.label(format!("{}  {} × {}", doc.name, h.width, h.height));
+            let (frame, frames) = (doc.frame, h.frames);
+            if frames > 1 {
+                ui.separator();
+                if ui.add_enabled(frame > 0, egui::Button::new("◀")).clicked() {
+                    self.show_frame(ctx, frame - 1);
+                }
+                ui.label(format!("{} / {frames}", frame + 1));
+                if ui
+                    .add_enabled(frame + 1 < frames, egui::Button::new("▶"))
+                    .clicked()
+                {
+                    self.show_frame(ctx, frame + 1);
+                }
+            }
+            ui.separator();
+            if ui.button("Fit").clicked() {
+                self.view.set_fit();
+            }
+            if ui.button("1:1").clicked() {
+                self.view.set_actual_size();
+            }
+            if ui.button("⟲").on_hover_text("Rotate left").clicked() {
+                self.view.rotation = (self.view.rotation + 3) % 4;
+            }
+            if ui.button("⟳").on_hover_text("Rotate right").clicked() {
+                self.view.rotation = (self.view.rotation + 1) % 4;
+            }
+        });
+        if let Some(e) = &self.error {
+            ui.colored_label(ui.visuals().error_fg_color, e);
+        }
+    }
+
+    fn image(&mut self, ctx: &egui::Context, ui: &mut egui::Ui) {
+        let Some(doc) = &self.doc else {
+            ui.centered_and_justified(|ui| ui.label("Open a .cti file"));
+            return;
+        };
+        let pane = ui.max_rect();
+        let resp = ui.allocate_rect(pane, egui::Sense::click_and_drag());
+        let size = doc.size();
+        let (scale, rect) = self.view.animate(ctx, pane, size);
+        view::paint_image(ui, pane, &doc.tex, rect, self.view.rotation);
+        if !doc.bad_tiles.is_empty() {
+            let rotation = self.view.rotation;
+            view::paint_bad_tiles(&ui.painter_at(pane), &doc.hdr, &doc.bad_tiles, |p| {
+                view::image_to_screen(rect, rotation, size, p)
+            });
+        }
+        if let Some(pan) = view::navigator(ui, pane, &doc.tex, rect, self.view.rotation, 180.0) {
+            self.view.pan += pan;
+        }
+
+        // kolečko zoomuje kolem kurzoru, dvojklik přiblíží (se Shiftem oddálí)
+        let delta = ctx.input(|i| i.raw_scroll_delta.y);
+        if let Some(pos) = resp.hover_pos().filter(|_| delta != 0.0) {
+            let factor = if delta > 0.0 { 1.1 } else { 0.9 };
+            self.view.zoom_at(factor, scale, pane, pos);
+        }
+        if resp.double_clicked()
+            && let Some(pos) = resp.interact_pointer_pos()
+        {
+            let factor = if ctx.input(|i| i.modifiers.shift) {
+                0.5
+            } else {
+                2.0
+            };
+            self.view.zoom_at(factor, scale, pane, pos);
+        }
+        if resp.dragged() && !self.view.fit {
+            self.view.pan += resp.drag_delta();
+        }
+    }
+}
+
+impl eframe::App for WebViewer {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        while let Ok((name, bytes)) = self.picked_rx.try_recv() {
+            self.open(ctx, name, bytes.into());
+        }
+        // přetažený soubor má v prohlížeči obsah rovnou v paměti
+        let dropped = ctx.input(|i| i.raw.dropped_files.first().cloned());
+        if let Some(file) = dropped {
+            match file.bytes {
+                Some(bytes) => self.open(ctx, file.name, bytes),
+                None => self.error = Some(format!("{}: could not read the file", file.name)),
+            }
+        }
+
+        egui::TopBottomPanel::top("toolbar").show(ctx, |ui| self.toolbar(ctx, ui));
+        egui::CentralPanel::default().show(ctx, |ui| self.image(ctx, ui));
+    }
+}
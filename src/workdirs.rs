@@ -0,0 +1,204 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::dryrun::human;
+
+/// Soubory mladší než tato doba se nemažou (mohou se právě zapisovat nebo je
+/// otevírá externí nástroj).
+const GRACE: Duration = Duration::from_secs(10 * 60);
+
+/// Spravovaná pracovní složka.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Area {
+    /// Dočasné soubory (export pro externí nástroje, tisk, schránka).
+    Temp,
+    /// Cache, kterou jde kdykoli smazat a znovu vytvořit.
+    Cache,
+}
+
+impl Area {
+    pub const ALL: [Area; 2] = [Area::Temp, Area::Cache];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Area::Temp => "temp",
+            Area::Cache => "cache",
+        }
+    }
+
+    /// Cesta ke složce (nemusí existovat); přepíše ji proměnná `CTI_VIEW_TEMP`
+    /// nebo `CTI_VIEW_CACHE`, např. na stanici s malým systémovým diskem.
+    pub fn path(self) -> PathBuf {
+        let var = match self {
+            Area::Temp => "CTI_VIEW_TEMP",
+            Area::Cache => "CTI_VIEW_CACHE",
+        };
+        if let Some(dir) = std::env::var_os(var).filter(|v| !v.is_empty()) {
+            return dir.into();
+        }
+        match self {
+            Area::Temp => std::env::temp_dir().join("cti-view"),
+            Area::Cache => cache_base().map_or_else(
+                || std::env::temp_dir().join("cti-view-cache"),
+                |b| b.join("cti-view"),
+            ),
+        }
+    }
+
+    /// Cesta ke složce; chybějící se založí.
+    pub fn dir(self) -> Result<PathBuf> {
+        let dir = self.path();
+        std::fs::create_dir_all(&dir).with_context(|| format!("create {}", dir.display()))?;
+        Ok(dir)
+    }
+
+    /// Výchozí pravidla úklidu.
+    pub fn default_retention(self) -> Retention {
+        match self {
+            Area::Temp => Retention {
+                max_age_days: 7,
+                max_mb: 2048,
+            },
+            Area::Cache => Retention {
+                max_age_days: 30,
+                max_mb: 10240,
+            },
+        }
+    }
+}
+
+/// Systémová složka pro cache.
+#[cfg(windows)]
+fn cache_base() -> Option<PathBuf> {
+    std::env::var_os("LOCALAPPDATA").map(PathBuf::from)
+}
+
+#[cfg(target_os = "macos")]
+fn cache_base() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|h| PathBuf::from(h).join("Library/Caches"))
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+fn cache_base() -> Option<PathBuf> {
+    std::env::var_os("XDG_CACHE_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".cache")))
+}
+
+/// Kolik smí složka zabírat: starší soubory se smažou, a je-li složka pořád větší
+/// než limit, mažou se nejdéle nepoužité. 0 = bez limitu.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Retention {
+    pub max_age_days: u32,
+    pub max_mb: u64,
+}
+
+/// Výsledek úklidu jedné složky.
+#[derive(Debug, Default)]
+pub struct Pruned {
+    pub removed: usize,
+    pub freed: u64,
+    pub kept: usize,
+    pub left: u64,
+}
+
+impl Pruned {
+    pub fn summary(&self, dry_run: bool) -> String {
+        format!(
+            "{} file(s) {} ({}), {} file(s) kept ({})",
+            self.removed,
+            if dry_run {
+                "would be removed"
+            } else {
+                "removed"
+            },
+            human(self.freed),
+            self.kept,
+            human(self.left)
+        )
+    }
+}
+
+/// Uklidí `dir` podle `rule`; `dry_run` jen spočítá, co by se smazalo. Soubory
+/// mladší než [`GRACE`] zůstávají vždy. Chybějící složka není chyba.
+pub fn prune(dir: &Path, rule: Retention, dry_run: bool) -> Result<Pruned> {
+    let mut files = Vec::new();
+    if dir.exists() {
+        walk(dir, &mut files)?;
+    }
+    // nejdéle nepoužité první
+    files.sort_by_key(|f| f.1);
+    let now = SystemTime::now();
+    let age = |t: SystemTime| now.duration_since(t).unwrap_or_default();
+    let max_age = Duration::from_secs(u64::from(rule.max_age_days) * 86_400);
+    let mut total: u64 = files.iter().map(|f| f.2).sum();
+    let mut out = Pruned::default();
+    for (path, used, size) in files {
+        let old = rule.max_age_days > 0 && age(used) > max_age;
+        let over = rule.max_mb > 0 && total > rule.max_mb << 20;
+        // soubor otevřený jinou aplikací (Windows) nejde smazat, zůstane do příštího úklidu
+        if (old || over) && age(used) > GRACE && (dry_run || std::fs::remove_file(&path).is_ok()) {
+            total -= size;
+            out.removed += 1;
+            out.freed += size;
+            continue;
+        }
+        out.kept += 1;
+        out.left += size;
+    }
+    if !dry_run && dir.exists() {
+        remove_empty(dir, dir);
+    }
+    Ok(out)
+}
+
+/// Všechny soubory pod `dir`: cesta, poslední použití (změna nebo čtení) a velikost.
+fn walk(dir: &Path, out: &mut Vec<(PathBuf, SystemTime, u64)>) -> Result<()> {
+    let entries = std::fs::read_dir(dir).with_context(|| format!("read {}", dir.display()))?;
+    for entry in entries {
+        let entry = entry?;
+        let meta = entry.metadata()?;
+        if meta.is_dir() {
+            walk(&entry.path(), out)?;
+        } else {
+            let modified = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            let used = meta.accessed().map_or(modified, |a| a.max(modified));
+            out.push((entry.path(), used, meta.len()));
+        }
+    }
+    Ok(())
+}
+
+/// Smaže prázdné podsložky (samotnou `root` ne).
+fn remove_empty(root: &Path, dir: &Path) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        if entry.file_type().is_ok_and(|t| t.is_dir()) {
+            remove_empty(root, &entry.path());
+        }
+    }
+    if dir != root {
+        // selže, pokud složka není prázdná
+        let _ = std::fs::remove_dir(dir);
+    }
+}
+
+/// Uklidí obě složky na pozadí (při spuštění prohlížeče).
+pub fn prune_in_background(temp: Retention, cache: Retention) {
+    std::thread::spawn(move || {
+        for (area, rule) in [(Area::Temp, temp), (Area::Cache, cache)] {
+            match prune(&area.path(), rule, false) {
+                Ok(p) if p.removed > 0 => {
+                    tracing::info!("{} cleanup: {}", area.label(), p.summary(false));
+                }
+                Ok(_) => {}
+                Err(e) => tracing::error!("{} cleanup error: {e:#}", area.label()),
+            }
+        }
+    });
+}
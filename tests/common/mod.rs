@@ -0,0 +1,85 @@
+//! Pomůcky sdílené integračními testy.
+
+#![allow(dead_code)]
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// Dočasný soubor (nebo složka), po testu se smaže.
+pub struct TempFile(pub PathBuf);
+
+impl TempFile {
+    pub fn new() -> Self {
+        Self::named("cti")
+    }
+
+    /// Dočasná cesta s příponou `ext` (prázdná = složka, kterou si test vytvoří).
+    pub fn named(ext: &str) -> Self {
+        static NEXT: AtomicU32 = AtomicU32::new(0);
+        let n = NEXT.fetch_add(1, Ordering::Relaxed);
+        let crate_name = env!("CARGO_CRATE_NAME");
+        let mut name = format!("cti-{crate_name}-{}-{n}", std::process::id());
+        if !ext.is_empty() {
+            name = format!("{name}.{ext}");
+        }
+        Self(std::env::temp_dir().join(name))
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if self.0.is_dir() {
+            let _ = std::fs::remove_dir_all(&self.0);
+        } else {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+}
+
+pub const HEADER_SIZE: usize = 64;
+pub const INDEX_ENTRY_SIZE: usize = 20;
+
+/// Ručně sestavená 64B hlavička verze 1.0 bez příznaků, bezeztrátová bez komprese.
+pub fn raw_header(width: u32, height: u32, tile_size: u32, color_type: u8) -> Vec<u8> {
+    let mut out = b"CTI1".to_vec();
+    out.extend_from_slice(&1u16.to_le_bytes());
+    out.extend_from_slice(&0u16.to_le_bytes());
+    for v in [
+        width,
+        height,
+        tile_size,
+        width.div_ceil(tile_size),
+        height.div_ceil(tile_size),
+    ] {
+        out.extend_from_slice(&v.to_le_bytes());
+    }
+    out.extend_from_slice(&[color_type, 0, 100]);
+    out.resize(HEADER_SIZE, 0);
+    out
+}
+
+/// [`raw_header`] s nulovými položkami indexu (dlaždice bez dat).
+pub fn raw_file(width: u32, height: u32, tile_size: u32, color_type: u8) -> Vec<u8> {
+    let mut out = raw_header(width, height, tile_size, color_type);
+    let tiles = width.div_ceil(tile_size) * height.div_ceil(tile_size);
+    out.resize(HEADER_SIZE + tiles as usize * INDEX_ENTRY_SIZE, 0);
+    out
+}
+
+/// Šum s hladkým přechodem, ať kodeky mají co komprimovat a chyby v posunu jsou vidět.
+pub fn pixels(width: u32, height: u32, bpp: usize) -> Vec<u8> {
+    let mut out = Vec::with_capacity(width as usize * height as usize * bpp);
+    let mut state = 0x2545_F491u32;
+    for y in 0..height {
+        for x in 0..width {
+            for c in 0..bpp {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                let ramp = (x * 3 + y * 5 + c as u32 * 40) as u8;
+                out.push(ramp.wrapping_add((state % 16) as u8));
+            }
+        }
+    }
+    out
+}
@@ -0,0 +1,122 @@
+//! Varianty zápisu (`cargo test --test encode`): kontrolní součty CRC32C dlaždic a zápis
+//! po řádcích dlaždic s indexem na konci.
+
+mod common;
+
+use std::io::Cursor;
+
+use common::{HEADER_SIZE, TempFile, pixels};
+use cti_view::crc;
+use cti_view::cti::{
+    self, CTIAppendEncoder, CTIDecoder, CTIEncoder, CTIExtensions, CTIMetadata, CompressionId,
+    EncodeParams, VERSION_APPEND,
+};
+
+const WIDTH: u32 = 70;
+const HEIGHT: u32 = 45;
+
+fn params(compression: CompressionId) -> EncodeParams {
+    EncodeParams {
+        tile_size: 32,
+        compression,
+        level: compression.default_level(),
+        ..EncodeParams::default()
+    }
+}
+
+/// Zapíše `data` (RGB8) po řádcích dlaždic tak, jak je dodává [`CTIAppendEncoder`].
+fn push_rows<W: std::io::Write>(enc: &mut CTIAppendEncoder<W>, data: &[u8]) {
+    let row_bytes = WIDTH as usize * 3;
+    let mut y = 0;
+    while y < HEIGHT as usize {
+        let rows = enc.next_row_height() as usize;
+        enc.push_row(&data[y * row_bytes..(y + rows) * row_bytes])
+            .unwrap();
+        y += rows;
+    }
+}
+
+/// Bez komprese jsou uložená data dlaždice přímo to, z čeho se počítá kontrolní součet.
+#[test]
+fn crc32c_tiles_round_trip_and_detect_damage() {
+    let file = TempFile::new();
+    let data = pixels(WIDTH, HEIGHT, 1);
+    let params = EncodeParams {
+        crc32c: true,
+        file_hash: false,
+        ..params(CompressionId::None)
+    };
+    let hdr = CTIEncoder::encode_file(&file.0, WIDTH, HEIGHT, 1, &data, &params).expect("encode");
+    assert!(hdr.has_crc32c());
+    assert_eq!(CTIDecoder::decode_file(&file.0).unwrap().1, data);
+
+    let mut bytes = std::fs::read(&file.0).unwrap();
+    let entry = &bytes[HEADER_SIZE..HEADER_SIZE + 20];
+    let offset = u64::from_le_bytes(entry[..8].try_into().unwrap()) as usize;
+    let size = u32::from_le_bytes(entry[8..12].try_into().unwrap()) as usize;
+    let stored = u32::from_le_bytes(entry[16..20].try_into().unwrap());
+    let tile = &bytes[offset..offset + size];
+    assert_eq!(stored, crc::crc32c(tile));
+    assert_ne!(stored, crc::crc32(tile));
+
+    bytes[offset] ^= 1;
+    std::fs::write(&file.0, &bytes).unwrap();
+    let err = CTIDecoder::decode_file(&file.0).unwrap_err();
+    assert!(
+        format!("{err:#}").contains("CRC mismatch at tile 0"),
+        "{err:#}"
+    );
+}
+
+#[test]
+fn append_encoder_round_trips() {
+    let file = TempFile::new();
+    let data = pixels(WIDTH, HEIGHT, 3);
+    let mut meta = CTIMetadata::default();
+    meta.set("Title", "Scanned");
+    let ext = CTIExtensions {
+        metadata: meta,
+        ..Default::default()
+    };
+    let params = params(CompressionId::Zstd);
+    let mut enc = CTIAppendEncoder::create(&file.0, WIDTH, HEIGHT, 3, &params, &ext).unwrap();
+    push_rows(&mut enc, &data);
+    let hdr = enc.finish().unwrap();
+    assert_eq!(hdr.version & 0xFF, VERSION_APPEND);
+    assert!(hdr.has_index_at_end());
+
+    assert_eq!(CTIDecoder::decode_file(&file.0).unwrap().1, data);
+    let (_, region) = CTIDecoder::decode_region(&file.0, 0, (30, 20, 10, 20)).unwrap();
+    let expected: Vec<u8> = (20..40)
+        .flat_map(|y| &data[(y * WIDTH as usize + 30) * 3..(y * WIDTH as usize + 40) * 3])
+        .copied()
+        .collect();
+    assert_eq!(region, expected);
+    assert_eq!(
+        CTIDecoder::metadata(&file.0).unwrap().get("Title"),
+        Some("Scanned")
+    );
+    assert!(cti::verify_file_hash(&file.0).unwrap());
+}
+
+/// Do výstupu se jen připisuje, takže stačí `Vec<u8>`; pás špatné velikosti, řádek navíc
+/// i nedokončený zápis skončí chybou.
+#[test]
+fn append_encoder_writes_to_any_writer() {
+    let data = pixels(WIDTH, HEIGHT, 3);
+    let params = params(CompressionId::Lz4);
+    let ext = CTIExtensions::default();
+    let mut enc = CTIAppendEncoder::new(Vec::new(), WIDTH, HEIGHT, 3, &params, &ext).unwrap();
+    assert!(enc.push_row(&data[..10]).is_err());
+    assert!(enc.finish().is_err());
+
+    let mut out = Vec::new();
+    let mut enc = CTIAppendEncoder::new(&mut out, WIDTH, HEIGHT, 3, &params, &ext).unwrap();
+    push_rows(&mut enc, &data);
+    assert_eq!(enc.next_row_height(), 0);
+    assert!(enc.push_row(&data[..WIDTH as usize * 3]).is_err());
+    enc.finish().unwrap();
+    let (hdr, decoded) = CTIDecoder::decode_frame_from(Cursor::new(&out), 0).unwrap();
+    assert!(hdr.has_index_at_end());
+    assert_eq!(decoded, data);
+}
@@ -0,0 +1,89 @@
+//! Hlavičky z ručně sestavených bajtů (`cargo test --test header`): co čtenář přijme
+//! a co odmítne dřív, než podle hlavičky cokoli alokuje nebo spočítá.
+
+mod common;
+
+use std::io::Cursor;
+
+use common::raw_file as file;
+use cti_view::cti::{
+    CTIDecoder, CTIHeader, FLAG_INDEX_AT_END, VERSION_APPEND, VERSION_MAJOR_MAX, VERSION_MINOR,
+};
+
+fn info(data: &[u8]) -> anyhow::Result<CTIHeader> {
+    CTIDecoder::info_from(Cursor::new(data))
+}
+
+/// Dlaždice 2^30 × 1 px RGBA8 má 4 GiB, což `original_size` v indexu (u32) nepopíše;
+/// dřív násobení rozměrů v u32 přeteklo a dekodér spadl.
+#[test]
+fn tile_larger_than_index_entry_is_rejected() {
+    let data = file(1 << 30, 1, 1 << 30, 4);
+    let err = info(&data).unwrap_err();
+    assert!(
+        format!("{err:#}").contains("too large for the tile index"),
+        "{err:#}"
+    );
+    assert!(CTIDecoder::decode_frame_from(Cursor::new(&data), 0).is_err());
+    let lossy = CTIDecoder::decode_frame_progressive_from(Cursor::new(&data), 0, |_, _| true);
+    assert!(lossy.is_err());
+}
+
+/// Široký obrázek s malými dlaždicemi projde hlavičkou; dekódování výřezu počítá řádky
+/// v usize a skončí chybou dlaždice, ne přetečením.
+#[test]
+fn wide_image_region_does_not_overflow() {
+    let data = file(1 << 30, 1, 1 << 28, 4);
+    let hdr = info(&data).unwrap();
+    assert_eq!((hdr.tiles_x, hdr.tiles_y), (4, 1));
+    let region = ((1 << 30) - 8, 0, 8, 1);
+    let err = CTIDecoder::decode_region_from(Cursor::new(&data), 0, region).unwrap_err();
+    assert!(
+        format!("{err:#}").contains("Wrong size of tile 3"),
+        "{err:#}"
+    );
+    // výřez přesahující obrázek (x + w přeteče u32)
+    let region = (u32::MAX - 1, 0, 8, 1);
+    let err = CTIDecoder::decode_region_from(Cursor::new(&data), 0, region).unwrap_err();
+    assert!(format!("{err:#}").contains("is outside"), "{err:#}");
+}
+
+/// `file` s verzí `major.minor` a příznaky `flags` v hlavičce.
+fn versioned(major: u16, minor: u16, flags: u16) -> Vec<u8> {
+    let mut data = file(70, 45, 32, 3);
+    data[4..6].copy_from_slice(&(major | minor << 8).to_le_bytes());
+    data[6..8].copy_from_slice(&flags.to_le_bytes());
+    data
+}
+
+fn message(data: &[u8]) -> String {
+    format!("{:#}", info(data).unwrap_err())
+}
+
+#[test]
+fn newer_major_version_is_rejected() {
+    assert!(info(&versioned(VERSION_MAJOR_MAX, VERSION_MINOR, 0)).is_ok());
+    let err = message(&versioned(VERSION_MAJOR_MAX + 1, 0, 0));
+    assert!(err.contains("requires a newer viewer"), "{err}");
+    let err = message(&versioned(0, 0, 0));
+    assert!(err.contains("Invalid CTI format version"), "{err}");
+}
+
+/// Neznámé příznaky smí přidat jen novější vedlejší verze; čtenář je pak zahodí.
+#[test]
+fn unknown_flags_need_a_newer_minor_version() {
+    let err = message(&versioned(1, VERSION_MINOR, 0x4000));
+    assert!(err.contains("Unknown header flags 0x4000"), "{err}");
+    let hdr = info(&versioned(1, VERSION_MINOR + 1, 0x4000)).unwrap();
+    assert_eq!(hdr.flags, 0);
+}
+
+#[test]
+fn index_at_end_needs_the_append_version() {
+    for major in 1..VERSION_APPEND {
+        let err = message(&versioned(major, 0, FLAG_INDEX_AT_END));
+        assert!(err.contains("Index-at-end flag"), "{err}");
+    }
+    let hdr = info(&versioned(VERSION_APPEND, 0, FLAG_INDEX_AT_END)).unwrap();
+    assert!(hdr.has_index_at_end());
+}
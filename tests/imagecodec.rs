@@ -0,0 +1,117 @@
+//! Adaptér pro crate `image` (`cargo test --test imagecodec`): `image::open` po registraci,
+//! výřezy do bufferu s delším řádkem, odmítnuté typy barev a limity z `image::Limits`.
+
+mod common;
+
+use std::io::Cursor;
+
+use common::{TempFile, pixels, raw_file, raw_header};
+use cti_view::cti::{CTIEncoder, CompressionId, EncodeParams};
+use cti_view::imagecodec::{self, CtiImageDecoder};
+use image::{ColorType, ImageDecoder, ImageDecoderRect, ImageReader, Limits};
+
+const WIDTH: u32 = 70;
+const HEIGHT: u32 = 45;
+
+/// (id typu barev CTI, bajty na pixel, typ barev v `image`)
+const TYPES: [(u8, usize, ColorType); 5] = [
+    (1, 1, ColorType::L8),
+    (2, 2, ColorType::L16),
+    (3, 3, ColorType::Rgb8),
+    (4, 4, ColorType::Rgba8),
+    (5, 6, ColorType::Rgb16),
+];
+
+fn encode(color_type: u8, data: &[u8]) -> TempFile {
+    let file = TempFile::new();
+    let params = EncodeParams {
+        tile_size: 32,
+        compression: CompressionId::Zstd,
+        ..EncodeParams::default()
+    };
+    CTIEncoder::encode_file(&file.0, WIDTH, HEIGHT, color_type, data, &params).expect("encode");
+    file
+}
+
+/// Vzorky po dvou bajtech jsou v CTI little-endian, `image` je vrací v pořadí platformy.
+fn native(data: &[u8], color: ColorType) -> Vec<u8> {
+    let size = (color.bytes_per_pixel() / color.channel_count()) as usize;
+    let mut out = data.to_vec();
+    if cfg!(target_endian = "big") && size > 1 {
+        out.chunks_exact_mut(size).for_each(<[u8]>::reverse);
+    }
+    out
+}
+
+#[test]
+fn image_open_decodes_after_register() {
+    imagecodec::register();
+    for (ct, bpp, color) in TYPES {
+        let data = pixels(WIDTH, HEIGHT, bpp);
+        let file = encode(ct, &data);
+        let img = image::open(&file.0).expect("open");
+        assert_eq!(
+            (img.width(), img.height(), img.color()),
+            (WIDTH, HEIGHT, color)
+        );
+        assert_eq!(img.as_bytes(), native(&data, color), "{color:?}");
+
+        // bez přípony podle signatury
+        let bytes = std::fs::read(&file.0).unwrap();
+        let reader = ImageReader::new(Cursor::new(bytes))
+            .with_guessed_format()
+            .unwrap();
+        assert_eq!(reader.decode().expect("decode").as_bytes(), img.as_bytes());
+    }
+}
+
+#[test]
+fn read_rect_honours_row_pitch() {
+    let (x, y, w, h) = (30, 20, 25, 20);
+    for (ct, bpp, color) in TYPES {
+        let data = pixels(WIDTH, HEIGHT, bpp);
+        let file = encode(ct, &data);
+        let mut dec = CtiImageDecoder::new(std::fs::File::open(&file.0).unwrap()).unwrap();
+        let row = w as usize * bpp;
+        let pitch = row + 7;
+        let mut buf = vec![0xAA; pitch * h as usize];
+        dec.read_rect(x, y, w, h, &mut buf, pitch)
+            .expect("read_rect");
+        let image_row = WIDTH as usize * bpp;
+        for (i, dst) in buf.chunks(pitch).enumerate() {
+            let start = (y as usize + i) * image_row + x as usize * bpp;
+            let expected = native(&data[start..start + row], color);
+            assert_eq!(&dst[..row], expected, "{color:?} row {i}");
+            // okraj za šířkou výřezu zůstane nedotčený
+            assert!(dst[row..].iter().all(|&b| b == 0xAA), "{color:?} row {i}");
+        }
+    }
+}
+
+#[test]
+fn cmyk_and_float_gray_are_rejected() {
+    for (ct, name) in [(8, "CMYK8"), (9, "L32F")] {
+        let err = CtiImageDecoder::new(Cursor::new(raw_file(4, 4, 4, ct))).err();
+        let msg = format!("{:#}", err.expect(name));
+        assert!(msg.contains(name), "{msg}");
+    }
+}
+
+/// Snímek 100 Mpx L8 se do `max_alloc` vejde, index dlaždic po 1 px (2 GB) ne: zastaví ho
+/// limit dekodéru dřív, než se index čte.
+#[test]
+fn limits_reach_the_decoder() {
+    let data = raw_header(10_000, 10_000, 1, 1);
+    let mut dec = CtiImageDecoder::new(Cursor::new(&data)).unwrap();
+    let mut limits = Limits::default();
+    limits.max_alloc = Some(200 << 20);
+    dec.set_limits(limits).unwrap();
+    let mut buf = [0u8; 64];
+    let err = dec.read_rect(0, 0, 8, 8, &mut buf, 8).unwrap_err();
+    assert!(err.to_string().contains("decode limit"), "{err}");
+
+    let mut dec = CtiImageDecoder::new(Cursor::new(&data)).unwrap();
+    let mut limits = Limits::no_limits();
+    limits.max_image_width = Some(5_000);
+    assert!(dec.set_limits(limits).is_err());
+}
@@ -0,0 +1,163 @@
+//! Limity dekódování ([`DecodeOptions`]) na všech vstupních bodech dekodéru (`cargo test
+//! --test limits`). Soubory z ručně sestavených bajtů slibují obrovský obrázek, ale index
+//! nebo dlaždice v nich chybí: bez limitu skončí dekódování chybou zkráceného souboru, s ním
+//! chybou limitu ještě předtím, než se index přečte nebo alokuje buffer.
+
+mod common;
+
+use std::io::Cursor;
+
+use common::{HEADER_SIZE, TempFile, raw_file, raw_header};
+use cti_view::cti::{CTIDecoder, DecodeOptions};
+use cti_view::tilecache::TileCache;
+
+/// 60000 × 60000 px RGBA8: snímek 14,4 GB, index 1,1 MB.
+const SIDE: u32 = 60_000;
+
+/// Vstupní bod dekodéru nad bajty souboru.
+type Entry = fn(&[u8], &DecodeOptions) -> anyhow::Result<()>;
+
+/// Soubor pro vstupní body, které berou cestu.
+fn temp(data: &[u8]) -> TempFile {
+    let file = TempFile::new();
+    std::fs::write(&file.0, data).unwrap();
+    file
+}
+
+const ENTRIES: &[(&str, Entry)] = &[
+    ("decode_frame_from", |data, opts| {
+        CTIDecoder::decode_frame_from_with_options(Cursor::new(data), 0, opts).map(drop)
+    }),
+    ("decode_frame", |data, opts| {
+        CTIDecoder::decode_frame_with_options(&temp(data).0, 0, opts).map(drop)
+    }),
+    ("decode_frame_lossy", |data, opts| {
+        CTIDecoder::decode_frame_lossy_with_options(&temp(data).0, 0, opts).map(drop)
+    }),
+    ("decode_frame_progressive_from", |data, opts| {
+        let reader = Cursor::new(data);
+        CTIDecoder::decode_frame_progressive_from_with_options(reader, 0, opts, |_, _| true)
+            .map(drop)
+    }),
+    ("decode_region_from", |data, opts| {
+        CTIDecoder::decode_region_from_with_options(Cursor::new(data), 0, (0, 0, 8, 8), opts)
+            .map(drop)
+    }),
+    ("decode_region_cached", |data, opts| {
+        let cache = TileCache::new(1 << 20);
+        let file = temp(data);
+        CTIDecoder::decode_region_cached_with_options(&file.0, 0, (0, 0, 8, 8), &cache, opts)
+            .map(drop)
+    }),
+    ("decode_into_from", |data, opts| {
+        let mut buf = [0u8; 64];
+        CTIDecoder::decode_into_from_with_options(Cursor::new(data), 0, &mut buf, 32, opts)
+            .map(drop)
+    }),
+    ("decode_region_into_from", |data, opts| {
+        let mut buf = [0u8; 256];
+        let region = (0, 0, 8, 8);
+        let reader = Cursor::new(data);
+        CTIDecoder::decode_region_into_from_with_options(reader, 0, region, &mut buf, 32, opts)
+            .map(drop)
+    }),
+    ("diagnose", |data, opts| {
+        CTIDecoder::diagnose_with_options(&temp(data).0, 0, opts).map(drop)
+    }),
+];
+
+/// Každý vstupní bod s `opts` odmítne `data` chybou obsahující `limit` a bez limitů jinou.
+fn assert_rejected(data: &[u8], opts: &DecodeOptions, limit: &str) {
+    for (name, entry) in ENTRIES {
+        let err = entry(data, opts).expect_err(name);
+        let msg = format!("{err:#}");
+        assert!(msg.contains(limit), "{name}: {msg}");
+        if let Err(err) = entry(data, &DecodeOptions::default()) {
+            let msg = format!("{err:#}");
+            assert!(
+                !msg.contains("decode limit"),
+                "{name} without limits: {msg}"
+            );
+        }
+    }
+}
+
+#[test]
+fn max_pixels_rejects_before_reading_the_index() {
+    // jen hlavička, index chybí
+    let data = raw_header(SIDE, SIDE, 256, 4);
+    let opts = DecodeOptions {
+        max_pixels: Some(100_000_000),
+        ..Default::default()
+    };
+    assert_rejected(&data, &opts, "exceeds the decode limit of 100000000 pixels");
+}
+
+#[test]
+fn max_total_bytes_rejects_before_reading_the_index() {
+    let data = raw_header(SIDE, SIDE, 256, 4);
+    let opts = DecodeOptions {
+        max_total_bytes: Some(1 << 20),
+        ..Default::default()
+    };
+    // do limitu se počítá i index (u bufferu volajícího jen on)
+    assert_rejected(&data, &opts, "more than the decode limit of 1048576 bytes");
+}
+
+#[test]
+fn max_tile_bytes_rejects_before_reading_tiles() {
+    // dlaždice podle indexu 4 GiB (komprimovaná i rozbalená), data v souboru nejsou
+    let mut data = raw_file(64, 64, 64, 4);
+    let entry = &mut data[HEADER_SIZE..];
+    entry[..8].copy_from_slice(&(HEADER_SIZE as u64 + 20).to_le_bytes());
+    entry[8..16].fill(0xFF);
+    let opts = DecodeOptions {
+        max_tile_bytes: Some(1 << 20),
+        ..Default::default()
+    };
+    assert_rejected(
+        &data,
+        &opts,
+        "Tile 0 of 4294967295 bytes exceeds the decode limit",
+    );
+}
+
+/// Soubor pod limity se dekóduje stejně jako bez nich.
+#[test]
+fn file_within_limits_decodes() {
+    let data = raw_file(64, 64, 64, 4);
+    let opts = DecodeOptions {
+        max_pixels: Some(64 * 64),
+        max_tile_bytes: Some(64 * 64 * 4),
+        max_total_bytes: Some(64 * 64 * 4 + 1024),
+    };
+    let (hdr, _, bad) = CTIDecoder::decode_frame_progressive_from_with_options(
+        Cursor::new(&data),
+        0,
+        &opts,
+        |_, _| true,
+    )
+    .unwrap();
+    assert_eq!((hdr.width, hdr.height), (64, 64));
+    // nulová položka indexu = poškozená dlaždice, ne překročený limit
+    assert_eq!(bad.len(), 1);
+    assert!(!bad[0].error.contains("decode limit"), "{}", bad[0].error);
+}
+
+/// Hlavička 2^31+1 × 1 px v dlaždicích po 1 px projde kontrolou hlavičky (IIIF `info.json`
+/// z ní počítá měřítka); dekódování pak zastaví až limit paměti podle velikosti indexu.
+#[test]
+fn huge_tile_grid_passes_the_header_checks() {
+    let data = raw_header((1 << 31) + 1, 1, 1, 1);
+    let hdr = CTIDecoder::info_from(Cursor::new(&data)).unwrap();
+    assert_eq!((hdr.tiles_x, hdr.tiles_y), ((1 << 31) + 1, 1));
+    let opts = DecodeOptions {
+        max_total_bytes: Some(1 << 20),
+        ..Default::default()
+    };
+    for (name, entry) in ENTRIES {
+        let err = entry(&data, &opts).expect_err(name);
+        let msg = format!("{err:#}");
+        assert!(msg.contains("decode limit"), "{name}: {msg}");
+    }
+}
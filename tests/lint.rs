@@ -0,0 +1,209 @@
+//! Kódy `cti-view lint` (`cargo test --test lint`): výstup enkodéru s cíleně poškozenými
+//! bajty musí dát právě očekávané nálezy, po jedné skupině pravidel na test.
+
+mod common;
+
+use common::{HEADER_SIZE, INDEX_ENTRY_SIZE, TempFile, pixels};
+use cti_view::cti::{
+    CTIAppendEncoder, CTIEncoder, CTIExtensions, CompressionId, EncodeParams, FLAG_FRAMES,
+    FLAG_INDEX_AT_END, FLAG_METADATA, VERSION_MINOR,
+};
+use cti_view::lint;
+
+const WIDTH: u32 = 70;
+const HEIGHT: u32 = 45;
+
+fn params(compression: CompressionId) -> EncodeParams {
+    EncodeParams {
+        tile_size: 32,
+        compression,
+        level: compression.default_level(),
+        file_hash: false,
+        ..EncodeParams::default()
+    }
+}
+
+/// Bajty souboru RGB8 70 × 45 v dlaždicích 32 px (3 × 2 dlaždice, index hned za hlavičkou).
+fn encode(params: &EncodeParams, ext: &CTIExtensions) -> Vec<u8> {
+    let file = TempFile::new();
+    let data = pixels(WIDTH, HEIGHT, 3);
+    CTIEncoder::encode_frames_with_extensions(&file.0, WIDTH, HEIGHT, 3, &[&data], params, ext)
+        .expect("encode");
+    std::fs::read(&file.0).unwrap()
+}
+
+fn zstd() -> Vec<u8> {
+    encode(&params(CompressionId::Zstd), &CTIExtensions::default())
+}
+
+fn lint_codes(data: &[u8]) -> Vec<&'static str> {
+    let file = TempFile::new();
+    std::fs::write(&file.0, data).unwrap();
+    let issues = lint::lint_file(&file.0).expect("lint");
+    issues.iter().map(|i| i.code).collect()
+}
+
+/// `data` po úpravě `f`.
+fn with(mut data: Vec<u8>, f: impl FnOnce(&mut Vec<u8>)) -> Vec<u8> {
+    f(&mut data);
+    data
+}
+
+fn put_u16(data: &mut [u8], at: usize, v: u16) {
+    data[at..at + 2].copy_from_slice(&v.to_le_bytes());
+}
+
+fn put_u32(data: &mut [u8], at: usize, v: u32) {
+    data[at..at + 4].copy_from_slice(&v.to_le_bytes());
+}
+
+fn add_flags(data: &mut [u8], flags: u16) {
+    let old = u16::from_le_bytes([data[6], data[7]]);
+    put_u16(data, 6, old | flags);
+}
+
+/// Začátek položky indexu dlaždice `i` (offset u64, komprimovaná a původní velikost u32).
+fn entry(i: usize) -> usize {
+    HEADER_SIZE + i * INDEX_ENTRY_SIZE
+}
+
+fn entry_u32(data: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(data[at..at + 4].try_into().unwrap())
+}
+
+#[test]
+fn encoder_output_is_clean() {
+    assert_eq!(lint_codes(&zstd()), Vec::<&str>::new());
+    let hashed = EncodeParams {
+        file_hash: true,
+        ..params(CompressionId::Zstd)
+    };
+    assert_eq!(
+        lint_codes(&encode(&hashed, &CTIExtensions::default())),
+        Vec::<&str>::new()
+    );
+}
+
+/// E00x/W0xx: hlavička.
+#[test]
+fn header_codes() {
+    let file = zstd();
+    let cases: [(&str, Vec<u8>); 14] = [
+        ("E001", file[..40].to_vec()),
+        ("E001", with(file.clone(), |d| d[0] = b'X')),
+        ("E002", with(file.clone(), |d| d[4] = 9)),
+        ("E003", with(file.clone(), |d| add_flags(d, 0x4000))),
+        (
+            "W003",
+            with(file.clone(), |d| {
+                add_flags(d, 0x4000);
+                d[5] = VERSION_MINOR as u8 + 1;
+            }),
+        ),
+        (
+            "E004",
+            with(file.clone(), |d| add_flags(d, FLAG_INDEX_AT_END)),
+        ),
+        ("E005", with(file.clone(), |d| put_u32(d, 16, 0))),
+        ("E006", with(file.clone(), |d| put_u32(d, 20, 5))),
+        ("E007", with(file.clone(), |d| d[28] = 99)),
+        ("E008", with(file.clone(), |d| d[29] = 200)),
+        ("W010", with(file.clone(), |d| d[30] = 50)),
+        ("E011", with(file.clone(), |d| add_flags(d, FLAG_FRAMES))),
+        ("W012", with(file.clone(), |d| d[50] = 1)),
+        ("E014", common::raw_file(1 << 30, 1, 1 << 30, 4)),
+    ];
+    for (code, data) in cases {
+        assert_eq!(lint_codes(&data), [code], "{code}");
+    }
+}
+
+/// E02x: umístění indexu.
+#[test]
+fn index_codes() {
+    let file = zstd();
+    let short = file[..entry(1)].to_vec();
+    assert_eq!(lint_codes(&short), ["E021"]);
+
+    // index na konci: poškozený lokátor CTIX (posledních 12 bajtů)
+    let out = TempFile::new();
+    let params = params(CompressionId::Zstd);
+    let ext = CTIExtensions::default();
+    let mut enc = CTIAppendEncoder::create(&out.0, WIDTH, HEIGHT, 3, &params, &ext).unwrap();
+    let data = pixels(WIDTH, HEIGHT, 3);
+    let mut y = 0;
+    while y < HEIGHT {
+        let rows = enc.next_row_height();
+        let strip = &data[(y * WIDTH * 3) as usize..((y + rows) * WIDTH * 3) as usize];
+        enc.push_row(strip).unwrap();
+        y += rows;
+    }
+    enc.finish().unwrap();
+    let appended = std::fs::read(&out.0).unwrap();
+    assert_eq!(lint_codes(&appended), Vec::<&str>::new());
+    let locator = appended.len() - 12;
+    let unfinished = with(appended, |d| d[locator] = b'X');
+    assert_eq!(lint_codes(&unfinished), ["E020"]);
+}
+
+/// E03x/W03x: položky indexu a data dlaždic.
+#[test]
+fn tile_codes() {
+    let file = zstd();
+    let last = file.len() - 10;
+    assert_eq!(lint_codes(&file[..last]), ["E030", "W037"]);
+    let zero_offset = with(file.clone(), |d| d[entry(0)..entry(0) + 8].fill(0));
+    assert_eq!(lint_codes(&zero_offset), ["E031", "W037"]);
+    // dlaždice 2 ukazuje na data dlaždice 0: překryv, pokles offsetu a nevyužitá data
+    let overlap = with(file.clone(), |d| {
+        let first = d[entry(0)..entry(0) + 8].to_vec();
+        d[entry(2)..entry(2) + 8].copy_from_slice(&first);
+    });
+    assert_eq!(lint_codes(&overlap), ["W033", "E032", "W037"]);
+    let size = entry_u32(&file, entry(0) + 12);
+    let wrong_size = with(file.clone(), |d| put_u32(d, entry(0) + 12, size + 1));
+    assert_eq!(lint_codes(&wrong_size), ["E034"]);
+    let empty = with(file.clone(), |d| put_u32(d, entry(0) + 8, 0));
+    assert_eq!(lint_codes(&empty), ["E036", "W037"]);
+    let mut garbage = file.clone();
+    garbage.extend_from_slice(&[0; 16]);
+    assert_eq!(lint_codes(&garbage), ["W037"]);
+
+    let raw = encode(&params(CompressionId::None), &CTIExtensions::default());
+    let comp = entry_u32(&raw, entry(5) + 8);
+    let shorter = with(raw, |d| put_u32(d, entry(5) + 8, comp - 1));
+    assert_eq!(lint_codes(&shorter), ["E035", "W037"]);
+}
+
+/// E040/W041: metadata a oblast bloků.
+#[test]
+fn extension_codes() {
+    // verze 1: příznak metadat bez bloku CTIM za indexem
+    let no_block = with(zstd(), |d| add_flags(d, FLAG_METADATA));
+    assert_eq!(lint_codes(&no_block), ["E040"]);
+
+    let ext = CTIExtensions {
+        icc: Some(b"not a real profile".to_vec()),
+        ..Default::default()
+    };
+    let chunks = encode(&params(CompressionId::Zstd), &ext);
+    assert_eq!(lint_codes(&chunks), Vec::<&str>::new());
+    // verze 2: oblast bloků je v pořádku, jen v ní metadata nejsou
+    let no_meta = with(chunks.clone(), |d| add_flags(d, FLAG_METADATA));
+    assert_eq!(lint_codes(&no_meta), ["W041"]);
+    // oblast bloků přes konec souboru; dlaždice pak leží uvnitř ní
+    let too_long = with(chunks, |d| put_u32(d, 35, 1 << 30));
+    assert_eq!(lint_codes(&too_long), ["E040", "E031"]);
+}
+
+/// E050: trailer s hashem souboru.
+#[test]
+fn trailer_codes() {
+    let hashed = EncodeParams {
+        file_hash: true,
+        ..params(CompressionId::Zstd)
+    };
+    let file = encode(&hashed, &CTIExtensions::default());
+    let without = file[..file.len() - 40].to_vec();
+    assert_eq!(lint_codes(&without), ["E050"]);
+}
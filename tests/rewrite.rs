@@ -0,0 +1,247 @@
+//! Úpravy hotových souborů (`cargo test --test rewrite`): přepis metadat, překódování,
+//! ořez, otočení a zrcadlení.
+
+mod common;
+
+use common::{HEADER_SIZE, INDEX_ENTRY_SIZE, TempFile, pixels};
+use cti_view::cti::{
+    self, CTIDecoder, CTIEncoder, CTIExtensions, CTIMetadata, CompressionId, Edit, EncodeParams,
+    FLAG_METADATA, PyramidLevel,
+};
+use cti_view::lint;
+
+const WIDTH: u32 = 70;
+const HEIGHT: u32 = 45;
+
+fn params() -> EncodeParams {
+    EncodeParams {
+        tile_size: 32,
+        compression: CompressionId::Zstd,
+        level: CompressionId::Zstd.default_level(),
+        ..EncodeParams::default()
+    }
+}
+
+/// Soubor verze 2 (s ICC profilem) a jeho pixely.
+fn encode_v2(file: &TempFile, meta: &CTIMetadata) -> Vec<u8> {
+    let data = pixels(WIDTH, HEIGHT, 3);
+    let ext = CTIExtensions {
+        metadata: meta.clone(),
+        icc: Some(b"not a real profile".to_vec()),
+        ..Default::default()
+    };
+    let frames = [&data[..]];
+    CTIEncoder::encode_frames_with_extensions(&file.0, WIDTH, HEIGHT, 3, &frames, &params(), &ext)
+        .expect("encode");
+    data
+}
+
+fn lint_codes(file: &TempFile) -> Vec<&'static str> {
+    let issues = lint::lint_file(&file.0).expect("lint");
+    issues.iter().map(|i| i.code).collect()
+}
+
+#[test]
+fn rewrite_metadata_keeps_tiles_and_chunks() {
+    let file = TempFile::new();
+    let data = encode_v2(&file, &CTIMetadata::default());
+    let mut meta = CTIMetadata::default();
+    meta.set("Title", "Rewritten");
+    let hdr = cti::rewrite_metadata(&file.0, &meta).expect("rewrite");
+    assert!(hdr.has_metadata());
+    assert_eq!(
+        CTIDecoder::metadata(&file.0).unwrap().get("Title"),
+        Some("Rewritten")
+    );
+    let ext = CTIDecoder::extensions(&file.0).unwrap();
+    assert_eq!(ext.icc.as_deref(), Some(&b"not a real profile"[..]));
+    assert_eq!(CTIDecoder::decode_file(&file.0).unwrap().1, data);
+    assert_eq!(lint_codes(&file), Vec::<&str>::new());
+}
+
+/// Oblast bloků verze 2 končí "CEND" i bez metadat; příznak metadat se po jejich smazání
+/// nesmí nastavit (lint W041).
+#[test]
+fn removing_metadata_clears_the_flag() {
+    let file = TempFile::new();
+    let mut meta = CTIMetadata::default();
+    meta.set("Title", "Original");
+    let data = encode_v2(&file, &meta);
+    let hdr = cti::rewrite_metadata(&file.0, &CTIMetadata::default()).expect("rewrite");
+    assert_eq!(hdr.flags & FLAG_METADATA, 0);
+    assert!(CTIDecoder::metadata(&file.0).unwrap().is_empty());
+    assert_eq!(CTIDecoder::decode_file(&file.0).unwrap().1, data);
+    assert_eq!(lint_codes(&file), Vec::<&str>::new());
+}
+
+/// Enkodér dlaždice úrovní pyramidy nezapisuje, takže jejich index nepřijme.
+#[test]
+fn encoder_rejects_pyramid_levels() {
+    let file = TempFile::new();
+    let data = pixels(WIDTH, HEIGHT, 3);
+    let ext = CTIExtensions {
+        pyramid: vec![PyramidLevel {
+            width: WIDTH / 2,
+            height: HEIGHT / 2,
+            tiles: Vec::new(),
+        }],
+        ..Default::default()
+    };
+    let frames = [&data[..]];
+    let res = CTIEncoder::encode_frames_with_extensions(
+        &file.0,
+        WIDTH,
+        HEIGHT,
+        3,
+        &frames,
+        &params(),
+        &ext,
+    );
+    assert!(res.is_err());
+    assert!(!file.0.exists());
+}
+
+/// Dva snímky RGB (druhý jiný než první), aby se ověřilo i čtení dlaždic dalšího snímku.
+fn encode_frames(file: &TempFile) -> [Vec<u8>; 2] {
+    let first = pixels(WIDTH, HEIGHT, 3);
+    let second: Vec<u8> = first.iter().map(|v| v ^ 0x5a).collect();
+    let frames = [&first[..], &second[..]];
+    let ext = CTIExtensions::default();
+    CTIEncoder::encode_frames_with_extensions(&file.0, WIDTH, HEIGHT, 3, &frames, &params(), &ext)
+        .expect("encode");
+    [first, second]
+}
+
+/// Obrázek `w × h` (RGB), jehož pixel `(u, v)` je pixel `src(u, v)` snímku `data`.
+fn remap(data: &[u8], w: u32, h: u32, src: impl Fn(u32, u32) -> (u32, u32)) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    for v in 0..h {
+        for u in 0..w {
+            let (x, y) = src(u, v);
+            let i = (y * WIDTH + x) as usize * 3;
+            out.extend_from_slice(&data[i..i + 3]);
+        }
+    }
+    out
+}
+
+/// Úpravu `edit` zapíše do nového souboru a porovná oba snímky s očekávanými pixely.
+fn check_edit(edit: Edit, size: (u32, u32), src: impl Fn(u32, u32) -> (u32, u32)) -> usize {
+    let file = TempFile::new();
+    let frames = encode_frames(&file);
+    let out = TempFile::new();
+    let (hdr, copied) = cti::edit_file(&file.0, &out.0, edit).expect("edit");
+    assert_eq!((hdr.width, hdr.height, hdr.frames), (size.0, size.1, 2));
+    for (n, data) in frames.iter().enumerate() {
+        let (_, got) = CTIDecoder::decode_frame(&out.0, n as u32).unwrap();
+        assert_eq!(
+            got,
+            remap(data, size.0, size.1, &src),
+            "{edit:?}, frame {n}"
+        );
+    }
+    assert_eq!(lint_codes(&out), Vec::<&str>::new());
+    copied
+}
+
+/// Výřez zarovnaný na mřížku převezme dlaždice beze změny, ostatní se skládají z pixelů.
+#[test]
+fn crop_copies_aligned_tiles() {
+    let copied = check_edit(Edit::Crop(32, 0, 38, 45), (38, 45), |u, v| (u + 32, v));
+    // 2 × 2 dlaždice na snímek, všechny shodné se zdrojem
+    assert_eq!(copied, 8);
+    let copied = check_edit(Edit::Crop(5, 7, 40, 30), (40, 30), |u, v| (u + 5, v + 7));
+    assert_eq!(copied, 0);
+}
+
+#[test]
+fn rotate_and_flip_move_pixels() {
+    let (w, h) = (WIDTH, HEIGHT);
+    check_edit(Edit::Rotate(1), (h, w), |u, v| (v, h - 1 - u));
+    check_edit(Edit::Rotate(2), (w, h), |u, v| (w - 1 - u, h - 1 - v));
+    check_edit(Edit::Rotate(3), (h, w), |u, v| (w - 1 - v, u));
+    check_edit(Edit::FlipHorizontal, (w, h), |u, v| (w - 1 - u, v));
+    check_edit(Edit::FlipVertical, (w, h), |u, v| (u, h - 1 - v));
+}
+
+#[test]
+fn edit_rejects_crop_outside_the_image() {
+    let file = TempFile::new();
+    encode_frames(&file);
+    let out = TempFile::new();
+    assert!(cti::edit_file(&file.0, &out.0, Edit::Crop(60, 0, 11, 10)).is_err());
+    assert!(cti::edit_file(&file.0, &out.0, Edit::Rotate(4)).is_err());
+}
+
+/// Položky indexu (offset vynechaný) všech dlaždic souboru s indexem za hlavičkou.
+fn index_entries(file: &TempFile, tiles: usize) -> Vec<Vec<u8>> {
+    let bytes = std::fs::read(&file.0).unwrap();
+    let index = &bytes[HEADER_SIZE..HEADER_SIZE + tiles * INDEX_ENTRY_SIZE];
+    index
+        .chunks_exact(INDEX_ENTRY_SIZE)
+        .map(|e| e[12..].to_vec())
+        .collect()
+}
+
+/// Bezeztrátový kodek → jiný bezeztrátový: dlaždice se jen přebalí, pixely, původní
+/// velikosti i CRC32C v indexu zůstanou.
+#[test]
+fn transcode_repacks_tiles() {
+    let file = TempFile::new();
+    let data = pixels(WIDTH, HEIGHT, 3);
+    let params = EncodeParams {
+        crc32c: true,
+        ..params()
+    };
+    let meta = CTIMetadata::default();
+    CTIEncoder::encode_frames_with_metadata(&file.0, WIDTH, HEIGHT, 3, &[&data], &params, &meta)
+        .expect("encode");
+    let out = TempFile::new();
+    let lz4 = CompressionId::Lz4;
+    let (hdr, reencoded) =
+        cti::transcode_file(&file.0, &out.0, lz4, lz4.default_level(), 100, None).unwrap();
+    assert!(!reencoded);
+    assert_eq!(hdr.compression, lz4.id());
+    assert!(hdr.has_crc32c());
+    assert_eq!(CTIDecoder::decode_file(&out.0).unwrap().1, data);
+    assert_eq!(index_entries(&out, 6), index_entries(&file, 6));
+    assert!(cti::verify_file_hash(&out.0).unwrap());
+    assert_eq!(lint_codes(&out), Vec::<&str>::new());
+}
+
+/// Jiná velikost dlaždic nebo přechod na DCT vyžaduje obrázek dekódovat a zakódovat znovu.
+#[test]
+fn transcode_reencodes_for_new_tiles_or_lossy_codec() {
+    let file = TempFile::new();
+    let frames = encode_frames(&file);
+    let out = TempFile::new();
+    let zstd = CompressionId::Zstd;
+    let (hdr, reencoded) =
+        cti::transcode_file(&file.0, &out.0, zstd, zstd.default_level(), 100, Some(16)).unwrap();
+    assert!(reencoded);
+    assert_eq!((hdr.tile_size, hdr.tiles_x, hdr.frames), (16, 5, 2));
+    for (n, data) in frames.iter().enumerate() {
+        assert_eq!(&CTIDecoder::decode_frame(&out.0, n as u32).unwrap().1, data);
+    }
+
+    let dct = CompressionId::Dct;
+    let (hdr, reencoded) =
+        cti::transcode_file(&file.0, &out.0, dct, dct.default_level(), 80, None).unwrap();
+    assert!(reencoded);
+    assert_eq!((hdr.compression, hdr.quality), (dct.id(), 80));
+    assert_eq!(
+        CTIDecoder::decode_frame(&out.0, 1).unwrap().1.len(),
+        frames[1].len()
+    );
+    assert_eq!(lint_codes(&out), Vec::<&str>::new());
+}
+
+#[test]
+fn transcode_refuses_to_overwrite_the_source() {
+    let file = TempFile::new();
+    let frames = encode_frames(&file);
+    let lz4 = CompressionId::Lz4;
+    let res = cti::transcode_file(&file.0, &file.0, lz4, lz4.default_level(), 100, None);
+    assert!(res.is_err());
+    assert_eq!(CTIDecoder::decode_frame(&file.0, 0).unwrap().1, frames[0]);
+}
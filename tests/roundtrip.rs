@@ -0,0 +1,192 @@
+//! Vlastnosti kodeků a RCT nad náhodnými daty (`cargo test --test roundtrip`): každý
+//! bezeztrátový kodek s každým typem barev vrátí po zakódování a dekódování stejné bajty,
+//! DCT při nejvyšší kvalitě zůstane v malé odchylce a dopředná RCT následovaná zpětnou je
+//! bezeztrátová pro všechny pixely, u kterých ji enkodér použije. Hlídá hlavně chyby ve
+//! znaménku a přetečení v transformacích a na okrajích dlaždic.
+
+mod common;
+
+use proptest::prelude::*;
+
+use common::TempFile;
+use cti_view::cti::{self, CTIDecoder, CTIEncoder, CompressionId, EncodeParams};
+use cti_view::dct;
+use cti_view::simd::{self, scalar};
+
+const COLOR_TYPES: [u8; 8] = [1, 2, 3, 4, 5, 8, 9, 10];
+
+/// Největší odchylka vzorku po DCT s kvalitou [`dct::MAX_QUALITY`] (zaokrouhlení koeficientů
+/// a převodu YCbCr).
+const DCT_TOLERANCE: u8 = 4;
+
+#[derive(Debug, Clone)]
+struct Image {
+    width: u32,
+    height: u32,
+    tile_size: u32,
+    data: Vec<u8>,
+}
+
+/// Obrázek náhodného rozměru a velikosti dlaždice; `pixel` vytváří jeden pixel.
+fn image<S>(pixel: S) -> impl Strategy<Value = Image>
+where
+    S: Strategy<Value = Vec<u8>> + Clone,
+{
+    (1..=70u32, 1..=70u32, 4..=48u32).prop_flat_map(move |(width, height, tile_size)| {
+        let count = (width * height) as usize;
+        prop::collection::vec(pixel.clone(), count).prop_map(move |px| Image {
+            width,
+            height,
+            tile_size,
+            data: px.concat(),
+        })
+    })
+}
+
+/// Libovolné bajty pixelu typu `color_type`.
+fn any_pixel(color_type: u8) -> impl Strategy<Value = Vec<u8>> + Clone {
+    let bpp = cti::bytes_per_pixel(color_type).unwrap() as usize;
+    prop::collection::vec(any::<u8>(), bpp)
+}
+
+/// Pixel RGB8, jehož rozdíly R−G a B−G se vejdou do i8 (RCT je pro něj bezeztrátová).
+fn rct_pixel8() -> impl Strategy<Value = Vec<u8>> + Clone {
+    (0..=255i32).prop_flat_map(|g| {
+        let near = (g - 128).max(0)..=(g + 127).min(255);
+        (near.clone(), near).prop_map(move |(r, b)| vec![r as u8, g as u8, b as u8])
+    })
+}
+
+/// Pixel RGB16, jehož rozdíly se vejdou do i16.
+fn rct_pixel16() -> impl Strategy<Value = Vec<u8>> + Clone {
+    (0..=65535i32).prop_flat_map(|g| {
+        let near = (g - 32768).max(0)..=(g + 32767).min(65535);
+        (near.clone(), near).prop_map(move |(r, b)| {
+            [r, g, b]
+                .iter()
+                .flat_map(|&v| (v as u16).to_le_bytes())
+                .collect()
+        })
+    })
+}
+
+fn lossless_codecs() -> Vec<CompressionId> {
+    let codecs = CompressionId::encoders().into_iter();
+    codecs.filter(|c| !c.is_lossy()).collect()
+}
+
+/// Zakóduje obrázek do dočasného souboru a dekóduje ho zpátky.
+fn round_trip(img: &Image, color_type: u8, params: EncodeParams) -> Vec<u8> {
+    let file = TempFile::new();
+    let params = EncodeParams {
+        tile_size: img.tile_size,
+        ..params
+    };
+    CTIEncoder::encode_file(
+        &file.0, img.width, img.height, color_type, &img.data, &params,
+    )
+    .expect("encode");
+    let (hdr, raw) = CTIDecoder::decode_file(&file.0).expect("decode");
+    assert_eq!((hdr.width, hdr.height), (img.width, img.height));
+    raw
+}
+
+fn params(compression: CompressionId) -> EncodeParams {
+    EncodeParams {
+        compression,
+        level: compression.default_level(),
+        ..EncodeParams::default()
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(96))]
+
+    #[test]
+    fn lossless_codecs_round_trip(
+        codec in prop::sample::select(lossless_codecs()),
+        (color_type, img) in prop::sample::select(COLOR_TYPES.to_vec())
+            .prop_flat_map(|c| (Just(c), image(any_pixel(c)))),
+        crc32c in any::<bool>(),
+    ) {
+        let params = EncodeParams { crc32c, ..params(codec) };
+        prop_assert_eq!(round_trip(&img, color_type, params), img.data);
+    }
+
+    /// Obrázky, u kterých enkodér RCT opravdu použije.
+    #[test]
+    fn rct_images_round_trip(
+        codec in prop::sample::select(lossless_codecs()),
+        (color_type, img) in prop_oneof![
+            image(rct_pixel8()).prop_map(|img| (3u8, img)),
+            image(rct_pixel16()).prop_map(|img| (5u8, img)),
+        ],
+    ) {
+        prop_assert_eq!(round_trip(&img, color_type, params(codec)), img.data);
+    }
+
+    /// L8 uložené sbaleně jako L1 nebo L4 (hodnoty, které sbalení zachová).
+    #[test]
+    fn packed_gray_round_trip(
+        (gray_bits, img) in prop_oneof![
+            image(prop::sample::select(vec![vec![0u8], vec![255]])).prop_map(|img| (1u8, img)),
+            image((0..16u8).prop_map(|v| vec![v * 17])).prop_map(|img| (4u8, img)),
+        ],
+    ) {
+        let params = EncodeParams { gray_bits, ..params(CompressionId::Zstd) };
+        prop_assert_eq!(round_trip(&img, 1, params), img.data);
+    }
+
+    #[test]
+    fn dct_max_quality_stays_close(
+        (color_type, img) in prop::sample::select(vec![1u8, 3, 4, 8])
+            .prop_flat_map(|c| (Just(c), image(any_pixel(c)))),
+    ) {
+        let params = EncodeParams { quality: dct::MAX_QUALITY, ..params(CompressionId::Dct) };
+        let raw = round_trip(&img, color_type, params);
+        prop_assert_eq!(raw.len(), img.data.len());
+        let worst = raw.iter().zip(&img.data).map(|(a, b)| a.abs_diff(*b)).max().unwrap();
+        prop_assert!(worst <= DCT_TOLERANCE, "max difference {}", worst);
+    }
+
+    #[test]
+    fn rct_rgb8_is_lossless(px in prop::collection::vec(rct_pixel8(), 1..300)) {
+        let original = px.concat();
+        let mut buf = original.clone();
+        cti::rct_forward_rgb8(&mut buf);
+        let mut reference = buf.clone();
+        simd::rct_inverse_rgb8(&mut buf);
+        scalar::rct_inverse_rgb8(&mut reference);
+        prop_assert_eq!(&buf, &original);
+        prop_assert_eq!(&reference, &original);
+    }
+
+    #[test]
+    fn rct_rgb16_is_lossless(px in prop::collection::vec(rct_pixel16(), 1..300)) {
+        let original = px.concat();
+        let mut buf = original.clone();
+        cti::rct_forward_rgb16(&mut buf);
+        let mut reference = buf.clone();
+        simd::rct_inverse_rgb16(&mut buf);
+        scalar::rct_inverse_rgb16(&mut reference);
+        prop_assert_eq!(&buf, &original);
+        prop_assert_eq!(&reference, &original);
+    }
+
+    /// Vektorizovaná zpětná RCT dává pro libovolná data (i mimo rozsah dopředné) totéž
+    /// co smyčka po pixelech, včetně zbytku za posledním celým blokem.
+    #[test]
+    fn rct_inverse_matches_scalar(
+        px8 in prop::collection::vec(any::<[u8; 3]>(), 0..300),
+        px16 in prop::collection::vec(any::<[u8; 6]>(), 0..300),
+    ) {
+        let (mut a, mut b) = (px8.concat(), px8.concat());
+        simd::rct_inverse_rgb8(&mut a);
+        scalar::rct_inverse_rgb8(&mut b);
+        prop_assert_eq!(a, b);
+        let (mut a, mut b) = (px16.concat(), px16.concat());
+        simd::rct_inverse_rgb16(&mut a);
+        scalar::rct_inverse_rgb16(&mut b);
+        prop_assert_eq!(a, b);
+    }
+}
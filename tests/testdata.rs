@@ -0,0 +1,74 @@
+//! Sada z `cti-view gen-testdata` (`cargo test --test testdata`): každá položka manifestu
+//! musí odpovídat tomu, co s vygenerovaným souborem udělá dekodér a lint.
+
+mod common;
+
+use std::path::Path;
+
+use common::TempFile;
+use cti_view::{cti, lint, testdata};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+/// SHA-256 pixelů všech snímků za sebou, jak ho zapisuje manifest.
+fn pixels_sha256(path: &Path, frames: u32) -> anyhow::Result<String> {
+    let mut hash = Sha256::new();
+    for n in 0..frames {
+        hash.update(cti::CTIDecoder::decode_frame(path, n)?.1);
+    }
+    Ok(hash.finalize().iter().map(|b| format!("{b:02x}")).collect())
+}
+
+fn lint_codes(path: &Path) -> Vec<String> {
+    let issues = lint::lint_file(path).expect("lint");
+    issues.iter().map(|i| i.code.to_string()).collect()
+}
+
+fn strings(v: &Value) -> Vec<String> {
+    let items = v.as_array().expect("array");
+    items
+        .iter()
+        .map(|s| s.as_str().unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn corpus_matches_manifest() {
+    let dir = TempFile::named("");
+    let cases = testdata::generate(&dir.0).expect("generate");
+    let manifest: Value =
+        serde_json::from_slice(&std::fs::read(dir.0.join("manifest.json")).unwrap()).unwrap();
+    let entries = manifest.as_array().expect("manifest is a list");
+    assert_eq!(entries.len(), cases.len());
+    assert!(entries.iter().any(|e| e["defect"].is_null()));
+    assert!(entries.iter().any(|e| !e["defect"].is_null()));
+
+    for entry in entries {
+        let name = entry["file"].as_str().unwrap();
+        let path = dir.0.join(name);
+        assert_eq!(lint_codes(&path), strings(&entry["lint"]), "{name}");
+        let frames = entry["frames"].as_u64().unwrap() as u32;
+        if entry["defect"].is_null() {
+            let hdr = cti::CTIDecoder::info(&path).unwrap();
+            let size = [hdr.width, hdr.height, hdr.tile_size, hdr.frames];
+            let recorded = ["width", "height", "tile_size", "frames"]
+                .map(|k| entry[k].as_u64().unwrap() as u32);
+            assert_eq!(size, recorded, "{name}");
+            let hash = pixels_sha256(&path, frames).unwrap();
+            assert_eq!(
+                Some(hash.as_str()),
+                entry["pixels_sha256"].as_str(),
+                "{name}"
+            );
+        } else {
+            // vadu najde dekodér, nebo (u dat v pořádku) kontrola hashe souboru
+            let decoded = pixels_sha256(&path, frames);
+            let hash = cti::verify_file_hash(&path);
+            assert!(
+                decoded.is_err() || hash.is_err(),
+                "{name}: defect not detected"
+            );
+            assert!(entry["pixels_sha256"].is_null(), "{name}");
+        }
+    }
+}